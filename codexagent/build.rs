@@ -1,6 +1,40 @@
+use std::path::Path;
+use std::process::Command;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+fn git(args: &[&str]) -> Option<String> {
+    let output = Command::new("git").args(args).output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    let text = String::from_utf8(output.stdout).ok()?;
+    let text = text.trim();
+    (!text.is_empty()).then(|| text.to_owned())
+}
+
+fn stamp_build() {
+    println!("cargo:rerun-if-changed=src");
+    println!("cargo:rerun-if-changed=build.rs");
+    if let Some(git_dir) = git(&["rev-parse", "--absolute-git-dir"]) {
+        for file in ["HEAD", "index"] {
+            let path = Path::new(&git_dir).join(file);
+            if path.exists() {
+                println!("cargo:rerun-if-changed={}", path.display());
+            }
+        }
+    }
+    let hash = git(&["rev-parse", "--short=12", "HEAD"]).unwrap_or_else(|| "unknown".to_owned());
+    let built_at = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |duration| duration.as_secs());
+    println!("cargo:rustc-env=CODEXAGENT_GIT_HASH={}", hash);
+    println!("cargo:rustc-env=CODEXAGENT_BUILD_TIME={}", built_at);
+}
+
 #[cfg(target_os = "windows")]
 fn main() {
     println!("cargo:rerun-if-changed=assets/app-icon.ico");
+    stamp_build();
 
     let mut resource = winresource::WindowsResource::new();
     resource.set_icon("assets/app-icon.ico");
@@ -10,4 +44,6 @@ fn main() {
 }
 
 #[cfg(not(target_os = "windows"))]
-fn main() {}
+fn main() {
+    stamp_build();
+}
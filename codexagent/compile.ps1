@@ -10,7 +10,7 @@ $targetDll = Join-Path $targetDir 'codexagent_contextmenu.dll'
 
 Push-Location $repoRoot
 try {
-  cargo build --release
+  cargo build --release --workspace
 
   if (-not (Test-Path $sourceExe)) {
     throw "Build succeeded but $sourceExe was not found."
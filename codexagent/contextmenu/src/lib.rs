@@ -146,6 +146,9 @@ pub extern "system" fn DllMain(module: HMODULE, reason: u32, _: *mut c_void) ->
     1
 }
 
+/// # Safety
+///
+/// Called by COM; takes no arguments and only reads the module reference count.
 #[unsafe(no_mangle)]
 pub unsafe extern "system" fn DllCanUnloadNow() -> HRESULT {
     if DLL_REF_COUNT.load(Ordering::Acquire) == 0 {
@@ -155,6 +158,10 @@ pub unsafe extern "system" fn DllCanUnloadNow() -> HRESULT {
     }
 }
 
+/// # Safety
+///
+/// `ppv` must be null or valid for writes, and `rclsid` and `riid` must be null or
+/// point to valid GUIDs, as COM guarantees for this export.
 #[unsafe(no_mangle)]
 pub unsafe extern "system" fn DllGetClassObject(
     rclsid: *const GUID,
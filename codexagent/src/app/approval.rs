@@ -0,0 +1,89 @@
+use crate::logging;
+use crate::prompt::ApprovalRequest;
+
+use super::CodexAgentApp;
+
+const APPROVAL_DETAIL_ROWS: usize = 6;
+
+pub(super) fn approval_detail_rows(detail: &str) -> impl Iterator<Item = &str> {
+    detail.lines().take(APPROVAL_DETAIL_ROWS)
+}
+
+// The worker runs the approved command itself and appends its result, so the
+// resumed session keeps the project's approval policy for every later step.
+fn approved_prompt(request: &ApprovalRequest) -> String {
+    format!(
+        "Approved. This command was run for you once; do not run it again:\n{}",
+        request.detail
+    )
+}
+
+impl CodexAgentApp {
+    pub(super) fn hold_for_approval(&mut self, request: ApprovalRequest) {
+        logging::trace(format!(
+            "holding {} approval request until the user decides",
+            request.kind.label().to_lowercase()
+        ));
+        self.transcript.push_stop_notice(&format!(
+            "waiting for approval to {}",
+            request.kind.label().to_lowercase()
+        ));
+        self.pending_approval = Some(request);
+    }
+
+    pub(super) fn drop_pending_approval(&mut self) {
+        if let Some(request) = self.pending_approval.take() {
+            logging::trace(format!(
+                "dropping unanswered {} approval request",
+                request.kind.label().to_lowercase()
+            ));
+        }
+    }
+
+    pub(super) fn approve_pending(&mut self) {
+        if self.busy || self.locked {
+            return;
+        }
+        let Some(request) = self
+            .pending_approval
+            .take_if(|request| request.can_approve())
+        else {
+            return;
+        };
+        if self.session_id.is_none() {
+            logging::error("cannot approve request; the codex session is gone");
+            self.push_local_error("Cannot approve: the codex session is no longer available");
+            self.finish_local_change();
+            return;
+        }
+        logging::trace(format!(
+            "approved {}; resuming the session",
+            request.kind.label().to_lowercase()
+        ));
+        let project = self.refresh_project_config();
+        self.last_response_range = None;
+        self.response_diff = None;
+        self.retry_baseline = None;
+        self.continuations_used = 0;
+        let Some(prompt_id) = self.begin_prompt_request() else {
+            self.pending_approval = Some(request);
+            return;
+        };
+        self.push_prompt_output(&format!("Approved: {}", request.kind.label()), &[]);
+        self.persist_history();
+        self.refresh_after_text_change();
+        self.spawn_prompt_request(prompt_id, approved_prompt(&request), project, Some(request));
+    }
+
+    pub(super) fn deny_pending(&mut self) {
+        let Some(request) = self.pending_approval.take() else {
+            return;
+        };
+        logging::trace(format!("denied {}", request.kind.label().to_lowercase()));
+        self.push_settings_output(&format!(
+            "Denied: {} \u{2014} codex was stopped and nothing ran",
+            request.kind.label()
+        ));
+        self.finish_local_change();
+    }
+}
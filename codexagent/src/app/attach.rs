@@ -0,0 +1,93 @@
+use crate::attach::{attach_long_prompt, attachment_dir, format_size, remove_attachments};
+use crate::config::{
+    DEFAULT_PROMPT_ATTACH_TEMPLATE, load_prompt_attach_template, set_prompt_attach_limit,
+    transcripts_persisted,
+};
+use crate::logging;
+use crate::prompt::codex_visible_path;
+
+use super::CodexAgentApp;
+
+impl CodexAgentApp {
+    pub(super) fn attach_long_prompt(&mut self, prompt: String, notes: &mut Vec<String>) -> String {
+        if self.prompt_attach_limit == 0 || prompt.len() <= self.prompt_attach_limit {
+            return prompt;
+        }
+        let template = load_prompt_attach_template().unwrap_or_else(|error| {
+            logging::error(format!("failed to load prompt attach template: {}", error));
+            DEFAULT_PROMPT_ATTACH_TEMPLATE.to_owned()
+        });
+        match attach_long_prompt(
+            &prompt,
+            self.prompt_attach_limit,
+            &template,
+            &attachment_dir(transcripts_persisted()),
+            codex_visible_path,
+        ) {
+            Ok((outgoing, attachments)) => {
+                for attachment in attachments {
+                    logging::trace(format!(
+                        "moved {} bytes of the prompt into {}",
+                        attachment.bytes,
+                        attachment.path.display()
+                    ));
+                    notes.push(attachment.note());
+                    self.prompt_attachments.push(attachment.path);
+                }
+                outgoing
+            }
+            Err(error) => {
+                logging::error(format!("failed to attach long prompt text: {}", error));
+                notes.push(format!(
+                    "Could not attach long text; sending it inline: {}",
+                    error
+                ));
+                prompt
+            }
+        }
+    }
+
+    pub(super) fn remove_prompt_attachments(&mut self) {
+        if self.prompt_attachments.is_empty() {
+            return;
+        }
+        if transcripts_persisted() {
+            logging::trace(format!(
+                "keeping {} prompt attachments with the persisted transcript",
+                self.prompt_attachments.len()
+            ));
+        } else {
+            let removed = remove_attachments(&self.prompt_attachments);
+            logging::trace(format!("removed {} prompt attachments", removed));
+        }
+        self.prompt_attachments.clear();
+    }
+
+    pub(super) fn select_prompt_attach_limit(&mut self, limit: usize) {
+        self.clear_picker_selection();
+        if self.prompt_attach_limit == limit {
+            return;
+        }
+        match set_prompt_attach_limit(limit) {
+            Ok(limit) => {
+                self.prompt_attach_limit = limit;
+                self.mark_output_for_rebuild();
+                let message = if limit == 0 {
+                    "Long prompt attachments disabled".to_owned()
+                } else {
+                    format!("Long prompts attached above {}", format_size(limit))
+                };
+                self.push_settings_output(&message);
+                self.finish_local_success();
+            }
+            Err(error) => {
+                logging::error(format!(
+                    "failed to set prompt attach limit {}: {}",
+                    limit, error
+                ));
+                self.push_local_error(&format!("Failed to set long prompt attachments: {}", error));
+            }
+        }
+        self.finish_local_change();
+    }
+}
@@ -0,0 +1,442 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::logging;
+
+use super::{CodexAgentApp, SetupState};
+
+const HEADER_PREFIX: &str = "#!";
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum BatchSession {
+    Same,
+    Fresh,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum BatchFailure {
+    Stop,
+    Skip,
+}
+
+#[derive(Debug, PartialEq)]
+struct BatchPlan {
+    items: Vec<String>,
+    session: BatchSession,
+    on_failure: BatchFailure,
+}
+
+pub(super) struct PromptBatch {
+    name: String,
+    items: Vec<String>,
+    session: BatchSession,
+    on_failure: BatchFailure,
+    next: usize,
+    current: Option<BatchItem>,
+    draft: Option<String>,
+    done: usize,
+    failed: usize,
+}
+
+struct BatchItem {
+    index: usize,
+    prompt_id: Option<u64>,
+    outcome: Option<Result<(), String>>,
+}
+
+fn is_batch_prompt(input: &str, prompt: &str) -> bool {
+    !input.trim().is_empty() && input.trim() == prompt.trim()
+}
+
+impl PromptBatch {
+    pub(super) fn progress(&self) -> String {
+        let position = self
+            .current
+            .as_ref()
+            .map_or(self.next, |item| item.index + 1);
+        format!("batch {}/{}", position.max(1), self.items.len())
+    }
+
+    pub(super) fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub(super) fn queued(&self) -> usize {
+        self.items.len() - self.next
+    }
+}
+
+fn parse_header(paragraph: &str) -> Result<Option<(BatchSession, BatchFailure)>, String> {
+    if !paragraph
+        .lines()
+        .all(|line| line.trim_start().starts_with(HEADER_PREFIX))
+    {
+        return Ok(None);
+    }
+    let mut session = BatchSession::Same;
+    let mut on_failure = BatchFailure::Stop;
+    for line in paragraph.lines() {
+        let directive = line.trim_start()[HEADER_PREFIX.len()..].trim();
+        let (key, value) = directive
+            .split_once(':')
+            .map(|(key, value)| (key.trim(), value.trim()))
+            .ok_or_else(|| format!("Unreadable batch header line: {}", line.trim()))?;
+        match (
+            key.to_ascii_lowercase().as_str(),
+            value.to_ascii_lowercase().as_str(),
+        ) {
+            ("session", "same") => session = BatchSession::Same,
+            ("session", "fresh") => session = BatchSession::Fresh,
+            ("on-error", "stop") => on_failure = BatchFailure::Stop,
+            ("on-error", "skip") => on_failure = BatchFailure::Skip,
+            _ => {
+                return Err(format!(
+                    "Unknown batch header {}: {} (use session: same|fresh, on-error: stop|skip)",
+                    key, value
+                ));
+            }
+        }
+    }
+    Ok(Some((session, on_failure)))
+}
+
+fn parse_batch(text: &str) -> Result<BatchPlan, String> {
+    let text = text.strip_prefix('\u{FEFF}').unwrap_or(text);
+    let mut paragraphs: Vec<String> = Vec::new();
+    let mut current: Vec<&str> = Vec::new();
+    for line in text.lines() {
+        if line.trim().is_empty() {
+            if !current.is_empty() {
+                paragraphs.push(current.join("\n"));
+                current.clear();
+            }
+        } else {
+            current.push(line);
+        }
+    }
+    if !current.is_empty() {
+        paragraphs.push(current.join("\n"));
+    }
+    let header = match paragraphs.first() {
+        Some(first) => parse_header(first)?,
+        None => None,
+    };
+    if header.is_some() {
+        paragraphs.remove(0);
+    }
+    if paragraphs.is_empty() {
+        return Err("The batch file has no prompts".to_owned());
+    }
+    let (session, on_failure) = header.unwrap_or((BatchSession::Same, BatchFailure::Stop));
+    Ok(BatchPlan {
+        items: paragraphs,
+        session,
+        on_failure,
+    })
+}
+
+impl CodexAgentApp {
+    pub(super) fn run_batch_command(&mut self, path: &str) -> Result<String, String> {
+        if path.trim().is_empty() {
+            return Err("Usage: /batch <path>".to_owned());
+        }
+        if self.batch.is_some() {
+            return Err(
+                "A batch is already running \u{2014} cancel it before starting another".to_owned(),
+            );
+        }
+        if self.busy {
+            return Err("Wait for the current prompt before starting a batch".to_owned());
+        }
+        let path = Path::new(&self.cwd_text).join(path.trim());
+        let text = fs::read_to_string(&path)
+            .map_err(|error| format!("Failed to read {}: {}", path.display(), error))?;
+        let plan = parse_batch(&text)?;
+        let name = path.file_name().map_or_else(
+            || path.display().to_string(),
+            |name| name.to_string_lossy().into_owned(),
+        );
+        logging::trace(format!(
+            "starting batch of {} prompts from {}",
+            plan.items.len(),
+            path.display()
+        ));
+        let message = format!(
+            "Batch of {} prompts from {} \u{2014} {}, {} on failure. Cancel \u{25BE} \u{2192} Cancel all stops the batch.",
+            plan.items.len(),
+            name,
+            match plan.session {
+                BatchSession::Same => "same session",
+                BatchSession::Fresh => "fresh session per prompt",
+            },
+            match plan.on_failure {
+                BatchFailure::Stop => "stop",
+                BatchFailure::Skip => "skip and continue",
+            }
+        );
+        self.batch = Some(PromptBatch {
+            name,
+            items: plan.items,
+            session: plan.session,
+            on_failure: plan.on_failure,
+            next: 0,
+            current: None,
+            draft: None,
+            done: 0,
+            failed: 0,
+        });
+        Ok(message)
+    }
+
+    pub(super) fn start_launch_batch(&mut self, path: PathBuf) {
+        match self.run_batch_command(&path.to_string_lossy()) {
+            Ok(message) => self.push_settings_output(&message),
+            Err(error) => {
+                logging::error(format!("failed to start batch from --batch: {}", error));
+                self.push_local_error(&error);
+            }
+        }
+        self.finish_local_change();
+    }
+
+    fn batch_waiting(&self) -> bool {
+        self.busy
+            || self.setup_state != SetupState::Ready
+            || self.cancelling_prompt.is_some()
+            || self.rate_limit_wait.is_some()
+            || self.pending_approval.is_some()
+            || self.secret_submit_confirmation.is_some()
+            || self.clear_confirmation.is_some()
+            || self.dirty_submit_confirmation.is_some()
+            || self.project_trust_confirmation
+            || self.token_submit_confirmation.is_some()
+    }
+
+    pub(super) fn note_batch_result(&mut self, prompt_id: u64, outcome: Result<(), String>) {
+        if let Some(item) = self
+            .batch
+            .as_mut()
+            .and_then(|batch| batch.current.as_mut())
+            .filter(|item| item.prompt_id == Some(prompt_id))
+        {
+            item.outcome = Some(outcome);
+        }
+    }
+
+    pub(super) fn pump_batch(&mut self) {
+        let Some(batch) = self.batch.as_mut() else {
+            return;
+        };
+        if let Some(item) = batch.current.as_mut()
+            && item.prompt_id.is_none()
+            && self.busy
+        {
+            item.prompt_id = self.active_prompt_id;
+        }
+        if self.batch_waiting() {
+            return;
+        }
+        let Some(batch) = self.batch.as_mut() else {
+            return;
+        };
+        let Some(item) = batch.current.take() else {
+            self.start_next_batch_item();
+            return;
+        };
+        let outcome = match item.outcome {
+            Some(outcome) => outcome,
+            None if item.prompt_id.is_some() => Ok(()),
+            None => Err("not sent".to_owned()),
+        };
+        self.finish_batch_item(item.index, outcome);
+    }
+
+    fn settle_batch_input(&mut self, index: usize) {
+        let Some(batch) = self.batch.as_mut() else {
+            return;
+        };
+        let draft = batch.draft.take();
+        if is_batch_prompt(&self.input, &batch.items[index]) {
+            self.input.clear();
+        }
+        if let Some(draft) = draft
+            && self.input.trim().is_empty()
+        {
+            self.input = draft;
+        }
+        self.invalidate_input_layout();
+        self.mark_draft_changed();
+    }
+
+    fn finish_batch_item(&mut self, index: usize, outcome: Result<(), String>) {
+        self.settle_batch_input(index);
+        let Some(batch) = self.batch.as_mut() else {
+            return;
+        };
+        let total = batch.items.len();
+        let stop = match &outcome {
+            Ok(()) => {
+                batch.done += 1;
+                false
+            }
+            Err(_) => {
+                batch.failed += 1;
+                batch.on_failure == BatchFailure::Stop
+            }
+        };
+        let message = match outcome {
+            Ok(()) => format!("Batch {}/{} done", index + 1, total),
+            Err(error) if stop => format!(
+                "Batch {}/{} failed ({}) \u{2014} stopping the batch",
+                index + 1,
+                total,
+                error
+            ),
+            Err(error) => format!(
+                "Batch {}/{} failed ({}) \u{2014} skipping to the next prompt",
+                index + 1,
+                total,
+                error
+            ),
+        };
+        logging::trace(message.to_lowercase());
+        self.push_settings_output(&message);
+        if stop {
+            self.end_batch("stopped");
+        } else {
+            self.finish_local_change();
+        }
+    }
+
+    fn start_next_batch_item(&mut self) {
+        let Some(batch) = self.batch.as_mut() else {
+            return;
+        };
+        let index = batch.next;
+        if index >= batch.items.len() {
+            self.end_batch("finished");
+            return;
+        }
+        batch.next += 1;
+        let prompt = batch.items[index].clone();
+        let fresh = batch.session == BatchSession::Fresh && index > 0;
+        batch.current = Some(BatchItem {
+            index,
+            prompt_id: None,
+            outcome: None,
+        });
+        if fresh && self.start_new_topic() {
+            logging::trace("starting a fresh session for the next batch prompt");
+        }
+        let draft = std::mem::replace(&mut self.input, prompt);
+        let previous_prompt = self.active_prompt_id;
+        self.submit();
+        let started = self.active_prompt_id != previous_prompt;
+        let waiting = self.batch_waiting();
+        let sent = self.input.trim().is_empty();
+        if let Some(batch) = self.batch.as_mut() {
+            batch.draft = Some(draft).filter(|draft| !draft.trim().is_empty());
+            if let Some(item) = batch.current.as_mut() {
+                if started {
+                    item.prompt_id = self.active_prompt_id;
+                } else if !waiting {
+                    item.outcome = Some(if sent {
+                        Ok(())
+                    } else {
+                        Err("not sent".to_owned())
+                    });
+                }
+            }
+        }
+        if started {
+            self.settle_batch_input(index);
+        }
+    }
+
+    fn end_batch(&mut self, reason: &str) {
+        let Some(batch) = self.batch.take() else {
+            return;
+        };
+        let skipped = batch.items.len() - batch.done - batch.failed;
+        let message = format!(
+            "Batch {} {}: {} done, {} failed, {} not run",
+            batch.name, reason, batch.done, batch.failed, skipped
+        );
+        logging::trace(message.to_lowercase());
+        self.push_settings_output(&message);
+        self.finish_local_change();
+    }
+
+    pub(super) fn cancel_batch(&mut self) {
+        if self.batch.is_none() {
+            return;
+        }
+        logging::trace("cancelling the batch");
+        self.abort_secret_submit();
+        self.abort_dirty_submit();
+        self.abort_project_trust();
+        self.abort_token_submit();
+        if self.busy {
+            self.cancel_active_prompt();
+        }
+        if let Some(index) = self
+            .batch
+            .as_mut()
+            .and_then(|batch| batch.current.take())
+            .map(|item| item.index)
+        {
+            self.settle_batch_input(index);
+            if let Some(batch) = self.batch.as_mut() {
+                batch.failed += 1;
+            }
+        }
+        self.end_batch("cancelled");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn paragraphs_become_prompts() {
+        let plan =
+            parse_batch("\u{FEFF}first prompt\n\n\n  second\n  spans lines  \n \nthird").unwrap();
+        assert_eq!(
+            plan.items,
+            ["first prompt", "  second\n  spans lines  ", "third"]
+        );
+        assert_eq!(plan.session, BatchSession::Same);
+        assert_eq!(plan.on_failure, BatchFailure::Stop);
+        assert!(parse_batch("\n \n").is_err());
+    }
+
+    #[test]
+    fn a_header_paragraph_sets_session_and_failure_handling() {
+        let plan = parse_batch("#! session: Fresh\n  #! on-error: skip\n\nonly prompt\n").unwrap();
+        assert_eq!(plan.items, ["only prompt"]);
+        assert_eq!(plan.session, BatchSession::Fresh);
+        assert_eq!(plan.on_failure, BatchFailure::Skip);
+
+        let mixed = parse_batch("#! session: fresh\nnot a header\n").unwrap();
+        assert_eq!(mixed.items, ["#! session: fresh\nnot a header"]);
+        assert_eq!(mixed.session, BatchSession::Same);
+
+        assert!(parse_batch("#! session: later\n\nprompt").is_err());
+        assert!(parse_batch("#! session\n\nprompt").is_err());
+        assert!(
+            parse_batch("#! on-error: skip\n").is_err(),
+            "a header alone has no prompts"
+        );
+    }
+
+    #[test]
+    fn only_the_untouched_batch_prompt_is_cleared() {
+        assert!(is_batch_prompt(" review the diff\n", "review the diff"));
+        assert!(!is_batch_prompt(
+            "review the diff and more",
+            "review the diff"
+        ));
+        assert!(!is_batch_prompt("  ", "  "));
+    }
+}
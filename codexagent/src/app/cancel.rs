@@ -0,0 +1,190 @@
+use crate::logging;
+
+use super::CodexAgentApp;
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub(super) enum CancelScope {
+    Active,
+    All,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub(super) enum PromptState {
+    Idle,
+    Busy(u64),
+    Cancelling(u64),
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum CancelAction {
+    Nothing,
+    ActivePrompt,
+    Batch,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum ClearAction {
+    Nothing,
+    Clear,
+    Confirm(usize),
+}
+
+fn prompt_state(active: Option<u64>, cancelling: Option<u64>) -> PromptState {
+    match (cancelling, active) {
+        (Some(prompt_id), _) => PromptState::Cancelling(prompt_id),
+        (None, Some(prompt_id)) => PromptState::Busy(prompt_id),
+        (None, None) => PromptState::Idle,
+    }
+}
+
+fn cancel_action(scope: CancelScope, busy: bool, batch: bool) -> CancelAction {
+    match scope {
+        CancelScope::All if batch => CancelAction::Batch,
+        _ if busy => CancelAction::ActivePrompt,
+        _ => CancelAction::Nothing,
+    }
+}
+
+fn clear_action(clearable: bool, queued: usize, notes: bool, confirmed: bool) -> ClearAction {
+    match (clearable, queued, notes) {
+        (false, ..) => ClearAction::Nothing,
+        (true, 0, false) => ClearAction::Clear,
+        (true, ..) if confirmed => ClearAction::Clear,
+        (true, queued, _) => ClearAction::Confirm(queued),
+    }
+}
+
+impl CodexAgentApp {
+    pub(super) fn prompt_state(&self) -> PromptState {
+        prompt_state(self.active_prompt_id, self.cancelling_prompt)
+    }
+
+    pub(super) fn queued_prompts(&self) -> usize {
+        self.batch.as_ref().map_or(0, |batch| batch.queued())
+    }
+
+    pub(super) fn can_cancel_all(&self) -> bool {
+        self.batch.is_some()
+    }
+
+    pub(super) fn cancel(&mut self, scope: CancelScope) {
+        match cancel_action(scope, self.busy, self.batch.is_some()) {
+            CancelAction::Nothing => {}
+            CancelAction::ActivePrompt => self.cancel_active_prompt(),
+            CancelAction::Batch => self.cancel_batch(),
+        }
+    }
+
+    pub(super) fn request_clear(&mut self) {
+        self.run_clear(false);
+    }
+
+    pub(super) fn confirm_clear(&mut self) {
+        if self.clear_confirmation.take().is_some() {
+            self.run_clear(true);
+        }
+    }
+
+    pub(super) fn abort_clear(&mut self) {
+        if self.clear_confirmation.take().is_some() {
+            logging::trace("clear aborted; keeping queued prompts");
+            self.resize_for_text();
+        }
+    }
+
+    fn run_clear(&mut self, confirmed: bool) {
+        match clear_action(
+            self.can_clear(),
+            self.queued_prompts(),
+            !self.notes.text.trim().is_empty(),
+            confirmed,
+        ) {
+            ClearAction::Nothing => self.clear_confirmation = None,
+            ClearAction::Clear => {
+                self.clear_confirmation = None;
+                if self.batch.is_some() {
+                    logging::trace("clearing the session discards the queued batch prompts");
+                    self.cancel_batch();
+                }
+                self.clear_session();
+            }
+            ClearAction::Confirm(queued) => {
+                logging::trace(format!(
+                    "clear needs confirmation: {} queued prompts, {} notes",
+                    queued,
+                    self.notes.text.len()
+                ));
+                self.clear_confirmation = Some(queued);
+                self.resize_for_text();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cancel_targets_the_active_prompt_unless_all_is_asked() {
+        use CancelAction::*;
+        use CancelScope::*;
+        // (scope, busy, batch) -> action
+        let table = [
+            (Active, false, false, Nothing),
+            (Active, true, false, ActivePrompt),
+            (Active, false, true, Nothing),
+            (Active, true, true, ActivePrompt),
+            (All, false, false, Nothing),
+            (All, true, false, ActivePrompt),
+            (All, false, true, Batch),
+            (All, true, true, Batch),
+        ];
+        for (scope, busy, batch, action) in table {
+            assert_eq!(
+                cancel_action(scope, busy, batch),
+                action,
+                "{:?} busy={} batch={}",
+                scope,
+                busy,
+                batch
+            );
+        }
+    }
+
+    #[test]
+    fn clear_confirms_before_discarding_queued_prompts_or_notes() {
+        use ClearAction::*;
+        // (clearable, queued, notes, confirmed) -> action
+        let table = [
+            (false, 0, false, false, Nothing),
+            (false, 3, true, true, Nothing),
+            (true, 0, false, false, Clear),
+            (true, 0, false, true, Clear),
+            (true, 2, false, false, Confirm(2)),
+            (true, 2, false, true, Clear),
+            (true, 0, true, false, Confirm(0)),
+            (true, 0, true, true, Clear),
+            (true, 4, true, false, Confirm(4)),
+        ];
+        for (clearable, queued, notes, confirmed, action) in table {
+            assert_eq!(
+                clear_action(clearable, queued, notes, confirmed),
+                action,
+                "clearable={} queued={} notes={} confirmed={}",
+                clearable,
+                queued,
+                notes,
+                confirmed
+            );
+        }
+    }
+
+    #[test]
+    fn cancelling_wins_over_the_active_prompt() {
+        assert_eq!(prompt_state(None, None), PromptState::Idle);
+        assert_eq!(prompt_state(Some(3), None), PromptState::Busy(3));
+        assert_eq!(prompt_state(Some(4), Some(3)), PromptState::Cancelling(3));
+        assert_eq!(prompt_state(None, Some(3)), PromptState::Cancelling(3));
+    }
+}
@@ -0,0 +1,222 @@
+use std::path::{Path, PathBuf};
+use std::thread;
+
+use crate::events::AppEvent;
+use crate::logging;
+use crate::runtime::{DiffLine, git_file_diff, git_revert_file, open_in_shell};
+
+use super::CodexAgentApp;
+
+pub(super) struct ChangedFile {
+    pub(super) path: String,
+    pub(super) diff: Option<Vec<DiffLine>>,
+    pub(super) error: Option<String>,
+    pub(super) confirming_revert: bool,
+    pub(super) pending: bool,
+    pub(super) reverted: bool,
+}
+
+pub(super) enum ChangedFileAction {
+    Open,
+    Diff,
+    Revert,
+    ConfirmRevert,
+    CancelRevert,
+}
+
+fn diff_lines(diff: &str) -> Vec<DiffLine> {
+    diff.lines()
+        .map(|line| {
+            if line.starts_with("+++") || line.starts_with("---") {
+                DiffLine::Same(line.to_owned())
+            } else if let Some(text) = line.strip_prefix('+') {
+                DiffLine::Added(text.to_owned())
+            } else if let Some(text) = line.strip_prefix('-') {
+                DiffLine::Removed(text.to_owned())
+            } else {
+                DiffLine::Same(line.strip_prefix(' ').unwrap_or(line).to_owned())
+            }
+        })
+        .collect()
+}
+
+pub(super) fn changed_files_label(files: &[ChangedFile]) -> String {
+    let names = files
+        .iter()
+        .take(2)
+        .map(|file| {
+            Path::new(&file.path).file_name().map_or_else(
+                || file.path.clone(),
+                |name| name.to_string_lossy().into_owned(),
+            )
+        })
+        .collect::<Vec<_>>()
+        .join(", ");
+    match files.len().saturating_sub(2) {
+        0 => format!("Files changed: {}", names),
+        more => format!("Files changed: {} +{}", names, more),
+    }
+}
+
+impl CodexAgentApp {
+    pub(super) fn record_changed_files(&mut self, files: Vec<String>) {
+        if files.is_empty() {
+            return;
+        }
+        logging::trace(format!("codex changed {} files", files.len()));
+        self.changed_files = files
+            .into_iter()
+            .map(|path| ChangedFile {
+                path,
+                diff: None,
+                error: None,
+                confirming_revert: false,
+                pending: false,
+                reverted: false,
+            })
+            .collect();
+        self.changed_files_open = false;
+    }
+
+    pub(super) fn changed_files_git_ready(&self) -> bool {
+        self.cwd_git_info().is_some()
+    }
+
+    pub(super) fn toggle_changed_files(&mut self) {
+        self.changed_files_open = !self.changed_files_open;
+        self.resize_for_text();
+    }
+
+    pub(super) fn display_changed_file_path<'a>(&self, path: &'a str) -> &'a str {
+        Path::new(path)
+            .strip_prefix(&self.cwd_text)
+            .ok()
+            .and_then(Path::to_str)
+            .unwrap_or(path)
+    }
+
+    fn changed_file_location(&self, path: &str) -> PathBuf {
+        Path::new(&self.cwd_text).join(path)
+    }
+
+    pub(super) fn run_changed_file_action(&mut self, index: usize, action: ChangedFileAction) {
+        let Some(path) = self.changed_files.get(index).map(|file| file.path.clone()) else {
+            return;
+        };
+        match action {
+            ChangedFileAction::Open => {
+                let location = self.changed_file_location(&path);
+                let result = open_in_shell(&location.to_string_lossy());
+                if let Some(file) = self.changed_files.get_mut(index) {
+                    file.error = result
+                        .err()
+                        .map(|error| format!("Failed to open: {}", error));
+                }
+            }
+            ChangedFileAction::Diff => self.toggle_changed_file_diff(index, path),
+            ChangedFileAction::Revert => {
+                if let Some(file) = self.changed_files.get_mut(index) {
+                    file.confirming_revert = true;
+                    file.error = None;
+                }
+            }
+            ChangedFileAction::CancelRevert => {
+                if let Some(file) = self.changed_files.get_mut(index) {
+                    file.confirming_revert = false;
+                }
+            }
+            ChangedFileAction::ConfirmRevert => self.revert_changed_file(index, path),
+        }
+        self.resize_for_text();
+    }
+
+    fn toggle_changed_file_diff(&mut self, index: usize, path: String) {
+        let Some(file) = self.changed_files.get_mut(index) else {
+            return;
+        };
+        if file.diff.take().is_some() || file.pending {
+            return;
+        }
+        file.pending = true;
+        file.error = None;
+        logging::trace(format!("loading git diff for {}", path));
+        let dir = PathBuf::from(&self.cwd_text);
+        let tx = self.tx.clone();
+        let ctx = self.ctx.clone();
+        thread::spawn(move || {
+            let result = match logging::catch_panic("changed file diff thread", || {
+                git_file_diff(&dir, &path).map_err(|error| error.to_string())
+            }) {
+                Ok(result) => result,
+                Err(message) => Err(message),
+            };
+            if tx.send(AppEvent::ChangedFileDiff(path, result)).is_err() {
+                logging::error("failed to deliver changed file diff to app");
+            }
+            ctx.request_repaint();
+        });
+    }
+
+    fn revert_changed_file(&mut self, index: usize, path: String) {
+        let Some(file) = self.changed_files.get_mut(index) else {
+            return;
+        };
+        if file.pending {
+            return;
+        }
+        file.confirming_revert = false;
+        file.pending = true;
+        logging::trace(format!("reverting {}", path));
+        let dir = PathBuf::from(&self.cwd_text);
+        let tx = self.tx.clone();
+        let ctx = self.ctx.clone();
+        thread::spawn(move || {
+            let result = match logging::catch_panic("changed file revert thread", || {
+                git_revert_file(&dir, &path).map_err(|error| error.to_string())
+            }) {
+                Ok(result) => result,
+                Err(message) => Err(message),
+            };
+            if tx
+                .send(AppEvent::ChangedFileReverted(path, result))
+                .is_err()
+            {
+                logging::error("failed to deliver changed file revert to app");
+            }
+            ctx.request_repaint();
+        });
+    }
+
+    pub(super) fn apply_changed_file_diff(&mut self, path: &str, result: Result<String, String>) {
+        let Some(file) = self.changed_files.iter_mut().find(|file| file.path == path) else {
+            return;
+        };
+        file.pending = false;
+        match result {
+            Ok(diff) if diff.trim().is_empty() => {
+                file.error = Some("No uncommitted changes".to_owned());
+            }
+            Ok(diff) => file.diff = Some(diff_lines(&diff)),
+            Err(error) => file.error = Some(format!("Diff failed: {}", error)),
+        }
+        self.resize_for_text();
+    }
+
+    pub(super) fn apply_changed_file_revert(&mut self, path: &str, result: Result<(), String>) {
+        let Some(file) = self.changed_files.iter_mut().find(|file| file.path == path) else {
+            return;
+        };
+        file.pending = false;
+        match result {
+            Ok(()) => {
+                logging::trace(format!("reverted {}", path));
+                file.reverted = true;
+                file.diff = None;
+                file.error = None;
+                self.invalidate_git_info();
+            }
+            Err(error) => file.error = Some(format!("Revert failed: {}", error)),
+        }
+        self.resize_for_text();
+    }
+}
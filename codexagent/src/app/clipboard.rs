@@ -0,0 +1,162 @@
+use std::time::{Duration, Instant};
+
+use eframe::egui::{self, OutputCommand};
+
+use crate::config::set_clipboard_watch;
+use crate::logging;
+use crate::runtime::watch_clipboard;
+
+use super::CodexAgentApp;
+use super::input_assist::wrap_in_fence;
+
+const CLIPBOARD_OFFER_TIMEOUT: Duration = Duration::from_secs(30);
+const CLIPBOARD_PREVIEW_CHARS: usize = 120;
+
+pub(super) struct ClipboardOffer {
+    text: String,
+    offered_at: Instant,
+}
+
+impl CodexAgentApp {
+    pub(super) fn sync_clipboard_watcher(&mut self) {
+        if !self.clipboard_watch {
+            if self.clipboard_watcher.take().is_some() {
+                self.clipboard_offer = None;
+            }
+            return;
+        }
+        if self.clipboard_watcher.is_none() {
+            self.clipboard_watcher = watch_clipboard(self.tx.clone(), self.repaint_handle());
+        }
+    }
+
+    pub(super) fn note_own_clipboard_copies(&mut self, ctx: &egui::Context) {
+        if !self.clipboard_watch {
+            return;
+        }
+        let copied = ctx.output(|output| {
+            output
+                .commands
+                .iter()
+                .rev()
+                .find_map(|command| match command {
+                    OutputCommand::CopyText(text) => Some(text.clone()),
+                    _ => None,
+                })
+        });
+        if copied.is_some() {
+            self.own_clipboard_text = copied;
+        }
+    }
+
+    pub(super) fn handle_clipboard_text(&mut self, text: String) {
+        if !self.clipboard_watch {
+            return;
+        }
+        if self.own_clipboard_text.as_deref() == Some(text.as_str()) {
+            logging::trace("ignoring clipboard text copied from this app");
+            return;
+        }
+        if self.was_minimized {
+            logging::trace("ignoring clipboard change while the window is minimized");
+            return;
+        }
+        logging::trace(format!(
+            "offering {} copied characters as a quick action",
+            text.chars().count()
+        ));
+        self.clipboard_offer = Some(ClipboardOffer {
+            text,
+            offered_at: Instant::now(),
+        });
+        self.resize_for_text();
+    }
+
+    pub(super) fn expire_clipboard_offer(&mut self) {
+        let Some(offer) = self.clipboard_offer.as_ref() else {
+            return;
+        };
+        let elapsed = offer.offered_at.elapsed();
+        if elapsed < CLIPBOARD_OFFER_TIMEOUT {
+            self.ctx
+                .request_repaint_after(CLIPBOARD_OFFER_TIMEOUT - elapsed);
+            return;
+        }
+        self.dismiss_clipboard_offer();
+    }
+
+    pub(super) fn clipboard_offer_label(&self) -> Option<(String, String)> {
+        let offer = self.clipboard_offer.as_ref()?;
+        let preview: String = offer
+            .text
+            .split_whitespace()
+            .collect::<Vec<_>>()
+            .join(" ")
+            .chars()
+            .take(CLIPBOARD_PREVIEW_CHARS)
+            .collect();
+        Some((
+            format!(
+                "Ask about copied text ({} chars)",
+                offer.text.chars().count()
+            ),
+            format!("Insert the copied text as a fenced block: {}", preview),
+        ))
+    }
+
+    pub(super) fn accept_clipboard_offer(&mut self) {
+        let Some(offer) = self.clipboard_offer.take() else {
+            return;
+        };
+        logging::trace("inserting copied text into the prompt");
+        let fenced = wrap_in_fence(&offer.text);
+        let draft = self.input.trim_end();
+        self.input = if draft.is_empty() {
+            format!("{}\n\n", fenced)
+        } else {
+            format!("{}\n\n{}\n\n", draft, fenced)
+        };
+        self.store_input_cursor(self.input.chars().count());
+        self.pending_input_focus = true;
+        self.mark_draft_changed();
+        self.refresh_after_text_change();
+    }
+
+    pub(super) fn dismiss_clipboard_offer(&mut self) {
+        if self.clipboard_offer.take().is_some() {
+            self.resize_for_text();
+        }
+    }
+
+    pub(super) fn select_clipboard_watch(&mut self, enabled: bool) {
+        self.clear_picker_selection();
+        if self.clipboard_watch == enabled {
+            return;
+        }
+        match set_clipboard_watch(enabled) {
+            Ok(enabled) => {
+                self.clipboard_watch = enabled;
+                self.sync_clipboard_watcher();
+                if enabled && self.clipboard_watcher.is_none() {
+                    self.push_local_error("Clipboard quick action could not start; see the log");
+                } else {
+                    self.push_settings_output(if enabled {
+                        "Clipboard quick action set to On \u{2014} copied text offers an \u{201C}Ask about\u{201D} chip"
+                    } else {
+                        "Clipboard quick action set to Off"
+                    });
+                    self.finish_local_success();
+                }
+            }
+            Err(error) => {
+                logging::error(format!(
+                    "failed to set clipboard quick action {}: {}",
+                    if enabled { "on" } else { "off" },
+                    error
+                ));
+                self.push_local_error(&format!("Failed to set clipboard quick action: {}", error));
+            }
+        }
+        self.finish_local_change();
+    }
+}
@@ -0,0 +1,727 @@
+use std::path::PathBuf;
+
+use crate::config::{
+    APP_DISPLAY_NAME, DEFAULT_EXPORT_TURN_MARKERS, PromptBudget, build_stamp,
+    load_export_turn_markers, load_prompt_budget, paths, set_prompt_budget,
+};
+use crate::logging;
+use crate::plaintext::markdown_to_plain;
+use crate::prompt::describe_codex_command;
+use crate::redact;
+use crate::runtime::{current_cwd_text, set_model, set_process_cwd, write_file_atomic};
+
+use super::notes::notes_markdown;
+use super::transcript::SegmentKind;
+use super::{CodexAgentApp, SLASH_COMMANDS};
+
+const EXPORT_PLAIN_FLAG: &str = "--plain";
+const EXPORT_JSON_FLAG: &str = "--json";
+const EXPORT_NOTES_FLAG: &str = "--notes";
+
+pub(super) enum LocalCommand {
+    Status,
+    Clear,
+    New,
+    Model(String),
+    Profile(String),
+    Title(String),
+    Cd(String),
+    Resume(String),
+    Export(ExportCommand),
+    Batch(String),
+    Stats,
+    Budget(String),
+    Retry,
+    Lang(String),
+    Concise,
+    Sessions,
+    Inspect,
+    DryRun,
+    Diag,
+    Help,
+    Unknown(String),
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub(super) enum ExportFormat {
+    Markdown,
+    Plain,
+    Json,
+}
+
+pub(super) struct ExportCommand {
+    path: String,
+    format: ExportFormat,
+    notes: bool,
+}
+
+pub(super) enum CommandError {
+    MissingArgument(&'static str),
+    TooManyArguments(&'static str),
+    UnterminatedQuote,
+}
+
+impl CommandError {
+    fn message(&self) -> String {
+        match self {
+            Self::MissingArgument(usage) => format!("Usage: {}", usage),
+            Self::TooManyArguments(usage) => {
+                format!("Usage: {} (quote arguments that contain spaces)", usage)
+            }
+            Self::UnterminatedQuote => "Unterminated quote in command".to_owned(),
+        }
+    }
+}
+
+pub(super) fn parse_local_command(input: &str) -> Option<Result<LocalCommand, CommandError>> {
+    let command = input.trim().strip_prefix('/')?;
+    let (name, args) = command
+        .split_once(char::is_whitespace)
+        .map_or((command, ""), |(name, args)| (name, args));
+    if name.is_empty()
+        || !name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+    {
+        return None;
+    }
+    let args = match split_arguments(args) {
+        Ok(args) => args,
+        Err(error) => return Some(Err(error)),
+    };
+    let Some(spec) = SLASH_COMMANDS.iter().find(|spec| spec.name == name) else {
+        return Some(Ok(LocalCommand::Unknown(name.to_owned())));
+    };
+    let takes_argument = spec.usage.contains('<');
+    let mut args = args.into_iter().peekable();
+    let (mut format, mut notes) = (ExportFormat::Markdown, false);
+    if name == "export" {
+        while let Some(flag) = args.next_if(|arg| {
+            [EXPORT_PLAIN_FLAG, EXPORT_JSON_FLAG, EXPORT_NOTES_FLAG].contains(&arg.as_str())
+        }) {
+            let chosen = match flag.as_str() {
+                EXPORT_PLAIN_FLAG => ExportFormat::Plain,
+                EXPORT_JSON_FLAG => ExportFormat::Json,
+                _ => {
+                    notes = true;
+                    continue;
+                }
+            };
+            if format != ExportFormat::Markdown && format != chosen {
+                return Some(Err(CommandError::MissingArgument(spec.usage)));
+            }
+            format = chosen;
+        }
+    }
+    let argument = args.next();
+    if args.next().is_some() || (!takes_argument && argument.is_some()) {
+        return Some(Err(CommandError::TooManyArguments(spec.usage)));
+    }
+    if takes_argument && argument.is_none() {
+        return Some(Err(CommandError::MissingArgument(spec.usage)));
+    }
+    let argument = argument.unwrap_or_default();
+    Some(Ok(match name {
+        "status" => LocalCommand::Status,
+        "clear" => LocalCommand::Clear,
+        "new" => LocalCommand::New,
+        "model" => LocalCommand::Model(argument),
+        "profile" => LocalCommand::Profile(argument),
+        "title" => LocalCommand::Title(argument),
+        "cd" => LocalCommand::Cd(argument),
+        "resume" => LocalCommand::Resume(argument),
+        "export" => LocalCommand::Export(ExportCommand {
+            path: argument,
+            format,
+            notes,
+        }),
+        "batch" => LocalCommand::Batch(argument),
+        "stats" => LocalCommand::Stats,
+        "budget" => LocalCommand::Budget(argument),
+        "retry" => LocalCommand::Retry,
+        "lang" => LocalCommand::Lang(argument),
+        "concise" => LocalCommand::Concise,
+        "sessions" => LocalCommand::Sessions,
+        "inspect" => LocalCommand::Inspect,
+        "dryrun" => LocalCommand::DryRun,
+        "diag" => LocalCommand::Diag,
+        "help" => LocalCommand::Help,
+        _ => LocalCommand::Unknown(name.to_owned()),
+    }))
+}
+
+fn split_arguments(args: &str) -> Result<Vec<String>, CommandError> {
+    let mut arguments = Vec::new();
+    let mut current = String::new();
+    let mut quote = None;
+    let mut in_argument = false;
+    for c in args.chars() {
+        match quote {
+            Some(open) if c == open => quote = None,
+            Some(_) => current.push(c),
+            None if c == '"' || c == '\'' => {
+                quote = Some(c);
+                in_argument = true;
+            }
+            None if c.is_whitespace() => {
+                if in_argument {
+                    arguments.push(std::mem::take(&mut current));
+                    in_argument = false;
+                }
+            }
+            None => {
+                current.push(c);
+                in_argument = true;
+            }
+        }
+    }
+    if quote.is_some() {
+        return Err(CommandError::UnterminatedQuote);
+    }
+    if in_argument {
+        arguments.push(current);
+    }
+    Ok(arguments)
+}
+
+fn parse_budget_limit(limit: &str, budget: PromptBudget) -> Option<PromptBudget> {
+    let limit = limit.trim().to_ascii_lowercase();
+    if limit == "off" {
+        return Some(PromptBudget::default());
+    }
+    let digits = limit.find(|c: char| !c.is_ascii_digit())?;
+    let (count, unit) = limit.split_at(digits);
+    let count = count.parse::<u64>().ok()?;
+    match unit {
+        "m" | "min" => Some(PromptBudget {
+            minutes: count,
+            ..budget
+        }),
+        "h" => Some(PromptBudget {
+            minutes: count.checked_mul(60)?,
+            ..budget
+        }),
+        "t" | "tools" | "calls" => Some(PromptBudget {
+            tool_events: usize::try_from(count).ok()?,
+            ..budget
+        }),
+        _ => None,
+    }
+}
+
+fn is_session_id(text: &str) -> bool {
+    text.len() == 36
+        && text.char_indices().all(|(index, c)| match index {
+            8 | 13 | 18 | 23 => c == '-',
+            _ => c.is_ascii_hexdigit(),
+        })
+}
+
+impl CodexAgentApp {
+    pub(super) fn try_run_local_command(&mut self, prompt: &str) -> bool {
+        let Some(command) = parse_local_command(prompt) else {
+            return false;
+        };
+        logging::trace(format!("running local command {}", prompt));
+        self.reset_prompt_history_navigation();
+        if let Ok(LocalCommand::Clear) = command {
+            self.push_prompt_history(prompt);
+            self.request_clear();
+            return true;
+        }
+        if let Ok(LocalCommand::New) = command
+            && self.start_new_topic()
+        {
+            self.push_prompt_history(prompt);
+            self.finish_local_success();
+            self.finish_local_change();
+            return true;
+        }
+        if let Ok(LocalCommand::Retry) = command
+            && let Some(last_prompt) = self.last_submitted_prompt.clone()
+        {
+            self.input = last_prompt;
+            self.submit();
+            return true;
+        }
+        self.push_command_echo(prompt);
+        let result = match command {
+            Ok(LocalCommand::Status) => {
+                self.append_status_output(true);
+                return true;
+            }
+            Ok(LocalCommand::Clear) => return true,
+            Ok(LocalCommand::New) => {
+                Err("Nothing to separate \u{2014} no turns since the last divider".to_owned())
+            }
+            Ok(LocalCommand::Model(model)) => self.run_model_command(&model),
+            Ok(LocalCommand::Profile(name)) => self.run_profile_command(&name),
+            Ok(LocalCommand::Title(title)) => self.run_title_command(&title),
+            Ok(LocalCommand::Cd(path)) => self.run_cd_command(&path),
+            Ok(LocalCommand::Resume(session_id)) => self.run_resume_command(&session_id),
+            Ok(LocalCommand::Export(export)) => self.run_export_command(&export),
+            Ok(LocalCommand::Batch(path)) => self.run_batch_command(&path),
+            Ok(LocalCommand::Stats) => self
+                .session_stats_text()
+                .ok_or_else(|| "No prompts have completed in this session yet".to_owned()),
+            Ok(LocalCommand::Budget(limit)) => self.run_budget_command(&limit),
+            Ok(LocalCommand::Retry) => Err("Nothing to retry yet".to_owned()),
+            Ok(LocalCommand::Lang(language)) => self.run_lang_command(&language),
+            Ok(LocalCommand::Concise) => Ok(self.toggle_concise()),
+            Ok(LocalCommand::Sessions) => self.open_recent_sessions(),
+            Ok(LocalCommand::Inspect) => self.open_event_inspector(),
+            Ok(LocalCommand::DryRun) => Ok(self.toggle_dry_run()),
+            Ok(LocalCommand::Diag) => {
+                self.copy_diagnostics();
+                Ok(format!(
+                    "Collecting diagnostics\u{2026} logs are in {}",
+                    paths::get().log_dir.display()
+                ))
+            }
+            Ok(LocalCommand::Help) => Ok(help_text()),
+            Ok(LocalCommand::Unknown(name)) => Err(format!(
+                "Unknown command /{}. Type /help to list commands.",
+                name
+            )),
+            Err(error) => Err(error.message()),
+        };
+        if result.is_ok() {
+            self.push_prompt_history(prompt);
+            self.finish_local_success();
+        } else {
+            self.input = prompt.to_owned();
+        }
+        self.push_command_result(result);
+        true
+    }
+
+    fn run_model_command(&mut self, model: &str) -> Result<String, String> {
+        let model = set_model(model).map_err(|error| {
+            logging::error(format!("failed to set model {}: {}", model, error));
+            format!("Failed to set model: {}", error)
+        })?;
+        self.current_model = model.clone();
+        Ok(format!("Model set to {}", model))
+    }
+
+    pub(super) fn run_cd_command(&mut self, path: &str) -> Result<String, String> {
+        let path = PathBuf::from(path);
+        set_process_cwd(&path).map_err(|error| {
+            format!(
+                "Failed to change directory to {}: {}",
+                path.display(),
+                error
+            )
+        })?;
+        self.cwd_text = current_cwd_text();
+        self.set_cwd_problem(None);
+        self.drop_warm_codex("the working directory changed");
+        self.refresh_project_config();
+        self.invalidate_git_info();
+        Ok(format!("Working directory: {}", self.cwd_text))
+    }
+
+    fn run_resume_command(&mut self, session_id: &str) -> Result<String, String> {
+        if !is_session_id(session_id) {
+            return Err(format!("Not a valid session id: {}", session_id));
+        }
+        self.session_id = Some(session_id.to_ascii_lowercase());
+        self.reset_session_title();
+        self.cancelled_resume_context = None;
+        self.follow_up_suggestions.clear();
+        self.continuation_offered = false;
+        Ok(format!(
+            "Resuming session {} \u{2014} the next prompt continues it.",
+            session_id
+        ))
+    }
+
+    fn run_budget_command(&mut self, limit: &str) -> Result<String, String> {
+        let current = load_prompt_budget().map_err(|error| {
+            logging::error(format!("failed to load prompt budget: {}", error));
+            format!("Failed to load budget: {}", error)
+        })?;
+        let budget = parse_budget_limit(limit, current).ok_or_else(|| {
+            format!(
+                "Not a budget: {} (use 10m, 2h, 25tools or off; 0 means unlimited)",
+                limit
+            )
+        })?;
+        let budget = set_prompt_budget(budget).map_err(|error| {
+            logging::error(format!("failed to set prompt budget: {}", error));
+            format!("Failed to set budget: {}", error)
+        })?;
+        logging::trace(format!("prompt budget set to {}", budget.describe()));
+        Ok(format!("Budget set to {}", budget.describe()))
+    }
+
+    fn run_export_command(&mut self, export: &ExportCommand) -> Result<String, String> {
+        let ExportCommand { format, notes, .. } = *export;
+        if export.path.is_empty() {
+            return Err("Usage: /export [--plain | --json] [--notes] <path>".to_owned());
+        }
+        let path = PathBuf::from(&export.path);
+        if format == ExportFormat::Json {
+            let json = serde_json::to_string_pretty(&self.session_json(notes))
+                .map_err(|error| format!("Failed to export to {}: {}", path.display(), error))?;
+            write_file_atomic(&path, json.as_bytes(), false)
+                .map_err(|error| format!("Failed to export to {}: {}", path.display(), error))?;
+            return Ok(format!(
+                "Transcript exported to {} as JSON{}",
+                path.display(),
+                if notes { " with notes" } else { "" }
+            ));
+        }
+        let plain = format == ExportFormat::Plain;
+        let turn_markers = load_export_turn_markers().unwrap_or_else(|error| {
+            logging::error(format!(
+                "failed to load export turn marker setting: {}",
+                error
+            ));
+            DEFAULT_EXPORT_TURN_MARKERS
+        });
+        let mut transcript = self.full_transcript_text(turn_markers).trim().to_owned();
+        if let Some(title) = self.session_title.text() {
+            transcript = format!("# {}\n\n{}", title, transcript);
+        }
+        if let Some(stats) = self.session_stats_markdown() {
+            transcript.push_str("\n\n");
+            transcript.push_str(&stats);
+        }
+        if let Some(ratings) = self.ratings_markdown() {
+            transcript.push_str("\n\n");
+            transcript.push_str(&ratings);
+        }
+        if notes && let Some(notes) = notes_markdown(&self.notes.text) {
+            transcript.push_str("\n\n");
+            transcript.push_str(&notes);
+        }
+        transcript.push_str(&format!(
+            "\n\n---\n\n_Exported by {} {}_",
+            APP_DISPLAY_NAME,
+            build_stamp()
+        ));
+        if plain {
+            transcript = markdown_to_plain(&transcript);
+        }
+        let transcript = self.processed_output(transcript);
+        let transcript = redact::redact_persistent(transcript.trim());
+        write_file_atomic(&path, transcript.as_bytes(), false)
+            .map_err(|error| format!("Failed to export to {}: {}", path.display(), error))?;
+        Ok(format!(
+            "Transcript exported to {}{}{}",
+            path.display(),
+            if plain { " as plain text" } else { "" },
+            if notes { " with notes" } else { "" }
+        ))
+    }
+
+    fn toggle_dry_run(&mut self) -> String {
+        self.dry_run = !self.dry_run;
+        logging::trace(format!(
+            "dry run {}",
+            if self.dry_run { "on" } else { "off" }
+        ));
+        if self.dry_run {
+            "Dry run on \u{2014} prompts show the codex command instead of running it.".to_owned()
+        } else {
+            "Dry run off \u{2014} prompts run normally.".to_owned()
+        }
+    }
+
+    pub(super) fn describe_prompt(&mut self, prompt: &str) {
+        let project = self.refresh_project_config();
+        let (outgoing, transform_notes) = self.transform_outgoing_prompt(prompt.to_owned());
+        let request_prompt = self.request_prompt(outgoing, project.as_ref());
+        let description = describe_codex_command(
+            &request_prompt,
+            self.session_id.as_deref(),
+            project.as_ref(),
+        );
+        logging::trace("described codex command for dry run");
+        self.push_command_echo(&format!(
+            "dry run: {}",
+            prompt.lines().next().unwrap_or_default()
+        ));
+        for note in transform_notes {
+            self.transcript
+                .push_line(SegmentKind::Notice, '\x1C', &note);
+        }
+        self.push_command_result(Ok(description));
+    }
+
+    fn push_command_echo(&mut self, prompt: &str) {
+        self.ensure_output_spacing();
+        self.transcript
+            .push_line(SegmentKind::Notice, '\x1C', prompt);
+    }
+
+    fn push_command_result(&mut self, result: Result<String, String>) {
+        let (kind, marker, message) = match result {
+            Ok(message) => (SegmentKind::Notice, '\x1C', message),
+            Err(message) => (SegmentKind::Error, '\x1D', message),
+        };
+        for line in message.lines() {
+            self.transcript.push_line(kind, marker, line);
+        }
+        self.transcript.push(kind, "\n");
+        self.finish_local_change();
+    }
+}
+
+fn help_text() -> String {
+    let width = SLASH_COMMANDS
+        .iter()
+        .map(|command| command.usage.len())
+        .max()
+        .unwrap_or_default();
+    SLASH_COMMANDS
+        .iter()
+        .map(|command| {
+            format!(
+                "{:<width$}  {}",
+                command.usage,
+                command.description,
+                width = width
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SESSION: &str = "0199a3f2-7c1e-7b40-9d2a-5e8f14c06b7a";
+
+    #[test]
+    fn resume_takes_one_session_id() {
+        let parsed = parse_local_command(&format!("  /resume {}  ", SESSION));
+        assert!(matches!(parsed, Some(Ok(LocalCommand::Resume(id))) if id == SESSION));
+        assert!(matches!(
+            parse_local_command("/resume"),
+            Some(Err(CommandError::MissingArgument(_)))
+        ));
+        assert!(matches!(
+            parse_local_command(&format!("/resume {} again", SESSION)),
+            Some(Err(CommandError::TooManyArguments(_)))
+        ));
+    }
+
+    #[test]
+    fn prompts_that_mention_resume_are_not_commands() {
+        assert!(parse_local_command("please /resume the last task").is_none());
+        assert!(parse_local_command("/ resume").is_none());
+        assert!(parse_local_command("/src/resume.rs is broken").is_none());
+    }
+
+    fn argument(input: &str) -> String {
+        match parse_local_command(input) {
+            Some(Ok(
+                LocalCommand::Model(argument)
+                | LocalCommand::Cd(argument)
+                | LocalCommand::Export(ExportCommand { path: argument, .. }),
+            )) => argument,
+            _ => panic!("{:?} should parse with an argument", input),
+        }
+    }
+
+    fn error(input: &str) -> String {
+        match parse_local_command(input) {
+            Some(Err(error)) => error.message(),
+            _ => panic!("{:?} should be rejected", input),
+        }
+    }
+
+    #[test]
+    fn every_listed_command_parses() {
+        for spec in SLASH_COMMANDS {
+            let input = if spec.usage.contains('<') {
+                format!("{} x", spec.label)
+            } else {
+                spec.label.to_owned()
+            };
+            assert!(
+                matches!(parse_local_command(&input), Some(Ok(command)) if !matches!(command, LocalCommand::Unknown(_))),
+                "{}",
+                input
+            );
+        }
+    }
+
+    #[test]
+    fn arguments_can_be_quoted() {
+        assert_eq!(
+            argument("/cd \"C:\\Program Files\\repo\""),
+            "C:\\Program Files\\repo"
+        );
+        assert_eq!(argument("/cd 'my repo'"), "my repo");
+        assert_eq!(argument("/model o3"), "o3");
+        assert_eq!(argument("/export notes\"  v2\".md"), "notes  v2.md");
+        assert_eq!(argument("/cd \"\""), "");
+        assert_eq!(argument("/cd \"it's\""), "it's");
+    }
+
+    #[test]
+    fn surrounding_whitespace_is_ignored() {
+        assert_eq!(argument("  /model   o3  \n"), "o3");
+        assert!(matches!(
+            parse_local_command("\t/clear \r\n"),
+            Some(Ok(LocalCommand::Clear))
+        ));
+    }
+
+    #[test]
+    fn argument_errors_show_the_usage() {
+        assert_eq!(error("/model"), "Usage: /model <name>");
+        assert_eq!(error("/cd   "), "Usage: /cd <path>");
+        assert_eq!(
+            error("/cd my repo"),
+            "Usage: /cd <path> (quote arguments that contain spaces)"
+        );
+        assert_eq!(
+            error("/clear now"),
+            "Usage: /clear (quote arguments that contain spaces)"
+        );
+        assert_eq!(error("/cd \"my repo"), "Unterminated quote in command");
+    }
+
+    #[test]
+    fn unknown_commands_are_reported_not_sent() {
+        assert!(matches!(
+            parse_local_command("/frobnicate the widget"),
+            Some(Ok(LocalCommand::Unknown(name))) if name == "frobnicate"
+        ));
+        assert!(matches!(
+            parse_local_command("/Clear"),
+            Some(Ok(LocalCommand::Unknown(_)))
+        ));
+        assert!(parse_local_command("/").is_none());
+        assert!(parse_local_command("explain /clear").is_none());
+    }
+
+    #[test]
+    fn session_ids_are_uuids() {
+        assert!(is_session_id(SESSION));
+        assert!(is_session_id(&SESSION.to_ascii_uppercase()));
+        assert!(!is_session_id(""));
+        assert!(!is_session_id(&SESSION[1..]));
+        assert!(!is_session_id(&SESSION.replace('-', "_")));
+        assert!(!is_session_id("0199a3f2-7c1e-7b40-9d2a-5e8f14c06b7z"));
+    }
+
+    #[test]
+    fn budget_limits_update_one_side() {
+        let budget = PromptBudget {
+            minutes: 10,
+            tool_events: 25,
+        };
+        for (limit, minutes, tool_events) in [
+            ("5m", 5, 25),
+            (" 2H ", 120, 25),
+            ("30min", 30, 25),
+            ("12tools", 10, 12),
+            ("3t", 10, 3),
+            ("0calls", 10, 0),
+            ("off", 0, 0),
+        ] {
+            assert_eq!(
+                parse_budget_limit(limit, budget),
+                Some(PromptBudget {
+                    minutes,
+                    tool_events
+                }),
+                "{:?}",
+                limit
+            );
+        }
+    }
+
+    #[test]
+    fn malformed_budget_limits_are_rejected() {
+        for limit in [
+            "",
+            "10",
+            "m",
+            "ten minutes",
+            "10s",
+            "-5m",
+            "99999999999999999999h",
+        ] {
+            assert_eq!(
+                parse_budget_limit(limit, PromptBudget::default()),
+                None,
+                "{:?}",
+                limit
+            );
+        }
+    }
+
+    fn export(input: &str) -> (String, bool, bool) {
+        match parse_local_command(input) {
+            Some(Ok(LocalCommand::Export(ExportCommand {
+                path,
+                format,
+                notes,
+            }))) => {
+                assert_ne!(format, ExportFormat::Json, "{:?}", input);
+                (path, format == ExportFormat::Plain, notes)
+            }
+            _ => panic!("{:?} should parse as an export", input),
+        }
+    }
+
+    #[test]
+    fn export_flags_come_before_the_path() {
+        assert_eq!(
+            export("/export out.md"),
+            ("out.md".to_owned(), false, false)
+        );
+        assert_eq!(
+            export("/export --plain out.txt"),
+            ("out.txt".to_owned(), true, false)
+        );
+        assert_eq!(
+            export("/export --notes --plain \"my notes.txt\""),
+            ("my notes.txt".to_owned(), true, true)
+        );
+        assert_eq!(
+            export("/export --draft.md"),
+            ("--draft.md".to_owned(), false, false)
+        );
+        assert_eq!(
+            error("/export --plain"),
+            "Usage: /export [--plain | --json] [--notes] <path>"
+        );
+        assert_eq!(
+            error("/export --plain out.txt extra"),
+            "Usage: /export [--plain | --json] [--notes] <path> (quote arguments that contain spaces)"
+        );
+        assert!(matches!(
+            parse_local_command("/cd --plain repo"),
+            Some(Err(CommandError::TooManyArguments(_)))
+        ));
+    }
+
+    #[test]
+    fn json_export_is_its_own_format() {
+        for (input, path, notes) in [
+            ("/export --json out.json", "out.json", false),
+            ("/export --notes --json out.json", "out.json", true),
+            ("/export --json --json out.json", "out.json", false),
+        ] {
+            match parse_local_command(input) {
+                Some(Ok(LocalCommand::Export(export))) => {
+                    assert_eq!(export.format, ExportFormat::Json, "{}", input);
+                    assert_eq!((export.path.as_str(), export.notes), (path, notes));
+                }
+                _ => panic!("{:?} should parse as an export", input),
+            }
+        }
+        assert_eq!(
+            error("/export --plain --json out"),
+            "Usage: /export [--plain | --json] [--notes] <path>"
+        );
+    }
+}
@@ -0,0 +1,324 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::config::project::ProjectConfig;
+use crate::events::PromptResult;
+use crate::logging;
+use crate::notify;
+use crate::prompt::{PromptChannels, PromptRequest, PromptStreamState, RunningPrompts};
+use crate::usage::{TokenUsage, format_tokens};
+
+use super::CodexAgentApp;
+use super::events::{spawn_prompt_worker, with_prompt_prefix};
+
+pub(super) struct Comparison {
+    runs: Vec<ComparisonRun>,
+    started_at: Instant,
+}
+
+struct ComparisonRun {
+    model: String,
+    prompt_id: u64,
+    running_prompt: Arc<Mutex<RunningPrompts>>,
+    shared_stream: Arc<Mutex<PromptStreamState>>,
+    stream_notification_pending: Arc<AtomicBool>,
+    text: String,
+    outcome: Option<RunOutcome>,
+}
+
+struct RunOutcome {
+    elapsed: Duration,
+    usage: Option<TokenUsage>,
+    error: Option<String>,
+}
+
+impl Comparison {
+    fn run_mut(&mut self, prompt_id: u64) -> Option<&mut ComparisonRun> {
+        self.runs.iter_mut().find(|run| run.prompt_id == prompt_id)
+    }
+
+    fn is_complete(&self) -> bool {
+        self.runs.iter().all(|run| run.outcome.is_some())
+    }
+
+    fn render(&self, with_summary: bool) -> String {
+        let mut text = String::new();
+        for run in &self.runs {
+            if !text.is_empty() {
+                text.push('\n');
+            }
+            text.push_str(&format!("\x1C\u{25B8} {}\n", run.model));
+            text.push_str(run.text.trim_end_matches('\n'));
+            if !run.text.is_empty() {
+                text.push('\n');
+            }
+            if let Some(error) = run
+                .outcome
+                .as_ref()
+                .and_then(|outcome| outcome.error.as_ref())
+            {
+                for line in error.lines() {
+                    text.push('\x1D');
+                    text.push_str(line);
+                    text.push('\n');
+                }
+            }
+        }
+        if with_summary {
+            text.push('\n');
+            for run in &self.runs {
+                text.push('\x1C');
+                text.push_str(&run.summary());
+                text.push('\n');
+            }
+        }
+        text
+    }
+}
+
+impl ComparisonRun {
+    fn summary(&self) -> String {
+        let Some(outcome) = self.outcome.as_ref() else {
+            return format!("{}: cancelled", self.model);
+        };
+        let mut summary = format!("{}: {:.1}s", self.model, outcome.elapsed.as_secs_f64());
+        if let Some(usage) = outcome.usage {
+            summary.push_str(&format!(
+                " \u{b7} {} in / {} out",
+                format_tokens(usage.input_tokens),
+                format_tokens(usage.output_tokens)
+            ));
+        }
+        if outcome.error.is_some() {
+            summary.push_str(" \u{b7} failed");
+        }
+        summary
+    }
+}
+
+impl CodexAgentApp {
+    pub(super) fn comparison_models(&self) -> Option<[String; 2]> {
+        let other = self
+            .compare_model
+            .as_ref()
+            .filter(|model| **model != self.current_model)?;
+        Some([self.current_model.clone(), other.clone()])
+    }
+
+    pub(super) fn select_compare_model(&mut self, model: Option<String>) {
+        self.clear_picker_selection();
+        if self.compare_model == model {
+            return;
+        }
+        let message = match model.as_deref() {
+            Some(model) => format!("Compare set to {} vs {}", self.current_model, model),
+            None => "Compare set to Off".to_owned(),
+        };
+        self.compare_model = model;
+        self.push_settings_output(&message);
+        self.finish_local_success();
+        self.finish_local_change();
+    }
+
+    pub(super) fn submit_comparison(
+        &mut self,
+        models: [String; 2],
+        prompt: &str,
+        notes: &[String],
+        outgoing: String,
+        project: Option<ProjectConfig>,
+    ) {
+        logging::trace(format!(
+            "comparing {} and {} for one prompt",
+            models[0], models[1]
+        ));
+        let Some(first_id) = self.begin_prompt_request() else {
+            return;
+        };
+        self.active_prompt_id = None;
+        self.transcript.push_prompt(prompt, notes);
+        self.mark_output_for_rebuild();
+        self.persist_history();
+        self.refresh_after_text_change();
+        self.start_event_log(first_id);
+        let request_prompt =
+            self.with_hidden_suffixes(with_prompt_prefix(project.as_ref(), outgoing));
+        let mut runs = Vec::with_capacity(models.len());
+        for (index, model) in models.into_iter().enumerate() {
+            let prompt_id = if index == 0 {
+                first_id
+            } else {
+                let prompt_id = self.next_prompt_id;
+                self.next_prompt_id += 1;
+                prompt_id
+            };
+            let run = ComparisonRun {
+                model: model.clone(),
+                prompt_id,
+                running_prompt: Arc::new(Mutex::new(RunningPrompts::default())),
+                shared_stream: Arc::new(Mutex::new(PromptStreamState::default())),
+                stream_notification_pending: Arc::new(AtomicBool::new(false)),
+                text: String::new(),
+                outcome: None,
+            };
+            run.shared_stream
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .start(prompt_id);
+            let mut run_project = project.clone().unwrap_or_default();
+            run_project.model = Some(model);
+            let request = PromptRequest {
+                id: prompt_id,
+                prompt: request_prompt.clone(),
+                session_id: None,
+                project: Some(run_project),
+                approved: None,
+            };
+            let channels = PromptChannels {
+                running_prompt: Arc::clone(&run.running_prompt),
+                shared_stream: Arc::clone(&run.shared_stream),
+                stream_notification_pending: Arc::clone(&run.stream_notification_pending),
+                event_log: Arc::clone(&self.event_log),
+                tx: self.tx.clone(),
+                repaint: self.repaint_handle(),
+            };
+            spawn_prompt_worker(Arc::clone(&self.backend), request, channels);
+            runs.push(run);
+        }
+        self.comparison = Some(Comparison {
+            runs,
+            started_at: Instant::now(),
+        });
+        self.render_comparison(false);
+    }
+
+    pub(super) fn owns_comparison_prompt(&self, prompt_id: u64) -> bool {
+        self.comparison
+            .as_ref()
+            .is_some_and(|comparison| comparison.runs.iter().any(|run| run.prompt_id == prompt_id))
+    }
+
+    pub(super) fn apply_comparison_stream(&mut self, prompt_id: u64) {
+        let Some(run) = self
+            .comparison
+            .as_mut()
+            .and_then(|comparison| comparison.run_mut(prompt_id))
+        else {
+            return;
+        };
+        run.stream_notification_pending
+            .store(false, Ordering::Relaxed);
+        {
+            let stream = run.shared_stream.lock().unwrap_or_else(|e| e.into_inner());
+            if stream.prompt_id != Some(prompt_id) || stream.text == run.text {
+                return;
+            }
+            run.text.clone_from(&stream.text);
+        }
+        self.render_comparison(false);
+    }
+
+    pub(super) fn finish_comparison_run(&mut self, prompt_id: u64, result: PromptResult) {
+        let Some(comparison) = self.comparison.as_mut() else {
+            return;
+        };
+        let elapsed = comparison.started_at.elapsed();
+        let Some(run) = comparison.run_mut(prompt_id) else {
+            return;
+        };
+        let outcome = match result {
+            PromptResult::Ok(output) => {
+                run.text = output.response;
+                RunOutcome {
+                    elapsed,
+                    usage: output.usage,
+                    error: None,
+                }
+            }
+            PromptResult::Err(_, error) => {
+                logging::error(format!("comparison run on {} failed: {}", run.model, error));
+                RunOutcome {
+                    elapsed,
+                    usage: None,
+                    error: Some(error),
+                }
+            }
+        };
+        logging::trace(format!(
+            "comparison run on {} finished after {} ms",
+            run.model,
+            elapsed.as_millis()
+        ));
+        run.outcome = Some(outcome);
+        run.shared_stream
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .clear(prompt_id);
+        if !comparison.is_complete() {
+            self.render_comparison(false);
+            return;
+        }
+        self.render_comparison(true);
+        self.finish_comparison();
+        if self.notifications_enabled {
+            notify::prompt_completed(self.hwnd);
+        }
+    }
+
+    pub(super) fn cancel_comparison(&mut self) -> bool {
+        let Some(comparison) = self.comparison.as_ref() else {
+            return false;
+        };
+        logging::trace("cancelling comparison runs");
+        for run in &comparison.runs {
+            run.shared_stream
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .mark_cancelled(run.prompt_id);
+            let running_prompt = run
+                .running_prompt
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .take(run.prompt_id);
+            let Some(running_prompt) = running_prompt else {
+                continue;
+            };
+            let pid = running_prompt.pid;
+            let backend = Arc::clone(&self.backend);
+            thread::spawn(move || {
+                let _ = logging::catch_panic("comparison cancel thread", || {
+                    if let Err(error) = backend.cancel(pid) {
+                        logging::error(format!("failed to cancel prompt pid {}: {}", pid, error));
+                    }
+                });
+            });
+        }
+        self.render_comparison(false);
+        self.finish_comparison();
+        self.transcript.push_cancelled();
+        true
+    }
+
+    fn render_comparison(&mut self, with_summary: bool) {
+        let Some(comparison) = self.comparison.as_ref() else {
+            return;
+        };
+        let text = comparison.render(with_summary);
+        self.transcript.replace_response(&text);
+        self.output_display_can_append = false;
+        self.refresh_after_output_change();
+    }
+
+    fn finish_comparison(&mut self) {
+        self.comparison = None;
+        self.busy = false;
+        self.locked = false;
+        self.pending_started_at = None;
+        self.pending_input_focus = true;
+        self.mark_output_for_rebuild();
+        self.persist_history();
+        self.refresh_after_output_change();
+    }
+}
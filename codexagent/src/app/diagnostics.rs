@@ -0,0 +1,227 @@
+use std::collections::HashMap;
+use std::thread;
+
+use crate::config::{APP_DISPLAY_NAME, build_stamp, paths, read_config};
+use crate::events::AppEvent;
+use crate::logging;
+use crate::prompt::{codex_version, describe_codex_launcher};
+use crate::redact;
+use crate::runtime::os_build;
+
+use super::CodexAgentApp;
+
+const MAX_DIAGNOSTICS_BYTES: usize = 24 * 1024;
+const SENSITIVE_SETTING_WORDS: [&str; 6] = ["key", "token", "secret", "password", "hook", "env"];
+const REDACTED_SETTING: &str = "<redacted>";
+const TRUNCATED_NOTE: &str = "\n\u{2026}diagnostics truncated\u{2026}\n";
+
+fn settings_summary(settings: &HashMap<String, String>) -> String {
+    let mut keys = settings.keys().collect::<Vec<_>>();
+    keys.sort();
+    keys.into_iter()
+        .map(|key| {
+            let lower = key.to_ascii_lowercase();
+            if SENSITIVE_SETTING_WORDS
+                .iter()
+                .any(|word| lower.contains(word))
+            {
+                format!("{} = {}", key, REDACTED_SETTING)
+            } else {
+                format!("{} = {}", key, settings[key])
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn diagnostics_report(sections: &[(&str, String)]) -> String {
+    let mut report = String::new();
+    for (title, body) in sections {
+        report.push_str(&format!("## {}\n{}\n\n", title, body.trim_end()));
+    }
+    bounded(
+        redact::redact_outgoing(report.trim_end()),
+        MAX_DIAGNOSTICS_BYTES,
+    )
+}
+
+fn bounded(mut text: String, limit: usize) -> String {
+    if text.len() <= limit {
+        return text;
+    }
+    let mut end = limit.saturating_sub(TRUNCATED_NOTE.len());
+    while !text.is_char_boundary(end) {
+        end -= 1;
+    }
+    text.truncate(end);
+    text.push_str(TRUNCATED_NOTE);
+    text
+}
+
+impl CodexAgentApp {
+    fn display_summary(&self) -> String {
+        let (monitor, native_scale, screen) = self.ctx.input(|input| {
+            (
+                input.viewport().monitor_size,
+                input.viewport().native_pixels_per_point,
+                input.screen_rect(),
+            )
+        });
+        format!(
+            "monitor: {}\nnative scale: {}\nui scale: {:.2}\nwindow: {:.0}x{:.0} points",
+            monitor.map_or_else(
+                || "unknown".to_owned(),
+                |size| format!("{:.0}x{:.0} points", size.x, size.y)
+            ),
+            native_scale.map_or_else(|| "unknown".to_owned(), |scale| format!("{:.2}", scale)),
+            self.ctx.pixels_per_point(),
+            screen.width(),
+            screen.height()
+        )
+    }
+
+    pub(super) fn copy_diagnostics(&mut self) {
+        self.clear_picker_selection();
+        if self.diagnostics_pending {
+            return;
+        }
+        self.diagnostics_pending = true;
+        logging::trace("collecting diagnostics");
+        let settings = match read_config() {
+            Ok(settings) => settings_summary(&settings),
+            Err(error) => format!("unavailable: {}", error),
+        };
+        let errors = logging::recent_errors();
+        let errors = if errors.is_empty() {
+            "none".to_owned()
+        } else {
+            errors.join("\n")
+        };
+        let display = self.display_summary();
+        let last_prompt = self
+            .last_turn_summary()
+            .unwrap_or_else(|| "none this session".to_owned());
+        let log_health = if self.log_health.file_logging {
+            "on".to_owned()
+        } else {
+            format!(
+                "off: {}",
+                self.log_health
+                    .last_error
+                    .as_deref()
+                    .unwrap_or("unknown error")
+            )
+        };
+        let disk_space = self.disk_space.summary(self.disk_space_warning_mb);
+        let tx = self.tx.clone();
+        let ctx = self.ctx.clone();
+        thread::spawn(move || {
+            let report = logging::catch_panic("diagnostics thread", || {
+                let codex = format!(
+                    "launcher: {}\nversion: {}",
+                    describe_codex_launcher(),
+                    codex_version().unwrap_or_else(|| "unknown".to_owned())
+                );
+                diagnostics_report(&[
+                    ("App", format!("{} {}", APP_DISPLAY_NAME, build_stamp())),
+                    ("Codex", codex),
+                    ("OS", os_build()),
+                    ("Display", display),
+                    ("Settings", settings),
+                    ("Last prompt", last_prompt),
+                    (
+                        "Logging",
+                        format!(
+                            "file logging: {}\nfolder: {}",
+                            log_health,
+                            paths::get().log_dir.display()
+                        ),
+                    ),
+                    ("Disk space", disk_space),
+                    ("Recent errors", errors),
+                ])
+            });
+            if tx.send(AppEvent::Diagnostics(report)).is_err() {
+                logging::error("failed to deliver diagnostics to app");
+            }
+            ctx.request_repaint();
+        });
+    }
+
+    pub(super) fn apply_diagnostics(&mut self, report: Result<String, String>) {
+        self.diagnostics_pending = false;
+        match report {
+            Ok(report) => {
+                let lines = report.lines().count();
+                self.ctx.copy_text(report);
+                self.push_settings_output(&format!(
+                    "Diagnostics copied to the clipboard ({} lines)",
+                    lines
+                ));
+                self.finish_local_success();
+            }
+            Err(error) => {
+                self.push_local_error(&format!("Failed to collect diagnostics: {}", error))
+            }
+        }
+        self.finish_local_change();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sensitive_settings_are_redacted() {
+        let settings = HashMap::from([
+            ("model".to_owned(), "o3".to_owned()),
+            ("api_key".to_owned(), "sk-live".to_owned()),
+            ("on_complete_hook".to_owned(), "make deploy".to_owned()),
+            ("Codex_Env".to_owned(), "TOKEN=abc".to_owned()),
+            ("auto_continue".to_owned(), "true".to_owned()),
+        ]);
+        assert_eq!(
+            settings_summary(&settings),
+            "Codex_Env = <redacted>\napi_key = <redacted>\nauto_continue = true\n\
+             model = o3\non_complete_hook = <redacted>"
+        );
+    }
+
+    #[test]
+    fn secrets_in_any_section_are_redacted() {
+        let report = diagnostics_report(&[
+            ("App", "codexagent 1.2.3\n".to_owned()),
+            (
+                "Recent errors",
+                "codex failed: OPENAI_API_KEY sk-proj-abcdefghijklmnopqrstuv".to_owned(),
+            ),
+        ]);
+        assert_eq!(
+            report,
+            "## App\ncodexagent 1.2.3\n\n## Recent errors\ncodex failed: OPENAI_API_KEY sk-[REDACTED]"
+        );
+    }
+
+    #[test]
+    fn reports_are_capped() {
+        let errors = (0..2000)
+            .map(|line| format!("error line {} \u{2014} ünïcode", line))
+            .collect::<Vec<_>>()
+            .join("\n");
+        let report = diagnostics_report(&[("Recent errors", errors)]);
+        assert!(report.len() <= MAX_DIAGNOSTICS_BYTES);
+        assert!(report.starts_with("## Recent errors\nerror line 0 "));
+        assert!(report.ends_with(TRUNCATED_NOTE));
+    }
+
+    #[test]
+    fn truncation_keeps_char_boundaries() {
+        let text = "é".repeat(100);
+        let limit = TRUNCATED_NOTE.len() + 5;
+        let capped = bounded(text, limit);
+        assert!(capped.len() <= limit);
+        assert_eq!(capped, format!("éé{}", TRUNCATED_NOTE));
+        assert_eq!(bounded("short".to_owned(), limit), "short");
+    }
+}
@@ -0,0 +1,177 @@
+use crate::config::{CANCEL_BUTTON_HEIGHT, apply_disk_space_low};
+use crate::logging;
+use crate::runtime::ProbeKey;
+
+use super::CodexAgentApp;
+use super::layout::PROMPT_PREFIX_CHIP_SPACING;
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub(super) enum DiskSpaceChange {
+    Unchanged,
+    Low,
+    Recovered,
+}
+
+#[derive(Default)]
+pub(super) struct DiskSpaceWarning {
+    free_mb: Option<u64>,
+    low: bool,
+    dismissed: bool,
+}
+
+impl DiskSpaceWarning {
+    pub(super) fn update(&mut self, free_mb: Option<u64>, threshold_mb: u64) -> DiskSpaceChange {
+        let Some(free) = free_mb else {
+            return DiskSpaceChange::Unchanged;
+        };
+        self.free_mb = Some(free);
+        let low = free < threshold_mb;
+        if low == self.low {
+            return DiskSpaceChange::Unchanged;
+        }
+        self.low = low;
+        self.dismissed = false;
+        if low {
+            DiskSpaceChange::Low
+        } else {
+            DiskSpaceChange::Recovered
+        }
+    }
+
+    pub(super) fn dismiss(&mut self) {
+        self.dismissed = true;
+    }
+
+    pub(super) fn visible(&self) -> bool {
+        self.low && !self.dismissed
+    }
+
+    pub(super) fn free_mb(&self) -> Option<u64> {
+        self.free_mb
+    }
+
+    pub(super) fn summary(&self, threshold_mb: u64) -> String {
+        let free = self
+            .free_mb
+            .map_or_else(|| "unknown".to_owned(), |free| format!("{} MB", free));
+        let status = if self.low {
+            "low; recordings and snapshots paused"
+        } else {
+            "ok"
+        };
+        format!(
+            "free: {}\nwarning below: {} MB\nstatus: {}",
+            free, threshold_mb, status
+        )
+    }
+}
+
+impl CodexAgentApp {
+    pub(super) fn request_disk_space_probe(&mut self) {
+        self.probes.request(&ProbeKey::disk_space());
+    }
+
+    pub(super) fn apply_disk_space(&mut self, free_mb: Option<u64>) {
+        match self.disk_space.update(free_mb, self.disk_space_warning_mb) {
+            DiskSpaceChange::Unchanged => return,
+            DiskSpaceChange::Low => {
+                logging::error(format!(
+                    "disk space is low: {} MB free, below {} MB; pausing recordings and snapshots",
+                    free_mb.unwrap_or_default(),
+                    self.disk_space_warning_mb
+                ));
+                apply_disk_space_low(true);
+            }
+            DiskSpaceChange::Recovered => {
+                logging::trace(format!(
+                    "disk space recovered: {} MB free; resuming recordings and snapshots",
+                    free_mb.unwrap_or_default()
+                ));
+                apply_disk_space_low(false);
+            }
+        }
+        self.resize_for_text();
+    }
+
+    pub(super) fn dismiss_disk_space_warning(&mut self) {
+        logging::trace("low disk space warning dismissed");
+        self.disk_space.dismiss();
+        self.resize_for_text();
+    }
+
+    pub(super) fn disk_space_warning_height(&self) -> f32 {
+        if !self.disk_space.visible() {
+            return 0.0;
+        }
+        CANCEL_BUTTON_HEIGHT + PROMPT_PREFIX_CHIP_SPACING
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const THRESHOLD_MB: u64 = 200;
+
+    #[test]
+    fn crossing_the_threshold_reports_each_transition_once() {
+        use DiskSpaceChange::*;
+        let mut warning = DiskSpaceWarning::default();
+        // free MB -> change, visible
+        let steps = [
+            (Some(5_000), Unchanged, false),
+            (Some(200), Unchanged, false),
+            (Some(199), Low, true),
+            (Some(50), Unchanged, true),
+            (None, Unchanged, true),
+            (Some(200), Recovered, false),
+            (Some(10_000), Unchanged, false),
+            (Some(0), Low, true),
+        ];
+        for (free_mb, change, visible) in steps {
+            assert_eq!(
+                warning.update(free_mb, THRESHOLD_MB),
+                change,
+                "{:?}",
+                free_mb
+            );
+            assert_eq!(warning.visible(), visible, "{:?}", free_mb);
+        }
+        assert_eq!(warning.free_mb(), Some(0));
+    }
+
+    #[test]
+    fn a_dismissed_warning_returns_only_after_recovery() {
+        let mut warning = DiskSpaceWarning::default();
+        warning.update(Some(100), THRESHOLD_MB);
+        warning.dismiss();
+        assert!(!warning.visible());
+        assert_eq!(
+            warning.update(Some(90), THRESHOLD_MB),
+            DiskSpaceChange::Unchanged
+        );
+        assert!(!warning.visible());
+        warning.update(Some(500), THRESHOLD_MB);
+        warning.update(Some(100), THRESHOLD_MB);
+        assert!(warning.visible());
+    }
+
+    #[test]
+    fn the_summary_reports_unknown_and_low_space() {
+        let mut warning = DiskSpaceWarning::default();
+        assert_eq!(
+            warning.update(None, THRESHOLD_MB),
+            DiskSpaceChange::Unchanged
+        );
+        assert!(!warning.visible());
+        assert_eq!(
+            warning.summary(THRESHOLD_MB),
+            "free: unknown\nwarning below: 200 MB\nstatus: ok"
+        );
+        warning.update(Some(42), THRESHOLD_MB);
+        assert_eq!(
+            warning.summary(THRESHOLD_MB),
+            "free: 42 MB\nwarning below: 200 MB\nstatus: low; recordings and snapshots paused"
+        );
+    }
+}
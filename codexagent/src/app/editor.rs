@@ -0,0 +1,149 @@
+use std::path::PathBuf;
+
+use crate::logging;
+use crate::runtime::{
+    CodexFile, CodexFileUpdate, apply_codex_file_update, ignore_codex_file_update, load_codex_file,
+    load_project_file, save_codex_file, save_project_file,
+};
+
+use super::{CodexAgentApp, CodexFileEditor, SetupState};
+
+impl CodexAgentApp {
+    pub(super) fn codex_file_editor_open(&self) -> bool {
+        self.codex_file_editor.is_some()
+    }
+
+    pub(super) fn open_codex_file_editor(&mut self, file: CodexFile) {
+        self.clear_picker_selection();
+        if self.busy || self.setup_state != SetupState::Ready {
+            return;
+        }
+        match load_codex_file(file) {
+            Ok((path, text)) => self.show_file_editor(Some(file), path, text),
+            Err(error) => {
+                self.push_local_error(&format!("Failed to open {}: {}", file.file_name(), error));
+                self.finish_local_change();
+            }
+        }
+    }
+
+    pub(super) fn open_project_file_editor(&mut self, path: PathBuf) {
+        self.clear_picker_selection();
+        if self.busy || self.setup_state != SetupState::Ready {
+            return;
+        }
+        match load_project_file(&path) {
+            Ok(text) => self.show_file_editor(None, path, text),
+            Err(error) => {
+                self.push_local_error(&format!("Failed to open {}: {}", path.display(), error));
+                self.finish_local_change();
+            }
+        }
+    }
+
+    fn show_file_editor(&mut self, file: Option<CodexFile>, path: PathBuf, text: String) {
+        logging::trace(format!("opened file editor for {}", path.display()));
+        self.codex_file_editor = Some(CodexFileEditor {
+            file,
+            path,
+            text,
+            error: None,
+        });
+        self.release_input_focus();
+        self.invalidate_text_layout();
+        self.resize_for_text();
+    }
+
+    pub(super) fn save_codex_file_editor(&mut self) {
+        let Some(editor) = self.codex_file_editor.as_mut() else {
+            return;
+        };
+        let result = match editor.file {
+            Some(file) => save_codex_file(file, &editor.text).map(|_| ()),
+            None => save_project_file(&editor.path, &editor.text),
+        };
+        match result {
+            Ok(()) => {
+                let message = format!("Saved {}", editor.path.display());
+                let project_file = editor.file.is_none();
+                self.codex_file_editor = None;
+                if project_file {
+                    self.invalidate_agents_files();
+                }
+                self.push_settings_output(&message);
+                self.finish_local_change();
+            }
+            Err(error) => {
+                logging::error(format!(
+                    "failed to save {}: {}",
+                    editor.path.display(),
+                    error
+                ));
+                editor.error = Some(error.to_string());
+            }
+        }
+    }
+
+    pub(super) fn close_codex_file_editor(&mut self) {
+        if self.codex_file_editor.take().is_none() {
+            return;
+        }
+        self.pending_input_focus = true;
+        self.invalidate_text_layout();
+        self.resize_for_text();
+    }
+
+    pub(super) fn toggle_codex_file_update_review(&mut self) {
+        self.codex_file_update_review = !self.codex_file_update_review;
+        self.invalidate_text_layout();
+        self.resize_for_text();
+    }
+
+    pub(super) fn apply_pending_codex_file_update(&mut self) {
+        let Some(update) = self.take_codex_file_update() else {
+            return;
+        };
+        let name = update.file.file_name();
+        match apply_codex_file_update(update.file) {
+            Ok(_) => self.push_settings_output(&format!(
+                "Updated {} to the current defaults; previous copy saved as {}.bak",
+                name, name
+            )),
+            Err(error) => self.push_local_error(&format!("Failed to update {}: {}", name, error)),
+        }
+        self.finish_local_change();
+    }
+
+    pub(super) fn ignore_pending_codex_file_update(&mut self, permanently: bool) {
+        let Some(update) = self.take_codex_file_update() else {
+            return;
+        };
+        if !permanently {
+            logging::trace(format!(
+                "ignoring {} defaults update for this session",
+                update.file.file_name()
+            ));
+            self.resize_for_text();
+            return;
+        }
+        if let Err(error) = ignore_codex_file_update(update.file) {
+            self.push_local_error(&format!(
+                "Failed to ignore {} update: {}",
+                update.file.file_name(),
+                error
+            ));
+            self.finish_local_change();
+            return;
+        }
+        self.resize_for_text();
+    }
+
+    fn take_codex_file_update(&mut self) -> Option<CodexFileUpdate> {
+        if self.codex_file_updates.is_empty() {
+            return None;
+        }
+        self.codex_file_update_review = false;
+        self.invalidate_text_layout();
+        Some(self.codex_file_updates.remove(0))
+    }
+}
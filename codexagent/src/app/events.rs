@@ -1,155 +1,1163 @@
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use std::sync::atomic::Ordering;
 use std::thread;
-use std::time::Instant;
+use std::time::{Duration, Instant};
 
 use eframe::egui;
 
-use crate::config::set_notifications_enabled;
-use crate::events::{AppEvent, CodexCheckResult, PromptResult};
+use crate::config::project::ProjectConfig;
+use crate::config::{
+    LauncherMode, PERMISSIVE_SANDBOX_MODE, PendingStyle, PromptWhitespace, ResponseDimming,
+    SubmitKey, Theme, WakeMode, load_model_prices, set_auto_continue, set_compact_mode,
+    set_dirty_tree_warning_enabled, set_exclude_from_capture, set_launcher_mode,
+    set_notifications_enabled, set_output_processor_enabled, set_pending_style,
+    set_persist_transcripts, set_project_config_enabled, set_prompt_transformer_enabled,
+    set_prompt_whitespace, set_redact_secrets, set_response_dimming, set_submit_key,
+    set_suggestions_enabled, set_theme, set_token_warning_limit, set_wake_mode,
+    set_window_shadow_enabled,
+};
+use crate::diff::marked_response_diff;
+use crate::events::{AppEvent, CodexCheckResult, ErrorKind, PromptResult, RepaintHandle};
+use crate::i18n::{Text, tr};
 use crate::logging;
 use crate::notify;
+use crate::postprocess::OutputProcessor;
 use crate::prompt::{
-    append_cancelled_text, check_codex_availability, has_node, kill_prompt_process, prompt_codex,
-    run_full_install,
+    ApprovalRequest, PromptBackend, PromptChannels, PromptOutput, PromptPhase, PromptRequest,
+    RateLimitBackoff, check_codex_availability, classify_failure, failure_kind, has_node,
+    looks_truncated, redetect_codex, retry_after, run_full_install, sanitize_external_text,
 };
+use crate::redact;
 use crate::runtime::{
-    ContextMenuSelection, current_context_menu_selection, ensure_codex_files, install_context_menu,
-    remove_context_menu, set_model,
+    ContextMenuSelection, GitInfo, ProbeKey, ProbeValue, current_context_menu_selection,
+    current_model, current_sandbox_mode, install_context_menu, read_clipboard_text,
+    remove_context_menu, set_capture_excluded, set_model,
 };
 use crate::status::current_usage_text;
+use crate::transform::{TransformContext, Transformer, transform_prompt};
+use crate::usage::{PriceTable, TokenUsage, format_tokens};
+
+use super::cancel::PromptState;
+use super::render::{FoldKind, normalize_prompt};
+use super::transcript::SegmentKind;
+use super::{
+    CodexAgentApp, ContextMenuState, RateLimitWait, ResponseDiff, SecretSubmitDecision, SetupState,
+};
+
+const CONTINUE_PROMPT: &str = "continue";
+const MAX_AUTO_CONTINUATIONS: usize = 2;
+const RATE_LIMIT_TICK: Duration = Duration::from_millis(250);
+const SUGGESTIONS_INSTRUCTION: &str = "\n\nEnd your reply with a fenced code block tagged `suggestions` listing up to three short follow-up prompts I might send next, one per line.";
+
+impl CodexAgentApp {
+    pub(super) fn submit(&mut self) {
+        let state = self.prompt_state();
+        if state != PromptState::Idle {
+            logging::error(format!("ignoring submit while {:?}", state));
+            return;
+        }
+        if self.busy
+            || self.locked
+            || self.secret_submit_confirmation.is_some()
+            || !normalize_prompt(&mut self.input, self.prompt_whitespace)
+        {
+            return;
+        }
+        self.clear_picker_selection();
+        let tokens = self.input_token_estimate();
+        let prompt = sanitize_external_text(&std::mem::take(&mut self.input)).into_owned();
+        self.mark_draft_changed();
+        if self.try_run_local_command(&prompt) {
+            return;
+        }
+        if self.dry_run {
+            self.describe_prompt(&prompt);
+            self.input = prompt;
+            self.resize_for_text();
+            return;
+        }
+        if self.cwd_problem.is_some() {
+            self.input = prompt;
+            self.probes
+                .invalidate(&ProbeKey::directory(Path::new(&self.cwd_text)));
+            self.push_local_error(&format!(
+                "Working directory {} is unavailable \u{2014} choose another folder.",
+                self.cwd_text
+            ));
+            self.finish_local_change();
+            return;
+        }
+        if self.token_submit_confirmation.is_none()
+            && self.token_warning_limit > 0
+            && tokens > self.token_warning_limit
+        {
+            logging::trace(format!(
+                "asking before submit; prompt is about {} tokens",
+                tokens
+            ));
+            self.input = prompt;
+            self.token_submit_confirmation = Some(tokens);
+            self.resize_for_text();
+            return;
+        }
+        if self.secret_submit_decision.is_none()
+            && !self.secret_warning_suppressed
+            && let Some(kind) = redact::detect_secret(&prompt)
+        {
+            logging::trace(format!(
+                "asking before submit; prompt looks like it contains a {}",
+                kind
+            ));
+            self.input = prompt;
+            self.secret_submit_confirmation = Some(kind);
+            self.resize_for_text();
+            return;
+        }
+        if self.project_trust_pending() {
+            logging::trace("asking before submit; the project config needs trust");
+            self.input = prompt;
+            self.project_trust_confirmation = true;
+            self.resize_for_text();
+            return;
+        }
+        if let Some(changes) = self.dirty_submit_changes() {
+            logging::trace(format!(
+                "asking before submit; working tree has {} uncommitted changes",
+                changes
+            ));
+            self.input = prompt;
+            self.dirty_submit_confirmation = Some(changes);
+            self.invalidate_git_info();
+            self.resize_for_text();
+            return;
+        }
+        self.dirty_submit_confirmation = None;
+        self.token_submit_confirmation = None;
+        self.sleep_retry_prompt = None;
+        self.tool_retry = None;
+        self.drop_pending_approval();
+        self.expanded_folds
+            .retain(|&(kind, _)| kind != FoldKind::Response);
+        self.push_prompt_history(&prompt);
+        self.persist_draft(true);
+        self.retry_baseline = self
+            .last_response_range
+            .filter(|_| self.last_submitted_prompt.as_deref() == Some(prompt.as_str()))
+            .and_then(|(start, end)| self.transcript.text().get(start..end))
+            .map(str::to_owned);
+        self.last_response_range = None;
+        self.response_diff = None;
+        self.continuations_used = 0;
+        self.rate_limit_backoff = RateLimitBackoff::default();
+        self.last_submitted_prompt = Some(prompt.clone());
+        let redact_prompt =
+            self.secret_submit_decision.take() == Some(SecretSubmitDecision::Redact);
+        let prompt = if redact_prompt {
+            logging::trace("redacting credentials from the outgoing prompt");
+            redact::redact_outgoing(&prompt)
+        } else {
+            prompt
+        };
+        let (outgoing, mut transform_notes) = self.transform_outgoing_prompt(prompt.clone());
+        let outgoing = if redact_prompt && !transform_notes.is_empty() {
+            redact::redact_outgoing(&outgoing)
+        } else {
+            outgoing
+        };
+        let outgoing = self.attach_long_prompt(outgoing, &mut transform_notes);
+
+        self.note_session_title(&prompt);
+
+        let project = self.refresh_project_config();
+
+        logging::trace(format!(
+            "submitting prompt with {} chars",
+            outgoing.chars().count()
+        ));
+        if let Some(models) = self.comparison_models() {
+            self.submit_comparison(models, &prompt, &transform_notes, outgoing, project);
+            return;
+        }
+        let Some(prompt_id) = self.begin_prompt_request() else {
+            return;
+        };
+        self.push_prompt_output(&prompt, &transform_notes);
+        self.persist_history();
+        self.refresh_after_text_change();
+        let request_prompt = self.request_prompt(outgoing, project.as_ref());
+        self.spawn_prompt_request(prompt_id, request_prompt, project, None);
+    }
+
+    pub(super) fn continue_response(&mut self) {
+        if self.busy || self.locked || self.session_id.is_none() {
+            return;
+        }
+        let Some(prompt_id) = self.begin_prompt_request() else {
+            return;
+        };
+        self.continuations_used += 1;
+        self.rate_limit_backoff = RateLimitBackoff::default();
+        logging::trace(format!(
+            "continuing the previous response (continuation {})",
+            self.continuations_used
+        ));
+        self.continued_response_start = self.last_response_range.map(|(start, _)| start);
+        self.last_response_range = None;
+        self.response_diff = None;
+        self.retry_baseline = None;
+        let project = self.refresh_project_config();
+        self.transcript.push_continuation();
+        self.mark_output_for_rebuild();
+        self.persist_history();
+        self.refresh_after_output_change();
+        self.spawn_prompt_request(prompt_id, CONTINUE_PROMPT.to_owned(), project, None);
+    }
+
+    pub(super) fn begin_prompt_request(&mut self) -> Option<u64> {
+        let state = self.prompt_state();
+        if state != PromptState::Idle {
+            logging::error(format!("ignoring prompt start while {:?}", state));
+            return None;
+        }
+        let prompt_id = self.next_prompt_id;
+        self.next_prompt_id += 1;
+        self.busy = true;
+        self.locked = true;
+        self.active_prompt_id = Some(prompt_id);
+        self.warm_codex = None;
+        let now = Instant::now();
+        self.pending_started_at = Some(now);
+        self.stream_throughput.start(now);
+        self.prompt_phase = PromptPhase::Waiting;
+        self.resume_retry_pending = false;
+        self.follow_up_suggestions.clear();
+        self.continuation_offered = false;
+        self.ctx.output_mut(|output| {
+            output.events.push(egui::output::OutputEvent::ValueChanged(
+                egui::WidgetInfo::labeled(egui::WidgetType::Label, true, "Working\u{2026}"),
+            ))
+        });
+        Some(prompt_id)
+    }
+
+    pub(super) fn spawn_prompt_request(
+        &mut self,
+        prompt_id: u64,
+        request_prompt: String,
+        project: Option<ProjectConfig>,
+        approved: Option<ApprovalRequest>,
+    ) {
+        self.refresh_codex_profiles();
+        self.last_request = Some((request_prompt.clone(), project.clone(), approved.clone()));
+        self.stream_notification_pending
+            .store(false, Ordering::Relaxed);
+        {
+            let mut stream = self.shared_stream.lock().unwrap_or_else(|e| e.into_inner());
+            stream.start(prompt_id);
+            self.stream_generation = stream.generation;
+            self.stream_visible_len = 0;
+        }
+        self.start_event_log(prompt_id);
+
+        let request = PromptRequest {
+            id: prompt_id,
+            prompt: request_prompt,
+            session_id: self.session_id.clone(),
+            project,
+            approved,
+        };
+        let channels = PromptChannels {
+            running_prompt: Arc::clone(&self.running_prompt),
+            shared_stream: Arc::clone(&self.shared_stream),
+            stream_notification_pending: Arc::clone(&self.stream_notification_pending),
+            event_log: Arc::clone(&self.event_log),
+            tx: self.tx.clone(),
+            repaint: self.repaint_handle(),
+        };
+        spawn_prompt_worker(Arc::clone(&self.backend), request, channels);
+    }
+
+    fn hold_for_rate_limit(&mut self, prompt_id: u64, kind: ErrorKind, error: &str) -> bool {
+        if kind != ErrorKind::RateLimited || self.stream_visible_len != 0 {
+            return false;
+        }
+        // An approved command has already run by the time codex is rate limited;
+        // resubmitting would run it a second time.
+        if matches!(&self.last_request, Some((_, _, Some(_)))) {
+            return false;
+        }
+        let hint = retry_after(error);
+        let Some(wait) = self.rate_limit_backoff.next_wait(hint) else {
+            logging::error(format!(
+                "rate limited after {} waits; giving up",
+                self.rate_limit_backoff.waits()
+            ));
+            return false;
+        };
+        let Some((prompt, project, approved)) = self.last_request.take() else {
+            return false;
+        };
+        logging::error(format!(
+            "rate limited; waiting {} ms before retry {} (suggested wait: {})",
+            wait.as_millis(),
+            self.rate_limit_backoff.waits(),
+            hint.map_or_else(
+                || "none".to_owned(),
+                |hint| format!("{} ms", hint.as_millis())
+            )
+        ));
+        let continued_start = self.continued_response_start;
+        self.finish_prompt(prompt_id);
+        self.continued_response_start = continued_start;
+        let now = Instant::now();
+        self.pending_started_at = Some(now);
+        self.rate_limit_wait = Some(RateLimitWait {
+            until: now + wait,
+            prompt,
+            project,
+            approved,
+        });
+        self.tick_rate_limit_wait();
+        true
+    }
+
+    fn tick_rate_limit_wait(&mut self) {
+        let Some(wait) = self.rate_limit_wait.as_ref() else {
+            return;
+        };
+        if Instant::now() < wait.until {
+            self.ctx.request_repaint_after(RATE_LIMIT_TICK);
+            return;
+        }
+        let Some(wait) = self.rate_limit_wait.take() else {
+            return;
+        };
+        logging::trace("rate limit wait elapsed; resubmitting prompt");
+        let Some(prompt_id) = self.begin_prompt_request() else {
+            return;
+        };
+        self.spawn_prompt_request(prompt_id, wait.prompt, wait.project, wait.approved);
+    }
+
+    fn cancel_rate_limit_wait(&mut self) -> bool {
+        if self.rate_limit_wait.take().is_none() {
+            return false;
+        }
+        logging::trace("cancelled rate limit wait");
+        self.busy = false;
+        self.locked = false;
+        self.pending_started_at = None;
+        self.pending_input_focus = true;
+        self.continued_response_start = None;
+        self.transcript.push_cancelled();
+        self.mark_output_for_rebuild();
+        self.persist_history();
+        self.refresh_after_output_change();
+        true
+    }
+
+    pub(super) fn repaint_handle(&self) -> RepaintHandle {
+        let ctx = self.ctx.clone();
+        RepaintHandle::new(move || ctx.request_repaint())
+    }
+
+    pub(super) fn request_prompt(&self, prompt: String, project: Option<&ProjectConfig>) -> String {
+        let prompt = self.with_hidden_suffixes(with_prompt_prefix(project, prompt));
+        let mut request_prompt = self.build_request_prompt(prompt);
+        if self.suggestions_enabled {
+            request_prompt.push_str(SUGGESTIONS_INSTRUCTION);
+        }
+        request_prompt
+    }
+
+    pub(super) fn transform_outgoing_prompt(&self, prompt: String) -> (String, Vec<String>) {
+        if self.prompt_transformers.is_empty() {
+            return (prompt, Vec::new());
+        }
+        let cwd = PathBuf::from(&self.cwd_text);
+        let context = TransformContext {
+            cwd: &cwd,
+            clipboard: &read_clipboard_text,
+        };
+        let (prompt, notes) = transform_prompt(prompt, &self.prompt_transformers, &context);
+        for note in &notes {
+            logging::trace(format!("prompt transform: {}", note));
+        }
+        (prompt, notes)
+    }
+
+    pub(super) fn select_prompt_transformer(&mut self, transformer: Transformer, enabled: bool) {
+        self.clear_picker_selection();
+        if self.prompt_transformers.contains(&transformer) == enabled {
+            return;
+        }
+        match set_prompt_transformer_enabled(transformer, enabled) {
+            Ok(enabled) => {
+                self.prompt_transformers
+                    .retain(|&active| active != transformer);
+                if enabled {
+                    self.prompt_transformers.push(transformer);
+                }
+                self.push_settings_output(&format!(
+                    "Prompt transform {} set to {}",
+                    transformer.name(),
+                    if enabled { "On" } else { "Off" }
+                ));
+                self.finish_local_success();
+            }
+            Err(error) => {
+                logging::error(format!(
+                    "failed to set prompt transform {} {}: {}",
+                    transformer.name(),
+                    if enabled { "on" } else { "off" },
+                    error
+                ));
+                self.push_local_error(&format!("Failed to set prompt transform: {}", error));
+            }
+        }
+        self.finish_local_change();
+    }
+
+    pub(super) fn select_output_processor(&mut self, processor: OutputProcessor, enabled: bool) {
+        self.clear_picker_selection();
+        if self.output_processors.contains(&processor) == enabled {
+            return;
+        }
+        match set_output_processor_enabled(processor, enabled) {
+            Ok(enabled) => {
+                self.output_processors.retain(|&active| active != processor);
+                if enabled {
+                    self.output_processors.push(processor);
+                }
+                self.push_settings_output(&format!(
+                    "Copy & export cleanup {} set to {}",
+                    processor.name(),
+                    if enabled { "On" } else { "Off" }
+                ));
+                self.finish_local_success();
+            }
+            Err(error) => {
+                logging::error(format!(
+                    "failed to set output processor {} {}: {}",
+                    processor.name(),
+                    if enabled { "on" } else { "off" },
+                    error
+                ));
+                self.push_local_error(&format!("Failed to set copy & export cleanup: {}", error));
+            }
+        }
+        self.finish_local_change();
+    }
+
+    pub(super) fn show_status(&mut self) {
+        if self.busy || self.locked {
+            return;
+        }
+        self.append_status_output(false);
+    }
+
+    pub(super) fn select_slash_command(&mut self, name: &str) {
+        self.clear_picker_selection();
+        self.input.clear();
+        self.input.push('/');
+        self.input.push_str(name);
+        self.reset_prompt_history_navigation();
+        self.pending_input_focus = true;
+        self.refresh_after_input_change();
+    }
+
+    pub(super) fn select_model(&mut self, model: &str) {
+        self.clear_picker_selection();
+        if self.current_model == model {
+            return;
+        }
+        self.apply_model_selection(model);
+    }
+
+    pub(super) fn select_notification(&mut self, enabled: bool) {
+        self.clear_picker_selection();
+        if self.notifications_enabled == enabled {
+            return;
+        }
+        match set_notifications_enabled(enabled) {
+            Ok(enabled) => {
+                self.notifications_enabled = enabled;
+                self.push_settings_output(if enabled {
+                    "Notification set to On"
+                } else {
+                    "Notification set to Off"
+                });
+                self.finish_local_success();
+            }
+            Err(error) => {
+                logging::error(format!(
+                    "failed to set notification {}: {}",
+                    if enabled { "on" } else { "off" },
+                    error
+                ));
+                self.push_local_error(&format!("Failed to set notification: {}", error));
+            }
+        }
+        self.finish_local_change();
+    }
+
+    pub(super) fn select_project_config(&mut self, enabled: bool) {
+        self.clear_picker_selection();
+        if self.project_config_enabled == enabled {
+            return;
+        }
+        match set_project_config_enabled(enabled) {
+            Ok(enabled) => {
+                self.project_config_enabled = enabled;
+                self.refresh_project_config();
+                self.push_settings_output(if enabled {
+                    "Project config set to On"
+                } else {
+                    "Project config set to Off"
+                });
+                self.finish_local_success();
+            }
+            Err(error) => {
+                logging::error(format!(
+                    "failed to set project config {}: {}",
+                    if enabled { "on" } else { "off" },
+                    error
+                ));
+                self.push_local_error(&format!("Failed to set project config: {}", error));
+            }
+        }
+        self.finish_local_change();
+    }
+
+    pub(super) fn select_dirty_tree_warning(&mut self, enabled: bool) {
+        self.clear_picker_selection();
+        if self.dirty_tree_warning_enabled == enabled {
+            return;
+        }
+        match set_dirty_tree_warning_enabled(enabled) {
+            Ok(enabled) => {
+                self.dirty_tree_warning_enabled = enabled;
+                self.push_settings_output(if enabled {
+                    "Dirty tree warning set to On"
+                } else {
+                    "Dirty tree warning set to Off"
+                });
+                self.finish_local_success();
+            }
+            Err(error) => {
+                logging::error(format!(
+                    "failed to set dirty tree warning {}: {}",
+                    if enabled { "on" } else { "off" },
+                    error
+                ));
+                self.push_local_error(&format!("Failed to set dirty tree warning: {}", error));
+            }
+        }
+        self.finish_local_change();
+    }
 
-use super::render::trim_string_in_place;
-use super::{CodexAgentApp, ContextMenuState, SetupState};
+    pub(super) fn select_suggestions(&mut self, enabled: bool) {
+        self.clear_picker_selection();
+        if self.suggestions_enabled == enabled {
+            return;
+        }
+        match set_suggestions_enabled(enabled) {
+            Ok(enabled) => {
+                self.suggestions_enabled = enabled;
+                if !enabled {
+                    self.follow_up_suggestions.clear();
+                }
+                self.push_settings_output(if enabled {
+                    "Follow-up suggestions set to On"
+                } else {
+                    "Follow-up suggestions set to Off"
+                });
+                self.finish_local_success();
+            }
+            Err(error) => {
+                logging::error(format!(
+                    "failed to set follow-up suggestions {}: {}",
+                    if enabled { "on" } else { "off" },
+                    error
+                ));
+                self.push_local_error(&format!("Failed to set follow-up suggestions: {}", error));
+            }
+        }
+        self.finish_local_change();
+    }
+
+    pub(super) fn select_auto_continue(&mut self, enabled: bool) {
+        self.clear_picker_selection();
+        if self.auto_continue == enabled {
+            return;
+        }
+        match set_auto_continue(enabled) {
+            Ok(enabled) => {
+                self.auto_continue = enabled;
+                self.push_settings_output(if enabled {
+                    "Auto continue set to On"
+                } else {
+                    "Auto continue set to Off"
+                });
+                self.finish_local_success();
+            }
+            Err(error) => {
+                logging::error(format!(
+                    "failed to set auto continue {}: {}",
+                    if enabled { "on" } else { "off" },
+                    error
+                ));
+                self.push_local_error(&format!("Failed to set auto continue: {}", error));
+            }
+        }
+        self.finish_local_change();
+    }
+
+    pub(super) fn use_follow_up_suggestion(&mut self, index: usize) {
+        if self.busy || self.locked || index >= self.follow_up_suggestions.len() {
+            return;
+        }
+        self.input = self.follow_up_suggestions.remove(index);
+        self.reset_prompt_history_navigation();
+        self.pending_input_focus = true;
+        self.refresh_after_text_change();
+    }
+
+    pub(super) fn select_window_shadow(&mut self, enabled: bool) {
+        self.clear_picker_selection();
+        if self.window_shadow.enabled == enabled {
+            return;
+        }
+        match set_window_shadow_enabled(enabled) {
+            Ok(enabled) => {
+                self.window_shadow.enabled = enabled;
+                self.text_layout_dirty = true;
+                self.push_settings_output(if enabled {
+                    "Window glow set to On"
+                } else {
+                    "Window glow set to Off"
+                });
+                self.finish_local_success();
+            }
+            Err(error) => {
+                logging::error(format!(
+                    "failed to set window glow {}: {}",
+                    if enabled { "on" } else { "off" },
+                    error
+                ));
+                self.push_local_error(&format!("Failed to set window glow: {}", error));
+            }
+        }
+        self.finish_local_change();
+    }
+
+    pub(super) fn select_submit_key(&mut self, key: SubmitKey) {
+        self.clear_picker_selection();
+        if self.submit_key == key {
+            return;
+        }
+        match set_submit_key(key) {
+            Ok(key) => {
+                self.submit_key = key;
+                self.invalidate_input_layout();
+                self.push_settings_output(&format!("Submit key set to {}", key.label()));
+                self.finish_local_success();
+            }
+            Err(error) => {
+                logging::error(format!(
+                    "failed to set submit key {}: {}",
+                    key.label(),
+                    error
+                ));
+                self.push_local_error(&format!("Failed to set submit key: {}", error));
+            }
+        }
+        self.finish_local_change();
+    }
+
+    pub(super) fn select_prompt_whitespace(&mut self, mode: PromptWhitespace) {
+        self.clear_picker_selection();
+        if self.prompt_whitespace == mode {
+            return;
+        }
+        match set_prompt_whitespace(mode) {
+            Ok(mode) => {
+                self.prompt_whitespace = mode;
+                self.push_settings_output(&format!("Prompt whitespace set to {}", mode.label()));
+                self.finish_local_success();
+            }
+            Err(error) => {
+                logging::error(format!(
+                    "failed to set prompt whitespace {}: {}",
+                    mode.label(),
+                    error
+                ));
+                self.push_local_error(&format!("Failed to set prompt whitespace: {}", error));
+            }
+        }
+        self.finish_local_change();
+    }
+
+    pub(super) fn select_compact_mode(&mut self, enabled: bool) {
+        self.clear_picker_selection();
+        if self.compact_mode == enabled {
+            return;
+        }
+        match set_compact_mode(enabled) {
+            Ok(enabled) => {
+                self.compact_mode = enabled;
+                self.push_settings_output(if enabled {
+                    "Compact mode set to On"
+                } else {
+                    "Compact mode set to Off"
+                });
+                self.finish_local_success();
+            }
+            Err(error) => {
+                logging::error(format!(
+                    "failed to set compact mode {}: {}",
+                    if enabled { "on" } else { "off" },
+                    error
+                ));
+                self.push_local_error(&format!("Failed to set compact mode: {}", error));
+            }
+        }
+        self.finish_local_change();
+    }
+
+    pub(super) fn apply_capture_exclusion(&mut self) {
+        if !self.exclude_from_capture {
+            return;
+        }
+        if let Err(error) = set_capture_excluded(self.hwnd, true) {
+            logging::error(format!(
+                "failed to exclude the window from screen capture: {}",
+                error
+            ));
+            self.capture_exclusion_error = Some(error.to_string());
+            self.exclude_from_capture = false;
+        }
+    }
+
+    pub(super) fn select_exclude_from_capture(&mut self, enabled: bool) {
+        self.clear_picker_selection();
+        if self.exclude_from_capture == enabled {
+            return;
+        }
+        if let Err(error) = set_capture_excluded(self.hwnd, enabled) {
+            logging::error(format!(
+                "failed to {} screen capture exclusion: {}",
+                if enabled { "enable" } else { "disable" },
+                error
+            ));
+            self.capture_exclusion_error = Some(error.to_string());
+            self.push_local_error(&format!(
+                "Failed to change screen capture exclusion: {}",
+                error
+            ));
+            self.finish_local_change();
+            return;
+        }
+        self.capture_exclusion_error = None;
+        match set_exclude_from_capture(enabled) {
+            Ok(enabled) => {
+                self.exclude_from_capture = enabled;
+                self.push_settings_output(if enabled {
+                    "Window hidden from screen capture and sharing"
+                } else {
+                    "Window visible to screen capture and sharing"
+                });
+                self.finish_local_success();
+            }
+            Err(error) => {
+                self.exclude_from_capture = enabled;
+                logging::error(format!(
+                    "failed to save screen capture exclusion {}: {}",
+                    if enabled { "on" } else { "off" },
+                    error
+                ));
+                self.push_local_error(&format!("Failed to save screen capture setting: {}", error));
+            }
+        }
+        self.finish_local_change();
+    }
+
+    pub(super) fn select_redact_secrets(&mut self, enabled: bool) {
+        self.clear_picker_selection();
+        if self.redact_secrets == enabled {
+            return;
+        }
+        match set_redact_secrets(enabled) {
+            Ok(enabled) => {
+                self.redact_secrets = enabled;
+                redact::set_enabled(enabled);
+                self.push_settings_output(if enabled {
+                    "Secret redaction set to On"
+                } else {
+                    "Secret redaction set to Off"
+                });
+                self.finish_local_success();
+            }
+            Err(error) => {
+                logging::error(format!(
+                    "failed to set secret redaction {}: {}",
+                    if enabled { "on" } else { "off" },
+                    error
+                ));
+                self.push_local_error(&format!("Failed to set secret redaction: {}", error));
+            }
+        }
+        self.finish_local_change();
+    }
+
+    pub(super) fn select_persist_transcripts(&mut self, enabled: bool) {
+        self.clear_picker_selection();
+        if self.persist_transcripts == enabled {
+            return;
+        }
+        match set_persist_transcripts(enabled) {
+            Ok(enabled) => {
+                self.persist_transcripts = enabled;
+                if enabled {
+                    self.mark_draft_changed();
+                } else {
+                    self.recent_session = None;
+                }
+                self.push_settings_output(if enabled {
+                    "Transcripts are saved again: history, draft and session snapshots"
+                } else {
+                    "Transcripts are no longer saved; stored history, draft and snapshots were removed"
+                });
+                self.finish_local_success();
+            }
+            Err(error) => {
+                logging::error(format!(
+                    "failed to set transcript persistence {}: {}",
+                    if enabled { "on" } else { "off" },
+                    error
+                ));
+                self.push_local_error(&format!("Failed to set transcript persistence: {}", error));
+            }
+        }
+        self.finish_local_change();
+    }
+
+    pub(super) fn select_wake_mode(&mut self, mode: WakeMode) {
+        self.clear_picker_selection();
+        if self.wake_mode == mode {
+            return;
+        }
+        match set_wake_mode(mode) {
+            Ok(mode) => {
+                self.wake_mode = mode;
+                self.push_settings_output(&format!("Keep awake set to {}", mode.label()));
+                self.finish_local_success();
+            }
+            Err(error) => {
+                logging::error(format!(
+                    "failed to set keep awake {}: {}",
+                    mode.label(),
+                    error
+                ));
+                self.push_local_error(&format!("Failed to set keep awake: {}", error));
+            }
+        }
+        self.finish_local_change();
+    }
+
+    pub(super) fn select_theme(&mut self, theme: Theme) {
+        self.clear_picker_selection();
+        if self.theme == theme {
+            return;
+        }
+        match set_theme(theme) {
+            Ok(theme) => {
+                self.theme = theme;
+                self.invalidate_output_layout();
+                self.invalidate_input_layout();
+                self.push_settings_output(&format!("Theme set to {}", theme.label()));
+                self.finish_local_success();
+            }
+            Err(error) => {
+                logging::error(format!("failed to set theme {}: {}", theme.label(), error));
+                self.push_local_error(&format!("Failed to set theme: {}", error));
+            }
+        }
+        self.finish_local_change();
+    }
+
+    pub(super) fn select_response_dimming(&mut self, dimming: ResponseDimming) {
+        self.clear_picker_selection();
+        if self.response_dimming == dimming {
+            return;
+        }
+        match set_response_dimming(dimming) {
+            Ok(dimming) => {
+                self.response_dimming = dimming;
+                self.invalidate_output_layout();
+                self.push_settings_output(&format!(
+                    "Old response dimming set to {}",
+                    dimming.label()
+                ));
+                self.finish_local_success();
+            }
+            Err(error) => {
+                logging::error(format!(
+                    "failed to set old response dimming {}: {}",
+                    dimming.label(),
+                    error
+                ));
+                self.push_local_error(&format!("Failed to set old response dimming: {}", error));
+            }
+        }
+        self.finish_local_change();
+    }
+
+    pub(super) fn select_pending_style(&mut self, style: PendingStyle) {
+        self.clear_picker_selection();
+        if self.pending_style == style {
+            return;
+        }
+        match set_pending_style(style) {
+            Ok(style) => {
+                self.pending_style = style;
+                self.render_step = None;
+                self.push_settings_output(&format!("Pending animation set to {}", style.label()));
+                self.finish_local_success();
+            }
+            Err(error) => {
+                logging::error(format!(
+                    "failed to set pending animation {}: {}",
+                    style.label(),
+                    error
+                ));
+                self.push_local_error(&format!("Failed to set pending animation: {}", error));
+            }
+        }
+        self.finish_local_change();
+    }
+
+    pub(super) fn confirm_dirty_submit(&mut self, remember: bool) {
+        if self.dirty_submit_confirmation.take().is_none() {
+            return;
+        }
+        if remember {
+            logging::trace("dirty tree warning suppressed for this session");
+            self.dirty_tree_warning_suppressed = true;
+        }
+        self.dirty_submit_confirmed = true;
+        self.submit();
+    }
+
+    pub(super) fn confirm_secret_submit(&mut self, decision: SecretSubmitDecision, remember: bool) {
+        if self.secret_submit_confirmation.take().is_none() {
+            return;
+        }
+        if remember {
+            logging::trace("secret warning suppressed for this session");
+            self.secret_warning_suppressed = true;
+        }
+        self.secret_submit_decision = Some(decision);
+        self.submit();
+    }
+
+    pub(super) fn abort_secret_submit(&mut self) {
+        if self.secret_submit_confirmation.take().is_none() {
+            return;
+        }
+        logging::trace("submit aborted because the prompt looks like it contains a secret");
+        self.secret_submit_decision = None;
+        self.pending_input_focus = true;
+        self.resize_for_text();
+    }
 
-impl CodexAgentApp {
-    pub(super) fn submit(&mut self) {
-        if self.busy || self.locked || !trim_string_in_place(&mut self.input) {
+    pub(super) fn abort_token_submit(&mut self) {
+        if self.token_submit_confirmation.take().is_none() {
             return;
         }
+        logging::trace("oversized submit aborted");
+        self.pending_input_focus = true;
+        self.resize_for_text();
+    }
+
+    pub(super) fn select_token_warning_limit(&mut self, limit: u64) {
         self.clear_picker_selection();
-        let prompt = std::mem::take(&mut self.input);
-        if self.try_run_local_command(&prompt) {
+        if self.token_warning_limit == limit {
             return;
         }
-        self.push_prompt_history(&prompt);
+        match set_token_warning_limit(limit) {
+            Ok(limit) => {
+                self.token_warning_limit = limit;
+                self.token_submit_confirmation = None;
+                let message = if limit == 0 {
+                    "Large prompt warning disabled".to_owned()
+                } else {
+                    format!("Large prompt warning above {} tokens", format_tokens(limit))
+                };
+                self.push_settings_output(&message);
+                self.finish_local_success();
+            }
+            Err(error) => {
+                logging::error(format!(
+                    "failed to set token warning limit {}: {}",
+                    limit, error
+                ));
+                self.push_local_error(&format!("Failed to set large prompt warning: {}", error));
+            }
+        }
+        self.finish_local_change();
+    }
 
-        if !self.title_set {
-            self.title_set = true;
-            let title: String = prompt.chars().take(40).collect();
-            self.ctx
-                .send_viewport_cmd(egui::ViewportCommand::Title(title));
+    pub(super) fn set_cwd_problem(&mut self, problem: Option<String>) {
+        if self.cwd_problem == problem {
+            return;
         }
+        match &problem {
+            Some(problem) => logging::trace(format!(
+                "working directory unavailable; submission disabled: {}",
+                problem
+            )),
+            None => logging::trace("working directory available again"),
+        }
+        self.cwd_problem = problem;
+    }
+
+    pub(super) fn dismiss_environment_notice(&mut self) {
+        self.environment_notice.clear();
+        logging::trace("environment notice dismissed");
+        self.resize_for_text();
+    }
 
-        if let Err(error) = ensure_codex_files() {
-            logging::error(format!("codex file check failed: {}", error));
+    pub(super) fn abort_dirty_submit(&mut self) {
+        if self.dirty_submit_confirmation.take().is_none() {
+            return;
         }
+        logging::trace("submit aborted because of uncommitted changes");
+        self.secret_submit_decision = None;
+        self.pending_input_focus = true;
+        self.resize_for_text();
+    }
 
-        logging::trace(format!(
-            "submitting prompt with {} chars",
-            prompt.chars().count()
-        ));
-        let prompt_id = self.next_prompt_id;
-        self.next_prompt_id += 1;
-        self.busy = true;
-        self.locked = true;
-        self.active_prompt_id = Some(prompt_id);
-        self.pending_started_at = Some(Instant::now());
-        self.push_prompt_output(&prompt);
-        self.persist_history();
-        self.refresh_after_text_change();
-        self.stream_notification_pending
-            .store(false, Ordering::Relaxed);
+    pub(super) fn confirm_project_trust(&mut self, trust: bool) {
+        if !std::mem::take(&mut self.project_trust_confirmation) {
+            return;
+        }
+        if !trust {
+            self.project_config.ignore();
+        } else if let Err(error) = self.project_config.trust() {
+            logging::error(format!("failed to trust project config: {}", error));
+            self.push_local_error(&format!("Failed to trust project config: {}", error));
+            self.finish_local_change();
+            return;
+        }
+        self.submit();
+    }
+
+    pub(super) fn abort_project_trust(&mut self) {
+        if !std::mem::take(&mut self.project_trust_confirmation) {
+            return;
+        }
+        logging::trace("submit aborted while the project config awaits trust");
+        self.pending_input_focus = true;
+        self.resize_for_text();
+    }
+
+    fn project_trust_pending(&mut self) -> bool {
+        self.refresh_project_config().is_some() && self.project_config.pending_trust().is_some()
+    }
+
+    fn dirty_submit_changes(&mut self) -> Option<usize> {
+        if std::mem::take(&mut self.dirty_submit_confirmed)
+            || !self.dirty_tree_warning_enabled
+            || self.dirty_tree_warning_suppressed
         {
-            let mut stream = self.shared_stream.lock().unwrap_or_else(|e| e.into_inner());
-            stream.start(prompt_id);
-            self.stream_generation = stream.generation;
-            self.stream_visible_len = 0;
+            return None;
         }
+        let changes = self.cwd_git_info().map_or(0, |info| info.changes);
+        if changes == 0 {
+            return None;
+        }
+        let sandbox_mode = self
+            .refresh_project_config()
+            .and_then(|project| project.sandbox_mode)
+            .or_else(current_sandbox_mode);
+        (sandbox_mode.as_deref() == Some(PERMISSIVE_SANDBOX_MODE)).then_some(changes)
+    }
 
-        let request_prompt = self.build_request_prompt(prompt);
-        let tx = self.tx.clone();
-        let ctx = self.ctx.clone();
-        let running_prompt = Arc::clone(&self.running_prompt);
-        let shared_stream = Arc::clone(&self.shared_stream);
-        let stream_notification_pending = Arc::clone(&self.stream_notification_pending);
-        let session_id = self.session_id.clone();
-        thread::spawn(move || {
-            let result = match logging::catch_panic("prompt worker thread", || {
-                match prompt_codex(
-                    prompt_id,
-                    request_prompt,
-                    session_id,
-                    running_prompt,
-                    shared_stream,
-                    stream_notification_pending,
-                    &tx,
-                    &ctx,
-                ) {
-                    Ok((output, sid)) => AppEvent::Prompt(prompt_id, PromptResult::Ok(output, sid)),
-                    Err(error) => {
-                        logging::error(format!("prompt execution failed: {}", error));
-                        AppEvent::Prompt(prompt_id, PromptResult::Err(error.to_string()))
-                    }
-                }
-            }) {
-                Ok(result) => result,
-                Err(message) => AppEvent::Prompt(prompt_id, PromptResult::Err(message)),
-            };
-            if tx.send(result).is_err() {
-                logging::error("failed to deliver prompt result to app");
-            }
-            ctx.request_repaint();
-        });
+    pub(super) fn refresh_project_config(&mut self) -> Option<ProjectConfig> {
+        if !self.project_config_enabled {
+            return None;
+        }
+        self.project_config
+            .refresh(Path::new(&self.cwd_text))
+            .cloned()
     }
 
-    pub(super) fn show_status(&mut self) {
-        if self.busy || self.locked {
-            return;
+    pub(super) fn active_prompt_prefix(&self) -> Option<&str> {
+        if !self.project_config_enabled {
+            return None;
         }
-        self.append_status_output(false);
+        self.project_config
+            .config()
+            .and_then(ProjectConfig::prompt_prefix)
     }
 
-    pub(super) fn select_slash_command(&mut self, name: &str) {
-        self.clear_picker_selection();
-        self.input.clear();
-        self.input.push('/');
-        self.input.push_str(name);
-        self.reset_prompt_history_navigation();
-        self.pending_input_focus = true;
-        self.refresh_after_input_change();
+    pub(super) fn prompt_model(&self) -> String {
+        self.project_config_enabled
+            .then(|| {
+                self.project_config
+                    .config()
+                    .and_then(|config| config.model.clone())
+            })
+            .flatten()
+            .unwrap_or_else(current_model)
     }
 
-    pub(super) fn select_model(&mut self, model: &str) {
+    fn record_prompt_usage(&mut self, usage: TokenUsage) -> Option<f64> {
+        let model = self.prompt_model();
+        let cost = match load_model_prices() {
+            Ok(prices) => PriceTable::parse(&prices).cost(&model, usage),
+            Err(error) => {
+                logging::error(format!("failed to read model prices: {}", error));
+                None
+            }
+        };
+        logging::trace(format!(
+            "prompt used {} input / {} output tokens on {}",
+            usage.input_tokens, usage.output_tokens, model
+        ));
+        self.session_usage.record(usage, cost);
+        cost
+    }
+
+    pub(super) fn redetect_codex_install(&mut self) {
         self.clear_picker_selection();
-        if self.current_model == model {
+        if self.busy {
             return;
         }
-        self.apply_model_selection(model);
+        let launcher = redetect_codex();
+        self.push_settings_output(&format!("Codex launcher: {}", launcher));
+        self.finish_local_success();
+        self.finish_local_change();
     }
 
-    pub(super) fn select_notification(&mut self, enabled: bool) {
+    pub(super) fn select_launcher_mode(&mut self, mode: LauncherMode) {
         self.clear_picker_selection();
-        if self.notifications_enabled == enabled {
+        if self.busy || self.launcher_mode == mode {
             return;
         }
-        match set_notifications_enabled(enabled) {
-            Ok(enabled) => {
-                self.notifications_enabled = enabled;
-                self.push_settings_output(if enabled {
-                    "Notification set to On"
-                } else {
-                    "Notification set to Off"
-                });
+        let label = mode.label();
+        match set_launcher_mode(mode) {
+            Ok(mode) => {
+                self.launcher_mode = mode;
+                let launcher = redetect_codex();
+                self.push_settings_output(&format!("Codex launcher: {}", launcher));
                 self.finish_local_success();
             }
             Err(error) => {
-                logging::error(format!(
-                    "failed to set notification {}: {}",
-                    if enabled { "on" } else { "off" },
-                    error
-                ));
-                self.push_local_error(&format!("Failed to set notification: {}", error));
+                logging::error(format!("failed to set codex launcher {}: {}", label, error));
+                self.push_local_error(&format!("Failed to set codex launcher: {}", error));
             }
         }
         self.finish_local_change();
@@ -210,43 +1218,50 @@ impl CodexAgentApp {
         self.finish_local_change();
     }
 
-    fn finish_local_change(&mut self) {
+    pub(super) fn finish_local_change(&mut self) {
         self.persist_history();
         self.pending_input_focus = true;
         self.refresh_after_text_change();
     }
 
-    fn finish_local_success(&mut self) {
+    pub(super) fn finish_local_success(&mut self) {
         self.input.clear();
         self.reset_prompt_history_navigation();
     }
 
-    fn push_local_error(&mut self, message: &str) {
-        self.output.push('\x1D');
-        self.output.push_str(message);
+    pub(super) fn push_local_error(&mut self, message: &str) {
+        self.transcript
+            .push(SegmentKind::Error, &format!("\x1D{}", message));
     }
 
-    fn push_settings_output(&mut self, message: &str) {
+    pub(super) fn push_settings_output(&mut self, message: &str) {
         self.ensure_output_spacing();
-        self.output.push('\x1C');
-        self.output.push_str(message);
-        self.output.push_str("\n\n");
+        self.transcript
+            .push(SegmentKind::Notice, &format!("\x1C{}\n\n", message));
     }
 
-    fn ensure_output_spacing(&mut self) {
-        if self.output.is_empty() {
-            return;
-        }
-        if !self.output.ends_with('\n') {
-            self.output.push_str("\n\n");
-        } else if !self.output.ends_with("\n\n") {
-            self.output.push('\n');
-        }
+    pub(super) fn ensure_output_spacing(&mut self) {
+        self.transcript.ensure_spacing();
     }
 
     fn finish_prompt(&mut self, prompt_id: u64) {
+        logging::trace(format!(
+            "prompt {} stream: {} events received, {} output mutations applied; {}",
+            prompt_id,
+            self.stream_events_received,
+            self.stream_mutations_applied,
+            self.stream_throughput.summary()
+        ));
+        self.stream_events_received = 0;
+        self.stream_mutations_applied = 0;
         self.active_prompt_id = None;
         self.pending_started_at = None;
+        self.sleeping_prompt = None;
+        self.sleep_killed_prompt = None;
+        self.resume_retry_pending = false;
+        self.retry_baseline = None;
+        self.continued_response_start = None;
+        self.finish_snapshot();
         self.stream_notification_pending
             .store(false, Ordering::Relaxed);
         self.clear_render_buffer();
@@ -258,14 +1273,15 @@ impl CodexAgentApp {
     fn start_install_flow(&mut self, node_available: bool) {
         self.setup_state = SetupState::Installing;
         self.clear_output_buffers();
-        self.output.push_str("Installing Codex CLI...\n\n");
+        self.transcript
+            .push(SegmentKind::Notice, "Installing Codex CLI...\n\n");
         self.refresh_after_text_change();
         self.spawn_install(node_available);
     }
 
     fn spawn_install(&self, node_available: bool) {
         let tx = self.tx.clone();
-        let ctx = self.ctx.clone();
+        let ctx = self.repaint_handle();
         let install_stdin = Arc::clone(&self.install_stdin);
         thread::spawn(move || {
             let result = run_full_install(node_available, &tx, &ctx, &install_stdin);
@@ -276,6 +1292,32 @@ impl CodexAgentApp {
         });
     }
 
+    fn spawn_response_diff(&self, range: (usize, usize), previous: String) {
+        logging::trace("comparing retried response with the previous answer");
+        let current = self.transcript.text()[range.0..range.1].to_owned();
+        let tx = self.tx.clone();
+        let ctx = self.ctx.clone();
+        thread::spawn(move || {
+            let diff = marked_response_diff(&previous, &current);
+            if tx.send(AppEvent::ResponseDiff(range, diff)).is_err() {
+                logging::error("failed to deliver response diff to app");
+            }
+            ctx.request_repaint();
+        });
+    }
+
+    pub(super) fn toggle_response_diff(&mut self) {
+        let Some(diff) = self.response_diff.as_mut() else {
+            return;
+        };
+        diff.showing = !diff.showing;
+        logging::trace(format!(
+            "{} changes from previous answer",
+            if diff.showing { "showing" } else { "hiding" }
+        ));
+        self.refresh_after_output_rewrite();
+    }
+
     fn spawn_codex_check(&self) {
         let tx = self.tx.clone();
         let ctx = self.ctx.clone();
@@ -305,165 +1347,472 @@ impl CodexAgentApp {
         });
     }
 
-    fn try_run_local_command(&mut self, prompt: &str) -> bool {
-        if prompt == "/status" {
-            self.append_status_output(true);
-            return true;
+    pub(super) fn agents_files_for_cwd(&self) -> Option<&[PathBuf]> {
+        match self
+            .probes
+            .get(&ProbeKey::agents_files(Path::new(&self.cwd_text)))
+        {
+            Some(ProbeValue::AgentsFiles(files)) => Some(files),
+            _ => None,
+        }
+    }
+
+    pub(super) fn request_cwd_probes(&mut self) {
+        let cwd = Path::new(&self.cwd_text);
+        self.probes.request(&ProbeKey::directory(cwd));
+        self.probes.request(&ProbeKey::agents_files(cwd));
+        self.probes.request(&ProbeKey::git_info(cwd));
+    }
+
+    fn apply_probe_result(&mut self, key: ProbeKey, value: ProbeValue) {
+        if let ProbeValue::DiskSpace(free_mb) = value {
+            self.apply_disk_space(free_mb);
+        }
+        let current = key.path == Path::new(&self.cwd_text);
+        if current && let ProbeValue::Directory(status) = &value {
+            if let Err(problem) = status
+                && self.cwd_problem.as_ref() != Some(problem)
+            {
+                logging::error(format!(
+                    "working directory {} is unavailable: {}",
+                    self.cwd_text, problem
+                ));
+            }
+            self.set_cwd_problem(status.clone().err());
+        }
+        self.probes.store(key, value);
+    }
+
+    pub(super) fn cwd_git_info(&self) -> Option<&GitInfo> {
+        match self
+            .probes
+            .get(&ProbeKey::git_info(Path::new(&self.cwd_text)))
+        {
+            Some(ProbeValue::GitInfo(info)) => info.as_ref(),
+            _ => None,
         }
-        false
     }
 
-    fn append_status_output(&mut self, add_to_history: bool) {
+    pub(super) fn invalidate_git_info(&mut self) {
+        self.probes
+            .invalidate(&ProbeKey::git_info(Path::new(&self.cwd_text)));
+    }
+
+    pub(super) fn invalidate_agents_files(&mut self) {
+        self.probes
+            .invalidate(&ProbeKey::agents_files(Path::new(&self.cwd_text)));
+    }
+
+    pub(super) fn append_status_output(&mut self, add_to_history: bool) {
         if add_to_history {
             self.push_prompt_history("/status");
         } else {
             self.reset_prompt_history_navigation();
         }
         self.ensure_output_spacing();
-        self.output_base = self.output.len();
-        self.output.push_str(&current_usage_text());
+        self.transcript.begin_response();
+        self.transcript
+            .push(SegmentKind::Notice, &current_usage_text());
         self.mark_output_for_rebuild();
         self.input.clear();
         self.finish_local_change();
     }
 
-    fn push_prompt_output(&mut self, prompt: &str) {
-        self.output.reserve(prompt.len() + 2);
-        self.ensure_output_spacing();
-        let prompt_start = self.output.len();
-        self.output.push_str(prompt);
-        self.prompt_ranges.push((prompt_start, self.output.len()));
-        self.output.push_str("\n\n");
-        self.output_base = self.output.len();
+    pub(super) fn push_prompt_output(&mut self, prompt: &str, notes: &[String]) {
+        self.transcript.push_prompt(prompt, notes);
         self.mark_output_for_rebuild();
     }
 
     pub(super) fn cancel_active_prompt(&mut self) {
-        let running_prompt = {
-            let mut active = self
-                .running_prompt
-                .lock()
-                .unwrap_or_else(|e| e.into_inner());
-            active.take()
-        };
-        let Some(running_prompt) = running_prompt else {
+        if self.cancelling_prompt.is_some()
+            || self.cancel_rate_limit_wait()
+            || self.cancel_comparison()
+        {
+            return;
+        }
+        let PromptState::Busy(prompt_id) = self.prompt_state() else {
+            logging::trace(format!("ignoring cancel while {:?}", self.prompt_state()));
             return;
         };
-
-        logging::trace(format!("canceling prompt pid {}", running_prompt.pid));
-        if let Some(session_id) = running_prompt.session_id {
+        let exited = self
+            .shared_stream
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .mark_cancelled(prompt_id);
+        if exited {
+            logging::trace("prompt process already exited; cancelling anyway");
+        }
+        let running_prompt = self
+            .running_prompt
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .take(prompt_id);
+        self.apply_prompt_stream(prompt_id);
+        let session_id = running_prompt
+            .as_ref()
+            .and_then(|running| running.session_id.clone());
+        if self.hook_output_lines.take().is_some() {
+            logging::trace("cancelling completion hook");
+        } else if let Some(session_id) = session_id {
             self.session_id = Some(session_id);
             self.cancelled_resume_context = None;
         } else {
             self.capture_cancelled_resume_context();
         }
         self.active_prompt_id = None;
+        self.cancelling_prompt = Some(prompt_id);
+        self.stream_notification_pending
+            .store(false, Ordering::Relaxed);
+
+        let Some(pid) = running_prompt.map(|running| running.pid) else {
+            logging::trace(format!(
+                "prompt {} has no registered process; its worker stops on its own",
+                prompt_id
+            ));
+            if self
+                .tx
+                .send(AppEvent::PromptCancelled(prompt_id, Ok(())))
+                .is_err()
+            {
+                logging::error("failed to deliver prompt cancellation to app");
+            }
+            return;
+        };
+        logging::trace(format!("canceling prompt {} pid {}", prompt_id, pid));
+        let tx = self.tx.clone();
+        let ctx = self.ctx.clone();
+        let backend = Arc::clone(&self.backend);
+        thread::spawn(move || {
+            let result = match logging::catch_panic("prompt cancel thread", || {
+                backend.cancel(pid).map_err(|error| {
+                    logging::error(format!("failed to cancel prompt pid {}: {}", pid, error));
+                    format!("Failed to stop codex process {}: {}", pid, error)
+                })
+            }) {
+                Ok(result) => result,
+                Err(message) => Err(format!("Failed to stop codex process {}: {}", pid, message)),
+            };
+            if tx
+                .send(AppEvent::PromptCancelled(prompt_id, result))
+                .is_err()
+            {
+                logging::error("failed to deliver prompt cancellation to app");
+            }
+            ctx.request_repaint();
+        });
+    }
+
+    fn finish_cancel(&mut self, prompt_id: u64, result: Result<(), String>) {
+        if self.cancelling_prompt != Some(prompt_id) {
+            return;
+        }
+        self.apply_prompt_stream(prompt_id);
+        let exited = self
+            .shared_stream
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .has_exited(prompt_id);
+        self.note_batch_result(prompt_id, Err("cancelled".to_owned()));
+        self.cancelling_prompt = None;
         self.busy = false;
         self.locked = false;
         self.pending_started_at = None;
-        self.stream_notification_pending
-            .store(false, Ordering::Relaxed);
+        self.pending_input_focus = true;
         self.clear_render_buffer();
         self.reset_stream_progress();
-        append_cancelled_text(&mut self.output);
+        self.finish_snapshot();
+        self.transcript.push_cancelled();
+        match result {
+            Err(error) if exited => logging::trace(format!(
+                "codex exited before the cancel landed; ignoring: {}",
+                error
+            )),
+            Err(error) => self
+                .transcript
+                .push(SegmentKind::Error, &format!("\n\x1D{}", error)),
+            Ok(()) => {}
+        }
         self.persist_history();
         self.refresh_after_output_change();
+        let mut stream = self.shared_stream.lock().unwrap_or_else(|e| e.into_inner());
+        stream.clear(prompt_id);
+    }
+
+    fn apply_prompt_stream(&mut self, prompt_id: u64) {
+        let mut updated = false;
         {
-            let mut stream = self.shared_stream.lock().unwrap_or_else(|e| e.into_inner());
-            stream.clear(running_prompt.id);
+            let stream = self.shared_stream.lock().unwrap_or_else(|e| e.into_inner());
+            if stream.prompt_id == Some(prompt_id) {
+                if self.prompt_phase != stream.phase {
+                    let label = stream.phase.label();
+                    self.ctx.output_mut(|output| {
+                        output.events.push(egui::output::OutputEvent::ValueChanged(
+                            egui::WidgetInfo::labeled(egui::WidgetType::Label, true, label),
+                        ))
+                    });
+                }
+                self.prompt_phase = stream.phase;
+                if stream.phase != PromptPhase::Waiting {
+                    self.resume_retry_pending = false;
+                }
+                let next = stream.text.as_str();
+                let next_len = next.len();
+                self.stream_throughput.record(
+                    next_len.saturating_sub(self.stream_visible_len),
+                    Instant::now(),
+                );
+                let needs_replace = self.stream_generation != stream.generation
+                    || self.stream_visible_len > next_len;
+                if needs_replace {
+                    let response_start = self.transcript.response_start();
+                    if self.transcript.text().get(response_start..) != Some(next) {
+                        self.transcript.replace_response(next);
+                        self.output_display_can_append = false;
+                        updated = true;
+                    }
+                    self.stream_generation = stream.generation;
+                    self.stream_visible_len = next_len;
+                } else if next_len > self.stream_visible_len {
+                    self.transcript
+                        .push_response(&next[self.stream_visible_len..]);
+                    self.stream_visible_len = next_len;
+                    updated = true;
+                }
+            }
         }
+        if updated {
+            self.stream_mutations_applied += 1;
+            self.refresh_after_output_change();
+        }
+    }
 
-        thread::spawn(move || {
-            let _ = logging::catch_panic("prompt cancel thread", || {
-                if let Err(error) = kill_prompt_process(running_prompt.pid) {
-                    logging::error(format!(
-                        "failed to cancel prompt pid {}: {}",
-                        running_prompt.pid, error
-                    ));
-                }
-            });
-        });
+    fn apply_stream_event(&mut self, prompt_id: u64) {
+        self.stream_notification_pending
+            .store(false, Ordering::Relaxed);
+        if self.active_prompt_id == Some(prompt_id) || self.cancelling_prompt == Some(prompt_id) {
+            self.apply_prompt_stream(prompt_id);
+        } else if self.owns_comparison_prompt(prompt_id) {
+            self.apply_comparison_stream(prompt_id);
+        }
     }
 
     fn handle_event(&mut self, result: AppEvent) {
         match result {
             AppEvent::PromptStream(prompt_id) => {
-                self.stream_notification_pending
-                    .store(false, Ordering::Relaxed);
-                if self.active_prompt_id == Some(prompt_id) {
-                    let mut updated = false;
-                    {
-                        let stream = self.shared_stream.lock().unwrap_or_else(|e| e.into_inner());
-                        if stream.prompt_id == Some(prompt_id) {
-                            let next = stream.text.as_str();
-                            let next_len = next.len();
-                            let needs_replace = self.stream_generation != stream.generation
-                                || self.stream_visible_len > next_len;
-                            if needs_replace {
-                                if self.output.get(self.output_base..) != Some(next) {
-                                    self.output.truncate(self.output_base);
-                                    self.output.push_str(next);
-                                    self.output_display_can_append = false;
-                                    updated = true;
-                                }
-                                self.stream_generation = stream.generation;
-                                self.stream_visible_len = next_len;
-                            } else if next_len > self.stream_visible_len {
-                                self.output.push_str(&next[self.stream_visible_len..]);
-                                self.stream_visible_len = next_len;
-                                updated = true;
-                            }
-                        }
-                    }
-                    if updated {
-                        self.refresh_after_output_change();
-                    }
+                self.stream_events_received += 1;
+                self.apply_stream_event(prompt_id);
+            }
+            AppEvent::PromptCancelled(prompt_id, result) => self.finish_cancel(prompt_id, result),
+            AppEvent::ProbeResult(key, value) => self.apply_probe_result(key, value),
+            AppEvent::Power(event) => self.handle_power_event(event),
+            AppEvent::ClipboardText(text) => self.handle_clipboard_text(text),
+            AppEvent::AccessibilityChanged(prefs) => {
+                let high_contrast = self.high_contrast();
+                self.accessibility = prefs;
+                if self.high_contrast() != high_contrast {
+                    self.invalidate_output_layout();
+                    self.invalidate_input_layout();
+                }
+            }
+            AppEvent::SystemThemeChanged(light) => {
+                let high_contrast = self.high_contrast();
+                self.system_light_theme = light;
+                if self.high_contrast() != high_contrast {
+                    logging::trace("switching card style to follow the system app theme");
+                    self.invalidate_output_layout();
+                    self.invalidate_input_layout();
+                }
+            }
+            AppEvent::ResponseDiff(range, text) => {
+                if self.busy || self.last_response_range != Some(range) {
+                    return;
                 }
+                if text.is_none() {
+                    logging::trace("response diff too large; skipped");
+                }
+                let changed = text
+                    .as_deref()
+                    .is_none_or(|text| text.contains(['\x11', '\x12']));
+                self.response_diff = Some(ResponseDiff {
+                    range,
+                    text,
+                    changed,
+                    showing: false,
+                });
+                self.resize_for_text();
+            }
+            AppEvent::SessionResumeFailed(prompt_id) => {
+                if self.active_prompt_id != Some(prompt_id) {
+                    return;
+                }
+                self.session_id = None;
+                self.cancelled_resume_context = None;
+                self.transcript.truncate_response();
+                self.transcript.push(
+                    SegmentKind::Notice,
+                    "\x1CPrevious session could not be resumed \u{2014} started a new one.\n\n",
+                );
+                self.transcript.begin_response();
+                self.resume_retry_pending = true;
+                self.reset_stream_progress();
+                self.mark_output_for_rebuild();
+                self.refresh_after_output_change();
+            }
+            AppEvent::Prompt(prompt_id, result) if self.owns_comparison_prompt(prompt_id) => {
+                self.finish_comparison_run(prompt_id, result);
+            }
+            AppEvent::Prompt(prompt_id, result) if self.owns_title_prompt(prompt_id) => {
+                self.finish_title_request(result);
             }
             AppEvent::Prompt(prompt_id, result) => {
                 if self.active_prompt_id != Some(prompt_id) {
                     return;
                 }
+                self.apply_prompt_stream(prompt_id);
+                if let PromptResult::Err(kind, error) = &result
+                    && self.hold_for_rate_limit(prompt_id, *kind, error)
+                {
+                    return;
+                }
+                let cost = match &result {
+                    PromptResult::Ok(PromptOutput {
+                        usage: Some(usage), ..
+                    }) => self.record_prompt_usage(*usage),
+                    _ => None,
+                };
+                self.record_turn_stats(prompt_id, &result, cost);
                 match &result {
-                    PromptResult::Ok(output, _) => logging::trace(format!(
+                    PromptResult::Ok(output) => logging::trace(format!(
                         "prompt completed; {} chars returned",
-                        output.chars().count()
+                        output.response.chars().count()
                     )),
-                    PromptResult::Err(error) => {
-                        logging::error(format!("prompt completed with error: {}", error))
+                    PromptResult::Err(kind, error) => {
+                        logging::error(format!("prompt completed with {:?} error: {}", kind, error))
                     }
                 }
+                let succeeded = matches!(
+                    result,
+                    PromptResult::Ok(PromptOutput { approval: None, .. })
+                );
+                if succeeded {
+                    self.request_codex_title();
+                }
+                self.note_batch_result(
+                    prompt_id,
+                    match &result {
+                        PromptResult::Ok(..) => Ok(()),
+                        PromptResult::Err(_, error) => {
+                            Err(error.lines().next().unwrap_or_default().to_owned())
+                        }
+                    },
+                );
+                let continued_start = self.continued_response_start.take();
                 self.busy = false;
                 self.locked = false;
                 self.pending_input_focus = true;
-                self.output.truncate(self.output_base);
                 self.mark_output_for_rebuild();
                 match result {
-                    PromptResult::Ok(text, sid) => {
-                        self.output.reserve(text.len());
-                        self.output.push_str(&text);
+                    PromptResult::Ok(PromptOutput {
+                        response: text,
+                        session_id: sid,
+                        usage: _,
+                        suggestions,
+                        length_stopped,
+                        budget_stop,
+                        changed_files,
+                        approval,
+                    }) => {
+                        let truncated = budget_stop.is_none()
+                            && approval.is_none()
+                            && looks_truncated(&text, length_stopped);
+                        self.transcript.truncate_response();
+                        self.transcript.push_response(&text);
+                        if let Some(summary) = budget_stop.as_deref() {
+                            self.transcript.push_stop_notice(summary);
+                        }
+                        if let Some(request) = approval {
+                            self.hold_for_approval(*request);
+                        }
+                        self.record_changed_files(changed_files);
+                        if self.suggestions_enabled {
+                            self.follow_up_suggestions = suggestions;
+                        }
                         if sid.is_some() {
                             self.session_id = sid;
                             self.cancelled_resume_context = None;
                         }
+                        let range = self.transcript.response_range();
+                        self.last_response_range = Some(range);
+                        if let Some(previous) = self.retry_baseline.take() {
+                            self.spawn_response_diff(range, previous);
+                        }
+                        if let Some(start) = continued_start {
+                            self.last_response_range = Some((start, range.1));
+                        }
+                        if !truncated
+                            && budget_stop.is_none()
+                            && self.pending_approval.is_none()
+                            && let Some(footer) = self.timing_footer()
+                        {
+                            self.transcript.push_stop_notice(&footer);
+                        }
+                        self.continuation_offered = truncated && self.session_id.is_some();
+                        if truncated {
+                            logging::trace(format!(
+                                "response looks truncated (length stop: {})",
+                                length_stopped
+                            ));
+                        }
                     }
-                    PromptResult::Err(error) => {
-                        if self.setup_state == SetupState::Ready && is_codex_missing(&error) {
+                    PromptResult::Err(kind, error) => {
+                        let error = self.sleep_interruption(prompt_id, error);
+                        self.capture_failed_step(prompt_id);
+                        let partial = self.stream_visible_len > 0;
+                        if !partial {
+                            self.transcript.truncate_response();
+                        }
+                        if self.setup_state == SetupState::Ready && kind == ErrorKind::CodexNotFound
+                        {
                             self.finish_prompt(prompt_id);
                             self.start_install_flow(has_node());
                             return;
                         }
-                        self.output
-                            .reserve(error.len() + error.lines().count().max(1));
-                        for line in error.split_inclusive('\n') {
-                            self.output.push('\x1D');
-                            self.output.push_str(line);
+                        let prompt = self.last_submitted_prompt.take().filter(|_| {
+                            continued_start.is_none() && !partial && self.input.trim().is_empty()
+                        });
+                        if let Some(prompt) = prompt {
+                            self.input = prompt;
+                            self.pending_input_focus = true;
+                            self.invalidate_input_layout();
+                            self.mark_draft_changed();
                         }
+                        if partial {
+                            logging::trace(format!(
+                                "keeping {} bytes of partial answer above the error",
+                                self.stream_visible_len
+                            ));
+                        }
+                        let (headline, hint) = error_report(kind);
+                        self.transcript
+                            .push_error_report(partial, headline, hint, &error);
                     }
                 }
                 self.finish_prompt(prompt_id);
                 self.persist_history();
+                self.invalidate_agents_files();
+                self.invalidate_git_info();
                 self.refresh_after_output_change();
+                if self.continuation_offered
+                    && self.auto_continue
+                    && self.continuations_used < MAX_AUTO_CONTINUATIONS
+                {
+                    self.continue_response();
+                    return;
+                }
+                if let Some(command) = succeeded.then(|| self.completion_hook()).flatten() {
+                    self.start_completion_hook(command);
+                    return;
+                }
                 if self.notifications_enabled {
                     notify::prompt_completed(self.hwnd);
                 }
@@ -482,23 +1831,25 @@ impl CodexAgentApp {
             },
             AppEvent::CodexInstallOutput(line) => {
                 if matches!(self.setup_state, SetupState::Installing) {
-                    self.output.push_str(&line);
-                    self.output.push('\n');
+                    self.transcript
+                        .push(SegmentKind::Notice, &format!("{}\n", line));
                     self.refresh_after_output_rewrite();
                 }
             }
             AppEvent::CodexInstallDone(result) => {
                 match result {
                     Ok(()) => {
-                        self.output
-                            .push_str("\nInstallation complete. Verifying...");
+                        self.transcript
+                            .push(SegmentKind::Notice, "\nInstallation complete. Verifying...");
                         self.setup_state = SetupState::Checking;
                         self.spawn_codex_check();
                     }
                     Err(msg) => {
                         self.setup_state = SetupState::InstallFailed(msg.clone());
-                        self.output.push_str("\nInstallation failed: ");
-                        self.output.push_str(&msg);
+                        self.transcript.push(
+                            SegmentKind::Error,
+                            &format!("\nInstallation failed: {}", msg),
+                        );
                     }
                 }
                 self.refresh_after_output_rewrite();
@@ -518,13 +1869,50 @@ impl CodexAgentApp {
                     }
                 }
             }
+            AppEvent::LogHealth(health) => {
+                if !health.file_logging {
+                    logging::trace(format!(
+                        "file logging degraded: {}",
+                        health.last_error.as_deref().unwrap_or("unknown error")
+                    ));
+                }
+                self.log_health = health;
+            }
+            AppEvent::Diagnostics(report) => self.apply_diagnostics(report),
+            AppEvent::ChangedFileDiff(path, result) => self.apply_changed_file_diff(&path, result),
+            AppEvent::ChangedFileReverted(path, result) => {
+                self.apply_changed_file_revert(&path, result)
+            }
+            AppEvent::CodexPath(path) => self.set_onboarding_codex_path(path),
+            AppEvent::CodexAuth(authenticated) => self.set_onboarding_authenticated(authenticated),
+            AppEvent::FolderPicked(path) => self.apply_picked_folder(path),
+            AppEvent::RecentSessionExport(path, folder) => self.export_recent_session(path, folder),
+            AppEvent::HookOutput(prompt_id, line) => self.append_hook_output(prompt_id, line),
+            AppEvent::HookDone(prompt_id, result) => self.finish_completion_hook(prompt_id, result),
         }
     }
 
     pub(super) fn poll(&mut self) {
-        while let Ok(result) = self.rx.try_recv() {
-            self.handle_event(result);
+        let mut pending_stream = None;
+        while let Ok(event) = self.rx.try_recv() {
+            if let AppEvent::PromptStream(prompt_id) = event {
+                self.stream_events_received += 1;
+                if let Some(previous) = pending_stream.replace(prompt_id)
+                    && previous != prompt_id
+                {
+                    self.apply_stream_event(previous);
+                }
+                continue;
+            }
+            if let Some(prompt_id) = pending_stream.take() {
+                self.apply_stream_event(prompt_id);
+            }
+            self.handle_event(event);
         }
+        if let Some(prompt_id) = pending_stream {
+            self.apply_stream_event(prompt_id);
+        }
+        self.tick_rate_limit_wait();
     }
 
     pub(super) fn start_codex_install(&mut self) {
@@ -548,19 +1936,75 @@ impl CodexAgentApp {
                 logging::error("installer input unavailable");
             }
         }
-        self.output.push_str(&input);
-        self.output.push('\n');
+        self.transcript
+            .push(SegmentKind::Notice, &format!("{}\n", input));
         self.mark_output_for_rebuild();
         self.pending_input_focus = true;
         self.refresh_after_text_change();
     }
 }
 
-fn is_codex_missing(error: &str) -> bool {
-    let error = error.to_ascii_lowercase();
-    error.contains("cannot find the file specified")
-        || error.contains("file not found")
-        || error.contains("program is not recognized")
-        || error.contains("program not found")
-        || error.contains("is not recognized as an internal or external command")
+fn error_report(kind: ErrorKind) -> (&'static str, &'static str) {
+    let (headline, hint) = match kind {
+        ErrorKind::SpawnFailed => (Text::SpawnFailed, Text::SpawnFailedHint),
+        ErrorKind::CodexNotFound => (Text::CodexNotFound, Text::CodexNotFoundHint),
+        ErrorKind::AuthRequired => (Text::AuthRequired, Text::AuthRequiredHint),
+        ErrorKind::RateLimited => (Text::RateLimited, Text::RateLimitedHint),
+        ErrorKind::Timeout => (Text::TimedOut, Text::TimedOutHint),
+        ErrorKind::Cancelled => (Text::PromptCancelled, Text::PromptCancelledHint),
+        ErrorKind::NonZeroExit => (Text::NonZeroExit, Text::NonZeroExitHint),
+        ErrorKind::InvalidOutput => (Text::InvalidOutput, Text::InvalidOutputHint),
+        ErrorKind::ResumeFailed => (Text::ResumeFailed, Text::ResumeFailedHint),
+        ErrorKind::BudgetExceeded => (Text::BudgetExceeded, Text::BudgetExceededHint),
+    };
+    (tr(headline), tr(hint))
+}
+
+pub(super) fn spawn_prompt_worker(
+    backend: Arc<dyn PromptBackend>,
+    request: PromptRequest,
+    channels: PromptChannels,
+) {
+    let prompt_id = request.id;
+    thread::spawn(move || {
+        let result = match logging::catch_panic("prompt worker thread", || {
+            match backend.run(request, &channels) {
+                Ok(output) => AppEvent::Prompt(prompt_id, PromptResult::Ok(output)),
+                Err(error) => {
+                    let kind = failure_kind(&error);
+                    logging::error(format!("prompt execution failed ({:?}): {}", kind, error));
+                    AppEvent::Prompt(prompt_id, PromptResult::Err(kind, error.to_string()))
+                }
+            }
+        }) {
+            Ok(result) => result,
+            Err(message) => AppEvent::Prompt(
+                prompt_id,
+                PromptResult::Err(classify_failure(None, &message, &[]), message),
+            ),
+        };
+        let cancelled = channels
+            .shared_stream
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .mark_exited(prompt_id);
+        if cancelled && let AppEvent::Prompt(_, PromptResult::Err(_, error)) = &result {
+            logging::trace(format!(
+                "prompt {} failed after it was cancelled; keeping the cancelled marker: {}",
+                prompt_id, error
+            ));
+            return;
+        }
+        if channels.tx.send(result).is_err() {
+            logging::error("failed to deliver prompt result to app");
+        }
+        channels.repaint.request_repaint();
+    });
+}
+
+pub(super) fn with_prompt_prefix(project: Option<&ProjectConfig>, prompt: String) -> String {
+    match project.and_then(ProjectConfig::prompt_prefix) {
+        Some(prefix) => format!("{}\n\n{}", prefix, prompt),
+        None => prompt,
+    }
 }
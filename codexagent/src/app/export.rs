@@ -0,0 +1,80 @@
+use serde_json::{Map, Value, json};
+
+use crate::config::{APP_DISPLAY_NAME, build_stamp};
+use crate::redact;
+
+use super::CodexAgentApp;
+use super::output::strip_line_markers;
+
+fn export_text(text: &str) -> String {
+    redact::redact_persistent(strip_line_markers(text, false).trim()).into_owned()
+}
+
+// Notices such as the timing footer describe the answer rather than belong to
+// it, and their content is in the turn's own fields.
+fn export_response(text: &str) -> String {
+    let answer: String = text
+        .split_inclusive('\n')
+        .filter(|line| !line.starts_with('\x1C'))
+        .collect();
+    export_text(&answer)
+}
+
+impl CodexAgentApp {
+    /// The session as structured data for `/export --json`; the same text
+    /// the markdown export writes, split into turns, with usage alongside.
+    pub(super) fn session_json(&self, notes: bool) -> Value {
+        let turns: Vec<Value> = self
+            .transcript
+            .turns()
+            .enumerate()
+            .map(|(index, (prompt, response))| {
+                let mut turn = Map::new();
+                turn.insert("turn".to_owned(), json!(index + 1));
+                turn.insert("prompt".to_owned(), json!(export_text(prompt)));
+                turn.insert("response".to_owned(), json!(export_response(response)));
+                turn.insert("rating".to_owned(), json!(self.transcript.rating(index)));
+                if let Some(Value::Object(fields)) = self.turn_stats_json(index) {
+                    turn.extend(fields);
+                }
+                Value::Object(turn)
+            })
+            .collect();
+        let usage = &self.session_usage;
+        let mut session = json!({
+            "exported_by": format!("{} {}", APP_DISPLAY_NAME, build_stamp()),
+            "title": self
+                .session_title
+                .text()
+                .map(|title| redact::redact_persistent(title).into_owned()),
+            "session_id": self.session_id,
+            "turns": turns,
+            "statistics": self.session_stats_json(),
+            "usage": {
+                "tokens": usage.tokens,
+                "cost_usd": usage.cost,
+                "unpriced_prompts": usage.unpriced_prompts,
+            },
+        });
+        if notes {
+            session["notes"] = json!(export_text(&self.notes.text));
+        }
+        session
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn exported_responses_keep_the_answer_and_drop_notices() {
+        assert_eq!(
+            export_response(
+                "\nThe parser reads tokens.\n\x1E thinking\n\x1C12.4s \u{b7} 3.4k tok\n"
+            ),
+            "The parser reads tokens.\n thinking"
+        );
+        assert_eq!(export_response("\x1Cwaiting for approval\n"), "");
+    }
+}
@@ -0,0 +1,175 @@
+use eframe::egui;
+
+use crate::logging;
+
+use super::CodexAgentApp;
+use super::layout::{PROMPT_PREFIX_CHIP_HEIGHT, PROMPT_PREFIX_CHIP_SPACING};
+
+pub(super) const FIND_ID: &str = "output-find";
+
+#[derive(Default)]
+pub(super) struct FindBar {
+    pub(super) open: bool,
+    pub(super) query: String,
+    pub(super) pending_scroll: bool,
+    current: Option<usize>,
+    matches: Vec<(usize, usize)>,
+    searched: Option<(String, usize)>,
+}
+
+/// Byte ranges of the non-overlapping, case-insensitive matches of `query`.
+pub(super) fn find_matches(text: &str, query: &str) -> Vec<(usize, usize)> {
+    let mut matches = Vec::new();
+    if query.is_empty() {
+        return matches;
+    }
+    let mut from = 0;
+    while let Some((offset, _)) = text[from..]
+        .char_indices()
+        .find(|&(offset, _)| match_len(&text[from + offset..], query).is_some())
+    {
+        let start = from + offset;
+        let end = start + match_len(&text[start..], query).unwrap_or_default();
+        matches.push((start, end));
+        from = end;
+    }
+    matches
+}
+
+fn match_len(text: &str, query: &str) -> Option<usize> {
+    let mut chars = text.char_indices();
+    for wanted in query.chars() {
+        let (_, found) = chars.next()?;
+        if found != wanted && !found.to_lowercase().eq(wanted.to_lowercase()) {
+            return None;
+        }
+    }
+    Some(chars.next().map_or(text.len(), |(offset, _)| offset))
+}
+
+// The selection usually is a phrase; only its first line fits the bar.
+fn query_from_selection(selection: &str) -> String {
+    selection
+        .trim()
+        .lines()
+        .next()
+        .unwrap_or_default()
+        .to_owned()
+}
+
+impl CodexAgentApp {
+    pub(super) fn find_bar_height(&self) -> f32 {
+        if self.find.open {
+            PROMPT_PREFIX_CHIP_HEIGHT + PROMPT_PREFIX_CHIP_SPACING
+        } else {
+            0.0
+        }
+    }
+
+    pub(super) fn find_focused(&self) -> bool {
+        self.find.open && self.ctx.memory(|mem| mem.has_focus(egui::Id::new(FIND_ID)))
+    }
+
+    /// Opens the find bar, searching for `selection` when there is one.
+    pub(super) fn open_find(&mut self, selection: Option<String>) {
+        if !self.find.open {
+            logging::trace("find bar opened");
+            self.find.open = true;
+            self.invalidate_text_layout();
+            self.resize_for_text();
+        }
+        if let Some(selection) = selection {
+            self.find.query = query_from_selection(&selection);
+            self.find.current = None;
+            self.find_next(true);
+        }
+        self.ctx
+            .memory_mut(|mem| mem.request_focus(egui::Id::new(FIND_ID)));
+    }
+
+    pub(super) fn close_find(&mut self) {
+        if !self.find.open {
+            return;
+        }
+        logging::trace("find bar closed");
+        self.find.open = false;
+        self.find.current = None;
+        self.find.pending_scroll = false;
+        self.pending_input_focus = true;
+        self.invalidate_text_layout();
+        self.resize_for_text();
+    }
+
+    pub(super) fn find_query_changed(&mut self) {
+        self.find.current = None;
+        self.find_next(true);
+    }
+
+    pub(super) fn find_next(&mut self, forward: bool) {
+        let count = self.refresh_find_matches();
+        if count == 0 {
+            self.find.current = None;
+            return;
+        }
+        let index = match (self.find.current, forward) {
+            (Some(index), true) => (index + 1) % count,
+            (Some(index), false) => (index + count - 1) % count,
+            (None, true) => 0,
+            (None, false) => count - 1,
+        };
+        self.find.current = Some(index.min(count - 1));
+        self.find.pending_scroll = true;
+        self.ctx.request_repaint();
+    }
+
+    /// The current match as char indices into the display buffer.
+    pub(super) fn current_find_match(&self) -> Option<(usize, usize)> {
+        let &(start, end) = self.find.matches.get(self.find.current?)?;
+        let buffer = self.output_display_buffer.get(..end)?;
+        let start_char = buffer.get(..start)?.chars().count();
+        Some((start_char, start_char + buffer[start..].chars().count()))
+    }
+
+    pub(super) fn find_status_label(&mut self) -> String {
+        let count = self.refresh_find_matches();
+        match (count, self.find.current) {
+            _ if self.find.query.is_empty() => String::new(),
+            (0, _) => "No matches".to_owned(),
+            (count, Some(index)) if index < count => format!("{} of {}", index + 1, count),
+            (count, _) => format!("{} matches", count),
+        }
+    }
+
+    fn refresh_find_matches(&mut self) -> usize {
+        let key = (self.find.query.clone(), self.output_display_buffer.len());
+        if self.find.searched.as_ref() != Some(&key) {
+            self.find.matches = find_matches(&self.output_display_buffer, &self.find.query);
+            self.find.searched = Some(key);
+        }
+        self.find.matches.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn matches_ignore_case_and_do_not_overlap() {
+        let text = "Aaa aA Ärger ärgern";
+        assert_eq!(find_matches(text, "aa"), [(0, 2), (4, 6)]);
+        let umlauts: Vec<_> = find_matches(text, "är")
+            .into_iter()
+            .map(|(start, end)| &text[start..end])
+            .collect();
+        assert_eq!(umlauts, ["Är", "är"]);
+        assert!(find_matches(text, "").is_empty());
+        assert!(find_matches(text, "ärgernis").is_empty());
+    }
+
+    #[test]
+    fn selections_search_their_first_line() {
+        assert_eq!(query_from_selection("  parse_args\n  more\n"), "parse_args");
+        assert_eq!(query_from_selection("\n\n"), "");
+    }
+}
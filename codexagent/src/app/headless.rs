@@ -0,0 +1,217 @@
+use std::io;
+use std::ptr;
+use std::sync::Arc;
+
+use eframe::egui;
+
+use crate::config::snapshot::Rating;
+use crate::prompt::{ApprovalRequest, PromptBackend};
+use crate::runtime::PowerEvent;
+
+use super::cancel::CancelScope;
+use super::{CodexAgentApp, SecretSubmitDecision};
+
+impl CodexAgentApp {
+    pub fn headless(backend: Arc<dyn PromptBackend>) -> io::Result<Self> {
+        let ctx = egui::Context::default();
+        // Layout needs the fonts that egui only loads on its first frame.
+        let _ = ctx.run(egui::RawInput::default(), |_| {});
+        Self::with_backend(ctx, ptr::null_mut(), backend)
+    }
+
+    pub fn submit_prompt(&mut self, prompt: &str) {
+        self.input = prompt.to_owned();
+        self.submit();
+    }
+
+    pub fn pump_events(&mut self) {
+        self.poll();
+        self.pump_batch();
+        self.merge_locked_typing();
+    }
+
+    pub fn type_text(&mut self, text: &str) {
+        if self.input_locked() {
+            self.buffer_locked_events(&mut vec![egui::Event::Text(text.to_owned())]);
+        } else {
+            self.input.push_str(text);
+            self.mark_draft_changed();
+        }
+    }
+
+    pub fn cancel_prompt(&mut self) {
+        self.cancel(CancelScope::Active);
+    }
+
+    pub fn approve(&mut self) {
+        self.approve_pending();
+    }
+
+    pub fn deny(&mut self) {
+        self.deny_pending();
+    }
+
+    /// Rates a response the way its hover buttons do; the same rating again
+    /// clears it.
+    pub fn rate(&mut self, turn: usize, rating: Rating) {
+        self.rate_response(turn, rating);
+    }
+
+    /// What the secret warning found in the prompt, while it is shown.
+    pub fn secret_warning(&self) -> Option<&'static str> {
+        self.secret_submit_confirmation
+    }
+
+    /// Answers the secret warning with "Send anyway" or "Redact and send",
+    /// optionally with "Don't ask" for the rest of the session.
+    pub fn send_past_secret_warning(&mut self, redact: bool, remember: bool) {
+        let decision = if redact {
+            SecretSubmitDecision::Redact
+        } else {
+            SecretSubmitDecision::Send
+        };
+        self.confirm_secret_submit(decision, remember);
+    }
+
+    pub fn cancel_secret_warning(&mut self) {
+        self.abort_secret_submit();
+    }
+
+    /// Delivers a suspend or resume notification as the power watcher does.
+    pub fn power_event(&mut self, event: PowerEvent) {
+        self.handle_power_event(event);
+    }
+
+    pub fn offers_sleep_retry(&self) -> bool {
+        self.sleep_retry_offered()
+    }
+
+    /// Clicks the retry chip shown after a prompt died during sleep.
+    pub fn retry_interrupted_prompt(&mut self) {
+        self.retry_after_sleep();
+    }
+
+    /// Copies text from inside the app, as the output's copy actions do.
+    pub fn copy_text(&mut self, text: &str) {
+        let ctx = self.ctx.clone();
+        let _ = ctx.run(egui::RawInput::default(), |ctx| {
+            ctx.copy_text(text.to_owned());
+            self.note_own_clipboard_copies(ctx);
+        });
+    }
+
+    /// Delivers copied text the way the clipboard watcher does.
+    pub fn clipboard_changed(&mut self, text: &str) {
+        self.handle_clipboard_text(text.to_owned());
+    }
+
+    pub fn set_clipboard_watch(&mut self, enabled: bool) {
+        self.select_clipboard_watch(enabled);
+    }
+
+    pub fn clipboard_offer(&self) -> Option<String> {
+        self.clipboard_offer_label().map(|(label, _)| label)
+    }
+
+    /// Clicks the "Ask about copied text" chip.
+    pub fn ask_about_copied_text(&mut self) {
+        self.accept_clipboard_offer();
+    }
+
+    pub fn set_persist_transcripts(&mut self, enabled: bool) {
+        self.select_persist_transcripts(enabled);
+    }
+
+    /// Makes the saves that frames and the close request make.
+    pub fn save_session_state(&mut self) {
+        self.save_snapshot();
+        self.autosave_recent_session();
+        self.persist_draft(true);
+    }
+
+    /// Runs the input's focus step for one frame in which egui does or does
+    /// not grant the focus it was asked for.
+    pub fn input_focus_frame(&mut self, granted: bool) {
+        self.settle_input_focus(granted);
+    }
+
+    /// Opens the find bar the way "Search for selection" does.
+    pub fn search_for_selection(&mut self, selection: &str) {
+        self.sync_output_display_buffer();
+        self.open_find(Some(selection.to_owned()));
+    }
+
+    pub fn find_status(&mut self) -> String {
+        self.find_status_label()
+    }
+
+    pub fn clear_transcript(&mut self) {
+        self.request_clear();
+    }
+
+    pub fn clear_needs_confirmation(&self) -> bool {
+        self.clear_confirmation.is_some()
+    }
+
+    pub fn confirm_pending_clear(&mut self) {
+        self.confirm_clear();
+    }
+
+    /// Replaces the notes panel's text as typing into it does.
+    pub fn write_notes(&mut self, text: &str) {
+        self.notes.text = text.to_owned();
+        self.notes.note_edit();
+    }
+
+    pub fn notes_text(&self) -> &str {
+        &self.notes.text
+    }
+
+    pub fn input_text(&self) -> &str {
+        &self.input
+    }
+
+    pub fn shown_input_text(&self) -> String {
+        self.shown_input().into_owned()
+    }
+
+    pub fn transcript_text(&self) -> &str {
+        self.transcript.text()
+    }
+
+    pub fn prompt_ranges(&self) -> &[(usize, usize)] {
+        self.transcript.prompt_ranges()
+    }
+
+    pub fn response_start(&self) -> usize {
+        self.transcript.response_start()
+    }
+
+    pub fn is_busy(&self) -> bool {
+        self.busy
+    }
+
+    pub fn is_cancelling(&self) -> bool {
+        self.cancelling_prompt.is_some()
+    }
+
+    pub fn has_running_process(&self) -> bool {
+        !self
+            .running_prompt
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .is_empty()
+    }
+
+    pub fn wants_input_focus(&self) -> bool {
+        self.pending_input_focus
+    }
+
+    pub fn pending_approval(&self) -> Option<&ApprovalRequest> {
+        self.pending_approval.as_ref()
+    }
+
+    pub fn session_id(&self) -> Option<&str> {
+        self.session_id.as_deref()
+    }
+}
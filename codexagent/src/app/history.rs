@@ -1,4 +1,7 @@
-use crate::config::trim_prompt_history;
+use std::time::{Duration, Instant};
+
+use crate::config::{load_prompt_draft, save_prompt_draft, trim_prompt_history};
+use crate::logging;
 
 use super::{CodexAgentApp, SLASH_COMMANDS, SlashCommand};
 
@@ -6,8 +9,41 @@ const SLASH_COMMAND_PANEL_TOP_SPACING: f32 = 6.0;
 const SLASH_COMMAND_PANEL_ROW_HEIGHT: f32 = 28.0;
 const SLASH_COMMAND_PANEL_ROW_SPACING: f32 = 4.0;
 const SLASH_COMMAND_PANEL_PADDING_Y: f32 = 8.0;
+const DRAFT_SAVE_DELAY: Duration = Duration::from_secs(1);
 
 impl CodexAgentApp {
+    pub(super) fn restore_prompt_draft(&mut self) {
+        match load_prompt_draft() {
+            Ok(draft) if !draft.trim().is_empty() => {
+                logging::trace(format!(
+                    "restoring prompt draft with {} chars",
+                    draft.chars().count()
+                ));
+                self.input = draft;
+                self.invalidate_input_layout();
+            }
+            Ok(_) => {}
+            Err(error) => logging::error(format!("failed to load prompt draft: {}", error)),
+        }
+    }
+
+    pub(super) fn mark_draft_changed(&mut self) {
+        self.draft_changed_at = Some(Instant::now());
+        self.note_warm_codex_activity();
+        self.ctx.request_repaint_after(DRAFT_SAVE_DELAY);
+    }
+
+    pub(super) fn persist_draft(&mut self, force: bool) {
+        let Some(changed_at) = self.draft_changed_at else {
+            return;
+        };
+        if !force && changed_at.elapsed() < DRAFT_SAVE_DELAY {
+            return;
+        }
+        self.draft_changed_at = None;
+        let _ = save_prompt_draft(&self.input);
+    }
+
     pub(super) fn slash_command_query(&self) -> Option<&str> {
         let input = self.input.trim();
         let query = input.strip_prefix('/')?;
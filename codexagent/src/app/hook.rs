@@ -0,0 +1,126 @@
+use std::sync::Arc;
+use std::thread;
+use std::time::Instant;
+
+use crate::config::load_on_complete_hook;
+use crate::config::project::ProjectConfig;
+use crate::events::AppEvent;
+use crate::logging;
+use crate::notify;
+use crate::prompt::run_hook;
+
+use super::CodexAgentApp;
+use super::transcript::SegmentKind;
+
+const HOOK_OUTPUT_MAX_LINES: usize = 200;
+
+impl CodexAgentApp {
+    pub(super) fn completion_hook(&self) -> Option<String> {
+        let project_hook = self
+            .project_config_enabled
+            .then(|| {
+                self.project_config
+                    .config()
+                    .and_then(ProjectConfig::on_complete)
+            })
+            .flatten();
+        if let Some(command) = project_hook {
+            return Some(command.to_owned());
+        }
+        match load_on_complete_hook() {
+            Ok(command) => command,
+            Err(error) => {
+                logging::error(format!("failed to read on_complete setting: {}", error));
+                None
+            }
+        }
+    }
+
+    pub(super) fn start_completion_hook(&mut self, command: String) {
+        let prompt_id = self.next_prompt_id;
+        self.next_prompt_id += 1;
+        self.busy = true;
+        self.locked = true;
+        self.active_prompt_id = Some(prompt_id);
+        self.pending_started_at = Some(Instant::now());
+        self.hook_output_lines = Some(0);
+        self.ensure_output_spacing();
+        self.transcript.push_line(
+            SegmentKind::Notice,
+            '\x1C',
+            &format!("Running hook: {}", command),
+        );
+        self.refresh_after_output_change();
+
+        let tx = self.tx.clone();
+        let ctx = self.repaint_handle();
+        let running_prompt = Arc::clone(&self.running_prompt);
+        thread::spawn(move || {
+            let result = match logging::catch_panic("hook worker thread", || {
+                run_hook(prompt_id, &command, running_prompt, &tx, &ctx)
+                    .map_err(|error| error.to_string())
+            }) {
+                Ok(result) => result,
+                Err(message) => Err(message),
+            };
+            if tx.send(AppEvent::HookDone(prompt_id, result)).is_err() {
+                logging::error("failed to deliver hook result to app");
+            }
+            ctx.request_repaint();
+        });
+    }
+
+    pub(super) fn append_hook_output(&mut self, prompt_id: u64, line: String) {
+        if self.active_prompt_id != Some(prompt_id) {
+            return;
+        }
+        let Some(lines) = self.hook_output_lines.as_mut() else {
+            return;
+        };
+        *lines += 1;
+        if *lines > HOOK_OUTPUT_MAX_LINES {
+            return;
+        }
+        self.transcript
+            .push_line(SegmentKind::Notice, '\x1C', &line);
+        self.refresh_after_output_change();
+    }
+
+    pub(super) fn finish_completion_hook(&mut self, prompt_id: u64, result: Result<i32, String>) {
+        if self.active_prompt_id != Some(prompt_id) {
+            return;
+        }
+        let lines = self.hook_output_lines.take().unwrap_or_default();
+        if lines > HOOK_OUTPUT_MAX_LINES {
+            self.transcript.push(
+                SegmentKind::Notice,
+                &format!("\x1C… {} more lines\n", lines - HOOK_OUTPUT_MAX_LINES),
+            );
+        }
+        match result {
+            Ok(code) => {
+                logging::trace(format!("completion hook finished with exit code {}", code));
+                self.transcript.push(
+                    SegmentKind::Notice,
+                    &format!("\x1CHook exited with code {}", code),
+                );
+            }
+            Err(error) => {
+                logging::error(format!("completion hook failed: {}", error));
+                self.transcript
+                    .push(SegmentKind::Error, &format!("\x1DHook failed: {}", error));
+            }
+        }
+        self.busy = false;
+        self.locked = false;
+        self.active_prompt_id = None;
+        self.pending_started_at = None;
+        self.pending_input_focus = true;
+        self.clear_render_buffer();
+        self.persist_history();
+        self.refresh_after_output_change();
+        if self.notifications_enabled {
+            notify::prompt_completed(self.hwnd);
+        }
+    }
+}
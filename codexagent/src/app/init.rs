@@ -2,23 +2,79 @@ use std::ffi::c_void;
 use std::io;
 use std::sync::{Arc, Mutex, atomic::AtomicBool, mpsc};
 
+use eframe::egui;
 use raw_window_handle::{HasWindowHandle, RawWindowHandle};
 
+use super::shortcut_map::{ShortcutMap, load_shortcuts};
+use crate::config::project::ProjectConfigCache;
 use crate::config::{
-    DEFAULT_NOTIFICATIONS_ENABLED, LINE_HEIGHT, PromptHistory, load_notifications_enabled,
-    load_prompt_history, save_prompt_history,
+    APP_DISPLAY_NAME, DEFAULT_AUTO_CONTINUE, DEFAULT_CANCEL_ON_SLEEP, DEFAULT_CLIPBOARD_WATCH,
+    DEFAULT_CODEX_TITLES, DEFAULT_COMPACT_MODE, DEFAULT_CONCISE_ANSWERS,
+    DEFAULT_DIRTY_TREE_WARNING_ENABLED, DEFAULT_DISK_SPACE_WARNING_MB,
+    DEFAULT_EXCLUDE_FROM_CAPTURE, DEFAULT_INPUT_ASSIST_ENABLED, DEFAULT_INSPECT_EVENTS,
+    DEFAULT_LAUNCHER_MODE, DEFAULT_NOTIFICATIONS_ENABLED, DEFAULT_PENDING_ANIMATION_INTERVAL,
+    DEFAULT_PENDING_STYLE, DEFAULT_PERSIST_TRANSCRIPTS, DEFAULT_PROJECT_CONFIG_ENABLED,
+    DEFAULT_PROMPT_ATTACH_LIMIT, DEFAULT_PROMPT_WHITESPACE, DEFAULT_RECENT_SESSION_LIMIT,
+    DEFAULT_REDACT_SECRETS, DEFAULT_RESPONSE_DIMMING, DEFAULT_RESPONSE_TRUNCATE_LIMIT,
+    DEFAULT_SUBMIT_KEY, DEFAULT_SUGGESTIONS_ENABLED, DEFAULT_THEME, DEFAULT_TOKEN_WARNING_LIMIT,
+    DEFAULT_TOPIC_SUGGESTIONS_ENABLED, DEFAULT_WAKE_MODE, DEFAULT_WARM_START,
+    DEFAULT_WINDOW_SHADOW, InputAssist, LINE_HEIGHT, PromptHistory, load_auto_continue,
+    load_cancel_on_sleep, load_clipboard_watch, load_codex_titles, load_compact_mode,
+    load_concise_answers, load_dirty_tree_warning_enabled, load_disk_space_warning_mb,
+    load_exclude_from_capture, load_input_assist, load_inspect_events, load_launcher_mode,
+    load_notifications_enabled, load_onboarding_pending, load_output_processors,
+    load_pending_interval, load_pending_style, load_persist_transcripts,
+    load_pinned_response_language, load_project_config_enabled, load_prompt_attach_limit,
+    load_prompt_history, load_prompt_transformers, load_prompt_whitespace,
+    load_recent_session_limit, load_redact_patterns, load_redact_secrets, load_response_dimming,
+    load_response_truncate_limit, load_stream_rate, load_submit_key, load_suggestions_enabled,
+    load_theme, load_token_warning_limit, load_topic_suggestions_enabled, load_ui_trace,
+    load_wake_mode, load_warm_start, load_window_shadow, save_prompt_history,
+    take_settings_recovery_notice,
 };
+use crate::events::RepaintHandle;
 use crate::logging;
-use crate::prompt::PromptStreamState;
-use crate::runtime::{available_models, current_cwd_text, current_model, set_window_app_id};
+use crate::prompt::{
+    CodexBackend, EventLog, PromptBackend, PromptPhase, PromptStreamState, RateLimitBackoff,
+    RunningPrompts,
+};
+use crate::redact;
+use crate::runtime::{
+    CodexProfiles, ProbeService, accessibility_prefs, available_models, check_seeded_codex_files,
+    current_cwd_text, current_model, environment_report, set_window_app_id, system_light_theme,
+    take_launch_batch, watch_accessibility_prefs, watch_power_events,
+};
+use crate::transform::TRANSFORMERS;
+use crate::usage::SessionUsage;
 
-use super::{CodexAgentApp, ContextMenuState, SetupState};
+use super::disk_space::DiskSpaceWarning;
+use super::find::FindBar;
+use super::layout::ResizeWrapWidth;
+use super::notes::SessionNotes;
+use super::throughput::StreamThroughput;
+use super::title::SessionTitle;
+use super::transcript::Transcript;
+use super::{CodexAgentApp, ContextMenuState, SetupState, ui_trace};
 
 impl CodexAgentApp {
     pub(super) const INPUT_ID: &'static str = "prompt-input";
 
-    pub(crate) fn new(cc: &eframe::CreationContext<'_>) -> io::Result<Self> {
+    pub fn new(cc: &eframe::CreationContext<'_>) -> io::Result<Self> {
+        let hwnd = creation_hwnd(cc);
+        set_window_app_id(hwnd);
+        Self::with_backend(cc.egui_ctx.clone(), hwnd, Arc::new(CodexBackend))
+    }
+
+    pub(crate) fn with_backend(
+        ctx: egui::Context,
+        hwnd: *mut c_void,
+        backend: Arc<dyn PromptBackend>,
+    ) -> io::Result<Self> {
         let (tx, rx) = mpsc::channel();
+        let probes = ProbeService::spawn(tx.clone(), {
+            let ctx = ctx.clone();
+            RepaintHandle::new(move || ctx.request_repaint())
+        });
         let history = match load_prompt_history() {
             Ok(history) => history,
             Err(error) => {
@@ -29,7 +85,23 @@ impl CodexAgentApp {
         if let Err(error) = save_prompt_history(&history) {
             logging::error(format!("failed to sanitize prompt history: {}", error));
         }
+        let onboarding_pending = match load_onboarding_pending() {
+            Ok(pending) => pending,
+            Err(error) => {
+                logging::error(format!("failed to load onboarding state: {}", error));
+                false
+            }
+        };
         logging::trace("app created");
+        ui_trace::configure(load_ui_trace().unwrap_or_else(|error| {
+            logging::error(format!("failed to load ui trace setting: {}", error));
+            false
+        }));
+        let show_stream_rate = ui_trace::enabled()
+            || load_stream_rate().unwrap_or_else(|error| {
+                logging::error(format!("failed to load stream rate setting: {}", error));
+                false
+            });
         let notifications_enabled = match load_notifications_enabled() {
             Ok(enabled) => enabled,
             Err(error) => {
@@ -37,17 +109,283 @@ impl CodexAgentApp {
                 DEFAULT_NOTIFICATIONS_ENABLED
             }
         };
+        let project_config_enabled = match load_project_config_enabled() {
+            Ok(enabled) => enabled,
+            Err(error) => {
+                logging::error(format!("failed to load project config setting: {}", error));
+                DEFAULT_PROJECT_CONFIG_ENABLED
+            }
+        };
+        let dirty_tree_warning_enabled = match load_dirty_tree_warning_enabled() {
+            Ok(enabled) => enabled,
+            Err(error) => {
+                logging::error(format!(
+                    "failed to load dirty tree warning setting: {}",
+                    error
+                ));
+                DEFAULT_DIRTY_TREE_WARNING_ENABLED
+            }
+        };
+        let persist_transcripts = load_persist_transcripts().unwrap_or_else(|error| {
+            logging::error(format!(
+                "failed to load transcript persistence setting: {}",
+                error
+            ));
+            DEFAULT_PERSIST_TRANSCRIPTS
+        });
+        let recent_session_limit = load_recent_session_limit().unwrap_or_else(|error| {
+            logging::error(format!("failed to load recent session limit: {}", error));
+            DEFAULT_RECENT_SESSION_LIMIT
+        });
+        let disk_space_warning_mb = load_disk_space_warning_mb().unwrap_or_else(|error| {
+            logging::error(format!(
+                "failed to load disk space warning threshold: {}",
+                error
+            ));
+            DEFAULT_DISK_SPACE_WARNING_MB
+        });
+        let inspect_events = load_inspect_events().unwrap_or_else(|error| {
+            logging::error(format!("failed to load inspect events setting: {}", error));
+            DEFAULT_INSPECT_EVENTS
+        });
+        let suggestions_enabled = match load_suggestions_enabled() {
+            Ok(enabled) => enabled,
+            Err(error) => {
+                logging::error(format!("failed to load suggestions setting: {}", error));
+                DEFAULT_SUGGESTIONS_ENABLED
+            }
+        };
+        let auto_continue = match load_auto_continue() {
+            Ok(enabled) => enabled,
+            Err(error) => {
+                logging::error(format!("failed to load auto continue setting: {}", error));
+                DEFAULT_AUTO_CONTINUE
+            }
+        };
+        let cancel_on_sleep = load_cancel_on_sleep().unwrap_or_else(|error| {
+            logging::error(format!("failed to load cancel on sleep setting: {}", error));
+            DEFAULT_CANCEL_ON_SLEEP
+        });
+        let clipboard_watch = load_clipboard_watch().unwrap_or_else(|error| {
+            logging::error(format!(
+                "failed to load clipboard quick action setting: {}",
+                error
+            ));
+            DEFAULT_CLIPBOARD_WATCH
+        });
+        let codex_titles = load_codex_titles().unwrap_or_else(|error| {
+            logging::error(format!("failed to load codex titles setting: {}", error));
+            DEFAULT_CODEX_TITLES
+        });
+        let warm_start = load_warm_start().unwrap_or_else(|error| {
+            logging::error(format!("failed to load warm start setting: {}", error));
+            DEFAULT_WARM_START
+        });
+        let topic_suggestions_enabled = match load_topic_suggestions_enabled() {
+            Ok(enabled) => enabled,
+            Err(error) => {
+                logging::error(format!(
+                    "failed to load topic suggestions setting: {}",
+                    error
+                ));
+                DEFAULT_TOPIC_SUGGESTIONS_ENABLED
+            }
+        };
+        let input_assists = InputAssist::ALL
+            .into_iter()
+            .filter(|assist| match load_input_assist(*assist) {
+                Ok(enabled) => enabled,
+                Err(error) => {
+                    logging::error(format!(
+                        "failed to load {} setting: {}",
+                        assist.label().to_ascii_lowercase(),
+                        error
+                    ));
+                    DEFAULT_INPUT_ASSIST_ENABLED
+                }
+            })
+            .collect();
+        let wake_mode = match load_wake_mode() {
+            Ok(mode) => mode,
+            Err(error) => {
+                logging::error(format!("failed to load keep awake setting: {}", error));
+                DEFAULT_WAKE_MODE
+            }
+        };
+        let pending_style = match load_pending_style() {
+            Ok(style) => style,
+            Err(error) => {
+                logging::error(format!(
+                    "failed to load pending animation setting: {}",
+                    error
+                ));
+                DEFAULT_PENDING_STYLE
+            }
+        };
+        let pending_interval = match load_pending_interval() {
+            Ok(interval) => interval,
+            Err(error) => {
+                logging::error(format!(
+                    "failed to load pending animation interval: {}",
+                    error
+                ));
+                DEFAULT_PENDING_ANIMATION_INTERVAL
+            }
+        };
+        let theme = match load_theme() {
+            Ok(theme) => theme,
+            Err(error) => {
+                logging::error(format!("failed to load theme setting: {}", error));
+                DEFAULT_THEME
+            }
+        };
+        let response_dimming = match load_response_dimming() {
+            Ok(dimming) => dimming,
+            Err(error) => {
+                logging::error(format!(
+                    "failed to load old response dimming setting: {}",
+                    error
+                ));
+                DEFAULT_RESPONSE_DIMMING
+            }
+        };
+        let redact_secrets = match load_redact_secrets() {
+            Ok(enabled) => enabled,
+            Err(error) => {
+                logging::error(format!(
+                    "failed to load secret redaction setting: {}",
+                    error
+                ));
+                DEFAULT_REDACT_SECRETS
+            }
+        };
+        let redact_patterns = load_redact_patterns().unwrap_or_else(|error| {
+            logging::error(format!("failed to load redaction patterns: {}", error));
+            Vec::new()
+        });
+        redact::configure(redact_secrets, redact_patterns);
+        let prompt_transformers = load_prompt_transformers().unwrap_or_else(|error| {
+            logging::error(format!(
+                "failed to load prompt transform settings: {}",
+                error
+            ));
+            TRANSFORMERS.to_vec()
+        });
+        let output_processors = load_output_processors().unwrap_or_else(|error| {
+            logging::error(format!(
+                "failed to load output post-processing settings: {}",
+                error
+            ));
+            Vec::new()
+        });
+        let launcher_mode = match load_launcher_mode() {
+            Ok(mode) => mode,
+            Err(error) => {
+                logging::error(format!("failed to load codex launcher setting: {}", error));
+                DEFAULT_LAUNCHER_MODE
+            }
+        };
+        let compact_mode = match load_compact_mode() {
+            Ok(enabled) => enabled,
+            Err(error) => {
+                logging::error(format!("failed to load compact mode setting: {}", error));
+                DEFAULT_COMPACT_MODE
+            }
+        };
+        let exclude_from_capture = match load_exclude_from_capture() {
+            Ok(enabled) => enabled,
+            Err(error) => {
+                logging::error(format!(
+                    "failed to load screen capture exclusion setting: {}",
+                    error
+                ));
+                DEFAULT_EXCLUDE_FROM_CAPTURE
+            }
+        };
+        let shortcuts = match load_shortcuts() {
+            Ok(shortcuts) => shortcuts,
+            Err(error) => {
+                logging::error(format!("failed to load shortcut settings: {}", error));
+                ShortcutMap::default()
+            }
+        };
+        let submit_key = match load_submit_key() {
+            Ok(key) => key,
+            Err(error) => {
+                logging::error(format!("failed to load submit key setting: {}", error));
+                DEFAULT_SUBMIT_KEY
+            }
+        };
+        let prompt_whitespace = match load_prompt_whitespace() {
+            Ok(mode) => mode,
+            Err(error) => {
+                logging::error(format!(
+                    "failed to load prompt whitespace setting: {}",
+                    error
+                ));
+                DEFAULT_PROMPT_WHITESPACE
+            }
+        };
+        let token_warning_limit = match load_token_warning_limit() {
+            Ok(limit) => limit,
+            Err(error) => {
+                logging::error(format!("failed to load token warning setting: {}", error));
+                DEFAULT_TOKEN_WARNING_LIMIT
+            }
+        };
+        let response_truncate_limit = match load_response_truncate_limit() {
+            Ok(limit) => limit,
+            Err(error) => {
+                logging::error(format!(
+                    "failed to load response truncation setting: {}",
+                    error
+                ));
+                DEFAULT_RESPONSE_TRUNCATE_LIMIT
+            }
+        };
+        let prompt_attach_limit = match load_prompt_attach_limit() {
+            Ok(limit) => limit,
+            Err(error) => {
+                logging::error(format!("failed to load prompt attach setting: {}", error));
+                DEFAULT_PROMPT_ATTACH_LIMIT
+            }
+        };
+        let concise_default = load_concise_answers().unwrap_or_else(|error| {
+            logging::error(format!("failed to load concise answers setting: {}", error));
+            DEFAULT_CONCISE_ANSWERS
+        });
+        let pinned_response_language = load_pinned_response_language().unwrap_or_else(|error| {
+            logging::error(format!(
+                "failed to load response language setting: {}",
+                error
+            ));
+            None
+        });
+        let window_shadow = match load_window_shadow() {
+            Ok(shadow) => shadow,
+            Err(error) => {
+                logging::error(format!("failed to load window shadow setting: {}", error));
+                DEFAULT_WINDOW_SHADOW
+            }
+        };
         let current_model = current_model();
         let model_options = available_models(&current_model);
-        Ok(Self {
+        let mut app = Self {
             input: String::new(),
             prompt_history: history.prompts,
             prompt_history_index: None,
             prompt_history_draft: None,
-            output_base: 0,
-            output: String::new(),
+            draft_changed_at: None,
+            last_submitted_prompt: None,
+            input_tokens: None,
+            token_warning_limit,
+            token_submit_confirmation: None,
+            transcript: Transcript::default(),
             current_model,
             model_options,
+            codex_profiles: CodexProfiles::default(),
+            codex_profiles_modified: None,
+            codex_profile: None,
             notifications_enabled,
             context_menu_state: ContextMenuState::Checking,
             context_menu_refresh_pending: false,
@@ -55,45 +393,186 @@ impl CodexAgentApp {
             output_display_buffer: String::new(),
             output_display_prompt_ranges: Vec::new(),
             output_display_line_kinds: Vec::new(),
+            output_display_folds: Vec::new(),
+            expanded_folds: std::collections::HashSet::new(),
+            response_truncate_limit,
+            prompt_attach_limit,
+            prompt_attachments: Vec::new(),
+            response_language: pinned_response_language.clone(),
+            pinned_response_language,
+            concise: concise_default,
+            concise_default,
+            error_focus: None,
+            pending_error_scroll: false,
             output_display_response_start: 0,
             output_display_response_chars: 0,
             output_display_base_len: 0,
             output_display_source_len: 0,
             output_display_can_append: false,
+            #[cfg(debug_assertions)]
+            output_display_generation: 0,
+            #[cfg(debug_assertions)]
+            output_display_diverged_generation: None,
             output_display_dirty: true,
             output_display_busy: false,
             output_galley: None,
+            retired_output_galleys: Vec::new(),
+            output_layout_scratch: None,
             output_galley_width: None,
             output_separator_y: None,
+            output_turn_markers: Vec::new(),
+            output_edit_id: None,
+            output_prompt_rects: Vec::new(),
+            output_pin_rects: Vec::new(),
+            output_response_ends: Vec::new(),
+            copied_prompt: None,
             input_galley: None,
             input_galley_width: None,
             cwd_text: current_cwd_text(),
+            cwd_problem: None,
             output_rows_cache: 0,
             input_rows_cache: 1,
             output_height_cache: 0.0,
             input_height_cache: LINE_HEIGHT,
             display_rows_width: None,
+            output_scrollbar_width: 0.0,
+            resize_wrap_width: ResizeWrapWidth::default(),
             text_layout_dirty: true,
             render_step: None,
-            prompt_ranges: Vec::new(),
             busy: false,
             locked: false,
+            locked_input: String::new(),
             next_prompt_id: 1,
             active_prompt_id: None,
             pending_input_focus: true,
+            input_focus_retries: 0,
+            ime_composing: false,
             picker_selection: None,
             settings_menu_open: false,
+            output_menu_pos: None,
+            codex_file_editor: None,
+            codex_file_updates: check_seeded_codex_files(),
+            codex_file_update_review: false,
+            environment_notice: environment_report()
+                .degraded
+                .iter()
+                .cloned()
+                .chain(take_settings_recovery_notice())
+                .collect(),
+            probes,
+            onboarding: None,
+            dirty_tree_warning_enabled,
+            suggestions_enabled,
+            inspect_events,
+            persist_transcripts,
+            event_log: Arc::new(Mutex::new(EventLog::default())),
+            event_inspector: None,
+            recent_session: None,
+            recent_sessions: None,
+            recent_session_limit,
+            notes: SessionNotes::default(),
+            find: FindBar::default(),
+            disk_space: DiskSpaceWarning::default(),
+            disk_space_warning_mb,
+            follow_up_suggestions: Vec::new(),
+            auto_continue,
+            cancel_on_sleep,
+            power_watcher: None,
+            clipboard_watch,
+            clipboard_watcher: None,
+            clipboard_offer: None,
+            warm_start,
+            warm_codex: None,
+            own_clipboard_text: None,
+            sleeping_prompt: None,
+            sleep_killed_prompt: None,
+            sleep_retry_prompt: None,
+            tool_retry: None,
+            continuation_offered: false,
+            continuations_used: 0,
+            continued_response_start: None,
+            input_assists,
+            paste_fence_offered: false,
+            topic_suggestions_enabled,
+            topic_shift_suggested: false,
+            topic_shift_dismissed: false,
+            topic_terms: None,
+            changed_files: Vec::new(),
+            changed_files_open: false,
+            pending_approval: None,
+            log_health: logging::health(),
+            diagnostics_pending: false,
+            last_request: None,
+            rate_limit_backoff: RateLimitBackoff::default(),
+            rate_limit_wait: None,
+            compare_model: None,
+            comparison: None,
+            last_response_range: None,
+            retry_baseline: None,
+            response_diff: None,
+            wake_mode,
+            pending_style,
+            pending_interval,
+            show_stream_rate,
+            stream_throughput: StreamThroughput::default(),
+            turn_stats: Vec::new(),
+            theme,
+            response_dimming,
+            accessibility: accessibility_prefs(),
+            system_light_theme: system_light_theme(),
+            launcher_mode,
+            redact_secrets,
+            prompt_transformers,
+            output_processors,
+            compact_mode,
+            exclude_from_capture,
+            capture_exclusion_error: None,
+            sandbox_mode: None,
+            status_strip: None,
+            submit_key,
+            prompt_whitespace,
+            shortcuts,
+            shortcut_capture: None,
+            triggered_shortcut: None,
+            window_shadow,
+            dirty_tree_warning_suppressed: false,
+            dirty_submit_confirmation: None,
+            dirty_submit_confirmed: false,
+            project_trust_confirmation: false,
+            secret_submit_confirmation: None,
+            clear_confirmation: None,
+            secret_submit_decision: None,
+            secret_warning_suppressed: false,
+            project_config: ProjectConfigCache::default(),
+            project_config_enabled,
+            hook_output_lines: None,
+            cancelling_prompt: None,
+            batch: None,
+            prompt_phase: PromptPhase::Waiting,
+            resume_retry_pending: false,
+            session_usage: SessionUsage::default(),
+            snapshot_len: 0,
+            snapshot_saved_at: None,
+            relaunch_snapshot_len: 0,
+            relaunch_snapshot_at: None,
             was_focused: false,
             drag_armed: false,
             window_dragging: false,
             was_minimized: false,
+            last_monitor_size: None,
+            last_pixels_per_point: None,
+            last_bounds_check: None,
             maximized: false,
             resizing: false,
+            mouse_passthrough: false,
             resize_start_inner_size: None,
             user_height_override: None,
             min_inner_size: None,
             last_inner_size: None,
+            pending_resize: None,
+            resize_sent_at: None,
             last_outer_size: None,
+            #[cfg(target_os = "windows")]
             last_viewport_outer_rect: None,
             pre_maximize_state: None,
             tiled_state: None,
@@ -101,30 +580,53 @@ impl CodexAgentApp {
             minimized_restore_state: None,
             minimized_monitor: None,
             pending_started_at: None,
-            ctx: cc.egui_ctx.clone(),
+            ctx,
             tx,
             rx,
-            running_prompt: Arc::new(Mutex::new(None)),
+            backend,
+            running_prompt: Arc::new(Mutex::new(RunningPrompts::default())),
             shared_stream: Arc::new(Mutex::new(PromptStreamState::default())),
             stream_notification_pending: Arc::new(AtomicBool::new(false)),
             stream_generation: 0,
             stream_visible_len: 0,
+            stream_events_received: 0,
+            stream_mutations_applied: 0,
             session_id: None,
+            dry_run: false,
             cancelled_resume_context: None,
             setup_state: SetupState::Ready,
             install_stdin: Arc::new(Mutex::new(None)),
             positioned: false,
-            title_set: false,
-            hwnd: {
-                let hwnd = creation_hwnd(cc);
-                set_window_app_id(hwnd);
-                hwnd
-            },
-        })
+            session_title: SessionTitle::default(),
+            codex_titles,
+            window_title: APP_DISPLAY_NAME.to_owned(),
+            hwnd,
+        };
+        app.refresh_project_config();
+        app.refresh_sandbox_mode();
+        app.refresh_codex_profiles();
+        app.apply_capture_exclusion();
+        watch_accessibility_prefs(app.tx.clone(), app.repaint_handle());
+        app.power_watcher = watch_power_events(app.tx.clone(), app.repaint_handle());
+        app.sync_clipboard_watcher();
+        app.watch_log_health();
+        app.start_frame_watchdog();
+        if !app.restore_recovery_handoff() {
+            app.restore_snapshot();
+            app.restore_prompt_draft();
+        }
+        if onboarding_pending {
+            app.start_onboarding();
+        }
+        if let Some(path) = take_launch_batch() {
+            app.start_launch_batch(path);
+        }
+        Ok(app)
     }
 
     pub(super) fn refresh_current_model(&mut self) {
         self.current_model = current_model();
+        self.refresh_sandbox_mode();
     }
 
     pub(super) fn refresh_model_options(&mut self) {
@@ -0,0 +1,385 @@
+use eframe::egui::{self, Event, Key, Modifiers, TextEdit};
+
+use crate::config::{InputAssist, SubmitKey, set_input_assist};
+use crate::logging;
+use crate::prompt::normalize_line_endings;
+
+use super::CodexAgentApp;
+
+const CODE_FENCE: &str = "```";
+const FENCE_TAB_WIDTH: usize = 4;
+const FENCE_MIN_RUN: usize = 3;
+const FENCE_MAX_INDENT: usize = 3;
+
+fn is_fence_line(line: &str) -> bool {
+    line.trim_start().starts_with(CODE_FENCE)
+}
+
+fn byte_index(text: &str, char_index: usize) -> usize {
+    text.char_indices()
+        .nth(char_index)
+        .map_or(text.len(), |(index, _)| index)
+}
+
+fn line_start(text: &str) -> usize {
+    text.rfind('\n').map_or(0, |index| index + 1)
+}
+
+fn fence_marker(line: &str) -> Option<(char, usize, &str)> {
+    let rest = line.trim_start_matches(' ');
+    if line.len() - rest.len() > FENCE_MAX_INDENT {
+        return None;
+    }
+    let marker = rest.chars().next().filter(|c| matches!(c, '`' | '~'))?;
+    let run = rest.chars().take_while(|c| *c == marker).count();
+    (run >= FENCE_MIN_RUN).then(|| (marker, run, &rest[run..]))
+}
+
+pub(super) fn open_fence(text: &str) -> bool {
+    let mut open: Option<(char, usize)> = None;
+    for line in text.lines() {
+        let Some((marker, run, info)) = fence_marker(line) else {
+            continue;
+        };
+        open = match open {
+            None if marker == '`' && info.contains('`') => None,
+            None => Some((marker, run)),
+            Some((open_marker, open_run))
+                if marker == open_marker && run >= open_run && info.trim().is_empty() =>
+            {
+                None
+            }
+            still_open => still_open,
+        };
+    }
+    open.is_some()
+}
+
+fn enter_adds_newline(text: &str, cursor: usize) -> bool {
+    open_fence(&text[..byte_index(text, cursor)])
+}
+
+fn inside_fence(text: &str, cursor: usize) -> bool {
+    let before = &text[..byte_index(text, cursor)];
+    open_fence(&before[..line_start(before)])
+}
+
+fn close_fence(text: &str, cursor: usize) -> Option<String> {
+    let (before, after) = text.split_at(byte_index(text, cursor));
+    let start = line_start(before);
+    if &before[start..] != CODE_FENCE
+        || after.lines().next().is_some_and(|line| !line.is_empty())
+        || open_fence(&before[..start])
+        || !open_fence(text)
+    {
+        return None;
+    }
+    Some(format!("{}\n{}{}", before, CODE_FENCE, after))
+}
+
+fn indent_in_fence(text: &str, start: usize, end: usize) -> Option<(String, usize)> {
+    if !inside_fence(text, start) {
+        return None;
+    }
+    let (start, end) = (byte_index(text, start), byte_index(text, end));
+    let before = &text[..start];
+    let column = before[line_start(before)..].chars().count();
+    let width = FENCE_TAB_WIDTH - column % FENCE_TAB_WIDTH;
+    let cursor = before.chars().count() + width;
+    Some((
+        format!("{}{}{}", before, " ".repeat(width), &text[end..]),
+        cursor,
+    ))
+}
+
+pub(super) fn wrap_in_fence(text: &str) -> String {
+    let longest = text
+        .lines()
+        .map(|line| line.trim_start().chars().take_while(|c| *c == '`').count())
+        .max()
+        .unwrap_or(0);
+    let fence = "`".repeat(longest.max(2) + 1);
+    format!(
+        "{}\n{}\n{}",
+        fence,
+        text.trim_end_matches(['\n', '\r']),
+        fence
+    )
+}
+
+fn typed_backtick(events: &[Event]) -> bool {
+    events
+        .iter()
+        .any(|event| matches!(event, Event::Text(text) if text.ends_with('`')))
+}
+
+fn pasted_multiline(events: &[Event]) -> bool {
+    events.iter().any(|event| {
+        matches!(event, Event::Paste(text)
+            if text.trim().contains('\n') && !text.lines().any(is_fence_line))
+    })
+}
+
+impl CodexAgentApp {
+    pub(super) fn input_assist_enabled(&self, assist: InputAssist) -> bool {
+        self.input_assists.contains(&assist)
+    }
+
+    pub(super) fn select_input_assist(&mut self, assist: InputAssist, enabled: bool) {
+        self.clear_picker_selection();
+        if self.input_assist_enabled(assist) == enabled {
+            return;
+        }
+        match set_input_assist(assist, enabled) {
+            Ok(enabled) => {
+                self.input_assists.retain(|active| *active != assist);
+                if enabled {
+                    self.input_assists.push(assist);
+                } else if assist == InputAssist::PasteFence {
+                    self.paste_fence_offered = false;
+                }
+                self.push_settings_output(&format!(
+                    "{} set to {}",
+                    assist.label(),
+                    if enabled { "On" } else { "Off" }
+                ));
+                self.finish_local_success();
+            }
+            Err(error) => {
+                logging::error(format!(
+                    "failed to set {} {}: {}",
+                    assist.label().to_ascii_lowercase(),
+                    if enabled { "on" } else { "off" },
+                    error
+                ));
+                self.push_local_error(&format!(
+                    "Failed to set {}: {}",
+                    assist.label().to_ascii_lowercase(),
+                    error
+                ));
+            }
+        }
+        self.finish_local_change();
+    }
+
+    fn input_selection(&self) -> Option<(usize, usize)> {
+        let range = TextEdit::load_state(&self.ctx, egui::Id::new(Self::INPUT_ID))?
+            .cursor
+            .char_range()?;
+        let (primary, secondary) = (range.primary.index, range.secondary.index);
+        Some((primary.min(secondary), primary.max(secondary)))
+    }
+
+    pub(super) fn store_input_cursor(&self, index: usize) {
+        let id = egui::Id::new(Self::INPUT_ID);
+        let mut state = TextEdit::load_state(&self.ctx, id).unwrap_or_default();
+        state
+            .cursor
+            .set_char_range(Some(egui::text::CCursorRange::one(
+                egui::text::CCursor::new(index),
+            )));
+        state.store(&self.ctx, id);
+    }
+
+    pub(super) fn input_tab_indents(&self) -> bool {
+        self.input_assist_enabled(InputAssist::FenceTab)
+            && !self.locked
+            && self
+                .ctx
+                .memory(|mem| mem.has_focus(egui::Id::new(Self::INPUT_ID)))
+            && self
+                .input_selection()
+                .is_some_and(|(start, _)| inside_fence(&self.input, start))
+    }
+
+    pub(super) fn enter_in_fence(&self) -> bool {
+        self.submit_key == SubmitKey::Enter
+            && !self.locked
+            && self
+                .ctx
+                .memory(|mem| mem.has_focus(egui::Id::new(Self::INPUT_ID)))
+            && self
+                .input_selection()
+                .is_some_and(|(start, _)| enter_adds_newline(&self.input, start))
+    }
+
+    pub(super) fn indent_input_in_fence(&mut self) {
+        if !self
+            .ctx
+            .input_mut(|input| input.consume_key(Modifiers::NONE, Key::Tab))
+        {
+            return;
+        }
+        let Some((start, end)) = self.input_selection() else {
+            return;
+        };
+        let Some((input, cursor)) = indent_in_fence(&self.input, start, end) else {
+            return;
+        };
+        self.input = input;
+        self.store_input_cursor(cursor);
+        self.paste_fence_offered = false;
+        self.mark_draft_changed();
+        self.refresh_after_text_change();
+    }
+
+    pub(super) fn normalize_pasted_line_endings(&self) {
+        if !self.input_assist_enabled(InputAssist::PasteLineEndings) {
+            return;
+        }
+        self.ctx.input_mut(|input| {
+            for event in &mut input.events {
+                if let Event::Paste(text) = event
+                    && text.contains('\r')
+                {
+                    *text = normalize_line_endings(text).into_owned();
+                }
+            }
+        });
+    }
+
+    pub(super) fn apply_input_assists(&mut self, input_was_empty: bool) {
+        let (typed_fence, pasted) = self.ctx.input(|input| {
+            (
+                typed_backtick(&input.events),
+                pasted_multiline(&input.events),
+            )
+        });
+        self.paste_fence_offered =
+            pasted && input_was_empty && self.input_assist_enabled(InputAssist::PasteFence);
+        if !typed_fence || !self.input_assist_enabled(InputAssist::FenceAutoClose) {
+            return;
+        }
+        let closed = self
+            .input_selection()
+            .filter(|(start, end)| start == end)
+            .and_then(|(cursor, _)| close_fence(&self.input, cursor));
+        if let Some(input) = closed {
+            self.input = input;
+            self.ctx.request_repaint();
+        }
+    }
+
+    pub(super) fn wrap_input_in_fence(&mut self) {
+        self.paste_fence_offered = false;
+        if self.input.is_empty() {
+            return;
+        }
+        self.input = wrap_in_fence(&self.input);
+        self.store_input_cursor(self.input.chars().count());
+        self.pending_input_focus = true;
+        self.mark_draft_changed();
+        self.refresh_after_text_change();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn close_at_end(text: &str) -> Option<String> {
+        close_fence(text, text.chars().count())
+    }
+
+    #[test]
+    fn wrapped_text_gets_a_fence_longer_than_its_own() {
+        assert_eq!(wrap_in_fence("let x = 1;\r\n"), "```\nlet x = 1;\n```");
+        assert_eq!(
+            wrap_in_fence("docs:\n  ````md\n  ```\n  ````"),
+            "`````\ndocs:\n  ````md\n  ```\n  ````\n`````"
+        );
+    }
+
+    #[test]
+    fn enter_adds_a_newline_only_inside_an_open_fence() {
+        let end = usize::MAX;
+        let cases = [
+            ("```\nfn main() {", end, true),
+            ("```\ncode\n```", end, false),
+            ("```\ncode\n```\nmore text", end, false),
+            ("```\ncode\n```", 8, true),
+            ("intro\n```\ncode", 3, false),
+            ("```rust", end, true),
+            ("use ```inline``` here", end, false),
+            ("text ```\ncode", end, false),
+            ("  ```\ncode", end, true),
+            ("    ```\ncode", end, false),
+            ("\t```\ncode", end, false),
+            ("``\ncode", end, false),
+            ("~~~\ncode", end, true),
+            ("~~~\ncode\n```", end, true),
+            ("````\ncode\n```", end, true),
+            ("```\ncode\n`````", end, false),
+            ("```rust\ncode\n``` rust", end, true),
+            ("``` a`b\ncode", end, false),
+            ("```\r\ncode\r\n", end, true),
+            ("```\r\ncode\r\n```\r\n", end, false),
+            ("```\na\n```\n\n~~~\nb", end, true),
+            ("```\nlet s = \"\u{00E9}\u{00E9}\";", end, true),
+        ];
+        for (text, cursor, newline) in cases {
+            let cursor = cursor.min(text.chars().count());
+            assert_eq!(
+                enter_adds_newline(text, cursor),
+                newline,
+                "{:?} at {}",
+                text,
+                cursor
+            );
+        }
+    }
+
+    #[test]
+    fn typed_backtick_fence_is_closed() {
+        assert_eq!(
+            close_at_end("intro\n```").as_deref(),
+            Some("intro\n```\n```")
+        );
+        assert_eq!(
+            close_at_end("use ```x``` inline\n```").as_deref(),
+            Some("use ```x``` inline\n```\n```")
+        );
+    }
+
+    #[test]
+    fn fence_closed_later_is_left_alone() {
+        let text = "```\ncode\n```";
+        assert_eq!(close_fence(text, 3), None);
+    }
+
+    #[test]
+    fn closing_backticks_are_not_doubled() {
+        assert_eq!(close_at_end("```\ncode\n```"), None);
+        assert_eq!(close_at_end("````\ncode\n```"), None);
+    }
+
+    #[test]
+    fn tilde_fences_follow_commonmark() {
+        assert_eq!(close_at_end("~~~\ncode\n```"), None);
+        assert_eq!(
+            close_at_end("~~~\ncode\n~~~\n```").as_deref(),
+            Some("~~~\ncode\n~~~\n```\n```")
+        );
+        assert!(open_fence("~~~~\n~~~\n"));
+        assert!(!open_fence("~~~\ncode\n~~~~\n"));
+        assert!(open_fence("~~~\n```\n"));
+    }
+
+    #[test]
+    fn backtick_fences_follow_commonmark() {
+        assert!(open_fence("```rust\nfn main() {}\n"));
+        assert!(!open_fence("```rust\nfn main() {}\n```\n"));
+        assert!(
+            !open_fence("``` a`b\n"),
+            "backtick info strings cannot hold backticks"
+        );
+        assert!(
+            open_fence("```\n``` rust\n"),
+            "a closing fence has no info string"
+        );
+        assert!(
+            !open_fence("    ```\n"),
+            "four spaces make an indented code block"
+        );
+    }
+}
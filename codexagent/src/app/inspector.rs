@@ -0,0 +1,336 @@
+use eframe::egui::text::{LayoutJob, TextFormat};
+use eframe::egui::{Color32, FontId};
+use serde_json::Value;
+
+use crate::config::{TEXT_FONT_SIZE, set_inspect_events};
+use crate::logging;
+use crate::prompt::EventLog;
+
+use super::CodexAgentApp;
+
+const UNTYPED_EVENT: &str = "(no type)";
+const INVALID_EVENT: &str = "(invalid json)";
+const PREAMBLE_EVENT: &str = "(preamble)";
+const EVENT_TEXT_ALPHA: u8 = 220;
+const PREAMBLE_TEXT_ALPHA: u8 = 110;
+
+pub(super) struct InspectedEvent {
+    pub(super) kind: String,
+    text: String,
+    preamble: bool,
+}
+
+pub(super) struct EventInspector {
+    pub(super) prompt_id: u64,
+    pub(super) events: Vec<InspectedEvent>,
+    pub(super) filter: Option<String>,
+    pub(super) dropped: usize,
+    pub(super) text: String,
+    pub(super) preamble_ranges: Vec<(usize, usize)>,
+}
+
+fn event_kind(event: &Value) -> Option<&str> {
+    event
+        .get("type")
+        .or_else(|| event.get("msg").and_then(|msg| msg.get("type")))
+        .and_then(Value::as_str)
+}
+
+fn inspected_event(line: &str) -> InspectedEvent {
+    match serde_json::from_str::<Value>(line) {
+        Ok(event) => InspectedEvent {
+            kind: event_kind(&event).unwrap_or(UNTYPED_EVENT).to_owned(),
+            text: serde_json::to_string_pretty(&event).unwrap_or_else(|_| line.to_owned()),
+            preamble: false,
+        },
+        Err(_) => InspectedEvent {
+            kind: INVALID_EVENT.to_owned(),
+            text: line.to_owned(),
+            preamble: false,
+        },
+    }
+}
+
+fn preamble_event(line: &str) -> InspectedEvent {
+    InspectedEvent {
+        kind: PREAMBLE_EVENT.to_owned(),
+        text: line.to_owned(),
+        preamble: true,
+    }
+}
+
+pub(super) fn inspector_layout_job(
+    text: &str,
+    preamble_ranges: &[(usize, usize)],
+    wrap_width: f32,
+) -> LayoutJob {
+    let format = |alpha| TextFormat {
+        font_id: FontId::monospace(TEXT_FONT_SIZE),
+        color: Color32::from_rgba_unmultiplied(188, 194, 202, alpha),
+        ..Default::default()
+    };
+    let mut job = LayoutJob::default();
+    let mut offset = 0;
+    for &(start, end) in preamble_ranges {
+        let (Some(before), Some(preamble)) = (text.get(offset..start), text.get(start..end)) else {
+            break;
+        };
+        job.append(before, 0.0, format(EVENT_TEXT_ALPHA));
+        job.append(preamble, 0.0, format(PREAMBLE_TEXT_ALPHA));
+        offset = end;
+    }
+    job.append(
+        text.get(offset..).unwrap_or_default(),
+        0.0,
+        format(EVENT_TEXT_ALPHA),
+    );
+    job.wrap.max_width = wrap_width;
+    job
+}
+
+impl EventInspector {
+    fn from_log(log: &EventLog) -> Option<Self> {
+        let prompt_id = log.prompt_id()?;
+        if log.is_empty() {
+            return None;
+        }
+        let mut inspector = Self {
+            prompt_id,
+            events: log
+                .lines()
+                .enumerate()
+                .map(|(index, line)| {
+                    if index < log.preamble() {
+                        preamble_event(line)
+                    } else {
+                        inspected_event(line)
+                    }
+                })
+                .collect(),
+            filter: None,
+            dropped: log.dropped(),
+            text: String::new(),
+            preamble_ranges: Vec::new(),
+        };
+        inspector.render();
+        Some(inspector)
+    }
+
+    pub(super) fn kinds(&self) -> Vec<(&str, usize)> {
+        let mut kinds: Vec<(&str, usize)> = Vec::new();
+        for event in &self.events {
+            match kinds.iter_mut().find(|(kind, _)| *kind == event.kind) {
+                Some((_, count)) => *count += 1,
+                None => kinds.push((&event.kind, 1)),
+            }
+        }
+        kinds
+    }
+
+    pub(super) fn visible_count(&self) -> usize {
+        self.visible().count()
+    }
+
+    fn visible(&self) -> impl Iterator<Item = (usize, &InspectedEvent)> {
+        self.events.iter().enumerate().filter(|(_, event)| {
+            self.filter
+                .as_deref()
+                .is_none_or(|filter| filter == event.kind)
+        })
+    }
+
+    fn render(&mut self) {
+        let mut text = String::new();
+        let mut preamble_ranges = Vec::new();
+        for (index, event) in self.visible() {
+            if !text.is_empty() {
+                text.push('\n');
+            }
+            let start = text.len();
+            text.push_str(&format!(
+                "// #{} {}\n{}\n",
+                self.dropped + index + 1,
+                event.kind,
+                event.text
+            ));
+            if event.preamble {
+                preamble_ranges.push((start, text.len()));
+            }
+        }
+        self.text = text;
+        self.preamble_ranges = preamble_ranges;
+    }
+}
+
+impl CodexAgentApp {
+    pub(super) fn event_inspector_open(&self) -> bool {
+        self.event_inspector.is_some()
+    }
+
+    pub(super) fn start_event_log(&self, prompt_id: u64) {
+        let mut log = self.event_log.lock().unwrap_or_else(|e| e.into_inner());
+        if self.inspect_events {
+            log.start(prompt_id);
+        } else {
+            log.reset();
+        }
+    }
+
+    pub(super) fn has_inspectable_events(&self) -> bool {
+        self.inspect_events
+            && !self
+                .event_log
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .is_empty()
+    }
+
+    pub(super) fn open_event_inspector(&mut self) -> Result<String, String> {
+        if !self.inspect_events {
+            return Err("Raw events are not kept. Turn on Settings \u{203A} Diagnostics \u{203A} Keep raw events, then run a prompt.".to_owned());
+        }
+        if self.codex_file_editor_open() {
+            return Err("Close the file editor before inspecting events".to_owned());
+        }
+        let inspector = {
+            let log = self.event_log.lock().unwrap_or_else(|e| e.into_inner());
+            EventInspector::from_log(&log)
+        };
+        let Some(inspector) = inspector else {
+            return Err("No events captured yet \u{2014} run a prompt first".to_owned());
+        };
+        let message = format!(
+            "Inspecting {} events from the last prompt",
+            inspector.events.len()
+        );
+        logging::trace(format!(
+            "opened event inspector for prompt {} ({} events, {} dropped)",
+            inspector.prompt_id,
+            inspector.events.len(),
+            inspector.dropped
+        ));
+        self.event_inspector = Some(inspector);
+        self.release_input_focus();
+        self.invalidate_text_layout();
+        self.resize_for_text();
+        Ok(message)
+    }
+
+    pub(super) fn inspect_events_from_menu(&mut self) {
+        self.clear_picker_selection();
+        if let Err(error) = self.open_event_inspector() {
+            self.push_local_error(&error);
+            self.finish_local_change();
+        }
+    }
+
+    pub(super) fn close_event_inspector(&mut self) {
+        if self.event_inspector.take().is_none() {
+            return;
+        }
+        self.pending_input_focus = true;
+        self.invalidate_text_layout();
+        self.resize_for_text();
+    }
+
+    pub(super) fn select_inspector_filter(&mut self, filter: Option<String>) {
+        if let Some(inspector) = self.event_inspector.as_mut() {
+            inspector.filter = filter;
+            inspector.render();
+        }
+    }
+
+    pub(super) fn copy_inspected_events(&self) {
+        if let Some(inspector) = self.event_inspector.as_ref() {
+            self.ctx.copy_text(inspector.text.clone());
+        }
+    }
+
+    pub(super) fn select_inspect_events(&mut self, enabled: bool) {
+        self.clear_picker_selection();
+        if self.inspect_events == enabled {
+            return;
+        }
+        match set_inspect_events(enabled) {
+            Ok(enabled) => {
+                self.inspect_events = enabled;
+                if !enabled {
+                    self.event_log
+                        .lock()
+                        .unwrap_or_else(|e| e.into_inner())
+                        .reset();
+                }
+                self.push_settings_output(if enabled {
+                    "Keeping raw events for the last prompt; use /inspect to view them"
+                } else {
+                    "Raw events are no longer kept"
+                });
+                self.finish_local_success();
+            }
+            Err(error) => {
+                logging::error(format!(
+                    "failed to set inspect events {}: {}",
+                    if enabled { "on" } else { "off" },
+                    error
+                ));
+                self.push_local_error(&format!("Failed to set raw event setting: {}", error));
+            }
+        }
+        self.finish_local_change();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn inspector() -> EventInspector {
+        let mut log = EventLog::default();
+        log.start(7);
+        log.record_preamble(7, "codex 0.46");
+        log.record(7, r#"{"type":"item.completed","item":{"text":"hi"}}"#);
+        log.record(7, r#"{"id":"0","msg":{"type":"agent_message"}}"#);
+        log.record(7, r#"{"type":"item.completed"}"#);
+        log.record(7, r#"{"no":"type"}"#);
+        log.record(7, "not json");
+        EventInspector::from_log(&log).unwrap()
+    }
+
+    #[test]
+    fn events_are_grouped_by_their_type() {
+        let inspector = inspector();
+        assert_eq!(inspector.prompt_id, 7);
+        assert_eq!(
+            inspector.kinds(),
+            [
+                (PREAMBLE_EVENT, 1),
+                ("item.completed", 2),
+                ("agent_message", 1),
+                (UNTYPED_EVENT, 1),
+                (INVALID_EVENT, 1),
+            ]
+        );
+        assert!(
+            inspector
+                .text
+                .contains("// #2 item.completed\n{\n  \"item\"")
+        );
+        assert_eq!(
+            inspector.preamble_ranges,
+            [(0, "// #1 (preamble)\ncodex 0.46\n".len())]
+        );
+        assert!(EventInspector::from_log(&EventLog::default()).is_none());
+    }
+
+    #[test]
+    fn filters_keep_the_original_event_numbers() {
+        let mut inspector = inspector();
+        inspector.filter = Some("item.completed".to_owned());
+        inspector.render();
+        assert_eq!(inspector.visible_count(), 2);
+        assert!(inspector.text.starts_with("// #2 item.completed"));
+        assert!(inspector.text.contains("// #4 item.completed"));
+        assert!(!inspector.text.contains("agent_message"));
+        assert!(inspector.preamble_ranges.is_empty());
+    }
+}
@@ -0,0 +1,136 @@
+use crate::config::{set_interface_locale, set_pinned_response_language};
+use crate::i18n::{apply_locale, pinned_locale};
+use crate::logging;
+
+use super::CodexAgentApp;
+
+const LANGUAGE_NAMES: [(&str, &str); 16] = [
+    ("ar", "Arabic"),
+    ("cs", "Czech"),
+    ("da", "Danish"),
+    ("de", "German"),
+    ("en", "English"),
+    ("es", "Spanish"),
+    ("fr", "French"),
+    ("it", "Italian"),
+    ("ja", "Japanese"),
+    ("ko", "Korean"),
+    ("nl", "Dutch"),
+    ("pl", "Polish"),
+    ("pt", "Portuguese"),
+    ("ru", "Russian"),
+    ("sv", "Swedish"),
+    ("zh", "Chinese"),
+];
+
+fn language_name(language: &str) -> String {
+    let language = language.trim();
+    let code = language
+        .split(['-', '_'])
+        .next()
+        .unwrap_or(language)
+        .to_ascii_lowercase();
+    LANGUAGE_NAMES
+        .iter()
+        .find(|(known, name)| *known == code || name.eq_ignore_ascii_case(language))
+        .map_or_else(|| language.to_owned(), |(_, name)| (*name).to_owned())
+}
+
+pub(super) fn response_language_directive(language: &str) -> String {
+    format!("Respond in {}.", language)
+}
+
+impl CodexAgentApp {
+    pub(super) fn run_lang_command(&mut self, language: &str) -> Result<String, String> {
+        if language.trim().is_empty() {
+            return Err("Usage: /lang <language|off>".to_owned());
+        }
+        if language.trim().eq_ignore_ascii_case("off") {
+            let Some(previous) = self.response_language.take() else {
+                return Ok("No response language was set".to_owned());
+            };
+            logging::trace(format!("response language {} cleared", previous));
+            return Ok(format!(
+                "Response language {} cleared \u{2014} codex answers in its default language.",
+                previous
+            ));
+        }
+        let language = language_name(language);
+        logging::trace(format!("response language set to {}", language));
+        let message = format!(
+            "Responses in {} \u{2014} each prompt ends with \u{201C}{}\u{201D}.",
+            language,
+            response_language_directive(&language)
+        );
+        self.response_language = Some(language);
+        Ok(message)
+    }
+
+    pub(super) fn reset_response_language(&mut self) {
+        if self.response_language != self.pinned_response_language {
+            logging::trace("resetting response language for the cleared session");
+        }
+        self.response_language = self.pinned_response_language.clone();
+    }
+
+    pub(super) fn select_response_language_pin(&mut self, language: Option<String>) {
+        self.clear_picker_selection();
+        if language == self.pinned_response_language {
+            return;
+        }
+        match set_pinned_response_language(language.as_deref()) {
+            Ok(()) => {
+                let message = match language.as_deref() {
+                    Some(language) => format!("Response language {} kept after clear", language),
+                    None => "Response language resets on clear".to_owned(),
+                };
+                self.pinned_response_language = language;
+                self.push_settings_output(&message);
+                self.finish_local_success();
+            }
+            Err(error) => {
+                logging::error(format!("failed to pin response language: {}", error));
+                self.push_local_error(&format!("Failed to pin response language: {}", error));
+            }
+        }
+        self.finish_local_change();
+    }
+
+    pub(super) fn select_interface_locale(&mut self, locale: Option<&'static str>) {
+        self.clear_picker_selection();
+        if locale == pinned_locale() {
+            return;
+        }
+        match set_interface_locale(locale) {
+            Ok(()) => {
+                let name = apply_locale(locale);
+                self.push_settings_output(&match locale {
+                    Some(_) => format!("Interface language set to {}", name),
+                    None => format!("Interface language follows Windows ({})", name),
+                });
+                self.invalidate_text_layout();
+                self.finish_local_success();
+            }
+            Err(error) => {
+                logging::error(format!("failed to set interface language: {}", error));
+                self.push_local_error(&format!("Failed to set interface language: {}", error));
+            }
+        }
+        self.finish_local_change();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn codes_and_names_map_to_english_names() {
+        assert_eq!(language_name("de"), "German");
+        assert_eq!(language_name(" pt-BR "), "Portuguese");
+        assert_eq!(language_name("zh_TW"), "Chinese");
+        assert_eq!(language_name("FRENCH"), "French");
+        assert_eq!(language_name("Klingon"), "Klingon");
+        assert_eq!(response_language_directive("German"), "Respond in German.");
+    }
+}
@@ -1,45 +1,103 @@
+use std::time::{Duration, Instant};
+
 use eframe::egui::{self, Vec2};
 
 use crate::config::{
-    AUTO_EXPAND_VISIBLE_ROWS, CARD_INNER_PADDING_X, LINE_HEIGHT, MAX_VISIBLE_ROWS,
-    MAX_WINDOW_HEIGHT, MIN_TEXT_WRAP_WIDTH, TEXT_EDIT_MARGIN_X, WINDOW_BOTTOM_PADDING,
-    WINDOW_PADDING,
+    AUTO_EXPAND_VISIBLE_ROWS, CANCEL_BUTTON_HEIGHT, CARD_INNER_PADDING_X, CODEX_FILE_EDITOR_ROWS,
+    CODEX_FILE_UPDATE_DIFF_ROWS, LINE_HEIGHT, MAX_WINDOW_HEIGHT, MAX_WINDOW_HEIGHT_MONITOR_RATIO,
+    MIN_TEXT_WRAP_WIDTH, TEXT_EDIT_MARGIN_X,
 };
 use crate::logging;
+use crate::usage::estimate_tokens;
 
 use super::CodexAgentApp;
-use super::render::{markdown_layout_job, response_separator_y};
+use super::approval::approval_detail_rows;
+use super::render::{
+    markdown_layout_job, pin_region_rects, prompt_region_rects, recycled_markdown_layout_job,
+    response_end_positions, response_separator_y, turn_marker_positions,
+};
+use super::ui_trace::{trace_focus, trace_focus_change, trace_resize};
+
+const CODEX_FILE_EDITOR_CHROME_HEIGHT: f32 = 64.0;
+const CARD_CHROME_HEIGHT: f32 = 58.0;
+const RESIZE_INTERVAL: Duration = Duration::from_millis(50);
+const RESIZE_TOLERANCE: f32 = 1.0;
+const INPUT_FOCUS_MAX_RETRIES: u32 = 10;
+pub(super) const CODEX_FILE_UPDATE_SPACING: f32 = 6.0;
+pub(super) const PROMPT_PREFIX_CHIP_HEIGHT: f32 = 18.0;
+pub(super) const PROMPT_PREFIX_CHIP_SPACING: f32 = 4.0;
+pub(super) const TOKEN_ESTIMATE_THRESHOLD: u64 = 1_000;
+
+// Holds the wrap width still while the window is drag-resized, so text
+// reflows once on release instead of on every frame of the drag.
+#[derive(Default)]
+pub(super) struct ResizeWrapWidth {
+    frozen: Option<f32>,
+}
+
+impl ResizeWrapWidth {
+    pub(super) fn begin(&mut self, width: f32) {
+        self.frozen = Some(width);
+    }
+
+    pub(super) fn release(&mut self) {
+        self.frozen = None;
+    }
+
+    pub(super) fn resolve(&self, live_width: f32) -> f32 {
+        self.frozen.unwrap_or(live_width)
+    }
+}
+
+fn wrap_width_beside_scrollbar(content_width: f32, scrollbar_width: f32) -> f32 {
+    (content_width - TEXT_EDIT_MARGIN_X - scrollbar_width).max(MIN_TEXT_WRAP_WIDTH)
+}
 
 impl CodexAgentApp {
+    pub(super) fn max_window_height(&self) -> f32 {
+        self.ctx
+            .input(|input| input.viewport().monitor_size)
+            .map(|monitor| monitor.y * MAX_WINDOW_HEIGHT_MONITOR_RATIO)
+            .unwrap_or(MAX_WINDOW_HEIGHT)
+            .max(self.min_inner_size().y)
+    }
+
     pub(super) fn visible_row_limit(&self) -> usize {
+        let chrome =
+            CARD_CHROME_HEIGHT + self.window_shadow.padding() + self.window_shadow.bottom_padding();
+        let max_rows =
+            (((self.max_window_height() - chrome) / LINE_HEIGHT).floor() as usize).max(2);
         if self.user_height_override.is_some() {
-            MAX_VISIBLE_ROWS
+            max_rows
         } else {
-            AUTO_EXPAND_VISIBLE_ROWS
+            max_rows.min(AUTO_EXPAND_VISIBLE_ROWS)
         }
     }
 
     pub(super) fn display_rows_for_width(&mut self, wrap_width: f32) -> (usize, usize) {
         if let Some(step) = self.pending_step() {
-            if self.render_step != Some(step) || self.text_layout_dirty {
-                self.sync_render_buffer(step);
-                self.render_step = Some(step);
+            let status = self.pending_status();
+            if self.render_step != Some((step, status)) || self.text_layout_dirty {
+                self.sync_render_buffer(step, status);
+                self.render_step = Some((step, status));
             }
         }
-        if !self.text_layout_dirty && Self::same_width(self.display_rows_width, wrap_width) {
+        let output_wrap_width = self.output_wrap_width(wrap_width);
+        if !self.text_layout_dirty && Self::same_width(self.display_rows_width, output_wrap_width) {
             return (self.output_rows_cache, self.input_rows_cache);
         }
-        self.sync_output_galley(wrap_width);
+        self.sync_output_galley(output_wrap_width);
         self.sync_input_galley(wrap_width);
-        let (raw_output, raw_output_h) = if self.output_display_buffer.is_empty() {
-            (0, 0.0)
-        } else if let Some(galley) = self.output_galley.as_ref() {
-            let rows = galley.rows.len().max(1);
-            (rows, rows as f32 * LINE_HEIGHT)
-        } else {
-            logging::error("output galley missing during layout sync");
-            (0, 0.0)
-        };
+        let (raw_output, raw_output_h) =
+            if self.output_display_buffer.is_empty() && self.render_buffer.is_empty() {
+                (0, 0.0)
+            } else if let Some(galley) = self.output_galley.as_ref() {
+                let rows = galley.rows.len().max(1);
+                (rows, rows as f32 * LINE_HEIGHT)
+            } else {
+                logging::error("output galley missing during layout sync");
+                (0, 0.0)
+            };
         let (raw_input, raw_input_h) = if let Some(input_galley) = self.input_galley.as_ref() {
             let rows = input_galley.rows.len().max(1);
             (rows, rows as f32 * LINE_HEIGHT)
@@ -65,28 +123,56 @@ impl CodexAgentApp {
         self.input_rows_cache = input_rows;
         self.output_height_cache = output_h;
         self.input_height_cache = input_h;
-        self.display_rows_width = Some(wrap_width);
+        self.display_rows_width = Some(output_wrap_width);
         self.text_layout_dirty = false;
         (output_rows, input_rows)
     }
 
     pub(super) fn sync_output_galley(&mut self, wrap_width: f32) {
         self.sync_output_display_buffer();
+        self.recycle_retired_output_galleys();
         if Self::same_width(self.output_galley_width, wrap_width) && self.output_galley.is_some() {
             return;
         }
-        let job = markdown_layout_job(
+        self.retire_output_galley();
+        let job = recycled_markdown_layout_job(
+            self.output_layout_scratch.take().unwrap_or_default(),
             &self.output_display_buffer,
             wrap_width,
             &self.output_display_prompt_ranges,
             self.output_display_response_start,
             &self.output_display_line_kinds,
+            self.high_contrast(),
+            self.response_dimming,
         );
         let galley = self.ctx.fonts(|fonts| fonts.layout_job(job));
         self.output_separator_y = (self.output_display_response_start
             < self.output_display_buffer.len())
         .then(|| response_separator_y(&galley, self.output_display_response_chars))
         .flatten();
+        self.output_prompt_rects = prompt_region_rects(
+            &galley,
+            &self.output_display_buffer,
+            &self.output_display_prompt_ranges,
+        );
+        self.output_pin_rects = pin_region_rects(
+            &galley,
+            &self.output_display_buffer,
+            &self.output_display_line_kinds,
+            &self.output_display_prompt_ranges,
+            &self.output_prompt_rects,
+        );
+        self.output_response_ends = response_end_positions(
+            &galley,
+            &self.output_display_buffer,
+            &self.output_display_line_kinds,
+            &self.output_display_prompt_ranges,
+        );
+        self.output_turn_markers = turn_marker_positions(
+            &galley,
+            &self.output_display_buffer,
+            &self.output_display_line_kinds,
+        );
         self.output_galley = Some(galley);
         self.output_galley_width = Some(wrap_width);
     }
@@ -95,17 +181,50 @@ impl CodexAgentApp {
         if Self::same_width(self.input_galley_width, wrap_width) && self.input_galley.is_some() {
             return;
         }
-        let job = markdown_layout_job(&self.input, wrap_width, &[], 0, &[]);
+        let job = markdown_layout_job(
+            &self.shown_input(),
+            wrap_width,
+            &[],
+            0,
+            &[],
+            self.high_contrast(),
+            self.response_dimming,
+        );
         self.input_galley = Some(self.ctx.fonts(|fonts| fonts.layout_job(job)));
         self.input_galley_width = Some(wrap_width);
     }
 
     pub(super) fn text_wrap_width(&self) -> f32 {
-        (self.ctx.screen_rect().width()
-            - WINDOW_PADDING * 2.0
-            - CARD_INNER_PADDING_X
-            - TEXT_EDIT_MARGIN_X)
-            .max(MIN_TEXT_WRAP_WIDTH)
+        self.resize_wrap_width.resolve(
+            (self.ctx.screen_rect().width()
+                - self.window_shadow.padding() * 2.0
+                - CARD_INNER_PADDING_X)
+                .max(MIN_TEXT_WRAP_WIDTH),
+        )
+    }
+
+    pub(super) fn content_wrap_width(&self, available_width: f32) -> f32 {
+        self.resize_wrap_width.resolve(available_width)
+    }
+
+    pub(super) fn output_wrap_width(&self, content_width: f32) -> f32 {
+        wrap_width_beside_scrollbar(content_width, self.output_scrollbar_width)
+    }
+
+    pub(super) fn set_output_scrollable(&mut self, scrollable: bool) {
+        let width = if scrollable {
+            let scroll = self.ctx.style().spacing.scroll;
+            scroll.bar_inner_margin + scroll.bar_width + scroll.bar_outer_margin
+        } else {
+            0.0
+        };
+        if Self::same_axis(self.output_scrollbar_width, width) {
+            return;
+        }
+        logging::trace(format!("output scrollbar width now {}", width));
+        self.output_scrollbar_width = width;
+        self.invalidate_text_layout();
+        self.ctx.request_repaint();
     }
 
     pub(super) fn resize_for_text(&mut self) {
@@ -122,35 +241,271 @@ impl CodexAgentApp {
         }
         let width = self.ctx.screen_rect().width();
         let (output_rows, _input_rows) = self.display_rows_for_width(wrap_width);
-        let separator = if output_rows > 0 { 9.0 } else { 0.0 };
-        let mut height = (58.0
-            + self.output_height_cache
-            + self.input_height_cache
-            + self.command_panel_height()
-            + separator
-            + WINDOW_PADDING
-            + WINDOW_BOTTOM_PADDING)
-            .clamp(self.min_inner_size().y, MAX_WINDOW_HEIGHT);
+        let body_height = if self.codex_file_editor_open()
+            || self.event_inspector_open()
+            || self.recent_sessions_open()
+        {
+            self.codex_file_editor_height()
+        } else {
+            let separator = if output_rows > 0 { 9.0 } else { 0.0 };
+            self.output_height_cache
+                + self.input_height_cache
+                + self.command_panel_height()
+                + self.codex_file_update_height()
+                + self.environment_notice_height()
+                + self.disk_space_warning_height()
+                + self.onboarding_height()
+                + self.prompt_prefix_chip_height()
+                + self.response_language_chip_height()
+                + self.concise_chip_height()
+                + self.follow_up_suggestions_height()
+                + self.paste_fence_offer_height()
+                + self.clipboard_offer_height()
+                + self.sleep_retry_height()
+                + self.tool_retry_height()
+                + self.topic_shift_height()
+                + self.approval_request_height()
+                + self.changed_files_height()
+                + self.response_diff_toggle_height()
+                + self.dirty_submit_confirmation_height()
+                + self.project_trust_confirmation_height()
+                + self.clear_confirmation_height()
+                + self.secret_submit_confirmation_height()
+                + self.token_estimate_height()
+                + self.status_strip_height()
+                + self.notes_height()
+                + self.find_bar_height()
+                + separator
+        };
+        let mut height = (CARD_CHROME_HEIGHT
+            + body_height
+            + self.window_shadow.padding()
+            + self.window_shadow.bottom_padding())
+        .clamp(self.min_inner_size().y, self.max_window_height());
         if let Some(max_height) = max_height {
             height = height.min(max_height);
         }
-        let size = Vec2::new(width, height);
-        if Self::same_size(self.last_inner_size, size) {
+        trace_resize(|| {
+            format!(
+                "resize_for_text wrap {:.1} output rows {} body {:.1} max {:?} -> {:.1}x{:.1}",
+                wrap_width, output_rows, body_height, max_height, width, height
+            )
+        });
+        self.request_auto_resize(self.sanitize_window_size(Vec2::new(width, height)));
+    }
+
+    fn request_auto_resize(&mut self, size: Vec2) {
+        let since_sent = self.resize_sent_at.map(|sent| sent.elapsed());
+        let decision = resize_decision(self.last_inner_size, size, since_sent, self.busy);
+        trace_resize(|| {
+            format!(
+                "auto resize to {:?} from {:?}: {:?}",
+                size, self.last_inner_size, decision
+            )
+        });
+        match decision {
+            ResizeDecision::Skip => self.pending_resize = None,
+            ResizeDecision::Send => {
+                self.pending_resize = None;
+                self.last_inner_size = Some(size);
+                self.resize_sent_at = Some(Instant::now());
+                self.apply_auto_resize(size);
+            }
+            ResizeDecision::Defer(wait) => {
+                self.pending_resize = Some(size);
+                self.ctx.request_repaint_after(wait);
+            }
+            ResizeDecision::Hold => self.pending_resize = Some(size),
+        }
+    }
+
+    pub(super) fn flush_pending_resize(&mut self) {
+        let Some(mut size) = self.pending_resize.take() else {
+            return;
+        };
+        if self.resizing || self.user_height_override.is_some() {
             return;
         }
-        self.last_inner_size = Some(size);
-        self.apply_auto_resize(size);
+        if let Some(max_height) = self.auto_resize_height_limit() {
+            size.y = size.y.min(max_height);
+        }
+        self.request_auto_resize(size);
+    }
+
+    pub(super) fn codex_file_editor_height(&self) -> f32 {
+        CODEX_FILE_EDITOR_ROWS as f32 * LINE_HEIGHT + CODEX_FILE_EDITOR_CHROME_HEIGHT
+    }
+
+    pub(super) fn codex_file_update_height(&self) -> f32 {
+        let Some(update) = self.codex_file_updates.first() else {
+            return 0.0;
+        };
+        let mut height = CANCEL_BUTTON_HEIGHT + CODEX_FILE_UPDATE_SPACING;
+        if self.codex_file_update_review {
+            let rows = update.diff.len().clamp(1, CODEX_FILE_UPDATE_DIFF_ROWS);
+            height += rows as f32 * LINE_HEIGHT + CODEX_FILE_UPDATE_SPACING;
+        }
+        height
+    }
+
+    pub(super) fn environment_notice_height(&self) -> f32 {
+        if self.environment_notice.is_empty() {
+            return 0.0;
+        }
+        CANCEL_BUTTON_HEIGHT + PROMPT_PREFIX_CHIP_SPACING
+    }
+
+    pub(super) fn prompt_prefix_chip_height(&self) -> f32 {
+        if self.active_prompt_prefix().is_none() {
+            return 0.0;
+        }
+        PROMPT_PREFIX_CHIP_HEIGHT + PROMPT_PREFIX_CHIP_SPACING
+    }
+
+    pub(super) fn response_language_chip_height(&self) -> f32 {
+        if self.response_language.is_none() {
+            return 0.0;
+        }
+        PROMPT_PREFIX_CHIP_HEIGHT + PROMPT_PREFIX_CHIP_SPACING
+    }
+
+    pub(super) fn concise_chip_height(&self) -> f32 {
+        if !self.concise {
+            return 0.0;
+        }
+        PROMPT_PREFIX_CHIP_HEIGHT + PROMPT_PREFIX_CHIP_SPACING
+    }
+
+    pub(super) fn follow_up_suggestions_height(&self) -> f32 {
+        if self.busy || self.follow_up_suggestions.is_empty() && !self.continuation_offered {
+            return 0.0;
+        }
+        PROMPT_PREFIX_CHIP_HEIGHT + PROMPT_PREFIX_CHIP_SPACING
+    }
+
+    pub(super) fn topic_shift_height(&self) -> f32 {
+        if !self.topic_shift_visible() {
+            return 0.0;
+        }
+        PROMPT_PREFIX_CHIP_HEIGHT + PROMPT_PREFIX_CHIP_SPACING
+    }
+
+    pub(super) fn paste_fence_offer_height(&self) -> f32 {
+        if !self.paste_fence_offered || self.input.is_empty() {
+            return 0.0;
+        }
+        PROMPT_PREFIX_CHIP_HEIGHT + PROMPT_PREFIX_CHIP_SPACING
+    }
+
+    pub(super) fn sleep_retry_height(&self) -> f32 {
+        if !self.sleep_retry_offered() {
+            return 0.0;
+        }
+        PROMPT_PREFIX_CHIP_HEIGHT + PROMPT_PREFIX_CHIP_SPACING
+    }
+
+    pub(super) fn clipboard_offer_height(&self) -> f32 {
+        if self.clipboard_offer.is_none() {
+            return 0.0;
+        }
+        PROMPT_PREFIX_CHIP_HEIGHT + PROMPT_PREFIX_CHIP_SPACING
+    }
+
+    pub(super) fn tool_retry_height(&self) -> f32 {
+        if !self.tool_retry_offered() {
+            return 0.0;
+        }
+        PROMPT_PREFIX_CHIP_HEIGHT + PROMPT_PREFIX_CHIP_SPACING
+    }
+
+    pub(super) fn approval_request_height(&self) -> f32 {
+        let Some(request) = self.pending_approval.as_ref().filter(|_| !self.busy) else {
+            return 0.0;
+        };
+        CANCEL_BUTTON_HEIGHT
+            + approval_detail_rows(&request.detail).count() as f32 * LINE_HEIGHT
+            + PROMPT_PREFIX_CHIP_SPACING
+    }
+
+    pub(super) fn changed_files_height(&self) -> f32 {
+        if self.busy || self.changed_files.is_empty() {
+            return 0.0;
+        }
+        let mut height = PROMPT_PREFIX_CHIP_HEIGHT + PROMPT_PREFIX_CHIP_SPACING;
+        if !self.changed_files_open {
+            return height;
+        }
+        for file in &self.changed_files {
+            height += CANCEL_BUTTON_HEIGHT + CODEX_FILE_UPDATE_SPACING;
+            if file.error.is_some() {
+                height += LINE_HEIGHT;
+            }
+            if let Some(diff) = file.diff.as_ref() {
+                let rows = diff.len().clamp(1, CODEX_FILE_UPDATE_DIFF_ROWS);
+                height += rows as f32 * LINE_HEIGHT + CODEX_FILE_UPDATE_SPACING;
+            }
+        }
+        height
+    }
+
+    pub(super) fn response_diff_toggle_height(&self) -> f32 {
+        if self.visible_response_diff().is_none() {
+            return 0.0;
+        }
+        PROMPT_PREFIX_CHIP_HEIGHT + PROMPT_PREFIX_CHIP_SPACING
+    }
+
+    pub(super) fn token_estimate_height(&self) -> f32 {
+        if self.token_submit_confirmation.is_some() {
+            return CANCEL_BUTTON_HEIGHT + PROMPT_PREFIX_CHIP_SPACING;
+        }
+        let tokens = self
+            .input_tokens
+            .unwrap_or_else(|| estimate_tokens(&self.input));
+        if tokens < TOKEN_ESTIMATE_THRESHOLD {
+            return 0.0;
+        }
+        PROMPT_PREFIX_CHIP_HEIGHT + PROMPT_PREFIX_CHIP_SPACING
+    }
+
+    pub(super) fn secret_submit_confirmation_height(&self) -> f32 {
+        if self.secret_submit_confirmation.is_none() {
+            return 0.0;
+        }
+        CANCEL_BUTTON_HEIGHT + PROMPT_PREFIX_CHIP_SPACING
+    }
+
+    pub(super) fn clear_confirmation_height(&self) -> f32 {
+        if self.clear_confirmation.is_none() {
+            return 0.0;
+        }
+        CANCEL_BUTTON_HEIGHT + PROMPT_PREFIX_CHIP_SPACING
+    }
+
+    pub(super) fn dirty_submit_confirmation_height(&self) -> f32 {
+        if self.dirty_submit_confirmation.is_none() {
+            return 0.0;
+        }
+        CANCEL_BUTTON_HEIGHT + PROMPT_PREFIX_CHIP_SPACING
+    }
+
+    pub(super) fn project_trust_confirmation_height(&self) -> f32 {
+        if !self.project_trust_confirmation {
+            return 0.0;
+        }
+        CANCEL_BUTTON_HEIGHT + PROMPT_PREFIX_CHIP_SPACING
     }
 
     pub(super) fn release_input_focus(&self) {
         let id = egui::Id::new(Self::INPUT_ID);
-        if !self.ctx.memory(|mem| mem.has_focus(id)) {
+        if self.ime_composing || !self.ctx.memory(|mem| mem.has_focus(id)) {
             return;
         }
         self.ctx.memory_mut(|mem| {
             mem.surrender_focus(id);
             mem.stop_text_input();
         });
+        trace_focus("input", false);
     }
 
     pub(super) fn sync_input_focus(&mut self, response: &egui::Response) {
@@ -158,14 +513,210 @@ impl CodexAgentApp {
             return;
         }
         if !self.ctx.input(|input| input.focused) {
+            self.input_focus_retries = 0;
             return;
         }
 
         response.request_focus();
-        if response.has_focus() {
-            self.pending_input_focus = false;
-        } else {
-            self.ctx.request_repaint();
+        trace_focus("input", true);
+        self.settle_input_focus(response.has_focus());
+    }
+
+    /// Counts one frame of a pending focus request and gives up once egui has
+    /// refused it for too long, so a stuck request stops forcing repaints.
+    pub(super) fn settle_input_focus(&mut self, has_focus: bool) {
+        if !self.pending_input_focus {
+            return;
+        }
+        match focus_retry(self.input_focus_retries, has_focus) {
+            FocusRetry::Granted => {
+                if self.input_focus_retries > 0 {
+                    let retries = self.input_focus_retries;
+                    trace_focus_change(|| format!("input focus granted after {} retries", retries));
+                }
+                self.pending_input_focus = false;
+                self.input_focus_retries = 0;
+            }
+            FocusRetry::Retry => {
+                self.input_focus_retries += 1;
+                self.ctx.request_repaint();
+            }
+            FocusRetry::GiveUp => {
+                logging::trace(format!(
+                    "input focus not granted after {} frames; waiting for a click",
+                    INPUT_FOCUS_MAX_RETRIES
+                ));
+                self.pending_input_focus = false;
+                self.input_focus_retries = 0;
+            }
+        }
+    }
+
+    pub(super) fn refocus_input_on_card_click(&mut self, card_rect: egui::Rect) {
+        if self.pending_input_focus
+            || self.locked
+            || self.codex_file_editor_open()
+            || self.event_inspector_open()
+            || self.recent_sessions_open()
+        {
+            return;
+        }
+        let clicked = self.ctx.input(|input| {
+            input.pointer.primary_clicked()
+                && input
+                    .pointer
+                    .interact_pos()
+                    .is_some_and(|pos| card_rect.contains(pos))
+        });
+        if !clicked || self.ctx.memory(|mem| mem.focused().is_some()) {
+            return;
         }
+        trace_focus_change(|| "card clicked with nothing focused; refocusing input".to_owned());
+        self.pending_input_focus = true;
+        self.ctx.request_repaint();
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum FocusRetry {
+    Granted,
+    Retry,
+    GiveUp,
+}
+
+fn focus_retry(retries: u32, has_focus: bool) -> FocusRetry {
+    if has_focus {
+        FocusRetry::Granted
+    } else if retries < INPUT_FOCUS_MAX_RETRIES {
+        FocusRetry::Retry
+    } else {
+        FocusRetry::GiveUp
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum ResizeDecision {
+    Skip,
+    Send,
+    Defer(Duration),
+    Hold,
+}
+
+fn resize_decision(
+    last: Option<Vec2>,
+    desired: Vec2,
+    since_sent: Option<Duration>,
+    streaming: bool,
+) -> ResizeDecision {
+    let Some(last) = last else {
+        return ResizeDecision::Send;
+    };
+    if (last.x - desired.x).abs() <= RESIZE_TOLERANCE
+        && (last.y - desired.y).abs() <= RESIZE_TOLERANCE
+    {
+        return ResizeDecision::Skip;
+    }
+    if streaming && desired.y < last.y {
+        return ResizeDecision::Hold;
+    }
+    match since_sent {
+        Some(elapsed) if elapsed < RESIZE_INTERVAL => {
+            ResizeDecision::Defer(RESIZE_INTERVAL - elapsed)
+        }
+        _ => ResizeDecision::Send,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn size(height: f32) -> Vec2 {
+        Vec2::new(600.0, height)
+    }
+
+    #[test]
+    fn first_size_is_sent_and_jitter_is_skipped() {
+        assert_eq!(
+            resize_decision(None, size(200.0), None, false),
+            ResizeDecision::Send
+        );
+        assert_eq!(
+            resize_decision(Some(size(200.0)), size(200.8), Some(Duration::ZERO), false),
+            ResizeDecision::Skip
+        );
+    }
+
+    #[test]
+    fn streaming_grows_immediately_and_shrinks_lazily() {
+        let last = Some(size(300.0));
+        let since = Some(RESIZE_INTERVAL);
+        assert_eq!(
+            resize_decision(last, size(340.0), since, true),
+            ResizeDecision::Send
+        );
+        assert_eq!(
+            resize_decision(last, size(260.0), since, true),
+            ResizeDecision::Hold
+        );
+        assert_eq!(
+            resize_decision(last, size(260.0), since, false),
+            ResizeDecision::Send
+        );
+    }
+
+    #[test]
+    fn typing_sends_at_most_one_resize_per_interval() {
+        // One keystroke every 10 ms, each adding a pixel and a half of height.
+        let mut last = None;
+        let mut sent_at = None;
+        let mut sent = Vec::new();
+        for step in 0..20u32 {
+            let now = Duration::from_millis(u64::from(step) * 10);
+            let desired = size(200.0 + step as f32 * 1.5);
+            let since = sent_at.map(|sent: Duration| now - sent);
+            match resize_decision(last, desired, since, false) {
+                ResizeDecision::Send => {
+                    last = Some(desired);
+                    sent_at = Some(now);
+                    sent.push(now);
+                }
+                ResizeDecision::Defer(wait) => {
+                    assert_eq!(now + wait, sent_at.unwrap() + RESIZE_INTERVAL);
+                }
+                ResizeDecision::Skip | ResizeDecision::Hold => {}
+            }
+        }
+        assert_eq!(sent, [0, 50, 100, 150].map(Duration::from_millis));
+    }
+
+    #[test]
+    fn wrap_width_changes_once_per_completed_resize() {
+        let mut wrap = ResizeWrapWidth::default();
+        let mut live = 800.0;
+        let mut widths = vec![wrap.resolve(live)];
+        for target in [900.0, 640.0, 1200.0] {
+            wrap.begin(wrap.resolve(live));
+            let start = live;
+            for frame in 1..=12 {
+                live = start + (target - start) * frame as f32 / 12.0;
+                widths.push(wrap.resolve(live));
+            }
+            wrap.release();
+            widths.push(wrap.resolve(live));
+        }
+        widths.dedup();
+        assert_eq!(widths, [800.0, 900.0, 640.0, 1200.0]);
+    }
+
+    #[test]
+    fn a_visible_scrollbar_narrows_the_output() {
+        let wide = wrap_width_beside_scrollbar(600.0, 0.0);
+        assert_eq!(wide, 600.0 - TEXT_EDIT_MARGIN_X);
+        assert_eq!(wrap_width_beside_scrollbar(600.0, 10.0), wide - 10.0);
+        assert_eq!(
+            wrap_width_beside_scrollbar(TEXT_EDIT_MARGIN_X, 10.0),
+            MIN_TEXT_WRAP_WIDTH
+        );
     }
 }
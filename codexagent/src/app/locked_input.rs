@@ -0,0 +1,163 @@
+use std::borrow::Cow;
+
+use eframe::egui::{self, Event, Key};
+
+use crate::logging;
+
+use super::{CodexAgentApp, SetupState};
+
+pub(super) const LOCKED_INPUT_OPACITY: f32 = 0.55;
+
+fn merge_locked_input(input: &str, buffered: &str) -> String {
+    if input.is_empty() {
+        return buffered.to_owned();
+    }
+    let separator = if input.ends_with('\n') { "" } else { "\n" };
+    format!("{}{}{}", input, separator, buffered)
+}
+
+fn buffer_event(buffered: &mut String, event: &Event) -> bool {
+    match event {
+        Event::Text(text) | Event::Paste(text) => {
+            buffered.push_str(text);
+            true
+        }
+        Event::Key {
+            key: Key::Backspace,
+            pressed: true,
+            ..
+        } => {
+            buffered.pop();
+            true
+        }
+        Event::Key {
+            key: Key::Enter,
+            pressed: true,
+            modifiers,
+            ..
+        } if modifiers.shift => {
+            buffered.push('\n');
+            true
+        }
+        _ => false,
+    }
+}
+
+impl CodexAgentApp {
+    pub(super) fn input_locked(&self) -> bool {
+        self.locked && !matches!(self.setup_state, SetupState::Installing)
+    }
+
+    pub(super) fn shown_input(&self) -> Cow<'_, str> {
+        if self.locked_input.is_empty() {
+            Cow::Borrowed(&self.input)
+        } else {
+            Cow::Owned(merge_locked_input(&self.input, &self.locked_input))
+        }
+    }
+
+    pub(super) fn buffer_locked_typing(&mut self) {
+        if !self.input_locked() {
+            return;
+        }
+        let input_id = egui::Id::new(Self::INPUT_ID);
+        let typing = self.ctx.input(|input| input.focused)
+            && self
+                .ctx
+                .memory(|mem| mem.focused().is_none_or(|id| id == input_id));
+        if !typing {
+            return;
+        }
+        let mut events = self
+            .ctx
+            .input_mut(|input| std::mem::take(&mut input.events));
+        self.buffer_locked_events(&mut events);
+        self.ctx.input_mut(|input| input.events = events);
+    }
+
+    pub(super) fn buffer_locked_events(&mut self, events: &mut Vec<Event>) {
+        let before = self.locked_input.len();
+        let mut changed = false;
+        events.retain(|event| {
+            let buffered = buffer_event(&mut self.locked_input, event);
+            changed |= buffered;
+            !buffered
+        });
+        if !changed {
+            return;
+        }
+        if before == 0 && !self.locked_input.is_empty() {
+            logging::trace("buffering typing while the input is locked");
+        }
+        self.invalidate_input_layout();
+        self.resize_for_text();
+    }
+
+    pub(super) fn merge_locked_typing(&mut self) {
+        if self.locked || self.locked_input.is_empty() {
+            return;
+        }
+        let buffered = std::mem::take(&mut self.locked_input);
+        logging::trace(format!(
+            "input unlocked; merging {} buffered chars",
+            buffered.chars().count()
+        ));
+        self.input = merge_locked_input(&self.input, &buffered);
+        self.store_input_cursor(self.input.chars().count());
+        self.pending_input_focus = true;
+        self.mark_draft_changed();
+        self.refresh_after_text_change();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use eframe::egui::Modifiers;
+
+    use super::*;
+
+    fn key(key: Key, modifiers: Modifiers) -> Event {
+        Event::Key {
+            key,
+            physical_key: None,
+            pressed: true,
+            repeat: false,
+            modifiers,
+        }
+    }
+
+    #[test]
+    fn buffered_text_goes_on_its_own_line() {
+        assert_eq!(merge_locked_input("", "next"), "next");
+        assert_eq!(merge_locked_input("retry", "next"), "retry\nnext");
+        assert_eq!(merge_locked_input("retry\n", "next"), "retry\nnext");
+    }
+
+    #[test]
+    fn typing_edits_the_buffer() {
+        let mut buffered = String::new();
+        for event in [
+            Event::Text("ad".to_owned()),
+            key(Key::Backspace, Modifiers::NONE),
+            Event::Text("dd tests".to_owned()),
+            key(Key::Enter, Modifiers::SHIFT),
+            Event::Paste("for lexer.rs".to_owned()),
+        ] {
+            assert!(buffer_event(&mut buffered, &event));
+        }
+        assert_eq!(buffered, "add tests\nfor lexer.rs");
+    }
+
+    #[test]
+    fn other_keys_are_left_alone() {
+        let mut buffered = "next".to_owned();
+        for event in [
+            key(Key::Enter, Modifiers::NONE),
+            key(Key::Escape, Modifiers::NONE),
+            key(Key::ArrowLeft, Modifiers::NONE),
+        ] {
+            assert!(!buffer_event(&mut buffered, &event));
+        }
+        assert_eq!(buffered, "next");
+    }
+}
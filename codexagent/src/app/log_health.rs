@@ -0,0 +1,52 @@
+use std::path::Path;
+
+use crate::config::paths;
+use crate::events::AppEvent;
+use crate::logging;
+use crate::runtime::explore_folder;
+
+use super::CodexAgentApp;
+
+impl CodexAgentApp {
+    pub(super) fn watch_log_health(&self) {
+        let tx = self.tx.clone();
+        let repaint = self.repaint_handle();
+        logging::watch_health(move |health| {
+            if tx.send(AppEvent::LogHealth(health)).is_ok() {
+                repaint.request_repaint();
+            }
+        });
+        if self
+            .tx
+            .send(AppEvent::LogHealth(logging::health()))
+            .is_err()
+        {
+            logging::error("failed to deliver log health to app");
+        }
+    }
+
+    pub(super) fn retry_file_logging(&mut self) {
+        self.log_health.last_error = Some("Retrying file logging...".to_owned());
+        logging::retry_file_logging();
+    }
+
+    pub(super) fn open_log_folder(&mut self) {
+        self.explore_app_folder("log", &paths::get().log_dir);
+    }
+
+    pub(super) fn open_data_folder(&mut self) {
+        self.explore_app_folder("data", paths::get().config_dir());
+    }
+
+    fn explore_app_folder(&mut self, label: &str, dir: &Path) {
+        if let Err(error) = explore_folder(dir) {
+            logging::error(format!(
+                "failed to open {} folder {}: {}",
+                label,
+                dir.display(),
+                error
+            ));
+            self.push_local_error(&format!("Failed to open {} folder: {}", label, error));
+        }
+    }
+}
@@ -1,25 +1,101 @@
+mod approval;
+mod attach;
+mod batch;
+mod cancel;
+mod changed_files;
+mod clipboard;
+mod commands;
+mod compare;
+mod diagnostics;
+mod disk_space;
+mod editor;
 mod events;
+mod export;
+mod find;
+mod headless;
 mod history;
+mod hook;
 mod init;
+mod input_assist;
+mod inspector;
+mod language;
 mod layout;
+mod locked_input;
+mod log_health;
+mod notes;
+mod onboarding;
 mod output;
 mod position;
+mod power;
+mod profiles;
+mod prompt_suffix;
+mod ratings;
+mod recovery;
 mod render;
+mod sessions;
+mod shortcut_map;
+mod shortcuts;
+mod snapshot;
+mod stats;
+mod status_bar;
+mod throughput;
+mod title;
+mod tool_retry;
+mod topic;
+mod transcript;
 mod ui;
+mod ui_trace;
+mod warm_start;
 mod window;
 
-use std::collections::HashMap;
+pub use recovery::{rendered_any_frame, take_render_restart, terminate_tracked_codex};
+
+use std::collections::{HashMap, HashSet};
 use std::ffi::c_void;
+use std::path::PathBuf;
 use std::process::ChildStdin;
 use std::sync::{Arc, Mutex, atomic::AtomicBool, mpsc};
-use std::time::Instant;
+use std::time::{Duration, Instant, SystemTime};
 
 use eframe::egui::{self, Vec2};
 
+use crate::config::project::{ProjectConfig, ProjectConfigCache};
+use crate::config::{
+    InputAssist, LauncherMode, PendingStyle, PromptWhitespace, ResponseDimming, SubmitKey, Theme,
+    WakeMode, WindowShadow,
+};
 use crate::events::AppEvent;
-use crate::prompt::{PromptStreamState, RunningPrompt};
+use crate::logging::LogHealth;
+use crate::postprocess::OutputProcessor;
+use crate::prompt::{
+    ApprovalRequest, EventLog, FailedToolStep, PromptBackend, PromptPhase, PromptStreamState,
+    RateLimitBackoff, RunningPrompts,
+};
+use crate::runtime::{
+    AccessibilityPrefs, ClipboardWatcher, CodexFile, CodexFileUpdate, CodexProfiles, PowerWatcher,
+    ProbeService,
+};
+use crate::transform::Transformer;
+use crate::usage::SessionUsage;
 
-use self::render::OutputLineKind;
+use self::batch::PromptBatch;
+use self::changed_files::ChangedFile;
+use self::clipboard::ClipboardOffer;
+use self::compare::Comparison;
+use self::disk_space::DiskSpaceWarning;
+use self::find::FindBar;
+use self::inspector::EventInspector;
+use self::layout::ResizeWrapWidth;
+use self::notes::SessionNotes;
+use self::render::{FoldKind, FoldRegion, OutputLineKind, PendingStatus};
+use self::sessions::{RecentSessionFile, RecentSessionsView};
+use self::shortcut_map::{ShortcutAction, ShortcutMap};
+use self::stats::TurnStats;
+use self::status_bar::StatusStrip;
+use self::throughput::StreamThroughput;
+use self::title::SessionTitle;
+use self::transcript::{PinTarget, Transcript};
+use self::warm_start::WarmCodexState;
 
 #[derive(Clone, Debug, PartialEq)]
 pub(super) enum SetupState {
@@ -29,6 +105,35 @@ pub(super) enum SetupState {
     InstallFailed(String),
 }
 
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(super) enum SecretSubmitDecision {
+    Send,
+    Redact,
+}
+
+pub(super) struct RateLimitWait {
+    until: Instant,
+    prompt: String,
+    project: Option<ProjectConfig>,
+    approved: Option<ApprovalRequest>,
+}
+
+impl RateLimitWait {
+    fn remaining_secs(&self, now: Instant) -> u64 {
+        self.until
+            .saturating_duration_since(now)
+            .as_millis()
+            .div_ceil(1000) as u64
+    }
+}
+
+pub(super) struct ResponseDiff {
+    range: (usize, usize),
+    text: Option<String>,
+    changed: bool,
+    showing: bool,
+}
+
 #[derive(Clone, Debug, PartialEq)]
 pub(super) enum ContextMenuState {
     Checking,
@@ -40,14 +145,132 @@ pub(super) enum ContextMenuState {
 pub(super) struct SlashCommand {
     pub(super) label: &'static str,
     pub(super) name: &'static str,
+    pub(super) usage: &'static str,
     pub(super) description: &'static str,
 }
 
-pub(super) const SLASH_COMMANDS: [SlashCommand; 1] = [SlashCommand {
-    label: "/status",
-    name: "status",
-    description: "Show local rate-limit status",
-}];
+pub(super) const SLASH_COMMANDS: [SlashCommand; 20] = [
+    SlashCommand {
+        label: "/status",
+        name: "status",
+        usage: "/status",
+        description: "Show local rate-limit status",
+    },
+    SlashCommand {
+        label: "/clear",
+        name: "clear",
+        usage: "/clear",
+        description: "Clear the transcript and session",
+    },
+    SlashCommand {
+        label: "/model",
+        name: "model",
+        usage: "/model <name>",
+        description: "Switch the codex model",
+    },
+    SlashCommand {
+        label: "/new",
+        name: "new",
+        usage: "/new",
+        description: "Start a new topic: fresh codex session, transcript kept (Ctrl+N)",
+    },
+    SlashCommand {
+        label: "/cd",
+        name: "cd",
+        usage: "/cd <path>",
+        description: "Change the working directory",
+    },
+    SlashCommand {
+        label: "/resume",
+        name: "resume",
+        usage: "/resume <session id>",
+        description: "Resume a codex session by id",
+    },
+    SlashCommand {
+        label: "/export",
+        name: "export",
+        usage: "/export [--plain | --json] [--notes] <path>",
+        description: "Save the transcript as markdown; --plain for plain text, --json for structured data, --notes to include notes",
+    },
+    SlashCommand {
+        label: "/batch",
+        name: "batch",
+        usage: "/batch <path>",
+        description: "Run each blank-line-separated prompt in a file, one after another",
+    },
+    SlashCommand {
+        label: "/stats",
+        name: "stats",
+        usage: "/stats",
+        description: "Show timing and token statistics for this session",
+    },
+    SlashCommand {
+        label: "/budget",
+        name: "budget",
+        usage: "/budget <10m|25tools|off>",
+        description: "Stop each prompt after a wall time or number of tool calls",
+    },
+    SlashCommand {
+        label: "/retry",
+        name: "retry",
+        usage: "/retry",
+        description: "Send the last prompt again and compare the answers",
+    },
+    SlashCommand {
+        label: "/profile",
+        name: "profile",
+        usage: "/profile <name|default>",
+        description: "Switch the codex profile from config.toml",
+    },
+    SlashCommand {
+        label: "/title",
+        name: "title",
+        usage: "/title <name|auto>",
+        description: "Rename this session (quote names with spaces)",
+    },
+    SlashCommand {
+        label: "/lang",
+        name: "lang",
+        usage: "/lang <language|off>",
+        description: "Ask codex to answer in a language for this session",
+    },
+    SlashCommand {
+        label: "/concise",
+        name: "concise",
+        usage: "/concise",
+        description: "Toggle asking codex for short answers in this session",
+    },
+    SlashCommand {
+        label: "/dryrun",
+        name: "dryrun",
+        usage: "/dryrun",
+        description: "Toggle showing the codex command instead of running it",
+    },
+    SlashCommand {
+        label: "/sessions",
+        name: "sessions",
+        usage: "/sessions",
+        description: "Open, export or delete a recently saved session",
+    },
+    SlashCommand {
+        label: "/inspect",
+        name: "inspect",
+        usage: "/inspect",
+        description: "Show the raw codex events from the last prompt",
+    },
+    SlashCommand {
+        label: "/diag",
+        name: "diag",
+        usage: "/diag",
+        description: "Copy diagnostics for a bug report to the clipboard",
+    },
+    SlashCommand {
+        label: "/help",
+        name: "help",
+        usage: "/help",
+        description: "List the available commands",
+    },
+];
 
 pub(super) struct NotificationOption {
     pub(super) name: &'static str,
@@ -65,6 +288,72 @@ pub(super) const NOTIFICATION_OPTIONS: [NotificationOption; 2] = [
     },
 ];
 
+pub(super) struct TokenLimitOption {
+    pub(super) name: &'static str,
+    pub(super) limit: u64,
+}
+
+pub(super) const TOKEN_LIMIT_OPTIONS: [TokenLimitOption; 4] = [
+    TokenLimitOption {
+        name: "Off",
+        limit: 0,
+    },
+    TokenLimitOption {
+        name: "8k tokens",
+        limit: 8_000,
+    },
+    TokenLimitOption {
+        name: "32k tokens",
+        limit: 32_000,
+    },
+    TokenLimitOption {
+        name: "128k tokens",
+        limit: 128_000,
+    },
+];
+
+pub(super) struct PromptAttachOption {
+    pub(super) name: &'static str,
+    pub(super) limit: usize,
+}
+
+pub(super) const PROMPT_ATTACH_OPTIONS: [PromptAttachOption; 5] = [
+    PromptAttachOption {
+        name: "Off",
+        limit: 0,
+    },
+    PromptAttachOption {
+        name: "Above 16 KB",
+        limit: 16 * 1024,
+    },
+    PromptAttachOption {
+        name: "Above 32 KB",
+        limit: 32 * 1024,
+    },
+    PromptAttachOption {
+        name: "Above 64 KB",
+        limit: 64 * 1024,
+    },
+    PromptAttachOption {
+        name: "Above 128 KB",
+        limit: 128 * 1024,
+    },
+];
+
+pub(super) struct CodexFileEditor {
+    file: Option<CodexFile>,
+    path: PathBuf,
+    text: String,
+    error: Option<String>,
+}
+
+#[derive(Default)]
+pub(super) struct Onboarding {
+    codex_path: Option<Option<PathBuf>>,
+    authenticated: Option<bool>,
+    cwd_chosen: bool,
+}
+
 #[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
 pub(super) struct MonitorKey {
     left: i32,
@@ -88,18 +377,27 @@ pub(super) struct TileCell {
 
 #[derive(Clone, Copy, Debug)]
 pub(super) struct TiledWindowState {
+    #[cfg_attr(not(target_os = "windows"), allow(dead_code))]
     cell: TileCell,
     restore: WindowRestoreState,
 }
 
-pub(crate) struct CodexAgentApp {
+pub struct CodexAgentApp {
     input: String,
     prompt_history: Vec<String>,
     prompt_history_index: Option<usize>,
     prompt_history_draft: Option<String>,
-    output: String,
+    draft_changed_at: Option<Instant>,
+    last_submitted_prompt: Option<String>,
+    input_tokens: Option<u64>,
+    token_warning_limit: u64,
+    token_submit_confirmation: Option<u64>,
+    transcript: Transcript,
     current_model: String,
     model_options: Vec<String>,
+    codex_profiles: CodexProfiles,
+    codex_profiles_modified: Option<SystemTime>,
+    codex_profile: Option<String>,
     notifications_enabled: bool,
     context_menu_state: ContextMenuState,
     context_menu_refresh_pending: bool,
@@ -107,46 +405,181 @@ pub(crate) struct CodexAgentApp {
     output_display_buffer: String,
     output_display_prompt_ranges: Vec<(usize, usize)>,
     output_display_line_kinds: Vec<(usize, OutputLineKind)>,
+    output_display_folds: Vec<FoldRegion>,
+    expanded_folds: HashSet<(FoldKind, usize)>,
+    response_truncate_limit: usize,
+    prompt_attach_limit: usize,
+    prompt_attachments: Vec<PathBuf>,
+    response_language: Option<String>,
+    pinned_response_language: Option<String>,
+    concise: bool,
+    concise_default: bool,
+    error_focus: Option<usize>,
+    pending_error_scroll: bool,
     output_display_response_start: usize,
     output_display_response_chars: usize,
     output_display_base_len: usize,
     output_display_source_len: usize,
     output_display_can_append: bool,
+    #[cfg(debug_assertions)]
+    output_display_generation: u64,
+    #[cfg(debug_assertions)]
+    output_display_diverged_generation: Option<u64>,
     output_display_dirty: bool,
     output_display_busy: bool,
     output_galley: Option<Arc<egui::Galley>>,
+    retired_output_galleys: Vec<Arc<egui::Galley>>,
+    output_layout_scratch: Option<egui::text::LayoutJob>,
     output_galley_width: Option<f32>,
     output_separator_y: Option<f32>,
+    output_turn_markers: Vec<(f32, String)>,
+    output_edit_id: Option<egui::Id>,
+    output_prompt_rects: Vec<egui::Rect>,
+    output_pin_rects: Vec<(PinTarget, egui::Rect)>,
+    output_response_ends: Vec<f32>,
+    copied_prompt: Option<(usize, Instant)>,
     input_galley: Option<Arc<egui::Galley>>,
     input_galley_width: Option<f32>,
     cwd_text: String,
+    cwd_problem: Option<String>,
     output_rows_cache: usize,
     input_rows_cache: usize,
     output_height_cache: f32,
     input_height_cache: f32,
     display_rows_width: Option<f32>,
+    output_scrollbar_width: f32,
+    resize_wrap_width: ResizeWrapWidth,
     text_layout_dirty: bool,
-    render_step: Option<u128>,
-    output_base: usize,
-    prompt_ranges: Vec<(usize, usize)>,
+    render_step: Option<(u128, PendingStatus)>,
     busy: bool,
     locked: bool,
+    locked_input: String,
     next_prompt_id: u64,
     active_prompt_id: Option<u64>,
     pending_input_focus: bool,
+    input_focus_retries: u32,
+    ime_composing: bool,
     picker_selection: Option<usize>,
     settings_menu_open: bool,
+    output_menu_pos: Option<egui::Pos2>,
+    codex_file_editor: Option<CodexFileEditor>,
+    codex_file_updates: Vec<CodexFileUpdate>,
+    codex_file_update_review: bool,
+    environment_notice: Vec<String>,
+    probes: ProbeService,
+    onboarding: Option<Onboarding>,
+    dirty_tree_warning_enabled: bool,
+    suggestions_enabled: bool,
+    inspect_events: bool,
+    persist_transcripts: bool,
+    event_log: Arc<Mutex<EventLog>>,
+    event_inspector: Option<EventInspector>,
+    recent_session: Option<RecentSessionFile>,
+    recent_sessions: Option<RecentSessionsView>,
+    recent_session_limit: usize,
+    notes: SessionNotes,
+    find: FindBar,
+    disk_space: DiskSpaceWarning,
+    disk_space_warning_mb: u64,
+    follow_up_suggestions: Vec<String>,
+    auto_continue: bool,
+    cancel_on_sleep: bool,
+    power_watcher: Option<PowerWatcher>,
+    clipboard_watch: bool,
+    clipboard_watcher: Option<ClipboardWatcher>,
+    warm_start: bool,
+    warm_codex: Option<WarmCodexState>,
+    clipboard_offer: Option<ClipboardOffer>,
+    own_clipboard_text: Option<String>,
+    sleeping_prompt: Option<u64>,
+    sleep_killed_prompt: Option<u64>,
+    sleep_retry_prompt: Option<String>,
+    tool_retry: Option<FailedToolStep>,
+    continuation_offered: bool,
+    continuations_used: usize,
+    continued_response_start: Option<usize>,
+    input_assists: Vec<InputAssist>,
+    paste_fence_offered: bool,
+    topic_suggestions_enabled: bool,
+    topic_shift_suggested: bool,
+    topic_shift_dismissed: bool,
+    topic_terms: Option<(usize, HashSet<String>)>,
+    changed_files: Vec<ChangedFile>,
+    changed_files_open: bool,
+    pending_approval: Option<ApprovalRequest>,
+    log_health: LogHealth,
+    diagnostics_pending: bool,
+    last_request: Option<(String, Option<ProjectConfig>, Option<ApprovalRequest>)>,
+    rate_limit_backoff: RateLimitBackoff,
+    rate_limit_wait: Option<RateLimitWait>,
+    compare_model: Option<String>,
+    comparison: Option<Comparison>,
+    last_response_range: Option<(usize, usize)>,
+    retry_baseline: Option<String>,
+    response_diff: Option<ResponseDiff>,
+    wake_mode: WakeMode,
+    pending_style: PendingStyle,
+    pending_interval: Duration,
+    show_stream_rate: bool,
+    stream_throughput: StreamThroughput,
+    turn_stats: Vec<TurnStats>,
+    theme: Theme,
+    response_dimming: ResponseDimming,
+    accessibility: AccessibilityPrefs,
+    system_light_theme: bool,
+    launcher_mode: LauncherMode,
+    redact_secrets: bool,
+    prompt_transformers: Vec<Transformer>,
+    output_processors: Vec<OutputProcessor>,
+    compact_mode: bool,
+    exclude_from_capture: bool,
+    capture_exclusion_error: Option<String>,
+    sandbox_mode: Option<String>,
+    status_strip: Option<StatusStrip>,
+    submit_key: SubmitKey,
+    prompt_whitespace: PromptWhitespace,
+    shortcuts: ShortcutMap,
+    shortcut_capture: Option<ShortcutAction>,
+    triggered_shortcut: Option<ShortcutAction>,
+    window_shadow: WindowShadow,
+    dirty_tree_warning_suppressed: bool,
+    dirty_submit_confirmation: Option<usize>,
+    dirty_submit_confirmed: bool,
+    project_trust_confirmation: bool,
+    secret_submit_confirmation: Option<&'static str>,
+    clear_confirmation: Option<usize>,
+    secret_submit_decision: Option<SecretSubmitDecision>,
+    secret_warning_suppressed: bool,
+    project_config: ProjectConfigCache,
+    project_config_enabled: bool,
+    hook_output_lines: Option<usize>,
+    cancelling_prompt: Option<u64>,
+    batch: Option<PromptBatch>,
+    prompt_phase: PromptPhase,
+    resume_retry_pending: bool,
+    session_usage: SessionUsage,
+    snapshot_len: usize,
+    snapshot_saved_at: Option<Instant>,
+    relaunch_snapshot_len: usize,
+    relaunch_snapshot_at: Option<Instant>,
     was_focused: bool,
     drag_armed: bool,
     window_dragging: bool,
     was_minimized: bool,
+    last_monitor_size: Option<Vec2>,
+    last_pixels_per_point: Option<f32>,
+    last_bounds_check: Option<Instant>,
     maximized: bool,
     resizing: bool,
+    mouse_passthrough: bool,
     resize_start_inner_size: Option<Vec2>,
     user_height_override: Option<f32>,
     min_inner_size: Option<Vec2>,
     last_inner_size: Option<Vec2>,
+    pending_resize: Option<Vec2>,
+    resize_sent_at: Option<Instant>,
     last_outer_size: Option<Vec2>,
+    #[cfg(target_os = "windows")]
     last_viewport_outer_rect: Option<egui::Rect>,
     pre_maximize_state: Option<WindowRestoreState>,
     tiled_state: Option<TiledWindowState>,
@@ -157,16 +590,22 @@ pub(crate) struct CodexAgentApp {
     ctx: egui::Context,
     tx: mpsc::Sender<AppEvent>,
     rx: mpsc::Receiver<AppEvent>,
-    running_prompt: Arc<Mutex<Option<RunningPrompt>>>,
+    backend: Arc<dyn PromptBackend>,
+    running_prompt: Arc<Mutex<RunningPrompts>>,
     shared_stream: Arc<Mutex<PromptStreamState>>,
     stream_notification_pending: Arc<AtomicBool>,
     stream_generation: u64,
     stream_visible_len: usize,
+    stream_events_received: u64,
+    stream_mutations_applied: u64,
     session_id: Option<String>,
+    dry_run: bool,
     cancelled_resume_context: Option<String>,
     setup_state: SetupState,
     install_stdin: Arc<Mutex<Option<ChildStdin>>>,
     positioned: bool,
-    title_set: bool,
+    session_title: SessionTitle,
+    codex_titles: bool,
+    window_title: String,
     hwnd: *mut c_void,
 }
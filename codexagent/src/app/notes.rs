@@ -0,0 +1,112 @@
+use std::time::{Duration, Instant};
+
+use eframe::egui;
+
+use crate::config::LINE_HEIGHT;
+use crate::logging;
+
+use super::CodexAgentApp;
+use super::layout::PROMPT_PREFIX_CHIP_HEIGHT;
+
+pub(super) const NOTES_ROWS: usize = 5;
+pub(super) const NOTES_ID: &str = "session-notes";
+const NOTES_SEPARATOR_HEIGHT: f32 = 9.0;
+const NOTES_SAVE_DELAY: Duration = Duration::from_secs(2);
+
+#[derive(Default)]
+pub(super) struct SessionNotes {
+    pub(super) text: String,
+    pub(super) open: bool,
+    edited_at: Option<Instant>,
+}
+
+impl SessionNotes {
+    pub(super) fn restored(text: String) -> Self {
+        Self {
+            open: !text.is_empty(),
+            text,
+            edited_at: None,
+        }
+    }
+
+    pub(super) fn settled(&self) -> bool {
+        self.edited_at
+            .is_none_or(|edited| edited.elapsed() >= NOTES_SAVE_DELAY)
+    }
+
+    pub(super) fn note_edit(&mut self) {
+        self.edited_at = Some(Instant::now());
+    }
+}
+
+pub(super) fn notes_markdown(notes: &str) -> Option<String> {
+    let notes = notes.trim();
+    (!notes.is_empty()).then(|| format!("## Notes\n\n{}", notes))
+}
+
+impl CodexAgentApp {
+    pub(super) fn notes_height(&self) -> f32 {
+        if !self.notes.open {
+            return 0.0;
+        }
+        PROMPT_PREFIX_CHIP_HEIGHT + NOTES_ROWS as f32 * LINE_HEIGHT + NOTES_SEPARATOR_HEIGHT
+    }
+
+    pub(super) fn notes_focused(&self) -> bool {
+        self.notes.open
+            && self
+                .ctx
+                .memory(|mem| mem.has_focus(egui::Id::new(NOTES_ID)))
+    }
+
+    pub(super) fn toggle_notes(&mut self) {
+        if self.notes.open {
+            self.close_notes();
+            return;
+        }
+        logging::trace("notes panel opened");
+        self.notes.open = true;
+        self.ctx
+            .memory_mut(|mem| mem.request_focus(egui::Id::new(NOTES_ID)));
+        self.invalidate_text_layout();
+        self.resize_for_text();
+    }
+
+    pub(super) fn close_notes(&mut self) {
+        if !self.notes.open {
+            return;
+        }
+        logging::trace("notes panel closed");
+        self.notes.open = false;
+        self.pending_input_focus = true;
+        self.invalidate_text_layout();
+        self.resize_for_text();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn notes_export_under_a_heading_unless_blank() {
+        assert_eq!(
+            notes_markdown("\n- ask about the lexer\n- rerun CI  \n").as_deref(),
+            Some("## Notes\n\n- ask about the lexer\n- rerun CI")
+        );
+        assert_eq!(notes_markdown(" \n\t\n"), None);
+    }
+
+    #[test]
+    fn restored_notes_open_the_panel_and_edits_settle() {
+        assert!(SessionNotes::restored("follow up".to_owned()).open);
+        assert!(!SessionNotes::restored(String::new()).open);
+
+        let mut notes = SessionNotes::default();
+        assert!(notes.settled());
+        notes.note_edit();
+        assert!(!notes.settled(), "saving waits for typing to pause");
+        notes.edited_at = Instant::now().checked_sub(NOTES_SAVE_DELAY);
+        assert!(notes.settled());
+    }
+}
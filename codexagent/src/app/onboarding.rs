@@ -0,0 +1,146 @@
+use std::path::PathBuf;
+use std::thread;
+
+use crate::config::{CANCEL_BUTTON_HEIGHT, CODEX_INSTALL_URL, set_onboarding_complete};
+use crate::events::AppEvent;
+use crate::logging;
+use crate::prompt::{launch_codex_login, resolve_codex_path};
+use crate::runtime::{
+    codex_authenticated, current_cwd_text, open_in_shell, pick_folder, set_process_cwd,
+    working_directory_chosen,
+};
+
+use super::{CodexAgentApp, Onboarding};
+
+pub(super) const ONBOARDING_ROWS: usize = 5;
+pub(super) const ONBOARDING_ROW_SPACING: f32 = 4.0;
+
+impl CodexAgentApp {
+    pub(super) fn onboarding_height(&self) -> f32 {
+        if self.onboarding.is_none() {
+            return 0.0;
+        }
+        (CANCEL_BUTTON_HEIGHT + ONBOARDING_ROW_SPACING) * ONBOARDING_ROWS as f32
+    }
+
+    pub(super) fn start_onboarding(&mut self) {
+        logging::trace("starting first-run onboarding");
+        self.onboarding = Some(Onboarding::default());
+        self.refresh_onboarding();
+    }
+
+    pub(super) fn refresh_onboarding(&mut self) {
+        let Some(onboarding) = self.onboarding.as_mut() else {
+            return;
+        };
+        onboarding.codex_path = None;
+        onboarding.authenticated = None;
+        onboarding.cwd_chosen = working_directory_chosen();
+        let tx = self.tx.clone();
+        let ctx = self.ctx.clone();
+        thread::spawn(move || {
+            let path = resolve_codex_path();
+            if tx.send(AppEvent::CodexPath(path)).is_err() {
+                logging::error("failed to deliver codex path probe to app");
+            }
+            ctx.request_repaint();
+        });
+        let tx = self.tx.clone();
+        let ctx = self.ctx.clone();
+        thread::spawn(move || {
+            let authenticated = codex_authenticated();
+            if tx.send(AppEvent::CodexAuth(authenticated)).is_err() {
+                logging::error("failed to deliver codex auth probe to app");
+            }
+            ctx.request_repaint();
+        });
+    }
+
+    pub(super) fn set_onboarding_codex_path(&mut self, path: Option<PathBuf>) {
+        match path.as_deref() {
+            Some(path) => logging::trace(format!("onboarding: codex found at {}", path.display())),
+            None => logging::trace("onboarding: codex not found"),
+        }
+        if let Some(onboarding) = self.onboarding.as_mut() {
+            onboarding.codex_path = Some(path);
+        }
+    }
+
+    pub(super) fn set_onboarding_authenticated(&mut self, authenticated: bool) {
+        logging::trace(format!("onboarding: codex authenticated {}", authenticated));
+        if let Some(onboarding) = self.onboarding.as_mut() {
+            onboarding.authenticated = Some(authenticated);
+        }
+    }
+
+    pub(super) fn open_codex_install_instructions(&mut self) {
+        if let Err(error) = open_in_shell(CODEX_INSTALL_URL) {
+            self.push_local_error(&format!("Failed to open install instructions: {}", error));
+            self.finish_local_change();
+        }
+    }
+
+    pub(super) fn start_codex_login(&mut self) {
+        if let Err(error) = launch_codex_login() {
+            self.push_local_error(&format!("Failed to start codex login: {}", error));
+            self.finish_local_change();
+        }
+    }
+
+    pub(super) fn pick_working_directory(&self) {
+        let owner = self.hwnd as usize;
+        let tx = self.tx.clone();
+        let ctx = self.ctx.clone();
+        thread::spawn(move || {
+            let path = pick_folder(owner, "Choose a working directory for Codex");
+            if tx.send(AppEvent::FolderPicked(path)).is_err() {
+                logging::error("failed to deliver folder selection to app");
+            }
+            ctx.request_repaint();
+        });
+    }
+
+    pub(super) fn apply_picked_folder(&mut self, path: Option<PathBuf>) {
+        let Some(path) = path else {
+            logging::trace("folder picker cancelled");
+            return;
+        };
+        if let Err(error) = set_process_cwd(&path) {
+            self.push_local_error(&format!(
+                "Failed to change directory to {}: {}",
+                path.display(),
+                error
+            ));
+            self.finish_local_change();
+            return;
+        }
+        self.cwd_text = current_cwd_text();
+        self.set_cwd_problem(None);
+        self.drop_warm_codex("the working directory changed");
+        self.refresh_project_config();
+        if let Some(onboarding) = self.onboarding.as_mut() {
+            onboarding.cwd_chosen = true;
+        }
+    }
+
+    pub(super) fn finish_onboarding(&mut self) {
+        if self.onboarding.take().is_none() {
+            return;
+        }
+        if let Err(error) = set_onboarding_complete() {
+            logging::error(format!("failed to record onboarding completion: {}", error));
+        }
+        logging::trace("onboarding finished");
+        self.pending_input_focus = true;
+        self.invalidate_text_layout();
+        self.resize_for_text();
+    }
+}
+
+impl Onboarding {
+    pub(super) fn complete(&self) -> bool {
+        matches!(self.codex_path, Some(Some(_)))
+            && self.authenticated == Some(true)
+            && self.cwd_chosen
+    }
+}
@@ -1,15 +1,24 @@
+use std::sync::Arc;
 use std::sync::atomic::Ordering;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
 use eframe::egui::{self, Vec2};
 
 use crate::config::{
-    APP_NAME, CANCELLED_TEXT, PENDING_ANIMATION_INTERVAL, save_prompt_history_prompts,
+    PARTIAL_RESPONSE_EXPORT_TEXT, PARTIAL_RESPONSE_TEXT, PendingStyle, SESSION_DIVIDER_TEXT,
+    TOPIC_DIVIDER_TEXT, save_prompt_history_prompts,
 };
 use crate::logging;
+use crate::plaintext::markdown_to_plain;
+use crate::postprocess::process_output;
+use crate::usage::{SessionUsage, estimate_tokens};
 
-use super::render::{append_output_display, pending_dots, prepare_output_display};
-use super::{CodexAgentApp, SetupState};
+use super::render::{
+    FoldKind, FoldRegion, OutputLineKind, PendingStatus, TextChange, append_output_display,
+    classify_text_change, prepare_output_display, push_pending_status, recycle_layout_job,
+};
+use super::transcript::{PinTarget, SegmentKind};
+use super::{CodexAgentApp, ResponseDiff, SetupState};
 
 const RETAINED_RENDER_CAPACITY: usize = 1024;
 const MAX_IDLE_RENDER_CAPACITY: usize = 16 * 1024;
@@ -17,12 +26,16 @@ const LAYOUT_EPSILON: f32 = 0.1;
 
 impl CodexAgentApp {
     pub(super) fn clear_output_buffers(&mut self) {
-        self.output.clear();
-        self.output_base = 0;
-        self.prompt_ranges.clear();
+        self.transcript.clear();
+        self.last_response_range = None;
+        self.continuation_offered = false;
+        self.response_diff = None;
         self.output_display_buffer.clear();
         self.output_display_prompt_ranges.clear();
         self.output_display_line_kinds.clear();
+        self.output_display_folds.clear();
+        self.expanded_folds.clear();
+        self.error_focus = None;
         self.output_display_response_start = 0;
         self.output_display_response_chars = 0;
         self.output_display_base_len = 0;
@@ -30,12 +43,42 @@ impl CodexAgentApp {
         self.output_display_can_append = false;
         self.output_display_dirty = true;
         self.output_display_busy = false;
-        self.output_galley = None;
+        self.retire_output_galley();
         self.output_galley_width = None;
         self.output_separator_y = None;
         self.reset_stream_progress();
     }
 
+    // A replaced galley stays in egui's galley cache until a frame passes
+    // without it, so it is kept until then and its job's buffers are reused
+    // for the next layout.
+    pub(super) fn retire_output_galley(&mut self) {
+        if let Some(galley) = self.output_galley.take() {
+            self.retired_output_galleys.push(galley);
+        }
+    }
+
+    pub(super) fn recycle_retired_output_galleys(&mut self) {
+        let mut index = 0;
+        while index < self.retired_output_galleys.len() {
+            if Arc::strong_count(&self.retired_output_galleys[index]) > 1 {
+                index += 1;
+                continue;
+            }
+            let galley = self.retired_output_galleys.swap_remove(index);
+            let Some(job) = recycle_layout_job(galley) else {
+                continue;
+            };
+            let larger = self
+                .output_layout_scratch
+                .as_ref()
+                .is_none_or(|scratch| scratch.text.capacity() < job.text.capacity());
+            if larger {
+                self.output_layout_scratch = Some(job);
+            }
+        }
+    }
+
     pub(super) fn mark_output_for_rebuild(&mut self) {
         self.output_display_can_append = false;
     }
@@ -47,7 +90,7 @@ impl CodexAgentApp {
 
     pub(super) fn invalidate_text_layout(&mut self) {
         self.text_layout_dirty = true;
-        self.output_galley = None;
+        self.retire_output_galley();
         self.output_galley_width = None;
         self.output_separator_y = None;
         self.input_galley = None;
@@ -55,14 +98,21 @@ impl CodexAgentApp {
     }
 
     pub(super) fn invalidate_input_layout(&mut self) {
+        self.input_tokens = None;
         self.text_layout_dirty = true;
         self.input_galley = None;
         self.input_galley_width = None;
     }
 
+    pub(super) fn input_token_estimate(&mut self) -> u64 {
+        *self
+            .input_tokens
+            .get_or_insert_with(|| estimate_tokens(&self.input))
+    }
+
     pub(super) fn invalidate_output_layout(&mut self) {
         self.text_layout_dirty = true;
-        self.output_galley = None;
+        self.retire_output_galley();
         self.output_galley_width = None;
         self.output_separator_y = None;
         self.output_display_dirty = true;
@@ -71,22 +121,39 @@ impl CodexAgentApp {
     pub(super) fn can_clear(&self) -> bool {
         !self.busy
             && self.setup_state == SetupState::Ready
-            && (!self.output.is_empty() || self.session_id.is_some())
+            && (self.transcript.len() > self.transcript.pinned_end()
+                || self.session_id.is_some()
+                || !self.notes.text.is_empty())
     }
 
     pub(super) fn clear_session(&mut self) {
         self.input.clear();
         self.reset_prompt_history_navigation();
+        let pinned = self.transcript.pinned_texts();
         self.clear_output_buffers();
+        if !pinned.is_empty() {
+            logging::trace(format!("keeping {} pinned responses", pinned.len()));
+            self.transcript.restore_pinned(&pinned);
+        }
         self.session_id = None;
+        self.recent_session = None;
+        self.tool_retry = None;
+        self.session_usage = SessionUsage::default();
+        self.turn_stats.clear();
+        self.follow_up_suggestions.clear();
+        self.changed_files.clear();
+        self.changed_files_open = false;
+        self.drop_pending_approval();
+        self.remove_prompt_attachments();
+        self.reset_response_language();
+        self.reset_concise();
         self.cancelled_resume_context = None;
         self.active_prompt_id = None;
         self.locked = false;
         self.pending_started_at = None;
         self.pending_input_focus = true;
-        self.title_set = false;
-        self.ctx
-            .send_viewport_cmd(egui::ViewportCommand::Title(APP_NAME.to_owned()));
+        self.reset_session_title();
+        self.notes.text.clear();
         self.stream_notification_pending
             .store(false, Ordering::Relaxed);
         self.clear_render_buffer();
@@ -121,29 +188,26 @@ impl CodexAgentApp {
             self.cancelled_resume_context = None;
             return;
         }
-        let context = build_resume_context(&self.output, &self.prompt_ranges);
+        let context = self.transcript.resume_context();
         self.cancelled_resume_context = (!context.is_empty()).then_some(context);
     }
 
     pub(super) fn pending_step(&self) -> Option<u128> {
         self.busy.then(|| {
+            if self.pending_style == PendingStyle::None {
+                return 0;
+            }
             self.pending_started_at
-                .map(|started| {
-                    started.elapsed().as_millis() / PENDING_ANIMATION_INTERVAL.as_millis()
-                })
+                .map(|started| started.elapsed().as_millis() / self.pending_interval.as_millis())
                 .unwrap_or(0)
         })
     }
 
     pub(super) fn pending_repaint_delay(&self) -> Option<Duration> {
-        if !self.busy {
+        if !self.busy || self.pending_style == PendingStyle::None {
             return None;
         }
-        let interval = PENDING_ANIMATION_INTERVAL;
-        let interval_ms = interval.as_millis();
-        if interval_ms == 0 {
-            return Some(Duration::ZERO);
-        }
+        let interval_ms = self.pending_interval.as_millis();
         let elapsed_ms = self
             .pending_started_at
             .map(|started| started.elapsed().as_millis())
@@ -152,19 +216,24 @@ impl CodexAgentApp {
         Some(Duration::from_millis(remaining_ms as u64))
     }
 
-    pub(super) fn sync_render_buffer(&mut self, step: u128) {
-        self.render_buffer.clear();
-        let dots = pending_dots(step);
-        if self.output.is_empty() {
-            self.render_buffer.push_str(dots);
-            self.output_galley = None;
-            return;
+    pub(super) fn pending_status(&self) -> PendingStatus {
+        let queued = self.batch.as_ref().map_or(0, |batch| batch.queued());
+        if self.cancelling_prompt.is_some() {
+            PendingStatus::Cancelling
+        } else if let Some(wait) = self.rate_limit_wait.as_ref() {
+            PendingStatus::RetryingIn(wait.remaining_secs(Instant::now()))
+        } else if self.resume_retry_pending {
+            PendingStatus::Retrying
+        } else if queued > 0 {
+            PendingStatus::Queued(queued)
+        } else {
+            PendingStatus::Active
         }
-        if !self.output.ends_with('\n') {
-            self.render_buffer.push('\n');
-        }
-        self.render_buffer.push_str(dots);
-        self.output_galley = None;
+    }
+
+    pub(super) fn sync_render_buffer(&mut self, step: u128, status: PendingStatus) {
+        self.render_buffer.clear();
+        push_pending_status(&mut self.render_buffer, status, self.pending_style, step);
     }
 
     pub(super) fn clear_render_buffer(&mut self) {
@@ -196,63 +265,125 @@ impl CodexAgentApp {
     }
 
     pub(super) fn sync_output_display_buffer(&mut self) {
+        let selection = self.output_edit_id.and_then(|id| {
+            let state = egui::TextEdit::load_state(&self.ctx, id)?;
+            let range = state.cursor.char_range()?;
+            (range.primary != range.secondary).then_some((id, state, range))
+        });
+        let Some((id, mut state, range)) = selection else {
+            self.sync_output_display_text();
+            return;
+        };
+        let previous = self.output_display_buffer.clone();
+        self.sync_output_display_text();
+        let limit = match classify_text_change(&previous, &self.output_display_buffer) {
+            TextChange::Unchanged | TextChange::Appended => usize::MAX,
+            TextChange::Rewritten { common_chars } => common_chars,
+        };
+        let clamp = |cursor: egui::text::CCursor| egui::text::CCursor {
+            index: cursor.index.min(limit),
+            ..cursor
+        };
+        state.cursor.set_char_range(Some(egui::text::CCursorRange {
+            primary: clamp(range.primary),
+            secondary: clamp(range.secondary),
+        }));
+        state.store(&self.ctx, id);
+    }
+
+    fn sync_output_display_text(&mut self) {
         if self.output_display_dirty {
             truncate_output_display_suffix(
                 &mut self.output_display_buffer,
                 self.output_display_base_len,
                 &mut self.output_display_busy,
             );
-            if self.output_display_can_append && self.output.len() >= self.output_display_source_len
+            let output = self.transcript.text();
+            let output_len = output.len();
+            if let Some(diff) = self
+                .response_diff
+                .as_mut()
+                .filter(|diff| diff.showing && diff.range.1 != output_len)
+            {
+                diff.showing = false;
+                self.output_display_can_append = false;
+            }
+            let diff_source = self
+                .response_diff
+                .as_ref()
+                .filter(|diff| diff.showing && diff.range.1 == output_len)
+                .and_then(|diff| {
+                    let text = diff.text.as_deref()?;
+                    Some(format!("{}{}", &output[..diff.range.0], text))
+                });
+            if self.output_display_can_append
+                && diff_source.is_none()
+                && output_len >= self.output_display_source_len
+                && !output[self.output_display_source_len..].contains('\x1D')
             {
                 let previous_len = self.output_display_source_len;
                 let line_start = previous_len == 0
-                    || self
-                        .output
+                    || output
                         .as_bytes()
                         .get(previous_len.saturating_sub(1))
                         .is_some_and(|byte| *byte == b'\n');
                 append_output_display(
-                    &self.output[previous_len..],
+                    &output[previous_len..],
                     line_start,
                     &mut self.output_display_buffer,
                     &mut self.output_display_line_kinds,
                 );
                 self.output_display_base_len = self.output_display_buffer.len();
-                self.output_display_source_len = self.output.len();
+                self.output_display_source_len = output_len;
+                #[cfg(debug_assertions)]
+                self.check_output_display_divergence();
             } else {
+                #[cfg(debug_assertions)]
+                {
+                    self.output_display_generation += 1;
+                }
                 self.output_display_response_start = prepare_output_display(
-                    &self.output,
-                    &self.prompt_ranges,
-                    self.output_base,
+                    diff_source.as_deref().unwrap_or(output),
+                    self.transcript.prompt_ranges(),
+                    self.transcript.response_start(),
+                    &self.expanded_folds,
+                    self.response_truncate_limit,
+                    self.prompt_attach_limit,
                     &mut self.output_display_buffer,
                     &mut self.output_display_prompt_ranges,
                     &mut self.output_display_line_kinds,
+                    &mut self.output_display_folds,
                 );
                 self.output_display_response_chars = self.output_display_buffer
                     [..self.output_display_response_start]
                     .chars()
                     .count();
                 self.output_display_base_len = self.output_display_buffer.len();
-                self.output_display_source_len = self.output.len();
+                self.output_display_source_len = output_len;
             }
             self.output_display_can_append = true;
             self.output_display_dirty = false;
-            self.output_galley = None;
+            self.retire_output_galley();
             self.output_separator_y = None;
         }
         if self.busy {
-            let suffix = self.render_buffer.as_str();
+            let base = &self.output_display_buffer[..self.output_display_base_len];
+            let suffix = if base.is_empty() || base.ends_with('\n') {
+                ""
+            } else {
+                "\n"
+            };
             let display = self.output_display_buffer.as_str();
             let needs_suffix = !self.output_display_busy
-                || !display
+                || display
                     .get(self.output_display_base_len..)
-                    .is_some_and(|current| current == suffix);
+                    .is_none_or(|current| current != suffix);
             if needs_suffix {
                 self.output_display_buffer
                     .truncate(self.output_display_base_len);
                 self.output_display_buffer.push_str(suffix);
                 self.output_display_busy = true;
-                self.output_galley = None;
+                self.retire_output_galley();
                 self.output_separator_y = None;
             }
             return;
@@ -263,11 +394,236 @@ impl CodexAgentApp {
                 self.output_display_base_len,
                 &mut self.output_display_busy,
             );
-            self.output_galley = None;
+            self.retire_output_galley();
             self.output_separator_y = None;
         }
     }
 
+    #[cfg(debug_assertions)]
+    fn check_output_display_divergence(&mut self) {
+        if self.output_display_diverged_generation == Some(self.output_display_generation) {
+            return;
+        }
+        if !self.transcript.cache_matches_segments() {
+            self.output_display_diverged_generation = Some(self.output_display_generation);
+            logging::error("transcript display cache no longer matches its segments");
+            return;
+        }
+        let mut rebuilt = String::new();
+        let mut prompt_ranges = Vec::new();
+        let mut line_kinds = Vec::new();
+        let mut folds = Vec::new();
+        prepare_output_display(
+            self.transcript.text(),
+            self.transcript.prompt_ranges(),
+            self.transcript.response_start(),
+            &self.expanded_folds,
+            self.response_truncate_limit,
+            self.prompt_attach_limit,
+            &mut rebuilt,
+            &mut prompt_ranges,
+            &mut line_kinds,
+            &mut folds,
+        );
+        if rebuilt == self.output_display_buffer && line_kinds == self.output_display_line_kinds {
+            return;
+        }
+        self.output_display_diverged_generation = Some(self.output_display_generation);
+        let common = rebuilt
+            .bytes()
+            .zip(self.output_display_buffer.bytes())
+            .take_while(|(lhs, rhs)| lhs == rhs)
+            .count();
+        logging::error(format!(
+            "incremental output display diverged from a full rebuild in generation {} at byte {} ({} vs {} bytes, {} vs {} line kinds)",
+            self.output_display_generation,
+            common,
+            self.output_display_buffer.len(),
+            rebuilt.len(),
+            self.output_display_line_kinds.len(),
+            line_kinds.len()
+        ));
+    }
+
+    pub(super) fn visible_response_diff(&self) -> Option<&ResponseDiff> {
+        self.response_diff
+            .as_ref()
+            .filter(|diff| !self.busy && diff.range.1 == self.transcript.len())
+    }
+
+    pub(super) fn processed_output(&self, text: String) -> String {
+        if self.output_processors.is_empty() {
+            return text;
+        }
+        process_output(text, &self.output_processors, &self.cwd_text)
+    }
+
+    pub(super) fn copy_plain_text(&self, output_id: Option<egui::Id>) {
+        let markdown = output_id
+            .and_then(|id| self.output_selection_text(id))
+            .unwrap_or_else(|| strip_line_markers(self.last_response_text(), false));
+        let plain = markdown_to_plain(&markdown);
+        if plain.is_empty() {
+            logging::trace("copy as plain text skipped; nothing selected and no response");
+            return;
+        }
+        logging::trace(format!("copied {} bytes as plain text", plain.len()));
+        self.ctx.copy_text(self.processed_output(plain));
+    }
+
+    pub(super) fn last_response_text(&self) -> &str {
+        let start = self.output_display_response_start;
+        let end = self
+            .output_display_line_kinds
+            .iter()
+            .find(|&&(line_start, kind)| line_start >= start && kind == OutputLineKind::Divider)
+            .map_or(self.output_display_buffer.len(), |&(line_start, _)| {
+                line_start
+            });
+        self.output_display_buffer
+            .get(start..end)
+            .unwrap_or_default()
+            .trim()
+    }
+
+    pub(super) fn full_transcript_text(&self, turn_markers: bool) -> String {
+        let output = &self.transcript.text()[self.transcript.pinned_end()..];
+        let mut transcript = String::with_capacity(output.len());
+        if self.transcript.has_pins() {
+            transcript.push_str("## Pinned\n\n");
+            for pinned in self.transcript.pinned_texts() {
+                transcript.push_str(&strip_line_markers(&pinned, turn_markers));
+                transcript.push_str("\n\n");
+            }
+            transcript.push_str("## Transcript\n\n");
+        }
+        transcript.push_str(&strip_line_markers(output, turn_markers));
+        transcript
+    }
+
+    pub(super) fn toggle_pin(&mut self, target: PinTarget) {
+        match target {
+            PinTarget::Turn(turn) => {
+                let pinned = self.transcript.toggle_turn_pin(turn);
+                logging::trace(format!(
+                    "{} response #{}",
+                    if pinned { "pinned" } else { "unpinned" },
+                    turn + 1
+                ));
+            }
+            PinTarget::Block(index) => {
+                let Some((start, len)) = self.transcript.unpin_block(index) else {
+                    return;
+                };
+                logging::trace(format!("removed pinned response {}", index + 1));
+                let shift = |offset: usize| if offset > start { offset - len } else { offset };
+                self.last_response_range = self
+                    .last_response_range
+                    .map(|(range_start, end)| (shift(range_start), shift(end)));
+                self.response_diff = None;
+                self.expanded_folds.clear();
+                self.error_focus = None;
+                self.mark_output_for_rebuild();
+            }
+        }
+        self.refresh_after_output_rewrite();
+    }
+
+    pub(super) fn toggle_fold(&mut self, clean_offset: usize) -> bool {
+        let Some(region) = self
+            .output_display_folds
+            .iter()
+            .find(|region| {
+                region.collapsible
+                    && clean_offset >= region.clean_start
+                    && clean_offset < region.header_end
+            })
+            .copied()
+        else {
+            return false;
+        };
+        let key = (region.kind, region.raw_start);
+        if !self.expanded_folds.remove(&key) {
+            self.expanded_folds.insert(key);
+        }
+        self.refresh_after_output_rewrite();
+        true
+    }
+
+    pub(super) fn error_fold(&self, index: usize) -> Option<&FoldRegion> {
+        self.output_display_folds
+            .iter()
+            .filter(|fold| fold.kind == FoldKind::Error)
+            .nth(index)
+    }
+
+    pub(super) fn focus_next_error(&mut self, forward: bool) {
+        let count = self
+            .output_display_folds
+            .iter()
+            .filter(|fold| fold.kind == FoldKind::Error)
+            .count();
+        if count == 0 {
+            return;
+        }
+        let index = match (self.error_focus, forward) {
+            (Some(index), true) => (index + 1) % count,
+            (Some(index), false) => (index + count - 1) % count,
+            (None, true) => 0,
+            (None, false) => count - 1,
+        };
+        self.error_focus = Some(index.min(count - 1));
+        self.pending_error_scroll = true;
+        self.ctx.request_repaint();
+    }
+
+    pub(super) fn start_new_session(&mut self) {
+        if self.busy || self.session_id.is_none() {
+            return;
+        }
+        logging::trace("starting a new session and keeping the transcript");
+        self.push_session_divider(SESSION_DIVIDER_TEXT);
+    }
+
+    pub(super) fn start_new_topic(&mut self) -> bool {
+        if self.busy || self.transcript.recent_turns_text(1).is_none() {
+            return false;
+        }
+        logging::trace("starting a new topic and keeping the transcript");
+        self.push_session_divider(TOPIC_DIVIDER_TEXT);
+        true
+    }
+
+    fn push_session_divider(&mut self, divider: &str) {
+        self.session_id = None;
+        self.reset_session_title();
+        self.cancelled_resume_context = None;
+        self.tool_retry = None;
+        self.follow_up_suggestions.clear();
+        self.continuation_offered = false;
+        self.topic_shift_suggested = false;
+        self.ensure_output_spacing();
+        self.transcript
+            .push(SegmentKind::Notice, &format!("\x1A{}\n\n", divider));
+        self.pending_input_focus = true;
+        self.refresh_after_output_change();
+    }
+
+    pub(super) fn output_selection_text(&self, output_id: egui::Id) -> Option<String> {
+        let range = egui::TextEdit::load_state(&self.ctx, output_id)?
+            .cursor
+            .char_range()?;
+        let [start, end] = range.sorted();
+        let text: String = self
+            .output_display_buffer
+            .chars()
+            .skip(start.index)
+            .take(end.index - start.index)
+            .filter(|c| !matches!(c, '\x19' | '\x1A' | '\x1C'..='\x1F'))
+            .collect();
+        (!text.is_empty()).then_some(text)
+    }
+
     pub(super) fn persist_history(&self) {
         if let Err(error) = save_prompt_history_prompts(&self.prompt_history) {
             logging::error(format!("failed to save prompt history: {}", error));
@@ -288,6 +644,7 @@ impl CodexAgentApp {
         })
     }
 
+    #[cfg(target_os = "windows")]
     pub(super) fn same_rect(lhs: Option<egui::Rect>, rhs: Option<egui::Rect>) -> bool {
         match (lhs, rhs) {
             (Some(lhs), Some(rhs)) => {
@@ -302,65 +659,29 @@ impl CodexAgentApp {
     }
 }
 
-fn truncate_output_display_suffix(buffer: &mut String, base_len: usize, busy: &mut bool) {
-    if !*busy {
-        return;
-    }
-    buffer.truncate(base_len);
-    *busy = false;
-}
-
-fn build_resume_context(output: &str, prompt_ranges: &[(usize, usize)]) -> String {
-    let mut transcript = String::new();
-    for (index, &(start, end)) in prompt_ranges.iter().enumerate() {
-        let Some(prompt) = output.get(start..end) else {
-            continue;
-        };
-        let prompt = prompt.trim();
-        if prompt.is_empty() {
+pub(super) fn strip_line_markers(text: &str, turn_markers: bool) -> String {
+    let mut stripped = String::with_capacity(text.len());
+    for line in text.split_inclusive('\n') {
+        if !turn_markers && line.starts_with('\x19') {
             continue;
         }
-        if !transcript.is_empty() {
-            transcript.push_str("\n\n");
-        }
-        transcript.push_str("User:\n");
-        transcript.push_str(prompt);
-        let response_end = prompt_ranges
-            .get(index + 1)
-            .map(|(next_start, _)| *next_start)
-            .unwrap_or(output.len());
-        let Some(response) = output.get(end..response_end) else {
+        if line.strip_prefix('\x1C').map(str::trim_end) == Some(PARTIAL_RESPONSE_TEXT) {
+            stripped.push_str(PARTIAL_RESPONSE_EXPORT_TEXT);
+            stripped.push('\n');
             continue;
-        };
-        append_resume_response(&mut transcript, response.trim_matches('\n'));
+        }
+        stripped.push_str(
+            line.strip_prefix(['\x19', '\x1A', '\x1C', '\x1D', '\x1E', '\x1F'])
+                .unwrap_or(line),
+        );
     }
-    transcript
+    stripped
 }
 
-fn append_resume_response(transcript: &mut String, response: &str) {
-    if response.is_empty() {
+fn truncate_output_display_suffix(buffer: &mut String, base_len: usize, busy: &mut bool) {
+    if !*busy {
         return;
     }
-    let mut current_label = "";
-    for line in response.lines() {
-        let (label, content) = match line.chars().next() {
-            Some('\x1D') => ("System", &line[1..]),
-            Some('\x1E') => ("Assistant reasoning", &line[1..]),
-            Some('\x1F') => ("Assistant note", &line[1..]),
-            _ if line == CANCELLED_TEXT => ("System", line),
-            _ => ("Assistant", line),
-        };
-        if content.is_empty() && current_label.is_empty() {
-            continue;
-        }
-        if label != current_label {
-            transcript.push_str("\n\n");
-            transcript.push_str(label);
-            transcript.push_str(":\n");
-            current_label = label;
-        } else if !transcript.ends_with('\n') {
-            transcript.push('\n');
-        }
-        transcript.push_str(content);
-    }
+    buffer.truncate(base_len);
+    *busy = false;
 }
@@ -0,0 +1,117 @@
+use crate::config::set_cancel_on_sleep;
+use crate::logging;
+use crate::runtime::{PowerEvent, process_running};
+
+use super::CodexAgentApp;
+
+const SLEEP_INTERRUPTED_TEXT: &str =
+    "Interrupted by system sleep \u{2014} codex stopped while the machine was asleep.";
+
+impl CodexAgentApp {
+    pub(super) fn handle_power_event(&mut self, event: PowerEvent) {
+        match event {
+            PowerEvent::Suspend => self.prepare_for_sleep(),
+            PowerEvent::Resume => self.check_prompt_after_sleep(),
+        }
+    }
+
+    fn prepare_for_sleep(&mut self) {
+        let Some(prompt_id) = self.active_prompt_id.filter(|_| self.busy) else {
+            return;
+        };
+        logging::trace(format!(
+            "system is suspending while prompt {} is running",
+            prompt_id
+        ));
+        self.save_snapshot();
+        if self.cancel_on_sleep {
+            logging::trace("cancelling the prompt before the system sleeps");
+            self.cancel_active_prompt();
+            return;
+        }
+        self.sleeping_prompt = Some(prompt_id);
+    }
+
+    fn check_prompt_after_sleep(&mut self) {
+        let Some(prompt_id) = self.sleeping_prompt.take() else {
+            return;
+        };
+        if self.active_prompt_id != Some(prompt_id) {
+            return;
+        }
+        let pid = self
+            .running_prompt
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .get(prompt_id)
+            .map(|running| running.pid);
+        match pid {
+            Some(pid) if process_running(pid) => {
+                logging::trace(format!("codex process {} survived system sleep", pid))
+            }
+            _ => {
+                logging::error(format!(
+                    "codex process for prompt {} exited during system sleep",
+                    prompt_id
+                ));
+                self.sleep_killed_prompt = Some(prompt_id);
+            }
+        }
+    }
+
+    pub(super) fn sleep_interruption(&mut self, prompt_id: u64, error: String) -> String {
+        if self.sleeping_prompt != Some(prompt_id) && self.sleep_killed_prompt != Some(prompt_id) {
+            return error;
+        }
+        logging::trace(format!("prompt failed after system sleep: {}", error));
+        self.sleeping_prompt = None;
+        self.sleep_killed_prompt = None;
+        self.sleep_retry_prompt = self.last_submitted_prompt.clone();
+        SLEEP_INTERRUPTED_TEXT.to_owned()
+    }
+
+    pub(super) fn sleep_retry_offered(&self) -> bool {
+        !self.busy && self.sleep_retry_prompt.is_some()
+    }
+
+    pub(super) fn retry_after_sleep(&mut self) {
+        let Some(prompt) = self.sleep_retry_prompt.take() else {
+            return;
+        };
+        logging::trace("retrying prompt interrupted by system sleep");
+        self.input = prompt;
+        self.submit();
+    }
+
+    pub(super) fn dismiss_sleep_retry(&mut self) {
+        self.sleep_retry_prompt = None;
+        self.resize_for_text();
+    }
+
+    pub(super) fn select_cancel_on_sleep(&mut self, enabled: bool) {
+        self.clear_picker_selection();
+        if self.cancel_on_sleep == enabled {
+            return;
+        }
+        match set_cancel_on_sleep(enabled) {
+            Ok(enabled) => {
+                self.cancel_on_sleep = enabled;
+                self.push_settings_output(if enabled {
+                    "Running prompts are cancelled before the machine sleeps"
+                } else {
+                    "Running prompts are left alone when the machine sleeps"
+                });
+                self.finish_local_success();
+            }
+            Err(error) => {
+                logging::error(format!(
+                    "failed to set cancel on sleep {}: {}",
+                    if enabled { "on" } else { "off" },
+                    error
+                ));
+                self.push_local_error(&format!("Failed to set cancel on sleep: {}", error));
+            }
+        }
+        self.finish_local_change();
+    }
+}
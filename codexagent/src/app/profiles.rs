@@ -0,0 +1,106 @@
+use crate::logging;
+use crate::prompt::set_codex_profile;
+use crate::runtime::{codex_config_modified, codex_profiles};
+
+use super::CodexAgentApp;
+
+const DEFAULT_PROFILE_ARGUMENT: &str = "default";
+
+impl CodexAgentApp {
+    pub(super) fn refresh_codex_profiles(&mut self) {
+        let modified = codex_config_modified();
+        if modified.is_some() && modified == self.codex_profiles_modified {
+            return;
+        }
+        self.codex_profiles_modified = modified;
+        self.codex_profiles = codex_profiles();
+        logging::trace(format!(
+            "found {} codex profiles in config.toml",
+            self.codex_profiles.names.len()
+        ));
+        if let Some(profile) = self.codex_profile.as_deref()
+            && !self.codex_profiles.names.iter().any(|name| name == profile)
+        {
+            logging::error(format!(
+                "codex profile {} is no longer in config.toml; using the config default",
+                profile
+            ));
+            self.codex_profile = None;
+            set_codex_profile(None);
+        }
+    }
+
+    pub(super) fn active_codex_profile(&self) -> Option<&str> {
+        self.codex_profile
+            .as_deref()
+            .or(self.codex_profiles.default.as_deref())
+    }
+
+    fn codex_profile_label(&self) -> String {
+        match (&self.codex_profile, &self.codex_profiles.default) {
+            (Some(profile), _) => profile.clone(),
+            (None, Some(default)) => format!("{} (config default)", default),
+            (None, None) => "the config default".to_owned(),
+        }
+    }
+
+    pub(super) fn run_profile_command(&mut self, name: &str) -> Result<String, String> {
+        self.refresh_codex_profiles();
+        let name = name.trim();
+        if let Some(profile) = self
+            .codex_profiles
+            .names
+            .iter()
+            .find(|&profile| profile == name)
+        {
+            let profile = profile.clone();
+            return Ok(self.switch_codex_profile(Some(profile)));
+        }
+        if name.eq_ignore_ascii_case(DEFAULT_PROFILE_ARGUMENT) {
+            return Ok(self.switch_codex_profile(None));
+        }
+        if self.codex_profiles.names.is_empty() {
+            return Err("No [profiles] tables in the codex config.toml".to_owned());
+        }
+        Err(format!(
+            "Unknown profile {} \u{2014} available: {}, or {}",
+            name,
+            self.codex_profiles.names.join(", "),
+            DEFAULT_PROFILE_ARGUMENT
+        ))
+    }
+
+    pub(super) fn select_codex_profile(&mut self, profile: Option<String>) {
+        self.clear_picker_selection();
+        if profile == self.codex_profile {
+            return;
+        }
+        if self.busy {
+            self.push_local_error("Wait for the running prompt before switching codex profiles");
+        } else {
+            let message = self.switch_codex_profile(profile);
+            self.push_settings_output(&message);
+            self.finish_local_success();
+        }
+        self.finish_local_change();
+    }
+
+    fn switch_codex_profile(&mut self, profile: Option<String>) -> String {
+        if profile == self.codex_profile {
+            return format!("Codex profile is already {}", self.codex_profile_label());
+        }
+        self.codex_profile = profile.clone();
+        set_codex_profile(profile);
+        let had_session = self.session_id.is_some();
+        self.start_new_session();
+        format!(
+            "Codex profile set to {}{}",
+            self.codex_profile_label(),
+            if had_session {
+                " \u{2014} the next prompt starts a new session"
+            } else {
+                ""
+            }
+        )
+    }
+}
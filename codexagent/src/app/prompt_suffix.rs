@@ -0,0 +1,123 @@
+use crate::config::set_concise_answers;
+use crate::logging;
+
+use super::CodexAgentApp;
+use super::language::response_language_directive;
+
+pub(super) const CONCISE_DIRECTIVE: &str =
+    "Answer tersely: one short paragraph, no headings, and a list only if it is essential.";
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum HiddenSuffix {
+    Concise,
+    ResponseLanguage,
+}
+
+impl HiddenSuffix {
+    const ORDER: [HiddenSuffix; 2] = [HiddenSuffix::Concise, HiddenSuffix::ResponseLanguage];
+}
+
+fn hidden_suffixes(concise: bool, response_language: Option<&str>) -> Vec<String> {
+    HiddenSuffix::ORDER
+        .into_iter()
+        .filter_map(|suffix| match suffix {
+            HiddenSuffix::Concise => concise.then(|| CONCISE_DIRECTIVE.to_owned()),
+            HiddenSuffix::ResponseLanguage => response_language.map(response_language_directive),
+        })
+        .collect()
+}
+
+fn append_hidden_suffixes(mut prompt: String, suffixes: &[String]) -> String {
+    if suffixes.is_empty() {
+        return prompt;
+    }
+    prompt.push_str("\n\n");
+    prompt.push_str(&suffixes.join(" "));
+    prompt
+}
+
+impl CodexAgentApp {
+    pub(super) fn with_hidden_suffixes(&self, prompt: String) -> String {
+        let suffixes = hidden_suffixes(self.concise, self.response_language.as_deref());
+        append_hidden_suffixes(prompt, &suffixes)
+    }
+
+    pub(super) fn toggle_concise(&mut self) -> String {
+        self.concise = !self.concise;
+        logging::trace(format!(
+            "concise answers {} for this session",
+            if self.concise { "on" } else { "off" }
+        ));
+        self.resize_for_text();
+        if self.concise {
+            "Concise answers on \u{2014} the next prompts ask codex for a short paragraph."
+                .to_owned()
+        } else {
+            "Concise answers off \u{2014} codex answers at its usual length.".to_owned()
+        }
+    }
+
+    pub(super) fn reset_concise(&mut self) {
+        if self.concise != self.concise_default {
+            logging::trace("resetting concise answers for the cleared session");
+        }
+        self.concise = self.concise_default;
+    }
+
+    pub(super) fn select_concise_default(&mut self, enabled: bool) {
+        self.clear_picker_selection();
+        if self.concise_default == enabled {
+            return;
+        }
+        match set_concise_answers(enabled) {
+            Ok(enabled) => {
+                self.concise_default = enabled;
+                self.concise = enabled;
+                self.push_settings_output(if enabled {
+                    "Concise answers set to On"
+                } else {
+                    "Concise answers set to Off"
+                });
+                self.finish_local_success();
+            }
+            Err(error) => {
+                logging::error(format!(
+                    "failed to set concise answers {}: {}",
+                    if enabled { "on" } else { "off" },
+                    error
+                ));
+                self.push_local_error(&format!("Failed to set concise answers: {}", error));
+            }
+        }
+        self.finish_local_change();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn no_suffixes_leave_the_prompt_alone() {
+        assert!(hidden_suffixes(false, None).is_empty());
+        assert_eq!(append_hidden_suffixes("Hi".to_owned(), &[]), "Hi");
+    }
+
+    #[test]
+    fn concise_comes_before_the_response_language() {
+        assert_eq!(hidden_suffixes(true, None), [CONCISE_DIRECTIVE]);
+        assert_eq!(
+            hidden_suffixes(false, Some("French")),
+            ["Respond in French."]
+        );
+        let suffixes = hidden_suffixes(true, Some("French"));
+        assert_eq!(suffixes, [CONCISE_DIRECTIVE, "Respond in French."]);
+        assert_eq!(
+            append_hidden_suffixes("Explain lifetimes".to_owned(), &suffixes),
+            format!(
+                "Explain lifetimes\n\n{} Respond in French.",
+                CONCISE_DIRECTIVE
+            )
+        );
+    }
+}
@@ -0,0 +1,165 @@
+use std::collections::BTreeMap;
+
+use crate::config::snapshot::{Rating, TurnRating};
+use crate::logging;
+
+use super::CodexAgentApp;
+
+pub(super) const RATINGS: [Rating; 2] = [Rating::Good, Rating::Bad];
+
+pub(super) fn rating_glyph(rating: Rating) -> &'static str {
+    match rating {
+        Rating::Good => "\u{1F44D}",
+        Rating::Bad => "\u{1F44E}",
+    }
+}
+
+fn rating_name(rating: Rating) -> &'static str {
+    match rating {
+        Rating::Good => "good",
+        Rating::Bad => "bad",
+    }
+}
+
+fn rating_group(rating: &TurnRating) -> String {
+    match rating.profile.as_deref() {
+        Some(profile) => format!("{} (profile {})", rating.model, profile),
+        None => rating.model.clone(),
+    }
+}
+
+fn rating_counts<'a>(ratings: impl Iterator<Item = &'a TurnRating>) -> (usize, usize) {
+    ratings.fold((0, 0), |(good, bad), rating| match rating.rating {
+        Rating::Good => (good + 1, bad),
+        Rating::Bad => (good, bad + 1),
+    })
+}
+
+fn format_counts((good, bad): (usize, usize)) -> String {
+    format!(
+        "{} {} \u{b7} {} {}",
+        good,
+        rating_glyph(Rating::Good),
+        bad,
+        rating_glyph(Rating::Bad)
+    )
+}
+
+fn ratings_summary(ratings: &[TurnRating]) -> Vec<String> {
+    if ratings.is_empty() {
+        return Vec::new();
+    }
+    let mut groups: BTreeMap<String, Vec<&TurnRating>> = BTreeMap::new();
+    for rating in ratings {
+        groups.entry(rating_group(rating)).or_default().push(rating);
+    }
+    let mut lines = vec![format!(
+        "Ratings: {}",
+        format_counts(rating_counts(ratings.iter()))
+    )];
+    for (group, ratings) in groups {
+        lines.push(format!(
+            "  {}: {}",
+            group,
+            format_counts(rating_counts(ratings.into_iter()))
+        ));
+    }
+    lines
+}
+
+impl CodexAgentApp {
+    pub(super) fn rate_response(&mut self, turn: usize, rating: Rating) {
+        if self.transcript.rating(turn) == Some(rating) {
+            if self.transcript.clear_rating(turn) {
+                logging::trace(format!("cleared rating of response #{}", turn + 1));
+            }
+        } else {
+            let stats = self
+                .turn_stats
+                .iter()
+                .rev()
+                .find(|stats| stats.turn == turn);
+            let model = stats.map_or_else(|| self.prompt_model(), |stats| stats.model.clone());
+            let profile = match stats {
+                Some(stats) => stats.profile.clone(),
+                None => self.active_codex_profile().map(str::to_owned),
+            };
+            logging::trace(format!(
+                "rated response #{} {} ({})",
+                turn + 1,
+                rating_name(rating),
+                model
+            ));
+            self.transcript.set_rating(TurnRating {
+                turn,
+                rating,
+                model,
+                profile,
+            });
+        }
+        self.ctx.request_repaint();
+    }
+
+    pub(super) fn ratings_summary_lines(&self) -> Vec<String> {
+        ratings_summary(self.transcript.ratings())
+    }
+
+    pub(super) fn ratings_markdown(&self) -> Option<String> {
+        let ratings = self.transcript.ratings();
+        if ratings.is_empty() {
+            return None;
+        }
+        let mut markdown =
+            String::from("## Ratings\n\n| # | Rating | Model | Profile |\n|---|---|---|---|\n");
+        for rating in ratings {
+            markdown.push_str(&format!(
+                "| {} | {} {} | {} | {} |\n",
+                rating.turn + 1,
+                rating_glyph(rating.rating),
+                rating_name(rating.rating),
+                rating.model,
+                rating.profile.as_deref().unwrap_or("\u{2014}")
+            ));
+        }
+        Some(markdown)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rated(turn: usize, rating: Rating, model: &str, profile: Option<&str>) -> TurnRating {
+        TurnRating {
+            turn,
+            rating,
+            model: model.to_owned(),
+            profile: profile.map(str::to_owned),
+        }
+    }
+
+    #[test]
+    fn summary_groups_ratings_by_model_and_profile() {
+        let ratings = [
+            rated(0, Rating::Good, "gpt-5", None),
+            rated(1, Rating::Bad, "gpt-5", None),
+            rated(2, Rating::Good, "gpt-5", Some("fast")),
+            rated(3, Rating::Good, "gpt-5", None),
+            rated(4, Rating::Bad, "gpt-4.1", None),
+        ];
+        assert_eq!(
+            ratings_summary(&ratings),
+            [
+                "Ratings: 3 \u{1F44D} \u{b7} 2 \u{1F44E}",
+                "  gpt-4.1: 0 \u{1F44D} \u{b7} 1 \u{1F44E}",
+                "  gpt-5: 2 \u{1F44D} \u{b7} 1 \u{1F44E}",
+                "  gpt-5 (profile fast): 1 \u{1F44D} \u{b7} 0 \u{1F44E}",
+            ]
+        );
+    }
+
+    #[test]
+    fn no_ratings_add_no_summary() {
+        assert!(ratings_summary(&[]).is_empty());
+    }
+}
@@ -0,0 +1,335 @@
+use std::env;
+use std::process::{self, Command};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Mutex, MutexGuard};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use eframe::egui;
+use eframe::glow::{self, HasContext};
+
+use crate::config::flush_settings;
+use crate::config::snapshot::{SessionSnapshot, clear_snapshot, save_snapshot_now};
+use crate::logging;
+use crate::prompt::kill_prompt_process;
+use crate::runtime::orphaned_process;
+
+use super::CodexAgentApp;
+
+const WATCHDOG_INTERVAL: Duration = Duration::from_secs(1);
+const FRAME_PROBE_AFTER: Duration = Duration::from_secs(5);
+const FRAME_STALL_TIMEOUT: Duration = Duration::from_secs(10);
+const FRAME_RELAUNCH_TIMEOUT: Duration = Duration::from_secs(20);
+const RELAUNCH_SNAPSHOT_INTERVAL: Duration = Duration::from_secs(2);
+pub(super) const RESTARTED_TEXT: &str = "\x1C(response interrupted by renderer restart)\n\n";
+
+struct FrameHeartbeat {
+    generation: u64,
+    last_frame: Option<Instant>,
+    minimized: bool,
+}
+
+struct RecoveryHandoff {
+    snapshot: SessionSnapshot,
+    input: String,
+}
+
+static HEARTBEAT: Mutex<FrameHeartbeat> = Mutex::new(FrameHeartbeat {
+    generation: 0,
+    last_frame: None,
+    minimized: false,
+});
+static HANDOFF: Mutex<Option<RecoveryHandoff>> = Mutex::new(None);
+// The UI thread is stuck by the time the watchdog relaunches, so it keeps a
+// recent copy of the session here for the watchdog to write out.
+static RELAUNCH_SNAPSHOT: Mutex<Option<SessionSnapshot>> = Mutex::new(None);
+static RESTART_REQUESTED: AtomicBool = AtomicBool::new(false);
+
+fn heartbeat() -> MutexGuard<'static, FrameHeartbeat> {
+    HEARTBEAT.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+pub fn take_render_restart() -> bool {
+    RESTART_REQUESTED.swap(false, Ordering::Relaxed)
+}
+
+pub fn rendered_any_frame() -> bool {
+    heartbeat().last_frame.is_some()
+}
+
+fn request_render_restart(reason: &str, ctx: &egui::Context) {
+    if RESTART_REQUESTED.swap(true, Ordering::Relaxed) {
+        return;
+    }
+    logging::error(format!("{}; restarting the renderer", reason));
+    ctx.send_viewport_cmd(egui::ViewportCommand::Close);
+    ctx.request_repaint();
+}
+
+#[derive(Debug, Eq, PartialEq)]
+enum WatchAction {
+    Wait,
+    Probe,
+    Restart,
+    Repaint,
+    Relaunch,
+}
+
+#[derive(Default)]
+struct FrameWatch {
+    probe_sent_at: Option<Instant>,
+    restart_sent_at: Option<Instant>,
+}
+
+impl FrameWatch {
+    fn check(&mut self, now: Instant, last_frame: Option<Instant>, minimized: bool) -> WatchAction {
+        let Some(last_frame) = last_frame else {
+            return WatchAction::Wait;
+        };
+        if minimized || self.probe_sent_at.is_some_and(|probe| last_frame >= probe) {
+            self.probe_sent_at = None;
+            self.restart_sent_at = None;
+        }
+        if minimized {
+            return WatchAction::Wait;
+        }
+        let Some(probe) = self.probe_sent_at else {
+            if now.saturating_duration_since(last_frame) >= FRAME_PROBE_AFTER {
+                self.probe_sent_at = Some(now);
+                return WatchAction::Probe;
+            }
+            return WatchAction::Wait;
+        };
+        if now.saturating_duration_since(probe) < FRAME_STALL_TIMEOUT {
+            return WatchAction::Wait;
+        }
+        match self.restart_sent_at {
+            None => {
+                self.restart_sent_at = Some(now);
+                WatchAction::Restart
+            }
+            Some(sent) if now.saturating_duration_since(sent) >= FRAME_RELAUNCH_TIMEOUT => {
+                WatchAction::Relaunch
+            }
+            Some(_) => WatchAction::Repaint,
+        }
+    }
+}
+
+fn watch_frames(ctx: egui::Context, generation: u64) {
+    let mut watch = FrameWatch::default();
+    loop {
+        thread::sleep(WATCHDOG_INTERVAL);
+        let (last_frame, minimized) = {
+            let heartbeat = heartbeat();
+            if heartbeat.generation != generation {
+                return;
+            }
+            (heartbeat.last_frame, heartbeat.minimized)
+        };
+        match watch.check(Instant::now(), last_frame, minimized) {
+            WatchAction::Wait => {}
+            WatchAction::Probe | WatchAction::Repaint => ctx.request_repaint(),
+            WatchAction::Restart => {
+                let stalled = last_frame.map_or(0, |frame| frame.elapsed().as_secs());
+                let reason = format!(
+                    "no frame presented for {}s while the window is visible",
+                    stalled
+                );
+                request_render_restart(&reason, &ctx);
+            }
+            WatchAction::Relaunch => relaunch_process(),
+        }
+    }
+}
+
+pub fn terminate_tracked_codex() {
+    let Some(pid) = orphaned_process() else {
+        return;
+    };
+    logging::trace(format!(
+        "codex process {} is still tracked; terminating it",
+        pid
+    ));
+    if let Err(error) = kill_prompt_process(pid) {
+        logging::error(format!(
+            "failed to terminate tracked codex process {}: {}",
+            pid, error
+        ));
+    }
+}
+
+fn relaunch_process() -> ! {
+    logging::error("event loop did not respond to the restart request; relaunching the app");
+    terminate_tracked_codex();
+    let _ = flush_settings();
+    save_relaunch_snapshot();
+    let relaunched =
+        env::current_exe().and_then(|exe| Command::new(exe).args(env::args_os().skip(1)).spawn());
+    if let Err(error) = relaunched {
+        logging::error(format!("failed to relaunch the app: {}", error));
+    }
+    logging::close();
+    process::exit(1);
+}
+
+fn save_relaunch_snapshot() {
+    let Some(mut snapshot) = RELAUNCH_SNAPSHOT
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .take()
+    else {
+        return;
+    };
+    snapshot.relaunched = true;
+    let _ = logging::log_result(save_snapshot_now(&snapshot), |error| {
+        format!("failed to save the session before relaunching: {}", error)
+    });
+}
+
+impl CodexAgentApp {
+    pub(super) fn publish_relaunch_snapshot(&mut self) {
+        if self.transcript.len() == self.relaunch_snapshot_len
+            || self
+                .relaunch_snapshot_at
+                .is_some_and(|published| published.elapsed() < RELAUNCH_SNAPSHOT_INTERVAL)
+        {
+            return;
+        }
+        *RELAUNCH_SNAPSHOT.lock().unwrap_or_else(|e| e.into_inner()) =
+            Some(self.session_snapshot(self.busy));
+        self.relaunch_snapshot_len = self.transcript.len();
+        self.relaunch_snapshot_at = Some(Instant::now());
+    }
+
+    pub(super) fn start_frame_watchdog(&self) {
+        let generation = {
+            let mut heartbeat = heartbeat();
+            heartbeat.generation += 1;
+            heartbeat.last_frame = None;
+            heartbeat.minimized = false;
+            heartbeat.generation
+        };
+        let ctx = self.ctx.clone();
+        thread::spawn(move || watch_frames(ctx, generation));
+    }
+
+    pub(super) fn record_frame(&self, frame: &eframe::Frame) {
+        if let Some(gl) = frame.gl()
+            && unsafe { gl.get_error() } == glow::CONTEXT_LOST
+        {
+            request_render_restart("OpenGL context lost", &self.ctx);
+            return;
+        }
+        let minimized = self
+            .ctx
+            .input(|input| input.viewport().minimized.unwrap_or(false));
+        let mut heartbeat = heartbeat();
+        heartbeat.last_frame = Some(Instant::now());
+        heartbeat.minimized = minimized;
+    }
+
+    pub(super) fn restore_recovery_handoff(&mut self) -> bool {
+        let Some(handoff) = HANDOFF.lock().unwrap_or_else(|e| e.into_inner()).take() else {
+            return false;
+        };
+        clear_snapshot();
+        logging::trace(format!(
+            "restoring session after renderer restart ({} bytes of output)",
+            handoff.snapshot.output.len()
+        ));
+        let in_progress = handoff.snapshot.in_progress;
+        self.apply_session_snapshot(handoff.snapshot, in_progress.then_some(RESTARTED_TEXT));
+        self.input = handoff.input;
+        self.invalidate_input_layout();
+        self.mark_draft_changed();
+        true
+    }
+}
+
+impl Drop for CodexAgentApp {
+    fn drop(&mut self) {
+        let handoff = RecoveryHandoff {
+            snapshot: self.session_snapshot(self.busy),
+            input: std::mem::take(&mut self.input),
+        };
+        *HANDOFF.lock().unwrap_or_else(|e| e.into_inner()) = Some(handoff);
+        heartbeat().generation += 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn secs(seconds: u64) -> Duration {
+        Duration::from_secs(seconds)
+    }
+
+    #[test]
+    fn waits_until_the_first_frame() {
+        let mut watch = FrameWatch::default();
+        assert_eq!(watch.check(Instant::now(), None, false), WatchAction::Wait);
+    }
+
+    #[test]
+    fn stalled_frames_probe_then_restart_then_relaunch() {
+        let frame = Instant::now();
+        let mut watch = FrameWatch::default();
+        assert_eq!(
+            watch.check(frame + secs(4), Some(frame), false),
+            WatchAction::Wait
+        );
+        let probe = frame + FRAME_PROBE_AFTER;
+        assert_eq!(watch.check(probe, Some(frame), false), WatchAction::Probe);
+        assert_eq!(
+            watch.check(probe + secs(9), Some(frame), false),
+            WatchAction::Wait
+        );
+        let restart = probe + FRAME_STALL_TIMEOUT;
+        assert_eq!(
+            watch.check(restart, Some(frame), false),
+            WatchAction::Restart
+        );
+        assert_eq!(
+            watch.check(restart + secs(19), Some(frame), false),
+            WatchAction::Repaint
+        );
+        assert_eq!(
+            watch.check(restart + FRAME_RELAUNCH_TIMEOUT, Some(frame), false),
+            WatchAction::Relaunch
+        );
+    }
+
+    #[test]
+    fn a_frame_after_the_probe_resets_the_watch() {
+        let frame = Instant::now();
+        let mut watch = FrameWatch::default();
+        let probe = frame + FRAME_PROBE_AFTER;
+        assert_eq!(watch.check(probe, Some(frame), false), WatchAction::Probe);
+        let answered = probe + secs(1);
+        assert_eq!(
+            watch.check(answered + secs(2), Some(answered), false),
+            WatchAction::Wait
+        );
+        assert_eq!(
+            watch.check(answered + FRAME_PROBE_AFTER, Some(answered), false),
+            WatchAction::Probe
+        );
+    }
+
+    #[test]
+    fn minimized_windows_are_never_restarted() {
+        let frame = Instant::now();
+        let mut watch = FrameWatch::default();
+        assert_eq!(
+            watch.check(frame + FRAME_PROBE_AFTER, Some(frame), false),
+            WatchAction::Probe
+        );
+        let later = frame + secs(60);
+        assert_eq!(watch.check(later, Some(frame), true), WatchAction::Wait);
+        assert_eq!(watch.probe_sent_at, None);
+        assert_eq!(watch.restart_sent_at, None);
+        assert_eq!(watch.check(later, Some(frame), false), WatchAction::Probe);
+    }
+}
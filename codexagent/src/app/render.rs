@@ -1,16 +1,40 @@
-use std::sync::OnceLock;
+use std::collections::HashSet;
+use std::sync::{Arc, OnceLock};
 
 use eframe::egui::{
-    Color32, FontId,
+    Color32, FontId, Rect, pos2,
     text::{LayoutJob, TextFormat},
 };
 use eframe::epaint::text::Galley;
 
 use crate::config::{
     CANCELLED_BOTTOM_PADDING, CANCELLED_TEXT, HIDDEN_MARKDOWN_FONT_SIZE, LINE_HEIGHT,
-    MIN_TEXT_WRAP_WIDTH, TEXT_FONT_SIZE,
+    MIN_TEXT_WRAP_WIDTH, PendingStyle, PromptWhitespace, ResponseDimming, TEXT_FONT_SIZE,
 };
 
+use super::transcript::{PINNED_LABEL, PinTarget};
+
+const ERROR_HEADER_PREFIX: &str = "\u{2717} ";
+const RESPONSE_PREVIEW_DIVISOR: usize = 4;
+const PROMPT_PREVIEW_BYTES: usize = 2 * 1024;
+pub(super) const TURN_MARKER_FONT_SIZE: f32 = 10.0;
+pub(super) const CARD_BACKGROUND: Color32 = Color32::from_rgb(14, 18, 24);
+pub(super) const HIGH_CONTRAST_CARD_BACKGROUND: Color32 = Color32::from_rgb(8, 10, 14);
+const SUBTLE_DIMMING: f32 = 0.2;
+const STRONG_DIMMING: f32 = 0.45;
+const PENDING_DOTS: [&str; 3] = [".", "..", "..."];
+const PENDING_SPINNER: [&str; 4] = ["|", "/", "\u{2500}", "\\"];
+const PENDING_BAR: [&str; 8] = [
+    "\u{2501}\u{2500}\u{2500}\u{2500}\u{2500}",
+    "\u{2500}\u{2501}\u{2500}\u{2500}\u{2500}",
+    "\u{2500}\u{2500}\u{2501}\u{2500}\u{2500}",
+    "\u{2500}\u{2500}\u{2500}\u{2501}\u{2500}",
+    "\u{2500}\u{2500}\u{2500}\u{2500}\u{2501}",
+    "\u{2500}\u{2500}\u{2500}\u{2501}\u{2500}",
+    "\u{2500}\u{2500}\u{2501}\u{2500}\u{2500}",
+    "\u{2500}\u{2501}\u{2500}\u{2500}\u{2500}",
+];
+
 #[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
 pub(super) enum OutputLineKind {
     #[default]
@@ -19,8 +43,13 @@ pub(super) enum OutputLineKind {
     Error,
     Reasoning,
     Agent,
+    Divider,
+    Turn,
+    DiffAdded,
+    DiffRemoved,
 }
 
+#[derive(Clone)]
 struct MarkdownFormats {
     plain_new: TextFormat,
     plain_old: TextFormat,
@@ -28,6 +57,7 @@ struct MarkdownFormats {
     code_old: TextFormat,
     hidden: TextFormat,
     cancelled: TextFormat,
+    error_old: TextFormat,
     cancelled_spacer: TextFormat,
     reasoning: TextFormat,
     reasoning_code: TextFormat,
@@ -35,17 +65,122 @@ struct MarkdownFormats {
     agent_code: TextFormat,
     action: TextFormat,
     action_code: TextFormat,
+    divider: TextFormat,
+    turn: TextFormat,
+    diff_added: TextFormat,
+    diff_added_word: TextFormat,
+    diff_removed: TextFormat,
+}
+
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub(super) enum FoldKind {
+    Error,
+    Prompt,
+    Response,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub(super) struct FoldRegion {
+    pub(super) kind: FoldKind,
+    pub(super) raw_start: usize,
+    pub(super) clean_start: usize,
+    pub(super) header_end: usize,
+    pub(super) collapsible: bool,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub(super) enum TextChange {
+    Unchanged,
+    Appended,
+    Rewritten { common_chars: usize },
+}
+
+pub(super) fn classify_text_change(previous: &str, current: &str) -> TextChange {
+    if previous.len() == current.len() && previous == current {
+        TextChange::Unchanged
+    } else if current.starts_with(previous) {
+        TextChange::Appended
+    } else {
+        TextChange::Rewritten {
+            common_chars: previous
+                .chars()
+                .zip(current.chars())
+                .take_while(|(lhs, rhs)| lhs == rhs)
+                .count(),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub(super) enum PendingStatus {
+    Active,
+    Queued(usize),
+    Retrying,
+    RetryingIn(u64),
+    Cancelling,
+}
+
+pub(super) fn push_pending_status(
+    buffer: &mut String,
+    status: PendingStatus,
+    style: PendingStyle,
+    step: u128,
+) {
+    match status {
+        PendingStatus::Active => {}
+        PendingStatus::Queued(queued) => {
+            buffer.push_str(&format!("working ({} queued)", queued));
+        }
+        PendingStatus::Retrying => buffer.push_str("starting a new session"),
+        PendingStatus::RetryingIn(secs) => {
+            buffer.push_str(&format!("rate limited \u{2014} retrying in {}s", secs));
+        }
+        PendingStatus::Cancelling => buffer.push_str("cancelling"),
+    }
+    let frame = pending_frame(style, step);
+    if status != PendingStatus::Active && !matches!(style, PendingStyle::Dots | PendingStyle::None)
+    {
+        buffer.push(' ');
+    }
+    buffer.push_str(frame);
+}
+
+pub(super) fn pending_status_format(high_contrast: bool, dimming: ResponseDimming) -> TextFormat {
+    markdown_formats(high_contrast, dimming).plain_new.clone()
 }
 
-pub(super) fn pending_dots(step: u128) -> &'static str {
-    match step % 3 {
-        0 => ".",
-        1 => "..",
-        _ => "...",
+fn pending_frame(style: PendingStyle, step: u128) -> &'static str {
+    match style {
+        PendingStyle::Dots => PENDING_DOTS[(step % PENDING_DOTS.len() as u128) as usize],
+        PendingStyle::Spinner => PENDING_SPINNER[(step % PENDING_SPINNER.len() as u128) as usize],
+        PendingStyle::Bar => PENDING_BAR[(step % PENDING_BAR.len() as u128) as usize],
+        PendingStyle::None => "",
     }
 }
 
-pub(super) fn trim_string_in_place(text: &mut String) -> bool {
+pub(super) fn normalize_prompt(text: &mut String, mode: PromptWhitespace) -> bool {
+    match mode {
+        PromptWhitespace::Trim => trim_string_in_place(text),
+        PromptWhitespace::TrimLineEnds => trim_line_ends_in_place(text),
+        PromptWhitespace::Preserve => !text.trim().is_empty(),
+    }
+}
+
+fn trim_line_ends_in_place(text: &mut String) -> bool {
+    if text.trim().is_empty() {
+        return false;
+    }
+    let trimmed = text
+        .lines()
+        .map(str::trim_end)
+        .skip_while(|line| line.is_empty())
+        .collect::<Vec<_>>()
+        .join("\n");
+    *text = trimmed.trim_end().to_owned();
+    true
+}
+
+fn trim_string_in_place(text: &mut String) -> bool {
     if text.trim().is_empty() {
         return false;
     }
@@ -60,16 +195,71 @@ pub(super) fn trim_string_in_place(text: &mut String) -> bool {
     true
 }
 
+fn line_start_before(text: &str, offset: usize) -> usize {
+    let end = offset.min(text.len());
+    text.as_bytes()[..end]
+        .iter()
+        .rposition(|&byte| byte == b'\n')
+        .map_or(0, |newline| newline + 1)
+}
+
 pub(super) fn markdown_layout_job(
     text: &str,
     wrap_width: f32,
     prompt_ranges: &[(usize, usize)],
     response_start: usize,
     line_kinds: &[(usize, OutputLineKind)],
+    high_contrast: bool,
+    dimming: ResponseDimming,
+) -> LayoutJob {
+    recycled_markdown_layout_job(
+        LayoutJob::default(),
+        text,
+        wrap_width,
+        prompt_ranges,
+        response_start,
+        line_kinds,
+        high_contrast,
+        dimming,
+    )
+}
+
+/// Takes back the job of a galley nothing else holds, emptied so its text
+/// and section buffers can be filled again.
+pub(super) fn recycle_layout_job(galley: Arc<Galley>) -> Option<LayoutJob> {
+    let job = Arc::try_unwrap(Arc::try_unwrap(galley).ok()?.job).ok()?;
+    let LayoutJob {
+        mut text,
+        mut sections,
+        ..
+    } = job;
+    text.clear();
+    sections.clear();
+    Some(LayoutJob {
+        text,
+        sections,
+        ..LayoutJob::default()
+    })
+}
+
+/// Lays out `text` into `job`, an empty job from [`recycle_layout_job`].
+#[allow(clippy::too_many_arguments)]
+pub(super) fn recycled_markdown_layout_job(
+    mut job: LayoutJob,
+    text: &str,
+    wrap_width: f32,
+    prompt_ranges: &[(usize, usize)],
+    response_start: usize,
+    line_kinds: &[(usize, OutputLineKind)],
+    high_contrast: bool,
+    dimming: ResponseDimming,
 ) -> LayoutJob {
-    let mut job = LayoutJob::default();
     job.wrap.max_width = wrap_width.max(MIN_TEXT_WRAP_WIDTH);
-    let formats = markdown_formats();
+    job.text.reserve(text.len());
+    job.sections
+        .reserve(text.bytes().filter(|&byte| byte == b'\n').count() + 1);
+    let formats = markdown_formats(high_contrast, dimming);
+    let response_start = line_start_before(text, response_start);
 
     let mut in_code = false;
     let mut byte_offset = 0usize;
@@ -92,27 +282,55 @@ pub(super) fn markdown_layout_job(
             } else {
                 OutputLineKind::Normal
             };
-        let (rest, is_reasoning, is_agent, is_error, is_action) = if line.starts_with('\x1C') {
-            job.append("\x1C", 0.0, formats.hidden.clone());
-            (&line[1..], false, false, false, true)
-        } else if line.starts_with('\x1D') {
-            job.append("\x1D", 0.0, formats.hidden.clone());
-            (&line[1..], false, false, true, false)
-        } else if line.starts_with('\x1E') {
-            job.append("\x1E", 0.0, formats.hidden.clone());
-            (&line[1..], true, false, false, false)
-        } else if line.starts_with('\x1F') {
-            job.append("\x1F", 0.0, formats.hidden.clone());
-            (&line[1..], false, true, false, false)
-        } else {
-            (
-                line,
-                line_kind == OutputLineKind::Reasoning,
-                line_kind == OutputLineKind::Agent,
-                line_kind == OutputLineKind::Error,
-                line_kind == OutputLineKind::Action,
-            )
-        };
+        if line.starts_with('\x19') || line_kind == OutputLineKind::Turn {
+            job.append(line, 0.0, formats.turn.clone());
+            byte_offset += line.len();
+            continue;
+        }
+        if line.starts_with('\x1A') || line_kind == OutputLineKind::Divider {
+            let rest = line.strip_prefix('\x1A').unwrap_or(line);
+            if line.starts_with('\x1A') {
+                job.append("\x1A", 0.0, formats.hidden.clone());
+            }
+            job.append(rest, 0.0, formats.divider.clone());
+            byte_offset += line.len();
+            continue;
+        }
+        if line_kind == OutputLineKind::DiffRemoved {
+            job.append(line, 0.0, formats.diff_removed.clone());
+            byte_offset += line.len();
+            continue;
+        }
+        if line_kind == OutputLineKind::DiffAdded {
+            if line.trim_start().starts_with("```") {
+                in_code = !in_code;
+            }
+            append_diff_line(&mut job, line, formats);
+            byte_offset += line.len();
+            continue;
+        }
+        let (rest, is_reasoning, is_agent, is_error, is_action) =
+            if let Some(rest) = line.strip_prefix('\x1C') {
+                job.append("\x1C", 0.0, formats.hidden.clone());
+                (rest, false, false, false, true)
+            } else if let Some(rest) = line.strip_prefix('\x1D') {
+                job.append("\x1D", 0.0, formats.hidden.clone());
+                (rest, false, false, true, false)
+            } else if let Some(rest) = line.strip_prefix('\x1E') {
+                job.append("\x1E", 0.0, formats.hidden.clone());
+                (rest, true, false, false, false)
+            } else if let Some(rest) = line.strip_prefix('\x1F') {
+                job.append("\x1F", 0.0, formats.hidden.clone());
+                (rest, false, true, false, false)
+            } else {
+                (
+                    line,
+                    line_kind == OutputLineKind::Reasoning,
+                    line_kind == OutputLineKind::Agent,
+                    line_kind == OutputLineKind::Error,
+                    line_kind == OutputLineKind::Action,
+                )
+            };
         let content = rest.strip_suffix('\n').unwrap_or(rest).trim_start();
         if content.starts_with("```") {
             let fence = rest.strip_suffix('\n').unwrap_or(rest);
@@ -133,7 +351,11 @@ pub(super) fn markdown_layout_job(
             continue;
         }
         let format = if is_error {
-            &formats.cancelled
+            if is_old {
+                &formats.error_old
+            } else {
+                &formats.cancelled
+            }
         } else if is_action {
             if in_code {
                 &formats.action_code
@@ -194,13 +416,18 @@ pub(super) fn markdown_layout_job(
     job
 }
 
+#[allow(clippy::too_many_arguments)]
 pub(super) fn prepare_output_display(
     text: &str,
     prompt_ranges: &[(usize, usize)],
     response_start: usize,
+    expanded_folds: &HashSet<(FoldKind, usize)>,
+    response_limit: usize,
+    prompt_limit: usize,
     clean_text: &mut String,
     clean_prompt_ranges: &mut Vec<(usize, usize)>,
     line_kinds: &mut Vec<(usize, OutputLineKind)>,
+    folds: &mut Vec<FoldRegion>,
 ) -> usize {
     let mut raw_offset = 0usize;
     let mut clean_offset = 0usize;
@@ -211,17 +438,127 @@ pub(super) fn prepare_output_display(
     clean_prompt_ranges.clear();
     clean_prompt_ranges.resize(prompt_ranges.len(), (0, 0));
     line_kinds.clear();
+    folds.clear();
     let mut next_prompt_index = 0usize;
     let mut next_prompt_is_end = false;
     let mut response_mapped = None;
+    let mut previous_kind = OutputLineKind::Normal;
+    let mut hiding_error = false;
+    let mut header = String::new();
+    let mut segment_index = 0usize;
+    let mut segment_start = 0usize;
+    let mut hiding_response = false;
+    let mut hiding_prompt = false;
 
-    for line in text.split_inclusive('\n') {
+    let mut lines = text.split_inclusive('\n');
+    while let Some(line) = lines.next() {
         let raw_line_start = raw_offset;
         let clean_line_start = clean_offset;
         let (kind, marker_len) = output_line_kind(line, true);
         let raw_content_start = raw_line_start + marker_len;
-        let clean_line = &line[marker_len..];
+        let mut clean_line = &line[marker_len..];
         let raw_line_end = raw_line_start + line.len();
+        while prompt_ranges
+            .get(segment_index)
+            .is_some_and(|&(start, _)| start <= raw_line_start)
+        {
+            segment_start = prompt_ranges[segment_index].1;
+            segment_index += 1;
+            hiding_response = false;
+            hiding_prompt = false;
+        }
+        let prompt_start = segment_index
+            .checked_sub(1)
+            .map(|index| prompt_ranges[index])
+            .filter(|&(start, end)| {
+                prompt_limit > 0
+                    && raw_line_start < end
+                    && end - start > prompt_limit
+                    && raw_line_start >= start + PROMPT_PREVIEW_BYTES
+                    && !expanded_folds.contains(&(FoldKind::Prompt, start))
+            })
+            .map(|(start, _)| start);
+        let starts_prompt_fold = prompt_start.is_some() && !hiding_prompt;
+        let segment_end = prompt_ranges
+            .get(segment_index)
+            .map_or(text.len(), |&(start, _)| start);
+        let truncates = response_limit > 0
+            && segment_end.saturating_sub(segment_start) > response_limit
+            && raw_line_start >= segment_start + response_limit / RESPONSE_PREVIEW_DIVISOR
+            && !expanded_folds.contains(&(FoldKind::Response, segment_start));
+        let starts_error = kind == OutputLineKind::Error && previous_kind != OutputLineKind::Error;
+        if let Some(start) = prompt_start {
+            if hiding_prompt {
+                clean_line = "";
+            } else {
+                let end = prompt_ranges[segment_index - 1].1;
+                header.clear();
+                header.push_str(&format!(
+                    "\u{2026} show {} more KB of this prompt\n",
+                    (end - raw_line_start).div_ceil(1024)
+                ));
+                clean_line = header.as_str();
+                hiding_prompt = true;
+                folds.push(FoldRegion {
+                    kind: FoldKind::Prompt,
+                    raw_start: start,
+                    clean_start: clean_line_start,
+                    header_end: clean_line_start + clean_line.len(),
+                    collapsible: true,
+                });
+            }
+        } else if hiding_response {
+            clean_line = "";
+        } else if truncates {
+            header.clear();
+            header.push_str(&format!(
+                "\u{2026} show {} more KB\n",
+                (segment_end - raw_line_start).div_ceil(1024)
+            ));
+            clean_line = header.as_str();
+            hiding_response = true;
+            folds.push(FoldRegion {
+                kind: FoldKind::Response,
+                raw_start: segment_start,
+                clean_start: clean_line_start,
+                header_end: clean_line_start + clean_line.len(),
+                collapsible: true,
+            });
+        } else if kind != OutputLineKind::Error {
+            hiding_error = false;
+        } else if hiding_error {
+            clean_line = "";
+        } else if starts_error {
+            let hidden_lines = lines
+                .clone()
+                .take_while(|next| next.starts_with('\x1D'))
+                .count();
+            let expanded = expanded_folds.contains(&(FoldKind::Error, raw_line_start));
+            header.clear();
+            header.push_str(ERROR_HEADER_PREFIX);
+            header.push_str(clean_line.strip_suffix('\n').unwrap_or(clean_line));
+            if hidden_lines > 0 && !expanded {
+                header.push_str(&format!(" (+{} more)", hidden_lines));
+                hiding_error = true;
+            }
+            if clean_line.ends_with('\n') {
+                header.push('\n');
+            }
+            clean_line = header.as_str();
+            folds.push(FoldRegion {
+                kind: FoldKind::Error,
+                raw_start: raw_line_start,
+                clean_start: clean_line_start,
+                header_end: clean_line_start + clean_line.len(),
+                collapsible: hidden_lines > 0,
+            });
+        }
+        previous_kind = kind;
+        let kind = if truncates || starts_prompt_fold {
+            OutputLineKind::Action
+        } else {
+            kind
+        };
         while let Some((raw_point, point)) = next_output_display_point(
             prompt_ranges,
             response_start,
@@ -232,7 +569,10 @@ pub(super) fn prepare_output_display(
             if raw_point > raw_line_end {
                 break;
             }
-            let mapped = clean_line_start + raw_point.saturating_sub(raw_content_start);
+            let mapped = clean_line_start
+                + raw_point
+                    .saturating_sub(raw_content_start)
+                    .min(clean_line.len());
             match point {
                 OutputDisplayPoint::PromptStart(index) => {
                     clean_prompt_ranges[index].0 = mapped;
@@ -248,7 +588,7 @@ pub(super) fn prepare_output_display(
                 }
             }
         }
-        if kind != OutputLineKind::Normal {
+        if kind != OutputLineKind::Normal && !clean_line.is_empty() {
             line_kinds.push((clean_line_start, kind));
         }
         clean_text.push_str(clean_line);
@@ -306,6 +646,166 @@ pub(super) fn append_output_display(
     }
 }
 
+pub(super) fn turn_marker_positions(
+    galley: &Galley,
+    text: &str,
+    line_kinds: &[(usize, OutputLineKind)],
+) -> Vec<(f32, String)> {
+    let mut markers = Vec::new();
+    let mut byte_offset = 0usize;
+    let mut char_index = 0usize;
+    for &(start, kind) in line_kinds {
+        if kind != OutputLineKind::Turn {
+            continue;
+        }
+        let Some(skipped) = text.get(byte_offset..start) else {
+            break;
+        };
+        char_index += skipped.chars().count();
+        byte_offset = start;
+        let label = text[start..].lines().next().unwrap_or_default().trim();
+        let rect = galley.pos_from_ccursor(eframe::egui::text::CCursor::new(char_index));
+        markers.push((rect.center().y, label.to_owned()));
+    }
+    markers
+}
+
+pub(super) fn prompt_region_rects(
+    galley: &Galley,
+    text: &str,
+    prompt_ranges: &[(usize, usize)],
+) -> Vec<Rect> {
+    let mut rects = Vec::with_capacity(prompt_ranges.len());
+    let mut byte_offset = 0usize;
+    let mut char_index = 0usize;
+    let mut char_at = |offset: usize| {
+        let skipped = text.get(byte_offset..offset)?;
+        char_index += skipped.chars().count();
+        byte_offset = offset;
+        Some(galley.pos_from_ccursor(eframe::egui::text::CCursor::new(char_index)))
+    };
+    for &(start, end) in prompt_ranges {
+        let (Some(first), Some(last)) = (char_at(start), char_at(end)) else {
+            break;
+        };
+        rects.push(Rect::from_min_max(
+            pos2(0.0, first.top()),
+            pos2(galley.rect.width(), last.bottom()),
+        ));
+    }
+    rects
+}
+
+pub(super) fn pin_region_rects(
+    galley: &Galley,
+    text: &str,
+    line_kinds: &[(usize, OutputLineKind)],
+    prompt_ranges: &[(usize, usize)],
+    prompt_rects: &[Rect],
+) -> Vec<(PinTarget, Rect)> {
+    let width = galley.rect.width();
+    let bottom = galley.rect.bottom();
+    let first_prompt = prompt_ranges
+        .first()
+        .map_or(text.len(), |&(start, _)| start);
+    let mut header_tops = Vec::new();
+    let mut byte_offset = 0usize;
+    let mut char_index = 0usize;
+    for &(start, kind) in line_kinds {
+        if start >= first_prompt {
+            break;
+        }
+        if kind != OutputLineKind::Action || !text[start..].starts_with(PINNED_LABEL) {
+            continue;
+        }
+        let Some(skipped) = text.get(byte_offset..start) else {
+            break;
+        };
+        char_index += skipped.chars().count();
+        byte_offset = start;
+        let rect = galley.pos_from_ccursor(eframe::egui::text::CCursor::new(char_index));
+        header_tops.push(rect.top());
+    }
+    let first_prompt_top = prompt_rects.first().map_or(bottom, Rect::top);
+    let mut rects = Vec::with_capacity(header_tops.len() + prompt_rects.len());
+    for (index, &top) in header_tops.iter().enumerate() {
+        let end = header_tops
+            .get(index + 1)
+            .copied()
+            .unwrap_or(first_prompt_top);
+        rects.push((
+            PinTarget::Block(index),
+            Rect::from_min_max(pos2(0.0, top), pos2(width, end)),
+        ));
+    }
+    for (turn, prompt) in prompt_rects.iter().enumerate() {
+        let end = prompt_rects.get(turn + 1).map_or(bottom, Rect::top);
+        if end > prompt.bottom() {
+            rects.push((
+                PinTarget::Turn(turn),
+                Rect::from_min_max(pos2(0.0, prompt.bottom()), pos2(width, end)),
+            ));
+        }
+    }
+    rects
+}
+
+pub(super) fn response_end_positions(
+    galley: &Galley,
+    text: &str,
+    line_kinds: &[(usize, OutputLineKind)],
+    prompt_ranges: &[(usize, usize)],
+) -> Vec<f32> {
+    response_end_char_indices(text, line_kinds, prompt_ranges)
+        .into_iter()
+        .map(|index| {
+            galley
+                .pos_from_ccursor(eframe::egui::text::CCursor::new(index))
+                .center()
+                .y
+        })
+        .collect()
+}
+
+// Where each turn's response text ends, before trailing whitespace and the
+// next turn's header, as char indices into the display text.
+fn response_end_char_indices(
+    text: &str,
+    line_kinds: &[(usize, OutputLineKind)],
+    prompt_ranges: &[(usize, usize)],
+) -> Vec<usize> {
+    let turn_lines: Vec<usize> = line_kinds
+        .iter()
+        .filter(|&&(_, kind)| kind == OutputLineKind::Turn)
+        .map(|&(start, _)| start)
+        .collect();
+    let mut ends = Vec::with_capacity(prompt_ranges.len());
+    let mut byte_offset = 0usize;
+    let mut char_index = 0usize;
+    for (turn, &(_, start)) in prompt_ranges.iter().enumerate() {
+        let next = prompt_ranges
+            .get(turn + 1)
+            .map_or(text.len(), |&(next, _)| next);
+        let end = turn_lines
+            .iter()
+            .rev()
+            .find(|&&line| line >= start && line < next)
+            .copied()
+            .unwrap_or(next);
+        let Some(response) = text.get(start..end) else {
+            break;
+        };
+        let offset = start + response.trim_end().len();
+        let Some(skipped) = text.get(byte_offset..offset) else {
+            break;
+        };
+        char_index += skipped.chars().count();
+        byte_offset = offset;
+        ends.push(char_index);
+    }
+    ends
+}
+
 pub(super) fn response_separator_y(
     galley: &Galley,
     response_start_char_index: usize,
@@ -322,92 +822,205 @@ fn output_line_kind(line: &str, line_start: bool) -> (OutputLineKind, usize) {
         return (OutputLineKind::Normal, 0);
     }
     match line.as_bytes().first().copied() {
+        Some(0x19) => (OutputLineKind::Turn, 1),
+        Some(0x1A) => (OutputLineKind::Divider, 1),
         Some(0x1C) => (OutputLineKind::Action, 1),
         Some(0x1D) => (OutputLineKind::Error, 1),
         Some(0x1E) => (OutputLineKind::Reasoning, 1),
         Some(0x1F) => (OutputLineKind::Agent, 1),
+        Some(0x11) => (OutputLineKind::DiffAdded, 1),
+        Some(0x12) => (OutputLineKind::DiffRemoved, 1),
         _ => (OutputLineKind::Normal, 0),
     }
 }
 
-fn markdown_formats() -> &'static MarkdownFormats {
-    static FORMATS: OnceLock<MarkdownFormats> = OnceLock::new();
-    FORMATS.get_or_init(|| {
-        let old_color = Color32::from_rgb(140, 145, 155);
-        MarkdownFormats {
-            plain_new: TextFormat {
-                font_id: FontId::proportional(TEXT_FONT_SIZE),
-                color: Color32::WHITE,
-                ..Default::default()
-            },
-            plain_old: TextFormat {
-                font_id: FontId::proportional(TEXT_FONT_SIZE),
-                color: old_color,
-                ..Default::default()
-            },
-            code_new: TextFormat {
-                font_id: FontId::monospace(TEXT_FONT_SIZE),
-                color: Color32::from_rgba_unmultiplied(188, 194, 202, 220),
-                ..Default::default()
-            },
-            code_old: TextFormat {
-                font_id: FontId::monospace(TEXT_FONT_SIZE),
-                color: Color32::from_rgb(130, 140, 150),
-                ..Default::default()
-            },
-            hidden: TextFormat {
-                font_id: FontId::monospace(HIDDEN_MARKDOWN_FONT_SIZE),
-                color: Color32::TRANSPARENT,
-                ..Default::default()
-            },
-            cancelled: TextFormat {
-                font_id: FontId::proportional(TEXT_FONT_SIZE),
-                color: Color32::from_rgb(255, 96, 96),
-                italics: true,
-                ..Default::default()
-            },
-            cancelled_spacer: TextFormat {
-                font_id: FontId::monospace(HIDDEN_MARKDOWN_FONT_SIZE),
-                line_height: Some(CANCELLED_BOTTOM_PADDING),
-                color: Color32::TRANSPARENT,
-                ..Default::default()
-            },
-            action: TextFormat {
-                font_id: FontId::proportional(TEXT_FONT_SIZE),
-                color: Color32::from_rgb(148, 148, 148),
-                italics: true,
-                ..Default::default()
-            },
-            action_code: TextFormat {
-                font_id: FontId::monospace(TEXT_FONT_SIZE),
-                color: Color32::from_rgb(148, 148, 148),
-                italics: true,
-                ..Default::default()
-            },
-            reasoning: TextFormat {
-                font_id: FontId::proportional(TEXT_FONT_SIZE),
-                color: Color32::from_rgb(130, 135, 145),
-                ..Default::default()
-            },
-            reasoning_code: TextFormat {
-                font_id: FontId::monospace(TEXT_FONT_SIZE),
-                color: Color32::from_rgb(130, 140, 150),
-                ..Default::default()
-            },
-            agent: TextFormat {
-                font_id: FontId::proportional(TEXT_FONT_SIZE),
-                color: Color32::WHITE,
-                ..Default::default()
-            },
-            agent_code: TextFormat {
-                font_id: FontId::monospace(TEXT_FONT_SIZE),
-                color: Color32::from_rgba_unmultiplied(188, 194, 202, 220),
-                ..Default::default()
-            },
+fn markdown_formats(high_contrast: bool, dimming: ResponseDimming) -> &'static MarkdownFormats {
+    static FORMATS: [OnceLock<MarkdownFormats>; 6] = [const { OnceLock::new() }; 6];
+    let index = usize::from(high_contrast) * ResponseDimming::ALL.len() + dimming as usize;
+    FORMATS[index].get_or_init(|| {
+        let amount = match dimming {
+            ResponseDimming::None => 0.0,
+            ResponseDimming::Subtle => SUBTLE_DIMMING,
+            ResponseDimming::Strong => STRONG_DIMMING,
+        };
+        if high_contrast {
+            standard_markdown_formats()
+                .opaque()
+                .dimmed(amount, HIGH_CONTRAST_CARD_BACKGROUND)
+        } else {
+            standard_markdown_formats()
+                .clone()
+                .dimmed(amount, CARD_BACKGROUND)
         }
     })
 }
 
+impl MarkdownFormats {
+    fn opaque(&self) -> Self {
+        Self {
+            plain_new: opaque_format(&self.plain_new),
+            plain_old: opaque_format(&self.plain_old),
+            code_new: opaque_format(&self.code_new),
+            code_old: opaque_format(&self.code_old),
+            hidden: opaque_format(&self.hidden),
+            cancelled: opaque_format(&self.cancelled),
+            error_old: opaque_format(&self.error_old),
+            cancelled_spacer: opaque_format(&self.cancelled_spacer),
+            reasoning: opaque_format(&self.reasoning),
+            reasoning_code: opaque_format(&self.reasoning_code),
+            agent: opaque_format(&self.agent),
+            agent_code: opaque_format(&self.agent_code),
+            action: opaque_format(&self.action),
+            action_code: opaque_format(&self.action_code),
+            divider: opaque_format(&self.divider),
+            turn: opaque_format(&self.turn),
+            diff_added: opaque_format(&self.diff_added),
+            diff_added_word: opaque_format(&self.diff_added_word),
+            diff_removed: opaque_format(&self.diff_removed),
+        }
+    }
+
+    fn dimmed(mut self, amount: f32, background: Color32) -> Self {
+        self.plain_old.color = dim_color(self.plain_new.color, background, amount);
+        self.code_old.color = dim_color(self.code_new.color, background, amount);
+        self.error_old.color = dim_color(self.cancelled.color, background, amount);
+        self
+    }
+}
+
+fn dim_color(color: Color32, background: Color32, amount: f32) -> Color32 {
+    let [r, g, b, a] = color.to_srgba_unmultiplied();
+    let channel =
+        |from: u8, to: u8| (from as f32 + (to as f32 - from as f32) * amount).round() as u8;
+    Color32::from_rgba_unmultiplied(
+        channel(r, background.r()),
+        channel(g, background.g()),
+        channel(b, background.b()),
+        a,
+    )
+}
+
+fn opaque_format(format: &TextFormat) -> TextFormat {
+    let mut format = format.clone();
+    if format.color.a() > 0 {
+        format.color = format.color.to_opaque();
+    }
+    if format.background.a() > 0 {
+        let background = format.background;
+        format.background = Color32::from_rgb(background.r(), background.g(), background.b());
+    }
+    format
+}
+
+fn standard_markdown_formats() -> &'static MarkdownFormats {
+    static FORMATS: OnceLock<MarkdownFormats> = OnceLock::new();
+    FORMATS.get_or_init(|| MarkdownFormats {
+        plain_new: TextFormat {
+            font_id: FontId::proportional(TEXT_FONT_SIZE),
+            color: Color32::WHITE,
+            ..Default::default()
+        },
+        plain_old: TextFormat {
+            font_id: FontId::proportional(TEXT_FONT_SIZE),
+            color: Color32::WHITE,
+            ..Default::default()
+        },
+        code_new: TextFormat {
+            font_id: FontId::monospace(TEXT_FONT_SIZE),
+            color: Color32::from_rgba_unmultiplied(188, 194, 202, 220),
+            ..Default::default()
+        },
+        code_old: TextFormat {
+            font_id: FontId::monospace(TEXT_FONT_SIZE),
+            color: Color32::from_rgba_unmultiplied(188, 194, 202, 220),
+            ..Default::default()
+        },
+        hidden: TextFormat {
+            font_id: FontId::monospace(HIDDEN_MARKDOWN_FONT_SIZE),
+            color: Color32::TRANSPARENT,
+            ..Default::default()
+        },
+        cancelled: TextFormat {
+            font_id: FontId::proportional(TEXT_FONT_SIZE),
+            color: Color32::from_rgb(255, 96, 96),
+            italics: true,
+            ..Default::default()
+        },
+        error_old: TextFormat {
+            font_id: FontId::proportional(TEXT_FONT_SIZE),
+            color: Color32::from_rgb(255, 96, 96),
+            italics: true,
+            ..Default::default()
+        },
+        cancelled_spacer: TextFormat {
+            font_id: FontId::monospace(HIDDEN_MARKDOWN_FONT_SIZE),
+            line_height: Some(CANCELLED_BOTTOM_PADDING),
+            color: Color32::TRANSPARENT,
+            ..Default::default()
+        },
+        action: TextFormat {
+            font_id: FontId::proportional(TEXT_FONT_SIZE),
+            color: Color32::from_rgb(148, 148, 148),
+            italics: true,
+            ..Default::default()
+        },
+        action_code: TextFormat {
+            font_id: FontId::monospace(TEXT_FONT_SIZE),
+            color: Color32::from_rgb(148, 148, 148),
+            italics: true,
+            ..Default::default()
+        },
+        divider: TextFormat {
+            font_id: FontId::proportional(TEXT_FONT_SIZE),
+            color: Color32::from_rgba_unmultiplied(148, 148, 148, 150),
+            ..Default::default()
+        },
+        turn: TextFormat {
+            font_id: FontId::proportional(TURN_MARKER_FONT_SIZE),
+            color: Color32::TRANSPARENT,
+            ..Default::default()
+        },
+        reasoning: TextFormat {
+            font_id: FontId::proportional(TEXT_FONT_SIZE),
+            color: Color32::from_rgb(130, 135, 145),
+            ..Default::default()
+        },
+        reasoning_code: TextFormat {
+            font_id: FontId::monospace(TEXT_FONT_SIZE),
+            color: Color32::from_rgb(130, 140, 150),
+            ..Default::default()
+        },
+        agent: TextFormat {
+            font_id: FontId::proportional(TEXT_FONT_SIZE),
+            color: Color32::WHITE,
+            ..Default::default()
+        },
+        agent_code: TextFormat {
+            font_id: FontId::monospace(TEXT_FONT_SIZE),
+            color: Color32::from_rgba_unmultiplied(188, 194, 202, 220),
+            ..Default::default()
+        },
+        diff_added: TextFormat {
+            font_id: FontId::proportional(TEXT_FONT_SIZE),
+            color: Color32::from_rgb(150, 220, 160),
+            background: Color32::from_rgba_unmultiplied(90, 200, 110, 18),
+            ..Default::default()
+        },
+        diff_added_word: TextFormat {
+            font_id: FontId::proportional(TEXT_FONT_SIZE),
+            color: Color32::from_rgb(190, 245, 195),
+            background: Color32::from_rgba_unmultiplied(90, 200, 110, 70),
+            ..Default::default()
+        },
+        diff_removed: TextFormat {
+            font_id: FontId::proportional(TEXT_FONT_SIZE),
+            color: Color32::from_rgba_unmultiplied(230, 120, 120, 170),
+            italics: true,
+            ..Default::default()
+        },
+    })
+}
+
 #[derive(Clone, Copy)]
 enum OutputDisplayPoint {
     PromptStart(usize),
@@ -430,7 +1043,7 @@ fn next_output_display_point(
         }
     });
     match (prompt_point, response_pending) {
-        (Some((offset, point)), true) if response_start < offset => {
+        (Some((offset, _point)), true) if response_start < offset => {
             Some((response_start, OutputDisplayPoint::Response))
         }
         (Some(prompt_point), _) => Some(prompt_point),
@@ -439,6 +1052,28 @@ fn next_output_display_point(
     }
 }
 
+fn append_diff_line(job: &mut LayoutJob, line: &str, formats: &MarkdownFormats) {
+    let mut highlighted = false;
+    for part in line.split_inclusive(['\x13', '\x14']) {
+        let (text, marker) = match part.strip_suffix(['\x13', '\x14']) {
+            Some(text) => (text, Some(&part[text.len()..])),
+            None => (part, None),
+        };
+        let format = if highlighted {
+            &formats.diff_added_word
+        } else {
+            &formats.diff_added
+        };
+        if !text.is_empty() {
+            job.append(text, 0.0, format.clone());
+        }
+        if let Some(marker) = marker {
+            job.append(marker, 0.0, formats.hidden.clone());
+            highlighted = marker == "\x13";
+        }
+    }
+}
+
 fn is_horizontal_rule(trimmed: &str) -> bool {
     let bytes = trimmed.as_bytes();
     if bytes.len() < 3 {
@@ -491,23 +1126,23 @@ fn append_markdown_line(
         let bracket = remaining.find('[');
         let mut at = remaining.len();
         let mut kind = 0u8;
-        if let Some(p) = bold {
-            if p < at {
-                at = p;
-                kind = 1;
-            }
+        if let Some(p) = bold
+            && p < at
+        {
+            at = p;
+            kind = 1;
         }
-        if let Some(p) = tick {
-            if p < at {
-                at = p;
-                kind = 2;
-            }
+        if let Some(p) = tick
+            && p < at
+        {
+            at = p;
+            kind = 2;
         }
-        if let Some(p) = bracket {
-            if p < at {
-                at = p;
-                kind = 3;
-            }
+        if let Some(p) = bracket
+            && p < at
+        {
+            at = p;
+            kind = 3;
         }
         if kind == 0 {
             job.append(remaining, 0.0, format.clone());
@@ -519,15 +1154,15 @@ fn append_markdown_line(
         remaining = &remaining[at..];
         match kind {
             1 => {
-                job.append("**", 0.0, hidden.clone());
-                remaining = &remaining[2..];
-                if let Some(end) = remaining.find("**") {
-                    job.append(&remaining[..end], 0.0, format.clone());
+                let inner = &remaining[2..];
+                if let Some(end) = inner.find("**") {
+                    job.append("**", 0.0, hidden.clone());
+                    job.append(&inner[..end], 0.0, format.clone());
                     job.append("**", 0.0, hidden.clone());
-                    remaining = &remaining[end + 2..];
+                    remaining = &inner[end + 2..];
                 } else {
                     job.append("**", 0.0, format.clone());
-                    break;
+                    remaining = inner;
                 }
             }
             2 => {
@@ -580,3 +1215,537 @@ fn append_markdown_line(
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::DEFAULT_RESPONSE_DIMMING;
+
+    fn status_line(status: PendingStatus, style: PendingStyle, step: u128) -> String {
+        let mut buffer = String::new();
+        push_pending_status(&mut buffer, status, style, step);
+        buffer
+    }
+
+    #[test]
+    fn active_status_is_only_the_animation() {
+        assert_eq!(
+            status_line(PendingStatus::Active, PendingStyle::Dots, 1),
+            ".."
+        );
+        assert_eq!(
+            status_line(PendingStatus::Active, PendingStyle::None, 1),
+            ""
+        );
+    }
+
+    #[test]
+    fn queued_status_counts_waiting_prompts() {
+        assert_eq!(
+            status_line(PendingStatus::Queued(2), PendingStyle::Dots, 0),
+            "working (2 queued)."
+        );
+        assert_eq!(
+            status_line(PendingStatus::Queued(1), PendingStyle::Spinner, 1),
+            "working (1 queued) /"
+        );
+    }
+
+    #[test]
+    fn rate_limit_wait_shows_a_countdown() {
+        assert_eq!(
+            status_line(PendingStatus::RetryingIn(42), PendingStyle::None, 7),
+            "rate limited \u{2014} retrying in 42s"
+        );
+        assert_eq!(
+            status_line(PendingStatus::RetryingIn(4), PendingStyle::Dots, 2),
+            "rate limited \u{2014} retrying in 4s..."
+        );
+    }
+
+    const MARKDOWN_TOKENS: [&str; 28] = [
+        "**",
+        "*",
+        "`",
+        "```",
+        "[",
+        "]",
+        "(",
+        ")",
+        "](",
+        "# ",
+        "## ",
+        "#",
+        "---",
+        "\n",
+        " ",
+        "word",
+        "\u{e9}",
+        "\u{1F642}",
+        "\u{4E2D}",
+        "/tmp/x",
+        "C:",
+        "\x19",
+        "\x1A",
+        "\x1C",
+        "\x1D",
+        "\x1E",
+        "\x1F",
+        "-",
+    ];
+
+    struct Tokens(u64);
+
+    impl Tokens {
+        fn next(&mut self) -> u64 {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            self.0
+        }
+
+        fn text(&mut self, max_tokens: u64) -> String {
+            let count = self.next() % max_tokens;
+            (0..count)
+                .map(|_| MARKDOWN_TOKENS[(self.next() % MARKDOWN_TOKENS.len() as u64) as usize])
+                .collect()
+        }
+    }
+
+    fn line_job(line: &str) -> LayoutJob {
+        let formats = markdown_formats(false, DEFAULT_RESPONSE_DIMMING);
+        let mut job = LayoutJob::default();
+        append_markdown_line(
+            &mut job,
+            line,
+            &formats.plain_new,
+            &formats.code_new,
+            &formats.hidden,
+        );
+        job
+    }
+
+    fn layout(text: &str, prompt_ranges: &[(usize, usize)], response_start: usize) -> LayoutJob {
+        markdown_layout_job(
+            text,
+            400.0,
+            prompt_ranges,
+            response_start,
+            &[],
+            false,
+            DEFAULT_RESPONSE_DIMMING,
+        )
+    }
+
+    fn assert_sections_cover_text(job: &LayoutJob) {
+        let mut end = 0;
+        for section in &job.sections {
+            assert_eq!(section.byte_range.start, end, "{:?}", job.text);
+            assert!(job.text.is_char_boundary(section.byte_range.end));
+            end = section.byte_range.end;
+        }
+        assert_eq!(end, job.text.len());
+    }
+
+    #[test]
+    fn streaming_growth_is_an_append() {
+        assert_eq!(classify_text_change("", ""), TextChange::Unchanged);
+        assert_eq!(
+            classify_text_change("The parser", "The parser"),
+            TextChange::Unchanged
+        );
+        assert_eq!(
+            classify_text_change("The parser", "The parser reads"),
+            TextChange::Appended
+        );
+        assert_eq!(classify_text_change("", "The"), TextChange::Appended);
+    }
+
+    #[test]
+    fn rewrites_keep_the_common_prefix_in_chars() {
+        assert_eq!(
+            classify_text_change("\x1Ethinking\nThe answer", "\x1Eplanning\nThe answer"),
+            TextChange::Rewritten { common_chars: 1 }
+        );
+        assert_eq!(
+            classify_text_change("The parser reads", "The parser"),
+            TextChange::Rewritten { common_chars: 10 }
+        );
+        assert_eq!(
+            classify_text_change("naïve café", "naïve cafe"),
+            TextChange::Rewritten { common_chars: 9 }
+        );
+        assert_eq!(
+            classify_text_change("abc", "xyz"),
+            TextChange::Rewritten { common_chars: 0 }
+        );
+    }
+
+    #[test]
+    fn markdown_lines_keep_every_byte() {
+        let mut tokens = Tokens(0x9E37_79B9_7F4A_7C15);
+        for _ in 0..4000 {
+            let line = tokens.text(24).replace('\n', "");
+            let job = line_job(&line);
+            assert_eq!(job.text, line);
+            assert_sections_cover_text(&job);
+        }
+    }
+
+    #[test]
+    fn markdown_lines_with_stray_markers_do_not_panic() {
+        for line in [
+            "ends with `*",
+            "`**",
+            "**`",
+            "`",
+            "**",
+            "*",
+            "[",
+            "[]",
+            "[](",
+            "[\u{e9}](",
+            "[x](\u{e9}",
+            "a**\u{e9}",
+            "**\u{e9}**",
+            "`\u{e9}`",
+            "\u{e9}`",
+            "``",
+            "[a](/b) [c](d)",
+            "\u{1F642}**\u{1F642}",
+        ] {
+            let job = line_job(line);
+            assert_eq!(job.text, line);
+            assert_sections_cover_text(&job);
+        }
+    }
+
+    #[test]
+    fn layout_keeps_text_without_fences() {
+        let mut tokens = Tokens(0xD1B5_4A32_D192_ED03);
+        for _ in 0..2000 {
+            let mut text = tokens.text(60);
+            while text.contains("```") {
+                text = text.replace("```", "``");
+            }
+            let text = text
+                .split_inclusive('\n')
+                .filter(|line| line.trim_end_matches('\n') != CANCELLED_TEXT)
+                .collect::<String>();
+            let response_start = (tokens.next() as usize) % (text.len() + 1);
+            let job = layout(&text, &[], response_start);
+            if !text.is_empty() {
+                assert_eq!(job.text, text);
+            }
+            assert_sections_cover_text(&job);
+        }
+    }
+
+    #[test]
+    fn layout_with_fences_and_ranges_does_not_panic() {
+        let mut tokens = Tokens(0x2545_F491_4F6C_DD1D);
+        for _ in 0..2000 {
+            let text = tokens.text(80);
+            let mut ranges: Vec<usize> = (0..4)
+                .map(|_| (tokens.next() as usize) % (text.len() + 1))
+                .collect();
+            ranges.sort_unstable();
+            let prompt_ranges = [(ranges[0], ranges[1]), (ranges[2], ranges[3])];
+            let response_start = (tokens.next() as usize) % (text.len() + 1);
+            let job = layout(&text, &prompt_ranges, response_start);
+            assert_sections_cover_text(&job);
+        }
+    }
+
+    #[test]
+    fn prompt_lines_are_never_dimmed_as_old() {
+        let formats = markdown_formats(false, DEFAULT_RESPONSE_DIMMING);
+        let text = "old answer\nmy prompt **bold**\nsecond line\nnew answer\n";
+        let prompt_start = text.find("my prompt").unwrap();
+        let prompt_end = text.find("new answer").unwrap();
+        let job = layout(text, &[(prompt_start, prompt_end)], prompt_end);
+        for section in &job.sections {
+            let range = section.byte_range.clone();
+            let format = &section.format;
+            if range.start >= prompt_start && range.end <= prompt_end {
+                assert!(*format != formats.plain_old, "{:?}", &job.text[range]);
+            } else if range.end <= prompt_start {
+                assert!(*format == formats.plain_old, "{:?}", &job.text[range]);
+            } else if range.start >= prompt_end {
+                assert!(*format == formats.plain_new, "{:?}", &job.text[range]);
+            }
+        }
+    }
+
+    fn sample_transcript(min_bytes: usize) -> String {
+        let mut text = String::new();
+        let mut turn = 0;
+        while text.len() < min_bytes {
+            turn += 1;
+            text.push_str(&format!("Why does step {} fail with **exit 1**?\n", turn));
+            text.push_str("The build script calls `cargo test` before the *fixtures* exist.\n\n");
+            text.push_str("- move the fixture step first\n- rerun with `--nocapture`\n\n");
+            text.push_str("```rust\nfn main() {\n    println!(\"step {}\", 1);\n}\n```\n\n");
+            text.push_str("See [the guide](https://example.com/guide) for details.\n\n");
+        }
+        text
+    }
+
+    #[test]
+    fn layout_of_a_large_transcript_keeps_every_byte() {
+        let text = sample_transcript(64 * 1024);
+        let job = layout(&text, &[], text.len() / 2);
+        assert_eq!(job.text.len(), text.len());
+        assert_sections_cover_text(&job);
+    }
+
+    #[test]
+    fn recycled_jobs_lay_out_the_same_sections() {
+        let ctx = eframe::egui::Context::default();
+        let _ = ctx.run(Default::default(), |_| {});
+        let text = sample_transcript(16 * 1024);
+        let first = ctx.fonts(|fonts| fonts.layout_job(layout(&text, &[], 0)));
+        let shared = first.clone();
+        assert!(recycle_layout_job(shared).is_none(), "still held elsewhere");
+        for _ in 0..2 {
+            // egui's galley cache lets go of a galley a frame after its last use.
+            let _ = ctx.run(Default::default(), |_| {});
+        }
+        let job =
+            recycle_layout_job(first).expect("a galley nothing else holds gives back its job");
+        assert!(job.text.is_empty() && job.sections.is_empty());
+        assert!(job.text.capacity() >= text.len());
+
+        let next = format!("{}More **text** after the recycle.\n", text);
+        let recycled = recycled_markdown_layout_job(
+            job,
+            &next,
+            400.0,
+            &[(10, 40)],
+            next.len() / 2,
+            &[],
+            false,
+            DEFAULT_RESPONSE_DIMMING,
+        );
+        let fresh = layout(&next, &[(10, 40)], next.len() / 2);
+        assert_eq!(recycled.text, fresh.text);
+        assert_eq!(recycled.sections, fresh.sections);
+        assert_eq!(recycled.wrap, fresh.wrap);
+    }
+
+    // cargo test --release layout_benchmark -- --ignored --nocapture
+    #[test]
+    #[ignore]
+    fn layout_benchmark() {
+        const RUNS: u32 = 20;
+        let text = sample_transcript(500 * 1024);
+        let started = std::time::Instant::now();
+        let mut sections = 0;
+        for _ in 0..RUNS {
+            sections = layout(&text, &[], text.len() / 2).sections.len();
+        }
+        println!(
+            "{} KB, {} sections: {:?} per layout job",
+            text.len() / 1024,
+            sections,
+            started.elapsed() / RUNS
+        );
+        let mut job = LayoutJob::default();
+        let started = std::time::Instant::now();
+        for _ in 0..RUNS {
+            job.text.clear();
+            job.sections.clear();
+            job = recycled_markdown_layout_job(
+                job,
+                &text,
+                400.0,
+                &[],
+                text.len() / 2,
+                &[],
+                false,
+                DEFAULT_RESPONSE_DIMMING,
+            );
+        }
+        println!("{:?} per recycled layout job", started.elapsed() / RUNS);
+    }
+
+    fn normalized(text: &str, mode: PromptWhitespace) -> Option<String> {
+        let mut text = text.to_owned();
+        normalize_prompt(&mut text, mode).then_some(text)
+    }
+
+    const FENCED_PROMPT: &str = "\n```rust\n    fn main() {  \n        run();\n    }\n```  \n\n";
+
+    #[test]
+    fn trim_strips_both_ends() {
+        assert_eq!(
+            normalized("  \n  fix the parser  \n", PromptWhitespace::Trim).as_deref(),
+            Some("fix the parser")
+        );
+        assert_eq!(
+            normalized("    let x = 1;\n    let y = 2;\n", PromptWhitespace::Trim).as_deref(),
+            Some("let x = 1;\n    let y = 2;")
+        );
+        assert_eq!(
+            normalized(FENCED_PROMPT, PromptWhitespace::Trim).as_deref(),
+            Some("```rust\n    fn main() {  \n        run();\n    }\n```")
+        );
+    }
+
+    #[test]
+    fn trim_line_ends_keeps_indentation() {
+        assert_eq!(
+            normalized(
+                "\n  \n    let x = 1;   \n    let y = 2;\t\n\n",
+                PromptWhitespace::TrimLineEnds
+            )
+            .as_deref(),
+            Some("    let x = 1;\n    let y = 2;")
+        );
+        assert_eq!(
+            normalized(FENCED_PROMPT, PromptWhitespace::TrimLineEnds).as_deref(),
+            Some("```rust\n    fn main() {\n        run();\n    }\n```")
+        );
+        assert_eq!(
+            normalized("a  \r\n  b  \r\n", PromptWhitespace::TrimLineEnds).as_deref(),
+            Some("a\n  b")
+        );
+    }
+
+    #[test]
+    fn preserve_sends_the_text_verbatim() {
+        for text in [FENCED_PROMPT, "    indented\n", "  spaced  "] {
+            assert_eq!(
+                normalized(text, PromptWhitespace::Preserve).as_deref(),
+                Some(text)
+            );
+        }
+    }
+
+    #[test]
+    fn blank_prompts_are_rejected_in_every_mode() {
+        for mode in PromptWhitespace::ALL {
+            for text in ["", "   ", "\n\t\r\n  "] {
+                assert_eq!(normalized(text, mode), None, "{:?} {:?}", mode, text);
+            }
+        }
+    }
+
+    #[test]
+    fn dimming_flips_only_at_the_line_holding_the_response_start() {
+        let formats = markdown_formats(false, DEFAULT_RESPONSE_DIMMING);
+        let text = "first old line\nsecond caf\u{00E9} line\nthird line\nlast";
+        for response_start in 0..=text.len() + 2 {
+            let job = layout(text, &[], response_start);
+            assert_eq!(job.text, text);
+            let boundary = line_start_before(text, response_start);
+            for section in &job.sections {
+                let range = section.byte_range.clone();
+                let line_start = line_start_before(text, range.start);
+                assert_eq!(
+                    section.format == formats.plain_old,
+                    line_start < boundary,
+                    "start {} section {:?}",
+                    response_start,
+                    &job.text[range]
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn dim_color_blends_toward_the_background() {
+        let color = Color32::from_rgba_unmultiplied(200, 100, 0, 180);
+        let background = Color32::from_rgb(0, 0, 100);
+        assert_eq!(dim_color(color, background, 0.0), color);
+        assert_eq!(
+            dim_color(color, background, 1.0),
+            Color32::from_rgba_unmultiplied(0, 0, 100, 180)
+        );
+        assert_eq!(
+            dim_color(color, background, 0.5),
+            Color32::from_rgba_unmultiplied(100, 50, 50, 180)
+        );
+    }
+
+    #[test]
+    fn dimming_levels_derive_old_colors_from_the_theme() {
+        let distance = |dimming| {
+            let formats = markdown_formats(false, dimming);
+            let [r, g, b, _] = formats.plain_new.color.to_array();
+            let [old_r, old_g, old_b, _] = formats.plain_old.color.to_array();
+            r.abs_diff(old_r) as u32 + g.abs_diff(old_g) as u32 + b.abs_diff(old_b) as u32
+        };
+        let undimmed = markdown_formats(false, ResponseDimming::None);
+        assert_eq!(undimmed.plain_old.color, undimmed.plain_new.color);
+        assert_eq!(undimmed.code_old.color, undimmed.code_new.color);
+        assert_eq!(undimmed.error_old.color, undimmed.cancelled.color);
+        assert!(0 < distance(ResponseDimming::Subtle));
+        assert!(distance(ResponseDimming::Subtle) < distance(ResponseDimming::Strong));
+
+        let high_contrast = markdown_formats(true, ResponseDimming::Strong);
+        assert_eq!(
+            high_contrast.plain_old.color,
+            dim_color(
+                high_contrast.plain_new.color,
+                HIGH_CONTRAST_CARD_BACKGROUND,
+                STRONG_DIMMING
+            )
+        );
+    }
+
+    #[test]
+    fn old_error_lines_dim_with_their_turn() {
+        let formats = markdown_formats(false, DEFAULT_RESPONSE_DIMMING);
+        let text = "\x1Dfirst failure\nretrying\n\x1Dsecond failure\n";
+        let job = layout(text, &[], text.find("retrying").unwrap());
+        let error_color = |needle: &str| {
+            job.sections
+                .iter()
+                .find(|section| job.text[section.byte_range.clone()].contains(needle))
+                .map(|section| section.format.color)
+                .unwrap()
+        };
+        assert_eq!(error_color("first failure"), formats.error_old.color);
+        assert_eq!(error_color("second failure"), formats.cancelled.color);
+        assert_ne!(formats.error_old.color, formats.cancelled.color);
+    }
+
+    #[test]
+    fn line_start_before_snaps_back_to_the_line() {
+        let text = "ab\ncd\n\u{00E9}f";
+        assert_eq!(line_start_before(text, 0), 0);
+        assert_eq!(line_start_before(text, 2), 0);
+        assert_eq!(line_start_before(text, 3), 3);
+        assert_eq!(line_start_before(text, 5), 3);
+        assert_eq!(line_start_before(text, 7), 6);
+        assert_eq!(line_start_before(text, 8), 6);
+        assert_eq!(line_start_before(text, 100), 6);
+    }
+
+    #[test]
+    fn responses_end_before_the_next_turn_header() {
+        let text = "#1\nfirst\n\nAnswer one\n\n#2\nsécond\n\nAnswer twö  \n";
+        let second_header = text.find("#2").unwrap();
+        let line_kinds = [
+            (0, OutputLineKind::Turn),
+            (3, OutputLineKind::Normal),
+            (second_header, OutputLineKind::Turn),
+        ];
+        let prompt_ranges = [(3, 8), (second_header + 3, second_header + 10)];
+        let ends = response_end_char_indices(text, &line_kinds, &prompt_ranges);
+        let char_end = |needle: &str| {
+            text[..text.find(needle).unwrap() + needle.len()]
+                .chars()
+                .count()
+        };
+        assert_eq!(ends, [char_end("Answer one"), char_end("Answer twö")]);
+    }
+
+    #[test]
+    fn unanswered_prompts_end_at_the_prompt() {
+        let text = "#1\nfirst\n\n";
+        let ends = response_end_char_indices(text, &[(0, OutputLineKind::Turn)], &[(3, 8)]);
+        assert_eq!(ends, [8]);
+    }
+}
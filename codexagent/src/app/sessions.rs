@@ -0,0 +1,421 @@
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::SystemTime;
+
+use crate::attach::format_size;
+use crate::config::snapshot::{
+    RecentSession, delete_recent_session, list_recent_sessions, load_recent_session,
+    recent_session_file_name, recent_session_file_stamp, recent_session_stamp,
+    save_recent_session_async,
+};
+use crate::config::{
+    APP_DISPLAY_NAME, DEFAULT_EXPORT_TURN_MARKERS, build_stamp, disk_space_low,
+    load_export_turn_markers, transcripts_persisted,
+};
+use crate::events::AppEvent;
+use crate::i18n::{Text, relative_age, tr, turn_count};
+use crate::logging;
+use crate::runtime::{pick_folder, write_file_atomic};
+
+use super::CodexAgentApp;
+use super::output::strip_line_markers;
+use super::snapshot::snapshot_is_consistent;
+
+const EXPORT_EXTENSION: &str = "md";
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub(super) enum RecentSessionAction {
+    Open,
+    ConfirmOpen,
+    Export,
+    Delete,
+    ConfirmDelete,
+    Keep,
+}
+
+pub(super) struct RecentSessionFile {
+    stamp: String,
+    name: String,
+    saved_len: usize,
+    saved_title: Option<String>,
+    saved_notes: String,
+}
+
+pub(super) struct RecentSessionsView {
+    pub(super) sessions: Vec<RecentSession>,
+    pub(super) confirming: Option<(usize, RecentSessionAction)>,
+    pub(super) status: Option<Result<String, String>>,
+}
+
+pub(super) fn recent_session_row(session: &RecentSession, now: SystemTime) -> (String, String) {
+    let title = session
+        .title
+        .clone()
+        .unwrap_or_else(|| tr(Text::UntitledSession).to_owned());
+    let age = now.duration_since(session.saved_at).unwrap_or_default();
+    let details = format!(
+        "{} \u{00B7} {} \u{00B7} {}",
+        turn_count(session.turns),
+        relative_age(age),
+        format_size(usize::try_from(session.size).unwrap_or(usize::MAX))
+    );
+    (title, details)
+}
+
+impl CodexAgentApp {
+    fn recent_session_saved(&self) -> bool {
+        self.recent_session.as_ref().is_some_and(|file| {
+            file.saved_len == self.transcript.len()
+                && file.saved_title.as_deref() == self.session_title.text()
+                && file.saved_notes == self.notes.text
+        })
+    }
+
+    fn recent_session_unsaved(&self) -> bool {
+        if self.transcript.prompt_ranges().is_empty() {
+            return !self.notes.text.is_empty();
+        }
+        !transcripts_persisted() || self.recent_session_limit == 0 || !self.recent_session_saved()
+    }
+
+    pub(super) fn autosave_recent_session(&mut self) {
+        if self.busy
+            || self.recent_session_limit == 0
+            || !transcripts_persisted()
+            || disk_space_low()
+            || self.transcript.prompt_ranges().is_empty()
+            || !self.notes.settled()
+            || self.recent_session_saved()
+        {
+            return;
+        }
+        let previous = self.recent_session.take();
+        let stamp = previous.as_ref().map_or_else(
+            || recent_session_stamp(SystemTime::now()),
+            |file| file.stamp.clone(),
+        );
+        let title = self.session_title.text().map(str::to_owned);
+        let name = recent_session_file_name(&stamp, title.as_deref());
+        save_recent_session_async(
+            &self.session_snapshot(false),
+            name.clone(),
+            previous.map(|file| file.name),
+            self.recent_session_limit,
+        );
+        self.recent_session = Some(RecentSessionFile {
+            stamp,
+            name,
+            saved_len: self.transcript.len(),
+            saved_title: title,
+            saved_notes: self.notes.text.clone(),
+        });
+    }
+
+    pub(super) fn recent_sessions_open(&self) -> bool {
+        self.recent_sessions.is_some()
+    }
+
+    pub(super) fn current_recent_session(&self, session: &RecentSession) -> bool {
+        self.recent_session
+            .as_ref()
+            .is_some_and(|file| file.name == session.file_name())
+    }
+
+    pub(super) fn open_recent_sessions(&mut self) -> Result<String, String> {
+        let sessions = list_recent_sessions().map_err(|error| {
+            logging::error(format!("failed to list recent sessions: {}", error));
+            format!("Failed to list recent sessions: {}", error)
+        })?;
+        if sessions.is_empty() {
+            return Err(
+                if self.recent_session_limit == 0 || !transcripts_persisted() {
+                    "Recent sessions are not kept \u{2014} transcripts are not persisted or recent_sessions is 0".to_owned()
+                } else {
+                    "No recent sessions yet \u{2014} sessions are kept after their first answer"
+                        .to_owned()
+                },
+            );
+        }
+        let message = format!("{} recent sessions", sessions.len());
+        logging::trace(format!("opened recent sessions ({})", sessions.len()));
+        self.recent_sessions = Some(RecentSessionsView {
+            sessions,
+            confirming: None,
+            status: None,
+        });
+        self.release_input_focus();
+        self.invalidate_text_layout();
+        self.resize_for_text();
+        Ok(message)
+    }
+
+    pub(super) fn close_recent_sessions(&mut self) {
+        if self.recent_sessions.take().is_none() {
+            return;
+        }
+        self.pending_input_focus = true;
+        self.invalidate_text_layout();
+        self.resize_for_text();
+    }
+
+    fn set_recent_sessions_status(&mut self, status: Result<String, String>) {
+        if let Some(view) = self.recent_sessions.as_mut() {
+            view.confirming = None;
+            view.status = Some(status);
+        }
+    }
+
+    pub(super) fn run_recent_session_action(&mut self, index: usize, action: RecentSessionAction) {
+        let Some(session) = self
+            .recent_sessions
+            .as_ref()
+            .and_then(|view| view.sessions.get(index))
+            .cloned()
+        else {
+            return;
+        };
+        match action {
+            RecentSessionAction::Open if self.recent_session_unsaved() => {
+                if let Some(view) = self.recent_sessions.as_mut() {
+                    view.confirming = Some((index, RecentSessionAction::Open));
+                    view.status = None;
+                }
+            }
+            RecentSessionAction::Open | RecentSessionAction::ConfirmOpen => {
+                self.open_recent_session(&session)
+            }
+            RecentSessionAction::Export => self.pick_recent_session_export(session.path),
+            RecentSessionAction::Delete => {
+                if let Some(view) = self.recent_sessions.as_mut() {
+                    view.confirming = Some((index, RecentSessionAction::Delete));
+                    view.status = None;
+                }
+            }
+            RecentSessionAction::ConfirmDelete => self.delete_recent_session(index, &session),
+            RecentSessionAction::Keep => {
+                if let Some(view) = self.recent_sessions.as_mut() {
+                    view.confirming = None;
+                }
+            }
+        }
+    }
+
+    fn delete_recent_session(&mut self, index: usize, session: &RecentSession) {
+        if self.current_recent_session(session) {
+            self.set_recent_sessions_status(Err(
+                "This is the current session \u{2014} clear it instead".to_owned(),
+            ));
+            return;
+        }
+        match delete_recent_session(&session.path) {
+            Ok(()) => {
+                logging::trace(format!("deleted recent session {}", session.path.display()));
+                if let Some(view) = self.recent_sessions.as_mut() {
+                    view.sessions.remove(index);
+                }
+                self.set_recent_sessions_status(Ok(format!(
+                    "Deleted {}",
+                    session
+                        .title
+                        .as_deref()
+                        .unwrap_or(tr(Text::UntitledSession))
+                )));
+            }
+            Err(error) => {
+                logging::error(format!(
+                    "failed to delete recent session {}: {}",
+                    session.path.display(),
+                    error
+                ));
+                self.set_recent_sessions_status(Err(format!("Failed to delete: {}", error)));
+            }
+        }
+    }
+
+    fn open_recent_session(&mut self, session: &RecentSession) {
+        if self.busy {
+            self.set_recent_sessions_status(Err(
+                "Wait for the running prompt before opening another session".to_owned(),
+            ));
+            return;
+        }
+        let snapshot = match load_recent_session(&session.path) {
+            Ok(snapshot) if snapshot_is_consistent(&snapshot) => snapshot,
+            Ok(_) => {
+                logging::error(format!(
+                    "recent session {} has inconsistent ranges",
+                    session.path.display()
+                ));
+                self.set_recent_sessions_status(Err(
+                    "Failed to open: the saved transcript is damaged".to_owned(),
+                ));
+                return;
+            }
+            Err(error) => {
+                logging::error(format!(
+                    "failed to load recent session {}: {}",
+                    session.path.display(),
+                    error
+                ));
+                self.set_recent_sessions_status(Err(format!("Failed to open: {}", error)));
+                return;
+            }
+        };
+        logging::trace(format!(
+            "opening recent session {} ({} bytes, saved by {})",
+            session.path.display(),
+            snapshot.output.len(),
+            snapshot.app_build
+        ));
+        let notice = snapshot
+            .cwd
+            .clone()
+            .filter(|cwd| *cwd != self.cwd_text)
+            .and_then(|cwd| self.restore_recent_session_cwd(&cwd));
+        let input = std::mem::take(&mut self.input);
+        self.clear_session();
+        self.input = input;
+        self.apply_session_snapshot(snapshot, notice.as_deref());
+        self.recent_session = Some(RecentSessionFile {
+            stamp: recent_session_file_stamp(session.file_name())
+                .map_or_else(|| recent_session_stamp(SystemTime::now()), str::to_owned),
+            name: session.file_name().to_owned(),
+            saved_len: self.transcript.len(),
+            saved_title: self.session_title.text().map(str::to_owned),
+            saved_notes: self.notes.text.clone(),
+        });
+        self.close_recent_sessions();
+        self.refresh_after_input_change();
+    }
+
+    fn restore_recent_session_cwd(&mut self, cwd: &str) -> Option<String> {
+        if !Path::new(cwd).is_dir() {
+            logging::error(format!(
+                "recent session directory {} no longer exists; staying in {}",
+                cwd, self.cwd_text
+            ));
+            return Some(format!(
+                "Working directory {} no longer exists \u{2014} staying in {}\n\n",
+                cwd, self.cwd_text
+            ));
+        }
+        match self.run_cd_command(cwd) {
+            Ok(_) => None,
+            Err(error) => {
+                logging::error(format!(
+                    "failed to restore recent session directory: {}",
+                    error
+                ));
+                Some(format!("{}\n\n", error))
+            }
+        }
+    }
+
+    fn pick_recent_session_export(&mut self, path: PathBuf) {
+        if let Some(view) = self.recent_sessions.as_mut() {
+            view.confirming = None;
+            view.status = None;
+        }
+        let owner = self.hwnd as usize;
+        let tx = self.tx.clone();
+        let ctx = self.ctx.clone();
+        thread::spawn(move || {
+            let folder = pick_folder(owner, "Choose a folder for the exported session");
+            if tx
+                .send(AppEvent::RecentSessionExport(path, folder))
+                .is_err()
+            {
+                logging::error("failed to deliver recent session export folder to app");
+            }
+            ctx.request_repaint();
+        });
+    }
+
+    pub(super) fn export_recent_session(&mut self, path: PathBuf, folder: Option<PathBuf>) {
+        let Some(folder) = folder else {
+            logging::trace("recent session export cancelled");
+            return;
+        };
+        let result = export_recent_session_file(&path, &folder);
+        if let Err(error) = &result {
+            logging::error(format!(
+                "failed to export recent session {}: {}",
+                path.display(),
+                error
+            ));
+        }
+        self.set_recent_sessions_status(
+            result.map(|target| format!("Exported to {}", target.display())),
+        );
+    }
+}
+
+fn export_recent_session_file(path: &Path, folder: &Path) -> Result<PathBuf, String> {
+    let snapshot =
+        load_recent_session(path).map_err(|error| format!("Failed to export: {}", error))?;
+    let turn_markers = load_export_turn_markers().unwrap_or_else(|error| {
+        logging::error(format!(
+            "failed to load export turn marker setting: {}",
+            error
+        ));
+        DEFAULT_EXPORT_TURN_MARKERS
+    });
+    let mut transcript = String::new();
+    if let Some(title) = snapshot.title.as_deref() {
+        transcript.push_str(&format!("# {}\n\n", title));
+    }
+    transcript.push_str(strip_line_markers(&snapshot.output, turn_markers).trim());
+    transcript.push_str(&format!(
+        "\n\n---\n\n_Exported by {} {}_",
+        APP_DISPLAY_NAME,
+        build_stamp()
+    ));
+    let target = folder.join(
+        path.with_extension(EXPORT_EXTENSION)
+            .file_name()
+            .unwrap_or_default(),
+    );
+    write_file_atomic(&target, transcript.as_bytes(), false)
+        .map_err(|error| format!("Failed to export to {}: {}", target.display(), error))?;
+    logging::trace(format!("exported recent session to {}", target.display()));
+    Ok(target)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    #[test]
+    fn rows_show_title_turns_age_and_size() {
+        let now = SystemTime::UNIX_EPOCH + Duration::from_secs(1_000_000);
+        let mut session = RecentSession {
+            path: PathBuf::from("2026-10-17_09-00-00-parser.session"),
+            title: Some("Parser fixes".to_owned()),
+            turns: 3,
+            saved_at: now - Duration::from_secs(2 * 3_600),
+            size: 2_048,
+        };
+        assert_eq!(
+            recent_session_row(&session, now),
+            (
+                "Parser fixes".to_owned(),
+                format!(
+                    "{} \u{00B7} {} \u{00B7} 2 KB",
+                    turn_count(3),
+                    relative_age(Duration::from_secs(2 * 3_600))
+                )
+            )
+        );
+        session.title = None;
+        session.saved_at = now + Duration::from_secs(60);
+        let (title, details) = recent_session_row(&session, now);
+        assert_eq!(title, tr(Text::UntitledSession));
+        assert!(
+            details.contains(&relative_age(Duration::ZERO)),
+            "{}",
+            details
+        );
+    }
+}
@@ -0,0 +1,372 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::io;
+
+use eframe::egui::{InputState, Key, KeyboardShortcut, Modifiers};
+
+use crate::logging;
+
+use crate::config::{read_settings, write_setting};
+
+const SHORTCUT_SETTING_PREFIX: &str = "shortcut_";
+const UNBOUND_VALUE: &str = "none";
+
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub(crate) enum ShortcutAction {
+    Cancel,
+    NextError,
+    PreviousError,
+    NewTopic,
+    SaveFile,
+    OutputMenu,
+    CopyPlainText,
+    ToggleNotes,
+    Find,
+}
+
+impl ShortcutAction {
+    pub(crate) const ALL: [ShortcutAction; 9] = [
+        ShortcutAction::Cancel,
+        ShortcutAction::NextError,
+        ShortcutAction::PreviousError,
+        ShortcutAction::NewTopic,
+        ShortcutAction::SaveFile,
+        ShortcutAction::OutputMenu,
+        ShortcutAction::CopyPlainText,
+        ShortcutAction::ToggleNotes,
+        ShortcutAction::Find,
+    ];
+
+    pub(crate) fn label(self) -> &'static str {
+        match self {
+            ShortcutAction::Cancel => "Cancel / close",
+            ShortcutAction::NextError => "Next error",
+            ShortcutAction::PreviousError => "Previous error",
+            ShortcutAction::NewTopic => "New topic",
+            ShortcutAction::SaveFile => "Save codex file",
+            ShortcutAction::OutputMenu => "Output menu",
+            ShortcutAction::CopyPlainText => "Copy as plain text",
+            ShortcutAction::ToggleNotes => "Toggle notes",
+            ShortcutAction::Find => "Find in transcript",
+        }
+    }
+
+    fn setting_name(self) -> &'static str {
+        match self {
+            ShortcutAction::Cancel => "cancel",
+            ShortcutAction::NextError => "next_error",
+            ShortcutAction::PreviousError => "previous_error",
+            ShortcutAction::NewTopic => "new_topic",
+            ShortcutAction::SaveFile => "save_file",
+            ShortcutAction::OutputMenu => "output_menu",
+            ShortcutAction::CopyPlainText => "copy_plain_text",
+            ShortcutAction::ToggleNotes => "toggle_notes",
+            ShortcutAction::Find => "find",
+        }
+    }
+
+    fn setting_key(self) -> String {
+        format!("{}{}", SHORTCUT_SETTING_PREFIX, self.setting_name())
+    }
+
+    pub(crate) fn default_shortcut(self) -> Shortcut {
+        match self {
+            ShortcutAction::Cancel => Shortcut::new(Modifiers::NONE, Key::Escape),
+            ShortcutAction::NextError => Shortcut::new(Modifiers::NONE, Key::F8),
+            ShortcutAction::PreviousError => Shortcut::new(Modifiers::SHIFT, Key::F8),
+            ShortcutAction::NewTopic => Shortcut::new(Modifiers::COMMAND, Key::N),
+            ShortcutAction::SaveFile => Shortcut::new(Modifiers::COMMAND, Key::S),
+            ShortcutAction::OutputMenu => Shortcut::new(Modifiers::SHIFT, Key::F10),
+            ShortcutAction::CopyPlainText => {
+                Shortcut::new(Modifiers::COMMAND | Modifiers::SHIFT, Key::C)
+            }
+            ShortcutAction::ToggleNotes => Shortcut::new(Modifiers::COMMAND, Key::Period),
+            ShortcutAction::Find => Shortcut::new(Modifiers::COMMAND, Key::F),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub(crate) struct Shortcut {
+    pub(crate) modifiers: Modifiers,
+    pub(crate) key: Key,
+}
+
+impl Shortcut {
+    pub(crate) fn new(modifiers: Modifiers, key: Key) -> Self {
+        Self {
+            modifiers: Modifiers {
+                ctrl: false,
+                mac_cmd: false,
+                command: modifiers.command || modifiers.ctrl || modifiers.mac_cmd,
+                ..modifiers
+            },
+            key,
+        }
+    }
+
+    pub(crate) fn parse(value: &str) -> Option<Self> {
+        let mut modifiers = Modifiers::NONE;
+        let mut parts = value.split('+').map(str::trim).peekable();
+        while let Some(part) = parts.next() {
+            if parts.peek().is_none() {
+                let key = Key::from_name(part).or_else(|| {
+                    Key::ALL
+                        .iter()
+                        .copied()
+                        .find(|key| key.name().eq_ignore_ascii_case(part))
+                })?;
+                return Some(Self::new(modifiers, key));
+            }
+            match part.to_ascii_lowercase().as_str() {
+                "ctrl" | "control" | "cmd" | "command" => modifiers.command = true,
+                "shift" => modifiers.shift = true,
+                "alt" | "option" => modifiers.alt = true,
+                _ => return None,
+            }
+        }
+        None
+    }
+
+    fn modifier_count(self) -> usize {
+        usize::from(self.modifiers.command)
+            + usize::from(self.modifiers.shift)
+            + usize::from(self.modifiers.alt)
+    }
+
+    fn keyboard_shortcut(self) -> KeyboardShortcut {
+        KeyboardShortcut::new(self.modifiers, self.key)
+    }
+}
+
+impl fmt::Display for Shortcut {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.modifiers.command {
+            f.write_str("Ctrl+")?;
+        }
+        if self.modifiers.alt {
+            f.write_str("Alt+")?;
+        }
+        if self.modifiers.shift {
+            f.write_str("Shift+")?;
+        }
+        f.write_str(self.key.name())
+    }
+}
+
+#[derive(Clone, Debug)]
+pub(crate) struct ShortcutMap {
+    bindings: HashMap<ShortcutAction, Shortcut>,
+}
+
+impl Default for ShortcutMap {
+    fn default() -> Self {
+        Self {
+            bindings: ShortcutAction::ALL
+                .into_iter()
+                .map(|action| (action, action.default_shortcut()))
+                .collect(),
+        }
+    }
+}
+
+impl ShortcutMap {
+    pub(crate) fn from_settings(settings: &HashMap<String, String>) -> Self {
+        let mut bindings: HashMap<ShortcutAction, Shortcut> = HashMap::new();
+        for action in ShortcutAction::ALL {
+            let shortcut = match settings
+                .get(&action.setting_key())
+                .map(|value| value.trim())
+            {
+                None | Some("") => Some(action.default_shortcut()),
+                Some(value) if value.eq_ignore_ascii_case(UNBOUND_VALUE) => None,
+                Some(value) => Shortcut::parse(value).or_else(|| {
+                    logging::error(format!(
+                        "ignoring unreadable shortcut '{}' for {}; using {}",
+                        value,
+                        action.label().to_lowercase(),
+                        action.default_shortcut()
+                    ));
+                    Some(action.default_shortcut())
+                }),
+            };
+            let Some(shortcut) = shortcut else {
+                continue;
+            };
+            if let Some((other, _)) = bindings.iter().find(|(_, bound)| **bound == shortcut) {
+                logging::error(format!(
+                    "shortcut {} for {} is already bound to {}; ignoring it",
+                    shortcut,
+                    action.label().to_lowercase(),
+                    other.label().to_lowercase()
+                ));
+                continue;
+            }
+            bindings.insert(action, shortcut);
+        }
+        Self { bindings }
+    }
+
+    pub(crate) fn get(&self, action: ShortcutAction) -> Option<Shortcut> {
+        self.bindings.get(&action).copied()
+    }
+
+    pub(crate) fn bind(&mut self, action: ShortcutAction, shortcut: Option<Shortcut>) {
+        match shortcut {
+            Some(shortcut) => self.bindings.insert(action, shortcut),
+            None => self.bindings.remove(&action),
+        };
+    }
+
+    pub(crate) fn action_for(&self, shortcut: Shortcut) -> Option<ShortcutAction> {
+        ShortcutAction::ALL
+            .into_iter()
+            .find(|action| self.get(*action) == Some(shortcut))
+    }
+
+    pub(crate) fn settings(&self) -> Vec<(String, String)> {
+        ShortcutAction::ALL
+            .into_iter()
+            .map(|action| {
+                (
+                    action.setting_key(),
+                    self.get(action)
+                        .map_or_else(|| UNBOUND_VALUE.to_owned(), |shortcut| shortcut.to_string()),
+                )
+            })
+            .collect()
+    }
+
+    pub(crate) fn consume(&self, input: &mut InputState) -> Option<ShortcutAction> {
+        let mut bindings: Vec<_> = self.bindings.iter().collect();
+        bindings.sort_by_key(|(_, shortcut)| std::cmp::Reverse(shortcut.modifier_count()));
+        bindings
+            .into_iter()
+            .find(|(_, shortcut)| input.consume_shortcut(&shortcut.keyboard_shortcut()))
+            .map(|(action, _)| *action)
+    }
+}
+
+pub(crate) fn load_shortcuts() -> io::Result<ShortcutMap> {
+    let settings = read_settings()?;
+    Ok(ShortcutMap::from_settings(&settings))
+}
+
+pub(crate) fn set_shortcut(action: ShortcutAction, shortcut: Option<Shortcut>) -> io::Result<()> {
+    let value = shortcut.map_or_else(|| UNBOUND_VALUE.to_owned(), |shortcut| shortcut.to_string());
+    write_setting(&action.setting_key(), &value)
+}
+
+pub(crate) fn reset_shortcuts() -> io::Result<ShortcutMap> {
+    let defaults = ShortcutMap::default();
+    for (key, value) in defaults.settings() {
+        write_setting(&key, &value)?;
+    }
+    Ok(defaults)
+}
+
+#[cfg(test)]
+mod tests {
+    use eframe::egui::Event;
+
+    use super::*;
+
+    fn settings(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+        pairs
+            .iter()
+            .map(|(key, value)| ((*key).to_owned(), (*value).to_owned()))
+            .collect()
+    }
+
+    fn key_press(modifiers: Modifiers, key: Key) -> InputState {
+        let mut input = InputState::default();
+        input.modifiers = modifiers;
+        input.events.push(Event::Key {
+            key,
+            physical_key: None,
+            pressed: true,
+            repeat: false,
+            modifiers,
+        });
+        input
+    }
+
+    #[test]
+    fn shortcuts_parse_case_insensitively() {
+        let copy = Shortcut::new(Modifiers::COMMAND | Modifiers::SHIFT, Key::C);
+        for value in [
+            "Ctrl+Shift+C",
+            "ctrl + shift + c",
+            "Shift+Control+C",
+            "cmd+SHIFT+c",
+        ] {
+            assert_eq!(Shortcut::parse(value), Some(copy), "{:?}", value);
+        }
+        assert_eq!(
+            Shortcut::parse("Alt+F8"),
+            Some(Shortcut::new(Modifiers::ALT, Key::F8))
+        );
+        assert_eq!(
+            Shortcut::parse("escape"),
+            Some(Shortcut::new(Modifiers::NONE, Key::Escape))
+        );
+    }
+
+    #[test]
+    fn malformed_shortcuts_do_not_parse() {
+        for value in ["", "Ctrl+", "Hyper+C", "Ctrl+Nope", "C+Ctrl"] {
+            assert_eq!(Shortcut::parse(value), None, "{:?}", value);
+        }
+    }
+
+    #[test]
+    fn shortcuts_round_trip_through_settings() {
+        let mut map = ShortcutMap::default();
+        map.bind(
+            ShortcutAction::CopyPlainText,
+            Shortcut::parse("Ctrl+Alt+Shift+P"),
+        );
+        map.bind(ShortcutAction::OutputMenu, None);
+        let saved: HashMap<_, _> = map.settings().into_iter().collect();
+        assert_eq!(saved["shortcut_copy_plain_text"], "Ctrl+Alt+Shift+P");
+        assert_eq!(saved["shortcut_output_menu"], "none");
+        let loaded = ShortcutMap::from_settings(&saved);
+        for action in ShortcutAction::ALL {
+            assert_eq!(loaded.get(action), map.get(action), "{:?}", action);
+        }
+    }
+
+    #[test]
+    fn missing_and_unreadable_settings_use_defaults() {
+        let map = ShortcutMap::from_settings(&settings(&[
+            ("shortcut_new_topic", "Ctrl+Hyper+N"),
+            ("shortcut_save_file", "  "),
+        ]));
+        for action in ShortcutAction::ALL {
+            assert_eq!(map.get(action), Some(action.default_shortcut()));
+        }
+    }
+
+    #[test]
+    fn later_conflicting_bindings_are_ignored() {
+        let map = ShortcutMap::from_settings(&settings(&[
+            ("shortcut_next_error", "Ctrl+N"),
+            ("shortcut_new_topic", "Ctrl+N"),
+        ]));
+        assert_eq!(
+            map.action_for(Shortcut::new(Modifiers::COMMAND, Key::N)),
+            Some(ShortcutAction::NextError)
+        );
+        assert_eq!(map.get(ShortcutAction::NewTopic), None);
+    }
+
+    #[test]
+    fn bindings_with_more_modifiers_win() {
+        let map = ShortcutMap::default();
+        let mut input = key_press(Modifiers::SHIFT, Key::F8);
+        assert_eq!(map.consume(&mut input), Some(ShortcutAction::PreviousError));
+        assert_eq!(map.consume(&mut input), None);
+        let mut input = key_press(Modifiers::NONE, Key::F8);
+        assert_eq!(map.consume(&mut input), Some(ShortcutAction::NextError));
+    }
+}
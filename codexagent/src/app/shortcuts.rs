@@ -0,0 +1,118 @@
+use eframe::egui::Event;
+
+use super::shortcut_map::{Shortcut, ShortcutAction, reset_shortcuts, set_shortcut};
+use crate::logging;
+
+use super::CodexAgentApp;
+
+const CAPTURE_PROMPT: &str = "Press a key\u{2026}";
+
+impl CodexAgentApp {
+    pub(super) fn read_shortcut(&mut self) -> Option<ShortcutAction> {
+        let Some(action) = self.shortcut_capture else {
+            return self.ctx.input_mut(|input| self.shortcuts.consume(input));
+        };
+        let captured = self.ctx.input_mut(|input| {
+            let index = input.events.iter().position(|event| {
+                matches!(
+                    event,
+                    Event::Key {
+                        pressed: true,
+                        repeat: false,
+                        ..
+                    }
+                )
+            })?;
+            let Event::Key { key, modifiers, .. } = input.events.remove(index) else {
+                return None;
+            };
+            input
+                .events
+                .retain(|event| !matches!(event, Event::Text(_)));
+            Some(Shortcut::new(modifiers, key))
+        });
+        if let Some(shortcut) = captured {
+            self.rebind_shortcut(action, shortcut);
+        }
+        None
+    }
+
+    pub(super) fn shortcut_triggered(&self, action: ShortcutAction) -> bool {
+        self.triggered_shortcut == Some(action)
+    }
+
+    pub(super) fn shortcut_label(&self, action: ShortcutAction) -> String {
+        if self.shortcut_capture == Some(action) {
+            return CAPTURE_PROMPT.to_owned();
+        }
+        self.shortcuts
+            .get(action)
+            .map_or_else(|| "Unbound".to_owned(), |shortcut| shortcut.to_string())
+    }
+
+    pub(super) fn toggle_shortcut_capture(&mut self, action: ShortcutAction) {
+        self.shortcut_capture = if self.shortcut_capture == Some(action) {
+            None
+        } else {
+            Some(action)
+        };
+    }
+
+    fn rebind_shortcut(&mut self, action: ShortcutAction, shortcut: Shortcut) {
+        self.shortcut_capture = None;
+        self.clear_picker_selection();
+        if let Some(other) = self
+            .shortcuts
+            .action_for(shortcut)
+            .filter(|other| *other != action)
+        {
+            logging::error(format!(
+                "refusing to bind {} to {}; it is already bound to {}",
+                shortcut,
+                action.label().to_lowercase(),
+                other.label().to_lowercase()
+            ));
+            self.push_local_error(&format!(
+                "{} is already bound to {}",
+                shortcut,
+                other.label()
+            ));
+            self.finish_local_change();
+            return;
+        }
+        match set_shortcut(action, Some(shortcut)) {
+            Ok(()) => {
+                self.shortcuts.bind(action, Some(shortcut));
+                self.push_settings_output(&format!("{} set to {}", action.label(), shortcut));
+                self.finish_local_success();
+            }
+            Err(error) => {
+                logging::error(format!(
+                    "failed to bind {} to {}: {}",
+                    action.label().to_lowercase(),
+                    shortcut,
+                    error
+                ));
+                self.push_local_error(&format!("Failed to set shortcut: {}", error));
+            }
+        }
+        self.finish_local_change();
+    }
+
+    pub(super) fn restore_default_shortcuts(&mut self) {
+        self.shortcut_capture = None;
+        self.clear_picker_selection();
+        match reset_shortcuts() {
+            Ok(shortcuts) => {
+                self.shortcuts = shortcuts;
+                self.push_settings_output("Shortcuts reset to defaults");
+                self.finish_local_success();
+            }
+            Err(error) => {
+                logging::error(format!("failed to reset shortcuts: {}", error));
+                self.push_local_error(&format!("Failed to reset shortcuts: {}", error));
+            }
+        }
+        self.finish_local_change();
+    }
+}
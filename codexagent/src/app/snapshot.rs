@@ -0,0 +1,136 @@
+use std::time::{Duration, Instant};
+
+use crate::config::snapshot::{
+    SessionSnapshot, clear_snapshot, load_snapshot, save_snapshot_async,
+};
+use crate::logging;
+
+use super::CodexAgentApp;
+use super::notes::SessionNotes;
+use super::recovery::RESTARTED_TEXT;
+use super::title::SessionTitle;
+use super::transcript::{SegmentKind, Transcript};
+
+const SNAPSHOT_INTERVAL: Duration = Duration::from_secs(5);
+const SNAPSHOT_MIN_GROWTH: usize = 16 * 1024;
+const INTERRUPTED_TEXT: &str = "\x1C(response interrupted by crash)\n\n";
+
+impl CodexAgentApp {
+    pub(super) fn autosave_snapshot(&mut self) {
+        if self.active_prompt_id.is_none() || self.transcript.len() == self.snapshot_len {
+            return;
+        }
+        let due = self
+            .snapshot_saved_at
+            .is_none_or(|saved| saved.elapsed() >= SNAPSHOT_INTERVAL)
+            || self.transcript.len().abs_diff(self.snapshot_len) >= SNAPSHOT_MIN_GROWTH;
+        if due {
+            self.save_snapshot();
+        }
+    }
+
+    pub(super) fn save_snapshot(&mut self) {
+        save_snapshot_async(&self.session_snapshot(true));
+        self.snapshot_len = self.transcript.len();
+        self.snapshot_saved_at = Some(Instant::now());
+    }
+
+    pub(super) fn session_snapshot(&self, in_progress: bool) -> SessionSnapshot {
+        SessionSnapshot {
+            in_progress,
+            output: self.transcript.text().to_owned(),
+            output_base: self.transcript.response_start(),
+            prompt_ranges: self.transcript.prompt_ranges().to_vec(),
+            pinned_ranges: self.transcript.pinned_ranges(),
+            pinned_turns: self.transcript.pinned_turns().to_vec(),
+            ratings: self.transcript.ratings().to_vec(),
+            session_id: self.session_id.clone(),
+            response_language: self.response_language.clone(),
+            title: self.session_title.text().map(str::to_owned),
+            title_renamed: self.session_title.renamed(),
+            cwd: Some(self.cwd_text.clone()),
+            notes: self.notes.text.clone(),
+            ..SessionSnapshot::default()
+        }
+    }
+
+    pub(super) fn finish_snapshot(&mut self) {
+        if self.snapshot_saved_at.take().is_some() {
+            clear_snapshot();
+        }
+        self.snapshot_len = 0;
+    }
+
+    pub(super) fn restore_snapshot(&mut self) {
+        let snapshot = match load_snapshot() {
+            Ok(Some(snapshot)) => snapshot,
+            Ok(None) => return,
+            Err(error) => {
+                logging::error(format!("failed to load session snapshot: {}", error));
+                clear_snapshot();
+                return;
+            }
+        };
+        clear_snapshot();
+        if !(snapshot.in_progress || snapshot.relaunched) || !snapshot_is_consistent(&snapshot) {
+            return;
+        }
+        logging::trace(format!(
+            "restoring {} session snapshot ({} bytes, saved by {})",
+            if snapshot.relaunched {
+                "relaunched"
+            } else {
+                "interrupted"
+            },
+            snapshot.output.len(),
+            snapshot.app_build
+        ));
+        let notice = match (snapshot.in_progress, snapshot.relaunched) {
+            (false, _) => None,
+            (true, true) => Some(RESTARTED_TEXT),
+            (true, false) => Some(INTERRUPTED_TEXT),
+        };
+        self.apply_session_snapshot(snapshot, notice);
+    }
+
+    pub(super) fn apply_session_snapshot(
+        &mut self,
+        snapshot: SessionSnapshot,
+        notice: Option<&str>,
+    ) {
+        self.transcript = Transcript::restore(
+            &snapshot.output,
+            &snapshot.prompt_ranges,
+            &snapshot.pinned_ranges,
+            &snapshot.pinned_turns,
+            &snapshot.ratings,
+        );
+        if !self.transcript.is_empty() && !self.transcript.text().ends_with('\n') {
+            self.transcript.push_response("\n");
+        }
+        if let Some(notice) = notice {
+            self.transcript.push(SegmentKind::Notice, notice);
+        }
+        self.transcript.begin_response();
+        self.session_id = snapshot.session_id;
+        self.response_language = snapshot.response_language;
+        self.session_title = SessionTitle::restored(snapshot.title, snapshot.title_renamed);
+        self.notes = SessionNotes::restored(snapshot.notes);
+        self.refresh_after_output_rewrite();
+    }
+}
+
+pub(super) fn snapshot_is_consistent(snapshot: &SessionSnapshot) -> bool {
+    let output = snapshot.output.as_str();
+    output.is_char_boundary(snapshot.output_base)
+        && snapshot
+            .prompt_ranges
+            .iter()
+            .chain(&snapshot.pinned_ranges)
+            .all(|&(start, end)| {
+                start <= end
+                    && end <= snapshot.output_base
+                    && output.is_char_boundary(start)
+                    && output.is_char_boundary(end)
+            })
+}
@@ -0,0 +1,530 @@
+use std::time::{Duration, Instant};
+
+use serde_json::{Value, json};
+
+use crate::events::PromptResult;
+use crate::logging;
+use crate::usage::{TokenUsage, format_cost, format_tokens};
+
+use super::CodexAgentApp;
+
+pub(super) struct TurnStats {
+    pub(super) turn: usize,
+    pub(super) model: String,
+    pub(super) profile: Option<String>,
+    submitted_at: Instant,
+    first_output_after: Option<Duration>,
+    completed_after: Duration,
+    response_chars: usize,
+    usage: Option<TokenUsage>,
+    cost: Option<f64>,
+    warm_start: bool,
+    failed: bool,
+}
+
+impl TurnStats {
+    /// The fields this turn adds to its entry in the JSON export.
+    pub(super) fn export_json(&self, session_start: Instant) -> Value {
+        json!({
+            "model": self.model,
+            "profile": self.profile,
+            "tokens": self.usage,
+            "cost_usd": self.cost,
+            "sent_after_ms": millis(self.submitted_at.saturating_duration_since(session_start)),
+            "first_output_ms": self.first_output_after.map(millis),
+            "duration_ms": millis(self.completed_after),
+            "response_chars": self.response_chars,
+            "warm_start": self.warm_start,
+            "failed": self.failed,
+        })
+    }
+}
+
+struct StatsSummary {
+    prompts: usize,
+    failed: usize,
+    first_output_median: Option<Duration>,
+    first_output_max: Option<Duration>,
+    duration_median: Option<Duration>,
+    tokens: TokenUsage,
+    unreported: usize,
+}
+
+fn summarize(turns: &[TurnStats]) -> StatsSummary {
+    let first_outputs = turns
+        .iter()
+        .filter_map(|turn| turn.first_output_after)
+        .collect::<Vec<_>>();
+    let mut tokens = TokenUsage::default();
+    let mut unreported = 0;
+    for turn in turns {
+        match turn.usage {
+            Some(usage) => tokens.add(usage),
+            None => unreported += 1,
+        }
+    }
+    StatsSummary {
+        prompts: turns.len(),
+        failed: turns.iter().filter(|turn| turn.failed).count(),
+        first_output_max: first_outputs.iter().max().copied(),
+        first_output_median: median(first_outputs),
+        duration_median: median(turns.iter().map(|turn| turn.completed_after).collect()),
+        tokens,
+        unreported,
+    }
+}
+
+fn millis(duration: Duration) -> u64 {
+    duration.as_millis().try_into().unwrap_or(u64::MAX)
+}
+
+fn summary_json(turns: &[TurnStats]) -> Value {
+    let summary = summarize(turns);
+    json!({
+        "prompts": summary.prompts,
+        "failed": summary.failed,
+        "first_output_median_ms": summary.first_output_median.map(millis),
+        "first_output_max_ms": summary.first_output_max.map(millis),
+        "duration_median_ms": summary.duration_median.map(millis),
+        "tokens": summary.tokens,
+        "prompts_without_usage": summary.unreported,
+    })
+}
+
+fn median(mut values: Vec<Duration>) -> Option<Duration> {
+    if values.is_empty() {
+        return None;
+    }
+    values.sort_unstable();
+    let middle = values.len() / 2;
+    Some(if values.len().is_multiple_of(2) {
+        (values[middle - 1] + values[middle]) / 2
+    } else {
+        values[middle]
+    })
+}
+
+fn format_seconds(duration: Duration) -> String {
+    let seconds = duration.as_secs_f64();
+    if seconds < 60.0 {
+        format!("{:.1}s", seconds)
+    } else {
+        format!(
+            "{}m{:02}s",
+            duration.as_secs() / 60,
+            duration.as_secs() % 60
+        )
+    }
+}
+
+fn format_optional(duration: Option<Duration>) -> String {
+    duration.map_or_else(|| "\u{2014}".to_owned(), format_seconds)
+}
+
+fn summary_lines(turns: &[TurnStats]) -> Vec<String> {
+    let StatsSummary {
+        prompts,
+        failed,
+        first_output_median,
+        first_output_max,
+        duration_median,
+        tokens,
+        unreported,
+    } = summarize(turns);
+    let mut prompts = format!("Prompts: {}", prompts);
+    if failed > 0 {
+        prompts.push_str(&format!(" ({} failed)", failed));
+    }
+    let (warm_first_outputs, cold_first_outputs): (Vec<_>, Vec<_>) = turns
+        .iter()
+        .filter_map(|turn| {
+            turn.first_output_after
+                .map(|after| (turn.warm_start, after))
+        })
+        .partition(|(warm, _)| *warm);
+    let mut token_line = format!(
+        "Tokens: {} ({} in / {} out)",
+        format_tokens(tokens.total()),
+        format_tokens(tokens.input_tokens),
+        format_tokens(tokens.output_tokens)
+    );
+    if unreported > 0 {
+        token_line.push_str(&format!(", {} prompt(s) without usage", unreported));
+    }
+    let mut lines = vec![
+        prompts,
+        format!(
+            "Time to first output: median {} \u{b7} max {}",
+            format_optional(first_output_median),
+            format_optional(first_output_max)
+        ),
+    ];
+    if !warm_first_outputs.is_empty() {
+        lines.push(warm_start_line(&warm_first_outputs, &cold_first_outputs));
+    }
+    lines.push(format!(
+        "Total duration: median {}",
+        format_optional(duration_median)
+    ));
+    lines.push(token_line);
+    lines
+}
+
+fn warm_start_line(warm: &[(bool, Duration)], cold: &[(bool, Duration)]) -> String {
+    let warm_median = median(warm.iter().map(|(_, after)| *after).collect());
+    let cold_median = median(cold.iter().map(|(_, after)| *after).collect());
+    let mut line = format!(
+        "Warm start: first output median {} warm ({}) vs {} cold ({})",
+        format_optional(warm_median),
+        warm.len(),
+        format_optional(cold_median),
+        cold.len()
+    );
+    if let (Some(warm), Some(cold)) = (warm_median, cold_median) {
+        line.push_str(&if warm <= cold {
+            format!(", {} faster", format_seconds(cold - warm))
+        } else {
+            format!(", {} slower", format_seconds(warm - cold))
+        });
+    }
+    line
+}
+
+fn turn_tokens(turn: &TurnStats) -> String {
+    turn.usage.map_or_else(
+        || "\u{2014}".to_owned(),
+        |usage| format_tokens(usage.total()),
+    )
+}
+
+// The dimmed line under each answer; tokens and cost only appear once codex
+// reported usage and the model has a price.
+fn footer_text(turn: &TurnStats) -> String {
+    let mut footer = format_seconds(turn.completed_after);
+    if let Some(after) = turn.first_output_after {
+        footer.push_str(&format!(" \u{b7} first output {}", format_seconds(after)));
+    }
+    if let Some(usage) = turn.usage {
+        footer.push_str(&format!(" \u{b7} {} tok", format_tokens(usage.total())));
+    }
+    if let Some(cost) = turn.cost {
+        footer.push_str(&format!(" \u{b7} {}", format_cost(cost)));
+    }
+    footer
+}
+
+fn stats_report(turns: &[TurnStats]) -> String {
+    let session_start = turns[0].submitted_at;
+    let mut lines = summary_lines(turns);
+    lines.push(String::new());
+    for (index, turn) in turns.iter().enumerate() {
+        lines.push(format!(
+            "#{} {} \u{b7} sent +{} \u{b7} first {}{} \u{b7} total {} \u{b7} {} chars \u{b7} {} tok{}",
+            index + 1,
+            turn.model,
+            format_seconds(turn.submitted_at.saturating_duration_since(session_start)),
+            format_optional(turn.first_output_after),
+            if turn.warm_start { " (warm)" } else { "" },
+            format_seconds(turn.completed_after),
+            turn.response_chars,
+            turn_tokens(turn),
+            if turn.failed { " \u{b7} failed" } else { "" }
+        ));
+    }
+    lines.join("\n")
+}
+
+fn stats_markdown(turns: &[TurnStats]) -> String {
+    let session_start = turns[0].submitted_at;
+    let mut markdown = String::from("## Statistics\n\n");
+    for line in summary_lines(turns) {
+        markdown.push_str(&format!("- {}\n", line));
+    }
+    markdown.push_str(
+        "\n| # | Model | Sent | First output | Total | Chars | Tokens | Result |\n\
+         |---|---|---|---|---|---|---|---|\n",
+    );
+    for (index, turn) in turns.iter().enumerate() {
+        markdown.push_str(&format!(
+            "| {} | {} | +{} | {} | {} | {} | {} | {} |\n",
+            index + 1,
+            turn.model,
+            format_seconds(turn.submitted_at.saturating_duration_since(session_start)),
+            format_optional(turn.first_output_after),
+            format_seconds(turn.completed_after),
+            turn.response_chars,
+            turn_tokens(turn),
+            if turn.failed { "failed" } else { "ok" }
+        ));
+    }
+    markdown
+}
+
+impl CodexAgentApp {
+    pub(super) fn record_turn_stats(
+        &mut self,
+        prompt_id: u64,
+        result: &PromptResult,
+        cost: Option<f64>,
+    ) {
+        let Some(submitted_at) = self.stream_throughput.started_at() else {
+            return;
+        };
+        let (response_chars, usage, failed) = match result {
+            PromptResult::Ok(output) => (output.response.chars().count(), output.usage, false),
+            PromptResult::Err(..) => (0, None, true),
+        };
+        let warm_start = self
+            .shared_stream
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .warm_start(prompt_id);
+        let turn = TurnStats {
+            turn: self.transcript.prompt_ranges().len().saturating_sub(1),
+            model: self.prompt_model(),
+            profile: self.active_codex_profile().map(str::to_owned),
+            submitted_at,
+            first_output_after: self.stream_throughput.first_byte_after(),
+            completed_after: submitted_at.elapsed(),
+            response_chars,
+            usage,
+            cost,
+            warm_start,
+            failed,
+        };
+        logging::trace(format!(
+            "turn {} took {} ms, first output after {} ({} start)",
+            self.turn_stats.len() + 1,
+            turn.completed_after.as_millis(),
+            turn.first_output_after.map_or_else(
+                || "none".to_owned(),
+                |after| format!("{} ms", after.as_millis())
+            ),
+            if turn.warm_start { "warm" } else { "cold" }
+        ));
+        self.turn_stats.push(turn);
+    }
+
+    pub(super) fn session_stats_text(&self) -> Option<String> {
+        let ratings = self.ratings_summary_lines();
+        if self.turn_stats.is_empty() {
+            return (!ratings.is_empty()).then(|| ratings.join("\n"));
+        }
+        let mut report = stats_report(&self.turn_stats);
+        if !ratings.is_empty() {
+            report.push_str("\n\n");
+            report.push_str(&ratings.join("\n"));
+        }
+        Some(report)
+    }
+
+    /// The footer for the answer that just finished, if its timing was
+    /// recorded.
+    pub(super) fn timing_footer(&self) -> Option<String> {
+        let current = self.transcript.prompt_ranges().len().checked_sub(1)?;
+        self.turn_stats
+            .last()
+            .filter(|turn| turn.turn == current && !turn.failed)
+            .map(footer_text)
+    }
+
+    pub(super) fn last_turn_summary(&self) -> Option<String> {
+        let turn = self.turn_stats.last()?;
+        Some(format!(
+            "{} \u{b7} first output {} \u{b7} total {} \u{b7} {} chars \u{b7} {} tok \u{b7} {}",
+            turn.model,
+            format_optional(turn.first_output_after),
+            format_seconds(turn.completed_after),
+            turn.response_chars,
+            turn_tokens(turn),
+            if turn.failed { "failed" } else { "ok" }
+        ))
+    }
+
+    pub(super) fn session_stats_markdown(&self) -> Option<String> {
+        (!self.turn_stats.is_empty()).then(|| stats_markdown(&self.turn_stats))
+    }
+
+    pub(super) fn session_stats_json(&self) -> Option<Value> {
+        (!self.turn_stats.is_empty()).then(|| summary_json(&self.turn_stats))
+    }
+
+    pub(super) fn turn_stats_json(&self, turn: usize) -> Option<Value> {
+        let session_start = self.turn_stats.first()?.submitted_at;
+        self.turn_stats
+            .iter()
+            .rev()
+            .find(|stats| stats.turn == turn)
+            .map(|stats| stats.export_json(session_start))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn turn(
+        first_output_ms: Option<u64>,
+        total_ms: u64,
+        usage: Option<TokenUsage>,
+        cost: Option<f64>,
+    ) -> TurnStats {
+        TurnStats {
+            turn: 0,
+            model: "gpt-5".to_owned(),
+            profile: None,
+            submitted_at: Instant::now(),
+            first_output_after: first_output_ms.map(Duration::from_millis),
+            completed_after: Duration::from_millis(total_ms),
+            response_chars: 10,
+            usage,
+            cost,
+            warm_start: false,
+            failed: false,
+        }
+    }
+
+    fn usage(input: u64, output: u64) -> TokenUsage {
+        TokenUsage {
+            input_tokens: input,
+            output_tokens: output,
+            ..TokenUsage::default()
+        }
+    }
+
+    #[test]
+    fn footer_shows_what_was_measured() {
+        assert_eq!(footer_text(&turn(None, 800, None, None)), "0.8s");
+        assert_eq!(
+            footer_text(&turn(Some(1200), 12_400, None, None)),
+            "12.4s \u{b7} first output 1.2s"
+        );
+        assert_eq!(
+            footer_text(&turn(Some(1200), 75_000, Some(usage(3000, 400)), None)),
+            "1m15s \u{b7} first output 1.2s \u{b7} 3.4k tok"
+        );
+        assert_eq!(
+            footer_text(&turn(
+                Some(1200),
+                12_400,
+                Some(usage(3000, 400)),
+                Some(0.0123)
+            )),
+            "12.4s \u{b7} first output 1.2s \u{b7} 3.4k tok \u{b7} $0.01"
+        );
+    }
+
+    #[test]
+    fn median_averages_the_middle_pair() {
+        let ms = |values: &[u64]| values.iter().map(|ms| Duration::from_millis(*ms)).collect();
+        assert_eq!(median(Vec::new()), None);
+        assert_eq!(
+            median(ms(&[300, 100, 200])),
+            Some(Duration::from_millis(200))
+        );
+        assert_eq!(
+            median(ms(&[400, 100, 300, 200])),
+            Some(Duration::from_millis(250))
+        );
+    }
+
+    #[test]
+    fn seconds_switch_to_minutes_at_a_minute() {
+        assert_eq!(format_seconds(Duration::from_millis(59_940)), "59.9s");
+        assert_eq!(format_seconds(Duration::from_secs(60)), "1m00s");
+        assert_eq!(format_seconds(Duration::from_secs(605)), "10m05s");
+        assert_eq!(format_optional(None), "\u{2014}");
+    }
+
+    #[test]
+    fn summary_counts_failures_and_missing_usage() {
+        let mut failed = turn(None, 3000, None, None);
+        failed.failed = true;
+        let turns = [
+            turn(Some(1000), 4000, Some(usage(1000, 200)), None),
+            turn(Some(3000), 8000, Some(usage(2000, 200)), None),
+            failed,
+        ];
+        assert_eq!(
+            summary_lines(&turns),
+            [
+                "Prompts: 3 (1 failed)",
+                "Time to first output: median 2.0s \u{b7} max 3.0s",
+                "Total duration: median 4.0s",
+                "Tokens: 3.4k (3.0k in / 400 out), 1 prompt(s) without usage",
+            ]
+        );
+    }
+
+    #[test]
+    fn warm_starts_are_compared_with_cold_ones() {
+        let mut warm = turn(Some(500), 2000, None, None);
+        warm.warm_start = true;
+        let turns = [warm, turn(Some(2000), 4000, None, None)];
+        assert_eq!(
+            summary_lines(&turns)[2],
+            "Warm start: first output median 0.5s warm (1) vs 2.0s cold (1), 1.5s faster"
+        );
+        let warm_only = [(true, Duration::from_millis(500))];
+        assert_eq!(
+            warm_start_line(&warm_only, &[]),
+            "Warm start: first output median 0.5s warm (1) vs \u{2014} cold (0)"
+        );
+    }
+
+    #[test]
+    fn markdown_has_a_row_per_turn() {
+        let start = Instant::now();
+        let mut second = turn(None, 1500, None, None);
+        second.submitted_at = start + Duration::from_secs(30);
+        second.failed = true;
+        let mut first = turn(Some(700), 5000, Some(usage(100, 20)), None);
+        first.submitted_at = start;
+        let markdown = stats_markdown(&[first, second]);
+        assert!(markdown.starts_with("## Statistics\n\n- Prompts: 2 (1 failed)\n"));
+        assert!(markdown.contains("| 1 | gpt-5 | +0.0s | 0.7s | 5.0s | 10 | 120 | ok |\n"));
+        assert!(
+            markdown
+                .contains("| 2 | gpt-5 | +30.0s | \u{2014} | 1.5s | 10 | \u{2014} | failed |\n")
+        );
+    }
+
+    #[test]
+    fn json_statistics_are_in_milliseconds() {
+        let start = Instant::now();
+        let mut first = turn(Some(700), 5000, Some(usage(100, 20)), Some(0.5));
+        first.submitted_at = start;
+        let mut second = turn(None, 1500, None, None);
+        second.submitted_at = start + Duration::from_secs(30);
+        second.warm_start = true;
+        assert_eq!(
+            first.export_json(start),
+            json!({
+                "model": "gpt-5",
+                "profile": null,
+                "tokens": usage(100, 20),
+                "cost_usd": 0.5,
+                "sent_after_ms": 0,
+                "first_output_ms": 700,
+                "duration_ms": 5000,
+                "response_chars": 10,
+                "warm_start": false,
+                "failed": false,
+            })
+        );
+        assert_eq!(second.export_json(start)["sent_after_ms"], 30_000);
+        assert_eq!(second.export_json(start)["first_output_ms"], Value::Null);
+        assert_eq!(
+            summary_json(&[first, second]),
+            json!({
+                "prompts": 2,
+                "failed": 0,
+                "first_output_median_ms": 700,
+                "first_output_max_ms": 700,
+                "duration_median_ms": 3250,
+                "tokens": usage(100, 20),
+                "prompts_without_usage": 1,
+            })
+        );
+    }
+}
@@ -0,0 +1,320 @@
+use std::path::Path;
+use std::sync::Arc;
+
+use eframe::egui::{
+    self, Color32, CursorIcon, FontId,
+    text::{CCursor, LayoutJob, TextFormat},
+};
+use eframe::epaint::text::Galley;
+
+use crate::config::LauncherMode;
+use crate::config::project::{PROJECT_CONFIG_FILE, ProjectConfig};
+use crate::logging;
+use crate::runtime::{CodexFile, current_sandbox_mode};
+
+use super::CodexAgentApp;
+
+pub(super) const STATUS_STRIP_HEIGHT: f32 = 14.0;
+pub(super) const STATUS_STRIP_SPACING: f32 = 4.0;
+const STATUS_STRIP_FONT_SIZE: f32 = 10.0;
+const STATUS_STRIP_SEPARATOR: &str = "  \u{00B7}  ";
+const SESSION_ID_PREFIX_CHARS: usize = 8;
+const FENCE_ENTER_HINT: &str = "inside code block \u{2014} Ctrl+Enter to send";
+const FENCE_ENTER_HINT_GAP: f32 = 12.0;
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum StatusTarget {
+    Title,
+    Backend,
+    Model,
+    Profile,
+    Sandbox,
+    Session,
+    ProjectConfig,
+}
+
+impl StatusTarget {
+    fn hint(self) -> &'static str {
+        match self {
+            Self::Title => "Session title \u{2014} click to rename",
+            Self::Backend => {
+                "Codex backend \u{2014} change it under Settings \u{203A} Codex Install"
+            }
+            Self::Model => "Model for the next prompt \u{2014} click to pick another",
+            Self::Profile => "Codex profile from config.toml \u{2014} click to switch",
+            Self::Sandbox => "Sandbox mode \u{2014} click to edit the config that sets it",
+            Self::Session => "Session the next prompt continues \u{2014} click to resume another",
+            Self::ProjectConfig => "Project config \u{2014} click to edit it",
+        }
+    }
+}
+
+pub(super) struct StatusStrip {
+    title: Option<String>,
+    launcher: LauncherMode,
+    model: String,
+    profile: Option<Option<String>>,
+    sandbox: Option<String>,
+    session: Option<String>,
+    project_config: bool,
+    galley: Arc<Galley>,
+    segments: Vec<(StatusTarget, f32, f32)>,
+}
+
+impl StatusStrip {
+    fn target_at(&self, x: f32) -> Option<StatusTarget> {
+        self.segments
+            .iter()
+            .find(|&&(_, start, end)| x >= start && x <= end)
+            .map(|&(target, ..)| target)
+    }
+}
+
+impl CodexAgentApp {
+    pub(super) fn refresh_sandbox_mode(&mut self) {
+        self.sandbox_mode = current_sandbox_mode();
+    }
+
+    pub(super) fn status_strip_height(&self) -> f32 {
+        if self.compact_mode {
+            return 0.0;
+        }
+        STATUS_STRIP_HEIGHT + STATUS_STRIP_SPACING
+    }
+
+    fn status_project_config(&self) -> Option<&ProjectConfig> {
+        self.project_config
+            .config()
+            .filter(|_| self.project_config_enabled)
+    }
+
+    fn status_model(&self) -> &str {
+        self.status_project_config()
+            .and_then(|config| config.model.as_deref())
+            .unwrap_or(&self.current_model)
+    }
+
+    fn status_profile(&self) -> Option<Option<String>> {
+        if self.codex_profiles.names.is_empty() && self.active_codex_profile().is_none() {
+            return None;
+        }
+        Some(self.active_codex_profile().map(str::to_owned))
+    }
+
+    fn status_sandbox(&self) -> Option<&str> {
+        self.status_project_config()
+            .and_then(|config| config.sandbox_mode.as_deref())
+            .or(self.sandbox_mode.as_deref())
+    }
+
+    fn status_strip_stale(&self) -> bool {
+        let Some(strip) = self.status_strip.as_ref() else {
+            return true;
+        };
+        strip.title.as_deref() != self.session_title.text()
+            || strip.launcher != self.launcher_mode
+            || strip.model != self.status_model()
+            || strip.profile != self.status_profile()
+            || strip.sandbox.as_deref() != self.status_sandbox()
+            || strip.session != self.session_id
+            || strip.project_config != self.status_project_config().is_some()
+    }
+
+    fn rebuild_status_strip(&mut self) {
+        let title = self.session_title.text().map(str::to_owned);
+        let launcher = self.launcher_mode.clone();
+        let model = self.status_model().to_owned();
+        let profile = self.status_profile();
+        let sandbox = self.status_sandbox().map(str::to_owned);
+        let session = self.session_id.clone();
+        let project_config = self.status_project_config().is_some();
+        let backend = match &launcher {
+            LauncherMode::Native => "codex".to_owned(),
+            LauncherMode::Wsl(None) => "codex (WSL)".to_owned(),
+            LauncherMode::Wsl(Some(distro)) => format!("codex (WSL: {})", distro),
+        };
+        let session_text = match &session {
+            Some(id) => format!(
+                "session {}",
+                id.chars().take(SESSION_ID_PREFIX_CHARS).collect::<String>()
+            ),
+            None => "new session".to_owned(),
+        };
+        let mut parts = Vec::new();
+        if let Some(title) = &title {
+            parts.push((StatusTarget::Title, title.clone()));
+        }
+        parts.extend([
+            (StatusTarget::Backend, backend),
+            (StatusTarget::Model, model.clone()),
+        ]);
+        if let Some(profile) = &profile {
+            parts.push((
+                StatusTarget::Profile,
+                match profile {
+                    Some(name) => format!("profile {}", name),
+                    None => "default profile".to_owned(),
+                },
+            ));
+        }
+        parts.extend([
+            (
+                StatusTarget::Sandbox,
+                sandbox
+                    .clone()
+                    .unwrap_or_else(|| "default sandbox".to_owned()),
+            ),
+            (StatusTarget::Session, session_text),
+            (
+                StatusTarget::ProjectConfig,
+                if project_config {
+                    PROJECT_CONFIG_FILE.to_owned()
+                } else {
+                    "no project config".to_owned()
+                },
+            ),
+        ]);
+        let format = TextFormat {
+            font_id: FontId::proportional(STATUS_STRIP_FONT_SIZE),
+            color: Color32::from_rgba_unmultiplied(214, 224, 238, 120),
+            ..Default::default()
+        };
+        let mut job = LayoutJob::default();
+        let mut ranges = Vec::with_capacity(parts.len());
+        for (index, (target, text)) in parts.iter().enumerate() {
+            if index > 0 {
+                job.append(STATUS_STRIP_SEPARATOR, 0.0, format.clone());
+            }
+            let start = job.text.chars().count();
+            job.append(text, 0.0, format.clone());
+            ranges.push((*target, start, start + text.chars().count()));
+        }
+        let galley = self.ctx.fonts(|fonts| fonts.layout_job(job));
+        let segments = ranges
+            .into_iter()
+            .map(|(target, start, end)| {
+                (
+                    target,
+                    galley.pos_from_ccursor(CCursor::new(start)).left(),
+                    galley.pos_from_ccursor(CCursor::new(end)).left(),
+                )
+            })
+            .collect();
+        logging::trace("rebuilt status strip");
+        self.status_strip = Some(StatusStrip {
+            title,
+            launcher,
+            model,
+            profile,
+            sandbox,
+            session,
+            project_config,
+            galley,
+            segments,
+        });
+    }
+
+    pub(super) fn show_status_strip(&mut self, ui: &mut egui::Ui, enter_in_fence: bool) {
+        if self.compact_mode {
+            return;
+        }
+        if self.status_strip_stale() {
+            self.rebuild_status_strip();
+        }
+        let Some(strip) = self.status_strip.as_ref() else {
+            return;
+        };
+        ui.add_space(STATUS_STRIP_SPACING);
+        let (rect, response) = ui.allocate_exact_size(
+            egui::vec2(ui.available_width(), STATUS_STRIP_HEIGHT),
+            egui::Sense::click(),
+        );
+        let mut strip_rect = rect;
+        if enter_in_fence {
+            let hint = ui.painter().layout_no_wrap(
+                FENCE_ENTER_HINT.to_owned(),
+                FontId::proportional(STATUS_STRIP_FONT_SIZE),
+                Color32::from_rgba_unmultiplied(255, 190, 120, 170),
+            );
+            let hint_origin = egui::pos2(
+                rect.right() - hint.size().x,
+                rect.center().y - hint.size().y / 2.0,
+            );
+            strip_rect.max.x = (hint_origin.x - FENCE_ENTER_HINT_GAP).max(rect.left());
+            ui.painter()
+                .with_clip_rect(rect)
+                .galley(hint_origin, hint, Color32::WHITE);
+        }
+        let origin = egui::pos2(rect.left(), rect.center().y - strip.galley.size().y / 2.0);
+        let hovered = response
+            .hover_pos()
+            .filter(|pos| strip_rect.contains(*pos))
+            .and_then(|pos| strip.target_at(pos.x - origin.x));
+        ui.painter().with_clip_rect(strip_rect).galley(
+            origin,
+            Arc::clone(&strip.galley),
+            Color32::WHITE,
+        );
+        let Some(target) = hovered else {
+            return;
+        };
+        if let Some(&(_, start, end)) = strip
+            .segments
+            .iter()
+            .find(|(segment, ..)| *segment == target)
+        {
+            ui.painter().hline(
+                origin.x + start..=origin.x + end,
+                rect.bottom() - 1.0,
+                egui::Stroke::new(1.0, Color32::from_rgba_unmultiplied(214, 224, 238, 60)),
+            );
+        }
+        let response = response
+            .on_hover_cursor(CursorIcon::PointingHand)
+            .on_hover_text(target.hint());
+        if response.clicked() {
+            self.open_status_target(target);
+        }
+    }
+
+    fn open_status_target(&mut self, target: StatusTarget) {
+        logging::trace(format!("status strip jump to {:?}", target));
+        match target {
+            StatusTarget::Title => self.begin_title_rename(),
+            StatusTarget::Backend => {
+                self.push_settings_output(&format!(
+                    "Codex backend: {} \u{2014} change it under Settings \u{203A} Codex Install",
+                    self.launcher_mode.label()
+                ));
+                self.finish_local_change();
+            }
+            StatusTarget::Model => self.select_slash_command("model"),
+            StatusTarget::Profile => self.select_slash_command("profile"),
+            StatusTarget::Session => self.select_slash_command("resume"),
+            StatusTarget::Sandbox => {
+                if self
+                    .status_project_config()
+                    .is_some_and(|config| config.sandbox_mode.is_some())
+                {
+                    self.open_project_file_editor(
+                        Path::new(&self.cwd_text).join(PROJECT_CONFIG_FILE),
+                    );
+                } else {
+                    self.open_codex_file_editor(CodexFile::Config);
+                }
+            }
+            StatusTarget::ProjectConfig => {
+                let path = Path::new(&self.cwd_text).join(PROJECT_CONFIG_FILE);
+                if path.is_file() {
+                    self.open_project_file_editor(path);
+                } else {
+                    self.push_settings_output(&format!(
+                        "No {} in {}",
+                        PROJECT_CONFIG_FILE, self.cwd_text
+                    ));
+                    self.finish_local_change();
+                }
+            }
+        }
+    }
+}
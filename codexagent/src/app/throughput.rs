@@ -0,0 +1,93 @@
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+const RATE_WINDOW: Duration = Duration::from_secs(2);
+const MIN_RATE_SPAN: Duration = Duration::from_millis(100);
+const MAX_RATE_SAMPLES: usize = 64;
+
+#[derive(Default)]
+pub(super) struct StreamThroughput {
+    started_at: Option<Instant>,
+    first_byte_after: Option<Duration>,
+    total_bytes: usize,
+    samples: VecDeque<(Instant, usize)>,
+    peak_rate: f64,
+}
+
+impl StreamThroughput {
+    pub(super) fn start(&mut self, now: Instant) {
+        self.started_at = Some(now);
+        self.first_byte_after = None;
+        self.total_bytes = 0;
+        self.samples.clear();
+        self.peak_rate = 0.0;
+    }
+
+    pub(super) fn record(&mut self, bytes: usize, now: Instant) {
+        if bytes == 0 {
+            return;
+        }
+        if self.first_byte_after.is_none() {
+            self.first_byte_after = self.started_at.map(|started| now - started);
+        }
+        self.total_bytes += bytes;
+        while self.samples.len() >= MAX_RATE_SAMPLES
+            || self
+                .samples
+                .front()
+                .is_some_and(|&(at, _)| now.duration_since(at) > RATE_WINDOW)
+        {
+            self.samples.pop_front();
+        }
+        self.samples.push_back((now, self.total_bytes));
+        if let Some(rate) = self.rate(now) {
+            self.peak_rate = self.peak_rate.max(rate);
+        }
+    }
+
+    pub(super) fn started_at(&self) -> Option<Instant> {
+        self.started_at
+    }
+
+    pub(super) fn first_byte_after(&self) -> Option<Duration> {
+        self.first_byte_after
+    }
+
+    pub(super) fn rate(&self, now: Instant) -> Option<f64> {
+        let &(oldest_at, oldest_total) = self.samples.front()?;
+        let span = now.duration_since(oldest_at);
+        if span < MIN_RATE_SPAN {
+            return None;
+        }
+        let recent = self.total_bytes - oldest_total;
+        Some(recent as f64 / span.as_secs_f64())
+    }
+
+    pub(super) fn rate_label(&self, now: Instant) -> Option<String> {
+        self.started_at?;
+        Some(format_rate(self.rate(now).unwrap_or(0.0)))
+    }
+
+    pub(super) fn summary(&self) -> String {
+        let first_byte = self.first_byte_after.map_or_else(
+            || "no output".to_owned(),
+            |after| format!("first byte after {} ms", after.as_millis()),
+        );
+        format!(
+            "{} bytes streamed, peak {}, {}",
+            self.total_bytes,
+            format_rate(self.peak_rate),
+            first_byte
+        )
+    }
+}
+
+fn format_rate(bytes_per_second: f64) -> String {
+    if bytes_per_second >= 1_000_000.0 {
+        format!("{:.1} MB/s", bytes_per_second / 1_000_000.0)
+    } else if bytes_per_second >= 1_000.0 {
+        format!("{:.1} kB/s", bytes_per_second / 1_000.0)
+    } else {
+        format!("{:.0} B/s", bytes_per_second)
+    }
+}
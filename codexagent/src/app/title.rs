@@ -0,0 +1,438 @@
+use std::sync::atomic::AtomicBool;
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use eframe::egui;
+
+use crate::config::project::ProjectConfig;
+use crate::config::{APP_DISPLAY_NAME, set_codex_titles};
+use crate::events::PromptResult;
+use crate::logging;
+use crate::prompt::{EventLog, PromptChannels, PromptRequest, PromptStreamState};
+
+use super::CodexAgentApp;
+use super::events::spawn_prompt_worker;
+use super::ui_trace::send_viewport_command;
+
+const MAX_TITLE_PROMPT_CHARS: usize = 40;
+const MAX_TITLE_SEED_CHARS: usize = 2000;
+const MAX_CODEX_TITLE_WORDS: usize = 6;
+const BUSY_TITLE_MARKER: &str = "\u{23F3}";
+const AUTO_TITLE_ARGUMENT: &str = "auto";
+const TITLE_MARKUP: [char; 8] = ['#', '>', '-', '*', '+', '`', '_', ' '];
+const TITLE_QUOTES: [char; 9] = [
+    '"', '\'', '`', '*', '.', '\u{201C}', '\u{201D}', '\u{2018}', '\u{2019}',
+];
+const TITLE_SANDBOX_MODE: &str = "read-only";
+const TITLE_APPROVAL_POLICY: &str = "never";
+const CODEX_TITLE_PROMPT: &str = "Write a title of at most four words for a conversation that starts with the request below. Reply with the title only, without quotes or trailing punctuation.\n\n";
+
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+enum TitleSource {
+    #[default]
+    Prompt,
+    Codex,
+    Renamed,
+}
+
+#[derive(Default)]
+pub(super) struct SessionTitle {
+    text: Option<String>,
+    source: TitleSource,
+    seed: Option<String>,
+    request: Option<u64>,
+    requested: bool,
+}
+
+impl SessionTitle {
+    pub(super) fn restored(text: Option<String>, renamed: bool) -> Self {
+        Self {
+            text,
+            source: if renamed {
+                TitleSource::Renamed
+            } else {
+                TitleSource::Prompt
+            },
+            ..Self::default()
+        }
+    }
+
+    pub(super) fn text(&self) -> Option<&str> {
+        self.text.as_deref()
+    }
+
+    pub(super) fn renamed(&self) -> bool {
+        self.source == TitleSource::Renamed
+    }
+}
+
+fn truncate_title(text: &str) -> String {
+    if text.chars().count() <= MAX_TITLE_PROMPT_CHARS {
+        return text.to_owned();
+    }
+    let mut title: String = text.chars().take(MAX_TITLE_PROMPT_CHARS).collect();
+    if let Some(space) = title.rfind(' ')
+        && space >= title.len() / 2
+    {
+        title.truncate(space);
+    }
+    title.truncate(title.trim_end().len());
+    title.push('\u{2026}');
+    title
+}
+
+fn clean_title_line(line: &str) -> String {
+    line.trim()
+        .trim_start_matches(TITLE_MARKUP)
+        .split_whitespace()
+        .map(|word| word.trim_matches('`'))
+        .filter(|word| !word.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn first_sentence(line: &str) -> &str {
+    let end = line
+        .char_indices()
+        .find(|&(index, c)| {
+            matches!(c, '.' | '?' | '!') && line[index + c.len_utf8()..].starts_with(' ')
+        })
+        .map_or(line.len(), |(index, c)| index + c.len_utf8());
+    line[..end].trim_end_matches('.')
+}
+
+fn session_title(prompt: &str) -> Option<String> {
+    let mut fenced = false;
+    let line = prompt
+        .lines()
+        .filter(|line| {
+            let fence = line.trim_start().starts_with("```");
+            fenced ^= fence;
+            !fenced && !fence
+        })
+        .map(clean_title_line)
+        .find(|line| !line.is_empty())?;
+    let sentence = first_sentence(&line);
+    (!sentence.is_empty()).then(|| truncate_title(sentence))
+}
+
+fn codex_title(output: &str) -> Option<String> {
+    let line = output
+        .lines()
+        .filter(|line| !line.starts_with(['\x19', '\x1A', '\x1C', '\x1D', '\x1E']))
+        .map(|line| line.trim_start_matches('\x1F').trim())
+        .find(|line| !line.is_empty())?;
+    let line = match line.get(..6) {
+        Some(prefix) if prefix.eq_ignore_ascii_case("title:") => &line[6..],
+        _ => line,
+    };
+    let title = line
+        .split_whitespace()
+        .take(MAX_CODEX_TITLE_WORDS)
+        .collect::<Vec<_>>()
+        .join(" ");
+    let title = title.trim_matches(TITLE_QUOTES).trim();
+    (!title.is_empty()).then(|| truncate_title(title))
+}
+
+// The title run only reads the seed, so it never gets the project's
+// sandbox, approval policy or completion hook.
+fn title_project(project: Option<ProjectConfig>) -> ProjectConfig {
+    let mut project = project.unwrap_or_default();
+    project.sandbox_mode = Some(TITLE_SANDBOX_MODE.to_owned());
+    project.approval_policy = Some(TITLE_APPROVAL_POLICY.to_owned());
+    project.on_complete = None;
+    project
+}
+
+fn quoted_argument(text: &str) -> String {
+    let quote = if text.contains('"') { '\'' } else { '"' };
+    format!("{}{}{}", quote, text, quote)
+}
+
+impl CodexAgentApp {
+    pub(super) fn reset_session_title(&mut self) {
+        self.cancel_title_request();
+        self.session_title = SessionTitle::default();
+    }
+
+    pub(super) fn cancel_title_request(&mut self) {
+        let Some(prompt_id) = self.session_title.request.take() else {
+            return;
+        };
+        let running = self
+            .running_prompt
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .take(prompt_id);
+        let Some(pid) = running.map(|running| running.pid) else {
+            return;
+        };
+        logging::trace(format!(
+            "stopping codex title request {} pid {}",
+            prompt_id, pid
+        ));
+        let backend = Arc::clone(&self.backend);
+        thread::spawn(move || {
+            if let Err(error) = backend.cancel(pid) {
+                logging::error(format!(
+                    "failed to stop codex title request pid {}: {}",
+                    pid, error
+                ));
+            }
+        });
+    }
+
+    pub(super) fn note_session_title(&mut self, prompt: &str) {
+        if self.session_title.text.is_some() {
+            return;
+        }
+        self.session_title.text = session_title(prompt);
+        self.session_title.source = TitleSource::Prompt;
+        self.session_title.seed = Some(prompt.chars().take(MAX_TITLE_SEED_CHARS).collect());
+    }
+
+    pub(super) fn request_codex_title(&mut self) {
+        if !self.codex_titles
+            || self.session_title.requested
+            || self.session_title.source != TitleSource::Prompt
+        {
+            return;
+        }
+        let Some(seed) = self.session_title.seed.clone() else {
+            return;
+        };
+        self.session_title.requested = true;
+        let prompt_id = self.next_prompt_id;
+        self.next_prompt_id += 1;
+        logging::trace(format!(
+            "asking codex for a session title (prompt {})",
+            prompt_id
+        ));
+        let shared_stream = Arc::new(Mutex::new(PromptStreamState::default()));
+        shared_stream
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .start(prompt_id);
+        let request = PromptRequest {
+            id: prompt_id,
+            prompt: format!("{}{}", CODEX_TITLE_PROMPT, seed),
+            session_id: None,
+            project: Some(title_project(self.refresh_project_config())),
+            approved: None,
+        };
+        let channels = PromptChannels {
+            running_prompt: Arc::clone(&self.running_prompt),
+            shared_stream,
+            stream_notification_pending: Arc::new(AtomicBool::new(false)),
+            event_log: Arc::new(Mutex::new(EventLog::default())),
+            tx: self.tx.clone(),
+            repaint: self.repaint_handle(),
+        };
+        spawn_prompt_worker(Arc::clone(&self.backend), request, channels);
+        self.session_title.request = Some(prompt_id);
+    }
+
+    pub(super) fn owns_title_prompt(&self, prompt_id: u64) -> bool {
+        self.session_title.request == Some(prompt_id)
+    }
+
+    pub(super) fn finish_title_request(&mut self, result: PromptResult) {
+        self.session_title.request = None;
+        let title = match &result {
+            PromptResult::Ok(output) => codex_title(&output.response),
+            PromptResult::Err(kind, error) => {
+                logging::trace(format!(
+                    "codex title request failed ({:?}); keeping the prompt title: {}",
+                    kind,
+                    error.lines().next().unwrap_or_default()
+                ));
+                return;
+            }
+        };
+        let Some(title) = title else {
+            logging::trace("codex returned no usable title; keeping the prompt title");
+            return;
+        };
+        if self.session_title.source != TitleSource::Prompt {
+            return;
+        }
+        logging::trace(format!("session title from codex: {:?}", title));
+        self.session_title.text = Some(title);
+        self.session_title.source = TitleSource::Codex;
+    }
+
+    pub(super) fn run_title_command(&mut self, title: &str) -> Result<String, String> {
+        let title = title.split_whitespace().collect::<Vec<_>>().join(" ");
+        if title.eq_ignore_ascii_case(AUTO_TITLE_ARGUMENT) {
+            if self.session_title.source != TitleSource::Renamed {
+                return Ok("Session title is already automatic".to_owned());
+            }
+            self.session_title.source = TitleSource::Prompt;
+            self.session_title.text = self.session_title.seed.as_deref().and_then(session_title);
+            return Ok(match self.session_title.text.as_deref() {
+                Some(title) => format!("Session title reset to {}", title),
+                None => "Session title cleared \u{2014} the next prompt names it".to_owned(),
+            });
+        }
+        if title.is_empty() {
+            return Err("Usage: /title <name|auto>".to_owned());
+        }
+        let title = truncate_title(&title);
+        logging::trace(format!("session renamed to {:?}", title));
+        self.session_title.text = Some(title.clone());
+        self.session_title.source = TitleSource::Renamed;
+        Ok(format!("Session title set to {}", title))
+    }
+
+    pub(super) fn begin_title_rename(&mut self) {
+        self.clear_picker_selection();
+        self.input = match self.session_title.text.as_deref() {
+            Some(title) => format!("/title {}", quoted_argument(title)),
+            None => "/title \"\"".to_owned(),
+        };
+        self.store_input_cursor(self.input.chars().count() - 1);
+        self.reset_prompt_history_navigation();
+        self.pending_input_focus = true;
+        self.refresh_after_input_change();
+    }
+
+    pub(super) fn select_codex_titles(&mut self, enabled: bool) {
+        self.clear_picker_selection();
+        if self.codex_titles == enabled {
+            return;
+        }
+        match set_codex_titles(enabled) {
+            Ok(enabled) => {
+                self.codex_titles = enabled;
+                self.push_settings_output(if enabled {
+                    "Codex titles set to On \u{2014} codex names each session after its first answer"
+                } else {
+                    "Codex titles set to Off"
+                });
+                self.finish_local_success();
+            }
+            Err(error) => {
+                logging::error(format!(
+                    "failed to set codex titles {}: {}",
+                    if enabled { "on" } else { "off" },
+                    error
+                ));
+                self.push_local_error(&format!("Failed to set codex titles: {}", error));
+            }
+        }
+        self.finish_local_change();
+    }
+
+    fn composed_window_title(&self) -> String {
+        let mut title = APP_DISPLAY_NAME.to_owned();
+        if let Some(session) = self.session_title.text() {
+            title.push_str(" \u{2014} ");
+            title.push_str(session);
+        }
+        if self.busy {
+            title.push(' ');
+            title.push_str(BUSY_TITLE_MARKER);
+        }
+        title
+    }
+
+    pub(super) fn sync_window_title(&mut self) {
+        let title = self.composed_window_title();
+        if title == self.window_title {
+            return;
+        }
+        logging::trace(format!("window title set to {:?}", title));
+        self.window_title = title.clone();
+        send_viewport_command(&self.ctx, egui::ViewportCommand::Title(title));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn title_run_is_read_only_without_approvals() {
+        let project = ProjectConfig {
+            model: Some("o3".to_owned()),
+            sandbox_mode: Some("danger-full-access".to_owned()),
+            approval_policy: Some("on-request".to_owned()),
+            on_complete: Some("make deploy".to_owned()),
+            ..ProjectConfig::default()
+        };
+        let title = title_project(Some(project));
+        assert_eq!(title.model.as_deref(), Some("o3"));
+        assert_eq!(title.sandbox_mode.as_deref(), Some(TITLE_SANDBOX_MODE));
+        assert_eq!(
+            title.approval_policy.as_deref(),
+            Some(TITLE_APPROVAL_POLICY)
+        );
+        assert_eq!(title.on_complete(), None);
+
+        let title = title_project(None);
+        assert_eq!(title.sandbox_mode.as_deref(), Some(TITLE_SANDBOX_MODE));
+        assert_eq!(
+            title.approval_policy.as_deref(),
+            Some(TITLE_APPROVAL_POLICY)
+        );
+    }
+
+    #[test]
+    fn prompt_titles_take_the_first_sentence_outside_code() {
+        let cases = [
+            ("Fix the parser. It fails on tabs.", Some("Fix the parser")),
+            (
+                "## Why does `cargo test` hang?\nmore",
+                Some("Why does cargo test hang?"),
+            ),
+            (
+                "```\nlet x = 1;\n```\n- refactor the loop",
+                Some("refactor the loop"),
+            ),
+            ("   \n```rust\nfn main() {}\n```\n", None),
+            (
+                "Überprüfe die Übersetzung",
+                Some("Überprüfe die Übersetzung"),
+            ),
+            (
+                "version 1.2 breaks the build",
+                Some("version 1.2 breaks the build"),
+            ),
+        ];
+        for (prompt, title) in cases {
+            assert_eq!(session_title(prompt).as_deref(), title, "{:?}", prompt);
+        }
+    }
+
+    #[test]
+    fn long_titles_are_cut_at_a_word() {
+        let title =
+            session_title("Explain how the incremental layout cache decides when to rebuild")
+                .unwrap();
+        assert_eq!(title, "Explain how the incremental layout\u{2026}");
+        let title = truncate_title(&"ä".repeat(50));
+        assert_eq!(title.chars().count(), MAX_TITLE_PROMPT_CHARS + 1);
+        assert!(title.ends_with('\u{2026}'));
+    }
+
+    #[test]
+    fn codex_titles_skip_markers_and_quotes() {
+        let cases = [
+            (
+                "\x1Ethinking...\n\x1F\"Parser Tab Handling\"\n",
+                Some("Parser Tab Handling"),
+            ),
+            ("Title: Flaky CI Fix.", Some("Flaky CI Fix")),
+            (
+                "one two three four five six seven eight",
+                Some("one two three four five six"),
+            ),
+            ("\x1Cran ls\n\n", None),
+            ("\u{201C}\u{201D}", None),
+        ];
+        for (output, title) in cases {
+            assert_eq!(codex_title(output).as_deref(), title, "{:?}", output);
+        }
+    }
+}
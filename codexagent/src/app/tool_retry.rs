@@ -0,0 +1,46 @@
+use crate::logging;
+
+use super::CodexAgentApp;
+
+impl CodexAgentApp {
+    pub(super) fn capture_failed_step(&mut self, prompt_id: u64) {
+        self.tool_retry = self
+            .shared_stream
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .failed_step(prompt_id);
+        if let Some(step) = self.tool_retry.as_ref() {
+            logging::trace(format!(
+                "prompt {} failed after a failing command (exit code {:?}); offering a targeted retry",
+                prompt_id, step.exit_code
+            ));
+        }
+    }
+
+    pub(super) fn tool_retry_offered(&self) -> bool {
+        !self.busy && self.tool_retry.is_some()
+    }
+
+    pub(super) fn prefill_tool_retry(&mut self) {
+        let Some(step) = self.tool_retry.take() else {
+            return;
+        };
+        if self.session_id.is_none()
+            && let Some(session_id) = step.session_id.clone()
+        {
+            self.session_id = Some(session_id);
+            self.cancelled_resume_context = None;
+        }
+        logging::trace("prefilling a retry of the failed command");
+        self.input = step.retry_prompt();
+        self.pending_input_focus = true;
+        self.invalidate_input_layout();
+        self.mark_draft_changed();
+        self.resize_for_text();
+    }
+
+    pub(super) fn dismiss_tool_retry(&mut self) {
+        self.tool_retry = None;
+        self.resize_for_text();
+    }
+}
@@ -0,0 +1,188 @@
+use std::collections::HashSet;
+
+use crate::config::set_topic_suggestions_enabled;
+use crate::logging;
+
+use super::CodexAgentApp;
+
+const TOPIC_TURNS: usize = 3;
+const MIN_TERM_CHARS: usize = 4;
+const MIN_PROMPT_TERMS: usize = 3;
+const STOP_WORDS: [&str; 48] = [
+    "about", "after", "again", "also", "been", "before", "being", "code", "could", "does", "doing",
+    "done", "each", "even", "file", "from", "have", "help", "here", "into", "just", "like", "make",
+    "more", "most", "much", "need", "only", "other", "over", "please", "same", "should", "some",
+    "than", "that", "their", "them", "then", "there", "these", "they", "this", "want", "what",
+    "when", "with", "would",
+];
+
+fn normalize_term(word: &str) -> String {
+    let word = word.to_lowercase();
+    if let Some(stem) = word.strip_suffix("es")
+        && ["ss", "sh", "ch", "x"]
+            .iter()
+            .any(|ending| stem.ends_with(ending))
+        && stem.chars().count() >= MIN_TERM_CHARS
+    {
+        return stem.to_owned();
+    }
+    match word.strip_suffix('s') {
+        Some(stem) if !stem.ends_with('s') && stem.chars().count() >= MIN_TERM_CHARS => {
+            stem.to_owned()
+        }
+        _ => word,
+    }
+}
+
+fn significant_terms(text: &str) -> HashSet<String> {
+    text.split(|c: char| !c.is_alphanumeric() && c != '_')
+        .filter(|word| word.chars().count() >= MIN_TERM_CHARS)
+        .filter(|word| !word.chars().all(|c| c.is_ascii_digit()))
+        .map(normalize_term)
+        .filter(|term| !STOP_WORDS.contains(&term.as_str()))
+        .collect()
+}
+
+fn is_topic_shift(prompt_terms: &HashSet<String>, recent_terms: &HashSet<String>) -> bool {
+    prompt_terms.len() >= MIN_PROMPT_TERMS
+        && !recent_terms.is_empty()
+        && prompt_terms.is_disjoint(recent_terms)
+}
+
+impl CodexAgentApp {
+    pub(super) fn topic_shift_visible(&self) -> bool {
+        self.topic_shift_suggested && !self.busy && !self.input.trim().is_empty()
+    }
+
+    pub(super) fn refresh_topic_shift(&mut self) {
+        if self.input.trim().is_empty() {
+            self.topic_shift_suggested = false;
+            self.topic_shift_dismissed = false;
+            return;
+        }
+        if !self.topic_suggestions_enabled
+            || self.topic_shift_dismissed
+            || self.busy
+            || self.session_id.is_none()
+            || self.input.trim_start().starts_with('/')
+        {
+            self.topic_shift_suggested = false;
+            return;
+        }
+        let len = self.transcript.text().len();
+        if self.topic_terms.as_ref().is_none_or(|(at, _)| *at != len) {
+            let terms = self
+                .transcript
+                .recent_turns_text(TOPIC_TURNS)
+                .map(significant_terms)
+                .unwrap_or_default();
+            self.topic_terms = Some((len, terms));
+        }
+        let Some((_, recent_terms)) = self.topic_terms.as_ref() else {
+            return;
+        };
+        let suggested = is_topic_shift(&significant_terms(&self.input), recent_terms);
+        if suggested && !self.topic_shift_suggested {
+            logging::trace("prompt shares no key terms with recent turns; suggesting a new topic");
+        }
+        self.topic_shift_suggested = suggested;
+    }
+
+    pub(super) fn dismiss_topic_shift(&mut self) {
+        self.topic_shift_suggested = false;
+        self.topic_shift_dismissed = true;
+    }
+
+    pub(super) fn accept_topic_shift(&mut self) {
+        self.topic_shift_suggested = false;
+        self.start_new_topic();
+    }
+
+    pub(super) fn select_topic_suggestions(&mut self, enabled: bool) {
+        self.clear_picker_selection();
+        if self.topic_suggestions_enabled == enabled {
+            return;
+        }
+        match set_topic_suggestions_enabled(enabled) {
+            Ok(enabled) => {
+                self.topic_suggestions_enabled = enabled;
+                self.topic_shift_suggested = false;
+                self.push_settings_output(if enabled {
+                    "Topic suggestions set to On"
+                } else {
+                    "Topic suggestions set to Off"
+                });
+                self.finish_local_success();
+            }
+            Err(error) => {
+                logging::error(format!(
+                    "failed to set topic suggestions {}: {}",
+                    if enabled { "on" } else { "off" },
+                    error
+                ));
+                self.push_local_error(&format!("Failed to set topic suggestions: {}", error));
+            }
+        }
+        self.finish_local_change();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn terms(words: &[&str]) -> HashSet<String> {
+        words.iter().map(|word| (*word).to_owned()).collect()
+    }
+
+    #[test]
+    fn plurals_share_a_term() {
+        for (word, term) in [
+            ("Parsers", "parser"),
+            ("classes", "class"),
+            ("patches", "patch"),
+            ("indexes", "index"),
+            ("address", "address"),
+            ("tests", "test"),
+            ("Cargo", "cargo"),
+        ] {
+            assert_eq!(normalize_term(word), term, "{:?}", word);
+        }
+    }
+
+    #[test]
+    fn short_common_and_numeric_words_are_not_terms() {
+        assert_eq!(
+            significant_terms("Please fix the 2024 parser bug in render_markdown, would you?"),
+            terms(&["parser", "render_markdown"])
+        );
+        assert!(significant_terms("").is_empty());
+        assert!(significant_terms("   \n\t").is_empty());
+    }
+
+    #[test]
+    fn unrelated_prompts_are_a_topic_shift() {
+        let recent = significant_terms("Refactor the markdown renderer to cache layout jobs.");
+        let prompt = significant_terms("Write a bash script that rotates nginx logs nightly.");
+        assert!(is_topic_shift(&prompt, &recent));
+    }
+
+    #[test]
+    fn shared_terms_keep_the_topic() {
+        let recent = significant_terms("Refactor the markdown renderer to cache layout jobs.");
+        let prompt = significant_terms("Also add tests for the layout job cache eviction.");
+        assert!(!is_topic_shift(&prompt, &recent));
+    }
+
+    #[test]
+    fn short_or_first_prompts_never_suggest() {
+        let recent = significant_terms("Refactor the markdown renderer to cache layout jobs.");
+        assert!(!is_topic_shift(&significant_terms(""), &recent));
+        assert!(!is_topic_shift(
+            &significant_terms("thanks, ship it"),
+            &recent
+        ));
+        let prompt = significant_terms("Write a bash script that rotates nginx logs nightly.");
+        assert!(!is_topic_shift(&prompt, &HashSet::new()));
+    }
+}
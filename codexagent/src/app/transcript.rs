@@ -0,0 +1,783 @@
+use crate::config::snapshot::{Rating, TurnRating};
+use crate::config::{CANCELLED_TEXT, PARTIAL_RESPONSE_TEXT};
+use crate::logging;
+
+pub(super) const PINNED_LABEL: &str = "\u{1F4CC} Pinned";
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub(super) enum SegmentKind {
+    Turn,
+    UserPrompt,
+    Response,
+    Continuation,
+    Reasoning,
+    Error,
+    Cancelled,
+    Notice,
+    Pinned,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub(super) enum PinTarget {
+    Turn(usize),
+    Block(usize),
+}
+
+impl SegmentKind {
+    fn of_line(line: &str) -> Option<Self> {
+        match line.chars().next() {
+            Some('\x19') => Some(Self::Turn),
+            Some('\x1A' | '\x1C') => Some(Self::Notice),
+            Some('\x1D') => Some(Self::Error),
+            Some('\x1E') => Some(Self::Reasoning),
+            _ if line.strip_suffix('\n').unwrap_or(line) == CANCELLED_TEXT => Some(Self::Cancelled),
+            _ if line.trim().is_empty() => None,
+            _ => Some(Self::Response),
+        }
+    }
+}
+
+struct Segment {
+    kind: SegmentKind,
+    start: usize,
+    text: String,
+}
+
+#[derive(Default)]
+pub(super) struct Transcript {
+    segments: Vec<Segment>,
+    display: String,
+    prompt_ranges: Vec<(usize, usize)>,
+    response_start: usize,
+    pinned_turns: Vec<usize>,
+    ratings: Vec<TurnRating>,
+}
+
+impl Transcript {
+    pub(super) fn restore(
+        output: &str,
+        prompt_ranges: &[(usize, usize)],
+        pinned_ranges: &[(usize, usize)],
+        pinned_turns: &[usize],
+        ratings: &[TurnRating],
+    ) -> Self {
+        let mut transcript = Self::default();
+        let mut offset = 0;
+        for &(start, end) in pinned_ranges {
+            let Some(block) = output.get(start..end).filter(|_| start == offset) else {
+                break;
+            };
+            transcript.push_pinned_block(block);
+            offset = end;
+        }
+        for &(start, end) in prompt_ranges {
+            let (Some(before), Some(prompt)) = (output.get(offset..start), output.get(start..end))
+            else {
+                continue;
+            };
+            transcript.push_response(before);
+            transcript.push_user_prompt(prompt);
+            offset = end;
+        }
+        transcript.push_response(output.get(offset..).unwrap_or_default());
+        transcript.begin_response();
+        transcript.pinned_turns = pinned_turns
+            .iter()
+            .copied()
+            .filter(|&turn| turn < transcript.prompt_ranges.len())
+            .collect();
+        for rating in ratings {
+            if rating.turn < transcript.prompt_ranges.len() {
+                transcript.set_rating(rating.clone());
+            }
+        }
+        transcript.check_ranges("restore");
+        transcript
+    }
+
+    pub(super) fn text(&self) -> &str {
+        &self.display
+    }
+
+    pub(super) fn len(&self) -> usize {
+        self.display.len()
+    }
+
+    pub(super) fn is_empty(&self) -> bool {
+        self.display.is_empty()
+    }
+
+    pub(super) fn prompt_ranges(&self) -> &[(usize, usize)] {
+        &self.prompt_ranges
+    }
+
+    pub(super) fn response_start(&self) -> usize {
+        self.response_start
+    }
+
+    pub(super) fn response_range(&self) -> (usize, usize) {
+        (self.response_start, self.display.len())
+    }
+
+    pub(super) fn pinned_end(&self) -> usize {
+        self.pinned_blocks()
+            .last()
+            .map_or(0, |segment| segment.start + segment.text.len())
+    }
+
+    pub(super) fn pinned_ranges(&self) -> Vec<(usize, usize)> {
+        self.pinned_blocks()
+            .map(|segment| (segment.start, segment.start + segment.text.len()))
+            .collect()
+    }
+
+    pub(super) fn pinned_turns(&self) -> &[usize] {
+        &self.pinned_turns
+    }
+
+    pub(super) fn is_pinned(&self, target: PinTarget) -> bool {
+        match target {
+            PinTarget::Turn(turn) => self.pinned_turns.contains(&turn),
+            PinTarget::Block(index) => index < self.pinned_blocks().count(),
+        }
+    }
+
+    pub(super) fn has_pins(&self) -> bool {
+        !self.pinned_turns.is_empty() || self.pinned_end() > 0
+    }
+
+    pub(super) fn pinned_texts(&self) -> Vec<String> {
+        let blocks = self
+            .pinned_blocks()
+            .map(|segment| pinned_block_body(&segment.text).to_owned());
+        let turns = self
+            .pinned_turns
+            .iter()
+            .filter_map(|&turn| self.turn_response(turn))
+            .filter(|response| !response.is_empty())
+            .map(str::to_owned);
+        blocks.chain(turns).collect()
+    }
+
+    pub(super) fn toggle_turn_pin(&mut self, turn: usize) -> bool {
+        if let Some(index) = self.pinned_turns.iter().position(|&pinned| pinned == turn) {
+            self.pinned_turns.remove(index);
+            return false;
+        }
+        if turn >= self.prompt_ranges.len() {
+            return false;
+        }
+        self.pinned_turns.push(turn);
+        self.pinned_turns.sort_unstable();
+        true
+    }
+
+    pub(super) fn ratings(&self) -> &[TurnRating] {
+        &self.ratings
+    }
+
+    pub(super) fn rating(&self, turn: usize) -> Option<Rating> {
+        self.ratings
+            .iter()
+            .find(|rating| rating.turn == turn)
+            .map(|rating| rating.rating)
+    }
+
+    pub(super) fn set_rating(&mut self, rating: TurnRating) {
+        match self
+            .ratings
+            .binary_search_by_key(&rating.turn, |existing| existing.turn)
+        {
+            Ok(index) => self.ratings[index] = rating,
+            Err(index) => self.ratings.insert(index, rating),
+        }
+    }
+
+    pub(super) fn clear_rating(&mut self, turn: usize) -> bool {
+        let before = self.ratings.len();
+        self.ratings.retain(|rating| rating.turn != turn);
+        self.ratings.len() != before
+    }
+
+    pub(super) fn unpin_block(&mut self, index: usize) -> Option<(usize, usize)> {
+        if index >= self.pinned_blocks().count() {
+            return None;
+        }
+        let segment = self.segments.remove(index);
+        let start = segment.start;
+        let len = segment.text.len();
+        self.display.replace_range(start..start + len, "");
+        for segment in &mut self.segments[index..] {
+            segment.start -= len;
+        }
+        for range in &mut self.prompt_ranges {
+            range.0 -= len;
+            range.1 -= len;
+        }
+        self.response_start = self.response_start.saturating_sub(len).max(start);
+        self.check_ranges("unpin");
+        Some((start, len))
+    }
+
+    pub(super) fn restore_pinned(&mut self, texts: &[String]) {
+        for text in texts {
+            let mut block = String::with_capacity(PINNED_LABEL.len() + text.len() + 4);
+            block.push('\x1C');
+            block.push_str(PINNED_LABEL);
+            block.push('\n');
+            block.push_str(text);
+            block.push_str("\n\n");
+            self.push_pinned_block(&block);
+        }
+        self.begin_response();
+    }
+
+    fn push_pinned_block(&mut self, block: &str) {
+        self.segments.push(Segment {
+            kind: SegmentKind::Pinned,
+            start: self.display.len(),
+            text: block.to_owned(),
+        });
+        self.display.push_str(block);
+    }
+
+    fn pinned_blocks(&self) -> impl Iterator<Item = &Segment> {
+        self.segments
+            .iter()
+            .take_while(|segment| segment.kind == SegmentKind::Pinned)
+    }
+
+    /// Each turn's prompt and the response under it, markers included.
+    pub(super) fn turns(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.prompt_ranges
+            .iter()
+            .enumerate()
+            .map_while(|(turn, &(start, end))| {
+                Some((self.display.get(start..end)?, self.turn_response(turn)?))
+            })
+    }
+
+    fn turn_response(&self, turn: usize) -> Option<&str> {
+        let &(_, start) = self.prompt_ranges.get(turn)?;
+        let end = self
+            .prompt_ranges
+            .get(turn + 1)
+            .map_or(self.display.len(), |&(next, _)| next);
+        let response = self.display.get(start..end)?;
+        let response = match response.rfind("\n\x19") {
+            Some(turn_line) if turn + 1 < self.prompt_ranges.len() => &response[..turn_line],
+            _ => response,
+        };
+        Some(response.trim_matches('\n'))
+    }
+
+    #[cfg(debug_assertions)]
+    pub(super) fn cache_matches_segments(&self) -> bool {
+        let mut rendered = String::with_capacity(self.display.len());
+        for segment in &self.segments {
+            if segment.start != rendered.len() {
+                return false;
+            }
+            rendered.push_str(&segment.text);
+        }
+        rendered == self.display
+    }
+
+    pub(super) fn clear(&mut self) {
+        self.segments.clear();
+        self.display.clear();
+        self.prompt_ranges.clear();
+        self.response_start = 0;
+        self.pinned_turns.clear();
+        self.ratings.clear();
+    }
+
+    pub(super) fn begin_response(&mut self) {
+        self.response_start = self.display.len();
+    }
+
+    pub(super) fn push(&mut self, kind: SegmentKind, text: &str) {
+        if text.is_empty() {
+            return;
+        }
+        match self.segments.last_mut() {
+            Some(last)
+                if last.kind == kind
+                    && !matches!(kind, SegmentKind::UserPrompt | SegmentKind::Pinned) =>
+            {
+                last.text.push_str(text);
+            }
+            _ => self.segments.push(Segment {
+                kind,
+                start: self.display.len(),
+                text: text.to_owned(),
+            }),
+        }
+        self.display.push_str(text);
+    }
+
+    pub(super) fn push_line(&mut self, kind: SegmentKind, marker: char, line: &str) {
+        let mut text = String::with_capacity(line.len() + 2);
+        text.push(marker);
+        text.push_str(line);
+        text.push('\n');
+        self.push(kind, &text);
+    }
+
+    pub(super) fn push_prompt(&mut self, prompt: &str, notes: &[String]) {
+        self.display.reserve(prompt.len() + 8);
+        self.ensure_spacing();
+        self.push(
+            SegmentKind::Turn,
+            &format!("\x19#{}\n", self.prompt_ranges.len() + 1),
+        );
+        self.push_user_prompt(prompt);
+        self.push(SegmentKind::Notice, "\n\n");
+        for note in notes {
+            self.push_line(SegmentKind::Notice, '\x1C', note);
+        }
+        if !notes.is_empty() {
+            self.push(SegmentKind::Notice, "\n");
+        }
+        self.begin_response();
+    }
+
+    fn push_user_prompt(&mut self, prompt: &str) {
+        let start = self.display.len();
+        self.segments.push(Segment {
+            kind: SegmentKind::UserPrompt,
+            start,
+            text: prompt.to_owned(),
+        });
+        self.display.push_str(prompt);
+        self.prompt_ranges.push((start, self.display.len()));
+    }
+
+    pub(super) fn push_response(&mut self, text: &str) {
+        self.display.reserve(text.len());
+        for line in text.split_inclusive('\n') {
+            let continues = !self.display.is_empty() && !self.display.ends_with('\n');
+            let kind = match self.segments.last() {
+                Some(last) if continues => last.kind,
+                last => SegmentKind::of_line(line).unwrap_or(match last {
+                    Some(last)
+                        if !matches!(
+                            last.kind,
+                            SegmentKind::Turn
+                                | SegmentKind::UserPrompt
+                                | SegmentKind::Continuation
+                                | SegmentKind::Pinned
+                        ) =>
+                    {
+                        last.kind
+                    }
+                    _ => SegmentKind::Response,
+                }),
+            };
+            self.push(kind, line);
+        }
+    }
+
+    pub(super) fn push_continuation(&mut self) {
+        let text = if self.display.is_empty() || self.display.ends_with('\n') {
+            ""
+        } else {
+            "\n"
+        };
+        self.segments.push(Segment {
+            kind: SegmentKind::Continuation,
+            start: self.display.len(),
+            text: text.to_owned(),
+        });
+        self.display.push_str(text);
+        self.begin_response();
+    }
+
+    pub(super) fn push_error(&mut self, message: &str) {
+        self.display
+            .reserve(message.len() + message.lines().count().max(1));
+        for line in message.split_inclusive('\n') {
+            self.push(SegmentKind::Error, "\x1D");
+            self.push(SegmentKind::Error, line);
+        }
+    }
+
+    pub(super) fn push_error_report(
+        &mut self,
+        partial: bool,
+        headline: &str,
+        hint: &str,
+        details: &str,
+    ) {
+        if partial {
+            self.ensure_spacing();
+            self.push(
+                SegmentKind::Notice,
+                &format!("\x1C{}\n", PARTIAL_RESPONSE_TEXT),
+            );
+        }
+        self.push_error(&format!("{}\n", headline));
+        self.push(SegmentKind::Notice, &format!("\x1C{}\n", hint));
+        self.push_error(details);
+    }
+
+    pub(super) fn push_stop_notice(&mut self, summary: &str) {
+        self.ensure_spacing();
+        self.push(SegmentKind::Notice, &format!("\x1C{}\n", summary));
+    }
+
+    pub(super) fn push_cancelled(&mut self) {
+        if self.display == "..." {
+            self.clear();
+        }
+        if !self.display.is_empty() {
+            if !self.display.ends_with('\n') {
+                self.push_spacing("\n");
+            }
+            self.push_spacing("\n");
+        }
+        self.push(SegmentKind::Cancelled, CANCELLED_TEXT);
+    }
+
+    pub(super) fn replace_response(&mut self, text: &str) {
+        self.truncate(self.response_start);
+        self.push_response(text);
+    }
+
+    pub(super) fn truncate_response(&mut self) {
+        self.truncate(self.response_start);
+    }
+
+    pub(super) fn ensure_spacing(&mut self) {
+        if self.display.is_empty() {
+            return;
+        }
+        if !self.display.ends_with('\n') {
+            self.push_spacing("\n\n");
+        } else if !self.display.ends_with("\n\n") {
+            self.push_spacing("\n");
+        }
+    }
+
+    fn push_spacing(&mut self, spacing: &str) {
+        let kind = match self.segments.last() {
+            Some(last) if !matches!(last.kind, SegmentKind::UserPrompt | SegmentKind::Pinned) => {
+                last.kind
+            }
+            _ => SegmentKind::Notice,
+        };
+        self.push(kind, spacing);
+    }
+
+    fn protected_len(&self) -> usize {
+        self.prompt_ranges
+            .last()
+            .map_or(0, |&(_, end)| end)
+            .max(self.pinned_end())
+    }
+
+    fn truncate(&mut self, len: usize) {
+        let protected = self.protected_len();
+        if len < protected {
+            logging::error(format!(
+                "refusing to truncate the transcript to {} bytes, inside the last prompt or pinned block ending at {}",
+                len, protected
+            ));
+        }
+        let len = len.max(protected);
+        if len >= self.display.len() {
+            return;
+        }
+        while let Some(last) = self.segments.last_mut() {
+            if last.start < len || last.start == len && last.text.is_empty() {
+                last.text.truncate(len - last.start);
+                break;
+            }
+            self.segments.pop();
+        }
+        self.display.truncate(len);
+        self.prompt_ranges.retain(|&(_, end)| end <= len);
+        let turns = self.prompt_ranges.len();
+        self.pinned_turns.retain(|&turn| turn < turns);
+        self.response_start = self.response_start.min(len);
+        self.check_ranges("truncate");
+    }
+
+    fn check_ranges(&mut self, mutation: &str) {
+        let display = &self.display;
+        let count = self.prompt_ranges.len();
+        let mut previous_end = 0;
+        self.prompt_ranges.retain(|&(start, end)| {
+            let valid = previous_end <= start
+                && start <= end
+                && end <= display.len()
+                && display.is_char_boundary(start)
+                && display.is_char_boundary(end);
+            if valid {
+                previous_end = end;
+            }
+            valid
+        });
+        let dropped = count - self.prompt_ranges.len();
+        let mut response_start = self.response_start.clamp(previous_end, display.len());
+        while !display.is_char_boundary(response_start) {
+            response_start -= 1;
+        }
+        if dropped == 0 && response_start == self.response_start {
+            return;
+        }
+        debug_assert!(
+            false,
+            "transcript ranges invalid after {}: dropped {} prompt ranges, response start {} -> {}",
+            mutation, dropped, self.response_start, response_start
+        );
+        logging::error(format!(
+            "transcript ranges invalid after {}: dropped {} of {} prompt ranges, response start {} -> {} ({} bytes)",
+            mutation,
+            dropped,
+            count,
+            self.response_start,
+            response_start,
+            display.len()
+        ));
+        let turns = self.prompt_ranges.len();
+        self.pinned_turns.retain(|&turn| turn < turns);
+        self.response_start = response_start;
+    }
+
+    pub(super) fn recent_turns_text(&self, turns: usize) -> Option<&str> {
+        let session_start = self.display.rfind('\x1A').unwrap_or(0);
+        let &(start, _) = self
+            .prompt_ranges
+            .iter()
+            .rev()
+            .take_while(|&&(start, _)| start >= session_start)
+            .take(turns)
+            .last()?;
+        self.display.get(start..)
+    }
+
+    pub(super) fn resume_context(&self) -> String {
+        let output = self.display.as_str();
+        let mut transcript = String::new();
+        let session_start = output.rfind('\x1A').unwrap_or(0);
+        for (index, &(start, end)) in self.prompt_ranges.iter().enumerate() {
+            if start < session_start {
+                continue;
+            }
+            let Some(prompt) = output.get(start..end) else {
+                continue;
+            };
+            let prompt = prompt.trim();
+            if prompt.is_empty() {
+                continue;
+            }
+            if !transcript.is_empty() {
+                transcript.push_str("\n\n");
+            }
+            transcript.push_str("User:\n");
+            transcript.push_str(prompt);
+            let response_end = self
+                .prompt_ranges
+                .get(index + 1)
+                .map(|(next_start, _)| *next_start)
+                .unwrap_or(output.len());
+            let Some(response) = output.get(end..response_end) else {
+                continue;
+            };
+            append_resume_response(&mut transcript, response.trim_matches('\n'));
+        }
+        transcript
+    }
+}
+
+fn append_resume_response(transcript: &mut String, response: &str) {
+    if response.is_empty() {
+        return;
+    }
+    let mut current_label = "";
+    for line in response.lines() {
+        let (label, content) = match line.chars().next() {
+            Some('\x19') => continue,
+            Some('\x1D') => ("System", &line[1..]),
+            Some('\x1E') => ("Assistant reasoning", &line[1..]),
+            Some('\x1F') => ("Assistant note", &line[1..]),
+            _ if line == CANCELLED_TEXT => ("System", line),
+            _ => ("Assistant", line),
+        };
+        if content.is_empty() && current_label.is_empty() {
+            continue;
+        }
+        if label != current_label {
+            transcript.push_str("\n\n");
+            transcript.push_str(label);
+            transcript.push_str(":\n");
+            current_label = label;
+        } else if !transcript.ends_with('\n') {
+            transcript.push('\n');
+        }
+        transcript.push_str(content);
+    }
+}
+
+fn pinned_block_body(block: &str) -> &str {
+    let body = block.split_once('\n').map_or("", |(_, body)| body);
+    body.trim_end_matches('\n')
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::prompt::normalize_line_endings;
+
+    use super::*;
+
+    fn two_turns() -> Transcript {
+        let mut transcript = Transcript::default();
+        transcript.push_prompt("first", &[]);
+        transcript.push_response("Answer line\n\x1Ereasoning...\n");
+        transcript.push_prompt("second", &["Inlined @a.rs".to_owned()]);
+        transcript
+    }
+
+    #[test]
+    fn prompts_and_responses_render_to_the_golden_text() {
+        let transcript = two_turns();
+        assert_eq!(
+            transcript.text(),
+            "\x19#1\nfirst\n\nAnswer line\n\x1Ereasoning...\n\n\x19#2\nsecond\n\n\x1CInlined @a.rs\n\n"
+        );
+        assert_eq!(transcript.prompt_ranges(), [(4, 9), (42, 48)]);
+        assert_eq!(transcript.response_start(), transcript.len());
+        assert!(transcript.cache_matches_segments());
+    }
+
+    #[test]
+    fn streaming_rewrites_only_the_current_response() {
+        let mut transcript = two_turns();
+        let before = transcript.text().to_owned();
+        transcript.replace_response("\x1Ethinking...\n");
+        transcript.push_response("Partial");
+        transcript.replace_response("\x1Ethinking...\n\x1FFinal answer");
+        assert_eq!(
+            transcript.text(),
+            format!("{}\x1Ethinking...\n\x1FFinal answer", before)
+        );
+        transcript.truncate_response();
+        assert_eq!(transcript.text(), before);
+        assert_eq!(transcript.prompt_ranges().len(), 2);
+        assert!(transcript.cache_matches_segments());
+    }
+
+    #[test]
+    fn errors_and_cancellation_have_their_own_lines() {
+        let mut transcript = Transcript::default();
+        transcript.push_prompt("task", &[]);
+        transcript.push_response("Working");
+        transcript.push_cancelled();
+        transcript.push_error("first\nsecond");
+        assert_eq!(
+            transcript.text(),
+            "\x19#1\ntask\n\nWorking\n\ncancelled\x1Dfirst\n\x1Dsecond"
+        );
+
+        let mut waiting = Transcript::default();
+        waiting.push_response("...");
+        waiting.push_cancelled();
+        assert_eq!(waiting.text(), CANCELLED_TEXT);
+    }
+
+    #[test]
+    fn continuations_extend_the_answer_without_a_prompt() {
+        let mut transcript = Transcript::default();
+        transcript.push_prompt("write it", &[]);
+        transcript.push_response("fn main() {");
+        transcript.push_continuation();
+        transcript.push_response("}\n");
+        assert_eq!(transcript.text(), "\x19#1\nwrite it\n\nfn main() {\n}\n");
+        assert_eq!(transcript.prompt_ranges().len(), 1);
+        assert_eq!(transcript.response_start(), transcript.len() - 2);
+    }
+
+    #[test]
+    fn truncation_never_cuts_into_a_prompt() {
+        let mut transcript = two_turns();
+        let (_, end) = transcript.prompt_ranges()[1];
+        transcript.truncate(end - 2);
+        assert_eq!(transcript.len(), end);
+        assert_eq!(transcript.prompt_ranges().len(), 2);
+    }
+
+    #[test]
+    fn restoring_a_saved_transcript_keeps_its_ranges() {
+        let mut saved = two_turns();
+        saved.push_response("Second answer\n");
+        let restored = Transcript::restore(saved.text(), saved.prompt_ranges(), &[], &[], &[]);
+        assert_eq!(restored.text(), saved.text());
+        assert_eq!(restored.prompt_ranges(), saved.prompt_ranges());
+        assert_eq!(restored.response_start(), restored.len());
+        assert!(restored.cache_matches_segments());
+    }
+
+    #[test]
+    fn normalized_responses_keep_prompt_ranges_in_place() {
+        let mut transcript = Transcript::default();
+        transcript.push_prompt("first", &[]);
+        transcript.push_response(&normalize_line_endings("Line one\r\nLine two\r\n"));
+        transcript.push_prompt("second", &[]);
+        let text = transcript.text();
+        assert!(!text.contains('\r'));
+        let prompts: Vec<_> = transcript
+            .prompt_ranges()
+            .iter()
+            .map(|&(start, end)| &text[start..end])
+            .collect();
+        assert_eq!(prompts, ["first", "second"]);
+        assert!(transcript.cache_matches_segments());
+    }
+
+    #[test]
+    fn restore_skips_ranges_that_do_not_fit() {
+        let saved = "\x19#1\nhéllo\n\nanswer";
+        let transcript = Transcript::restore(
+            saved,
+            &[(4, 10), (6, 8), (5, 7), (20, 30)],
+            &[],
+            &[0, 3],
+            &[],
+        );
+        assert_eq!(transcript.text(), saved);
+        assert_eq!(transcript.prompt_ranges(), [(4, 10)]);
+        assert_eq!(transcript.pinned_turns(), [0]);
+        assert_eq!(transcript.response_start(), transcript.len());
+        assert!(transcript.cache_matches_segments());
+    }
+
+    #[test]
+    fn ratings_stay_sorted_and_restore_only_for_existing_turns() {
+        let rating = |turn, rating| TurnRating {
+            turn,
+            rating,
+            model: "gpt-5".to_owned(),
+            profile: None,
+        };
+        let mut transcript = two_turns();
+        transcript.set_rating(rating(1, Rating::Bad));
+        transcript.set_rating(rating(0, Rating::Good));
+        transcript.set_rating(rating(1, Rating::Good));
+        let turns: Vec<_> = transcript.ratings().iter().map(|r| r.turn).collect();
+        assert_eq!(turns, [0, 1]);
+        assert_eq!(transcript.rating(1), Some(Rating::Good));
+        assert!(transcript.clear_rating(0));
+        assert!(!transcript.clear_rating(0));
+
+        let restored = Transcript::restore(
+            transcript.text(),
+            transcript.prompt_ranges(),
+            &[],
+            &[],
+            &[rating(1, Rating::Bad), rating(5, Rating::Good)],
+        );
+        assert_eq!(restored.rating(1), Some(Rating::Bad));
+        assert_eq!(restored.ratings().len(), 1);
+    }
+}
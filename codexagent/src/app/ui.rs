@@ -1,22 +1,59 @@
 use std::sync::Arc;
+use std::time::{Instant, SystemTime};
 
 use eframe::egui::{
     self, Color32, CursorIcon, FontId, Key, KeyboardShortcut, Modifiers, RichText, TextEdit,
+    WidgetInfo, WidgetType,
 };
 
+use super::shortcut_map::ShortcutAction;
+use crate::config::paths;
+use crate::config::snapshot::Rating;
 use crate::config::{
-    CANCEL_BUTTON_HEIGHT, CANCEL_BUTTON_WIDTH, LINE_HEIGHT, PROMPT_SCROLL_ID, TEXT_FONT_SIZE,
-    WINDOW_BOTTOM_PADDING, WINDOW_PADDING,
+    CANCEL_BUTTON_HEIGHT, CANCEL_BUTTON_WIDTH, CODEX_FILE_EDITOR_ROWS, CODEX_FILE_UPDATE_DIFF_ROWS,
+    InputAssist, LINE_HEIGHT, LauncherMode, PROMPT_SCROLL_ID, PendingStyle, PromptWhitespace,
+    ResponseDimming, SubmitKey, TEXT_FONT_SIZE, Theme, WakeMode,
 };
+use crate::i18n::{CATALOGS, Text, pinned_locale, tr};
+use crate::logging;
 use crate::notify;
+use crate::postprocess::OUTPUT_PROCESSORS;
+use crate::runtime::{CodexFile, DiffLine, FramePath};
+use crate::transform::TRANSFORMERS;
+use crate::usage::format_tokens;
 
+use super::approval::approval_detail_rows;
+use super::cancel::CancelScope;
+use super::changed_files::{ChangedFileAction, changed_files_label};
+use super::find::FIND_ID;
+use super::inspector::inspector_layout_job;
+use super::language::response_language_directive;
+use super::layout::{
+    CODEX_FILE_UPDATE_SPACING, PROMPT_PREFIX_CHIP_HEIGHT, PROMPT_PREFIX_CHIP_SPACING,
+    TOKEN_ESTIMATE_THRESHOLD,
+};
+use super::locked_input::LOCKED_INPUT_OPACITY;
+use super::notes::{NOTES_ID, NOTES_ROWS};
+use super::onboarding::ONBOARDING_ROW_SPACING;
 use super::position::startup_outer_position;
-use super::render::{OutputLineKind, markdown_layout_job};
-use super::{CodexAgentApp, NOTIFICATION_OPTIONS, SLASH_COMMANDS, SetupState, WindowRestoreState};
+use super::prompt_suffix::CONCISE_DIRECTIVE;
+use super::ratings::{RATINGS, rating_glyph};
+use super::render::{
+    CARD_BACKGROUND, HIGH_CONTRAST_CARD_BACKGROUND, OutputLineKind, TURN_MARKER_FONT_SIZE,
+    markdown_layout_job, pending_status_format,
+};
+use super::sessions::{RecentSessionAction, recent_session_row};
+use super::transcript::PinTarget;
+use super::ui_trace::{send_viewport_command, trace_focus};
+use super::{
+    CodexAgentApp, NOTIFICATION_OPTIONS, PROMPT_ATTACH_OPTIONS, SLASH_COMMANDS,
+    SecretSubmitDecision, SetupState, TOKEN_LIMIT_OPTIONS, WindowRestoreState,
+};
 
 const TITLEBAR_BUTTON_SIZE: f32 = 24.0;
 const TITLEBAR_BUTTON_SPACING: f32 = 2.0;
 const CANCEL_BUSY_BUTTON_WIDTH: f32 = CANCEL_BUTTON_WIDTH * 0.8;
+const CANCEL_MENU_BUTTON_WIDTH: f32 = 22.0;
 const SETTINGS_MENU_WIDTH: f32 = 360.0 * 0.4 * 1.2;
 const SETTINGS_SUBMENU_WIDTH: f32 = SETTINGS_MENU_WIDTH * 1.2 * 1.3 * 1.3;
 const SETTINGS_SUBMENU_PICKER_WIDTH: f32 = SETTINGS_SUBMENU_WIDTH;
@@ -27,25 +64,212 @@ const SETTINGS_ROW_PADDING_X: f32 = 8.0;
 const SETTINGS_ROW_PADDING_Y: f32 = 5.0;
 const SETTINGS_ACTIVE_BADGE_WIDTH: f32 = 44.0;
 const SETTINGS_ACTIVE_BADGE_GAP: f32 = 10.0;
+const CODEX_FILE_EDITOR_ID: &str = "codex-file-editor";
+const EVENT_INSPECTOR_ID: &str = "event-inspector";
+const AGENTS_INDICATOR_WIDTH: f32 = 16.0;
+const PROMPT_PHASE_LABEL_WIDTH: f32 = 72.0;
+const STREAM_RATE_LABEL_WIDTH: f32 = 64.0;
+const AGENTS_INDICATOR_RADIUS: f32 = 3.5;
+const PROMPT_COPY_BUTTON_SIZE: egui::Vec2 = egui::vec2(40.0, 16.0);
+const RATING_BUTTON_SIZE: egui::Vec2 = egui::vec2(20.0, 16.0);
+const FIND_FIELD_WIDTH: f32 = 180.0;
+const PROMPT_COPIED_FLASH: std::time::Duration = std::time::Duration::from_millis(1200);
+#[derive(Clone, Copy)]
+enum TokenSubmitAction {
+    Send,
+    Edit,
+}
+
+#[derive(Clone, Copy)]
+enum ClearConfirmAction {
+    Discard,
+    Keep,
+}
+
+#[derive(Clone, Copy)]
+enum DirtySubmitAction {
+    Continue,
+    ContinueAlways,
+    Abort,
+}
+
+#[derive(Clone, Copy)]
+enum ProjectTrustAction {
+    Trust,
+    Ignore,
+    Abort,
+}
+
+#[derive(Clone, Copy)]
+enum SecretSubmitAction {
+    Send,
+    SendAlways,
+    Redact,
+    Cancel,
+}
+
+enum OnboardingAction {
+    Install,
+    Login,
+    PickFolder,
+    Recheck,
+    Finish,
+}
+
+enum CodexFileUpdateAction {
+    Review,
+    Apply,
+    Ignore,
+    IgnorePermanently,
+}
+
 struct GlowPalette {
     stroke: Color32,
     shadow: Color32,
     separator: Color32,
 }
 
+#[derive(Clone, Copy)]
+enum SessionMenuAction {
+    CopyId,
+    StartNew,
+}
+
+enum LogHealthAction {
+    Retry,
+    OpenFolder,
+}
+
+fn short_session_id(session_id: &str) -> &str {
+    session_id
+        .char_indices()
+        .nth(8)
+        .map_or(session_id, |(index, _)| &session_id[..index])
+}
+
+#[derive(Clone, Copy)]
+enum OutputMenuAction {
+    CopySelection,
+    CopyLastResponse,
+    CopyMarkdown,
+    CopyPlainText,
+    SelectAll,
+    SearchSelection,
+    InspectEvents,
+    ClearSession,
+}
+
+fn show_output_menu(
+    ui: &mut egui::Ui,
+    has_selection: bool,
+    has_response: bool,
+    has_events: bool,
+    busy: bool,
+) -> Option<OutputMenuAction> {
+    let items = [
+        (
+            OutputMenuAction::CopySelection,
+            "Copy selection",
+            has_selection,
+        ),
+        (
+            OutputMenuAction::CopyLastResponse,
+            "Copy last response",
+            has_response,
+        ),
+        (OutputMenuAction::CopyMarkdown, "Copy as markdown", true),
+        (
+            OutputMenuAction::CopyPlainText,
+            "Copy as plain text",
+            has_selection || has_response,
+        ),
+        (OutputMenuAction::SelectAll, "Select all", true),
+        (
+            OutputMenuAction::SearchSelection,
+            "Search for selection",
+            has_selection,
+        ),
+        (
+            OutputMenuAction::InspectEvents,
+            "Inspect events",
+            has_events,
+        ),
+        (OutputMenuAction::ClearSession, "Clear session", !busy),
+    ];
+    let mut picked = None;
+    for (action, label, enabled) in items {
+        if matches!(action, OutputMenuAction::ClearSession) {
+            ui.separator();
+        }
+        if ui.add_enabled(enabled, egui::Button::new(label)).clicked() {
+            picked = Some(action);
+        }
+    }
+    if picked.is_some() {
+        ui.close_menu();
+    }
+    picked
+}
+
+fn enter_submits(
+    events: &[egui::Event],
+    composing: bool,
+    submit_key: SubmitKey,
+    in_fence: bool,
+) -> (bool, bool) {
+    let mut composing = composing;
+    let mut committed = false;
+    let mut submit = false;
+    for event in events {
+        match event {
+            egui::Event::Ime(egui::ImeEvent::Preedit(text)) => composing = !text.is_empty(),
+            egui::Event::Ime(egui::ImeEvent::Commit(_)) => {
+                composing = false;
+                committed = true;
+            }
+            egui::Event::Ime(egui::ImeEvent::Disabled) => composing = false,
+            egui::Event::Key {
+                key: Key::Enter,
+                pressed: true,
+                modifiers,
+                ..
+            } if !composing && !committed => {
+                submit |= match submit_key {
+                    SubmitKey::Enter if in_fence => modifiers.command,
+                    SubmitKey::Enter => !modifiers.shift,
+                    SubmitKey::CtrlEnter => modifiers.command,
+                };
+            }
+            _ => {}
+        }
+    }
+    (submit, composing)
+}
+
 fn cached_markdown_layouter<'a>(
     galley: Option<Arc<egui::Galley>>,
     galley_width: Option<f32>,
     prompt_ranges: &'a [(usize, usize)],
     response_start: usize,
     line_kinds: &'a [(usize, OutputLineKind)],
+    high_contrast: bool,
+    dimming: ResponseDimming,
 ) -> impl FnMut(&egui::Ui, &str, f32) -> Arc<egui::Galley> + 'a {
     let mut galley = galley;
     let mut galley_width = galley_width;
     move |ui: &egui::Ui, text: &str, wrap_width: f32| {
-        if galley.is_none() || !CodexAgentApp::same_width(galley_width, wrap_width) {
-            let job =
-                markdown_layout_job(text, wrap_width, prompt_ranges, response_start, line_kinds);
+        let reusable = galley.as_ref().is_some_and(|galley| galley.text() == text)
+            && CodexAgentApp::same_width(galley_width, wrap_width);
+        if !reusable {
+            let job = markdown_layout_job(
+                text,
+                wrap_width,
+                prompt_ranges,
+                response_start,
+                line_kinds,
+                high_contrast,
+                dimming,
+            );
             galley = Some(ui.fonts(|fonts| fonts.layout_job(job)));
             galley_width = Some(wrap_width);
         }
@@ -151,6 +375,81 @@ fn show_picker_row(
     response.on_hover_cursor(CursorIcon::PointingHand)
 }
 
+fn show_suggestion_chip(ui: &mut egui::Ui, label: &str, hover: &str) -> bool {
+    egui::Frame::new()
+        .fill(Color32::from_rgba_unmultiplied(124, 189, 255, 24))
+        .stroke(egui::Stroke::new(
+            1.0,
+            Color32::from_rgba_unmultiplied(124, 189, 255, 60),
+        ))
+        .corner_radius(egui::CornerRadius::same(255))
+        .inner_margin(egui::Margin::symmetric(8, 0))
+        .show(ui, |ui| {
+            ui.set_height(PROMPT_PREFIX_CHIP_HEIGHT);
+            ui.add(
+                egui::Label::new(
+                    RichText::new(label)
+                        .small()
+                        .color(Color32::from_rgba_unmultiplied(214, 224, 238, 190)),
+                )
+                .truncate()
+                .selectable(false)
+                .sense(egui::Sense::click()),
+            )
+            .on_hover_text(hover)
+            .on_hover_cursor(egui::CursorIcon::PointingHand)
+            .clicked()
+        })
+        .inner
+}
+
+fn show_diff_lines(ui: &mut egui::Ui, id_salt: impl std::hash::Hash, lines: &[DiffLine]) {
+    egui::ScrollArea::vertical()
+        .id_salt(id_salt)
+        .max_height(CODEX_FILE_UPDATE_DIFF_ROWS as f32 * LINE_HEIGHT)
+        .show(ui, |ui| {
+            ui.spacing_mut().item_spacing.y = 0.0;
+            for line in lines {
+                let (prefix, text, color) = match line {
+                    DiffLine::Same(text) => (
+                        ' ',
+                        text,
+                        Color32::from_rgba_unmultiplied(188, 194, 202, 150),
+                    ),
+                    DiffLine::Removed(text) => ('-', text, Color32::from_rgb(255, 120, 120)),
+                    DiffLine::Added(text) => ('+', text, Color32::from_rgb(120, 220, 140)),
+                };
+                ui.add_sized(
+                    egui::vec2(ui.available_width(), LINE_HEIGHT),
+                    egui::Label::new(
+                        RichText::new(format!("{} {}", prefix, text))
+                            .monospace()
+                            .color(color),
+                    )
+                    .truncate(),
+                );
+            }
+        });
+}
+
+fn show_pill_button(ui: &mut egui::Ui, label: &str) -> bool {
+    let resp = ui.add(
+        egui::Button::new(RichText::new(label).color(Color32::WHITE))
+            .min_size(egui::vec2(CANCEL_BUTTON_WIDTH, CANCEL_BUTTON_HEIGHT))
+            .fill(Color32::TRANSPARENT)
+            .stroke(egui::Stroke::NONE)
+            .corner_radius(egui::CornerRadius::same(255)),
+    );
+    if resp.hovered() {
+        ui.painter().rect_filled(
+            resp.rect.expand2(egui::vec2(1.4336, 2.304)),
+            egui::CornerRadius::same(255),
+            Color32::from_rgba_unmultiplied(255, 255, 255, 15),
+        );
+    }
+    resp.on_hover_cursor(CursorIcon::PointingHand).clicked()
+}
+
 fn show_picker(ui: &mut egui::Ui, add_contents: impl FnOnce(&mut egui::Ui)) {
     egui::Frame::new()
         .fill(Color32::from_rgba_unmultiplied(20, 26, 34, 214))
@@ -217,114 +516,2709 @@ impl CodexAgentApp {
         }
     }
 
-    fn glow_palette(&self) -> GlowPalette {
-        if self.busy {
-            return GlowPalette {
-                stroke: Color32::from_rgba_unmultiplied(158, 164, 173, 84),
-                shadow: Color32::from_rgba_unmultiplied(122, 128, 138, 64),
-                separator: Color32::from_rgba_unmultiplied(158, 164, 173, 36),
-            };
+    fn show_notes_button(&mut self, ui: &mut egui::Ui) {
+        let alpha = if self.notes.open { 230 } else { 170 };
+        let response = ui.add(
+            egui::Button::new(
+                RichText::new("Notes").color(Color32::from_rgba_unmultiplied(214, 224, 238, alpha)),
+            )
+            .fill(Color32::TRANSPARENT)
+            .stroke(egui::Stroke::NONE)
+            .corner_radius(egui::CornerRadius::same(255)),
+        );
+        if response.hovered() {
+            ui.painter().rect_filled(
+                response.rect.expand2(egui::vec2(1.4336, 2.304)),
+                egui::CornerRadius::same(255),
+                Color32::from_rgba_unmultiplied(255, 255, 255, 15),
+            );
+        }
+        let hover = match self.shortcuts.get(ShortcutAction::ToggleNotes) {
+            Some(shortcut) => format!("Session notes, never sent to codex ({})", shortcut),
+            None => "Session notes, never sent to codex".to_owned(),
+        };
+        if response
+            .on_hover_cursor(CursorIcon::PointingHand)
+            .on_hover_text(hover)
+            .clicked()
+        {
+            self.toggle_notes();
         }
+    }
 
-        GlowPalette {
-            stroke: Color32::from_rgba_unmultiplied(124, 189, 255, 92),
-            shadow: Color32::from_rgba_unmultiplied(96, 176, 255, 88),
-            separator: Color32::from_rgba_unmultiplied(124, 189, 255, 40),
+    fn show_find_bar(&mut self, ui: &mut egui::Ui) {
+        if !self.find.open {
+            return;
+        }
+        let muted = Color32::from_rgba_unmultiplied(214, 224, 238, 150);
+        let status = self.find_status_label();
+        let mut step = None;
+        let mut close = false;
+        ui.horizontal(|ui| {
+            ui.set_min_height(PROMPT_PREFIX_CHIP_HEIGHT);
+            ui.add(egui::Label::new(RichText::new("Find").small().color(muted)).selectable(false));
+            let edit = ui.add(
+                TextEdit::singleline(&mut self.find.query)
+                    .id_source(FIND_ID)
+                    .desired_width(FIND_FIELD_WIDTH)
+                    .hint_text("Search the transcript"),
+            );
+            edit.widget_info(|| WidgetInfo::labeled(WidgetType::TextEdit, true, "Find"));
+            if edit.changed() {
+                step = Some(None);
+            }
+            if edit.lost_focus() && ui.input(|input| input.key_pressed(Key::Enter)) {
+                step = Some(Some(!ui.input(|input| input.modifiers.shift)));
+                edit.request_focus();
+            }
+            let has_query = !self.find.query.is_empty();
+            if ui
+                .add_enabled(has_query, egui::Button::new("\u{25B2}").small())
+                .on_hover_text("Previous match (Shift+Enter)")
+                .clicked()
+            {
+                step = Some(Some(false));
+            }
+            if ui
+                .add_enabled(has_query, egui::Button::new("\u{25BC}").small())
+                .on_hover_text("Next match (Enter)")
+                .clicked()
+            {
+                step = Some(Some(true));
+            }
+            ui.add(egui::Label::new(RichText::new(&status).small().color(muted)).selectable(false));
+            if ui
+                .add(egui::Button::new("\u{2715}").small())
+                .on_hover_text("Close")
+                .clicked()
+            {
+                close = true;
+            }
+        });
+        ui.add_space(PROMPT_PREFIX_CHIP_SPACING);
+        match step {
+            Some(None) => self.find_query_changed(),
+            Some(Some(forward)) => self.find_next(forward),
+            None => {}
+        }
+        if close {
+            self.close_find();
         }
     }
 
-    fn handle_picker_keys(&mut self, ctx: &egui::Context) -> bool {
-        if self.picker_item_count() == 0 {
-            return false;
+    fn show_notes(&mut self, ui: &mut egui::Ui, separator: Color32) {
+        if !self.notes.open {
+            return;
         }
-        if self.picker_selection().is_some()
-            && ctx.input_mut(|input| input.consume_key(Modifiers::NONE, Key::Enter))
-        {
-            return self.activate_picker_selection();
+        ui.horizontal(|ui| {
+            ui.set_min_height(PROMPT_PREFIX_CHIP_HEIGHT);
+            ui.add(
+                egui::Label::new(
+                    RichText::new("Notes")
+                        .small()
+                        .color(Color32::from_rgba_unmultiplied(214, 224, 238, 150)),
+                )
+                .selectable(false),
+            );
+        });
+        let high_contrast = self.high_contrast();
+        let dimming = self.response_dimming;
+        let notes = &mut self.notes.text;
+        let edit = ui
+            .scope(|ui| {
+                ui.visuals_mut().override_text_color = Some(Color32::WHITE);
+                egui::ScrollArea::vertical()
+                    .id_salt("notes-scroll")
+                    .max_height(NOTES_ROWS as f32 * LINE_HEIGHT)
+                    .show(ui, |ui| {
+                        ui.style_mut().override_font_id =
+                            Some(FontId::proportional(TEXT_FONT_SIZE));
+                        let mut layouter = cached_markdown_layouter(
+                            None,
+                            None,
+                            &[],
+                            0,
+                            &[],
+                            high_contrast,
+                            dimming,
+                        );
+                        TextEdit::multiline(notes)
+                            .id_source(NOTES_ID)
+                            .desired_width(f32::INFINITY)
+                            .desired_rows(NOTES_ROWS)
+                            .hint_text("Jot down what to ask next \u{2014} never sent to codex")
+                            .layouter(&mut layouter)
+                            .frame(false)
+                            .show(ui)
+                    })
+                    .inner
+            })
+            .inner;
+        edit.response
+            .widget_info(|| WidgetInfo::labeled(WidgetType::TextEdit, true, "Session notes"));
+        if edit.response.changed() {
+            self.notes.note_edit();
         }
-        let mut moved = false;
-        if ctx.input_mut(|input| input.consume_key(Modifiers::NONE, Key::ArrowDown)) {
-            moved |= self.move_picker_selection(1);
+        ui.add_space(4.0);
+        let (sep_rect, _) =
+            ui.allocate_exact_size(egui::vec2(ui.available_width(), 1.0), egui::Sense::hover());
+        ui.painter().rect_filled(sep_rect, 0.0, separator);
+        ui.add_space(4.0);
+    }
+
+    fn show_codex_file_editor(&mut self, ui: &mut egui::Ui, separator: Color32) {
+        let mut save = self.shortcut_triggered(ShortcutAction::SaveFile);
+        let Some(editor) = self.codex_file_editor.as_mut() else {
+            return;
+        };
+        let mut close = false;
+        ui.horizontal(|ui| {
+            ui.set_min_height(CANCEL_BUTTON_HEIGHT);
+            ui.add(
+                egui::Label::new(
+                    RichText::new(editor.path.display().to_string())
+                        .monospace()
+                        .color(Color32::from_rgba_unmultiplied(214, 224, 238, 150)),
+                )
+                .truncate()
+                .selectable(false),
+            );
+        });
+        ui.add_space(4.0);
+        egui::ScrollArea::vertical()
+            .id_salt("codex-file-editor-scroll")
+            .max_height(CODEX_FILE_EDITOR_ROWS as f32 * LINE_HEIGHT)
+            .show(ui, |ui| {
+                ui.visuals_mut().override_text_color =
+                    Some(Color32::from_rgba_unmultiplied(188, 194, 202, 220));
+                ui.add(
+                    TextEdit::multiline(&mut editor.text)
+                        .id_source(CODEX_FILE_EDITOR_ID)
+                        .code_editor()
+                        .font(FontId::monospace(TEXT_FONT_SIZE))
+                        .desired_width(f32::INFINITY)
+                        .desired_rows(CODEX_FILE_EDITOR_ROWS)
+                        .frame(false),
+                );
+            });
+        ui.add_space(4.0);
+        let (sep_rect, _) =
+            ui.allocate_exact_size(egui::vec2(ui.available_width(), 1.0), egui::Sense::hover());
+        ui.painter().rect_filled(sep_rect, 0.0, separator);
+        ui.add_space(4.0);
+        ui.horizontal(|ui| {
+            ui.set_min_height(CANCEL_BUTTON_HEIGHT);
+            if let Some(error) = editor.error.as_deref() {
+                ui.add(
+                    egui::Label::new(
+                        RichText::new(error)
+                            .italics()
+                            .color(Color32::from_rgb(255, 96, 96)),
+                    )
+                    .truncate(),
+                );
+            }
+            ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                close = show_pill_button(ui, tr(Text::Cancel));
+                save |= show_pill_button(ui, tr(Text::Save));
+            });
+        });
+        if save {
+            self.save_codex_file_editor();
+        } else if close {
+            self.close_codex_file_editor();
         }
-        if ctx.input_mut(|input| input.consume_key(Modifiers::NONE, Key::ArrowUp)) {
-            moved |= self.move_picker_selection(-1);
+    }
+
+    fn show_event_inspector(&mut self, ui: &mut egui::Ui, separator: Color32) {
+        let Some(inspector) = self.event_inspector.as_ref() else {
+            return;
+        };
+        let mut filter = None;
+        let mut copy = false;
+        let mut close = false;
+        ui.horizontal(|ui| {
+            ui.set_min_height(CANCEL_BUTTON_HEIGHT);
+            ui.add(
+                egui::Label::new(
+                    RichText::new(format!(
+                        "Events from prompt {} \u{00B7} {} of {}",
+                        inspector.prompt_id,
+                        inspector.visible_count(),
+                        inspector.events.len()
+                    ))
+                    .monospace()
+                    .color(Color32::from_rgba_unmultiplied(214, 224, 238, 150)),
+                )
+                .truncate()
+                .selectable(false),
+            );
+            ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                let label = inspector.filter.as_deref().unwrap_or("All types");
+                ui.menu_button(RichText::new(label).monospace(), |ui| {
+                    ui.set_width(SETTINGS_SUBMENU_WIDTH);
+                    show_picker(ui, |ui| {
+                        ui.set_width(SETTINGS_SUBMENU_PICKER_WIDTH);
+                        let all = inspector.events.len().to_string();
+                        if show_picker_row(ui, "All types", &all, false, inspector.filter.is_none())
+                            .clicked()
+                        {
+                            filter = Some(None);
+                        }
+                        for (kind, count) in inspector.kinds() {
+                            let active = inspector.filter.as_deref() == Some(kind);
+                            if show_picker_row(ui, kind, &count.to_string(), false, active)
+                                .clicked()
+                            {
+                                filter = Some(Some(kind.to_owned()));
+                            }
+                        }
+                    });
+                    if filter.is_some() {
+                        ui.close_menu();
+                    }
+                });
+            });
+        });
+        ui.add_space(4.0);
+        egui::ScrollArea::vertical()
+            .id_salt("event-inspector-scroll")
+            .max_height(CODEX_FILE_EDITOR_ROWS as f32 * LINE_HEIGHT)
+            .show(ui, |ui| {
+                let mut layouter = |ui: &egui::Ui, text: &str, wrap_width: f32| {
+                    let job = inspector_layout_job(text, &inspector.preamble_ranges, wrap_width);
+                    ui.fonts(|fonts| fonts.layout_job(job))
+                };
+                ui.add(
+                    TextEdit::multiline(&mut inspector.text.as_str())
+                        .id_source(EVENT_INSPECTOR_ID)
+                        .code_editor()
+                        .font(FontId::monospace(TEXT_FONT_SIZE))
+                        .layouter(&mut layouter)
+                        .desired_width(f32::INFINITY)
+                        .desired_rows(CODEX_FILE_EDITOR_ROWS)
+                        .frame(false),
+                );
+            });
+        ui.add_space(4.0);
+        let (sep_rect, _) =
+            ui.allocate_exact_size(egui::vec2(ui.available_width(), 1.0), egui::Sense::hover());
+        ui.painter().rect_filled(sep_rect, 0.0, separator);
+        ui.add_space(4.0);
+        ui.horizontal(|ui| {
+            ui.set_min_height(CANCEL_BUTTON_HEIGHT);
+            if inspector.dropped > 0 {
+                ui.add(
+                    egui::Label::new(
+                        RichText::new(format!(
+                            "{} older events dropped to stay within the buffer",
+                            inspector.dropped
+                        ))
+                        .italics()
+                        .color(Color32::from_rgba_unmultiplied(214, 224, 238, 150)),
+                    )
+                    .truncate(),
+                );
+            }
+            ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                close = show_pill_button(ui, tr(Text::Close));
+                copy = show_pill_button(ui, tr(Text::CopyAll));
+            });
+        });
+        if let Some(filter) = filter {
+            self.select_inspector_filter(filter);
+        }
+        if copy {
+            self.copy_inspected_events();
+        }
+        if close {
+            self.close_event_inspector();
         }
-        moved
     }
 
-    fn handle_prompt_history_keys(&mut self, ctx: &egui::Context) -> bool {
-        if self.prompt_history.is_empty() {
-            return false;
+    fn show_recent_sessions(&mut self, ui: &mut egui::Ui, separator: Color32) {
+        let Some(view) = self.recent_sessions.as_ref() else {
+            return;
+        };
+        let now = SystemTime::now();
+        let mut action = None;
+        let mut close = false;
+        ui.horizontal(|ui| {
+            ui.set_min_height(CANCEL_BUTTON_HEIGHT);
+            ui.add(
+                egui::Label::new(
+                    RichText::new(format!(
+                        "Recent sessions \u{00B7} {} of {} kept",
+                        view.sessions.len(),
+                        self.recent_session_limit
+                    ))
+                    .monospace()
+                    .color(Color32::from_rgba_unmultiplied(214, 224, 238, 150)),
+                )
+                .truncate()
+                .selectable(false),
+            );
+        });
+        ui.add_space(4.0);
+        egui::ScrollArea::vertical()
+            .id_salt("recent-sessions-scroll")
+            .max_height(CODEX_FILE_EDITOR_ROWS as f32 * LINE_HEIGHT)
+            .show(ui, |ui| {
+                ui.set_min_height(CODEX_FILE_EDITOR_ROWS as f32 * LINE_HEIGHT);
+                for (index, session) in view.sessions.iter().enumerate() {
+                    let (title, details) = recent_session_row(session, now);
+                    let current = self.current_recent_session(session);
+                    ui.horizontal(|ui| {
+                        ui.set_min_height(CANCEL_BUTTON_HEIGHT);
+                        ui.add(
+                            egui::Label::new(
+                                RichText::new(if current {
+                                    format!("{} (current)", title)
+                                } else {
+                                    title
+                                })
+                                .monospace()
+                                .color(Color32::from_rgba_unmultiplied(214, 224, 238, 190)),
+                            )
+                            .truncate()
+                            .selectable(false),
+                        )
+                        .on_hover_text(session.file_name());
+                        ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                            match view.confirming {
+                                Some((confirming, RecentSessionAction::Open))
+                                    if confirming == index =>
+                                {
+                                    if show_pill_button(ui, tr(Text::Keep)) {
+                                        action = Some((index, RecentSessionAction::Keep));
+                                    }
+                                    if show_pill_button(ui, tr(Text::Replace)) {
+                                        action = Some((index, RecentSessionAction::ConfirmOpen));
+                                    }
+                                    ui.label(
+                                        RichText::new("Replace the unsaved transcript?")
+                                            .small()
+                                            .color(Color32::from_rgb(255, 190, 120)),
+                                    );
+                                    return;
+                                }
+                                Some((confirming, RecentSessionAction::Delete))
+                                    if confirming == index =>
+                                {
+                                    if show_pill_button(ui, tr(Text::Keep)) {
+                                        action = Some((index, RecentSessionAction::Keep));
+                                    }
+                                    if show_pill_button(ui, tr(Text::Delete)) {
+                                        action = Some((index, RecentSessionAction::ConfirmDelete));
+                                    }
+                                    ui.label(
+                                        RichText::new("Delete this session?")
+                                            .small()
+                                            .color(Color32::from_rgb(255, 190, 120)),
+                                    );
+                                    return;
+                                }
+                                _ => {}
+                            }
+                            if !current && show_pill_button(ui, tr(Text::Delete)) {
+                                action = Some((index, RecentSessionAction::Delete));
+                            }
+                            if show_pill_button(ui, tr(Text::Export)) {
+                                action = Some((index, RecentSessionAction::Export));
+                            }
+                            if !current && show_pill_button(ui, tr(Text::Open)) {
+                                action = Some((index, RecentSessionAction::Open));
+                            }
+                            ui.label(
+                                RichText::new(details)
+                                    .small()
+                                    .color(Color32::from_rgba_unmultiplied(214, 224, 238, 120)),
+                            );
+                        });
+                    });
+                }
+            });
+        ui.add_space(4.0);
+        let (sep_rect, _) =
+            ui.allocate_exact_size(egui::vec2(ui.available_width(), 1.0), egui::Sense::hover());
+        ui.painter().rect_filled(sep_rect, 0.0, separator);
+        ui.add_space(4.0);
+        ui.horizontal(|ui| {
+            ui.set_min_height(CANCEL_BUTTON_HEIGHT);
+            if let Some(status) = view.status.as_ref() {
+                let (text, color) = match status {
+                    Ok(message) => (
+                        message.as_str(),
+                        Color32::from_rgba_unmultiplied(214, 224, 238, 150),
+                    ),
+                    Err(error) => (error.as_str(), Color32::from_rgb(255, 96, 96)),
+                };
+                ui.add(egui::Label::new(RichText::new(text).italics().color(color)).truncate());
+            }
+            ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                close = show_pill_button(ui, tr(Text::Close));
+            });
+        });
+        if let Some((index, action)) = action {
+            self.run_recent_session_action(index, action);
+        } else if close {
+            self.close_recent_sessions();
         }
-        let mut handled = false;
-        if ctx.input_mut(|input| input.consume_key(Modifiers::NONE, Key::ArrowUp)) {
-            handled |= self.browse_prompt_history(false);
+    }
+
+    fn show_onboarding(&mut self, ui: &mut egui::Ui) {
+        let Some(onboarding) = self.onboarding.as_ref() else {
+            return;
+        };
+        let mut action = None;
+        let codex = match &onboarding.codex_path {
+            None => (None, "Checking for the Codex CLI".to_owned()),
+            Some(Some(path)) => (
+                Some(true),
+                format!("Codex CLI detected at {}", path.display()),
+            ),
+            Some(None) => (Some(false), "Codex CLI not found".to_owned()),
+        };
+        let auth = match onboarding.authenticated {
+            None => (None, "Checking Codex sign-in".to_owned()),
+            Some(true) => (Some(true), "Codex is signed in".to_owned()),
+            Some(false) => (Some(false), "Codex is not signed in".to_owned()),
+        };
+        let cwd = if onboarding.cwd_chosen {
+            (Some(true), format!("Working directory: {}", self.cwd_text))
+        } else {
+            (Some(false), "No working directory chosen".to_owned())
+        };
+        let complete = onboarding.complete();
+        ui.horizontal(|ui| {
+            ui.set_min_height(CANCEL_BUTTON_HEIGHT);
+            ui.add(
+                egui::Label::new(
+                    RichText::new("Getting started")
+                        .strong()
+                        .color(Color32::WHITE),
+                )
+                .selectable(false),
+            );
+        });
+        ui.add_space(ONBOARDING_ROW_SPACING);
+        for ((met, text), button, item_action) in [
+            (codex, "Install", OnboardingAction::Install),
+            (auth, "Log in", OnboardingAction::Login),
+            (cwd, "Choose", OnboardingAction::PickFolder),
+        ] {
+            ui.horizontal(|ui| {
+                ui.set_min_height(CANCEL_BUTTON_HEIGHT);
+                let (rect, _) = ui.allocate_exact_size(
+                    egui::vec2(AGENTS_INDICATOR_WIDTH, CANCEL_BUTTON_HEIGHT),
+                    egui::Sense::hover(),
+                );
+                let color = match met {
+                    Some(true) => Color32::from_rgb(120, 220, 140),
+                    Some(false) => Color32::from_rgb(255, 120, 120),
+                    None => Color32::from_rgba_unmultiplied(214, 224, 238, 150),
+                };
+                if met == Some(true) {
+                    ui.painter()
+                        .circle_filled(rect.center(), AGENTS_INDICATOR_RADIUS, color);
+                } else {
+                    ui.painter().circle_stroke(
+                        rect.center(),
+                        AGENTS_INDICATOR_RADIUS,
+                        egui::Stroke::new(1.0, color),
+                    );
+                }
+                ui.add(
+                    egui::Label::new(
+                        RichText::new(text)
+                            .color(Color32::from_rgba_unmultiplied(214, 224, 238, 190)),
+                    )
+                    .truncate()
+                    .selectable(false),
+                );
+                if met == Some(false) {
+                    ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                        if show_pill_button(ui, button) {
+                            action = Some(item_action);
+                        }
+                    });
+                }
+            });
+            ui.add_space(ONBOARDING_ROW_SPACING);
         }
-        if self.prompt_history_index.is_some()
-            && ctx.input_mut(|input| input.consume_key(Modifiers::NONE, Key::ArrowDown))
-        {
-            handled |= self.browse_prompt_history(true);
+        ui.horizontal(|ui| {
+            ui.set_min_height(CANCEL_BUTTON_HEIGHT);
+            ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                if show_pill_button(ui, tr(if complete { Text::Done } else { Text::Dismiss })) {
+                    action = Some(OnboardingAction::Finish);
+                }
+                if !complete && show_pill_button(ui, tr(Text::CheckAgain)) {
+                    action = Some(OnboardingAction::Recheck);
+                }
+            });
+        });
+        ui.add_space(ONBOARDING_ROW_SPACING);
+        match action {
+            Some(OnboardingAction::Install) => self.open_codex_install_instructions(),
+            Some(OnboardingAction::Login) => self.start_codex_login(),
+            Some(OnboardingAction::PickFolder) => self.pick_working_directory(),
+            Some(OnboardingAction::Recheck) => self.refresh_onboarding(),
+            Some(OnboardingAction::Finish) => self.finish_onboarding(),
+            None => {}
         }
-        handled
     }
 
-    fn show_settings_menu(&mut self, ui: &mut egui::Ui) {
-        let button = egui::Button::new(
-            RichText::new("Settings").color(Color32::from_rgba_unmultiplied(214, 224, 238, 170)),
-        )
-        .fill(Color32::TRANSPARENT)
-        .stroke(egui::Stroke::NONE)
-        .corner_radius(egui::CornerRadius::same(255));
-        let menu = egui::menu::menu_custom_button(ui, button, |ui| {
-            if !self.settings_menu_open {
-                self.refresh_current_model();
-                self.refresh_model_options();
-                self.refresh_notifications_enabled();
-                self.refresh_context_menu_state_async();
-                self.settings_menu_open = true;
+    fn show_clear_confirmation(&mut self, ui: &mut egui::Ui) {
+        if self.clear_confirmation.is_none() {
+            return;
+        }
+        let queued = self.queued_prompts();
+        let notes = !self.notes.text.trim().is_empty();
+        let message = match (queued, notes) {
+            (0, false) => {
+                self.clear_confirmation = None;
+                return;
             }
-            ui.set_width(SETTINGS_MENU_WIDTH);
-            ui.scope(|ui| {
-                style_settings_menu_rows(ui);
-                ui.spacing_mut().menu_spacing = SETTINGS_SUBMENU_SPACING;
-                let close_model_menu = ui
-                    .menu_button(RichText::new("Model").monospace(), |ui| {
+            (0, true) => {
+                "The session notes are not saved anywhere else \u{2014} discard them and clear?"
+                    .to_owned()
+            }
+            (1, notes) => format!(
+                "1 batch prompt is still queued \u{2014} discard it{} and clear?",
+                if notes { " and the notes" } else { "" }
+            ),
+            (queued, notes) => format!(
+                "{} batch prompts are still queued \u{2014} discard them{} and clear?",
+                queued,
+                if notes { " and the notes" } else { "" }
+            ),
+        };
+        let mut action = None;
+        ui.horizontal(|ui| {
+            ui.set_min_height(CANCEL_BUTTON_HEIGHT);
+            ui.add(
+                egui::Label::new(
+                    RichText::new(message)
+                        .italics()
+                        .color(Color32::from_rgb(255, 190, 120)),
+                )
+                .truncate()
+                .selectable(false),
+            );
+            ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                if show_pill_button(ui, tr(Text::Keep)) {
+                    action = Some(ClearConfirmAction::Keep);
+                }
+                if show_pill_button(ui, tr(Text::DiscardAndClear)) {
+                    action = Some(ClearConfirmAction::Discard);
+                }
+            });
+        });
+        ui.add_space(PROMPT_PREFIX_CHIP_SPACING);
+        match action {
+            Some(ClearConfirmAction::Discard) => self.confirm_clear(),
+            Some(ClearConfirmAction::Keep) => self.abort_clear(),
+            None => {}
+        }
+    }
+
+    fn show_dirty_submit_confirmation(&mut self, ui: &mut egui::Ui) {
+        let Some(changes) = self.dirty_submit_confirmation else {
+            return;
+        };
+        let message = if changes == 1 {
+            "Working tree has 1 uncommitted change \u{2014} continue?".to_owned()
+        } else {
+            format!(
+                "Working tree has {} uncommitted changes \u{2014} continue?",
+                changes
+            )
+        };
+        let mut action = None;
+        ui.horizontal(|ui| {
+            ui.set_min_height(CANCEL_BUTTON_HEIGHT);
+            ui.add(
+                egui::Label::new(
+                    RichText::new(message)
+                        .italics()
+                        .color(Color32::from_rgb(255, 190, 120)),
+                )
+                .truncate()
+                .selectable(false),
+            );
+            ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                if show_pill_button(ui, tr(Text::Abort)) {
+                    action = Some(DirtySubmitAction::Abort);
+                }
+                if show_pill_button(ui, tr(Text::DontAsk)) {
+                    action = Some(DirtySubmitAction::ContinueAlways);
+                }
+                if show_pill_button(ui, tr(Text::Continue)) {
+                    action = Some(DirtySubmitAction::Continue);
+                }
+            });
+        });
+        ui.add_space(PROMPT_PREFIX_CHIP_SPACING);
+        match action {
+            Some(DirtySubmitAction::Continue) => self.confirm_dirty_submit(false),
+            Some(DirtySubmitAction::ContinueAlways) => self.confirm_dirty_submit(true),
+            Some(DirtySubmitAction::Abort) => self.abort_dirty_submit(),
+            None => {}
+        }
+    }
+
+    fn show_project_trust_confirmation(&mut self, ui: &mut egui::Ui) {
+        if !self.project_trust_confirmation {
+            return;
+        }
+        let Some((path, keys)) = self.project_config.pending_trust() else {
+            return;
+        };
+        let message = format!(
+            "{} sets {} \u{2014} trust this project?",
+            path.display(),
+            keys.join(", ")
+        );
+        let mut action = None;
+        ui.horizontal(|ui| {
+            ui.set_min_height(CANCEL_BUTTON_HEIGHT);
+            ui.add(
+                egui::Label::new(
+                    RichText::new(message)
+                        .italics()
+                        .color(Color32::from_rgb(255, 190, 120)),
+                )
+                .truncate()
+                .selectable(false),
+            );
+            ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                if show_pill_button(ui, tr(Text::Abort)) {
+                    action = Some(ProjectTrustAction::Abort);
+                }
+                if show_pill_button(ui, tr(Text::Ignore)) {
+                    action = Some(ProjectTrustAction::Ignore);
+                }
+                if show_pill_button(ui, tr(Text::Approve)) {
+                    action = Some(ProjectTrustAction::Trust);
+                }
+            });
+        });
+        ui.add_space(PROMPT_PREFIX_CHIP_SPACING);
+        match action {
+            Some(ProjectTrustAction::Trust) => self.confirm_project_trust(true),
+            Some(ProjectTrustAction::Ignore) => self.confirm_project_trust(false),
+            Some(ProjectTrustAction::Abort) => self.abort_project_trust(),
+            None => {}
+        }
+    }
+
+    fn show_secret_submit_confirmation(&mut self, ui: &mut egui::Ui) {
+        let Some(kind) = self.secret_submit_confirmation else {
+            return;
+        };
+        let message = format!(
+            "Prompt looks like it contains a credential ({}) \u{2014} send it?",
+            kind
+        );
+        let mut action = None;
+        ui.horizontal(|ui| {
+            ui.set_min_height(CANCEL_BUTTON_HEIGHT);
+            ui.add(
+                egui::Label::new(
+                    RichText::new(message)
+                        .italics()
+                        .color(Color32::from_rgb(255, 190, 120)),
+                )
+                .truncate()
+                .selectable(false),
+            );
+            ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                if show_pill_button(ui, tr(Text::Cancel)) {
+                    action = Some(SecretSubmitAction::Cancel);
+                }
+                if show_pill_button(ui, tr(Text::DontAsk)) {
+                    action = Some(SecretSubmitAction::SendAlways);
+                }
+                if show_pill_button(ui, tr(Text::RedactAndSend)) {
+                    action = Some(SecretSubmitAction::Redact);
+                }
+                if show_pill_button(ui, tr(Text::SendAnyway)) {
+                    action = Some(SecretSubmitAction::Send);
+                }
+            });
+        });
+        ui.add_space(PROMPT_PREFIX_CHIP_SPACING);
+        match action {
+            Some(SecretSubmitAction::Send) => {
+                self.confirm_secret_submit(SecretSubmitDecision::Send, false)
+            }
+            Some(SecretSubmitAction::SendAlways) => {
+                self.confirm_secret_submit(SecretSubmitDecision::Send, true)
+            }
+            Some(SecretSubmitAction::Redact) => {
+                self.confirm_secret_submit(SecretSubmitDecision::Redact, false)
+            }
+            Some(SecretSubmitAction::Cancel) => self.abort_secret_submit(),
+            None => {}
+        }
+    }
+
+    fn show_token_estimate(&mut self, ui: &mut egui::Ui) {
+        let tokens = self.input_token_estimate();
+        if let Some(tokens) = self.token_submit_confirmation {
+            let message = format!(
+                "~{} tokens is over the {} warning \u{2014} send anyway?",
+                format_tokens(tokens),
+                format_tokens(self.token_warning_limit)
+            );
+            let mut action = None;
+            ui.add_space(PROMPT_PREFIX_CHIP_SPACING);
+            ui.horizontal(|ui| {
+                ui.set_min_height(CANCEL_BUTTON_HEIGHT);
+                ui.add(
+                    egui::Label::new(
+                        RichText::new(message)
+                            .italics()
+                            .color(Color32::from_rgb(255, 190, 120)),
+                    )
+                    .truncate()
+                    .selectable(false),
+                );
+                ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                    if show_pill_button(ui, tr(Text::Edit)) {
+                        action = Some(TokenSubmitAction::Edit);
+                    }
+                    if show_pill_button(ui, tr(Text::Send)) {
+                        action = Some(TokenSubmitAction::Send);
+                    }
+                });
+            });
+            match action {
+                Some(TokenSubmitAction::Send) => self.submit(),
+                Some(TokenSubmitAction::Edit) => self.abort_token_submit(),
+                None => {}
+            }
+            return;
+        }
+        if tokens < TOKEN_ESTIMATE_THRESHOLD {
+            return;
+        }
+        let color = if self.token_warning_limit > 0 && tokens > self.token_warning_limit {
+            Color32::from_rgb(255, 190, 120)
+        } else {
+            Color32::from_rgba_unmultiplied(214, 224, 238, 110)
+        };
+        ui.add_space(PROMPT_PREFIX_CHIP_SPACING);
+        ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+            ui.set_height(PROMPT_PREFIX_CHIP_HEIGHT);
+            ui.add(
+                egui::Label::new(
+                    RichText::new(format!("~{} tokens", format_tokens(tokens))).color(color),
+                )
+                .selectable(false),
+            );
+        });
+    }
+
+    fn show_prompt_prefix_chip(&self, ui: &mut egui::Ui) {
+        let Some(prefix) = self.active_prompt_prefix() else {
+            return;
+        };
+        let first_line = prefix.lines().next().unwrap_or_default();
+        show_input_chip(ui, &format!("Project prefix: {}", first_line), prefix);
+    }
+
+    fn show_response_language_chip(&self, ui: &mut egui::Ui) {
+        let Some(language) = self.response_language.as_deref() else {
+            return;
+        };
+        show_input_chip(
+            ui,
+            &format!("\u{1F310} {}", language),
+            &format!(
+                "Each prompt ends with \u{201C}{}\u{201D} (not shown in the transcript). /lang off clears it.",
+                response_language_directive(language)
+            ),
+        );
+    }
+
+    fn show_concise_chip(&mut self, ui: &mut egui::Ui) {
+        if !self.concise {
+            return;
+        }
+        let clicked = show_suggestion_chip(
+            ui,
+            "\u{2702} Concise",
+            &format!(
+                "Each prompt ends with \u{201C}{}\u{201D} (not shown in the transcript). Click or /concise to turn it off.",
+                CONCISE_DIRECTIVE
+            ),
+        );
+        ui.add_space(PROMPT_PREFIX_CHIP_SPACING);
+        if clicked {
+            let message = self.toggle_concise();
+            self.push_settings_output(&message);
+            self.finish_local_change();
+        }
+    }
+
+    fn apply_output_menu_action(&mut self, action: OutputMenuAction, output_id: egui::Id) {
+        match action {
+            OutputMenuAction::CopySelection => {
+                if let Some(text) = self.output_selection_text(output_id) {
+                    self.ctx.copy_text(self.processed_output(text));
+                }
+            }
+            OutputMenuAction::CopyLastResponse => {
+                self.ctx
+                    .copy_text(self.processed_output(self.last_response_text().to_owned()));
+            }
+            OutputMenuAction::CopyMarkdown => {
+                self.ctx
+                    .copy_text(self.processed_output(self.output_display_buffer.trim().to_owned()));
+            }
+            OutputMenuAction::CopyPlainText => self.copy_plain_text(Some(output_id)),
+            OutputMenuAction::SelectAll => {
+                let mut state = TextEdit::load_state(&self.ctx, output_id).unwrap_or_default();
+                let end = self.output_display_buffer.chars().count();
+                state
+                    .cursor
+                    .set_char_range(Some(egui::text::CCursorRange::two(
+                        egui::text::CCursor::new(0),
+                        egui::text::CCursor::new(end),
+                    )));
+                state.store(&self.ctx, output_id);
+                self.ctx
+                    .memory_mut(|memory| memory.request_focus(output_id));
+                trace_focus("output", true);
+            }
+            OutputMenuAction::SearchSelection => {
+                let selection = self.output_selection_text(output_id);
+                self.open_find(selection);
+            }
+            OutputMenuAction::InspectEvents => self.inspect_events_from_menu(),
+            OutputMenuAction::ClearSession => self.request_clear(),
+        }
+    }
+
+    fn show_prompt_copy_button(
+        &mut self,
+        ui: &mut egui::Ui,
+        galley_pos: egui::Pos2,
+        output_rect: egui::Rect,
+    ) {
+        let copied = self
+            .copied_prompt
+            .filter(|(_, at)| at.elapsed() < PROMPT_COPIED_FLASH)
+            .map(|(index, _)| index);
+        if copied.is_none() {
+            self.copied_prompt = None;
+        }
+        let hovered = ui
+            .input(|input| {
+                let pointer = &input.pointer;
+                (!pointer.any_down() && !self.window_dragging)
+                    .then(|| pointer.hover_pos())
+                    .flatten()
+            })
+            .filter(|pos| output_rect.contains(*pos) && ui.clip_rect().contains(*pos))
+            .and_then(|pos| {
+                let local = pos - galley_pos.to_vec2();
+                self.output_prompt_rects
+                    .iter()
+                    .position(|rect| rect.contains(local))
+            });
+        let Some(index) = copied.or(hovered) else {
+            return;
+        };
+        let Some(region) = self.output_prompt_rects.get(index) else {
+            return;
+        };
+        let button_rect = egui::Rect::from_min_size(
+            egui::pos2(
+                output_rect.right() - PROMPT_COPY_BUTTON_SIZE.x,
+                galley_pos.y + region.top(),
+            ),
+            PROMPT_COPY_BUTTON_SIZE,
+        );
+        if copied == Some(index) {
+            ui.painter().text(
+                button_rect.right_center(),
+                egui::Align2::RIGHT_CENTER,
+                "Copied",
+                FontId::proportional(TURN_MARKER_FONT_SIZE + 1.0),
+                Color32::from_rgba_unmultiplied(214, 224, 238, 170),
+            );
+            ui.ctx().request_repaint_after(PROMPT_COPIED_FLASH);
+            return;
+        }
+        let response = ui
+            .put(
+                button_rect,
+                egui::Button::new(
+                    RichText::new("\u{1F4CB}")
+                        .size(TURN_MARKER_FONT_SIZE + 1.0)
+                        .color(Color32::from_rgba_unmultiplied(214, 224, 238, 170)),
+                )
+                .fill(Color32::TRANSPARENT)
+                .stroke(egui::Stroke::NONE),
+            )
+            .on_hover_text("Copy prompt");
+        if response.clicked() {
+            let prompt = self
+                .output_display_prompt_ranges
+                .get(index)
+                .and_then(|&(start, end)| self.output_display_buffer.get(start..end))
+                .map(|prompt| prompt.trim().to_owned());
+            if let Some(prompt) = prompt {
+                self.ctx.copy_text(prompt);
+                self.copied_prompt = Some((index, std::time::Instant::now()));
+            }
+        }
+    }
+
+    fn show_pin_buttons(
+        &mut self,
+        ui: &mut egui::Ui,
+        galley_pos: egui::Pos2,
+        output_rect: egui::Rect,
+    ) {
+        if self.busy {
+            return;
+        }
+        let hovered = ui
+            .input(|input| {
+                let pointer = &input.pointer;
+                (!pointer.any_down() && !self.window_dragging)
+                    .then(|| pointer.hover_pos())
+                    .flatten()
+            })
+            .filter(|pos| output_rect.contains(*pos) && ui.clip_rect().contains(*pos))
+            .and_then(|pos| {
+                let local = pos - galley_pos.to_vec2();
+                self.output_pin_rects
+                    .iter()
+                    .find(|(_, rect)| rect.contains(local))
+                    .map(|&(target, _)| target)
+            });
+        let mut clicked = None;
+        for &(target, region) in &self.output_pin_rects {
+            let pinned = self.transcript.is_pinned(target);
+            if !pinned && hovered != Some(target) {
+                continue;
+            }
+            let button_rect = egui::Rect::from_min_size(
+                egui::pos2(
+                    output_rect.right() - PROMPT_COPY_BUTTON_SIZE.x,
+                    galley_pos.y + region.top(),
+                ),
+                PROMPT_COPY_BUTTON_SIZE,
+            );
+            if !ui.clip_rect().intersects(button_rect) {
+                continue;
+            }
+            let alpha = if hovered == Some(target) { 170 } else { 90 };
+            let hover_text = match target {
+                PinTarget::Block(_) => "Unpin and remove",
+                PinTarget::Turn(_) if pinned => "Unpin response",
+                PinTarget::Turn(_) => "Pin response so Clear keeps it",
+            };
+            let response = ui
+                .put(
+                    button_rect,
+                    egui::Button::new(
+                        RichText::new("\u{1F4CC}")
+                            .size(TURN_MARKER_FONT_SIZE + 1.0)
+                            .color(Color32::from_rgba_unmultiplied(214, 224, 238, alpha)),
+                    )
+                    .fill(Color32::TRANSPARENT)
+                    .stroke(egui::Stroke::NONE),
+                )
+                .on_hover_text(hover_text);
+            if response.clicked() {
+                clicked = Some(target);
+            }
+        }
+        if let Some(target) = clicked {
+            self.toggle_pin(target);
+        }
+    }
+
+    fn show_rating_buttons(
+        &mut self,
+        ui: &mut egui::Ui,
+        galley_pos: egui::Pos2,
+        output_rect: egui::Rect,
+    ) {
+        let hovered = ui
+            .input(|input| {
+                let pointer = &input.pointer;
+                (!self.busy && !pointer.any_down() && !self.window_dragging)
+                    .then(|| pointer.hover_pos())
+                    .flatten()
+            })
+            .filter(|pos| output_rect.contains(*pos) && ui.clip_rect().contains(*pos))
+            .and_then(|pos| {
+                let local = pos - galley_pos.to_vec2();
+                self.output_pin_rects
+                    .iter()
+                    .find_map(|&(target, rect)| match target {
+                        PinTarget::Turn(turn) if rect.contains(local) => Some(turn),
+                        _ => None,
+                    })
+            });
+        let right = output_rect.right() - PROMPT_COPY_BUTTON_SIZE.x;
+        let mut clicked = None;
+        for (turn, &end_y) in self.output_response_ends.iter().enumerate() {
+            let rating = self.transcript.rating(turn);
+            let y = galley_pos.y + end_y;
+            if hovered != Some(turn) {
+                let pos = egui::pos2(right - RATING_BUTTON_SIZE.x / 2.0, y);
+                if let Some(rating) = rating
+                    && ui.clip_rect().contains(pos)
+                {
+                    ui.painter().text(
+                        pos,
+                        egui::Align2::CENTER_CENTER,
+                        rating_glyph(rating),
+                        FontId::proportional(TURN_MARKER_FONT_SIZE + 1.0),
+                        Color32::from_rgba_unmultiplied(214, 224, 238, 120),
+                    );
+                }
+                continue;
+            }
+            for (index, &option) in RATINGS.iter().rev().enumerate() {
+                let button_rect = egui::Rect::from_center_size(
+                    egui::pos2(right - RATING_BUTTON_SIZE.x * (index as f32 + 0.5), y),
+                    RATING_BUTTON_SIZE,
+                );
+                if !ui.clip_rect().intersects(button_rect) {
+                    continue;
+                }
+                let active = rating == Some(option);
+                let hover_text = match option {
+                    _ if active => "Remove the rating",
+                    Rating::Good => "Mark this response good",
+                    Rating::Bad => "Mark this response bad",
+                };
+                let response = ui
+                    .put(
+                        button_rect,
+                        egui::Button::new(
+                            RichText::new(rating_glyph(option))
+                                .size(TURN_MARKER_FONT_SIZE + 1.0)
+                                .color(Color32::from_rgba_unmultiplied(
+                                    214,
+                                    224,
+                                    238,
+                                    if active { 220 } else { 110 },
+                                )),
+                        )
+                        .fill(Color32::TRANSPARENT)
+                        .stroke(egui::Stroke::NONE),
+                    )
+                    .on_hover_text(hover_text);
+                if response.clicked() {
+                    clicked = Some((turn, option));
+                }
+            }
+        }
+        if let Some((turn, rating)) = clicked {
+            self.rate_response(turn, rating);
+        }
+    }
+
+    fn show_follow_up_suggestions(&mut self, ui: &mut egui::Ui) {
+        if self.busy || self.follow_up_suggestions.is_empty() && !self.continuation_offered {
+            return;
+        }
+        let mut picked = None;
+        let mut continue_clicked = false;
+        ui.horizontal(|ui| {
+            if self.continuation_offered {
+                continue_clicked = show_suggestion_chip(
+                    ui,
+                    "Continue",
+                    "The answer looks cut off \u{2014} ask codex to continue it",
+                );
+            }
+            for (index, suggestion) in self.follow_up_suggestions.iter().enumerate() {
+                if show_suggestion_chip(ui, suggestion, suggestion) {
+                    picked = Some(index);
+                }
+            }
+        });
+        ui.add_space(PROMPT_PREFIX_CHIP_SPACING);
+        if continue_clicked {
+            self.continue_response();
+        } else if let Some(index) = picked {
+            self.use_follow_up_suggestion(index);
+        }
+    }
+
+    fn show_topic_shift_suggestion(&mut self, ui: &mut egui::Ui) {
+        if !self.topic_shift_visible() {
+            return;
+        }
+        let mut accepted = false;
+        let mut dismissed = false;
+        ui.horizontal(|ui| {
+            accepted = show_suggestion_chip(
+                ui,
+                "New topic? Start a fresh session",
+                &format!(
+                    "This prompt shares no key terms with the last few turns \u{2014} \
+                     start a new codex session and keep the transcript ({})",
+                    self.shortcut_label(ShortcutAction::NewTopic)
+                ),
+            );
+            dismissed = show_suggestion_chip(ui, "\u{2715}", "Keep the current session");
+        });
+        ui.add_space(PROMPT_PREFIX_CHIP_SPACING);
+        if accepted {
+            self.accept_topic_shift();
+        } else if dismissed {
+            self.dismiss_topic_shift();
+        }
+    }
+
+    fn show_paste_fence_offer(&mut self, ui: &mut egui::Ui) {
+        if !self.paste_fence_offered || self.input.is_empty() {
+            return;
+        }
+        let clicked = show_suggestion_chip(
+            ui,
+            "Wrap paste in code fence",
+            "Surround the pasted text with a ``` fence",
+        );
+        ui.add_space(PROMPT_PREFIX_CHIP_SPACING);
+        if clicked {
+            self.wrap_input_in_fence();
+        }
+    }
+
+    fn show_clipboard_offer(&mut self, ui: &mut egui::Ui) {
+        let Some((label, hover)) = self.clipboard_offer_label() else {
+            return;
+        };
+        let mut accepted = false;
+        let mut dismissed = false;
+        ui.horizontal(|ui| {
+            accepted = show_suggestion_chip(ui, &label, &hover);
+            dismissed = show_suggestion_chip(ui, "\u{2715}", "Dismiss");
+        });
+        ui.add_space(PROMPT_PREFIX_CHIP_SPACING);
+        if accepted {
+            self.accept_clipboard_offer();
+        } else if dismissed {
+            self.dismiss_clipboard_offer();
+        }
+    }
+
+    fn show_sleep_retry(&mut self, ui: &mut egui::Ui) {
+        if !self.sleep_retry_offered() {
+            return;
+        }
+        let mut retry = false;
+        let mut dismissed = false;
+        ui.horizontal(|ui| {
+            retry = show_suggestion_chip(
+                ui,
+                "Retry prompt interrupted by sleep",
+                "Send the same prompt again",
+            );
+            dismissed = show_suggestion_chip(ui, "\u{2715}", "Dismiss");
+        });
+        ui.add_space(PROMPT_PREFIX_CHIP_SPACING);
+        if retry {
+            self.retry_after_sleep();
+        } else if dismissed {
+            self.dismiss_sleep_retry();
+        }
+    }
+
+    fn show_tool_retry(&mut self, ui: &mut egui::Ui) {
+        if !self.tool_retry_offered() {
+            return;
+        }
+        let hover = self.tool_retry.as_ref().map_or_else(String::new, |step| {
+            format!(
+                "Prefill a follow-up asking codex to retry `{}` in the same session",
+                step.command.lines().next().unwrap_or_default()
+            )
+        });
+        let mut retry = false;
+        let mut dismissed = false;
+        ui.horizontal(|ui| {
+            retry = show_suggestion_chip(ui, "Retry from failure", &hover);
+            dismissed = show_suggestion_chip(ui, "\u{2715}", "Dismiss");
+        });
+        ui.add_space(PROMPT_PREFIX_CHIP_SPACING);
+        if retry {
+            self.prefill_tool_retry();
+        } else if dismissed {
+            self.dismiss_tool_retry();
+        }
+    }
+
+    fn show_approval_request(&mut self, ui: &mut egui::Ui) {
+        let Some(request) = self.pending_approval.as_ref().filter(|_| !self.busy) else {
+            return;
+        };
+        let mut approved = None;
+        ui.horizontal(|ui| {
+            ui.set_min_height(CANCEL_BUTTON_HEIGHT);
+            let title = ui.add(
+                egui::Label::new(
+                    RichText::new(format!(
+                        "Codex asks to {}",
+                        request.kind.label().to_lowercase()
+                    ))
+                    .strong()
+                    .color(Color32::from_rgb(255, 214, 138)),
+                )
+                .truncate()
+                .selectable(false),
+            );
+            if let Some(reason) = request.reason.as_deref() {
+                title.on_hover_text(reason);
+            }
+            ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                if show_pill_button(ui, tr(Text::Deny)) {
+                    approved = Some(false);
+                }
+                if request.can_approve() && show_pill_button(ui, tr(Text::Approve)) {
+                    approved = Some(true);
+                }
+            });
+        });
+        for row in approval_detail_rows(&request.detail) {
+            ui.add(
+                egui::Label::new(
+                    RichText::new(row)
+                        .monospace()
+                        .color(Color32::from_rgba_unmultiplied(214, 224, 238, 190)),
+                )
+                .truncate()
+                .selectable(false),
+            );
+        }
+        ui.add_space(PROMPT_PREFIX_CHIP_SPACING);
+        match approved {
+            Some(true) => self.approve_pending(),
+            Some(false) => self.deny_pending(),
+            None => {}
+        }
+    }
+
+    fn show_input_lock(&self, ui: &mut egui::Ui, rect: egui::Rect) {
+        ui.painter().text(
+            egui::pos2(rect.right() - 4.0, rect.top() + LINE_HEIGHT * 0.5),
+            egui::Align2::RIGHT_CENTER,
+            "\u{1F512}",
+            FontId::proportional(TEXT_FONT_SIZE),
+            Color32::from_rgba_unmultiplied(214, 224, 238, 170),
+        );
+        ui.interact(
+            rect,
+            egui::Id::new("prompt-input-lock"),
+            egui::Sense::hover(),
+        )
+        .on_hover_text(format!(
+            "Waiting for the current prompt \u{2014} press {} to cancel",
+            self.shortcut_label(ShortcutAction::Cancel)
+        ));
+    }
+
+    fn show_changed_files(&mut self, ui: &mut egui::Ui) {
+        if self.busy || self.changed_files.is_empty() {
+            return;
+        }
+        let hover = if self.changed_files_open {
+            "Hide the files codex changed"
+        } else {
+            "Review the files codex changed"
+        };
+        if show_suggestion_chip(ui, &changed_files_label(&self.changed_files), hover) {
+            self.toggle_changed_files();
+        }
+        ui.add_space(PROMPT_PREFIX_CHIP_SPACING);
+        if !self.changed_files_open {
+            return;
+        }
+        let git_ready = self.changed_files_git_ready();
+        let mut action = None;
+        for (index, file) in self.changed_files.iter().enumerate() {
+            ui.horizontal(|ui| {
+                ui.set_min_height(CANCEL_BUTTON_HEIGHT);
+                let mut path = self.display_changed_file_path(&file.path).to_owned();
+                if file.reverted {
+                    path.push_str(" (reverted)");
+                }
+                ui.add(
+                    egui::Label::new(
+                        RichText::new(path)
+                            .monospace()
+                            .color(Color32::from_rgba_unmultiplied(214, 224, 238, 190)),
+                    )
+                    .truncate()
+                    .selectable(false),
+                )
+                .on_hover_text(file.path.as_str());
+                ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                    if file.confirming_revert {
+                        if show_pill_button(ui, tr(Text::Keep)) {
+                            action = Some((index, ChangedFileAction::CancelRevert));
+                        }
+                        if show_pill_button(ui, tr(Text::Revert)) {
+                            action = Some((index, ChangedFileAction::ConfirmRevert));
+                        }
+                        ui.label(
+                            RichText::new("Discard codex's changes?")
+                                .small()
+                                .color(Color32::from_rgb(255, 190, 120)),
+                        );
+                        return;
+                    }
+                    if git_ready && !file.pending && !file.reverted {
+                        if show_pill_button(ui, tr(Text::Revert)) {
+                            action = Some((index, ChangedFileAction::Revert));
+                        }
+                        let diff_label = tr(if file.diff.is_some() {
+                            Text::Hide
+                        } else {
+                            Text::Diff
+                        });
+                        if show_pill_button(ui, diff_label) {
+                            action = Some((index, ChangedFileAction::Diff));
+                        }
+                    }
+                    if show_pill_button(ui, tr(Text::Open)) {
+                        action = Some((index, ChangedFileAction::Open));
+                    }
+                });
+            });
+            if let Some(error) = file.error.as_deref() {
+                ui.add_sized(
+                    egui::vec2(ui.available_width(), LINE_HEIGHT),
+                    egui::Label::new(
+                        RichText::new(error)
+                            .small()
+                            .color(Color32::from_rgb(255, 120, 120)),
+                    )
+                    .truncate(),
+                );
+            }
+            if let Some(diff) = file.diff.as_ref() {
+                ui.add_space(CODEX_FILE_UPDATE_SPACING);
+                show_diff_lines(ui, ("changed-file-diff", index), diff);
+            }
+            ui.add_space(CODEX_FILE_UPDATE_SPACING);
+        }
+        if let Some((index, action)) = action {
+            self.run_changed_file_action(index, action);
+        }
+    }
+
+    fn show_response_diff_toggle(&mut self, ui: &mut egui::Ui) {
+        let Some(diff) = self.visible_response_diff() else {
+            return;
+        };
+        let (label, clickable) = match (&diff.text, diff.changed, diff.showing) {
+            (None, ..) => ("Diff too large to compare with previous answer", false),
+            (Some(_), false, _) => ("No changes from previous answer", false),
+            (Some(_), true, false) => ("Show changes from previous answer", true),
+            (Some(_), true, true) => ("Hide changes from previous answer", true),
+        };
+        let clicked = egui::Frame::new()
+            .fill(Color32::from_rgba_unmultiplied(90, 200, 110, 20))
+            .stroke(egui::Stroke::new(
+                1.0,
+                Color32::from_rgba_unmultiplied(90, 200, 110, 60),
+            ))
+            .corner_radius(egui::CornerRadius::same(255))
+            .inner_margin(egui::Margin::symmetric(8, 0))
+            .show(ui, |ui| {
+                ui.set_height(PROMPT_PREFIX_CHIP_HEIGHT);
+                let label = ui.add(
+                    egui::Label::new(
+                        RichText::new(label)
+                            .small()
+                            .color(Color32::from_rgba_unmultiplied(214, 238, 218, 190)),
+                    )
+                    .selectable(false)
+                    .sense(if clickable {
+                        egui::Sense::click()
+                    } else {
+                        egui::Sense::hover()
+                    }),
+                );
+                if clickable {
+                    label
+                        .on_hover_cursor(egui::CursorIcon::PointingHand)
+                        .clicked()
+                } else {
+                    false
+                }
+            })
+            .inner;
+        ui.add_space(PROMPT_PREFIX_CHIP_SPACING);
+        if clicked {
+            self.toggle_response_diff();
+        }
+    }
+
+    fn show_cwd_label(&mut self, ui: &mut egui::Ui) {
+        let Some(problem) = self.cwd_problem.as_deref() else {
+            ui.add(
+                egui::Label::new(
+                    RichText::new(self.cwd_text.as_str())
+                        .color(Color32::from_rgba_unmultiplied(214, 224, 238, 150)),
+                )
+                .selectable(false),
+            );
+            return;
+        };
+        let clicked = ui
+            .add(
+                egui::Label::new(
+                    RichText::new(self.cwd_text.as_str()).color(Color32::from_rgb(255, 120, 120)),
+                )
+                .selectable(false)
+                .sense(egui::Sense::click()),
+            )
+            .on_hover_cursor(egui::CursorIcon::PointingHand)
+            .on_hover_text(format!(
+                "Working directory unavailable: {}.\nPrompts are disabled until it is back; click to choose another folder.",
+                problem
+            ))
+            .clicked();
+        if clicked {
+            self.pick_working_directory();
+        }
+    }
+
+    fn show_environment_notice(&mut self, ui: &mut egui::Ui) {
+        if self.environment_notice.is_empty() {
+            return;
+        }
+        let details = self.environment_notice.join("\n");
+        let mut dismissed = false;
+        ui.horizontal(|ui| {
+            ui.set_min_height(CANCEL_BUTTON_HEIGHT);
+            ui.add(
+                egui::Label::new(
+                    RichText::new(self.environment_notice.join("; "))
+                        .italics()
+                        .color(Color32::from_rgb(255, 190, 120)),
+                )
+                .truncate()
+                .selectable(false),
+            )
+            .on_hover_text(details);
+            ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                dismissed = show_pill_button(ui, tr(Text::Dismiss));
+            });
+        });
+        ui.add_space(PROMPT_PREFIX_CHIP_SPACING);
+        if dismissed {
+            self.dismiss_environment_notice();
+        }
+    }
+
+    fn show_disk_space_warning(&mut self, ui: &mut egui::Ui) {
+        if !self.disk_space.visible() {
+            return;
+        }
+        let message = format!(
+            "Disk space is low ({} MB free) \u{2014} recordings and snapshots are paused",
+            self.disk_space.free_mb().unwrap_or_default()
+        );
+        let mut dismissed = false;
+        ui.horizontal(|ui| {
+            ui.set_min_height(CANCEL_BUTTON_HEIGHT);
+            ui.add(
+                egui::Label::new(
+                    RichText::new(message)
+                        .italics()
+                        .color(Color32::from_rgb(255, 190, 120)),
+                )
+                .truncate()
+                .selectable(false),
+            )
+            .on_hover_text(format!(
+                "The drive holding settings and logs has less than {} MB free. Free some space and this clears on its own.",
+                self.disk_space_warning_mb
+            ));
+            ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                dismissed = show_pill_button(ui, tr(Text::Dismiss));
+            });
+        });
+        ui.add_space(PROMPT_PREFIX_CHIP_SPACING);
+        if dismissed {
+            self.dismiss_disk_space_warning();
+        }
+    }
+
+    fn show_codex_file_update_notice(&mut self, ui: &mut egui::Ui) {
+        let Some(update) = self.codex_file_updates.first() else {
+            return;
+        };
+        let subject = match update.file {
+            CodexFile::Config => "codex config",
+            CodexFile::Agents => "AGENTS.md",
+        };
+        let mut action = None;
+        ui.horizontal(|ui| {
+            ui.set_min_height(CANCEL_BUTTON_HEIGHT);
+            ui.add(
+                egui::Label::new(
+                    RichText::new(format!("Default {} has changed \u{2014} review?", subject))
+                        .italics()
+                        .color(Color32::from_rgba_unmultiplied(214, 224, 238, 190)),
+                )
+                .truncate()
+                .selectable(false),
+            );
+            ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                if show_pill_button(ui, tr(Text::Never)) {
+                    action = Some(CodexFileUpdateAction::IgnorePermanently);
+                }
+                if show_pill_button(ui, tr(Text::Ignore)) {
+                    action = Some(CodexFileUpdateAction::Ignore);
+                }
+                if show_pill_button(ui, tr(Text::Apply)) {
+                    action = Some(CodexFileUpdateAction::Apply);
+                }
+                let review_label = tr(if self.codex_file_update_review {
+                    Text::Hide
+                } else {
+                    Text::Review
+                });
+                if show_pill_button(ui, review_label) {
+                    action = Some(CodexFileUpdateAction::Review);
+                }
+            });
+        });
+        if self.codex_file_update_review {
+            ui.add_space(CODEX_FILE_UPDATE_SPACING);
+            show_diff_lines(ui, "codex-file-update-diff", &update.diff);
+        }
+        ui.add_space(CODEX_FILE_UPDATE_SPACING);
+        match action {
+            Some(CodexFileUpdateAction::Review) => self.toggle_codex_file_update_review(),
+            Some(CodexFileUpdateAction::Apply) => self.apply_pending_codex_file_update(),
+            Some(CodexFileUpdateAction::Ignore) => self.ignore_pending_codex_file_update(false),
+            Some(CodexFileUpdateAction::IgnorePermanently) => {
+                self.ignore_pending_codex_file_update(true)
+            }
+            None => {}
+        }
+    }
+
+    pub(super) fn high_contrast(&self) -> bool {
+        self.theme
+            .opaque(self.accessibility, self.system_light_theme)
+    }
+
+    fn card_fill(&self) -> Color32 {
+        if self.high_contrast() {
+            return HIGH_CONTRAST_CARD_BACKGROUND;
+        }
+        Color32::from_rgba_unmultiplied(
+            CARD_BACKGROUND.r(),
+            CARD_BACKGROUND.g(),
+            CARD_BACKGROUND.b(),
+            204,
+        )
+    }
+
+    fn glow_palette(&self) -> GlowPalette {
+        if self.high_contrast() {
+            return GlowPalette {
+                stroke: if self.busy {
+                    Color32::from_rgb(200, 200, 200)
+                } else {
+                    Color32::WHITE
+                },
+                shadow: Color32::TRANSPARENT,
+                separator: Color32::from_rgb(160, 160, 160),
+            };
+        }
+        if self.busy {
+            return GlowPalette {
+                stroke: Color32::from_rgba_unmultiplied(158, 164, 173, 84),
+                shadow: Color32::from_rgba_unmultiplied(122, 128, 138, 64),
+                separator: Color32::from_rgba_unmultiplied(158, 164, 173, 36),
+            };
+        }
+
+        GlowPalette {
+            stroke: Color32::from_rgba_unmultiplied(124, 189, 255, 92),
+            shadow: Color32::from_rgba_unmultiplied(96, 176, 255, 88),
+            separator: Color32::from_rgba_unmultiplied(124, 189, 255, 40),
+        }
+    }
+
+    fn card_shadow(&self, glow: Color32) -> egui::epaint::Shadow {
+        if !self.window_shadow.enabled || self.high_contrast() {
+            return egui::epaint::Shadow::NONE;
+        }
+        egui::epaint::Shadow {
+            offset: [0, 0],
+            blur: self.window_shadow.blur,
+            spread: self.window_shadow.spread,
+            color: self
+                .window_shadow
+                .color
+                .map(|[r, g, b, a]| Color32::from_rgba_unmultiplied(r, g, b, a))
+                .unwrap_or(glow),
+        }
+    }
+
+    fn handle_picker_keys(&mut self, ctx: &egui::Context) -> bool {
+        if self.picker_item_count() == 0 {
+            return false;
+        }
+        if self.picker_selection().is_some()
+            && ctx.input_mut(|input| input.consume_key(Modifiers::NONE, Key::Enter))
+        {
+            return self.activate_picker_selection();
+        }
+        let mut moved = false;
+        if ctx.input_mut(|input| input.consume_key(Modifiers::NONE, Key::ArrowDown)) {
+            moved |= self.move_picker_selection(1);
+        }
+        if ctx.input_mut(|input| input.consume_key(Modifiers::NONE, Key::ArrowUp)) {
+            moved |= self.move_picker_selection(-1);
+        }
+        moved
+    }
+
+    fn handle_prompt_history_keys(&mut self, ctx: &egui::Context) -> bool {
+        if self.prompt_history.is_empty() {
+            return false;
+        }
+        let mut handled = false;
+        if ctx.input_mut(|input| input.consume_key(Modifiers::NONE, Key::ArrowUp)) {
+            handled |= self.browse_prompt_history(false);
+        }
+        if self.prompt_history_index.is_some()
+            && ctx.input_mut(|input| input.consume_key(Modifiers::NONE, Key::ArrowDown))
+        {
+            handled |= self.browse_prompt_history(true);
+        }
+        handled
+    }
+
+    fn show_git_info(&self, ui: &mut egui::Ui) {
+        let Some(info) = self.cwd_git_info() else {
+            return;
+        };
+        ui.add_space(6.0);
+        let mut text = format!("branch {}", info.branch);
+        if info.changes > 0 {
+            text.push_str(" \u{2022}");
+        }
+        let tooltip = match info.changes {
+            0 => "No uncommitted changes".to_owned(),
+            1 => "1 uncommitted change".to_owned(),
+            count => format!("{} uncommitted changes", count),
+        };
+        ui.add(
+            egui::Label::new(
+                RichText::new(text).color(Color32::from_rgba_unmultiplied(214, 224, 238, 120)),
+            )
+            .selectable(false),
+        )
+        .on_hover_text(tooltip);
+    }
+
+    fn stream_rate_width(&self) -> f32 {
+        if self.show_stream_rate && self.hook_output_lines.is_none() {
+            STREAM_RATE_LABEL_WIDTH
+        } else {
+            0.0
+        }
+    }
+
+    fn show_stream_rate(&self, ui: &mut egui::Ui) {
+        if self.stream_rate_width() == 0.0 {
+            return;
+        }
+        let label = self
+            .stream_throughput
+            .rate_label(Instant::now())
+            .unwrap_or_default();
+        ui.add_sized(
+            [STREAM_RATE_LABEL_WIDTH, CANCEL_BUTTON_HEIGHT],
+            egui::Label::new(
+                RichText::new(label)
+                    .small()
+                    .color(Color32::from_rgba_unmultiplied(214, 224, 238, 90)),
+            )
+            .selectable(false),
+        );
+    }
+
+    fn show_prompt_phase(&self, ui: &mut egui::Ui) {
+        let label = if self.cancelling_prompt.is_some() {
+            ""
+        } else if self.hook_output_lines.is_some() {
+            "hook"
+        } else {
+            self.prompt_phase.label()
+        };
+        ui.add_sized(
+            [PROMPT_PHASE_LABEL_WIDTH, CANCEL_BUTTON_HEIGHT],
+            egui::Label::new(
+                RichText::new(label).color(Color32::from_rgba_unmultiplied(214, 224, 238, 110)),
+            )
+            .selectable(false),
+        );
+    }
+
+    fn show_session_usage(&self, ui: &mut egui::Ui) {
+        if self.session_usage.is_empty() {
+            return;
+        }
+        ui.add_space(6.0);
+        ui.add(
+            egui::Label::new(
+                RichText::new(self.session_usage.summary())
+                    .color(Color32::from_rgba_unmultiplied(214, 224, 238, 120)),
+            )
+            .selectable(false),
+        )
+        .on_hover_text(self.session_usage.details());
+    }
+
+    fn show_batch_progress(&self, ui: &mut egui::Ui) {
+        let Some(batch) = self.batch.as_ref() else {
+            return;
+        };
+        ui.add_space(6.0);
+        ui.add(
+            egui::Label::new(
+                RichText::new(batch.progress())
+                    .color(Color32::from_rgba_unmultiplied(214, 224, 238, 120)),
+            )
+            .selectable(false),
+        )
+        .on_hover_text(format!(
+            "Running prompts from {} \u{2014} press {} to cancel the batch",
+            batch.name(),
+            self.shortcut_label(ShortcutAction::Cancel)
+        ));
+    }
+
+    fn show_session_indicator(&mut self, ui: &mut egui::Ui) {
+        let Some(session_id) = self.session_id.clone() else {
+            return;
+        };
+        ui.add_space(6.0);
+        let button = egui::Button::new(
+            RichText::new("session \u{25CF}")
+                .color(Color32::from_rgba_unmultiplied(214, 224, 238, 120)),
+        )
+        .fill(Color32::TRANSPARENT)
+        .stroke(egui::Stroke::NONE)
+        .corner_radius(egui::CornerRadius::same(255));
+        let mut action = None;
+        let menu = egui::menu::menu_custom_button(ui, button, |ui| {
+            if ui.button("Copy session id").clicked() {
+                action = Some(SessionMenuAction::CopyId);
+            }
+            if ui
+                .add_enabled(
+                    !self.busy,
+                    egui::Button::new("Start new session (keep transcript)"),
+                )
+                .clicked()
+            {
+                action = Some(SessionMenuAction::StartNew);
+            }
+            if action.is_some() {
+                ui.close_menu();
+            }
+        });
+        menu.response
+            .on_hover_text(format!("Session {}", short_session_id(&session_id)));
+        match action {
+            Some(SessionMenuAction::CopyId) => self.ctx.copy_text(session_id),
+            Some(SessionMenuAction::StartNew) => self.start_new_session(),
+            None => {}
+        }
+    }
+
+    fn show_log_health_indicator(&mut self, ui: &mut egui::Ui) {
+        if self.log_health.file_logging {
+            return;
+        }
+        ui.add_space(6.0);
+        let button = egui::Button::new(
+            RichText::new("\u{26A0}").color(Color32::from_rgba_unmultiplied(255, 196, 90, 220)),
+        )
+        .fill(Color32::TRANSPARENT)
+        .stroke(egui::Stroke::NONE)
+        .corner_radius(egui::CornerRadius::same(255));
+        let mut action = None;
+        let menu = egui::menu::menu_custom_button(ui, button, |ui| {
+            if ui.button("Retry file logging").clicked() {
+                action = Some(LogHealthAction::Retry);
+            }
+            if ui.button("Open log folder").clicked() {
+                action = Some(LogHealthAction::OpenFolder);
+            }
+            if action.is_some() {
+                ui.close_menu();
+            }
+        });
+        let mut hover = format!(
+            "File logging is off: {}",
+            self.log_health
+                .last_error
+                .as_deref()
+                .unwrap_or("unknown error")
+        );
+        for path in &self.log_health.log_files {
+            hover.push_str(&format!("\n{}", path.display()));
+        }
+        menu.response.on_hover_text(hover);
+        match action {
+            Some(LogHealthAction::Retry) => self.retry_file_logging(),
+            Some(LogHealthAction::OpenFolder) => self.open_log_folder(),
+            None => {}
+        }
+    }
+
+    fn show_agents_indicator(&mut self, ui: &mut egui::Ui) {
+        let (rect, response) = ui.allocate_exact_size(
+            egui::vec2(AGENTS_INDICATOR_WIDTH, CANCEL_BUTTON_HEIGHT),
+            egui::Sense::click(),
+        );
+        let files = self.agents_files_for_cwd();
+        let nearest = files.and_then(|files| files.first()).cloned();
+        response.widget_info(|| {
+            WidgetInfo::labeled(WidgetType::Button, nearest.is_some(), "Open AGENTS.md")
+        });
+        let color = if response.hovered() || response.has_focus() {
+            Color32::from_rgba_unmultiplied(214, 224, 238, 230)
+        } else {
+            Color32::from_rgba_unmultiplied(214, 224, 238, 150)
+        };
+        if nearest.is_some() {
+            ui.painter()
+                .circle_filled(rect.center(), AGENTS_INDICATOR_RADIUS, color);
+        } else {
+            ui.painter().circle_stroke(
+                rect.center(),
+                AGENTS_INDICATOR_RADIUS,
+                egui::Stroke::new(1.0, color),
+            );
+        }
+        let tooltip = match files {
+            None => "Checking for AGENTS.md".to_owned(),
+            Some([]) => "No AGENTS.md for this directory".to_owned(),
+            Some(files) => files
+                .iter()
+                .map(|path| path.display().to_string())
+                .collect::<Vec<_>>()
+                .join("\n"),
+        };
+        let mut response = response.on_hover_text(tooltip);
+        if let Some(path) = nearest {
+            response = response.on_hover_cursor(CursorIcon::PointingHand);
+            if response.clicked() {
+                self.open_project_file_editor(path);
+            }
+        }
+    }
+
+    fn show_settings_menu(&mut self, ui: &mut egui::Ui) {
+        let button = egui::Button::new(
+            RichText::new(tr(Text::Settings))
+                .color(Color32::from_rgba_unmultiplied(214, 224, 238, 170)),
+        )
+        .fill(Color32::TRANSPARENT)
+        .stroke(egui::Stroke::NONE)
+        .corner_radius(egui::CornerRadius::same(255));
+        let menu = egui::menu::menu_custom_button(ui, button, |ui| {
+            if !self.settings_menu_open {
+                self.refresh_current_model();
+                self.refresh_model_options();
+                self.refresh_codex_profiles();
+                self.refresh_notifications_enabled();
+                self.refresh_context_menu_state_async();
+                self.settings_menu_open = true;
+            }
+            ui.set_width(SETTINGS_MENU_WIDTH);
+            ui.scope(|ui| {
+                style_settings_menu_rows(ui);
+                ui.spacing_mut().menu_spacing = SETTINGS_SUBMENU_SPACING;
+                let close_model_menu = ui
+                    .menu_button(RichText::new("Model").monospace(), |ui| {
+                        ui.set_width(SETTINGS_SUBMENU_WIDTH);
+                        let mut close_parent = false;
+                        let mut selected_model: Option<String> = None;
+                        show_picker(ui, |ui| {
+                            ui.set_width(SETTINGS_SUBMENU_PICKER_WIDTH);
+                            for option in self.model_options.iter() {
+                                let active = option == &self.current_model;
+                                if show_picker_row(ui, option, "", false, active).clicked() {
+                                    if !active {
+                                        selected_model = Some(option.clone());
+                                    }
+                                    close_parent = true;
+                                }
+                            }
+                        });
+                        if let Some(model) = selected_model {
+                            self.select_model(&model);
+                        }
+                        close_parent
+                    })
+                    .inner
+                    .unwrap_or(false);
+                if close_model_menu {
+                    ui.close_menu();
+                }
+                if !self.codex_profiles.names.is_empty() {
+                    let close_profile_menu = ui
+                        .menu_button(RichText::new("Profile").monospace(), |ui| {
+                            ui.set_width(SETTINGS_SUBMENU_WIDTH);
+                            let mut close_parent = false;
+                            let mut selected: Option<Option<String>> = None;
+                            show_picker(ui, |ui| {
+                                ui.set_width(SETTINGS_SUBMENU_PICKER_WIDTH);
+                                let default = self.codex_profile.is_none();
+                                let description =
+                                    self.codex_profiles.default.as_deref().unwrap_or("");
+                                if show_picker_row(ui, "Default", description, false, default)
+                                    .clicked()
+                                {
+                                    if !default {
+                                        selected = Some(None);
+                                    }
+                                    close_parent = true;
+                                }
+                                for option in self.codex_profiles.names.iter() {
+                                    let active = self.codex_profile.as_ref() == Some(option);
+                                    if show_picker_row(ui, option, "", false, active).clicked() {
+                                        if !active {
+                                            selected = Some(Some(option.clone()));
+                                        }
+                                        close_parent = true;
+                                    }
+                                }
+                            });
+                            if let Some(profile) = selected {
+                                self.select_codex_profile(profile);
+                            }
+                            close_parent
+                        })
+                        .inner
+                        .unwrap_or(false);
+                    if close_profile_menu {
+                        ui.close_menu();
+                    }
+                }
+                let close_compare_menu = ui
+                    .menu_button(RichText::new("Compare").monospace(), |ui| {
+                        ui.set_width(SETTINGS_SUBMENU_WIDTH);
+                        let mut close_parent = false;
+                        let mut selected: Option<Option<String>> = None;
+                        show_picker(ui, |ui| {
+                            ui.set_width(SETTINGS_SUBMENU_PICKER_WIDTH);
+                            let off = self.compare_model.is_none();
+                            if show_picker_row(ui, "Off", "", false, off).clicked() {
+                                if !off {
+                                    selected = Some(None);
+                                }
+                                close_parent = true;
+                            }
+                            for option in self.model_options.iter() {
+                                if option == &self.current_model {
+                                    continue;
+                                }
+                                let active = self.compare_model.as_ref() == Some(option);
+                                if show_picker_row(ui, option, "", false, active).clicked() {
+                                    if !active {
+                                        selected = Some(Some(option.clone()));
+                                    }
+                                    close_parent = true;
+                                }
+                            }
+                        });
+                        if let Some(model) = selected {
+                            self.select_compare_model(model);
+                        }
+                        close_parent
+                    })
+                    .inner
+                    .unwrap_or(false);
+                if close_compare_menu {
+                    ui.close_menu();
+                }
+                let close_notification_menu = ui
+                    .menu_button(RichText::new("Notification").monospace(), |ui| {
+                        ui.set_width(SETTINGS_SUBMENU_WIDTH);
+                        let mut close_parent = false;
+                        show_picker(ui, |ui| {
+                            ui.set_width(SETTINGS_SUBMENU_PICKER_WIDTH);
+                            for option in NOTIFICATION_OPTIONS.iter() {
+                                let active = option.enabled == self.notifications_enabled;
+                                if show_picker_row(ui, option.name, "", false, active).clicked() {
+                                    if !active {
+                                        self.select_notification(option.enabled);
+                                    }
+                                    close_parent = true;
+                                }
+                            }
+                        });
+                        close_parent
+                    })
+                    .inner
+                    .unwrap_or(false);
+                if close_notification_menu {
+                    ui.close_menu();
+                }
+                let close_project_config_menu = ui
+                    .menu_button(RichText::new("Project Config").monospace(), |ui| {
+                        ui.set_width(SETTINGS_SUBMENU_WIDTH);
+                        let mut close_parent = false;
+                        show_picker(ui, |ui| {
+                            ui.set_width(SETTINGS_SUBMENU_PICKER_WIDTH);
+                            for option in NOTIFICATION_OPTIONS.iter() {
+                                let active = option.enabled == self.project_config_enabled;
+                                if show_picker_row(ui, option.name, "", false, active).clicked() {
+                                    if !active {
+                                        self.select_project_config(option.enabled);
+                                    }
+                                    close_parent = true;
+                                }
+                            }
+                        });
+                        close_parent
+                    })
+                    .inner
+                    .unwrap_or(false);
+                if close_project_config_menu {
+                    ui.close_menu();
+                }
+                let close_dirty_tree_menu = ui
+                    .menu_button(RichText::new("Dirty Tree Warning").monospace(), |ui| {
+                        ui.set_width(SETTINGS_SUBMENU_WIDTH);
+                        let mut close_parent = false;
+                        show_picker(ui, |ui| {
+                            ui.set_width(SETTINGS_SUBMENU_PICKER_WIDTH);
+                            for option in NOTIFICATION_OPTIONS.iter() {
+                                let active = option.enabled == self.dirty_tree_warning_enabled;
+                                if show_picker_row(ui, option.name, "", false, active).clicked() {
+                                    if !active {
+                                        self.select_dirty_tree_warning(option.enabled);
+                                    }
+                                    close_parent = true;
+                                }
+                            }
+                        });
+                        close_parent
+                    })
+                    .inner
+                    .unwrap_or(false);
+                if close_dirty_tree_menu {
+                    ui.close_menu();
+                }
+                let close_suggestions_menu = ui
+                    .menu_button(RichText::new("Follow-up Suggestions").monospace(), |ui| {
+                        ui.set_width(SETTINGS_SUBMENU_WIDTH);
+                        let mut close_parent = false;
+                        show_picker(ui, |ui| {
+                            ui.set_width(SETTINGS_SUBMENU_PICKER_WIDTH);
+                            for option in NOTIFICATION_OPTIONS.iter() {
+                                let active = option.enabled == self.suggestions_enabled;
+                                if show_picker_row(ui, option.name, "", false, active).clicked() {
+                                    if !active {
+                                        self.select_suggestions(option.enabled);
+                                    }
+                                    close_parent = true;
+                                }
+                            }
+                        });
+                        close_parent
+                    })
+                    .inner
+                    .unwrap_or(false);
+                if close_suggestions_menu {
+                    ui.close_menu();
+                }
+                let close_auto_continue_menu = ui
+                    .menu_button(RichText::new("Auto Continue").monospace(), |ui| {
+                        ui.set_width(SETTINGS_SUBMENU_WIDTH);
+                        let mut close_parent = false;
+                        show_picker(ui, |ui| {
+                            ui.set_width(SETTINGS_SUBMENU_PICKER_WIDTH);
+                            for option in NOTIFICATION_OPTIONS.iter() {
+                                let active = option.enabled == self.auto_continue;
+                                if show_picker_row(ui, option.name, "", false, active).clicked() {
+                                    if !active {
+                                        self.select_auto_continue(option.enabled);
+                                    }
+                                    close_parent = true;
+                                }
+                            }
+                        });
+                        close_parent
+                    })
+                    .inner
+                    .unwrap_or(false);
+                if close_auto_continue_menu {
+                    ui.close_menu();
+                }
+                let close_topic_menu = ui
+                    .menu_button(RichText::new("Topic Suggestions").monospace(), |ui| {
+                        ui.set_width(SETTINGS_SUBMENU_WIDTH);
+                        let mut close_parent = false;
+                        show_picker(ui, |ui| {
+                            ui.set_width(SETTINGS_SUBMENU_PICKER_WIDTH);
+                            for option in NOTIFICATION_OPTIONS.iter() {
+                                let active = option.enabled == self.topic_suggestions_enabled;
+                                if show_picker_row(ui, option.name, "", false, active).clicked() {
+                                    if !active {
+                                        self.select_topic_suggestions(option.enabled);
+                                    }
+                                    close_parent = true;
+                                }
+                            }
+                        });
+                        close_parent
+                    })
+                    .inner
+                    .unwrap_or(false);
+                if close_topic_menu {
+                    ui.close_menu();
+                }
+                let close_input_assist_menu = ui
+                    .menu_button(RichText::new("Input Assists").monospace(), |ui| {
+                        ui.set_width(SETTINGS_SUBMENU_WIDTH);
+                        let mut close_parent = false;
+                        show_picker(ui, |ui| {
+                            ui.set_width(SETTINGS_SUBMENU_PICKER_WIDTH);
+                            for assist in InputAssist::ALL {
+                                let active = self.input_assist_enabled(assist);
+                                if show_picker_row(ui, assist.label(), "", false, active).clicked()
+                                {
+                                    self.select_input_assist(assist, !active);
+                                    close_parent = true;
+                                }
+                            }
+                        });
+                        close_parent
+                    })
+                    .inner
+                    .unwrap_or(false);
+                if close_input_assist_menu {
+                    ui.close_menu();
+                }
+                let close_redact_menu = ui
+                    .menu_button(RichText::new("Redact Secrets").monospace(), |ui| {
+                        ui.set_width(SETTINGS_SUBMENU_WIDTH);
+                        let mut close_parent = false;
+                        show_picker(ui, |ui| {
+                            ui.set_width(SETTINGS_SUBMENU_PICKER_WIDTH);
+                            for option in NOTIFICATION_OPTIONS.iter() {
+                                let active = option.enabled == self.redact_secrets;
+                                if show_picker_row(ui, option.name, "", false, active).clicked() {
+                                    if !active {
+                                        self.select_redact_secrets(option.enabled);
+                                    }
+                                    close_parent = true;
+                                }
+                            }
+                        });
+                        close_parent
+                    })
+                    .inner
+                    .unwrap_or(false);
+                if close_redact_menu {
+                    ui.close_menu();
+                }
+                let close_transform_menu = ui
+                    .menu_button(RichText::new("Prompt Transforms").monospace(), |ui| {
+                        ui.set_width(SETTINGS_SUBMENU_WIDTH);
+                        let mut close_parent = false;
+                        show_picker(ui, |ui| {
+                            ui.set_width(SETTINGS_SUBMENU_PICKER_WIDTH);
+                            for transformer in TRANSFORMERS {
+                                let active = self.prompt_transformers.contains(&transformer);
+                                if show_picker_row(
+                                    ui,
+                                    transformer.name(),
+                                    transformer.description(),
+                                    false,
+                                    active,
+                                )
+                                .clicked()
+                                {
+                                    self.select_prompt_transformer(transformer, !active);
+                                    close_parent = true;
+                                }
+                            }
+                        });
+                        close_parent
+                    })
+                    .inner
+                    .unwrap_or(false);
+                if close_transform_menu {
+                    ui.close_menu();
+                }
+                let close_processor_menu = ui
+                    .menu_button(RichText::new("Copy & Export Cleanup").monospace(), |ui| {
+                        ui.set_width(SETTINGS_SUBMENU_WIDTH);
+                        let mut close_parent = false;
+                        show_picker(ui, |ui| {
+                            ui.set_width(SETTINGS_SUBMENU_PICKER_WIDTH);
+                            for processor in OUTPUT_PROCESSORS {
+                                let active = self.output_processors.contains(&processor);
+                                if show_picker_row(
+                                    ui,
+                                    processor.name(),
+                                    processor.description(),
+                                    false,
+                                    active,
+                                )
+                                .clicked()
+                                {
+                                    self.select_output_processor(processor, !active);
+                                    close_parent = true;
+                                }
+                            }
+                        });
+                        close_parent
+                    })
+                    .inner
+                    .unwrap_or(false);
+                if close_processor_menu {
+                    ui.close_menu();
+                }
+                let close_clipboard_menu = ui
+                    .menu_button(RichText::new("Clipboard Quick Action").monospace(), |ui| {
+                        ui.set_width(SETTINGS_SUBMENU_WIDTH);
+                        let mut close_parent = false;
+                        show_picker(ui, |ui| {
+                            ui.set_width(SETTINGS_SUBMENU_PICKER_WIDTH);
+                            for option in NOTIFICATION_OPTIONS.iter() {
+                                let active = option.enabled == self.clipboard_watch;
+                                if show_picker_row(ui, option.name, "", false, active)
+                                    .on_hover_text(
+                                        "Watch the clipboard and offer to ask about newly copied text",
+                                    )
+                                    .clicked()
+                                {
+                                    if !active {
+                                        self.select_clipboard_watch(option.enabled);
+                                    }
+                                    close_parent = true;
+                                }
+                            }
+                        });
+                        close_parent
+                    })
+                    .inner
+                    .unwrap_or(false);
+                if close_clipboard_menu {
+                    ui.close_menu();
+                }
+                let close_warm_start_menu = ui
+                    .menu_button(RichText::new("Warm Start").monospace(), |ui| {
+                        ui.set_width(SETTINGS_SUBMENU_WIDTH);
+                        let mut close_parent = false;
+                        show_picker(ui, |ui| {
+                            ui.set_width(SETTINGS_SUBMENU_PICKER_WIDTH);
+                            for option in NOTIFICATION_OPTIONS.iter() {
+                                let active = option.enabled == self.warm_start;
+                                if show_picker_row(ui, option.name, "", false, active)
+                                    .on_hover_text(
+                                        "Keep a codex process waiting for the next prompt to cut time to first output",
+                                    )
+                                    .clicked()
+                                {
+                                    if !active {
+                                        self.select_warm_start(option.enabled);
+                                    }
+                                    close_parent = true;
+                                }
+                            }
+                        });
+                        close_parent
+                    })
+                    .inner
+                    .unwrap_or(false);
+                if close_warm_start_menu {
+                    ui.close_menu();
+                }
+                let close_codex_titles_menu = ui
+                    .menu_button(RichText::new("Codex Titles").monospace(), |ui| {
+                        ui.set_width(SETTINGS_SUBMENU_WIDTH);
+                        let mut close_parent = false;
+                        show_picker(ui, |ui| {
+                            ui.set_width(SETTINGS_SUBMENU_PICKER_WIDTH);
+                            for option in NOTIFICATION_OPTIONS.iter() {
+                                let active = option.enabled == self.codex_titles;
+                                if show_picker_row(ui, option.name, "", false, active)
+                                    .on_hover_text(
+                                        "After the first answer, ask codex for a short session title",
+                                    )
+                                    .clicked()
+                                {
+                                    if !active {
+                                        self.select_codex_titles(option.enabled);
+                                    }
+                                    close_parent = true;
+                                }
+                            }
+                        });
+                        close_parent
+                    })
+                    .inner
+                    .unwrap_or(false);
+                if close_codex_titles_menu {
+                    ui.close_menu();
+                }
+                let close_shadow_menu = ui
+                    .menu_button(RichText::new("Window Glow").monospace(), |ui| {
+                        ui.set_width(SETTINGS_SUBMENU_WIDTH);
+                        let mut close_parent = false;
+                        show_picker(ui, |ui| {
+                            ui.set_width(SETTINGS_SUBMENU_PICKER_WIDTH);
+                            for option in NOTIFICATION_OPTIONS.iter() {
+                                let active = option.enabled == self.window_shadow.enabled;
+                                if show_picker_row(ui, option.name, "", false, active).clicked() {
+                                    if !active {
+                                        self.select_window_shadow(option.enabled);
+                                    }
+                                    close_parent = true;
+                                }
+                            }
+                        });
+                        close_parent
+                    })
+                    .inner
+                    .unwrap_or(false);
+                if close_shadow_menu {
+                    ui.close_menu();
+                }
+                let close_compact_menu = ui
+                    .menu_button(RichText::new("Compact Mode").monospace(), |ui| {
+                        ui.set_width(SETTINGS_SUBMENU_WIDTH);
+                        let mut close_parent = false;
+                        show_picker(ui, |ui| {
+                            ui.set_width(SETTINGS_SUBMENU_PICKER_WIDTH);
+                            for option in NOTIFICATION_OPTIONS.iter() {
+                                let active = option.enabled == self.compact_mode;
+                                if show_picker_row(ui, option.name, "", false, active).clicked() {
+                                    if !active {
+                                        self.select_compact_mode(option.enabled);
+                                    }
+                                    close_parent = true;
+                                }
+                            }
+                        });
+                        close_parent
+                    })
+                    .inner
+                    .unwrap_or(false);
+                if close_compact_menu {
+                    ui.close_menu();
+                }
+                let close_capture_menu = ui
+                    .menu_button(RichText::new("Hide From Capture").monospace(), |ui| {
+                        ui.set_width(SETTINGS_SUBMENU_WIDTH);
+                        let mut close_parent = false;
+                        show_picker(ui, |ui| {
+                            ui.set_width(SETTINGS_SUBMENU_PICKER_WIDTH);
+                            for option in NOTIFICATION_OPTIONS.iter() {
+                                let active = option.enabled == self.exclude_from_capture;
+                                if show_picker_row(ui, option.name, "", false, active).clicked() {
+                                    if !active {
+                                        self.select_exclude_from_capture(option.enabled);
+                                    }
+                                    close_parent = true;
+                                }
+                            }
+                        });
+                        if let Some(error) = self.capture_exclusion_error.as_deref() {
+                            ui.add(
+                                egui::Label::new(
+                                    RichText::new(format!("Unavailable: {}", error))
+                                        .small()
+                                        .color(Color32::from_rgb(240, 150, 150)),
+                                )
+                                .wrap(),
+                            );
+                        }
+                        close_parent
+                    })
+                    .inner
+                    .unwrap_or(false);
+                if close_capture_menu {
+                    ui.close_menu();
+                }
+                let close_submit_key_menu = ui
+                    .menu_button(RichText::new("Submit Key").monospace(), |ui| {
+                        ui.set_width(SETTINGS_SUBMENU_WIDTH);
+                        let mut close_parent = false;
+                        show_picker(ui, |ui| {
+                            ui.set_width(SETTINGS_SUBMENU_PICKER_WIDTH);
+                            for key in SubmitKey::ALL {
+                                let active = key == self.submit_key;
+                                if show_picker_row(ui, key.label(), key.hint(), false, active)
+                                    .clicked()
+                                {
+                                    if !active {
+                                        self.select_submit_key(key);
+                                    }
+                                    close_parent = true;
+                                }
+                            }
+                        });
+                        close_parent
+                    })
+                    .inner
+                    .unwrap_or(false);
+                if close_submit_key_menu {
+                    ui.close_menu();
+                }
+                let close_whitespace_menu = ui
+                    .menu_button(RichText::new("Prompt Whitespace").monospace(), |ui| {
+                        ui.set_width(SETTINGS_SUBMENU_WIDTH);
+                        let mut close_parent = false;
+                        show_picker(ui, |ui| {
+                            ui.set_width(SETTINGS_SUBMENU_PICKER_WIDTH);
+                            for mode in PromptWhitespace::ALL {
+                                let active = mode == self.prompt_whitespace;
+                                if show_picker_row(ui, mode.label(), mode.hint(), false, active)
+                                    .clicked()
+                                {
+                                    if !active {
+                                        self.select_prompt_whitespace(mode);
+                                    }
+                                    close_parent = true;
+                                }
+                            }
+                        });
+                        close_parent
+                    })
+                    .inner
+                    .unwrap_or(false);
+                if close_whitespace_menu {
+                    ui.close_menu();
+                }
+                let close_token_menu = ui
+                    .menu_button(RichText::new("Large Prompt Warning").monospace(), |ui| {
+                        ui.set_width(SETTINGS_SUBMENU_WIDTH);
+                        let mut close_parent = false;
+                        show_picker(ui, |ui| {
+                            ui.set_width(SETTINGS_SUBMENU_PICKER_WIDTH);
+                            for option in TOKEN_LIMIT_OPTIONS.iter() {
+                                let active = option.limit == self.token_warning_limit;
+                                if show_picker_row(ui, option.name, "", false, active).clicked() {
+                                    if !active {
+                                        self.select_token_warning_limit(option.limit);
+                                    }
+                                    close_parent = true;
+                                }
+                            }
+                        });
+                        close_parent
+                    })
+                    .inner
+                    .unwrap_or(false);
+                if close_token_menu {
+                    ui.close_menu();
+                }
+                ui.menu_button(RichText::new("Shortcuts").monospace(), |ui| {
+                    ui.set_width(SETTINGS_SUBMENU_WIDTH);
+                    show_picker(ui, |ui| {
+                        ui.set_width(SETTINGS_SUBMENU_PICKER_WIDTH);
+                        for action in ShortcutAction::ALL {
+                            let capturing = self.shortcut_capture == Some(action);
+                            let label = self.shortcut_label(action);
+                            if show_picker_row(ui, action.label(), &label, capturing, false)
+                                .on_hover_text("Click, then press the new key combination")
+                                .clicked()
+                            {
+                                self.toggle_shortcut_capture(action);
+                            }
+                        }
+                        if show_picker_row(ui, "Reset to defaults", "", false, false).clicked() {
+                            self.restore_default_shortcuts();
+                        }
+                    });
+                });
+                let close_attach_menu = ui
+                    .menu_button(RichText::new("Attach Long Prompts").monospace(), |ui| {
+                        ui.set_width(SETTINGS_SUBMENU_WIDTH);
+                        let mut close_parent = false;
+                        show_picker(ui, |ui| {
+                            ui.set_width(SETTINGS_SUBMENU_PICKER_WIDTH);
+                            for option in PROMPT_ATTACH_OPTIONS.iter() {
+                                let active = option.limit == self.prompt_attach_limit;
+                                if show_picker_row(ui, option.name, "", false, active).clicked() {
+                                    if !active {
+                                        self.select_prompt_attach_limit(option.limit);
+                                    }
+                                    close_parent = true;
+                                }
+                            }
+                        });
+                        close_parent
+                    })
+                    .inner
+                    .unwrap_or(false);
+                if close_attach_menu {
+                    ui.close_menu();
+                }
+                let close_language_menu = ui
+                    .menu_button(RichText::new("Response Language").monospace(), |ui| {
+                        ui.set_width(SETTINGS_SUBMENU_WIDTH);
+                        let mut close_parent = false;
+                        let pinned = self.pinned_response_language.clone();
+                        let mut languages: Vec<String> = pinned.iter().cloned().collect();
+                        if let Some(language) = self.response_language.clone()
+                            && !languages.contains(&language)
+                        {
+                            languages.push(language);
+                        }
+                        show_picker(ui, |ui| {
+                            ui.set_width(SETTINGS_SUBMENU_PICKER_WIDTH);
+                            if show_picker_row(ui, "Reset On Clear", "", false, pinned.is_none())
+                                .clicked()
+                            {
+                                self.select_response_language_pin(None);
+                                close_parent = true;
+                            }
+                            for language in languages {
+                                let active = pinned.as_ref() == Some(&language);
+                                let name = format!("Keep {}", language);
+                                if show_picker_row(ui, &name, "", false, active).clicked() {
+                                    self.select_response_language_pin(Some(language));
+                                    close_parent = true;
+                                }
+                            }
+                        });
+                        close_parent
+                    })
+                    .inner
+                    .unwrap_or(false);
+                if close_language_menu {
+                    ui.close_menu();
+                }
+                let close_locale_menu = ui
+                    .menu_button(RichText::new("Interface Language").monospace(), |ui| {
+                        ui.set_width(SETTINGS_SUBMENU_WIDTH);
+                        let mut close_parent = false;
+                        let pinned = pinned_locale();
+                        show_picker(ui, |ui| {
+                            ui.set_width(SETTINGS_SUBMENU_PICKER_WIDTH);
+                            if show_picker_row(ui, "System default", "", false, pinned.is_none())
+                                .clicked()
+                            {
+                                self.select_interface_locale(None);
+                                close_parent = true;
+                            }
+                            for catalog in CATALOGS.iter() {
+                                let active = pinned == Some(catalog.tag);
+                                if show_picker_row(ui, catalog.name, "", false, active).clicked() {
+                                    self.select_interface_locale(Some(catalog.tag));
+                                    close_parent = true;
+                                }
+                            }
+                        });
+                        close_parent
+                    })
+                    .inner
+                    .unwrap_or(false);
+                if close_locale_menu {
+                    ui.close_menu();
+                }
+                let close_concise_menu = ui
+                    .menu_button(RichText::new("Concise Answers").monospace(), |ui| {
+                        ui.set_width(SETTINGS_SUBMENU_WIDTH);
+                        let mut close_parent = false;
+                        show_picker(ui, |ui| {
+                            ui.set_width(SETTINGS_SUBMENU_PICKER_WIDTH);
+                            for option in NOTIFICATION_OPTIONS.iter() {
+                                let active = option.enabled == self.concise_default;
+                                if show_picker_row(ui, option.name, "", false, active).clicked() {
+                                    if !active {
+                                        self.select_concise_default(option.enabled);
+                                    }
+                                    close_parent = true;
+                                }
+                            }
+                        });
+                        close_parent
+                    })
+                    .inner
+                    .unwrap_or(false);
+                if close_concise_menu {
+                    ui.close_menu();
+                }
+                let close_wake_menu = ui
+                    .menu_button(RichText::new("Keep Awake").monospace(), |ui| {
                         ui.set_width(SETTINGS_SUBMENU_WIDTH);
                         let mut close_parent = false;
-                        let mut selected_model: Option<String> = None;
                         show_picker(ui, |ui| {
                             ui.set_width(SETTINGS_SUBMENU_PICKER_WIDTH);
-                            for option in self.model_options.iter() {
-                                let active = option == &self.current_model;
-                                if show_picker_row(ui, option, "", false, active).clicked() {
+                            for mode in WakeMode::ALL {
+                                let active = mode == self.wake_mode;
+                                if show_picker_row(ui, mode.label(), "", false, active).clicked() {
                                     if !active {
-                                        selected_model = Some(option.clone());
+                                        self.select_wake_mode(mode);
                                     }
                                     close_parent = true;
                                 }
                             }
+                            if show_picker_row(
+                                ui,
+                                "Cancel prompt on sleep",
+                                "Stop codex cleanly before the machine suspends",
+                                false,
+                                self.cancel_on_sleep,
+                            )
+                            .clicked()
+                            {
+                                self.select_cancel_on_sleep(!self.cancel_on_sleep);
+                                close_parent = true;
+                            }
                         });
-                        if let Some(model) = selected_model {
-                            self.select_model(&model);
-                        }
                         close_parent
                     })
                     .inner
                     .unwrap_or(false);
-                if close_model_menu {
+                if close_wake_menu {
                     ui.close_menu();
                 }
-                let close_notification_menu = ui
-                    .menu_button(RichText::new("Notification").monospace(), |ui| {
+                let close_theme_menu = ui
+                    .menu_button(RichText::new("Theme").monospace(), |ui| {
                         ui.set_width(SETTINGS_SUBMENU_WIDTH);
                         let mut close_parent = false;
                         show_picker(ui, |ui| {
                             ui.set_width(SETTINGS_SUBMENU_PICKER_WIDTH);
-                            for option in NOTIFICATION_OPTIONS.iter() {
-                                let active = option.enabled == self.notifications_enabled;
-                                if show_picker_row(ui, option.name, "", false, active).clicked() {
+                            for theme in Theme::ALL {
+                                let active = theme == self.theme;
+                                if show_picker_row(
+                                    ui,
+                                    theme.label(),
+                                    theme.description(),
+                                    false,
+                                    active,
+                                )
+                                .clicked()
+                                {
                                     if !active {
-                                        self.select_notification(option.enabled);
+                                        self.select_theme(theme);
                                     }
                                     close_parent = true;
                                 }
@@ -334,7 +3228,61 @@ impl CodexAgentApp {
                     })
                     .inner
                     .unwrap_or(false);
-                if close_notification_menu {
+                if close_theme_menu {
+                    ui.close_menu();
+                }
+                let close_dimming_menu = ui
+                    .menu_button(RichText::new("Dim Old Responses").monospace(), |ui| {
+                        ui.set_width(SETTINGS_SUBMENU_WIDTH);
+                        let mut close_parent = false;
+                        show_picker(ui, |ui| {
+                            ui.set_width(SETTINGS_SUBMENU_PICKER_WIDTH);
+                            for dimming in ResponseDimming::ALL {
+                                let active = dimming == self.response_dimming;
+                                if show_picker_row(
+                                    ui,
+                                    dimming.label(),
+                                    dimming.description(),
+                                    false,
+                                    active,
+                                )
+                                .clicked()
+                                {
+                                    if !active {
+                                        self.select_response_dimming(dimming);
+                                    }
+                                    close_parent = true;
+                                }
+                            }
+                        });
+                        close_parent
+                    })
+                    .inner
+                    .unwrap_or(false);
+                if close_dimming_menu {
+                    ui.close_menu();
+                }
+                let close_pending_menu = ui
+                    .menu_button(RichText::new("Pending Animation").monospace(), |ui| {
+                        ui.set_width(SETTINGS_SUBMENU_WIDTH);
+                        let mut close_parent = false;
+                        show_picker(ui, |ui| {
+                            ui.set_width(SETTINGS_SUBMENU_PICKER_WIDTH);
+                            for style in PendingStyle::ALL {
+                                let active = style == self.pending_style;
+                                if show_picker_row(ui, style.label(), "", false, active).clicked() {
+                                    if !active {
+                                        self.select_pending_style(style);
+                                    }
+                                    close_parent = true;
+                                }
+                            }
+                        });
+                        close_parent
+                    })
+                    .inner
+                    .unwrap_or(false);
+                if close_pending_menu {
                     ui.close_menu();
                 }
                 let close_context_menu = ui
@@ -359,6 +3307,174 @@ impl CodexAgentApp {
                 if close_context_menu {
                     ui.close_menu();
                 }
+                let close_codex_install_menu = ui
+                    .menu_button(RichText::new("Codex Install").monospace(), |ui| {
+                        ui.set_width(SETTINGS_SUBMENU_WIDTH);
+                        let mut close_parent = false;
+                        show_picker(ui, |ui| {
+                            ui.set_width(SETTINGS_SUBMENU_PICKER_WIDTH);
+                            if show_picker_row(ui, "Re-detect", "Find codex again", false, false)
+                                .clicked()
+                            {
+                                self.redetect_codex_install();
+                                close_parent = true;
+                            }
+                            let native = self.launcher_mode == LauncherMode::Native;
+                            if show_picker_row(ui, "Native", "Run codex on Windows", false, native)
+                                .clicked()
+                            {
+                                self.select_launcher_mode(LauncherMode::Native);
+                                close_parent = true;
+                            }
+                            let wsl_mode = match &self.launcher_mode {
+                                LauncherMode::Wsl(distro) => LauncherMode::Wsl(distro.clone()),
+                                LauncherMode::Native => LauncherMode::Wsl(None),
+                            };
+                            if show_picker_row(
+                                ui,
+                                &wsl_mode.label(),
+                                "Run codex inside WSL",
+                                false,
+                                !native,
+                            )
+                            .clicked()
+                            {
+                                self.select_launcher_mode(wsl_mode);
+                                close_parent = true;
+                            }
+                        });
+                        close_parent
+                    })
+                    .inner
+                    .unwrap_or(false);
+                if close_codex_install_menu {
+                    ui.close_menu();
+                }
+                let close_diagnostics_menu = ui
+                    .menu_button(RichText::new("Diagnostics").monospace(), |ui| {
+                        ui.set_width(SETTINGS_SUBMENU_WIDTH);
+                        let mut close_parent = false;
+                        show_picker(ui, |ui| {
+                            ui.set_width(SETTINGS_SUBMENU_PICKER_WIDTH);
+                            if show_picker_row(ui, "Open logs", "Error and trace logs", false, false)
+                                .clicked()
+                            {
+                                self.clear_picker_selection();
+                                self.open_log_folder();
+                                close_parent = true;
+                            }
+                            if show_picker_row(
+                                ui,
+                                "Open data folder",
+                                "Settings, history and saved sessions",
+                                false,
+                                false,
+                            )
+                            .clicked()
+                            {
+                                self.clear_picker_selection();
+                                self.open_data_folder();
+                                close_parent = true;
+                            }
+                            if show_picker_row(
+                                ui,
+                                "Do not persist transcripts",
+                                "No history, draft, snapshots or event recordings on disk",
+                                false,
+                                !self.persist_transcripts,
+                            )
+                            .clicked()
+                            {
+                                self.select_persist_transcripts(!self.persist_transcripts);
+                                close_parent = true;
+                            }
+                            if show_picker_row(
+                                ui,
+                                "Keep raw events",
+                                "Last prompt only, in memory, for /inspect",
+                                false,
+                                self.inspect_events,
+                            )
+                            .clicked()
+                            {
+                                self.select_inspect_events(!self.inspect_events);
+                                close_parent = true;
+                            }
+                            if self.inspect_events
+                                && show_picker_row(
+                                    ui,
+                                    "Inspect events",
+                                    "Raw codex events from the last prompt",
+                                    false,
+                                    false,
+                                )
+                                .clicked()
+                            {
+                                self.inspect_events_from_menu();
+                                close_parent = true;
+                            }
+                            if show_picker_row(
+                                ui,
+                                "Copy diagnostics",
+                                "Versions, settings and recent errors for a bug report",
+                                false,
+                                false,
+                            )
+                            .clicked()
+                            {
+                                self.copy_diagnostics();
+                                close_parent = true;
+                            }
+                        });
+                        close_parent
+                    })
+                    .inner
+                    .unwrap_or(false);
+                if close_diagnostics_menu {
+                    ui.close_menu();
+                }
+                let close_codex_files_menu = ui
+                    .menu_button(RichText::new("Codex Files").monospace(), |ui| {
+                        ui.set_width(SETTINGS_SUBMENU_WIDTH);
+                        let mut close_parent = false;
+                        show_picker(ui, |ui| {
+                            ui.set_width(SETTINGS_SUBMENU_PICKER_WIDTH);
+                            for (file, description) in [
+                                (CodexFile::Config, "Model and sandbox defaults"),
+                                (CodexFile::Agents, "Standing instructions"),
+                            ] {
+                                if show_picker_row(ui, file.file_name(), description, false, false)
+                                    .clicked()
+                                {
+                                    self.open_codex_file_editor(file);
+                                    close_parent = true;
+                                }
+                            }
+                            let settings_file = &paths::get().settings_file;
+                            let settings_label = settings_file
+                                .file_name()
+                                .map(|name| name.to_string_lossy())
+                                .unwrap_or_default();
+                            if show_picker_row(
+                                ui,
+                                &settings_label,
+                                "App settings and model prices",
+                                false,
+                                false,
+                            )
+                            .clicked()
+                            {
+                                self.open_project_file_editor(settings_file.clone());
+                                close_parent = true;
+                            }
+                        });
+                        close_parent
+                    })
+                    .inner
+                    .unwrap_or(false);
+                if close_codex_files_menu {
+                    ui.close_menu();
+                }
             });
         });
         let response = menu.response;
@@ -380,37 +3496,97 @@ impl eframe::App for CodexAgentApp {
         Color32::TRANSPARENT.to_normalized_gamma_f32()
     }
 
-    fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+    fn update(&mut self, ctx: &egui::Context, frame: &mut eframe::Frame) {
+        let _frame_path = FramePath::enter();
+        self.record_frame(frame);
         self.poll();
+        self.pump_batch();
+        self.sync_window_title();
+        self.expire_clipboard_offer();
+        self.sync_warm_codex();
+        self.trace_state_transitions();
+        self.autosave_snapshot();
+        self.publish_relaunch_snapshot();
+        self.autosave_recent_session();
+        self.persist_draft(false);
+        self.sync_pixels_per_point();
 
-        if !self.positioned {
-            if let Some(monitor) = ctx.input(|i| i.viewport().monitor_size) {
-                ctx.send_viewport_cmd(egui::ViewportCommand::OuterPosition(
-                    startup_outer_position(monitor),
-                ));
-                self.positioned = true;
-            }
+        if !self.positioned
+            && let Some(monitor) = ctx.input(|i| i.viewport().monitor_size)
+        {
+            send_viewport_command(
+                ctx,
+                egui::ViewportCommand::OuterPosition(startup_outer_position(monitor)),
+            );
+            self.positioned = true;
         }
 
         if ctx.input(|input| input.viewport().close_requested()) {
+            self.persist_draft(true);
             if self.busy {
                 self.cancel_active_prompt();
             }
+            self.cancel_title_request();
+            self.remove_prompt_attachments();
             notify::cleanup(self.hwnd);
             return;
         }
 
-        if ctx.input(|input| input.key_pressed(Key::Escape)) {
-            if self.busy {
-                self.cancel_active_prompt();
+        self.triggered_shortcut = self.read_shortcut();
+        if self.shortcut_triggered(ShortcutAction::Cancel) {
+            if self.codex_file_editor_open() {
+                self.close_codex_file_editor();
+            } else if self.event_inspector_open() {
+                self.close_event_inspector();
+            } else if self.recent_sessions_open() {
+                self.close_recent_sessions();
+            } else if self.notes_focused() {
+                self.close_notes();
+            } else if self.find_focused() {
+                self.close_find();
+            } else if self.clear_confirmation.is_some() {
+                self.abort_clear();
+            } else if self.secret_submit_confirmation.is_some() {
+                self.abort_secret_submit();
+            } else if self.dirty_submit_confirmation.is_some() {
+                self.abort_dirty_submit();
+            } else if self.project_trust_confirmation {
+                self.abort_project_trust();
+            } else if self.token_submit_confirmation.is_some() {
+                self.abort_token_submit();
+            } else if self.busy {
+                self.cancel(CancelScope::Active);
+            } else if self.batch.is_some() {
+                logging::trace(
+                    "escape ignored between batch prompts; use Cancel all to stop the batch",
+                );
             } else {
-                ctx.send_viewport_cmd(egui::ViewportCommand::Close);
+                self.persist_draft(true);
+                send_viewport_command(ctx, egui::ViewportCommand::Close);
             }
             return;
         }
 
+        match self.triggered_shortcut {
+            Some(ShortcutAction::NextError) => self.focus_next_error(true),
+            Some(ShortcutAction::PreviousError) => self.focus_next_error(false),
+            Some(ShortcutAction::NewTopic) => {
+                self.start_new_topic();
+            }
+            Some(ShortcutAction::CopyPlainText) => self.copy_plain_text(self.output_edit_id),
+            Some(ShortcutAction::ToggleNotes) => self.toggle_notes(),
+            Some(ShortcutAction::Find) => {
+                let selection = self
+                    .output_edit_id
+                    .and_then(|id| self.output_selection_text(id));
+                self.open_find(selection);
+            }
+            _ => {}
+        }
+        self.merge_locked_typing();
+
         let focused = ctx.input(|input| input.focused);
-        if focused && !self.was_focused {
+        if focused && !self.was_focused && !self.was_minimized {
             self.pending_input_focus = true;
         }
         if focused {
@@ -419,12 +3595,19 @@ impl eframe::App for CodexAgentApp {
         self.was_focused = focused;
 
         if self.resizing && ctx.input(|input| !input.pointer.primary_down()) {
-            let size = ctx.screen_rect().size();
+            let screen_size = ctx.screen_rect().size();
             let resized = self
                 .resize_start_inner_size
                 .take()
-                .is_some_and(|start| !Self::same_size(Some(start), size));
+                .is_some_and(|start| !Self::same_size(Some(start), screen_size));
             self.resizing = false;
+            self.resize_wrap_width.release();
+            let max_height = self.max_window_height();
+            let size =
+                self.sanitize_window_size(egui::vec2(screen_size.x, screen_size.y.min(max_height)));
+            if !Self::same_size(Some(size), screen_size) {
+                send_viewport_command(ctx, egui::ViewportCommand::InnerSize(size));
+            }
             self.last_inner_size = Some(size);
             self.user_height_override = if resized || self.user_height_override.is_some() {
                 Some(size.y)
@@ -440,6 +3623,11 @@ impl eframe::App for CodexAgentApp {
         }
 
         self.sync_windows_tiling();
+        self.sync_visible_bounds();
+        self.flush_pending_resize();
+
+        self.request_cwd_probes();
+        self.request_disk_space_probe();
 
         if let Some(delay) = self.pending_repaint_delay() {
             ctx.request_repaint_after(delay);
@@ -451,50 +3639,54 @@ impl eframe::App for CodexAgentApp {
 
         egui::CentralPanel::default()
             .frame(egui::Frame::NONE.inner_margin(egui::Margin {
-                left: WINDOW_PADDING as i8,
-                right: WINDOW_PADDING as i8,
-                top: WINDOW_PADDING as i8,
-                bottom: WINDOW_BOTTOM_PADDING as i8,
+                left: self.window_shadow.padding() as i8,
+                right: self.window_shadow.padding() as i8,
+                top: self.window_shadow.padding() as i8,
+                bottom: self.window_shadow.bottom_padding() as i8,
             }))
             .show(ctx, |ui| {
                 let glow = self.glow_palette();
                 ui.set_min_size(ui.available_size());
                 let resize_rect = ui.max_rect();
                 let card_response = egui::Frame::new()
-                    .fill(Color32::from_rgba_unmultiplied(14, 18, 24, 204))
+                    .fill(self.card_fill())
                     .stroke(egui::Stroke::new(1.0, glow.stroke))
                     .corner_radius(egui::CornerRadius::same(18))
                     .inner_margin(egui::Margin::symmetric(18, 10))
-                    .shadow(egui::epaint::Shadow {
-                        offset: [0, 0],
-                        blur: 32,
-                        spread: 3,
-                        color: glow.shadow,
-                    })
+                    .shadow(self.card_shadow(glow.shadow))
                     .show(ui, |ui| {
                         ui.style_mut().spacing.item_spacing.y = 0.0;
                         let mut cancel = false;
+                        let mut cancel_all = false;
                         let mut clear = false;
                         let mut minimize = false;
                         let mut maximize = false;
                         let mut close = false;
                         ui.horizontal(|ui| {
                             ui.set_min_height(CANCEL_BUTTON_HEIGHT);
-                            ui.add(
-                                egui::Label::new(
-                                    RichText::new(self.cwd_text.as_str())
-                                        .color(Color32::from_rgba_unmultiplied(214, 224, 238, 150)),
-                                )
-                                .selectable(false),
-                            );
+                            self.show_cwd_label(ui);
+                            self.show_agents_indicator(ui);
+                            self.show_git_info(ui);
+                            self.show_session_usage(ui);
+                            self.show_batch_progress(ui);
+                            self.show_session_indicator(ui);
+                            self.show_log_health_indicator(ui);
                             ui.add_space(10.0);
                             self.show_status_button(ui);
+                            self.show_notes_button(ui);
                             self.show_settings_menu(ui);
                             let titlebar_w =
                                 TITLEBAR_BUTTON_SIZE * 3.0 + TITLEBAR_BUTTON_SPACING * 2.0;
                             let action_w = if self.busy || self.can_clear() {
                                 if self.busy {
                                     CANCEL_BUSY_BUTTON_WIDTH
+                                        + PROMPT_PHASE_LABEL_WIDTH
+                                        + self.stream_rate_width()
+                                        + if self.can_cancel_all() {
+                                            CANCEL_MENU_BUTTON_WIDTH
+                                        } else {
+                                            0.0
+                                        }
                                 } else {
                                     CANCEL_BUTTON_WIDTH
                                 }
@@ -503,21 +3695,33 @@ impl eframe::App for CodexAgentApp {
                             };
                             ui.add_space((ui.available_width() - action_w - titlebar_w).max(0.0));
                             if self.busy {
+                                self.show_stream_rate(ui);
+                                self.show_prompt_phase(ui);
                                 cancel = egui::Frame::new()
                                     .corner_radius(egui::CornerRadius::same(255))
-                                    .shadow(egui::epaint::Shadow {
-                                        offset: [0, 0],
-                                        blur: 12,
-                                        spread: 2,
-                                        color: Color32::from_rgba_unmultiplied(255, 30, 30, 60),
+                                    .shadow(if self.high_contrast() {
+                                        egui::epaint::Shadow::NONE
+                                    } else {
+                                        egui::epaint::Shadow {
+                                            offset: [0, 0],
+                                            blur: 12,
+                                            spread: 2,
+                                            color: Color32::from_rgba_unmultiplied(255, 30, 30, 60),
+                                        }
                                     })
                                     .show(ui, |ui| {
                                         ui.spacing_mut().button_padding = egui::vec2(14.0, 4.0);
-                                        let resp = ui.add(
+                                        let cancelling = self.cancelling_prompt.is_some();
+                                        let resp = ui.add_enabled(
+                                            !cancelling,
                                             egui::Button::new(
-                                                RichText::new("Cancel")
-                                                    .strong()
-                                                    .color(Color32::WHITE),
+                                                RichText::new(tr(if cancelling {
+                                                    Text::Cancelling
+                                                } else {
+                                                    Text::Cancel
+                                                }))
+                                                .strong()
+                                                .color(Color32::WHITE),
                                             )
                                             .min_size(egui::vec2(
                                                 CANCEL_BUSY_BUTTON_WIDTH,
@@ -534,20 +3738,46 @@ impl eframe::App for CodexAgentApp {
                                                 Color32::from_rgba_unmultiplied(255, 40, 40, 25),
                                             );
                                         }
-                                        resp.on_hover_cursor(egui::CursorIcon::PointingHand)
-                                            .clicked()
+                                        let clicked = resp
+                                            .on_hover_cursor(egui::CursorIcon::PointingHand)
+                                            .on_hover_text("Cancel the running prompt")
+                                            .clicked();
+                                        if self.can_cancel_all() {
+                                            let queued = self.queued_prompts();
+                                            ui.spacing_mut().button_padding = egui::vec2(4.0, 4.0);
+                                            ui.menu_button(
+                                                RichText::new("\u{25BE}")
+                                                    .strong()
+                                                    .color(Color32::WHITE),
+                                                |ui| {
+                                                    let label = match queued {
+                                                        0 => "Cancel all".to_owned(),
+                                                        queued => format!(
+                                                            "Cancel all ({} queued)",
+                                                            queued
+                                                        ),
+                                                    };
+                                                    if ui.button(label).clicked() {
+                                                        cancel_all = true;
+                                                        ui.close_menu();
+                                                    }
+                                                },
+                                            )
+                                            .response
+                                            .on_hover_text("Cancel the batch too");
+                                        }
+                                        clicked
                                     })
                                     .inner;
                             } else if self.can_clear() {
                                 let resp = ui.add(
-                                    egui::Button::new(RichText::new("Clear").color(Color32::WHITE))
-                                        .min_size(egui::vec2(
-                                            CANCEL_BUTTON_WIDTH,
-                                            CANCEL_BUTTON_HEIGHT,
-                                        ))
-                                        .fill(Color32::TRANSPARENT)
-                                        .stroke(egui::Stroke::NONE)
-                                        .corner_radius(egui::CornerRadius::same(255)),
+                                    egui::Button::new(
+                                        RichText::new(tr(Text::Clear)).color(Color32::WHITE),
+                                    )
+                                    .min_size(egui::vec2(CANCEL_BUTTON_WIDTH, CANCEL_BUTTON_HEIGHT))
+                                    .fill(Color32::TRANSPARENT)
+                                    .stroke(egui::Stroke::NONE)
+                                    .corner_radius(egui::CornerRadius::same(255)),
                                 );
                                 if resp.hovered() {
                                     ui.painter().rect_filled(
@@ -563,7 +3793,10 @@ impl eframe::App for CodexAgentApp {
                             let btn = egui::vec2(TITLEBAR_BUTTON_SIZE, TITLEBAR_BUTTON_SIZE);
                             let (min_rect, min_resp) =
                                 ui.allocate_exact_size(btn, egui::Sense::click());
-                            if min_resp.hovered() {
+                            min_resp.widget_info(|| {
+                                WidgetInfo::labeled(WidgetType::Button, true, "Minimize")
+                            });
+                            if min_resp.hovered() || min_resp.has_focus() {
                                 ui.painter().rect_filled(
                                     min_rect,
                                     4.0,
@@ -582,7 +3815,15 @@ impl eframe::App for CodexAgentApp {
                             ui.add_space(TITLEBAR_BUTTON_SPACING);
                             let (max_rect, max_resp) =
                                 ui.allocate_exact_size(btn, egui::Sense::click());
-                            if max_resp.hovered() {
+                            let max_label = if self.maximized {
+                                "Restore"
+                            } else {
+                                "Maximize"
+                            };
+                            max_resp.widget_info(|| {
+                                WidgetInfo::labeled(WidgetType::Button, true, max_label)
+                            });
+                            if max_resp.hovered() || max_resp.has_focus() {
                                 ui.painter().rect_filled(
                                     max_rect,
                                     4.0,
@@ -636,7 +3877,10 @@ impl eframe::App for CodexAgentApp {
                             ui.add_space(TITLEBAR_BUTTON_SPACING);
                             let (cls_rect, cls_resp) =
                                 ui.allocate_exact_size(btn, egui::Sense::click());
-                            if cls_resp.hovered() {
+                            cls_resp.widget_info(|| {
+                                WidgetInfo::labeled(WidgetType::Button, true, "Close")
+                            });
+                            if cls_resp.hovered() || cls_resp.has_focus() {
                                 ui.painter().rect_filled(
                                     cls_rect,
                                     4.0,
@@ -665,14 +3909,17 @@ impl eframe::App for CodexAgentApp {
                             close = cls_resp.on_hover_cursor(CursorIcon::PointingHand).clicked();
                         });
                         if cancel {
-                            self.cancel_active_prompt();
+                            self.cancel(CancelScope::Active);
+                        }
+                        if cancel_all {
+                            self.cancel(CancelScope::All);
                         }
                         if clear {
-                            self.clear_session();
+                            self.request_clear();
                         }
                         if minimize {
                             self.prepare_for_minimize_from_ctx();
-                            ctx.send_viewport_cmd(egui::ViewportCommand::Minimized(true));
+                            send_viewport_command(ctx, egui::ViewportCommand::Minimized(true));
                         }
                         if maximize {
                             let next_maximized = !ctx.input(|input| {
@@ -695,15 +3942,21 @@ impl eframe::App for CodexAgentApp {
                                     });
                             }
                             self.maximized = next_maximized;
-                            ctx.send_viewport_cmd(egui::ViewportCommand::Maximized(next_maximized));
+                            send_viewport_command(
+                                ctx,
+                                egui::ViewportCommand::Maximized(next_maximized),
+                            );
                             if next_maximized {
                                 self.user_height_override = Some(ctx.screen_rect().height());
                             } else if let Some(state) = self.pre_maximize_state.take() {
-                                self.user_height_override = state.user_height_override;
-                                self.last_inner_size = Some(state.inner_size);
-                                ctx.send_viewport_cmd(egui::ViewportCommand::InnerSize(
-                                    state.inner_size,
-                                ));
+                                let inner_size = self.sanitize_window_size(state.inner_size);
+                                self.user_height_override =
+                                    self.sanitize_height_override(state.user_height_override);
+                                self.last_inner_size = Some(inner_size);
+                                send_viewport_command(
+                                    ctx,
+                                    egui::ViewportCommand::InnerSize(inner_size),
+                                );
                                 self.invalidate_text_layout();
                             } else {
                                 self.user_height_override = None;
@@ -715,10 +3968,39 @@ impl eframe::App for CodexAgentApp {
                             if self.busy {
                                 self.cancel_active_prompt();
                             }
-                            ctx.send_viewport_cmd(egui::ViewportCommand::Close);
+                            send_viewport_command(ctx, egui::ViewportCommand::Close);
                         }
                         ui.add_space(6.0);
-                        let content_width = ui.available_width();
+                        let content_width = self.content_wrap_width(ui.available_width());
+                        if self.codex_file_editor_open() {
+                            self.resize_for_text_with_width(
+                                content_width,
+                                self.auto_resize_height_limit(),
+                            );
+                            self.show_codex_file_editor(ui, glow.separator);
+                            return;
+                        }
+                        if self.event_inspector_open() {
+                            self.resize_for_text_with_width(
+                                content_width,
+                                self.auto_resize_height_limit(),
+                            );
+                            self.show_event_inspector(ui, glow.separator);
+                            return;
+                        }
+                        if self.recent_sessions_open() {
+                            self.resize_for_text_with_width(
+                                content_width,
+                                self.auto_resize_height_limit(),
+                            );
+                            self.show_recent_sessions(ui, glow.separator);
+                            return;
+                        }
+                        self.show_codex_file_update_notice(ui);
+                        self.show_environment_notice(ui);
+                        self.show_disk_space_warning(ui);
+                        self.show_onboarding(ui);
+                        self.show_find_bar(ui);
                         let (output_rows, input_rows) = self.display_rows_for_width(content_width);
                         self.resize_for_text_with_width(
                             content_width,
@@ -727,12 +4009,23 @@ impl eframe::App for CodexAgentApp {
                         let input_h = self.input_height_cache;
                         let output_h = self.output_height_cache.max(LINE_HEIGHT);
                         if output_rows > 0 {
+                            let mut output_menu_action = None;
+                            let mut output_target = None;
+                            let output_menu_busy = self.busy;
+                            let output_has_response = !self.last_response_text().is_empty();
+                            let output_has_events = self.has_inspectable_events();
                             let output_height = if self.user_height_override.is_some() {
                                 let available = ui.available_height();
-                                (available - input_h - 9.0).max(LINE_HEIGHT)
+                                (available
+                                    - input_h
+                                    - self.notes_height()
+                                    - self.find_bar_height()
+                                    - 9.0)
+                                    .max(LINE_HEIGHT)
                             } else {
                                 output_h
                             };
+                            let mut clicked_output_char = None;
                             ui.scope(|ui| {
                                 ui.visuals_mut().override_text_color = Some(Color32::WHITE);
                                 let mut scroll = egui::ScrollArea::vertical()
@@ -742,24 +4035,30 @@ impl eframe::App for CodexAgentApp {
                                 if self.user_height_override.is_some() {
                                     scroll = scroll.auto_shrink([true, false]);
                                 }
-                                scroll.show(ui, |ui| {
+                                let scroll_output = scroll.show(ui, |ui| {
                                     ui.style_mut().override_font_id =
                                         Some(FontId::proportional(TEXT_FONT_SIZE));
-                                    let wrap_width = ui.available_width();
+                                    let wrap_width = self.output_wrap_width(content_width);
                                     self.sync_output_galley(wrap_width);
                                     let prompt_ranges = &self.output_display_prompt_ranges;
                                     let output_base = self.output_display_response_start;
                                     let line_kinds = &self.output_display_line_kinds;
                                     let output_galley = self.output_galley.clone();
                                     let output_galley_width = self.output_galley_width;
+                                    let high_contrast = self.high_contrast();
                                     let output_display_buffer = &mut self.output_display_buffer;
-                                    let mut layouter = cached_markdown_layouter(
+                                    let mut cached_layouter = cached_markdown_layouter(
                                         output_galley.clone(),
                                         output_galley_width,
                                         prompt_ranges,
                                         output_base,
                                         line_kinds,
+                                        high_contrast,
+                                        self.response_dimming,
                                     );
+                                    let mut layouter = move |ui: &egui::Ui, text: &str, _: f32| {
+                                        cached_layouter(ui, text, wrap_width)
+                                    };
                                     let output_edit = TextEdit::multiline(output_display_buffer)
                                         .id_source("output-display")
                                         .desired_width(f32::INFINITY)
@@ -767,6 +4066,92 @@ impl eframe::App for CodexAgentApp {
                                         .layouter(&mut layouter)
                                         .frame(false)
                                         .show(ui);
+                                    drop(layouter);
+                                    if self.busy
+                                        && !self.render_buffer.is_empty()
+                                        && let Some(row) = output_edit.galley.rows.last()
+                                    {
+                                        let format = pending_status_format(
+                                            high_contrast,
+                                            self.response_dimming,
+                                        );
+                                        ui.painter().text(
+                                            output_edit.galley_pos + row.rect.min.to_vec2(),
+                                            egui::Align2::LEFT_TOP,
+                                            &self.render_buffer,
+                                            format.font_id,
+                                            format.color,
+                                        );
+                                    }
+                                    output_edit.response.widget_info(|| {
+                                        WidgetInfo::labeled(
+                                            WidgetType::TextEdit,
+                                            true,
+                                            "Conversation transcript",
+                                        )
+                                    });
+                                    let has_selection = output_edit
+                                        .cursor_range
+                                        .is_some_and(|range| !range.is_empty());
+                                    output_edit.response.context_menu(|ui| {
+                                        output_menu_action = show_output_menu(
+                                            ui,
+                                            has_selection,
+                                            output_has_response,
+                                            output_has_events,
+                                            output_menu_busy,
+                                        );
+                                    });
+                                    if output_edit.response.has_focus()
+                                        && self.shortcut_triggered(ShortcutAction::OutputMenu)
+                                    {
+                                        self.output_menu_pos = Some(
+                                            output_edit.response.rect.left_top()
+                                                + egui::vec2(12.0, 12.0),
+                                        );
+                                    }
+                                    output_target = Some((output_edit.response.id, has_selection));
+                                    self.output_edit_id = Some(output_edit.response.id);
+                                    if output_edit.response.clicked() && !has_selection {
+                                        clicked_output_char = output_edit
+                                            .cursor_range
+                                            .map(|range| range.primary.ccursor.index);
+                                    }
+                                    let focused_error = self
+                                        .error_focus
+                                        .and_then(|index| self.error_fold(index))
+                                        .filter(|_| self.pending_error_scroll);
+                                    if let Some(region) = focused_error {
+                                        let index = self.output_display_buffer
+                                            [..region.clean_start]
+                                            .chars()
+                                            .count();
+                                        let rect = output_edit
+                                            .galley
+                                            .pos_from_ccursor(egui::text::CCursor::new(index))
+                                            .translate(output_edit.galley_pos.to_vec2());
+                                        ui.scroll_to_rect(rect, Some(egui::Align::Center));
+                                        self.pending_error_scroll = false;
+                                    }
+                                    let found = self
+                                        .current_find_match()
+                                        .filter(|_| self.find.pending_scroll);
+                                    if let Some((start, end)) = found {
+                                        let mut state = output_edit.state.clone();
+                                        state.cursor.set_char_range(Some(
+                                            egui::text::CCursorRange::two(
+                                                egui::text::CCursor::new(start),
+                                                egui::text::CCursor::new(end),
+                                            ),
+                                        ));
+                                        state.store(ui.ctx(), output_edit.response.id);
+                                        let rect = output_edit
+                                            .galley
+                                            .pos_from_ccursor(egui::text::CCursor::new(start))
+                                            .translate(output_edit.galley_pos.to_vec2());
+                                        ui.scroll_to_rect(rect, Some(egui::Align::Center));
+                                        self.find.pending_scroll = false;
+                                    }
                                     if output_galley.is_some() {
                                         if let Some(y) = self.output_separator_y {
                                             let sep_rect = egui::Rect::from_min_size(
@@ -778,9 +4163,82 @@ impl eframe::App for CodexAgentApp {
                                             );
                                             ui.painter().rect_filled(sep_rect, 0.0, glow.separator);
                                         }
+                                        let rect = output_edit.response.rect;
+                                        for (y, label) in self.output_turn_markers.iter() {
+                                            let y = output_edit.galley_pos.y + y;
+                                            if y < ui.clip_rect().top() - LINE_HEIGHT
+                                                || y > ui.clip_rect().bottom() + LINE_HEIGHT
+                                            {
+                                                continue;
+                                            }
+                                            let label = ui.painter().text(
+                                                egui::pos2(rect.right() - 2.0, y),
+                                                egui::Align2::RIGHT_CENTER,
+                                                label,
+                                                FontId::proportional(TURN_MARKER_FONT_SIZE),
+                                                Color32::from_rgba_unmultiplied(214, 224, 238, 90),
+                                            );
+                                            ui.painter().hline(
+                                                rect.left()..=label.left() - 6.0,
+                                                y,
+                                                egui::Stroke::new(1.0, glow.separator),
+                                            );
+                                        }
+                                        self.show_prompt_copy_button(
+                                            ui,
+                                            output_edit.galley_pos,
+                                            rect,
+                                        );
+                                        self.show_pin_buttons(ui, output_edit.galley_pos, rect);
+                                        self.show_rating_buttons(ui, output_edit.galley_pos, rect);
                                     }
                                 });
+                                self.set_output_scrollable(
+                                    scroll_output.content_size.y
+                                        > scroll_output.inner_rect.height() + 1.0,
+                                );
                             });
+                            if let Some(index) = clicked_output_char {
+                                let offset = self
+                                    .output_display_buffer
+                                    .char_indices()
+                                    .nth(index)
+                                    .map_or(self.output_display_buffer.len(), |(offset, _)| offset);
+                                self.toggle_fold(offset);
+                            }
+                            if let Some((output_id, has_selection)) = output_target {
+                                if let Some(pos) = self.output_menu_pos {
+                                    let area =
+                                        egui::Area::new(egui::Id::new("output-keyboard-menu"))
+                                            .order(egui::Order::Foreground)
+                                            .fixed_pos(pos)
+                                            .show(ui.ctx(), |ui| {
+                                                egui::Frame::menu(ui.style())
+                                                    .show(ui, |ui| {
+                                                        show_output_menu(
+                                                            ui,
+                                                            has_selection,
+                                                            output_has_response,
+                                                            output_has_events,
+                                                            output_menu_busy,
+                                                        )
+                                                    })
+                                                    .inner
+                                            });
+                                    if area.inner.is_some() {
+                                        output_menu_action = area.inner;
+                                    }
+                                    if area.inner.is_some()
+                                        || area.response.clicked_elsewhere()
+                                        || ui.input(|input| input.key_pressed(Key::Escape))
+                                    {
+                                        self.output_menu_pos = None;
+                                    }
+                                }
+                                if let Some(action) = output_menu_action {
+                                    self.apply_output_menu_action(action, output_id);
+                                }
+                            }
                             ui.add_space(4.0);
                             let (sep_rect, _) = ui.allocate_exact_size(
                                 egui::vec2(ui.available_width(), 1.0),
@@ -789,6 +4247,7 @@ impl eframe::App for CodexAgentApp {
                             ui.painter().rect_filled(sep_rect, 0.0, glow.separator);
                             ui.add_space(4.0);
                         }
+                        self.show_notes(ui, glow.separator);
                         if matches!(self.setup_state, SetupState::InstallFailed(_)) {
                             ui.add_space(8.0);
                             ui.horizontal(|ui| {
@@ -818,9 +4277,38 @@ impl eframe::App for CodexAgentApp {
                             });
                             ui.add_space(4.0);
                         }
+                        self.show_approval_request(ui);
+                        self.show_changed_files(ui);
+                        self.show_response_diff_toggle(ui);
+                        self.show_follow_up_suggestions(ui);
+                        self.show_secret_submit_confirmation(ui);
+                        self.show_clear_confirmation(ui);
+                        self.show_dirty_submit_confirmation(ui);
+                        self.show_project_trust_confirmation(ui);
+                        self.show_prompt_prefix_chip(ui);
+                        self.show_response_language_chip(ui);
+                        self.show_concise_chip(ui);
+                        self.show_paste_fence_offer(ui);
+                        self.show_clipboard_offer(ui);
+                        self.show_sleep_retry(ui);
+                        self.show_tool_retry(ui);
+                        self.show_topic_shift_suggestion(ui);
+                        let input_was_empty = self.input.is_empty();
+                        self.normalize_pasted_line_endings();
+                        self.buffer_locked_typing();
+                        let locked = self.input_locked();
+                        let shown_input = locked.then(|| self.shown_input().into_owned());
+                        let tab_indents = self.input_tab_indents();
+                        if tab_indents {
+                            self.indent_input_in_fence();
+                        }
+                        let enter_in_fence = self.enter_in_fence();
                         let input_edit = ui
                             .scope(|ui| {
                                 ui.visuals_mut().override_text_color = Some(Color32::WHITE);
+                                if locked {
+                                    ui.multiply_opacity(LOCKED_INPUT_OPACITY);
+                                }
                                 egui::ScrollArea::vertical()
                                     .id_salt(PROMPT_SCROLL_ID)
                                     .stick_to_bottom(true)
@@ -828,27 +4316,42 @@ impl eframe::App for CodexAgentApp {
                                     .show(ui, |ui| {
                                         ui.style_mut().override_font_id =
                                             Some(FontId::proportional(TEXT_FONT_SIZE));
-                                        let mut layouter =
-                                            |ui: &egui::Ui, text: &str, width: f32| {
-                                                let job =
-                                                    markdown_layout_job(text, width, &[], 0, &[]);
-                                                ui.fonts(|fonts| fonts.layout_job(job))
-                                            };
-                                        TextEdit::multiline(&mut self.input)
+                                        let mut layouter = cached_markdown_layouter(
+                                            self.input_galley.clone(),
+                                            self.input_galley_width,
+                                            &[],
+                                            0,
+                                            &[],
+                                            self.high_contrast(),
+                                            self.response_dimming,
+                                        );
+                                        let mut locked_text = shown_input.as_deref().unwrap_or("");
+                                        let text: &mut dyn egui::TextBuffer = if locked {
+                                            &mut locked_text
+                                        } else {
+                                            &mut self.input
+                                        };
+                                        TextEdit::multiline(text)
                                             .id_source(Self::INPUT_ID)
                                             .desired_width(f32::INFINITY)
                                             .desired_rows(input_rows)
-                                            .interactive(
-                                                !self.locked
-                                                    || matches!(
-                                                        self.setup_state,
-                                                        SetupState::Installing
-                                                    ),
-                                            )
-                                            .return_key(KeyboardShortcut::new(
-                                                Modifiers::SHIFT,
-                                                Key::Enter,
-                                            ))
+                                            .interactive(!locked)
+                                            .return_key(match self.submit_key {
+                                                SubmitKey::Enter if !enter_in_fence => {
+                                                    KeyboardShortcut::new(
+                                                        Modifiers::SHIFT,
+                                                        Key::Enter,
+                                                    )
+                                                }
+                                                SubmitKey::Enter | SubmitKey::CtrlEnter => {
+                                                    KeyboardShortcut::new(
+                                                        Modifiers::NONE,
+                                                        Key::Enter,
+                                                    )
+                                                }
+                                            })
+                                            .lock_focus(tab_indents)
+                                            .hint_text(self.submit_key.hint())
                                             .layouter(&mut layouter)
                                             .frame(false)
                                             .show(ui)
@@ -857,6 +4360,12 @@ impl eframe::App for CodexAgentApp {
                             })
                             .inner;
                         let response = input_edit.response;
+                        response.widget_info(|| {
+                            WidgetInfo::labeled(WidgetType::TextEdit, !self.locked, "Prompt input")
+                        });
+                        if locked {
+                            self.show_input_lock(ui, response.rect);
+                        }
                         let raw_input_rows = input_edit.galley.rows.len().max(1);
                         let visible_row_limit = self.visible_row_limit();
                         let max_input_rows = if output_rows > 0 {
@@ -866,6 +4375,12 @@ impl eframe::App for CodexAgentApp {
                         };
                         let expected_input_rows = raw_input_rows.min(max_input_rows);
                         let input_needs_growth = expected_input_rows > input_rows;
+                        if response.changed() {
+                            self.apply_input_assists(input_was_empty);
+                            self.refresh_topic_shift();
+                            self.mark_draft_changed();
+                            self.token_submit_confirmation = None;
+                        }
                         if response.changed() || input_needs_growth {
                             self.clear_picker_selection();
                             self.reset_prompt_history_navigation();
@@ -875,6 +4390,7 @@ impl eframe::App for CodexAgentApp {
                                 self.auto_resize_height_limit(),
                             );
                         }
+                        self.show_token_estimate(ui);
                         if self.slash_command_count() > 0 {
                             ui.add_space(6.0);
                             show_picker(ui, |ui| {
@@ -909,22 +4425,162 @@ impl eframe::App for CodexAgentApp {
                             && !self.locked
                             && !picker_handled
                             && self.handle_prompt_history_keys(ctx);
+                        let (enter_submit, composing) = ui.input(|input| {
+                            enter_submits(
+                                &input.events,
+                                self.ime_composing,
+                                self.submit_key,
+                                enter_in_fence,
+                            )
+                        });
+                        self.ime_composing = composing;
                         let submit = response.has_focus()
                             && !picker_handled
                             && !history_handled
-                            && ui.input(|input| {
-                                input.key_pressed(Key::Enter) && !input.modifiers.shift
-                            });
+                            && enter_submit;
 
                         if submit && matches!(self.setup_state, SetupState::Installing) {
                             self.send_install_input();
                         } else if submit && !self.busy && !self.locked {
                             self.submit();
                         }
+                        self.show_status_strip(ui, enter_in_fence);
                     });
                 let card_rect = card_response.response.rect;
                 let drag_rect = card_rect.shrink2(egui::vec2(18.0, 8.0));
                 self.update_window_drag(resize_rect, drag_rect, self.output_rows_cache > 0);
+                self.sync_mouse_passthrough(card_rect);
+                self.refocus_input_on_card_click(card_rect);
             });
+        self.note_own_clipboard_copies(ctx);
+    }
+}
+
+fn show_input_chip(ui: &mut egui::Ui, text: &str, hover: &str) {
+    egui::Frame::new()
+        .fill(Color32::from_rgba_unmultiplied(124, 189, 255, 24))
+        .stroke(egui::Stroke::new(
+            1.0,
+            Color32::from_rgba_unmultiplied(124, 189, 255, 60),
+        ))
+        .corner_radius(egui::CornerRadius::same(255))
+        .inner_margin(egui::Margin::symmetric(8, 0))
+        .show(ui, |ui| {
+            ui.set_height(PROMPT_PREFIX_CHIP_HEIGHT);
+            ui.add(
+                egui::Label::new(
+                    RichText::new(text)
+                        .small()
+                        .color(Color32::from_rgba_unmultiplied(214, 224, 238, 190)),
+                )
+                .truncate()
+                .selectable(false),
+            )
+            .on_hover_text(hover);
+        });
+    ui.add_space(PROMPT_PREFIX_CHIP_SPACING);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(key: Key, modifiers: Modifiers) -> egui::Event {
+        egui::Event::Key {
+            key,
+            physical_key: None,
+            pressed: true,
+            repeat: false,
+            modifiers,
+        }
+    }
+
+    fn outside_fence(
+        events: &[egui::Event],
+        composing: bool,
+        submit_key: SubmitKey,
+    ) -> (bool, bool) {
+        enter_submits(events, composing, submit_key, false)
+    }
+
+    fn enter() -> egui::Event {
+        key(Key::Enter, Modifiers::NONE)
+    }
+
+    fn preedit(text: &str) -> egui::Event {
+        egui::Event::Ime(egui::ImeEvent::Preedit(text.to_owned()))
+    }
+
+    fn commit(text: &str) -> egui::Event {
+        egui::Event::Ime(egui::ImeEvent::Commit(text.to_owned()))
+    }
+
+    #[test]
+    fn plain_outside_fence() {
+        assert_eq!(
+            outside_fence(&[enter()], false, SubmitKey::Enter),
+            (true, false)
+        );
+        assert_eq!(
+            outside_fence(
+                &[key(Key::Enter, Modifiers::SHIFT)],
+                false,
+                SubmitKey::Enter
+            ),
+            (false, false)
+        );
+        assert_eq!(
+            outside_fence(&[enter()], false, SubmitKey::CtrlEnter),
+            (false, false)
+        );
+        assert_eq!(
+            outside_fence(
+                &[key(Key::Enter, Modifiers::COMMAND)],
+                false,
+                SubmitKey::CtrlEnter
+            ),
+            (true, false)
+        );
+    }
+
+    #[test]
+    fn enter_during_a_composition_does_not_submit() {
+        assert_eq!(
+            outside_fence(&[preedit("にほ"), enter()], false, SubmitKey::Enter),
+            (false, true)
+        );
+        // A composition started in an earlier frame is still pending.
+        assert_eq!(
+            outside_fence(&[enter()], true, SubmitKey::Enter),
+            (false, true)
+        );
+    }
+
+    #[test]
+    fn enter_that_commits_the_composition_does_not_submit() {
+        assert_eq!(
+            outside_fence(&[commit("日本"), enter()], true, SubmitKey::Enter),
+            (false, false)
+        );
+        assert_eq!(
+            outside_fence(&[enter()], false, SubmitKey::Enter),
+            (true, false)
+        );
+    }
+
+    #[test]
+    fn cleared_or_disabled_composition_allows_submit() {
+        assert_eq!(
+            outside_fence(&[preedit(""), enter()], true, SubmitKey::Enter),
+            (true, false)
+        );
+        assert_eq!(
+            outside_fence(
+                &[egui::Event::Ime(egui::ImeEvent::Disabled), enter()],
+                true,
+                SubmitKey::Enter
+            ),
+            (true, false)
+        );
     }
 }
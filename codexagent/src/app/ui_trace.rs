@@ -0,0 +1,116 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+
+use eframe::egui;
+
+use crate::logging;
+
+use super::CodexAgentApp;
+
+const UI_TRACE_ENV: &str = "AUTOAGENT_UI_TRACE";
+const UI_TRACE_INTERVAL: Duration = Duration::from_millis(100);
+const TRACED_FLAGS: [&str; 4] = ["busy", "locked", "resizing", "window_dragging"];
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+type TraceLimits = HashMap<&'static str, (Option<Instant>, u32)>;
+
+static LIMITS: Mutex<Option<TraceLimits>> = Mutex::new(None);
+static FLAGS: Mutex<Option<[bool; 4]>> = Mutex::new(None);
+
+pub(super) fn configure(setting_enabled: bool) {
+    let env_enabled = std::env::var_os(UI_TRACE_ENV).is_some_and(|value| value == "1");
+    let enabled = env_enabled || setting_enabled;
+    ENABLED.store(enabled, Ordering::Relaxed);
+    if enabled {
+        logging::trace(format!(
+            "ui trace enabled via {}",
+            if env_enabled { UI_TRACE_ENV } else { "setting" }
+        ));
+    }
+}
+
+#[inline]
+pub(super) fn enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+}
+
+fn emit(category: &'static str, message: impl FnOnce() -> String) {
+    let now = Instant::now();
+    let suppressed = {
+        let mut limits = LIMITS.lock().unwrap_or_else(|e| e.into_inner());
+        let entry = limits
+            .get_or_insert_with(HashMap::new)
+            .entry(category)
+            .or_insert((None, 0));
+        if entry
+            .0
+            .is_some_and(|last| now.duration_since(last) < UI_TRACE_INTERVAL)
+        {
+            entry.1 += 1;
+            return;
+        }
+        let suppressed = entry.1;
+        *entry = (Some(now), 0);
+        suppressed
+    };
+    if suppressed > 0 {
+        logging::trace(format!(
+            "ui {}: {} ({} suppressed)",
+            category,
+            message(),
+            suppressed
+        ));
+    } else {
+        logging::trace(format!("ui {}: {}", category, message()));
+    }
+}
+
+pub(super) fn send_viewport_command(ctx: &egui::Context, command: egui::ViewportCommand) {
+    if enabled() {
+        emit("viewport", || format!("{:?}", command));
+    }
+    ctx.send_viewport_cmd(command);
+}
+
+pub(super) fn trace_focus(target: &'static str, grabbed: bool) {
+    if enabled() {
+        emit("focus", || {
+            format!("{} {}", if grabbed { "grab" } else { "release" }, target)
+        });
+    }
+}
+
+pub(super) fn trace_focus_change(message: impl FnOnce() -> String) {
+    if enabled() {
+        emit("focus", message);
+    }
+}
+
+pub(super) fn trace_resize(message: impl FnOnce() -> String) {
+    if enabled() {
+        emit("resize", message);
+    }
+}
+
+impl CodexAgentApp {
+    pub(super) fn trace_state_transitions(&self) {
+        if !enabled() {
+            return;
+        }
+        let flags = [self.busy, self.locked, self.resizing, self.window_dragging];
+        let previous = FLAGS
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .replace(flags);
+        let Some(previous) = previous.filter(|previous| *previous != flags) else {
+            return;
+        };
+        for ((name, before), after) in TRACED_FLAGS.iter().zip(previous).zip(flags) {
+            if before != after {
+                logging::trace(format!("ui state: {} {} -> {}", name, before, after));
+            }
+        }
+    }
+}
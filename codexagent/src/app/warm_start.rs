@@ -0,0 +1,100 @@
+use std::time::Instant;
+
+use crate::config::set_warm_start;
+use crate::logging;
+use crate::prompt::{WARM_CODEX_MAX_AGE, discard_warm_codex_async, prewarm_codex};
+
+use super::CodexAgentApp;
+
+#[derive(PartialEq)]
+struct WarmTarget {
+    session_id: Option<String>,
+    cwd: String,
+    profile: Option<String>,
+}
+
+pub(super) struct WarmCodexState {
+    target: WarmTarget,
+    warmed_at: Instant,
+}
+
+impl CodexAgentApp {
+    fn warm_target(&self) -> WarmTarget {
+        WarmTarget {
+            session_id: self.session_id.clone(),
+            cwd: self.cwd_text.clone(),
+            profile: self.codex_profile.clone(),
+        }
+    }
+
+    pub(super) fn sync_warm_codex(&mut self) {
+        if !self.warm_start
+            || self.busy
+            || self.locked
+            || self.onboarding.is_some()
+            || self.rate_limit_wait.is_some()
+        {
+            return;
+        }
+        let target = self.warm_target();
+        if self
+            .warm_codex
+            .as_ref()
+            .is_some_and(|warm| warm.target == target)
+        {
+            return;
+        }
+        logging::trace("warming a codex process for the next prompt");
+        let project = self.refresh_project_config();
+        prewarm_codex(target.session_id.clone(), project);
+        self.warm_codex = Some(WarmCodexState {
+            target,
+            warmed_at: Instant::now(),
+        });
+    }
+
+    pub(super) fn note_warm_codex_activity(&mut self) {
+        if self
+            .warm_codex
+            .as_ref()
+            .is_some_and(|warm| warm.warmed_at.elapsed() >= WARM_CODEX_MAX_AGE)
+        {
+            self.warm_codex = None;
+        }
+    }
+
+    pub(super) fn drop_warm_codex(&mut self, reason: &'static str) {
+        self.warm_codex = None;
+        discard_warm_codex_async(reason);
+    }
+
+    pub(super) fn select_warm_start(&mut self, enabled: bool) {
+        self.clear_picker_selection();
+        if self.warm_start == enabled {
+            return;
+        }
+        match set_warm_start(enabled) {
+            Ok(enabled) => {
+                self.warm_start = enabled;
+                if !enabled {
+                    self.drop_warm_codex("warm start was turned off");
+                }
+                self.push_settings_output(if enabled {
+                    "Warm start set to On \u{2014} a codex process waits for the next prompt"
+                } else {
+                    "Warm start set to Off"
+                });
+                self.finish_local_success();
+            }
+            Err(error) => {
+                logging::error(format!(
+                    "failed to set warm start {}: {}",
+                    if enabled { "on" } else { "off" },
+                    error
+                ));
+                self.push_local_error(&format!("Failed to set warm start: {}", error));
+            }
+        }
+        self.finish_local_change();
+    }
+}
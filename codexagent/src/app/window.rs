@@ -1,18 +1,30 @@
 #[cfg(target_os = "windows")]
 use std::mem;
+use std::time::{Duration, Instant};
 
-use eframe::egui::{self, CursorIcon, Rect, Vec2};
+use eframe::egui::{self, CursorIcon, Pos2, Rect, Vec2};
 
 use crate::config::{MIN_WINDOW_HEIGHT, MIN_WINDOW_WIDTH, RESIZE_HANDLE_SIZE};
 use crate::logging;
 
-use super::{CodexAgentApp, MonitorKey, TileCell, TiledWindowState, WindowRestoreState};
+use super::ui_trace::{send_viewport_command, trace_focus_change};
+use super::{CodexAgentApp, MonitorKey, WindowRestoreState};
+#[cfg(target_os = "windows")]
+use super::{TileCell, TiledWindowState};
 
 const BOTTOM_RESIZE_OFFSET: f32 = 0.0;
+#[cfg(target_os = "windows")]
 const TILE_GRID_DIMENSION: i32 = 4;
+#[cfg(target_os = "windows")]
 const TILE_SNAP_TOLERANCE: i32 = 56;
+#[cfg(target_os = "windows")]
 const TILE_RELEASE_TOLERANCE: i32 = 84;
+#[cfg(target_os = "windows")]
 const TILE_RECT_TOLERANCE: i32 = 2;
+const BOUNDS_CHECK_INTERVAL: Duration = Duration::from_secs(3);
+const MONITOR_RESIZE_RATIO: f32 = 1.25;
+const MOUSE_PASSTHROUGH_MARGIN: f32 = 4.0;
+const MOUSE_PASSTHROUGH_POLL_INTERVAL: Duration = Duration::from_millis(50);
 
 impl CodexAgentApp {
     pub(super) fn min_inner_size(&self) -> Vec2 {
@@ -20,6 +32,48 @@ impl CodexAgentApp {
             .unwrap_or(Vec2::new(MIN_WINDOW_WIDTH, MIN_WINDOW_HEIGHT))
     }
 
+    pub(super) fn sanitize_window_size(&self, size: Vec2) -> Vec2 {
+        let min = self
+            .min_inner_size()
+            .max(Vec2::new(MIN_WINDOW_WIDTH, MIN_WINDOW_HEIGHT));
+        let monitor = self.ctx.input(|input| input.viewport().monitor_size);
+        let sanitized = sanitize_window_size(size, min, monitor);
+        if sanitized != size {
+            logging::trace(format!(
+                "sanitized window size {:?} to {:?}",
+                size, sanitized
+            ));
+        }
+        sanitized
+    }
+
+    pub(super) fn sanitize_height_override(&self, height: Option<f32>) -> Option<f32> {
+        height.map(|height| {
+            self.sanitize_window_size(Vec2::new(MIN_WINDOW_WIDTH, height))
+                .y
+        })
+    }
+
+    pub(super) fn sync_pixels_per_point(&mut self) {
+        let pixels_per_point = self.ctx.pixels_per_point();
+        let previous = self.last_pixels_per_point.replace(pixels_per_point);
+        if previous.is_none_or(|previous| (previous - pixels_per_point).abs() < f32::EPSILON) {
+            return;
+        }
+        logging::trace(format!(
+            "display scale changed from {:.2} to {:.2}",
+            previous.unwrap_or_default(),
+            pixels_per_point
+        ));
+        self.last_monitor_size = None;
+        self.invalidate_text_layout();
+        self.output_turn_markers.clear();
+        self.output_prompt_rects.clear();
+        self.output_pin_rects.clear();
+        self.output_response_ends.clear();
+        self.resize_for_text();
+    }
+
     fn sync_min_inner_size(&mut self, inner_rect: Option<Rect>) {
         let Some(inner_size) = inner_rect.map(|rect| rect.size()) else {
             return;
@@ -28,8 +82,7 @@ impl CodexAgentApp {
             return;
         }
         self.min_inner_size = Some(inner_size);
-        self.ctx
-            .send_viewport_cmd(egui::ViewportCommand::MinInnerSize(inner_size));
+        send_viewport_command(&self.ctx, egui::ViewportCommand::MinInnerSize(inner_size));
     }
 
     pub(super) fn sync_viewport_state(&mut self) -> bool {
@@ -57,6 +110,17 @@ impl CodexAgentApp {
         if self.was_minimized {
             self.was_minimized = false;
             self.restore_from_minimize(inner_rect, outer_rect);
+            let focused = self.ctx.input(|input| input.focused);
+            trace_focus_change(|| {
+                format!(
+                    "restored from minimize {} focus",
+                    if focused { "with" } else { "without" }
+                )
+            });
+            if focused {
+                self.pending_input_focus = true;
+                self.input_focus_retries = 0;
+            }
             return false;
         }
 
@@ -101,12 +165,12 @@ impl CodexAgentApp {
             return;
         };
 
-        self.user_height_override = state.user_height_override;
-        self.last_inner_size = Some(state.inner_size);
+        let inner_size = self.sanitize_window_size(state.inner_size);
+        self.user_height_override = self.sanitize_height_override(state.user_height_override);
+        self.last_inner_size = Some(inner_size);
         self.last_outer_size = Some(state.outer_size);
-        if !Self::same_size(inner_rect.map(|rect| rect.size()), state.inner_size) {
-            self.ctx
-                .send_viewport_cmd(egui::ViewportCommand::InnerSize(state.inner_size));
+        if !Self::same_size(inner_rect.map(|rect| rect.size()), inner_size) {
+            send_viewport_command(&self.ctx, egui::ViewportCommand::InnerSize(inner_size));
         }
         self.invalidate_text_layout();
     }
@@ -249,6 +313,98 @@ impl CodexAgentApp {
         }
     }
 
+    pub(super) fn sync_visible_bounds(&mut self) {
+        self.ctx.request_repaint_after(BOUNDS_CHECK_INTERVAL);
+        if self.window_dragging || self.resizing || self.maximized || self.was_minimized {
+            return;
+        }
+        let monitor_size = self.ctx.input(|input| input.viewport().monitor_size);
+        if let (Some(previous), Some(current)) = (self.last_monitor_size, monitor_size) {
+            let ratio = (current.x * current.y) / (previous.x * previous.y).max(1.0);
+            if !(1.0 / MONITOR_RESIZE_RATIO..=MONITOR_RESIZE_RATIO).contains(&ratio) {
+                logging::trace(format!(
+                    "monitor size changed from {:?} to {:?}; repositioning",
+                    previous, current
+                ));
+                self.positioned = false;
+            }
+        }
+        if monitor_size.is_some() {
+            self.last_monitor_size = monitor_size;
+        }
+        if self
+            .last_bounds_check
+            .is_some_and(|checked| checked.elapsed() < BOUNDS_CHECK_INTERVAL)
+        {
+            return;
+        }
+        self.last_bounds_check = Some(Instant::now());
+        #[cfg(target_os = "windows")]
+        if self.window_off_screen() {
+            logging::trace("window is off screen; moving it into the nearest work area");
+            self.recover_window_bounds();
+        }
+    }
+
+    #[cfg(target_os = "windows")]
+    fn window_off_screen(&self) -> bool {
+        use windows_sys::Win32::Graphics::Gdi::{MONITOR_DEFAULTTONULL, MonitorFromWindow};
+
+        !self.hwnd.is_null()
+            && unsafe { MonitorFromWindow(self.hwnd, MONITOR_DEFAULTTONULL) }.is_null()
+    }
+
+    pub(super) fn sync_mouse_passthrough(&mut self, card_rect: Rect) {
+        let hit_rect = card_rect.expand(MOUSE_PASSTHROUGH_MARGIN);
+        let screen_rect = self.ctx.screen_rect();
+        let passthrough = !self.window_dragging
+            && !self.resizing
+            && !self.settings_menu_open
+            && !self.ctx.memory(|memory| memory.any_popup_open())
+            && self
+                .cursor_position()
+                .is_some_and(|pos| screen_rect.contains(pos) && !hit_rect.contains(pos));
+        if passthrough != self.mouse_passthrough {
+            self.mouse_passthrough = passthrough;
+            send_viewport_command(
+                &self.ctx,
+                egui::ViewportCommand::MousePassthrough(passthrough),
+            );
+        }
+        if passthrough {
+            self.ctx
+                .request_repaint_after(MOUSE_PASSTHROUGH_POLL_INTERVAL);
+        }
+    }
+
+    #[cfg(target_os = "windows")]
+    fn cursor_position(&self) -> Option<Pos2> {
+        use windows_sys::Win32::Foundation::POINT;
+        use windows_sys::Win32::Graphics::Gdi::ClientToScreen;
+        use windows_sys::Win32::UI::WindowsAndMessaging::GetCursorPos;
+
+        if self.hwnd.is_null() {
+            return None;
+        }
+        let mut cursor = POINT { x: 0, y: 0 };
+        let mut origin = POINT { x: 0, y: 0 };
+        if unsafe { GetCursorPos(&mut cursor) } == 0
+            || unsafe { ClientToScreen(self.hwnd, &mut origin) } == 0
+        {
+            return None;
+        }
+        let scale = self.ctx.pixels_per_point();
+        Some(egui::pos2(
+            (cursor.x - origin.x) as f32 / scale,
+            (cursor.y - origin.y) as f32 / scale,
+        ))
+    }
+
+    #[cfg(not(target_os = "windows"))]
+    fn cursor_position(&self) -> Option<Pos2> {
+        None
+    }
+
     pub(super) fn update_window_drag(
         &mut self,
         resize_rect: Rect,
@@ -358,16 +514,22 @@ impl CodexAgentApp {
             self.drag_armed = false;
             self.window_dragging = false;
             self.resizing = true;
+            self.resize_wrap_width.begin(self.text_wrap_width());
             self.resize_start_inner_size = Some(self.ctx.screen_rect().size());
-            self.ctx
-                .send_viewport_cmd(egui::ViewportCommand::BeginResize(direction));
+            send_viewport_command(&self.ctx, egui::ViewportCommand::BeginResize(direction));
             return;
         }
 
         let text_cursor = self.ctx.output(|o| o.cursor_icon == CursorIcon::Text);
+        let on_background = self
+            .ctx
+            .input(|input| input.pointer.interact_pos())
+            .and_then(|pos| self.ctx.layer_id_at(pos))
+            .is_none_or(|layer| layer.order == egui::Order::Background);
         let start_drag = self.ctx.input(|input| {
             if input.pointer.primary_pressed() {
                 self.drag_armed = !text_cursor
+                    && on_background
                     && input
                         .pointer
                         .interact_pos()
@@ -389,10 +551,11 @@ impl CodexAgentApp {
                 self.leave_tile(true);
             }
             self.window_dragging = true;
-            self.ctx.send_viewport_cmd(egui::ViewportCommand::StartDrag);
+            send_viewport_command(&self.ctx, egui::ViewportCommand::StartDrag);
         }
     }
 
+    #[cfg(target_os = "windows")]
     fn current_restore_state(&self) -> WindowRestoreState {
         let inner_size = self
             .last_inner_size
@@ -412,7 +575,8 @@ impl CodexAgentApp {
                 let viewport = input.viewport();
                 (viewport.outer_rect, viewport.inner_rect)
             });
-            if let (Some(outer_rect), Some(work_area)) = (outer_rect, self.monitor_work_area()) {
+            if let (Some(outer_rect), Some(work_area)) = (outer_rect, self.auto_resize_work_area())
+            {
                 let current_inner_height = inner_rect
                     .map(|rect| rect.height())
                     .unwrap_or_else(|| self.ctx.screen_rect().height());
@@ -422,17 +586,18 @@ impl CodexAgentApp {
                 let max_top = (work_area.bottom as f32 - desired_outer_height).max(min_top);
                 let next_top = outer_rect.min.y.clamp(min_top, max_top);
                 if (next_top - outer_rect.min.y).abs() > 0.5 {
-                    self.ctx
-                        .send_viewport_cmd(egui::ViewportCommand::OuterPosition(egui::pos2(
+                    send_viewport_command(
+                        &self.ctx,
+                        egui::ViewportCommand::OuterPosition(egui::pos2(
                             outer_rect.min.x,
                             next_top,
-                        )));
+                        )),
+                    );
                 }
             }
         }
 
-        self.ctx
-            .send_viewport_cmd(egui::ViewportCommand::InnerSize(size));
+        send_viewport_command(&self.ctx, egui::ViewportCommand::InnerSize(size));
     }
 
     #[cfg(target_os = "windows")]
@@ -450,6 +615,13 @@ impl CodexAgentApp {
         self.monitor_info(outer_rect).map(|info| info.rcWork)
     }
 
+    #[cfg(target_os = "windows")]
+    fn auto_resize_work_area(&self) -> Option<windows_sys::Win32::Foundation::RECT> {
+        let outer_rect = self.ctx.input(|input| input.viewport().outer_rect);
+        self.monitor_info(outer_rect)
+            .map(|info| exclude_auto_hide_bars(info.rcMonitor, info.rcWork))
+    }
+
     #[cfg(target_os = "windows")]
     pub(super) fn auto_resize_height_limit(&self) -> Option<f32> {
         let rect = self.window_rect().or_else(|| {
@@ -457,7 +629,7 @@ impl CodexAgentApp {
                 .input(|input| input.viewport().outer_rect)
                 .map(Self::outer_rect_to_native_rect)
         })?;
-        let work_area = self.monitor_work_area()?;
+        let work_area = self.auto_resize_work_area()?;
         let outer_extra =
             ((rect.bottom - rect.top) as f32 - self.ctx.screen_rect().height()).max(0.0);
         let max_outer_height = (work_area.bottom - rect.top)
@@ -531,8 +703,10 @@ impl CodexAgentApp {
         self.set_native_resizable(true);
 
         if restore_size {
-            self.user_height_override = state.restore.user_height_override;
-            self.last_inner_size = Some(state.restore.inner_size);
+            let inner_size = self.sanitize_window_size(state.restore.inner_size);
+            self.user_height_override =
+                self.sanitize_height_override(state.restore.user_height_override);
+            self.last_inner_size = Some(inner_size);
             self.last_outer_size = Some(state.restore.outer_size);
 
             #[cfg(target_os = "windows")]
@@ -542,8 +716,7 @@ impl CodexAgentApp {
                 self.set_window_rect(rect.left, rect.top, width, height);
             }
 
-            self.ctx
-                .send_viewport_cmd(egui::ViewportCommand::InnerSize(state.restore.inner_size));
+            send_viewport_command(&self.ctx, egui::ViewportCommand::InnerSize(inner_size));
         } else {
             self.user_height_override = None;
             self.invalidate_text_layout();
@@ -702,3 +875,115 @@ fn rect_matches(
         && (current.right - expected.right).abs() <= tolerance
         && (current.bottom - expected.bottom).abs() <= tolerance
 }
+
+#[cfg(target_os = "windows")]
+fn exclude_auto_hide_bars(
+    monitor: windows_sys::Win32::Foundation::RECT,
+    work_area: windows_sys::Win32::Foundation::RECT,
+) -> windows_sys::Win32::Foundation::RECT {
+    use windows_sys::Win32::Foundation::RECT;
+    use windows_sys::Win32::UI::Shell::{
+        ABE_BOTTOM, ABE_LEFT, ABE_RIGHT, ABE_TOP, ABM_GETAUTOHIDEBAREX, APPBARDATA, SHAppBarMessage,
+    };
+    use windows_sys::Win32::UI::WindowsAndMessaging::GetWindowRect;
+
+    let mut usable = work_area;
+    for edge in [ABE_LEFT, ABE_TOP, ABE_RIGHT, ABE_BOTTOM] {
+        let mut data: APPBARDATA = unsafe { mem::zeroed() };
+        data.cbSize = mem::size_of::<APPBARDATA>() as u32;
+        data.uEdge = edge;
+        data.rc = monitor;
+        let bar = unsafe { SHAppBarMessage(ABM_GETAUTOHIDEBAREX, &mut data) };
+        if bar == 0 {
+            continue;
+        }
+        let mut rect: RECT = unsafe { mem::zeroed() };
+        if unsafe { GetWindowRect(bar as _, &mut rect) } == 0 {
+            continue;
+        }
+        let width = (rect.right - rect.left).max(0);
+        let height = (rect.bottom - rect.top).max(0);
+        match edge {
+            ABE_LEFT => usable.left = usable.left.max(monitor.left + width),
+            ABE_TOP => usable.top = usable.top.max(monitor.top + height),
+            ABE_RIGHT => usable.right = usable.right.min(monitor.right - width),
+            _ => usable.bottom = usable.bottom.min(monitor.bottom - height),
+        }
+    }
+    if usable.right <= usable.left || usable.bottom <= usable.top {
+        logging::trace("auto-hide taskbars cover the work area; ignoring them for auto resize");
+        return work_area;
+    }
+    usable
+}
+
+fn sanitize_window_size(size: Vec2, min: Vec2, monitor: Option<Vec2>) -> Vec2 {
+    let max = monitor
+        .filter(|monitor| monitor.x.is_finite() && monitor.y.is_finite())
+        .map_or(Vec2::INFINITY, |monitor| monitor.max(min));
+    let axis = |value: f32, min: f32, max: f32| {
+        if value.is_finite() && value > 0.0 {
+            value.clamp(min, max)
+        } else {
+            min
+        }
+    };
+    Vec2::new(axis(size.x, min.x, max.x), axis(size.y, min.y, max.y))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MIN: Vec2 = Vec2::new(MIN_WINDOW_WIDTH, MIN_WINDOW_HEIGHT);
+    const MONITOR: Option<Vec2> = Some(Vec2::new(1920.0, 1080.0));
+
+    #[test]
+    fn sizes_in_range_are_kept() {
+        let size = Vec2::new(MIN_WINDOW_WIDTH + 100.0, MIN_WINDOW_HEIGHT + 50.0);
+        assert_eq!(sanitize_window_size(size, MIN, MONITOR), size);
+        assert_eq!(sanitize_window_size(size, MIN, None), size);
+    }
+
+    #[test]
+    fn sizes_clamp_to_the_minimum_and_the_monitor() {
+        assert_eq!(
+            sanitize_window_size(Vec2::new(10.0, 10.0), MIN, MONITOR),
+            MIN
+        );
+        assert_eq!(
+            sanitize_window_size(Vec2::new(5000.0, 3000.0), MIN, MONITOR),
+            Vec2::new(1920.0, 1080.0)
+        );
+        assert_eq!(
+            sanitize_window_size(Vec2::new(5000.0, 3000.0), MIN, None),
+            Vec2::new(5000.0, 3000.0)
+        );
+    }
+
+    #[test]
+    fn invalid_sizes_fall_back_to_the_minimum() {
+        for value in [0.0, -20.0, f32::NAN, f32::INFINITY, f32::NEG_INFINITY] {
+            assert_eq!(
+                sanitize_window_size(Vec2::new(value, value), MIN, MONITOR),
+                MIN,
+                "{}",
+                value
+            );
+        }
+    }
+
+    #[test]
+    fn small_or_bogus_monitors_never_go_below_the_minimum() {
+        let tiny = Some(Vec2::new(MIN_WINDOW_WIDTH / 2.0, MIN_WINDOW_HEIGHT / 2.0));
+        assert_eq!(
+            sanitize_window_size(Vec2::new(800.0, 600.0), MIN, tiny),
+            MIN
+        );
+        let size = Vec2::new(4000.0, 3000.0);
+        assert_eq!(
+            sanitize_window_size(size, MIN, Some(Vec2::new(f32::NAN, 1080.0))),
+            size
+        );
+    }
+}
@@ -0,0 +1,305 @@
+use std::env;
+use std::fs;
+use std::io;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::process;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, SystemTime};
+
+use crate::config::{APP_NAME, paths};
+use crate::logging;
+use crate::runtime::process_running;
+
+const MAX_ATTACHMENTS: usize = 4;
+const MIN_ATTACHMENT_BYTES: usize = 1024;
+const ATTACHMENT_DIR: &str = "attachments";
+const ATTACHMENT_PREFIX: &str = "prompt-";
+const ATTACHMENT_EXTENSION: &str = "txt";
+const PERSISTED_ATTACHMENT_MAX_AGE: Duration = Duration::from_secs(30 * 24 * 60 * 60);
+const LOG_LINE_RATIO: usize = 2;
+
+static NEXT_ATTACHMENT: AtomicU64 = AtomicU64::new(1);
+
+pub struct PromptAttachment {
+    pub path: PathBuf,
+    pub bytes: usize,
+    pub kind: String,
+}
+
+impl PromptAttachment {
+    pub fn note(&self) -> String {
+        format!(
+            "Attached {} ({}) as {}",
+            self.kind,
+            format_size(self.bytes),
+            self.path.display()
+        )
+    }
+}
+
+// Persisted transcripts can be resumed later, so their attachments live in
+// the app data directory; otherwise they stay in the temp directory.
+pub fn attachment_dir(persist: bool) -> PathBuf {
+    if persist {
+        paths::get().attachments_dir.clone()
+    } else {
+        env::temp_dir().join(APP_NAME).join(ATTACHMENT_DIR)
+    }
+}
+
+pub fn format_size(bytes: usize) -> String {
+    if bytes >= 1024 * 1024 {
+        format!("{:.1} MB", bytes as f64 / (1024.0 * 1024.0))
+    } else {
+        format!("{} KB", bytes.div_ceil(1024))
+    }
+}
+
+fn is_fence(line: &str) -> bool {
+    line.trim_start().starts_with("```")
+}
+
+fn blocks(prompt: &str) -> Vec<Range<usize>> {
+    let mut blocks = Vec::new();
+    let mut start = None;
+    let mut in_fence = false;
+    let mut offset = 0;
+    for line in prompt.split_inclusive('\n') {
+        let end = offset + line.len();
+        if is_fence(line) {
+            in_fence = !in_fence;
+        }
+        if line.trim().is_empty() && !in_fence {
+            if let Some(start) = start.take() {
+                blocks.push(start..offset);
+            }
+        } else if start.is_none() {
+            start = Some(offset);
+        }
+        offset = end;
+    }
+    if let Some(start) = start {
+        blocks.push(start..prompt.len());
+    }
+    blocks
+}
+
+fn looks_like_log(text: &str) -> bool {
+    let lines = text.lines().filter(|line| !line.trim().is_empty());
+    let (total, stamped) = lines.fold((0, 0), |(total, stamped), line| {
+        let first = line.trim_start().chars().next();
+        let stamp = first.is_some_and(|c| c.is_ascii_digit() || c == '[');
+        (total + 1, stamped + usize::from(stamp))
+    });
+    total > 0 && stamped * LOG_LINE_RATIO >= total
+}
+
+fn block_contents(block: &str) -> (&str, String) {
+    let trimmed = block.trim_end();
+    let Some((first, rest)) = trimmed.split_once('\n') else {
+        return (block, kind_of(block, None));
+    };
+    if !is_fence(first) || !rest.lines().last().is_some_and(is_fence) {
+        return (block, kind_of(block, None));
+    }
+    let inner = rest.rsplit_once('\n').map_or("", |(inner, _)| inner);
+    let language = first.trim_start().trim_start_matches('`').trim();
+    (inner, kind_of(inner, Some(language)))
+}
+
+fn kind_of(text: &str, language: Option<&str>) -> String {
+    match language {
+        Some(language) if !language.is_empty() => format!("{} code", language),
+        _ if looks_like_log(text) => "pasted log".to_owned(),
+        Some(_) => "pasted code".to_owned(),
+        None => "pasted text".to_owned(),
+    }
+}
+
+fn render_reference(template: &str, attachment: &PromptAttachment, shown_path: &str) -> String {
+    template
+        .replace("{path}", shown_path)
+        .replace("{size}", &format_size(attachment.bytes))
+        .replace("{kind}", &attachment.kind)
+}
+
+fn write_attachment(dir: &Path, contents: &str, kind: String) -> io::Result<PromptAttachment> {
+    fs::create_dir_all(dir)?;
+    let path = dir.join(format!(
+        "{}{}-{}.{}",
+        ATTACHMENT_PREFIX,
+        process::id(),
+        NEXT_ATTACHMENT.fetch_add(1, Ordering::Relaxed),
+        ATTACHMENT_EXTENSION
+    ));
+    fs::write(&path, contents)?;
+    Ok(PromptAttachment {
+        path,
+        bytes: contents.len(),
+        kind,
+    })
+}
+
+pub fn attach_long_prompt(
+    prompt: &str,
+    limit: usize,
+    template: &str,
+    dir: &Path,
+    shown_path: impl Fn(&Path) -> String,
+) -> io::Result<(String, Vec<PromptAttachment>)> {
+    let mut prompt = prompt.to_owned();
+    let mut attachments: Vec<PromptAttachment> = Vec::new();
+    while limit > 0 && prompt.len() > limit && attachments.len() < MAX_ATTACHMENTS {
+        let Some(block) = blocks(&prompt)
+            .into_iter()
+            .filter(|block| block.len() >= MIN_ATTACHMENT_BYTES)
+            .max_by_key(|block| block.len())
+        else {
+            break;
+        };
+        let text = &prompt[block.clone()];
+        let (contents, kind) = block_contents(text);
+        let attachment = match write_attachment(dir, contents, kind) {
+            Ok(attachment) => attachment,
+            Err(error) => {
+                remove_attachments(attachments.iter().map(|attachment| &attachment.path));
+                return Err(error);
+            }
+        };
+        let trailing = &text[text.trim_end_matches(['\n', '\r']).len()..];
+        let reference = format!(
+            "{}{}",
+            render_reference(template, &attachment, &shown_path(&attachment.path)),
+            trailing
+        );
+        prompt.replace_range(block, &reference);
+        attachments.push(attachment);
+    }
+    Ok((prompt, attachments))
+}
+
+pub fn remove_attachments<'a>(paths: impl IntoIterator<Item = &'a PathBuf>) -> usize {
+    let mut removed = 0;
+    for path in paths {
+        match fs::remove_file(path) {
+            Ok(()) => removed += 1,
+            Err(error) if error.kind() == io::ErrorKind::NotFound => {}
+            Err(error) => logging::error(format!(
+                "failed to remove prompt attachment {}: {}",
+                path.display(),
+                error
+            )),
+        }
+    }
+    removed
+}
+
+// Removes temp attachments left by processes that no longer run and
+// persisted ones older than their transcripts are worth resuming.
+pub fn sweep_attachments() {
+    let mut stale: Vec<PathBuf> = attachment_files(&attachment_dir(false))
+        .into_iter()
+        .filter(|(path, _)| attachment_pid(path).is_none_or(|pid| !process_running(pid)))
+        .map(|(path, _)| path)
+        .collect();
+    stale.extend(
+        attachment_files(&attachment_dir(true))
+            .into_iter()
+            .filter(|(_, modified)| {
+                modified
+                    .elapsed()
+                    .is_ok_and(|age| age > PERSISTED_ATTACHMENT_MAX_AGE)
+            })
+            .map(|(path, _)| path),
+    );
+    let removed = remove_attachments(&stale);
+    if removed > 0 {
+        logging::trace(format!("removed {} stale prompt attachments", removed));
+    }
+}
+
+fn attachment_files(dir: &Path) -> Vec<(PathBuf, SystemTime)> {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(error) => {
+            if error.kind() != io::ErrorKind::NotFound {
+                logging::error(format!(
+                    "failed to list prompt attachments in {}: {}",
+                    dir.display(),
+                    error
+                ));
+            }
+            return Vec::new();
+        }
+    };
+    entries
+        .filter_map(Result::ok)
+        .map(|entry| entry.path())
+        .filter(|path| is_attachment(path))
+        .filter_map(|path| {
+            let modified = fs::metadata(&path).and_then(|m| m.modified()).ok()?;
+            Some((path, modified))
+        })
+        .collect()
+}
+
+fn is_attachment(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|ext| ext == ATTACHMENT_EXTENSION)
+        && path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .is_some_and(|name| name.starts_with(ATTACHMENT_PREFIX))
+}
+
+fn attachment_pid(path: &Path) -> Option<u32> {
+    path.file_stem()?
+        .to_str()?
+        .strip_prefix(ATTACHMENT_PREFIX)?
+        .split('-')
+        .next()?
+        .parse()
+        .ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reference_uses_the_path_codex_sees() {
+        let dir = env::temp_dir().join(format!("codexagent-attach-{}", process::id()));
+        let prompt = format!(
+            "Why does this fail?\n\n{}\n",
+            "2024-01-01 error\n".repeat(200)
+        );
+        let (outgoing, attachments) = attach_long_prompt(
+            &prompt,
+            MIN_ATTACHMENT_BYTES,
+            "see {path} ({size}, {kind})",
+            &dir,
+            |path| format!("/mnt/c/{}", path.file_name().unwrap().to_string_lossy()),
+        )
+        .expect("attachment written");
+        assert_eq!(attachments.len(), 1);
+        let name = attachments[0].path.file_name().unwrap().to_string_lossy();
+        assert!(outgoing.contains(&format!("see /mnt/c/{} (", name)));
+        assert!(outgoing.contains("pasted log"));
+        assert_eq!(remove_attachments(attachments.iter().map(|a| &a.path)), 1);
+        let _ = fs::remove_dir(&dir);
+    }
+
+    #[test]
+    fn attachment_names_carry_the_owning_pid() {
+        let path = Path::new("dir").join(format!(
+            "{}4242-7.{}",
+            ATTACHMENT_PREFIX, ATTACHMENT_EXTENSION
+        ));
+        assert!(is_attachment(&path));
+        assert_eq!(attachment_pid(&path), Some(4242));
+        assert!(!is_attachment(Path::new("dir/notes.txt")));
+        assert!(!is_attachment(Path::new("dir/prompt-1-1.log")));
+        assert_eq!(attachment_pid(Path::new("dir/prompt-x-1.txt")), None);
+    }
+}
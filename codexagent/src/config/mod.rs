@@ -1,81 +1,1309 @@
+pub mod paths;
+pub mod project;
+pub mod snapshot;
+mod store;
+
+pub use store::{flush_settings, take_settings_recovery_notice};
+
 use std::collections::HashMap;
 use std::fs;
 use std::io;
-use std::path::{Path, PathBuf};
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
 
 use crate::logging;
+use crate::postprocess::{OUTPUT_PROCESSORS, OutputProcessor};
+use crate::redact;
+use crate::runtime::AccessibilityPrefs;
+use crate::transform::{TRANSFORMERS, Transformer};
 
-pub(crate) const APP_NAME: &str = "codexagent";
-pub(crate) const APP_DISPLAY_NAME: &str = "Codex Agent";
-pub(crate) const APP_USER_MODEL_ID: &str = "Codex.Agent";
-pub(crate) const DEFAULT_MODEL: &str = "gpt-5.3-codex";
-pub(crate) const DEFAULT_NOTIFICATIONS_ENABLED: bool = true;
-pub(crate) const WINDOW_PADDING: f32 = 36.0;
-pub(crate) const WINDOW_BOTTOM_PADDING: f32 = 44.0;
-pub(crate) const LINE_HEIGHT: f32 = 20.0;
-pub(crate) const TEXT_FONT_SIZE: f32 = 14.0;
-pub(crate) const AUTO_EXPAND_VISIBLE_ROWS: usize = 120;
-pub(crate) const MAX_VISIBLE_ROWS: usize = 160;
-pub(crate) const DEFAULT_WINDOW_WIDTH: f32 = 864.0;
-pub(crate) const DEFAULT_WINDOW_HEIGHT: f32 =
-    58.0 + LINE_HEIGHT + WINDOW_PADDING + WINDOW_BOTTOM_PADDING;
-pub(crate) const MIN_WINDOW_WIDTH: f32 = 504.0;
-pub(crate) const MIN_WINDOW_HEIGHT: f32 = 88.0;
-pub(crate) const MAX_WINDOW_HEIGHT: f32 = 3323.0;
-pub(crate) const CARD_INNER_PADDING_X: f32 = 36.0;
-pub(crate) const CANCEL_BUTTON_WIDTH: f32 = 84.0;
-pub(crate) const CANCEL_BUTTON_HEIGHT: f32 = 24.0;
-pub(crate) const CANCELLED_BOTTOM_PADDING: f32 = 6.0;
-pub(crate) const TEXT_EDIT_MARGIN_X: f32 = 8.0;
-pub(crate) const MIN_TEXT_WRAP_WIDTH: f32 = 24.0;
-pub(crate) const RESIZE_HANDLE_SIZE: f32 = 14.0;
-pub(crate) const HIDDEN_MARKDOWN_FONT_SIZE: f32 = 0.5;
-pub(crate) const CODEX_CONFIG_CONTENTS: &[u8] = b"approval_policy = \"never\"\nnetwork_access = \"enabled\"\nmodel = \"gpt-5.3-codex\"\nmodel_reasoning_effort = \"high\"\nsandbox_mode = \"danger-full-access\"";
-pub(crate) const CODEX_AGENTS_CONTENTS: &[u8] = b"Windows 11\nBe concise. Guess if ambiguous; don\xe2\x80\x99t ask.\nAvoid frameworks unless already present. Avoid comments.\nNever commit or push.\nDon\xe2\x80\x99t read git history (git log/show/etc) unless explicitly asked.\n";
-pub(crate) const PROMPT_SCROLL_ID: &str = "prompt-scroll";
-pub(crate) const PROMPT_HISTORY_PATH: &str = r"C:\Local\Config\CodexAgent.history";
-pub(crate) const MAX_PROMPT_HISTORY: usize = 100;
-pub(crate) const PENDING_ANIMATION_INTERVAL: std::time::Duration =
+pub const APP_NAME: &str = "codexagent";
+pub const APP_DISPLAY_NAME: &str = "Codex Agent";
+#[cfg(windows)]
+pub const APP_USER_MODEL_ID: &str = "Codex.Agent";
+pub const APP_VERSION: &str = env!("CARGO_PKG_VERSION");
+pub const BUILD_GIT_HASH: &str = env!("CODEXAGENT_GIT_HASH");
+const BUILD_TIME: &str = env!("CODEXAGENT_BUILD_TIME");
+pub const DEFAULT_MODEL: &str = "gpt-5.3-codex";
+pub const DEFAULT_NOTIFICATIONS_ENABLED: bool = true;
+pub const DEFAULT_PROJECT_CONFIG_ENABLED: bool = true;
+pub const DEFAULT_DIRTY_TREE_WARNING_ENABLED: bool = true;
+pub const DEFAULT_SUGGESTIONS_ENABLED: bool = false;
+pub const DEFAULT_INSPECT_EVENTS: bool = false;
+pub const DEFAULT_PERSIST_TRANSCRIPTS: bool = true;
+pub const DEFAULT_AUTO_CONTINUE: bool = false;
+pub const DEFAULT_CANCEL_ON_SLEEP: bool = false;
+pub const DEFAULT_CLIPBOARD_WATCH: bool = false;
+pub const DEFAULT_CONCISE_ANSWERS: bool = false;
+pub const DEFAULT_WARM_START: bool = false;
+pub const DEFAULT_CODEX_TITLES: bool = false;
+pub const DEFAULT_TOPIC_SUGGESTIONS_ENABLED: bool = false;
+pub const DEFAULT_INPUT_ASSIST_ENABLED: bool = true;
+pub const DEFAULT_EXPORT_TURN_MARKERS: bool = true;
+pub const DEFAULT_REDACT_SECRETS: bool = true;
+pub const DEFAULT_PROMPT_TRANSFORMER_ENABLED: bool = true;
+pub const DEFAULT_OUTPUT_PROCESSOR_ENABLED: bool = false;
+pub const DEFAULT_COMPACT_MODE: bool = false;
+pub const DEFAULT_EXCLUDE_FROM_CAPTURE: bool = false;
+pub const DEFAULT_WAKE_MODE: WakeMode = WakeMode::System;
+pub const DEFAULT_SUBMIT_KEY: SubmitKey = SubmitKey::Enter;
+pub const DEFAULT_PROMPT_WHITESPACE: PromptWhitespace = PromptWhitespace::Trim;
+pub const DEFAULT_LAUNCHER_MODE: LauncherMode = LauncherMode::Native;
+pub const DEFAULT_TOKEN_WARNING_LIMIT: u64 = 32_000;
+pub const DEFAULT_RESPONSE_TRUNCATE_LIMIT: usize = 200 * 1024;
+pub const DEFAULT_PROMPT_ATTACH_LIMIT: usize = 32 * 1024;
+pub const DEFAULT_PROMPT_ATTACH_TEMPLATE: &str = "see attached file at {path} ({size}, {kind})";
+pub const DEFAULT_STREAM_UPDATE_INTERVAL: std::time::Duration =
+    std::time::Duration::from_millis(50);
+pub const DEFAULT_REASONING_WINDOW: usize = 3;
+pub const DEFAULT_RECENT_SESSION_LIMIT: usize = 10;
+pub const DEFAULT_DISK_SPACE_WARNING_MB: u64 = 200;
+pub const DEFAULT_REASONING_RETENTION_LIMIT: usize = 256 * 1024;
+pub const PERMISSIVE_SANDBOX_MODE: &str = "danger-full-access";
+pub const WINDOW_PADDING: f32 = 36.0;
+pub const WINDOW_BOTTOM_PADDING: f32 = 44.0;
+pub const SHADOWLESS_WINDOW_PADDING: f32 = 2.0;
+pub const DEFAULT_WINDOW_SHADOW: WindowShadow = WindowShadow {
+    enabled: true,
+    blur: 32,
+    spread: 3,
+    color: None,
+};
+pub const LINE_HEIGHT: f32 = 20.0;
+pub const TEXT_FONT_SIZE: f32 = 14.0;
+pub const AUTO_EXPAND_VISIBLE_ROWS: usize = 120;
+pub const CODEX_FILE_EDITOR_ROWS: usize = 18;
+pub const CODEX_FILE_UPDATE_DIFF_ROWS: usize = 8;
+pub const DEFAULT_WINDOW_WIDTH: f32 = 864.0;
+pub const DEFAULT_WINDOW_HEIGHT: f32 = 58.0 + LINE_HEIGHT + WINDOW_PADDING + WINDOW_BOTTOM_PADDING;
+pub const MIN_WINDOW_WIDTH: f32 = 504.0;
+pub const MIN_WINDOW_HEIGHT: f32 = 88.0;
+pub const MAX_WINDOW_HEIGHT: f32 = 3323.0;
+pub const MAX_WINDOW_HEIGHT_MONITOR_RATIO: f32 = 0.8;
+pub const CARD_INNER_PADDING_X: f32 = 36.0;
+pub const CANCEL_BUTTON_WIDTH: f32 = 84.0;
+pub const CANCEL_BUTTON_HEIGHT: f32 = 24.0;
+pub const CANCELLED_BOTTOM_PADDING: f32 = 6.0;
+pub const TEXT_EDIT_MARGIN_X: f32 = 8.0;
+pub const MIN_TEXT_WRAP_WIDTH: f32 = 24.0;
+pub const RESIZE_HANDLE_SIZE: f32 = 14.0;
+pub const HIDDEN_MARKDOWN_FONT_SIZE: f32 = 0.5;
+pub const CODEX_CONFIG_CONTENTS: &[u8] = b"approval_policy = \"never\"\nnetwork_access = \"enabled\"\nmodel = \"gpt-5.3-codex\"\nmodel_reasoning_effort = \"high\"\nsandbox_mode = \"danger-full-access\"";
+pub const CODEX_AGENTS_CONTENTS: &[u8] = b"Windows 11\nBe concise. Guess if ambiguous; don\xe2\x80\x99t ask.\nAvoid frameworks unless already present. Avoid comments.\nNever commit or push.\nDon\xe2\x80\x99t read git history (git log/show/etc) unless explicitly asked.\n";
+pub const PROMPT_SCROLL_ID: &str = "prompt-scroll";
+pub const MAX_PROMPT_HISTORY: usize = 100;
+pub const DEFAULT_PENDING_ANIMATION_INTERVAL: std::time::Duration =
     std::time::Duration::from_millis(400);
-pub(crate) const CANCELLED_TEXT: &str = "cancelled";
+pub const MIN_PENDING_ANIMATION_INTERVAL: std::time::Duration =
+    std::time::Duration::from_millis(50);
+pub const DEFAULT_PENDING_STYLE: PendingStyle = PendingStyle::Dots;
+pub const DEFAULT_THEME: Theme = Theme::Auto;
+pub const DEFAULT_RESPONSE_DIMMING: ResponseDimming = ResponseDimming::Strong;
+pub const CANCELLED_TEXT: &str = "cancelled";
+pub const PARTIAL_RESPONSE_TEXT: &str =
+    "\u{2500}\u{2500} response incomplete \u{2014} error below \u{2500}\u{2500}";
+pub const PARTIAL_RESPONSE_EXPORT_TEXT: &str =
+    "_Partial response \u{2014} codex failed before finishing; the error follows._";
+pub const SESSION_DIVIDER_TEXT: &str = "\u{2500}\u{2500}\u{2500}\u{2500}\u{2500}\u{2500}  new session  \u{2500}\u{2500}\u{2500}\u{2500}\u{2500}\u{2500}";
+pub const TOPIC_DIVIDER_TEXT: &str = "\u{2500}\u{2500}\u{2500}\u{2500}\u{2500}\u{2500}  new topic  \u{2500}\u{2500}\u{2500}\u{2500}\u{2500}\u{2500}";
+pub const CODEX_INSTALL_URL: &str = "https://github.com/openai/codex#quickstart";
 
 const NOTIFICATION_SETTING_KEY: &str = "notification";
+const ONBOARDING_SETTING_KEY: &str = "onboarding";
+const PROJECT_CONFIG_SETTING_KEY: &str = "project_config";
+const TRUSTED_PROJECT_SETTING_PREFIX: &str = "trusted_project.";
+const DIRTY_TREE_WARNING_SETTING_KEY: &str = "dirty_tree_warning";
+const SUGGESTIONS_SETTING_KEY: &str = "suggestions";
+const AUTO_CONTINUE_SETTING_KEY: &str = "auto_continue";
+const CANCEL_ON_SLEEP_SETTING_KEY: &str = "cancel_on_sleep";
+const CLIPBOARD_WATCH_SETTING_KEY: &str = "clipboard_watch";
+const CONCISE_ANSWERS_SETTING_KEY: &str = "concise_answers";
+const WARM_START_SETTING_KEY: &str = "warm_start";
+const CODEX_TITLES_SETTING_KEY: &str = "codex_titles";
+const TOPIC_SUGGESTIONS_SETTING_KEY: &str = "topic_suggestions";
+const EXPORT_TURN_MARKERS_SETTING_KEY: &str = "export_turn_markers";
+const REDACT_SECRETS_SETTING_KEY: &str = "redact_secrets";
+const REDACT_PATTERNS_SETTING_KEY: &str = "redact_patterns";
+const COMPACT_MODE_SETTING_KEY: &str = "compact_mode";
+const EXCLUDE_FROM_CAPTURE_SETTING_KEY: &str = "exclude_from_capture";
+const UI_TRACE_SETTING_KEY: &str = "ui_trace";
+const RECORD_EVENTS_SETTING_KEY: &str = "record_events";
+const INSPECT_EVENTS_SETTING_KEY: &str = "inspect_events";
+const PERSIST_TRANSCRIPTS_SETTING_KEY: &str = "persist_transcripts";
+
+static PERSIST_TRANSCRIPTS: AtomicBool = AtomicBool::new(DEFAULT_PERSIST_TRANSCRIPTS);
+static DISK_SPACE_LOW: AtomicBool = AtomicBool::new(false);
+const STREAM_RATE_SETTING_KEY: &str = "stream_rate";
+const TRANSFORM_SETTING_PREFIX: &str = "transform.";
+const OUTPUT_PROCESSOR_SETTING_PREFIX: &str = "postprocess.";
+const ON_COMPLETE_SETTING_KEY: &str = "on_complete";
+const PRICE_SETTING_PREFIX: &str = "price.";
+const ENV_SETTING_PREFIX: &str = "env.";
+const WAKE_MODE_SETTING_KEY: &str = "keep_awake";
+const SUBMIT_KEY_SETTING_KEY: &str = "submit_key";
+const PROMPT_WHITESPACE_SETTING_KEY: &str = "prompt_whitespace";
+const LAUNCHER_MODE_SETTING_KEY: &str = "codex_launcher";
+const TOKEN_WARNING_LIMIT_SETTING_KEY: &str = "token_warning_limit";
+const RESPONSE_TRUNCATE_SETTING_KEY: &str = "response_truncate_kb";
+const PROMPT_ATTACH_SETTING_KEY: &str = "prompt_attach_kb";
+const PROMPT_ATTACH_TEMPLATE_SETTING_KEY: &str = "prompt_attach_template";
+const RESPONSE_LANGUAGE_SETTING_KEY: &str = "response_language";
+const RESPONSE_LANGUAGE_UNPINNED: &str = "off";
+const INTERFACE_LOCALE_SETTING_KEY: &str = "locale";
+const INTERFACE_LOCALE_SYSTEM: &str = "auto";
+const STREAM_UPDATE_INTERVAL_SETTING_KEY: &str = "stream_update_interval_ms";
+const REASONING_WINDOW_SETTING_KEY: &str = "reasoning_window";
+const RECENT_SESSIONS_SETTING_KEY: &str = "recent_sessions";
+const DISK_SPACE_WARNING_SETTING_KEY: &str = "disk_space_warning_mb";
+const REASONING_RETENTION_SETTING_KEY: &str = "reasoning_retention_kb";
+const BUDGET_MINUTES_SETTING_KEY: &str = "budget_minutes";
+const BUDGET_TOOL_EVENTS_SETTING_KEY: &str = "budget_tool_events";
+const PENDING_STYLE_SETTING_KEY: &str = "pending_animation";
+const PENDING_INTERVAL_SETTING_KEY: &str = "pending_animation_ms";
+const THEME_SETTING_KEY: &str = "theme";
+const RESPONSE_DIMMING_SETTING_KEY: &str = "old_response_dimming";
+const WINDOW_SHADOW_SETTING_KEY: &str = "window_shadow";
+const WINDOW_SHADOW_BLUR_SETTING_KEY: &str = "window_shadow_blur";
+const WINDOW_SHADOW_SPREAD_SETTING_KEY: &str = "window_shadow_spread";
+const WINDOW_SHADOW_COLOR_SETTING_KEY: &str = "window_shadow_color";
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum WakeMode {
+    Off,
+    System,
+    SystemAndDisplay,
+}
+
+impl WakeMode {
+    pub const ALL: [WakeMode; 3] = [WakeMode::Off, WakeMode::System, WakeMode::SystemAndDisplay];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            WakeMode::Off => "Off",
+            WakeMode::System => "System",
+            WakeMode::SystemAndDisplay => "System and display",
+        }
+    }
+
+    fn setting_value(self) -> &'static str {
+        match self {
+            WakeMode::Off => "off",
+            WakeMode::System => "system",
+            WakeMode::SystemAndDisplay => "display",
+        }
+    }
+
+    fn parse(value: &str) -> Option<Self> {
+        WakeMode::ALL
+            .into_iter()
+            .find(|mode| value.eq_ignore_ascii_case(mode.setting_value()))
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum PendingStyle {
+    Dots,
+    Spinner,
+    Bar,
+    None,
+}
+
+impl PendingStyle {
+    pub const ALL: [PendingStyle; 4] = [
+        PendingStyle::Dots,
+        PendingStyle::Spinner,
+        PendingStyle::Bar,
+        PendingStyle::None,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            PendingStyle::Dots => "Dots",
+            PendingStyle::Spinner => "Spinner",
+            PendingStyle::Bar => "Bar",
+            PendingStyle::None => "None",
+        }
+    }
+
+    fn setting_value(self) -> &'static str {
+        match self {
+            PendingStyle::Dots => "dots",
+            PendingStyle::Spinner => "spinner",
+            PendingStyle::Bar => "bar",
+            PendingStyle::None => "none",
+        }
+    }
+
+    fn parse(value: &str) -> Option<Self> {
+        PendingStyle::ALL
+            .into_iter()
+            .find(|style| value.trim().eq_ignore_ascii_case(style.setting_value()))
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Theme {
+    Auto,
+    Standard,
+    HighContrast,
+}
+
+impl Theme {
+    pub const ALL: [Theme; 3] = [Theme::Auto, Theme::Standard, Theme::HighContrast];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Theme::Auto => "Auto",
+            Theme::Standard => "Standard",
+            Theme::HighContrast => "High contrast",
+        }
+    }
+
+    pub fn description(self) -> &'static str {
+        match self {
+            Theme::Auto => "Follow the Windows app theme, contrast and transparency",
+            Theme::Standard => "Translucent card with glow",
+            Theme::HighContrast => "Opaque card, full-alpha text",
+        }
+    }
+
+    /// Whether the card is drawn opaque. Only Auto looks at the system; a
+    /// manual choice ignores both the app theme and the accessibility settings.
+    pub fn opaque(self, accessibility: AccessibilityPrefs, system_light: bool) -> bool {
+        match self {
+            Theme::Auto => accessibility.wants_opaque() || system_light,
+            Theme::Standard => false,
+            Theme::HighContrast => true,
+        }
+    }
+
+    fn setting_value(self) -> &'static str {
+        match self {
+            Theme::Auto => "auto",
+            Theme::Standard => "standard",
+            Theme::HighContrast => "high_contrast",
+        }
+    }
+
+    fn parse(value: &str) -> Option<Self> {
+        Theme::ALL
+            .into_iter()
+            .find(|theme| value.trim().eq_ignore_ascii_case(theme.setting_value()))
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ResponseDimming {
+    None,
+    Subtle,
+    Strong,
+}
+
+impl ResponseDimming {
+    pub const ALL: [ResponseDimming; 3] = [
+        ResponseDimming::None,
+        ResponseDimming::Subtle,
+        ResponseDimming::Strong,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            ResponseDimming::None => "None",
+            ResponseDimming::Subtle => "Subtle",
+            ResponseDimming::Strong => "Strong",
+        }
+    }
+
+    pub fn description(self) -> &'static str {
+        match self {
+            ResponseDimming::None => "Earlier answers keep full brightness",
+            ResponseDimming::Subtle => "Earlier answers fade slightly",
+            ResponseDimming::Strong => "Earlier answers fade toward the background",
+        }
+    }
+
+    fn setting_value(self) -> &'static str {
+        match self {
+            ResponseDimming::None => "none",
+            ResponseDimming::Subtle => "subtle",
+            ResponseDimming::Strong => "strong",
+        }
+    }
+
+    fn parse(value: &str) -> Option<Self> {
+        ResponseDimming::ALL
+            .into_iter()
+            .find(|dimming| value.trim().eq_ignore_ascii_case(dimming.setting_value()))
+    }
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum LauncherMode {
+    Native,
+    Wsl(Option<String>),
+}
+
+impl LauncherMode {
+    pub fn label(&self) -> String {
+        match self {
+            LauncherMode::Native => "Native".to_owned(),
+            LauncherMode::Wsl(None) => "WSL".to_owned(),
+            LauncherMode::Wsl(Some(distro)) => format!("WSL ({})", distro),
+        }
+    }
+
+    fn setting_value(&self) -> String {
+        match self {
+            LauncherMode::Native => "native".to_owned(),
+            LauncherMode::Wsl(None) => "wsl".to_owned(),
+            LauncherMode::Wsl(Some(distro)) => format!("wsl:{}", distro),
+        }
+    }
+
+    fn parse(value: &str) -> Option<Self> {
+        let value = value.trim();
+        if value.eq_ignore_ascii_case("native") {
+            return Some(LauncherMode::Native);
+        }
+        if value.eq_ignore_ascii_case("wsl") {
+            return Some(LauncherMode::Wsl(None));
+        }
+        let (prefix, distro) = value.split_once(':')?;
+        let distro = distro.trim();
+        (prefix.eq_ignore_ascii_case("wsl") && !distro.is_empty())
+            .then(|| LauncherMode::Wsl(Some(distro.to_owned())))
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum SubmitKey {
+    Enter,
+    CtrlEnter,
+}
+
+impl SubmitKey {
+    pub const ALL: [SubmitKey; 2] = [SubmitKey::Enter, SubmitKey::CtrlEnter];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            SubmitKey::Enter => "Enter sends",
+            SubmitKey::CtrlEnter => "Ctrl+Enter sends",
+        }
+    }
+
+    pub fn hint(self) -> &'static str {
+        match self {
+            SubmitKey::Enter => "Enter to send, Shift+Enter for a new line",
+            SubmitKey::CtrlEnter => "Ctrl+Enter to send, Enter for a new line",
+        }
+    }
+
+    fn setting_value(self) -> &'static str {
+        match self {
+            SubmitKey::Enter => "enter",
+            SubmitKey::CtrlEnter => "ctrl_enter",
+        }
+    }
+
+    fn parse(value: &str) -> Option<Self> {
+        SubmitKey::ALL
+            .into_iter()
+            .find(|key| value.eq_ignore_ascii_case(key.setting_value()))
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum PromptWhitespace {
+    Trim,
+    TrimLineEnds,
+    Preserve,
+}
+
+impl PromptWhitespace {
+    pub const ALL: [PromptWhitespace; 3] = [
+        PromptWhitespace::Trim,
+        PromptWhitespace::TrimLineEnds,
+        PromptWhitespace::Preserve,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            PromptWhitespace::Trim => "Trim",
+            PromptWhitespace::TrimLineEnds => "Trim line ends",
+            PromptWhitespace::Preserve => "Preserve formatting",
+        }
+    }
+
+    pub fn hint(self) -> &'static str {
+        match self {
+            PromptWhitespace::Trim => "Strip leading and trailing whitespace",
+            PromptWhitespace::TrimLineEnds => "Keep indentation, drop trailing spaces per line",
+            PromptWhitespace::Preserve => "Send exactly as typed",
+        }
+    }
+
+    fn setting_value(self) -> &'static str {
+        match self {
+            PromptWhitespace::Trim => "trim",
+            PromptWhitespace::TrimLineEnds => "trim_line_ends",
+            PromptWhitespace::Preserve => "preserve",
+        }
+    }
+
+    fn parse(value: &str) -> Option<Self> {
+        PromptWhitespace::ALL
+            .into_iter()
+            .find(|mode| value.trim().eq_ignore_ascii_case(mode.setting_value()))
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum InputAssist {
+    FenceAutoClose,
+    PasteFence,
+    FenceTab,
+    PasteLineEndings,
+}
+
+impl InputAssist {
+    pub const ALL: [InputAssist; 4] = [
+        InputAssist::FenceAutoClose,
+        InputAssist::PasteFence,
+        InputAssist::FenceTab,
+        InputAssist::PasteLineEndings,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            InputAssist::FenceAutoClose => "Auto-close code fences",
+            InputAssist::PasteFence => "Offer to fence pastes",
+            InputAssist::FenceTab => "Tab indents in fences",
+            InputAssist::PasteLineEndings => "Normalize pasted line endings",
+        }
+    }
+
+    fn setting_key(self) -> &'static str {
+        match self {
+            InputAssist::FenceAutoClose => "fence_auto_close",
+            InputAssist::PasteFence => "paste_fence",
+            InputAssist::FenceTab => "fence_tab",
+            InputAssist::PasteLineEndings => "paste_line_endings",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct PromptBudget {
+    pub minutes: u64,
+    pub tool_events: usize,
+}
+
+impl PromptBudget {
+    pub fn is_unlimited(self) -> bool {
+        self.minutes == 0 && self.tool_events == 0
+    }
+
+    pub fn describe(self) -> String {
+        let mut limits = Vec::new();
+        if self.minutes > 0 {
+            limits.push(format!("{}m", self.minutes));
+        }
+        if self.tool_events > 0 {
+            limits.push(format!(
+                "{} tool {}",
+                self.tool_events,
+                if self.tool_events == 1 {
+                    "call"
+                } else {
+                    "calls"
+                }
+            ));
+        }
+        if limits.is_empty() {
+            "Off".to_owned()
+        } else {
+            limits.join(" / ")
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct WindowShadow {
+    pub enabled: bool,
+    pub blur: u8,
+    pub spread: u8,
+    pub color: Option<[u8; 4]>,
+}
+
+impl WindowShadow {
+    pub fn padding(self) -> f32 {
+        if self.enabled {
+            WINDOW_PADDING
+        } else {
+            SHADOWLESS_WINDOW_PADDING
+        }
+    }
+
+    pub fn bottom_padding(self) -> f32 {
+        self.padding() + WINDOW_BOTTOM_PADDING - WINDOW_PADDING
+    }
+}
 
 #[derive(Clone, Default)]
-pub(crate) struct PromptHistory {
-    pub(crate) prompts: Vec<String>,
+pub struct PromptHistory {
+    pub prompts: Vec<String>,
 }
 
-#[allow(dead_code)]
-pub(crate) fn read_config() -> io::Result<HashMap<String, String>> {
+pub fn build_stamp() -> String {
+    let built_at = BUILD_TIME
+        .parse::<i64>()
+        .map_or_else(|_| "unknown".to_owned(), logging::format_timestamp);
+    format!(
+        "{} ({}, built {} UTC)",
+        APP_VERSION, BUILD_GIT_HASH, built_at
+    )
+}
+
+pub fn read_config() -> io::Result<HashMap<String, String>> {
     let path = default_config_path();
-    logging::log_result(read_config_path(&path), |error| {
+    logging::log_result(read_settings(), |error| {
         format!("failed to read config {}: {}", path.display(), error)
     })
 }
 
 #[allow(dead_code)]
-pub(crate) fn write() -> io::Result<()> {
+pub fn write() -> io::Result<()> {
     let settings = read_config()?;
     let path = default_config_path();
-    logging::log_result(overwrite_config(&path, &settings), |error| {
+    logging::log_result(store::stage_settings(settings), |error| {
         format!("failed to write config {}: {}", path.display(), error)
     })
 }
 
-pub(crate) fn load_notifications_enabled() -> io::Result<bool> {
+pub fn load_notifications_enabled() -> io::Result<bool> {
+    load_toggle_setting(NOTIFICATION_SETTING_KEY, DEFAULT_NOTIFICATIONS_ENABLED)
+}
+
+pub fn load_project_config_enabled() -> io::Result<bool> {
+    load_toggle_setting(PROJECT_CONFIG_SETTING_KEY, DEFAULT_PROJECT_CONFIG_ENABLED)
+}
+
+pub fn set_project_config_enabled(enabled: bool) -> io::Result<bool> {
+    write_setting(
+        PROJECT_CONFIG_SETTING_KEY,
+        notification_setting_value(enabled),
+    )?;
+    Ok(enabled)
+}
+
+pub fn load_project_trust(project: &str) -> io::Result<Option<String>> {
+    let settings = read_settings()?;
+    Ok(settings
+        .get(&format!("{}{}", TRUSTED_PROJECT_SETTING_PREFIX, project))
+        .cloned())
+}
+
+pub fn set_project_trust(project: &str, fingerprint: &str) -> io::Result<()> {
+    write_setting(
+        &format!("{}{}", TRUSTED_PROJECT_SETTING_PREFIX, project),
+        fingerprint,
+    )
+}
+
+pub fn load_dirty_tree_warning_enabled() -> io::Result<bool> {
+    load_toggle_setting(
+        DIRTY_TREE_WARNING_SETTING_KEY,
+        DEFAULT_DIRTY_TREE_WARNING_ENABLED,
+    )
+}
+
+pub fn set_dirty_tree_warning_enabled(enabled: bool) -> io::Result<bool> {
+    write_setting(
+        DIRTY_TREE_WARNING_SETTING_KEY,
+        notification_setting_value(enabled),
+    )?;
+    Ok(enabled)
+}
+
+pub fn load_suggestions_enabled() -> io::Result<bool> {
+    load_toggle_setting(SUGGESTIONS_SETTING_KEY, DEFAULT_SUGGESTIONS_ENABLED)
+}
+
+pub fn load_auto_continue() -> io::Result<bool> {
+    load_toggle_setting(AUTO_CONTINUE_SETTING_KEY, DEFAULT_AUTO_CONTINUE)
+}
+
+pub fn load_cancel_on_sleep() -> io::Result<bool> {
+    load_toggle_setting(CANCEL_ON_SLEEP_SETTING_KEY, DEFAULT_CANCEL_ON_SLEEP)
+}
+
+pub fn load_clipboard_watch() -> io::Result<bool> {
+    load_toggle_setting(CLIPBOARD_WATCH_SETTING_KEY, DEFAULT_CLIPBOARD_WATCH)
+}
+
+pub fn load_concise_answers() -> io::Result<bool> {
+    load_toggle_setting(CONCISE_ANSWERS_SETTING_KEY, DEFAULT_CONCISE_ANSWERS)
+}
+
+pub fn load_warm_start() -> io::Result<bool> {
+    load_toggle_setting(WARM_START_SETTING_KEY, DEFAULT_WARM_START)
+}
+
+pub fn load_codex_titles() -> io::Result<bool> {
+    load_toggle_setting(CODEX_TITLES_SETTING_KEY, DEFAULT_CODEX_TITLES)
+}
+
+pub fn load_topic_suggestions_enabled() -> io::Result<bool> {
+    load_toggle_setting(
+        TOPIC_SUGGESTIONS_SETTING_KEY,
+        DEFAULT_TOPIC_SUGGESTIONS_ENABLED,
+    )
+}
+
+pub fn load_input_assist(assist: InputAssist) -> io::Result<bool> {
+    load_toggle_setting(assist.setting_key(), DEFAULT_INPUT_ASSIST_ENABLED)
+}
+
+pub fn load_export_turn_markers() -> io::Result<bool> {
+    load_toggle_setting(EXPORT_TURN_MARKERS_SETTING_KEY, DEFAULT_EXPORT_TURN_MARKERS)
+}
+
+pub fn load_redact_secrets() -> io::Result<bool> {
+    load_toggle_setting(REDACT_SECRETS_SETTING_KEY, DEFAULT_REDACT_SECRETS)
+}
+
+pub fn set_redact_secrets(enabled: bool) -> io::Result<bool> {
+    write_setting(
+        REDACT_SECRETS_SETTING_KEY,
+        notification_setting_value(enabled),
+    )?;
+    Ok(enabled)
+}
+
+pub fn load_compact_mode() -> io::Result<bool> {
+    load_toggle_setting(COMPACT_MODE_SETTING_KEY, DEFAULT_COMPACT_MODE)
+}
+
+pub fn set_compact_mode(enabled: bool) -> io::Result<bool> {
+    write_setting(
+        COMPACT_MODE_SETTING_KEY,
+        notification_setting_value(enabled),
+    )?;
+    Ok(enabled)
+}
+
+pub fn load_exclude_from_capture() -> io::Result<bool> {
+    load_toggle_setting(
+        EXCLUDE_FROM_CAPTURE_SETTING_KEY,
+        DEFAULT_EXCLUDE_FROM_CAPTURE,
+    )
+}
+
+pub fn set_exclude_from_capture(enabled: bool) -> io::Result<bool> {
+    write_setting(
+        EXCLUDE_FROM_CAPTURE_SETTING_KEY,
+        notification_setting_value(enabled),
+    )?;
+    Ok(enabled)
+}
+
+pub fn load_ui_trace() -> io::Result<bool> {
+    let settings = read_settings()?;
+    Ok(settings
+        .get(UI_TRACE_SETTING_KEY)
+        .and_then(|value| parse_notification_value(value))
+        .unwrap_or(false))
+}
+
+pub fn load_stream_rate() -> io::Result<bool> {
+    let settings = read_settings()?;
+    Ok(settings
+        .get(STREAM_RATE_SETTING_KEY)
+        .and_then(|value| parse_notification_value(value))
+        .unwrap_or(false))
+}
+
+pub fn load_record_events() -> io::Result<bool> {
+    let settings = read_settings()?;
+    Ok(settings
+        .get(RECORD_EVENTS_SETTING_KEY)
+        .and_then(|value| parse_notification_value(value))
+        .unwrap_or(false))
+}
+
+pub fn load_inspect_events() -> io::Result<bool> {
+    load_toggle_setting(INSPECT_EVENTS_SETTING_KEY, DEFAULT_INSPECT_EVENTS)
+}
+
+pub fn set_inspect_events(enabled: bool) -> io::Result<bool> {
+    write_setting(
+        INSPECT_EVENTS_SETTING_KEY,
+        notification_setting_value(enabled),
+    )?;
+    Ok(enabled)
+}
+
+pub fn load_persist_transcripts() -> io::Result<bool> {
+    load_toggle_setting(PERSIST_TRANSCRIPTS_SETTING_KEY, DEFAULT_PERSIST_TRANSCRIPTS)
+}
+
+pub fn set_persist_transcripts(enabled: bool) -> io::Result<bool> {
+    write_setting(
+        PERSIST_TRANSCRIPTS_SETTING_KEY,
+        notification_setting_value(enabled),
+    )?;
+    apply_persist_transcripts(enabled);
+    if !enabled {
+        forget_transcripts();
+    }
+    Ok(enabled)
+}
+
+fn forget_transcripts() {
+    let paths = paths::get();
+    for path in [paths.history_file.as_path(), paths.draft_file.as_path()] {
+        let result = match fs::remove_file(path) {
+            Err(error) if error.kind() == io::ErrorKind::NotFound => Ok(()),
+            result => result,
+        };
+        let _ = logging::log_result(result, |error| {
+            format!(
+                "failed to remove transcript file {}: {}",
+                path.display(),
+                error
+            )
+        });
+    }
+    let attachments = paths.attachments_dir.as_path();
+    let result = match fs::remove_dir_all(attachments) {
+        Err(error) if error.kind() == io::ErrorKind::NotFound => Ok(()),
+        result => result,
+    };
+    let _ = logging::log_result(result, |error| {
+        format!(
+            "failed to remove prompt attachments {}: {}",
+            attachments.display(),
+            error
+        )
+    });
+    snapshot::clear_snapshot();
+    snapshot::forget_recent_sessions();
+}
+
+pub fn apply_persist_transcripts(enabled: bool) {
+    PERSIST_TRANSCRIPTS.store(enabled, Ordering::Relaxed);
+}
+
+pub fn transcripts_persisted() -> bool {
+    PERSIST_TRANSCRIPTS.load(Ordering::Relaxed)
+}
+
+pub fn apply_disk_space_low(low: bool) {
+    DISK_SPACE_LOW.store(low, Ordering::Relaxed);
+}
+
+pub fn disk_space_low() -> bool {
+    DISK_SPACE_LOW.load(Ordering::Relaxed)
+}
+
+pub fn load_prompt_transformers() -> io::Result<Vec<Transformer>> {
+    let mut enabled = Vec::with_capacity(TRANSFORMERS.len());
+    for transformer in TRANSFORMERS {
+        if load_toggle_setting(
+            &transformer_setting_key(transformer),
+            DEFAULT_PROMPT_TRANSFORMER_ENABLED,
+        )? {
+            enabled.push(transformer);
+        }
+    }
+    Ok(enabled)
+}
+
+pub fn set_prompt_transformer_enabled(transformer: Transformer, enabled: bool) -> io::Result<bool> {
+    write_setting(
+        &transformer_setting_key(transformer),
+        notification_setting_value(enabled),
+    )?;
+    Ok(enabled)
+}
+
+fn transformer_setting_key(transformer: Transformer) -> String {
+    let name = match transformer {
+        Transformer::TrailingWhitespace => "trailing_whitespace",
+        Transformer::FileReferences => "file_references",
+        Transformer::Clipboard => "clipboard",
+    };
+    format!("{}{}", TRANSFORM_SETTING_PREFIX, name)
+}
+
+pub fn load_output_processors() -> io::Result<Vec<OutputProcessor>> {
+    let mut enabled = Vec::with_capacity(OUTPUT_PROCESSORS.len());
+    for processor in OUTPUT_PROCESSORS {
+        if load_toggle_setting(
+            &output_processor_setting_key(processor),
+            DEFAULT_OUTPUT_PROCESSOR_ENABLED,
+        )? {
+            enabled.push(processor);
+        }
+    }
+    Ok(enabled)
+}
+
+pub fn set_output_processor_enabled(processor: OutputProcessor, enabled: bool) -> io::Result<bool> {
+    write_setting(
+        &output_processor_setting_key(processor),
+        notification_setting_value(enabled),
+    )?;
+    Ok(enabled)
+}
+
+fn output_processor_setting_key(processor: OutputProcessor) -> String {
+    let name = match processor {
+        OutputProcessor::WorkingDirectory => "working_directory",
+        OutputProcessor::TrailingWhitespace => "trailing_whitespace",
+        OutputProcessor::BlankLines => "blank_lines",
+    };
+    format!("{}{}", OUTPUT_PROCESSOR_SETTING_PREFIX, name)
+}
+
+pub fn load_redact_patterns() -> io::Result<Vec<String>> {
+    let settings = read_settings()?;
+    Ok(settings
+        .get(REDACT_PATTERNS_SETTING_KEY)
+        .map(|patterns| {
+            patterns
+                .split(',')
+                .map(str::trim)
+                .filter(|pattern| !pattern.is_empty())
+                .map(ToOwned::to_owned)
+                .collect()
+        })
+        .unwrap_or_default())
+}
+
+pub fn set_suggestions_enabled(enabled: bool) -> io::Result<bool> {
+    write_setting(SUGGESTIONS_SETTING_KEY, notification_setting_value(enabled))?;
+    Ok(enabled)
+}
+
+pub fn set_auto_continue(enabled: bool) -> io::Result<bool> {
+    write_setting(
+        AUTO_CONTINUE_SETTING_KEY,
+        notification_setting_value(enabled),
+    )?;
+    Ok(enabled)
+}
+
+pub fn set_cancel_on_sleep(enabled: bool) -> io::Result<bool> {
+    write_setting(
+        CANCEL_ON_SLEEP_SETTING_KEY,
+        notification_setting_value(enabled),
+    )?;
+    Ok(enabled)
+}
+
+pub fn set_clipboard_watch(enabled: bool) -> io::Result<bool> {
+    write_setting(
+        CLIPBOARD_WATCH_SETTING_KEY,
+        notification_setting_value(enabled),
+    )?;
+    Ok(enabled)
+}
+
+pub fn set_concise_answers(enabled: bool) -> io::Result<bool> {
+    write_setting(
+        CONCISE_ANSWERS_SETTING_KEY,
+        notification_setting_value(enabled),
+    )?;
+    Ok(enabled)
+}
+
+pub fn set_warm_start(enabled: bool) -> io::Result<bool> {
+    write_setting(WARM_START_SETTING_KEY, notification_setting_value(enabled))?;
+    Ok(enabled)
+}
+
+pub fn set_codex_titles(enabled: bool) -> io::Result<bool> {
+    write_setting(
+        CODEX_TITLES_SETTING_KEY,
+        notification_setting_value(enabled),
+    )?;
+    Ok(enabled)
+}
+
+pub fn set_topic_suggestions_enabled(enabled: bool) -> io::Result<bool> {
+    write_setting(
+        TOPIC_SUGGESTIONS_SETTING_KEY,
+        notification_setting_value(enabled),
+    )?;
+    Ok(enabled)
+}
+
+pub fn set_input_assist(assist: InputAssist, enabled: bool) -> io::Result<bool> {
+    write_setting(assist.setting_key(), notification_setting_value(enabled))?;
+    Ok(enabled)
+}
+
+pub fn load_on_complete_hook() -> io::Result<Option<String>> {
+    let settings = read_settings()?;
+    Ok(settings
+        .get(ON_COMPLETE_SETTING_KEY)
+        .map(|command| command.trim())
+        .filter(|command| !command.is_empty())
+        .map(str::to_owned))
+}
+
+pub fn load_model_prices() -> io::Result<HashMap<String, String>> {
+    let settings = read_settings()?;
+    Ok(settings
+        .into_iter()
+        .filter_map(|(key, value)| {
+            key.strip_prefix(PRICE_SETTING_PREFIX)
+                .map(|model| (model.to_owned(), value))
+        })
+        .collect())
+}
+
+pub fn load_env_overrides() -> io::Result<Vec<(String, String)>> {
+    let settings = read_settings()?;
+    let mut overrides: Vec<(String, String)> = settings
+        .into_iter()
+        .filter_map(|(key, value)| {
+            key.strip_prefix(ENV_SETTING_PREFIX)
+                .filter(|name| !name.is_empty())
+                .map(|name| (name.to_owned(), value))
+        })
+        .collect();
+    overrides.sort();
+    Ok(overrides)
+}
+
+pub fn load_wake_mode() -> io::Result<WakeMode> {
+    let path = default_config_path();
+    let settings = read_settings()?;
+    let mode = settings
+        .get(WAKE_MODE_SETTING_KEY)
+        .and_then(|value| WakeMode::parse(value))
+        .unwrap_or(DEFAULT_WAKE_MODE);
+    if settings.get(WAKE_MODE_SETTING_KEY).map(String::as_str) != Some(mode.setting_value()) {
+        let change = [(WAKE_MODE_SETTING_KEY, mode.setting_value())];
+        logging::log_result(store::stage_settings(change), |error| {
+            format!(
+                "failed to persist {} setting to {}: {}",
+                WAKE_MODE_SETTING_KEY,
+                path.display(),
+                error
+            )
+        })?;
+    }
+    Ok(mode)
+}
+
+pub fn set_wake_mode(mode: WakeMode) -> io::Result<WakeMode> {
+    write_setting(WAKE_MODE_SETTING_KEY, mode.setting_value())?;
+    Ok(mode)
+}
+
+pub fn load_launcher_mode() -> io::Result<LauncherMode> {
+    let settings = read_settings()?;
+    Ok(settings
+        .get(LAUNCHER_MODE_SETTING_KEY)
+        .and_then(|value| LauncherMode::parse(value))
+        .unwrap_or(DEFAULT_LAUNCHER_MODE))
+}
+
+pub fn set_launcher_mode(mode: LauncherMode) -> io::Result<LauncherMode> {
+    write_setting(LAUNCHER_MODE_SETTING_KEY, &mode.setting_value())?;
+    Ok(mode)
+}
+
+pub fn load_submit_key() -> io::Result<SubmitKey> {
+    let settings = read_settings()?;
+    Ok(settings
+        .get(SUBMIT_KEY_SETTING_KEY)
+        .and_then(|value| SubmitKey::parse(value))
+        .unwrap_or(DEFAULT_SUBMIT_KEY))
+}
+
+pub fn set_submit_key(key: SubmitKey) -> io::Result<SubmitKey> {
+    write_setting(SUBMIT_KEY_SETTING_KEY, key.setting_value())?;
+    Ok(key)
+}
+
+pub fn load_prompt_whitespace() -> io::Result<PromptWhitespace> {
+    let settings = read_settings()?;
+    Ok(settings
+        .get(PROMPT_WHITESPACE_SETTING_KEY)
+        .and_then(|value| PromptWhitespace::parse(value))
+        .unwrap_or(DEFAULT_PROMPT_WHITESPACE))
+}
+
+pub fn set_prompt_whitespace(mode: PromptWhitespace) -> io::Result<PromptWhitespace> {
+    write_setting(PROMPT_WHITESPACE_SETTING_KEY, mode.setting_value())?;
+    Ok(mode)
+}
+
+pub fn load_token_warning_limit() -> io::Result<u64> {
+    let settings = read_settings()?;
+    Ok(settings
+        .get(TOKEN_WARNING_LIMIT_SETTING_KEY)
+        .and_then(|value| value.trim().parse::<u64>().ok())
+        .unwrap_or(DEFAULT_TOKEN_WARNING_LIMIT))
+}
+
+pub fn load_response_truncate_limit() -> io::Result<usize> {
+    let settings = read_settings()?;
+    Ok(settings
+        .get(RESPONSE_TRUNCATE_SETTING_KEY)
+        .and_then(|value| value.trim().parse::<usize>().ok())
+        .map_or(DEFAULT_RESPONSE_TRUNCATE_LIMIT, |kb| {
+            kb.saturating_mul(1024)
+        }))
+}
+
+pub fn load_prompt_attach_limit() -> io::Result<usize> {
+    let settings = read_settings()?;
+    Ok(settings
+        .get(PROMPT_ATTACH_SETTING_KEY)
+        .and_then(|value| value.trim().parse::<usize>().ok())
+        .map_or(DEFAULT_PROMPT_ATTACH_LIMIT, |kb| kb.saturating_mul(1024)))
+}
+
+pub fn set_prompt_attach_limit(limit: usize) -> io::Result<usize> {
+    write_setting(PROMPT_ATTACH_SETTING_KEY, &(limit / 1024).to_string())?;
+    Ok(limit)
+}
+
+pub fn load_prompt_attach_template() -> io::Result<String> {
+    let settings = read_settings()?;
+    Ok(settings
+        .get(PROMPT_ATTACH_TEMPLATE_SETTING_KEY)
+        .map(|template| template.trim())
+        .filter(|template| template.contains("{path}"))
+        .unwrap_or(DEFAULT_PROMPT_ATTACH_TEMPLATE)
+        .to_owned())
+}
+
+pub fn load_pinned_response_language() -> io::Result<Option<String>> {
+    let settings = read_settings()?;
+    Ok(settings
+        .get(RESPONSE_LANGUAGE_SETTING_KEY)
+        .map(|language| language.trim())
+        .filter(|language| {
+            !language.is_empty() && !language.eq_ignore_ascii_case(RESPONSE_LANGUAGE_UNPINNED)
+        })
+        .map(str::to_owned))
+}
+
+pub fn set_pinned_response_language(language: Option<&str>) -> io::Result<()> {
+    write_setting(
+        RESPONSE_LANGUAGE_SETTING_KEY,
+        language.unwrap_or(RESPONSE_LANGUAGE_UNPINNED),
+    )
+}
+
+pub fn load_interface_locale() -> io::Result<Option<String>> {
+    let settings = read_settings()?;
+    Ok(settings
+        .get(INTERFACE_LOCALE_SETTING_KEY)
+        .map(|locale| locale.trim())
+        .filter(|locale| {
+            !locale.is_empty() && !locale.eq_ignore_ascii_case(INTERFACE_LOCALE_SYSTEM)
+        })
+        .map(str::to_owned))
+}
+
+pub fn set_interface_locale(locale: Option<&str>) -> io::Result<()> {
+    write_setting(
+        INTERFACE_LOCALE_SETTING_KEY,
+        locale.unwrap_or(INTERFACE_LOCALE_SYSTEM),
+    )
+}
+
+pub fn load_stream_update_interval() -> io::Result<std::time::Duration> {
+    let settings = read_settings()?;
+    Ok(settings
+        .get(STREAM_UPDATE_INTERVAL_SETTING_KEY)
+        .and_then(|value| value.trim().parse::<u64>().ok())
+        .map_or(
+            DEFAULT_STREAM_UPDATE_INTERVAL,
+            std::time::Duration::from_millis,
+        ))
+}
+
+pub fn load_reasoning_window() -> io::Result<usize> {
+    let settings = read_settings()?;
+    Ok(settings
+        .get(REASONING_WINDOW_SETTING_KEY)
+        .and_then(|value| value.trim().parse::<usize>().ok())
+        .unwrap_or(DEFAULT_REASONING_WINDOW))
+}
+
+pub fn load_recent_session_limit() -> io::Result<usize> {
+    let settings = read_settings()?;
+    Ok(settings
+        .get(RECENT_SESSIONS_SETTING_KEY)
+        .and_then(|value| value.trim().parse::<usize>().ok())
+        .unwrap_or(DEFAULT_RECENT_SESSION_LIMIT))
+}
+
+pub fn load_disk_space_warning_mb() -> io::Result<u64> {
+    let settings = read_settings()?;
+    Ok(settings
+        .get(DISK_SPACE_WARNING_SETTING_KEY)
+        .and_then(|value| value.trim().parse::<u64>().ok())
+        .unwrap_or(DEFAULT_DISK_SPACE_WARNING_MB))
+}
+
+pub fn load_reasoning_retention_limit() -> io::Result<usize> {
+    let settings = read_settings()?;
+    Ok(settings
+        .get(REASONING_RETENTION_SETTING_KEY)
+        .and_then(|value| value.trim().parse::<usize>().ok())
+        .map_or(DEFAULT_REASONING_RETENTION_LIMIT, |kb| {
+            kb.saturating_mul(1024)
+        }))
+}
+
+pub fn load_prompt_budget() -> io::Result<PromptBudget> {
+    let settings = read_settings()?;
+    Ok(PromptBudget {
+        minutes: settings
+            .get(BUDGET_MINUTES_SETTING_KEY)
+            .and_then(|value| value.trim().parse::<u64>().ok())
+            .unwrap_or_default(),
+        tool_events: settings
+            .get(BUDGET_TOOL_EVENTS_SETTING_KEY)
+            .and_then(|value| value.trim().parse::<usize>().ok())
+            .unwrap_or_default(),
+    })
+}
+
+pub fn set_prompt_budget(budget: PromptBudget) -> io::Result<PromptBudget> {
+    write_settings(&HashMap::from([
+        (
+            BUDGET_MINUTES_SETTING_KEY.to_owned(),
+            budget.minutes.to_string(),
+        ),
+        (
+            BUDGET_TOOL_EVENTS_SETTING_KEY.to_owned(),
+            budget.tool_events.to_string(),
+        ),
+    ]))?;
+    Ok(budget)
+}
+
+pub fn load_pending_style() -> io::Result<PendingStyle> {
+    let settings = read_settings()?;
+    Ok(settings
+        .get(PENDING_STYLE_SETTING_KEY)
+        .and_then(|value| PendingStyle::parse(value))
+        .unwrap_or(DEFAULT_PENDING_STYLE))
+}
+
+pub fn set_pending_style(style: PendingStyle) -> io::Result<PendingStyle> {
+    write_setting(PENDING_STYLE_SETTING_KEY, style.setting_value())?;
+    Ok(style)
+}
+
+pub fn load_theme() -> io::Result<Theme> {
+    let settings = read_settings()?;
+    Ok(settings
+        .get(THEME_SETTING_KEY)
+        .and_then(|value| Theme::parse(value))
+        .unwrap_or(DEFAULT_THEME))
+}
+
+pub fn set_theme(theme: Theme) -> io::Result<Theme> {
+    write_setting(THEME_SETTING_KEY, theme.setting_value())?;
+    Ok(theme)
+}
+
+pub fn load_response_dimming() -> io::Result<ResponseDimming> {
+    let settings = read_settings()?;
+    Ok(settings
+        .get(RESPONSE_DIMMING_SETTING_KEY)
+        .and_then(|value| ResponseDimming::parse(value))
+        .unwrap_or(DEFAULT_RESPONSE_DIMMING))
+}
+
+pub fn set_response_dimming(dimming: ResponseDimming) -> io::Result<ResponseDimming> {
+    write_setting(RESPONSE_DIMMING_SETTING_KEY, dimming.setting_value())?;
+    Ok(dimming)
+}
+
+pub fn load_pending_interval() -> io::Result<std::time::Duration> {
+    let settings = read_settings()?;
+    Ok(settings
+        .get(PENDING_INTERVAL_SETTING_KEY)
+        .and_then(|value| value.trim().parse::<u64>().ok())
+        .map_or(DEFAULT_PENDING_ANIMATION_INTERVAL, |ms| {
+            std::time::Duration::from_millis(ms).max(MIN_PENDING_ANIMATION_INTERVAL)
+        }))
+}
+
+pub fn set_token_warning_limit(limit: u64) -> io::Result<u64> {
+    write_setting(TOKEN_WARNING_LIMIT_SETTING_KEY, &limit.to_string())?;
+    Ok(limit)
+}
+
+pub fn load_window_shadow() -> io::Result<WindowShadow> {
+    let enabled = load_toggle_setting(WINDOW_SHADOW_SETTING_KEY, DEFAULT_WINDOW_SHADOW.enabled)?;
+    let settings = read_settings()?;
+    let number = |key: &str, default: u8| {
+        settings
+            .get(key)
+            .and_then(|value| value.trim().parse::<u8>().ok())
+            .unwrap_or(default)
+    };
+    Ok(WindowShadow {
+        enabled,
+        blur: number(WINDOW_SHADOW_BLUR_SETTING_KEY, DEFAULT_WINDOW_SHADOW.blur),
+        spread: number(
+            WINDOW_SHADOW_SPREAD_SETTING_KEY,
+            DEFAULT_WINDOW_SHADOW.spread,
+        ),
+        color: settings
+            .get(WINDOW_SHADOW_COLOR_SETTING_KEY)
+            .and_then(|value| parse_color(value)),
+    })
+}
+
+pub fn set_window_shadow_enabled(enabled: bool) -> io::Result<bool> {
+    write_setting(
+        WINDOW_SHADOW_SETTING_KEY,
+        notification_setting_value(enabled),
+    )?;
+    Ok(enabled)
+}
+
+fn parse_color(value: &str) -> Option<[u8; 4]> {
+    let hex = value.trim().trim_start_matches('#');
+    if !hex.is_ascii() || (hex.len() != 6 && hex.len() != 8) {
+        return None;
+    }
+    let channel = |index: usize| u8::from_str_radix(&hex[index * 2..index * 2 + 2], 16).ok();
+    Some([
+        channel(0)?,
+        channel(1)?,
+        channel(2)?,
+        if hex.len() == 8 { channel(3)? } else { 255 },
+    ])
+}
+
+fn load_toggle_setting(key: &str, default: bool) -> io::Result<bool> {
     let path = default_config_path();
-    let mut settings = read_config_path(&path)?;
+    let settings = read_settings()?;
     let enabled = settings
-        .get(NOTIFICATION_SETTING_KEY)
+        .get(key)
         .and_then(|value| parse_notification_value(value))
-        .unwrap_or(DEFAULT_NOTIFICATIONS_ENABLED);
+        .unwrap_or(default);
     let expected = notification_setting_value(enabled);
-    if settings.get(NOTIFICATION_SETTING_KEY).map(String::as_str) != Some(expected) {
-        settings.insert(NOTIFICATION_SETTING_KEY.to_owned(), expected.to_owned());
-        logging::log_result(overwrite_config(&path, &settings), |error| {
+    if settings.get(key).map(String::as_str) != Some(expected) {
+        logging::log_result(store::stage_settings([(key, expected)]), |error| {
             format!(
-                "failed to persist notification setting to {}: {}",
+                "failed to persist {} setting to {}: {}",
+                key,
                 path.display(),
                 error
             )
@@ -84,7 +1312,21 @@ pub(crate) fn load_notifications_enabled() -> io::Result<bool> {
     Ok(enabled)
 }
 
-pub(crate) fn set_notifications_enabled(enabled: bool) -> io::Result<bool> {
+pub fn load_onboarding_pending() -> io::Result<bool> {
+    let path = default_config_path();
+    if !path.exists() {
+        write_setting(ONBOARDING_SETTING_KEY, "pending")?;
+        return Ok(true);
+    }
+    let settings = read_settings()?;
+    Ok(settings.get(ONBOARDING_SETTING_KEY).map(String::as_str) == Some("pending"))
+}
+
+pub fn set_onboarding_complete() -> io::Result<()> {
+    write_setting(ONBOARDING_SETTING_KEY, "done")
+}
+
+pub fn set_notifications_enabled(enabled: bool) -> io::Result<bool> {
     write_setting(
         NOTIFICATION_SETTING_KEY,
         notification_setting_value(enabled),
@@ -93,7 +1335,7 @@ pub(crate) fn set_notifications_enabled(enabled: bool) -> io::Result<bool> {
 }
 
 #[allow(dead_code)]
-pub(crate) fn write_setting(label: &str, value: &str) -> io::Result<()> {
+pub fn write_setting(label: &str, value: &str) -> io::Result<()> {
     if label.is_empty() {
         return Err(io::Error::new(
             io::ErrorKind::InvalidInput,
@@ -108,9 +1350,7 @@ pub(crate) fn write_setting(label: &str, value: &str) -> io::Result<()> {
     }
 
     let path = default_config_path();
-    let mut settings = read_config_path(&path)?;
-    settings.insert(label.to_owned(), value.to_owned());
-    logging::log_result(overwrite_config(&path, &settings), |error| {
+    logging::log_result(store::stage_settings([(label, value)]), |error| {
         format!(
             "failed to write setting {} to {}: {}",
             label,
@@ -121,25 +1361,22 @@ pub(crate) fn write_setting(label: &str, value: &str) -> io::Result<()> {
 }
 
 #[allow(dead_code)]
-pub(crate) fn write_settings(new_config: &HashMap<String, String>) -> io::Result<()> {
+pub fn write_settings(new_config: &HashMap<String, String>) -> io::Result<()> {
     if new_config.is_empty() {
         return Ok(());
     }
 
     let path = default_config_path();
-    let mut settings = read_config_path(&path)?;
-    settings.extend(
-        new_config
-            .iter()
-            .map(|(key, value)| (key.clone(), value.clone())),
-    );
-    logging::log_result(overwrite_config(&path, &settings), |error| {
+    let changes = new_config
+        .iter()
+        .map(|(key, value)| (key.clone(), value.clone()));
+    logging::log_result(store::stage_settings(changes), |error| {
         format!("failed to write settings to {}: {}", path.display(), error)
     })
 }
 
-pub(crate) fn load_prompt_history() -> io::Result<PromptHistory> {
-    let path = Path::new(PROMPT_HISTORY_PATH);
+pub fn load_prompt_history() -> io::Result<PromptHistory> {
+    let path = paths::get().history_file.as_path();
     let settings = logging::log_result(read_config_path(path), |error| {
         format!(
             "failed to read prompt history {}: {}",
@@ -156,17 +1393,21 @@ pub(crate) fn load_prompt_history() -> io::Result<PromptHistory> {
     })
 }
 
-pub(crate) fn save_prompt_history(history: &PromptHistory) -> io::Result<()> {
+pub fn save_prompt_history(history: &PromptHistory) -> io::Result<()> {
     save_prompt_history_prompts(&history.prompts)
 }
 
-pub(crate) fn save_prompt_history_prompts(prompts: &[String]) -> io::Result<()> {
-    let path = Path::new(PROMPT_HISTORY_PATH);
+pub fn save_prompt_history_prompts(prompts: &[String]) -> io::Result<()> {
+    if !transcripts_persisted() {
+        return Ok(());
+    }
+    let path = paths::get().history_file.as_path();
+    let redacted: Vec<String> = prompts
+        .iter()
+        .map(|prompt| redact::redact_persistent(prompt).into_owned())
+        .collect();
     logging::log_result(
-        overwrite_config(
-            Path::new(PROMPT_HISTORY_PATH),
-            &prompt_history_to_settings_slice(prompts),
-        ),
+        overwrite_config(path, &prompt_history_to_settings_slice(&redacted)),
         |error| {
             format!(
                 "failed to save prompt history {}: {}",
@@ -177,9 +1418,63 @@ pub(crate) fn save_prompt_history_prompts(prompts: &[String]) -> io::Result<()>
     )
 }
 
-#[allow(dead_code)]
-fn default_config_path() -> PathBuf {
-    PathBuf::from(r"C:\Local\Config").join("CodexAgent.ini")
+pub fn load_prompt_draft() -> io::Result<String> {
+    read_prompt_draft(&paths::get().draft_file)
+}
+
+pub fn save_prompt_draft(draft: &str) -> io::Result<()> {
+    let path = paths::get().draft_file.as_path();
+    logging::log_result(
+        write_prompt_draft(path, draft, transcripts_persisted()),
+        |error| format!("failed to save prompt draft {}: {}", path.display(), error),
+    )
+}
+
+fn read_prompt_draft(path: &Path) -> io::Result<String> {
+    match fs::read(path) {
+        Ok(bytes) => Ok(String::from_utf8_lossy(&bytes).into_owned()),
+        Err(error) if error.kind() == io::ErrorKind::NotFound => Ok(String::new()),
+        Err(error) => Err(error),
+    }
+}
+
+fn write_prompt_draft(path: &Path, draft: &str, persisted: bool) -> io::Result<()> {
+    if draft.trim().is_empty() || !persisted {
+        return match fs::remove_file(path) {
+            Err(error) if error.kind() == io::ErrorKind::NotFound => Ok(()),
+            result => result,
+        };
+    }
+    crate::runtime::write_file_atomic(path, redact::redact_persistent(draft).as_bytes(), false)
+}
+
+pub fn load_codex_seed_state() -> io::Result<HashMap<String, String>> {
+    let path = paths::get().seed_state_file.as_path();
+    logging::log_result(read_config_path(path), |error| {
+        format!(
+            "failed to read codex seed state {}: {}",
+            path.display(),
+            error
+        )
+    })
+}
+
+pub fn save_codex_seed_state(state: &HashMap<String, String>) -> io::Result<()> {
+    let path = paths::get().seed_state_file.as_path();
+    logging::log_result(
+        crate::runtime::write_file_atomic(path, config_buffer(state).as_bytes(), false),
+        |error| {
+            format!(
+                "failed to save codex seed state {}: {}",
+                path.display(),
+                error
+            )
+        },
+    )
+}
+
+fn default_config_path() -> &'static Path {
+    &paths::get().settings_file
 }
 
 fn notification_setting_value(enabled: bool) -> &'static str {
@@ -204,6 +1499,10 @@ fn parse_notification_value(value: &str) -> Option<bool> {
     None
 }
 
+pub fn read_settings() -> io::Result<HashMap<String, String>> {
+    store::read_settings(default_config_path())
+}
+
 fn read_config_path(path: &Path) -> io::Result<HashMap<String, String>> {
     let raw_config = get_config(path)?;
     Ok(parse_config(&raw_config))
@@ -245,7 +1544,10 @@ fn get_config(path: &Path) -> io::Result<Vec<u8>> {
 
 fn overwrite_config(path: &Path, current: &HashMap<String, String>) -> io::Result<()> {
     ensure_path(path)?;
+    fs::write(path, config_buffer(current))
+}
 
+fn config_buffer(current: &HashMap<String, String>) -> String {
     let mut keys: Vec<_> = current.keys().collect();
     keys.sort_unstable();
 
@@ -261,8 +1563,7 @@ fn overwrite_config(path: &Path, current: &HashMap<String, String>) -> io::Resul
             buffer.push('\n');
         }
     }
-
-    fs::write(path, buffer)
+    buffer
 }
 
 fn ensure_path(path: &Path) -> io::Result<()> {
@@ -307,7 +1608,7 @@ fn prompt_history_from_settings(settings: &HashMap<String, String>) -> io::Resul
     Ok(PromptHistory { prompts })
 }
 
-pub(crate) fn trim_prompt_history(prompts: &mut Vec<String>) {
+pub fn trim_prompt_history(prompts: &mut Vec<String>) {
     if prompts.len() > MAX_PROMPT_HISTORY {
         let overflow = prompts.len() - MAX_PROMPT_HISTORY;
         prompts.drain(0..overflow);
@@ -423,7 +1724,7 @@ fn prompt_history_hex_len(prompts: &[String]) -> usize {
 }
 
 fn decode_hex_to_string(value: &str) -> io::Result<String> {
-    if value.len() % 2 != 0 {
+    if !value.len().is_multiple_of(2) {
         return Err(io::Error::new(
             io::ErrorKind::InvalidData,
             "hex value has odd length",
@@ -445,3 +1746,88 @@ fn decode_hex_to_string(value: &str) -> io::Result<String> {
 
     String::from_utf8(bytes).map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))
 }
+
+#[cfg(test)]
+mod tests {
+    use std::{env, process};
+
+    use super::*;
+
+    #[test]
+    fn prompt_drafts_round_trip() {
+        let dir = env::temp_dir().join(format!("codexagent-draft-{}", process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("CodexAgent.draft");
+        assert_eq!(read_prompt_draft(&path).unwrap(), "");
+
+        write_prompt_draft(&path, "refactor the parser\n", true).unwrap();
+        assert_eq!(read_prompt_draft(&path).unwrap(), "refactor the parser\n");
+
+        let large = "explain this line of the build log\n".repeat(40_000);
+        write_prompt_draft(&path, &large, true).unwrap();
+        assert_eq!(read_prompt_draft(&path).unwrap(), large);
+
+        write_prompt_draft(&path, "  \n", true).unwrap();
+        assert!(!path.exists());
+        assert_eq!(read_prompt_draft(&path).unwrap(), "");
+        write_prompt_draft(&path, "", true).unwrap();
+
+        write_prompt_draft(&path, "kept in memory only", false).unwrap();
+        assert!(!path.exists());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn response_dimming_setting_values_round_trip() {
+        for dimming in ResponseDimming::ALL {
+            assert_eq!(
+                ResponseDimming::parse(dimming.setting_value()),
+                Some(dimming)
+            );
+        }
+        assert_eq!(
+            ResponseDimming::parse(" Subtle "),
+            Some(ResponseDimming::Subtle)
+        );
+        assert_eq!(ResponseDimming::parse("dim"), None);
+    }
+
+    #[test]
+    fn theme_resolution_matrix() {
+        let plain = AccessibilityPrefs::default();
+        let high_contrast = AccessibilityPrefs {
+            high_contrast: true,
+            ..plain
+        };
+        let reduced_transparency = AccessibilityPrefs {
+            reduced_transparency: true,
+            ..plain
+        };
+        let table = [
+            (Theme::Auto, plain, false, false),
+            (Theme::Auto, plain, true, true),
+            (Theme::Auto, high_contrast, false, true),
+            (Theme::Auto, high_contrast, true, true),
+            (Theme::Auto, reduced_transparency, false, true),
+            (Theme::Standard, plain, false, false),
+            (Theme::Standard, plain, true, false),
+            (Theme::Standard, high_contrast, false, false),
+            (Theme::Standard, high_contrast, true, false),
+            (Theme::Standard, reduced_transparency, true, false),
+            (Theme::HighContrast, plain, false, true),
+            (Theme::HighContrast, plain, true, true),
+            (Theme::HighContrast, high_contrast, false, true),
+            (Theme::HighContrast, high_contrast, true, true),
+        ];
+        for (theme, accessibility, system_light, opaque) in table {
+            assert_eq!(
+                theme.opaque(accessibility, system_light),
+                opaque,
+                "{:?} with {:?}, system light {}",
+                theme,
+                accessibility,
+                system_light
+            );
+        }
+    }
+}
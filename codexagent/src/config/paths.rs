@@ -0,0 +1,562 @@
+use std::env;
+use std::ffi::OsString;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+#[cfg(windows)]
+use windows_sys::Win32::Storage::FileSystem::{
+    FILE_ATTRIBUTE_NOT_CONTENT_INDEXED, GetFileAttributesW, INVALID_FILE_ATTRIBUTES,
+    SetFileAttributesW,
+};
+
+use crate::config::APP_NAME;
+use crate::logging;
+
+const PORTABLE_MARKERS: [&str; 2] = ["portable.txt", "autoagent.portable"];
+const PORTABLE_DATA_DIR: &str = "data";
+const CONFIG_DIR_ENV: &str = "CODEXAGENT_CONFIG_DIR";
+const LOG_DIR_ENV: &str = "CODEXAGENT_LOG_DIR";
+#[cfg(windows)]
+const LOCAL_APP_DATA_ENV: &str = "LOCALAPPDATA";
+#[cfg(windows)]
+const LEGACY_CONFIG_DIR: &str = r"C:\Local\Config";
+#[cfg(windows)]
+const LEGACY_LOGS_DIR: &str = r"C:\Local\Logs";
+
+static PATHS: OnceLock<Paths> = OnceLock::new();
+
+#[derive(Clone, Debug)]
+pub struct Paths {
+    pub portable: bool,
+    pub log_dir: PathBuf,
+    pub settings_file: PathBuf,
+    pub history_file: PathBuf,
+    pub seed_state_file: PathBuf,
+    pub running_process_file: PathBuf,
+    pub snapshot_file: PathBuf,
+    pub sessions_dir: PathBuf,
+    pub attachments_dir: PathBuf,
+    pub draft_file: PathBuf,
+}
+
+impl Paths {
+    pub fn detect() -> Self {
+        let exe_dir = env::current_exe()
+            .ok()
+            .and_then(|path| path.parent().map(Path::to_path_buf));
+        Self::resolve(exe_dir.as_deref())
+    }
+
+    pub fn resolve(exe_dir: Option<&Path>) -> Self {
+        match exe_dir.filter(|dir| is_portable_dir(dir)) {
+            Some(dir) => Self::portable(&dir.join(PORTABLE_DATA_DIR)),
+            None => Self::installed(),
+        }
+    }
+
+    fn installed() -> Self {
+        let (config_dir, log_dir) = installed_dirs();
+        let config_dir = env_dir(CONFIG_DIR_ENV).unwrap_or(config_dir);
+        let log_dir = env_dir(LOG_DIR_ENV).unwrap_or(log_dir);
+        Self::in_dirs(&config_dir, log_dir)
+    }
+
+    fn in_dirs(config_dir: &Path, log_dir: PathBuf) -> Self {
+        Self {
+            portable: false,
+            log_dir,
+            settings_file: config_dir.join("CodexAgent.ini"),
+            history_file: config_dir.join("CodexAgent.history"),
+            seed_state_file: config_dir.join("CodexAgent.seeds"),
+            running_process_file: config_dir.join("CodexAgent.pid"),
+            snapshot_file: config_dir.join("CodexAgent.snapshot"),
+            sessions_dir: config_dir.join("Sessions"),
+            attachments_dir: config_dir.join("Attachments"),
+            draft_file: config_dir.join("CodexAgent.draft"),
+        }
+    }
+
+    pub fn portable(data_dir: &Path) -> Self {
+        Self {
+            portable: true,
+            log_dir: data_dir.join("Logs"),
+            settings_file: data_dir.join("CodexAgent.ini"),
+            history_file: data_dir.join("CodexAgent.history"),
+            seed_state_file: data_dir.join("CodexAgent.seeds"),
+            running_process_file: data_dir.join("CodexAgent.pid"),
+            snapshot_file: data_dir.join("CodexAgent.snapshot"),
+            sessions_dir: data_dir.join("Sessions"),
+            attachments_dir: data_dir.join("Attachments"),
+            draft_file: data_dir.join("CodexAgent.draft"),
+        }
+    }
+
+    pub fn config_dir(&self) -> &Path {
+        self.settings_file.parent().unwrap_or(&self.log_dir)
+    }
+
+    fn persisted_files(&self) -> [&Path; 6] {
+        [
+            &self.settings_file,
+            &self.history_file,
+            &self.seed_state_file,
+            &self.running_process_file,
+            &self.snapshot_file,
+            &self.draft_file,
+        ]
+    }
+
+    fn persisted_dirs(&self) -> [&Path; 3] {
+        [&self.sessions_dir, &self.attachments_dir, &self.log_dir]
+    }
+}
+
+pub fn prepare_dirs(paths: &Paths) -> Vec<String> {
+    [paths.config_dir(), paths.log_dir.as_path()]
+        .into_iter()
+        .filter_map(|dir| {
+            fs::create_dir_all(dir)
+                .and_then(|()| exclude_from_indexing(dir))
+                .err()
+                .map(|error| {
+                    format!(
+                        "failed to prepare app data directory {}: {}",
+                        dir.display(),
+                        error
+                    )
+                })
+        })
+        .collect()
+}
+
+#[cfg(windows)]
+fn exclude_from_indexing(dir: &Path) -> io::Result<()> {
+    use std::os::windows::ffi::OsStrExt;
+
+    let wide: Vec<u16> = dir
+        .as_os_str()
+        .encode_wide()
+        .chain(std::iter::once(0))
+        .collect();
+    let attributes = unsafe { GetFileAttributesW(wide.as_ptr()) };
+    if attributes == INVALID_FILE_ATTRIBUTES {
+        return Err(io::Error::last_os_error());
+    }
+    if attributes & FILE_ATTRIBUTE_NOT_CONTENT_INDEXED != 0 {
+        return Ok(());
+    }
+    if unsafe {
+        SetFileAttributesW(
+            wide.as_ptr(),
+            attributes | FILE_ATTRIBUTE_NOT_CONTENT_INDEXED,
+        )
+    } == 0
+    {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+#[cfg(not(windows))]
+fn exclude_from_indexing(_dir: &Path) -> io::Result<()> {
+    Ok(())
+}
+
+#[cfg(not(windows))]
+pub fn migrate_legacy_files(_paths: &Paths) {}
+
+#[cfg(windows)]
+pub fn migrate_legacy_files(paths: &Paths) {
+    if paths.portable {
+        return;
+    }
+    let legacy = Paths::in_dirs(
+        Path::new(LEGACY_CONFIG_DIR),
+        PathBuf::from(LEGACY_LOGS_DIR).join(APP_NAME),
+    );
+    if paths.settings_file == legacy.settings_file
+        || paths.settings_file.exists()
+        || !legacy.settings_file.is_file()
+    {
+        return;
+    }
+    let moved = migrate_files(&legacy, paths);
+    logging::trace(format!(
+        "migrated {} legacy files from {}",
+        moved, LEGACY_CONFIG_DIR
+    ));
+}
+
+// Moves every persisted file and directory from one layout to another,
+// leaving anything already present at the destination untouched.
+pub fn migrate_files(from: &Paths, to: &Paths) -> usize {
+    let files = from
+        .persisted_files()
+        .into_iter()
+        .zip(to.persisted_files())
+        .filter(|(from, _)| from.is_file())
+        .filter(|(from, to)| move_file(from, to));
+    let dirs = from
+        .persisted_dirs()
+        .into_iter()
+        .zip(to.persisted_dirs())
+        .map(|(from, to)| move_dir_contents(from, to));
+    files.count() + dirs.sum::<usize>()
+}
+
+fn move_dir_contents(from: &Path, to: &Path) -> usize {
+    if from == to || !from.is_dir() {
+        return 0;
+    }
+    let entries = match fs::read_dir(from) {
+        Ok(entries) => entries,
+        Err(error) => {
+            logging::error(format!(
+                "failed to list legacy directory {}: {}",
+                from.display(),
+                error
+            ));
+            return 0;
+        }
+    };
+    let mut moved = 0;
+    for entry in entries.filter_map(Result::ok) {
+        let source = entry.path();
+        let target = to.join(entry.file_name());
+        if source.is_dir() {
+            moved += move_dir_contents(&source, &target);
+        } else if move_file(&source, &target) {
+            moved += 1;
+        }
+    }
+    let _ = fs::remove_dir(from);
+    moved
+}
+
+fn move_file(from: &Path, to: &Path) -> bool {
+    if to.exists() {
+        logging::trace(format!(
+            "keeping {}; legacy {} left in place",
+            to.display(),
+            from.display()
+        ));
+        return false;
+    }
+    if let Some(parent) = to.parent()
+        && let Err(error) = fs::create_dir_all(parent)
+    {
+        logging::error(format!(
+            "failed to create {} for legacy {}: {}",
+            parent.display(),
+            from.display(),
+            error
+        ));
+        return false;
+    }
+    // rename fails across volumes; copying and deleting still moves the file.
+    let result = fs::rename(from, to).or_else(|_| {
+        fs::copy(from, to)?;
+        fs::remove_file(from)
+    });
+    match result {
+        Ok(()) => {
+            logging::trace(format!("moved {} to {}", from.display(), to.display()));
+            true
+        }
+        Err(error) => {
+            logging::error(format!(
+                "failed to move legacy {} to {}: {}",
+                from.display(),
+                to.display(),
+                error
+            ));
+            false
+        }
+    }
+}
+
+pub fn init(paths: Paths) -> &'static Paths {
+    PATHS.get_or_init(|| paths)
+}
+
+pub fn get() -> &'static Paths {
+    PATHS.get_or_init(Paths::detect)
+}
+
+fn installed_dirs() -> (PathBuf, PathBuf) {
+    installed_dirs_from(|key| env::var_os(key))
+}
+
+#[cfg(windows)]
+fn installed_dirs_from(lookup: impl Fn(&str) -> Option<OsString>) -> (PathBuf, PathBuf) {
+    match lookup(LOCAL_APP_DATA_ENV).filter(|dir| !dir.is_empty()) {
+        Some(local) => {
+            let local = PathBuf::from(local);
+            let app_dir = local.join(APP_NAME);
+            (app_dir.join("Config"), app_dir.join("Logs"))
+        }
+        None => (
+            PathBuf::from(LEGACY_CONFIG_DIR),
+            PathBuf::from(LEGACY_LOGS_DIR).join(APP_NAME),
+        ),
+    }
+}
+
+#[cfg(not(windows))]
+fn installed_dirs_from(lookup: impl Fn(&str) -> Option<OsString>) -> (PathBuf, PathBuf) {
+    let home = lookup("HOME")
+        .filter(|home| !home.is_empty())
+        .map_or_else(|| PathBuf::from("."), PathBuf::from);
+    let xdg_dir = |key: &str, fallback: &[&str]| {
+        lookup(key).filter(|dir| !dir.is_empty()).map_or_else(
+            || {
+                fallback
+                    .iter()
+                    .fold(home.clone(), |dir, part| dir.join(part))
+            },
+            PathBuf::from,
+        )
+    };
+    (
+        xdg_dir("XDG_CONFIG_HOME", &[".config"]).join(APP_NAME),
+        xdg_dir("XDG_STATE_HOME", &[".local", "state"]).join(APP_NAME),
+    )
+}
+
+/// Where installed-mode settings and logs end up when the variables they are
+/// derived from are missing, for the startup environment report.
+#[cfg(windows)]
+pub fn path_degradation_from(
+    lookup: impl Fn(&str) -> Option<OsString>,
+) -> Vec<(&'static str, String)> {
+    let set = |key: &str| lookup(key).is_some_and(|value| !value.is_empty());
+    if set(LOCAL_APP_DATA_ENV) {
+        return Vec::new();
+    }
+    [
+        (CONFIG_DIR_ENV, "settings stored in", LEGACY_CONFIG_DIR),
+        (LOG_DIR_ENV, "logs written to", LEGACY_LOGS_DIR),
+    ]
+    .into_iter()
+    .filter(|(override_key, _, _)| !set(override_key))
+    .map(|(_, what, dir)| {
+        (
+            LOCAL_APP_DATA_ENV,
+            format!("{} {}: {} not set", what, dir, LOCAL_APP_DATA_ENV),
+        )
+    })
+    .collect()
+}
+
+#[cfg(not(windows))]
+pub fn path_degradation_from(
+    lookup: impl Fn(&str) -> Option<OsString>,
+) -> Vec<(&'static str, String)> {
+    let set = |key: &str| lookup(key).is_some_and(|value| !value.is_empty());
+    if set("HOME") {
+        return Vec::new();
+    }
+    [
+        (CONFIG_DIR_ENV, "XDG_CONFIG_HOME", "settings"),
+        (LOG_DIR_ENV, "XDG_STATE_HOME", "logs"),
+    ]
+    .into_iter()
+    .filter(|(override_key, xdg_key, _)| !set(override_key) && !set(xdg_key))
+    .map(|(_, _, what)| {
+        (
+            "HOME",
+            format!("{} stored in the working directory: HOME not set", what),
+        )
+    })
+    .collect()
+}
+
+fn env_dir(key: &str) -> Option<PathBuf> {
+    env::var_os(key)
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+}
+
+fn is_portable_dir(dir: &Path) -> bool {
+    PORTABLE_MARKERS
+        .iter()
+        .any(|marker| dir.join(marker).is_file())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lookup_from<'a>(vars: &'a [(&str, &str)]) -> impl Fn(&str) -> Option<OsString> + 'a {
+        |key| {
+            vars.iter()
+                .find(|(name, _)| *name == key)
+                .map(|(_, value)| OsString::from(value))
+        }
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn installed_dirs_live_under_local_app_data() {
+        let (config, logs) = installed_dirs_from(lookup_from(&[(
+            "LOCALAPPDATA",
+            r"C:\Users\dev\AppData\Local",
+        )]));
+        let app_dir = Path::new(r"C:\Users\dev\AppData\Local").join(APP_NAME);
+        assert_eq!(config, app_dir.join("Config"));
+        assert_eq!(logs, app_dir.join("Logs"));
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn installed_dirs_fall_back_without_local_app_data() {
+        let (config, logs) = installed_dirs_from(lookup_from(&[("LOCALAPPDATA", "")]));
+        assert_eq!(config, PathBuf::from(LEGACY_CONFIG_DIR));
+        assert_eq!(logs, PathBuf::from(LEGACY_LOGS_DIR).join(APP_NAME));
+    }
+
+    #[cfg(not(windows))]
+    #[test]
+    fn installed_dirs_follow_xdg_dirs() {
+        let (config, logs) = installed_dirs_from(lookup_from(&[
+            ("HOME", "/home/dev"),
+            ("XDG_CONFIG_HOME", "/xdg/config"),
+            ("XDG_STATE_HOME", "/xdg/state"),
+        ]));
+        assert_eq!(config, Path::new("/xdg/config").join(APP_NAME));
+        assert_eq!(logs, Path::new("/xdg/state").join(APP_NAME));
+    }
+
+    #[cfg(not(windows))]
+    #[test]
+    fn installed_dirs_default_under_home() {
+        let (config, logs) = installed_dirs_from(lookup_from(&[
+            ("HOME", "/home/dev"),
+            ("XDG_STATE_HOME", ""),
+        ]));
+        assert_eq!(config, Path::new("/home/dev/.config").join(APP_NAME));
+        assert_eq!(logs, Path::new("/home/dev/.local/state").join(APP_NAME));
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn nothing_degrades_with_local_app_data() {
+        let lookup = lookup_from(&[("LOCALAPPDATA", r"C:\Users\dev\AppData\Local")]);
+        assert!(path_degradation_from(lookup).is_empty());
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn missing_local_app_data_reports_both_legacy_dirs() {
+        assert_eq!(
+            path_degradation_from(lookup_from(&[("LOCALAPPDATA", "")])),
+            [
+                (
+                    "LOCALAPPDATA",
+                    r"settings stored in C:\Local\Config: LOCALAPPDATA not set".to_owned()
+                ),
+                (
+                    "LOCALAPPDATA",
+                    r"logs written to C:\Local\Logs: LOCALAPPDATA not set".to_owned()
+                ),
+            ]
+        );
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn dir_overrides_silence_missing_local_app_data() {
+        let lookup = lookup_from(&[(CONFIG_DIR_ENV, r"D:\config")]);
+        assert_eq!(
+            path_degradation_from(lookup),
+            [(
+                "LOCALAPPDATA",
+                r"logs written to C:\Local\Logs: LOCALAPPDATA not set".to_owned()
+            )]
+        );
+        let lookup = lookup_from(&[(CONFIG_DIR_ENV, r"D:\config"), (LOG_DIR_ENV, r"D:\logs")]);
+        assert!(path_degradation_from(lookup).is_empty());
+    }
+
+    #[cfg(not(windows))]
+    #[test]
+    fn nothing_degrades_with_home() {
+        assert!(path_degradation_from(lookup_from(&[("HOME", "/home/dev")])).is_empty());
+    }
+
+    #[cfg(not(windows))]
+    #[test]
+    fn missing_home_reports_settings_and_logs() {
+        assert_eq!(
+            path_degradation_from(lookup_from(&[("HOME", "")])),
+            [
+                (
+                    "HOME",
+                    "settings stored in the working directory: HOME not set".to_owned()
+                ),
+                (
+                    "HOME",
+                    "logs stored in the working directory: HOME not set".to_owned()
+                ),
+            ]
+        );
+    }
+
+    #[cfg(not(windows))]
+    #[test]
+    fn xdg_and_dir_overrides_silence_missing_home() {
+        let lookup = lookup_from(&[("XDG_CONFIG_HOME", "/xdg/config")]);
+        assert_eq!(
+            path_degradation_from(lookup),
+            [(
+                "HOME",
+                "logs stored in the working directory: HOME not set".to_owned()
+            )]
+        );
+        let lookup = lookup_from(&[(CONFIG_DIR_ENV, "/config"), (LOG_DIR_ENV, "/logs")]);
+        assert!(path_degradation_from(lookup).is_empty());
+    }
+
+    #[test]
+    fn migration_moves_files_and_directories() {
+        let root = env::temp_dir().join(format!("codexagent-migrate-{}", std::process::id()));
+        let legacy = Paths::in_dirs(&root.join("legacy"), root.join("legacy-logs"));
+        let current = Paths::in_dirs(&root.join("current"), root.join("current-logs"));
+        for dir in [legacy.config_dir(), legacy.sessions_dir.as_path()] {
+            fs::create_dir_all(dir).unwrap();
+        }
+        fs::write(&legacy.settings_file, "theme=dark\n").unwrap();
+        fs::write(&legacy.snapshot_file, "snapshot").unwrap();
+        fs::write(legacy.sessions_dir.join("session-1.json"), "{}").unwrap();
+        fs::create_dir_all(current.config_dir()).unwrap();
+        fs::write(&current.snapshot_file, "newer snapshot").unwrap();
+
+        assert_eq!(migrate_files(&legacy, &current), 2);
+        assert_eq!(
+            fs::read_to_string(&current.settings_file).unwrap(),
+            "theme=dark\n"
+        );
+        assert!(!legacy.settings_file.exists());
+        assert!(current.sessions_dir.join("session-1.json").is_file());
+        assert!(!legacy.sessions_dir.exists());
+        assert_eq!(
+            fs::read_to_string(&current.snapshot_file).unwrap(),
+            "newer snapshot"
+        );
+        assert!(
+            legacy.snapshot_file.exists(),
+            "existing files are not replaced"
+        );
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn portable_paths_stay_in_the_data_dir() {
+        let paths = Paths::portable(Path::new("data"));
+        assert!(paths.portable);
+        assert_eq!(paths.log_dir, Path::new("data").join("Logs"));
+        assert_eq!(paths.config_dir(), Path::new("data"));
+    }
+}
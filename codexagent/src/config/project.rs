@@ -0,0 +1,258 @@
+use std::collections::{BTreeMap, HashSet};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use serde::Deserialize;
+
+use crate::config::{load_project_trust, set_project_trust};
+use crate::logging;
+use crate::runtime::content_hash;
+
+pub const PROJECT_CONFIG_FILE: &str = ".autoagent.toml";
+
+// Keys that run commands or loosen the sandbox; a checked-out repository
+// only gets them once the user trusts its config.
+const TRUST_KEYS: [&str; 4] = ["sandbox_mode", "approval_policy", "on_complete", "env"];
+
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct ProjectConfig {
+    pub model: Option<String>,
+    pub sandbox_mode: Option<String>,
+    pub approval_policy: Option<String>,
+    pub prompt_prefix: Option<String>,
+    pub on_complete: Option<String>,
+    pub env: BTreeMap<String, String>,
+}
+
+impl ProjectConfig {
+    pub fn prompt_prefix(&self) -> Option<&str> {
+        self.prompt_prefix
+            .as_deref()
+            .map(str::trim)
+            .filter(|prefix| !prefix.is_empty())
+    }
+
+    pub fn on_complete(&self) -> Option<&str> {
+        self.on_complete
+            .as_deref()
+            .map(str::trim)
+            .filter(|command| !command.is_empty())
+    }
+
+    pub fn trust_keys(&self) -> Vec<&'static str> {
+        let set = [
+            self.sandbox_mode.is_some(),
+            self.approval_policy.is_some(),
+            self.on_complete().is_some(),
+            !self.env.is_empty(),
+        ];
+        TRUST_KEYS
+            .into_iter()
+            .zip(set)
+            .filter_map(|(key, set)| set.then_some(key))
+            .collect()
+    }
+
+    pub fn without_trust_keys(mut self) -> Self {
+        self.sandbox_mode = None;
+        self.approval_policy = None;
+        self.on_complete = None;
+        self.env.clear();
+        self
+    }
+
+    pub fn trust_fingerprint(&self) -> String {
+        let values = format!(
+            "{:?}{:?}{:?}{:?}",
+            self.sandbox_mode, self.approval_policy, self.on_complete, self.env
+        );
+        content_hash(values.as_bytes())
+    }
+}
+
+#[derive(Default)]
+pub struct ProjectConfigCache {
+    path: Option<PathBuf>,
+    modified: Option<SystemTime>,
+    config: Option<ProjectConfig>,
+    pending: Option<ProjectConfig>,
+    ignored: HashSet<(PathBuf, String)>,
+}
+
+impl ProjectConfigCache {
+    pub fn config(&self) -> Option<&ProjectConfig> {
+        self.config.as_ref()
+    }
+
+    pub fn refresh(&mut self, dir: &Path) -> Option<&ProjectConfig> {
+        let path = dir.join(PROJECT_CONFIG_FILE);
+        let modified = match fs::metadata(&path) {
+            Ok(metadata) => metadata.modified().ok(),
+            Err(error) => {
+                if error.kind() != io::ErrorKind::NotFound {
+                    logging::error(format!("failed to stat {}: {}", path.display(), error));
+                }
+                self.path = Some(path);
+                self.modified = None;
+                self.config = None;
+                self.pending = None;
+                return None;
+            }
+        };
+        if self.path.as_deref() == Some(path.as_path())
+            && self.modified.is_some()
+            && self.modified == modified
+        {
+            return self.config.as_ref();
+        }
+        self.pending = None;
+        self.config = match load_project_config(&path) {
+            Some(config) if !config.trust_keys().is_empty() && !project_trusted(&path, &config) => {
+                let ignored = (path.clone(), config.trust_fingerprint());
+                let stripped = config.clone().without_trust_keys();
+                if !self.ignored.contains(&ignored) {
+                    logging::trace(format!(
+                        "holding {} from {} until the project is trusted",
+                        config.trust_keys().join(", "),
+                        path.display()
+                    ));
+                    self.pending = Some(config);
+                }
+                Some(stripped)
+            }
+            config => config,
+        };
+        self.path = Some(path);
+        self.modified = modified;
+        self.config.as_ref()
+    }
+
+    pub fn pending_trust(&self) -> Option<(&Path, Vec<&'static str>)> {
+        let path = self.path.as_deref()?;
+        let config = self.pending.as_ref()?;
+        Some((path, config.trust_keys()))
+    }
+
+    pub fn trust(&mut self) -> io::Result<()> {
+        let (Some(path), Some(config)) = (self.path.as_deref(), self.pending.as_ref()) else {
+            return Ok(());
+        };
+        set_project_trust(&project_key(path), &config.trust_fingerprint())?;
+        logging::trace(format!("trusted project config {}", path.display()));
+        self.config = self.pending.take();
+        Ok(())
+    }
+
+    pub fn ignore(&mut self) {
+        let (Some(path), Some(config)) = (self.path.clone(), self.pending.take()) else {
+            return;
+        };
+        logging::trace(format!(
+            "ignoring {} from {} for this session",
+            config.trust_keys().join(", "),
+            path.display()
+        ));
+        self.ignored.insert((path, config.trust_fingerprint()));
+    }
+}
+
+fn project_key(path: &Path) -> String {
+    content_hash(path.to_string_lossy().as_bytes())
+}
+
+fn project_trusted(path: &Path, config: &ProjectConfig) -> bool {
+    match load_project_trust(&project_key(path)) {
+        Ok(fingerprint) => fingerprint.as_deref() == Some(config.trust_fingerprint().as_str()),
+        Err(error) => {
+            logging::error(format!(
+                "failed to read trust for {}: {}",
+                path.display(),
+                error
+            ));
+            false
+        }
+    }
+}
+
+fn load_project_config(path: &Path) -> Option<ProjectConfig> {
+    let contents = match fs::read_to_string(path) {
+        Ok(contents) => contents,
+        Err(error) => {
+            logging::error(format!(
+                "failed to read project config {}: {}",
+                path.display(),
+                error
+            ));
+            return None;
+        }
+    };
+    match toml::from_str::<ProjectConfig>(&contents) {
+        Ok(config) => {
+            logging::trace(format!("loaded project config {}", path.display()));
+            Some(config)
+        }
+        Err(error) => {
+            logging::error(format!(
+                "ignoring malformed project config {}: {}",
+                path.display(),
+                error.message()
+            ));
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(contents: &str) -> ProjectConfig {
+        toml::from_str(contents).expect("valid project config")
+    }
+
+    #[test]
+    fn plain_keys_need_no_trust() {
+        let config = parse("model = \"o3\"\nprompt_prefix = \"Be brief.\"\non_complete = \"  \"\n");
+        assert!(config.trust_keys().is_empty());
+    }
+
+    #[test]
+    fn hook_env_and_sandbox_keys_need_trust() {
+        let config = parse(
+            "sandbox_mode = \"danger-full-access\"\napproval_policy = \"never\"\n\
+             on_complete = \"make deploy\"\n[env]\nNODE_OPTIONS = \"--require ./x.js\"\n",
+        );
+        assert_eq!(
+            config.trust_keys(),
+            ["sandbox_mode", "approval_policy", "on_complete", "env"]
+        );
+        let stripped = config.without_trust_keys();
+        assert!(stripped.trust_keys().is_empty());
+        assert_eq!(stripped.on_complete(), None);
+        assert!(stripped.env.is_empty());
+    }
+
+    #[test]
+    fn stripping_keeps_plain_keys() {
+        let config =
+            parse("model = \"o3\"\nprompt_prefix = \"Be brief.\"\non_complete = \"make\"\n");
+        let stripped = config.without_trust_keys();
+        assert_eq!(stripped.model.as_deref(), Some("o3"));
+        assert_eq!(stripped.prompt_prefix(), Some("Be brief."));
+    }
+
+    #[test]
+    fn fingerprint_follows_only_trusted_values() {
+        let config = parse("model = \"o3\"\non_complete = \"make\"\n");
+        let renamed = parse("model = \"o4-mini\"\non_complete = \"make\"\n");
+        assert_eq!(config.trust_fingerprint(), renamed.trust_fingerprint());
+
+        let hook = parse("on_complete = \"make deploy\"\n");
+        assert_ne!(config.trust_fingerprint(), hook.trust_fingerprint());
+        let env = parse("on_complete = \"make\"\n[env]\nNODE_OPTIONS = \"\"\n");
+        assert_ne!(config.trust_fingerprint(), env.trust_fingerprint());
+    }
+}
@@ -0,0 +1,689 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::thread;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+use crate::config::{build_stamp, disk_space_low, paths, transcripts_persisted};
+use crate::logging;
+use crate::redact;
+use crate::runtime::write_file_atomic;
+
+static SNAPSHOT_WRITE: Mutex<()> = Mutex::new(());
+static RECENT_SESSION_WRITE: Mutex<()> = Mutex::new(());
+const SNAPSHOT_VERSION: u64 = 1;
+const UNVERSIONED_BUILD: &str = "unversioned build";
+const SNAPSHOT_MIGRATIONS: [fn(&mut Map<String, Value>); SNAPSHOT_VERSION as usize] =
+    [migrate_unversioned];
+const RECENT_SESSION_EXTENSION: &str = "session";
+const RECENT_SESSION_STAMP_LEN: usize = 19;
+const MAX_RECENT_SESSION_SLUG_CHARS: usize = 40;
+const UNTITLED_SESSION_SLUG: &str = "untitled";
+
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Rating {
+    Good,
+    Bad,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct TurnRating {
+    pub turn: usize,
+    pub rating: Rating,
+    pub model: String,
+    #[serde(default)]
+    pub profile: Option<String>,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct SessionSnapshot {
+    pub version: u64,
+    pub app_build: String,
+    pub in_progress: bool,
+    pub relaunched: bool,
+    pub output: String,
+    pub output_base: usize,
+    pub prompt_ranges: Vec<(usize, usize)>,
+    pub pinned_ranges: Vec<(usize, usize)>,
+    pub pinned_turns: Vec<usize>,
+    pub ratings: Vec<TurnRating>,
+    pub session_id: Option<String>,
+    pub response_language: Option<String>,
+    pub title: Option<String>,
+    pub title_renamed: bool,
+    pub cwd: Option<String>,
+    pub notes: String,
+}
+
+#[derive(Default, Deserialize)]
+#[serde(default)]
+struct RecentSessionHeader {
+    title: Option<String>,
+    prompt_ranges: Vec<(usize, usize)>,
+}
+
+#[derive(Clone, Debug)]
+pub struct RecentSession {
+    pub path: PathBuf,
+    pub title: Option<String>,
+    pub turns: usize,
+    pub saved_at: SystemTime,
+    pub size: u64,
+}
+
+impl RecentSession {
+    pub fn file_name(&self) -> &str {
+        self.path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .unwrap_or_default()
+    }
+}
+
+fn snapshot_contents(snapshot: &SessionSnapshot) -> Option<Vec<u8>> {
+    let mut snapshot = snapshot.clone();
+    snapshot.version = SNAPSHOT_VERSION;
+    snapshot.app_build = build_stamp();
+    let mut offsets: Vec<&mut usize> = std::iter::once(&mut snapshot.output_base)
+        .chain(
+            snapshot
+                .prompt_ranges
+                .iter_mut()
+                .chain(snapshot.pinned_ranges.iter_mut())
+                .flat_map(|(start, end)| [start, end]),
+        )
+        .collect();
+    let output =
+        redact::redact_persistent_with_offsets(&snapshot.output, &mut offsets).into_owned();
+    snapshot.output = output;
+    snapshot.title = snapshot
+        .title
+        .map(|title| redact::redact_persistent(&title).into_owned());
+    snapshot.notes = redact::redact_persistent(&snapshot.notes).into_owned();
+    serde_json::to_vec(&snapshot)
+        .map_err(|error| logging::error(format!("failed to serialize session snapshot: {}", error)))
+        .ok()
+}
+
+fn snapshot_write_allowed() -> bool {
+    if !transcripts_persisted() {
+        return false;
+    }
+    if disk_space_low() {
+        logging::trace("session snapshot skipped; disk space is low");
+        return false;
+    }
+    true
+}
+
+fn write_snapshot(contents: &[u8]) -> io::Result<()> {
+    let _guard = SNAPSHOT_WRITE.lock().unwrap_or_else(|e| e.into_inner());
+    // A save queued before persistence was turned off must not bring back
+    // the snapshot that turning it off removed.
+    if !transcripts_persisted() {
+        return Ok(());
+    }
+    write_file_atomic(paths::get().snapshot_file.as_path(), contents, false)
+}
+
+pub fn save_snapshot_async(snapshot: &SessionSnapshot) {
+    if !snapshot_write_allowed() {
+        return;
+    }
+    let Some(contents) = snapshot_contents(snapshot) else {
+        return;
+    };
+    thread::spawn(move || {
+        let _ = logging::log_result(write_snapshot(&contents), |error| {
+            format!(
+                "failed to write session snapshot {}: {}",
+                paths::get().snapshot_file.display(),
+                error
+            )
+        });
+    });
+}
+
+/// Writes the snapshot before returning, for callers that are about to exit.
+pub fn save_snapshot_now(snapshot: &SessionSnapshot) -> io::Result<()> {
+    if !snapshot_write_allowed() {
+        return Ok(());
+    }
+    match snapshot_contents(snapshot) {
+        Some(contents) => write_snapshot(&contents),
+        None => Err(io::Error::other("session snapshot could not be serialized")),
+    }
+}
+
+pub fn load_snapshot() -> io::Result<Option<SessionSnapshot>> {
+    let path = paths::get().snapshot_file.as_path();
+    let contents = match fs::read(path) {
+        Ok(contents) => contents,
+        Err(error) if error.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(error) => return Err(error),
+    };
+    parse_snapshot(&contents).map(Some)
+}
+
+fn parse_snapshot(contents: &[u8]) -> io::Result<SessionSnapshot> {
+    let invalid = |error: String| io::Error::new(io::ErrorKind::InvalidData, error);
+    let value: Value =
+        serde_json::from_slice(contents).map_err(|error| invalid(error.to_string()))?;
+    let migrated = migrate_snapshot(value).map_err(invalid)?;
+    serde_json::from_value(migrated).map_err(|error| invalid(error.to_string()))
+}
+
+fn migrate_snapshot(value: Value) -> Result<Value, String> {
+    let Value::Object(mut snapshot) = value else {
+        return Err("session snapshot is not a JSON object".to_owned());
+    };
+    let version = match snapshot.get("version") {
+        None => 0,
+        Some(version) => version
+            .as_u64()
+            .ok_or_else(|| format!("session snapshot version is not a number: {}", version))?,
+    };
+    if version > SNAPSHOT_VERSION {
+        return Err(format!(
+            "session snapshot version {} from {} is newer than supported version {}",
+            version,
+            snapshot
+                .get("app_build")
+                .and_then(Value::as_str)
+                .unwrap_or("an unknown build"),
+            SNAPSHOT_VERSION
+        ));
+    }
+    for (from, migrate) in SNAPSHOT_MIGRATIONS
+        .iter()
+        .enumerate()
+        .skip(version as usize)
+    {
+        migrate(&mut snapshot);
+        snapshot.insert("version".to_owned(), Value::from(from as u64 + 1));
+        logging::trace(format!(
+            "migrated session snapshot from version {} to {}",
+            from,
+            from + 1
+        ));
+    }
+    Ok(Value::Object(snapshot))
+}
+
+fn migrate_unversioned(snapshot: &mut Map<String, Value>) {
+    snapshot
+        .entry("app_build")
+        .or_insert_with(|| Value::from(UNVERSIONED_BUILD));
+}
+
+pub fn clear_snapshot() {
+    thread::spawn(|| {
+        let _guard = SNAPSHOT_WRITE.lock().unwrap_or_else(|e| e.into_inner());
+        let path = paths::get().snapshot_file.as_path();
+        match fs::remove_file(path) {
+            Ok(()) => {}
+            Err(error) if error.kind() == io::ErrorKind::NotFound => {}
+            Err(error) => logging::error(format!(
+                "failed to remove session snapshot {}: {}",
+                path.display(),
+                error
+            )),
+        }
+    });
+}
+
+pub fn recent_session_stamp(now: SystemTime) -> String {
+    let secs = now
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs() as i64)
+        .unwrap_or(0);
+    logging::format_timestamp(secs)
+        .replace(' ', "_")
+        .replace(':', "-")
+}
+
+pub fn recent_session_file_name(stamp: &str, title: Option<&str>) -> String {
+    let mut slug = String::new();
+    for word in title
+        .unwrap_or_default()
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+    {
+        if slug.chars().count() + word.chars().count() >= MAX_RECENT_SESSION_SLUG_CHARS {
+            break;
+        }
+        if !slug.is_empty() {
+            slug.push('-');
+        }
+        slug.extend(word.chars().flat_map(char::to_lowercase));
+    }
+    if slug.is_empty() {
+        slug.push_str(UNTITLED_SESSION_SLUG);
+    }
+    format!("{}-{}.{}", stamp, slug, RECENT_SESSION_EXTENSION)
+}
+
+pub fn recent_session_file_stamp(name: &str) -> Option<&str> {
+    name.get(..RECENT_SESSION_STAMP_LEN)
+        .filter(|_| name.ends_with(RECENT_SESSION_EXTENSION))
+}
+
+fn is_recent_session_file(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|extension| extension == RECENT_SESSION_EXTENSION)
+}
+
+fn recent_session_files(dir: &Path) -> io::Result<Vec<(PathBuf, fs::Metadata)>> {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(error) if error.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(error) => return Err(error),
+    };
+    let mut files = Vec::new();
+    for entry in entries {
+        let entry = entry?;
+        let path = entry.path();
+        if !is_recent_session_file(&path) {
+            continue;
+        }
+        match entry.metadata() {
+            Ok(metadata) if metadata.is_file() => files.push((path, metadata)),
+            Ok(_) => {}
+            Err(error) => logging::error(format!(
+                "failed to read recent session {}: {}",
+                path.display(),
+                error
+            )),
+        }
+    }
+    Ok(files)
+}
+
+fn newest_first(left: &(PathBuf, SystemTime), right: &(PathBuf, SystemTime)) -> std::cmp::Ordering {
+    right.1.cmp(&left.1).then_with(|| right.0.cmp(&left.0))
+}
+
+pub fn sort_recent_sessions(sessions: &mut [RecentSession]) {
+    sessions.sort_by(|left, right| {
+        right
+            .saved_at
+            .cmp(&left.saved_at)
+            .then_with(|| right.path.cmp(&left.path))
+    });
+}
+
+fn sessions_to_prune(
+    mut files: Vec<(PathBuf, SystemTime)>,
+    limit: usize,
+    keep: &Path,
+) -> Vec<PathBuf> {
+    files.sort_by(newest_first);
+    let mut kept = usize::from(files.iter().any(|(path, _)| path == keep));
+    files
+        .into_iter()
+        .filter(|(path, _)| {
+            if path == keep {
+                return false;
+            }
+            if kept < limit {
+                kept += 1;
+                return false;
+            }
+            true
+        })
+        .map(|(path, _)| path)
+        .collect()
+}
+
+fn prune_recent_sessions(dir: &Path, limit: usize, keep: &Path) {
+    let files = match recent_session_files(dir) {
+        Ok(files) => files,
+        Err(error) => {
+            logging::error(format!(
+                "failed to list recent sessions in {}: {}",
+                dir.display(),
+                error
+            ));
+            return;
+        }
+    };
+    let files = files
+        .into_iter()
+        .map(|(path, metadata)| {
+            let modified = metadata.modified().unwrap_or(UNIX_EPOCH);
+            (path, modified)
+        })
+        .collect();
+    for path in sessions_to_prune(files, limit, keep) {
+        logging::trace(format!("pruning recent session {}", path.display()));
+        remove_recent_session_file(&path);
+    }
+}
+
+fn remove_recent_session_file(path: &Path) {
+    match fs::remove_file(path) {
+        Ok(()) => {}
+        Err(error) if error.kind() == io::ErrorKind::NotFound => {}
+        Err(error) => logging::error(format!(
+            "failed to remove recent session {}: {}",
+            path.display(),
+            error
+        )),
+    }
+}
+
+pub fn save_recent_session_async(
+    snapshot: &SessionSnapshot,
+    name: String,
+    previous: Option<String>,
+    limit: usize,
+) {
+    if !transcripts_persisted() || limit == 0 {
+        return;
+    }
+    if disk_space_low() {
+        logging::trace("recent session save skipped; disk space is low");
+        return;
+    }
+    let Some(contents) = snapshot_contents(snapshot) else {
+        return;
+    };
+    thread::spawn(move || {
+        let _guard = RECENT_SESSION_WRITE
+            .lock()
+            .unwrap_or_else(|e| e.into_inner());
+        let dir = paths::get().sessions_dir.as_path();
+        let path = dir.join(&name);
+        if logging::log_result(write_file_atomic(&path, &contents, false), |error| {
+            format!(
+                "failed to write recent session {}: {}",
+                path.display(),
+                error
+            )
+        })
+        .is_err()
+        {
+            return;
+        }
+        if let Some(previous) = previous.filter(|previous| *previous != name) {
+            remove_recent_session_file(&dir.join(previous));
+        }
+        prune_recent_sessions(dir, limit, &path);
+    });
+}
+
+pub fn list_recent_sessions() -> io::Result<Vec<RecentSession>> {
+    let _guard = RECENT_SESSION_WRITE
+        .lock()
+        .unwrap_or_else(|e| e.into_inner());
+    let mut sessions = Vec::new();
+    for (path, metadata) in recent_session_files(&paths::get().sessions_dir)? {
+        let header = fs::read(&path).and_then(|contents| {
+            serde_json::from_slice::<RecentSessionHeader>(&contents)
+                .map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))
+        });
+        let header = match header {
+            Ok(header) => header,
+            Err(error) => {
+                logging::error(format!(
+                    "skipping unreadable recent session {}: {}",
+                    path.display(),
+                    error
+                ));
+                continue;
+            }
+        };
+        sessions.push(RecentSession {
+            path,
+            title: header.title,
+            turns: header.prompt_ranges.len(),
+            saved_at: metadata.modified().unwrap_or(UNIX_EPOCH),
+            size: metadata.len(),
+        });
+    }
+    sort_recent_sessions(&mut sessions);
+    Ok(sessions)
+}
+
+pub fn load_recent_session(path: &Path) -> io::Result<SessionSnapshot> {
+    let contents = {
+        let _guard = RECENT_SESSION_WRITE
+            .lock()
+            .unwrap_or_else(|e| e.into_inner());
+        fs::read(path)?
+    };
+    parse_snapshot(&contents)
+}
+
+pub fn delete_recent_session(path: &Path) -> io::Result<()> {
+    let _guard = RECENT_SESSION_WRITE
+        .lock()
+        .unwrap_or_else(|e| e.into_inner());
+    fs::remove_file(path)
+}
+
+pub fn forget_recent_sessions() {
+    thread::spawn(|| {
+        let _guard = RECENT_SESSION_WRITE
+            .lock()
+            .unwrap_or_else(|e| e.into_inner());
+        let dir = paths::get().sessions_dir.as_path();
+        match recent_session_files(dir) {
+            Ok(files) => {
+                for (path, _) in files {
+                    remove_recent_session_file(&path);
+                }
+            }
+            Err(error) => logging::error(format!(
+                "failed to list recent sessions in {}: {}",
+                dir.display(),
+                error
+            )),
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use serde_json::json;
+
+    use super::*;
+
+    fn parse(value: Value) -> io::Result<SessionSnapshot> {
+        parse_snapshot(value.to_string().as_bytes())
+    }
+
+    #[test]
+    fn unversioned_snapshots_migrate_forward() {
+        let snapshot = parse(json!({
+            "output": "\x19#1\nhello\n\n",
+            "prompt_ranges": [[4, 9]],
+            "session_id": "abc",
+            "in_progress": true,
+        }))
+        .expect("migrated snapshot");
+        assert_eq!(snapshot.version, SNAPSHOT_VERSION);
+        assert_eq!(snapshot.app_build, UNVERSIONED_BUILD);
+        assert_eq!(snapshot.output, "\x19#1\nhello\n\n");
+        assert_eq!(snapshot.prompt_ranges, [(4, 9)]);
+        assert_eq!(snapshot.session_id.as_deref(), Some("abc"));
+        assert!(snapshot.in_progress);
+    }
+
+    #[test]
+    fn saved_snapshots_load_unchanged() {
+        let saved = SessionSnapshot {
+            output: "\x19#1\nhello\n\nworld".to_owned(),
+            prompt_ranges: vec![(4, 9)],
+            title: Some("Greeting".to_owned()),
+            response_language: Some("German".to_owned()),
+            notes: "ask about the lexer".to_owned(),
+            ..SessionSnapshot::default()
+        };
+        let contents = snapshot_contents(&saved).expect("serialized snapshot");
+        let loaded = parse_snapshot(&contents).expect("loaded snapshot");
+        assert_eq!(loaded.version, SNAPSHOT_VERSION);
+        assert_eq!(loaded.app_build, build_stamp());
+        assert_eq!(loaded.output, saved.output);
+        assert_eq!(loaded.prompt_ranges, saved.prompt_ranges);
+        assert_eq!(loaded.title, saved.title);
+        assert_eq!(loaded.response_language, saved.response_language);
+        assert_eq!(loaded.notes, saved.notes);
+    }
+
+    #[test]
+    fn ratings_are_optional_and_round_trip() {
+        let before_ratings = parse(json!({
+            "version": SNAPSHOT_VERSION,
+            "output": "\x19#1\nhello\n\n",
+            "prompt_ranges": [[4, 9]],
+        }))
+        .expect("snapshot without ratings");
+        assert!(before_ratings.ratings.is_empty());
+
+        let without_profile = parse(json!({
+            "version": SNAPSHOT_VERSION,
+            "ratings": [{"turn": 0, "rating": "bad", "model": "gpt-5"}],
+        }))
+        .expect("rating without a profile");
+        assert_eq!(without_profile.ratings[0].rating, Rating::Bad);
+        assert_eq!(without_profile.ratings[0].profile, None);
+
+        let saved = SessionSnapshot {
+            ratings: vec![TurnRating {
+                turn: 2,
+                rating: Rating::Good,
+                model: "gpt-5".to_owned(),
+                profile: Some("fast".to_owned()),
+            }],
+            ..SessionSnapshot::default()
+        };
+        let contents = snapshot_contents(&saved).expect("serialized snapshot");
+        let loaded = parse_snapshot(&contents).expect("loaded snapshot");
+        let rating = &loaded.ratings[0];
+        assert_eq!(
+            (rating.turn, rating.rating, rating.model.as_str()),
+            (2, Rating::Good, "gpt-5")
+        );
+        assert_eq!(rating.profile.as_deref(), Some("fast"));
+    }
+
+    #[test]
+    fn newer_snapshots_are_rejected() {
+        let error = parse(json!({
+            "version": SNAPSHOT_VERSION + 1,
+            "app_build": "9.9.9 (abc1234)",
+            "output": "future",
+        }))
+        .expect_err("newer snapshot");
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        assert!(error.to_string().contains("9.9.9 (abc1234)"), "{}", error);
+    }
+
+    #[test]
+    fn malformed_snapshots_are_rejected() {
+        for value in [json!([1, 2]), json!({"version": "one"}), json!("text")] {
+            let error = parse(value).expect_err("malformed snapshot");
+            assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        }
+        assert!(parse_snapshot(b"{not json").is_err());
+    }
+
+    #[test]
+    fn relaunch_marker_survives_a_round_trip() {
+        let saved = SessionSnapshot {
+            relaunched: true,
+            output: "idle session".to_owned(),
+            ..SessionSnapshot::default()
+        };
+        let contents = snapshot_contents(&saved).expect("serialized snapshot");
+        let loaded = parse_snapshot(&contents).expect("loaded snapshot");
+        assert!(loaded.relaunched);
+        assert!(!loaded.in_progress);
+        assert!(!parse(json!({"output": "older"})).unwrap().relaunched);
+    }
+
+    fn recent(name: &str, saved_secs: u64) -> RecentSession {
+        RecentSession {
+            path: PathBuf::from(name),
+            title: None,
+            turns: 1,
+            saved_at: UNIX_EPOCH + Duration::from_secs(saved_secs),
+            size: 0,
+        }
+    }
+
+    #[test]
+    fn recent_session_files_are_named_by_stamp_and_title() {
+        let stamp = recent_session_stamp(UNIX_EPOCH + Duration::from_secs(86_400 + 3_723));
+        assert_eq!(stamp.len(), RECENT_SESSION_STAMP_LEN);
+        assert!(!stamp.contains([' ', ':']), "{}", stamp);
+        let name = recent_session_file_name(&stamp, Some("Fix the Parser: CRLF & tabs!"));
+        assert_eq!(name, format!("{}-fix-the-parser-crlf-tabs.session", stamp));
+        assert_eq!(recent_session_file_stamp(&name), Some(stamp.as_str()));
+        assert_eq!(
+            recent_session_file_name(&stamp, Some("?!")),
+            format!("{}-untitled.session", stamp)
+        );
+        let long = recent_session_file_name(&stamp, Some(&"word ".repeat(20)));
+        let slug = &long[stamp.len() + 1..long.len() - ".session".len()];
+        assert!(slug.len() < MAX_RECENT_SESSION_SLUG_CHARS, "{}", slug);
+        assert_eq!(recent_session_file_stamp("notes.txt"), None);
+    }
+
+    #[test]
+    fn recent_sessions_sort_newest_first() {
+        let mut sessions = vec![recent("b", 10), recent("a", 30), recent("c", 10)];
+        sort_recent_sessions(&mut sessions);
+        let names: Vec<_> = sessions.iter().map(RecentSession::file_name).collect();
+        assert_eq!(names, ["a", "c", "b"]);
+    }
+
+    #[test]
+    fn pruning_keeps_the_newest_and_the_current_session() {
+        let files = |ages: &[(&str, u64)]| {
+            ages.iter()
+                .map(|&(name, secs)| (PathBuf::from(name), UNIX_EPOCH + Duration::from_secs(secs)))
+                .collect::<Vec<_>>()
+        };
+        let all = files(&[("old", 1), ("new", 5), ("mid", 3), ("older", 0)]);
+        assert_eq!(
+            sessions_to_prune(all.clone(), 2, Path::new("none")),
+            [PathBuf::from("old"), PathBuf::from("older")]
+        );
+        assert_eq!(
+            sessions_to_prune(all.clone(), 2, Path::new("older")),
+            [PathBuf::from("mid"), PathBuf::from("old")]
+        );
+        assert!(sessions_to_prune(all.clone(), 10, Path::new("none")).is_empty());
+        assert_eq!(sessions_to_prune(all, 1, Path::new("new")).len(), 3);
+    }
+
+    #[test]
+    fn pruning_walks_only_session_files() {
+        let dir =
+            std::env::temp_dir().join(format!("codexagent-recent-sessions-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        for name in ["a.session", "b.session", "c.session", "notes.txt"] {
+            fs::write(dir.join(name), name).unwrap();
+        }
+        let keep = dir.join("a.session");
+        prune_recent_sessions(&dir, 1, &keep);
+        let mut left: Vec<_> = fs::read_dir(&dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+            .collect();
+        left.sort();
+        assert_eq!(left, ["a.session", "notes.txt"]);
+        fs::remove_dir_all(&dir).unwrap();
+        prune_recent_sessions(&dir, 1, &keep);
+    }
+}
@@ -0,0 +1,406 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, MutexGuard};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::logging;
+use crate::runtime::write_file_atomic;
+
+use super::{config_buffer, default_config_path, ensure_path, parse_config};
+
+const SETTINGS_FLUSH_INTERVAL: Duration = Duration::from_secs(1);
+
+struct PendingSettings {
+    changes: BTreeMap<String, String>,
+    writing: BTreeMap<String, String>,
+    last_flush: Option<Instant>,
+    flush_scheduled: bool,
+}
+
+static PENDING: Mutex<PendingSettings> = Mutex::new(PendingSettings {
+    changes: BTreeMap::new(),
+    writing: BTreeMap::new(),
+    last_flush: None,
+    flush_scheduled: false,
+});
+// Serializes writers so two flushes cannot interleave their read and write
+// of the file; PENDING itself is only held while values move in or out.
+static FLUSH: Mutex<()> = Mutex::new(());
+static RECOVERED_FROM: Mutex<Option<PathBuf>> = Mutex::new(None);
+
+fn pending() -> MutexGuard<'static, PendingSettings> {
+    PENDING.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+fn backup_path(path: &Path) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_owned();
+    name.push(".bak");
+    path.with_file_name(name)
+}
+
+fn check_settings(raw: &[u8]) -> Result<(), String> {
+    let text = std::str::from_utf8(raw).map_err(|_| "not valid UTF-8".to_owned())?;
+    for (index, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        if line
+            .split_once('=')
+            .is_none_or(|(key, _)| key.trim().is_empty())
+        {
+            return Err(format!("line {} is not key=value", index + 1));
+        }
+    }
+    Ok(())
+}
+
+fn read_backup(path: &Path) -> Option<Vec<u8>> {
+    let raw = fs::read(backup_path(path)).ok()?;
+    (check_settings(&raw).is_ok() && !raw.trim_ascii().is_empty()).then_some(raw)
+}
+
+fn read_settings_file(path: &Path) -> io::Result<(HashMap<String, String>, bool)> {
+    ensure_path(path)?;
+    let raw = fs::read(path)?;
+    let problem = match check_settings(&raw) {
+        Err(problem) => problem,
+        Ok(()) if raw.trim_ascii().is_empty() && read_backup(path).is_some() => {
+            "file is empty".to_owned()
+        }
+        Ok(()) => return Ok((parse_config(&raw), true)),
+    };
+    let Some(backup) = read_backup(path) else {
+        logging::error(format!(
+            "settings file {} is damaged ({}) and there is no usable backup; reading what is left",
+            path.display(),
+            problem
+        ));
+        return Ok((parse_config(&raw), false));
+    };
+    logging::error(format!(
+        "settings file {} is damaged ({}); restoring {}",
+        path.display(),
+        problem,
+        backup_path(path).display()
+    ));
+    if let Err(error) = write_file_atomic(path, &backup, false) {
+        logging::error(format!(
+            "failed to restore settings backup over {}: {}",
+            path.display(),
+            error
+        ));
+        return Ok((parse_config(&backup), false));
+    }
+    *RECOVERED_FROM.lock().unwrap_or_else(|e| e.into_inner()) = Some(backup_path(path));
+    Ok((parse_config(&backup), true))
+}
+
+pub(super) fn read_settings(path: &Path) -> io::Result<HashMap<String, String>> {
+    let staged: Vec<(String, String)> = {
+        let pending = pending();
+        pending
+            .writing
+            .iter()
+            .chain(&pending.changes)
+            .map(|(key, value)| (key.clone(), value.clone()))
+            .collect()
+    };
+    let (mut settings, _) = read_settings_file(path)?;
+    settings.extend(staged);
+    Ok(settings)
+}
+
+pub(super) fn stage_settings<K, V>(changes: impl IntoIterator<Item = (K, V)>) -> io::Result<()>
+where
+    K: Into<String>,
+    V: Into<String>,
+{
+    stage_settings_to(default_config_path(), changes)
+}
+
+fn stage_settings_to<K, V>(path: &Path, changes: impl IntoIterator<Item = (K, V)>) -> io::Result<()>
+where
+    K: Into<String>,
+    V: Into<String>,
+{
+    let wait = {
+        let mut pending = pending();
+        pending.changes.extend(
+            changes
+                .into_iter()
+                .map(|(key, value)| (key.into(), value.into())),
+        );
+        if pending.flush_scheduled {
+            return Ok(());
+        }
+        let wait = pending.last_flush.map_or(Duration::ZERO, |at| {
+            SETTINGS_FLUSH_INTERVAL.saturating_sub(at.elapsed())
+        });
+        pending.flush_scheduled = !wait.is_zero();
+        wait
+    };
+    if wait.is_zero() {
+        return flush_settings_to(path);
+    }
+    let path = path.to_owned();
+    thread::spawn(move || {
+        thread::sleep(wait);
+        let _ = flush_settings_to(&path);
+    });
+    Ok(())
+}
+
+pub fn flush_settings() -> io::Result<()> {
+    flush_settings_to(default_config_path())
+}
+
+fn flush_settings_to(path: &Path) -> io::Result<()> {
+    let _flush = FLUSH.lock().unwrap_or_else(|e| e.into_inner());
+    let changes = {
+        let mut pending = pending();
+        pending.flush_scheduled = false;
+        if pending.changes.is_empty() {
+            return Ok(());
+        }
+        pending.last_flush = Some(Instant::now());
+        let changes = std::mem::take(&mut pending.changes);
+        pending.writing = changes.clone();
+        changes
+    };
+    let result = read_settings_file(path).and_then(|(mut settings, trusted)| {
+        settings.extend(
+            changes
+                .iter()
+                .map(|(key, value)| (key.clone(), value.clone())),
+        );
+        write_file_atomic(path, config_buffer(&settings).as_bytes(), trusted)
+    });
+    {
+        let mut pending = pending();
+        pending.writing.clear();
+        if result.is_err() {
+            for (key, value) in changes {
+                pending.changes.entry(key).or_insert(value);
+            }
+        }
+    }
+    logging::log_result(result, |error| {
+        format!("failed to save settings to {}: {}", path.display(), error)
+    })
+}
+
+pub fn take_settings_recovery_notice() -> Option<String> {
+    let path = RECOVERED_FROM
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .take()?;
+    Some(format!(
+        "Settings were damaged and have been restored from {}",
+        path.display()
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+    use std::process;
+
+    use super::*;
+
+    // PENDING and RECOVERED_FROM are process-wide, so tests that touch them
+    // take turns.
+    static STORE_TEST: Mutex<()> = Mutex::new(());
+
+    fn store_test() -> MutexGuard<'static, ()> {
+        STORE_TEST.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn temp_settings(name: &str) -> (PathBuf, PathBuf) {
+        let dir = env::temp_dir().join(format!("codexagent-store-{}-{}", name, process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("settings.ini");
+        (dir, path)
+    }
+
+    // Leaves the file the way a crash would: the temp file was cut short and
+    // then renamed over the settings.
+    fn interrupt_write(path: &Path, contents: &str, written: usize) {
+        let temp = path.with_file_name("settings.ini.tmp");
+        fs::write(&temp, contents).unwrap();
+        fs::OpenOptions::new()
+            .write(true)
+            .open(&temp)
+            .unwrap()
+            .set_len(written as u64)
+            .unwrap();
+        fs::rename(&temp, path).unwrap();
+    }
+
+    fn stage(key: &str, value: &str) {
+        pending().changes.insert(key.to_owned(), value.to_owned());
+    }
+
+    fn take_staged() -> BTreeMap<String, String> {
+        std::mem::take(&mut pending().changes)
+    }
+
+    #[test]
+    fn store_reads_staged_and_in_flight_values_and_requeues_failed_writes() {
+        let _store = store_test();
+        let (dir, path) = temp_settings("staged");
+        fs::write(&path, "theme=dark\nstale=1\n").unwrap();
+
+        pending().writing.insert("stale".to_owned(), "2".to_owned());
+        stage("draft", "on");
+        let settings = read_settings(&path).unwrap();
+        assert_eq!(settings["theme"], "dark");
+        assert_eq!(settings["stale"], "2", "values being written are visible");
+        assert_eq!(settings["draft"], "on");
+        pending().writing.clear();
+
+        flush_settings_to(&path).unwrap();
+        assert!(take_staged().is_empty());
+        assert!(pending().writing.is_empty());
+        let written = fs::read_to_string(&path).unwrap();
+        assert!(written.contains("draft=on"), "{}", written);
+
+        stage("draft", "off");
+        let blocked = path.join("settings.ini");
+        assert!(flush_settings_to(&blocked).is_err());
+        assert!(pending().writing.is_empty());
+        assert_eq!(take_staged().get("draft").map(String::as_str), Some("off"));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn a_truncated_write_is_restored_from_the_backup() {
+        let _store = store_test();
+        let (dir, path) = temp_settings("truncated");
+        fs::write(&path, "theme=dark\nwidth=900\n").unwrap();
+        stage("draft", "on");
+        flush_settings_to(&path).unwrap();
+        let _ = take_settings_recovery_notice();
+
+        let next = "draft=off\ntheme=light\nwidth=1200\n";
+        interrupt_write(&path, next, next.find("width").unwrap() + 3);
+        let settings = read_settings(&path).unwrap();
+        assert_eq!(settings["theme"], "dark");
+        assert_eq!(settings["width"], "900");
+        assert!(!settings.contains_key("draft"), "backup predates the draft");
+        assert_eq!(
+            fs::read_to_string(&path).unwrap(),
+            "theme=dark\nwidth=900\n"
+        );
+        let notice = take_settings_recovery_notice().unwrap();
+        assert!(notice.contains("settings.ini.bak"), "{}", notice);
+
+        interrupt_write(&path, next, 0);
+        assert_eq!(read_settings(&path).unwrap()["width"], "900");
+        assert!(take_settings_recovery_notice().is_some());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn a_truncated_write_without_a_backup_keeps_what_is_left() {
+        let _store = store_test();
+        let (dir, path) = temp_settings("no-backup");
+        let _ = take_settings_recovery_notice();
+
+        interrupt_write(&path, "theme=dark\nwidth=900\n", 14);
+        let settings = read_settings(&path).unwrap();
+        assert_eq!(settings["theme"], "dark");
+        assert!(!settings.contains_key("width"));
+        assert!(take_settings_recovery_notice().is_none());
+
+        stage("width", "1000");
+        flush_settings_to(&path).unwrap();
+        assert!(
+            !backup_path(&path).exists(),
+            "a damaged file is not kept as the backup"
+        );
+        assert_eq!(
+            fs::read_to_string(&path).unwrap(),
+            "theme=dark\nwidth=1000\n"
+        );
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn keys_this_build_does_not_know_survive_a_save() {
+        let _store = store_test();
+        let (dir, path) = temp_settings("unknown");
+        fs::write(
+            &path,
+            "# written by a newer build\nfuture_panel=left\ntheme=dark\nx.experimental = \"a=b\"\n",
+        )
+        .unwrap();
+
+        stage("theme", "light");
+        flush_settings_to(&path).unwrap();
+        let settings = read_settings(&path).unwrap();
+        assert_eq!(settings["theme"], "light");
+        assert_eq!(settings["future_panel"], "left");
+        assert_eq!(settings["x.experimental"], "a=b");
+
+        stage("width", "900");
+        flush_settings_to(&path).unwrap();
+        assert_eq!(read_settings(&path).unwrap()["future_panel"], "left");
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn changes_made_together_are_written_in_one_flush() {
+        let _store = store_test();
+        let (dir, path) = temp_settings("debounce");
+        fs::write(&path, "theme=dark\n").unwrap();
+        {
+            let mut pending = pending();
+            pending.last_flush = Some(Instant::now());
+            pending.flush_scheduled = false;
+        }
+
+        let writers: Vec<_> = (0..4)
+            .map(|index| {
+                let path = path.clone();
+                thread::spawn(move || {
+                    stage_settings_to(&path, [(format!("key{}", index), index.to_string())])
+                })
+            })
+            .collect();
+        for writer in writers {
+            writer.join().unwrap().unwrap();
+        }
+        assert!(pending().flush_scheduled);
+        assert_eq!(fs::read_to_string(&path).unwrap(), "theme=dark\n");
+        let staged = read_settings(&path).unwrap();
+        assert!((0..4).all(|index| staged[&format!("key{}", index)] == index.to_string()));
+
+        let deadline = Instant::now() + SETTINGS_FLUSH_INTERVAL * 5;
+        while pending().flush_scheduled || !pending().changes.is_empty() {
+            assert!(Instant::now() < deadline, "the scheduled flush never ran");
+            thread::sleep(Duration::from_millis(20));
+        }
+        let flushed = FLUSH.lock().unwrap_or_else(|e| e.into_inner());
+        assert_eq!(
+            fs::read_to_string(&path).unwrap(),
+            "key0=0\nkey1=1\nkey2=2\nkey3=3\ntheme=dark\n"
+        );
+        assert_eq!(
+            fs::read_to_string(backup_path(&path)).unwrap(),
+            "theme=dark\n",
+            "the four changes replaced the file once"
+        );
+        drop(flushed);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
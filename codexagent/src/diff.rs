@@ -0,0 +1,297 @@
+const MAX_DIFF_LINES: usize = 20_000;
+const MAX_LINE_EDITS: usize = 1_000;
+const MAX_WORD_EDITS: usize = 200;
+const REMOVED_PREVIEW_CHARS: usize = 80;
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Edit {
+    Same,
+    Removed,
+    Added,
+}
+
+pub fn edit_script<T: PartialEq>(old: &[T], new: &[T], max_edits: usize) -> Option<Vec<Edit>> {
+    let (n, m) = (old.len() as isize, new.len() as isize);
+    let limit = (n + m).min(max_edits as isize);
+    let offset = limit + 1;
+    let mut frontier = vec![0isize; 2 * offset as usize + 1];
+    let mut trace = Vec::new();
+    for d in 0..=limit {
+        trace.push(frontier[(offset - d) as usize..=(offset + d) as usize].to_vec());
+        let mut k = -d;
+        while k <= d {
+            let index = (offset + k) as usize;
+            let mut x = if k == -d || (k != d && frontier[index - 1] < frontier[index + 1]) {
+                frontier[index + 1]
+            } else {
+                frontier[index - 1] + 1
+            };
+            let mut y = x - k;
+            while x < n && y < m && old[x as usize] == new[y as usize] {
+                x += 1;
+                y += 1;
+            }
+            frontier[index] = x;
+            if x >= n && y >= m {
+                return Some(backtrack(&trace, n, m));
+            }
+            k += 2;
+        }
+    }
+    None
+}
+
+fn backtrack(trace: &[Vec<isize>], n: isize, m: isize) -> Vec<Edit> {
+    let mut edits = Vec::with_capacity((n + m) as usize);
+    let (mut x, mut y) = (n, m);
+    for (d, frontier) in trace.iter().enumerate().rev() {
+        let d = d as isize;
+        if d == 0 {
+            edits.extend(std::iter::repeat_n(Edit::Same, x as usize));
+            break;
+        }
+        let at = |k: isize| frontier[(k + d) as usize];
+        let k = x - y;
+        let previous_k = if k == -d || (k != d && at(k - 1) < at(k + 1)) {
+            k + 1
+        } else {
+            k - 1
+        };
+        let previous_x = at(previous_k);
+        let previous_y = previous_x - previous_k;
+        while x > previous_x && y > previous_y {
+            edits.push(Edit::Same);
+            x -= 1;
+            y -= 1;
+        }
+        edits.push(if x == previous_x {
+            Edit::Added
+        } else {
+            Edit::Removed
+        });
+        x = previous_x;
+        y = previous_y;
+    }
+    edits.reverse();
+    edits
+}
+
+pub fn marked_response_diff(previous: &str, current: &str) -> Option<String> {
+    let old: Vec<&str> = response_lines(previous).collect();
+    let new: Vec<&str> = response_lines(current).collect();
+    if old.len() + new.len() > MAX_DIFF_LINES {
+        return None;
+    }
+    let edits = edit_script(&old, &new, MAX_LINE_EDITS)?;
+    let mut marked = String::with_capacity(current.len() + new.len());
+    let mut removed = Vec::new();
+    let mut added = Vec::new();
+    let (mut old_index, mut new_index) = (0, 0);
+    for edit in edits {
+        match edit {
+            Edit::Removed => {
+                removed.push(old[old_index]);
+                old_index += 1;
+            }
+            Edit::Added => {
+                added.push(new[new_index]);
+                new_index += 1;
+            }
+            Edit::Same => {
+                push_change_block(&mut marked, &removed, &added);
+                removed.clear();
+                added.clear();
+                marked.push_str(new[new_index]);
+                marked.push('\n');
+                old_index += 1;
+                new_index += 1;
+            }
+        }
+    }
+    push_change_block(&mut marked, &removed, &added);
+    Some(marked)
+}
+
+fn response_lines(text: &str) -> impl Iterator<Item = &str> {
+    text.lines()
+        .filter(|line| !line.starts_with(['\x19', '\x1A', '\x1C', '\x1D', '\x1E']))
+        .map(|line| line.strip_prefix('\x1F').unwrap_or(line))
+}
+
+fn push_change_block(marked: &mut String, removed: &[&str], added: &[&str]) {
+    if let Some(first) = removed.first() {
+        marked.push('\x12');
+        marked.push('\u{2212}');
+        marked.push(' ');
+        let first = first.trim();
+        marked.extend(first.chars().take(REMOVED_PREVIEW_CHARS));
+        if first.chars().count() > REMOVED_PREVIEW_CHARS {
+            marked.push('\u{2026}');
+        }
+        if removed.len() > 1 {
+            marked.push_str(&format!(" (+{} more removed)", removed.len() - 1));
+        }
+        marked.push('\n');
+    }
+    for (index, line) in added.iter().enumerate() {
+        marked.push('\x11');
+        match removed.get(index) {
+            Some(previous) => push_word_diff(marked, previous, line),
+            None => marked.push_str(line),
+        }
+        marked.push('\n');
+    }
+}
+
+fn push_word_diff(marked: &mut String, previous: &str, line: &str) {
+    let old: Vec<&str> = words(previous).collect();
+    let new: Vec<&str> = words(line).collect();
+    let Some(edits) = edit_script(&old, &new, MAX_WORD_EDITS) else {
+        marked.push_str(line);
+        return;
+    };
+    let mut new_index = 0;
+    let mut highlighting = false;
+    for edit in edits {
+        if edit == Edit::Removed {
+            continue;
+        }
+        let changed = edit == Edit::Added;
+        if changed != highlighting {
+            marked.push(if changed { '\x13' } else { '\x14' });
+            highlighting = changed;
+        }
+        marked.push_str(new[new_index]);
+        new_index += 1;
+    }
+    if highlighting {
+        marked.push('\x14');
+    }
+}
+
+fn words(line: &str) -> impl Iterator<Item = &str> {
+    let mut rest = line;
+    std::iter::from_fn(move || {
+        if rest.is_empty() {
+            return None;
+        }
+        let word_end = rest.find(char::is_whitespace).unwrap_or(rest.len());
+        let end = rest[word_end..]
+            .find(|c: char| !c.is_whitespace())
+            .map_or(rest.len(), |gap| word_end + gap);
+        let word = &rest[..end];
+        rest = &rest[end..];
+        Some(word)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn check_script(old: &[char], new: &[char], edits: &[Edit]) {
+        let (mut old_index, mut new_index) = (0, 0);
+        for edit in edits {
+            match edit {
+                Edit::Same => {
+                    assert_eq!(old[old_index], new[new_index]);
+                    old_index += 1;
+                    new_index += 1;
+                }
+                Edit::Removed => old_index += 1,
+                Edit::Added => new_index += 1,
+            }
+        }
+        assert_eq!((old_index, new_index), (old.len(), new.len()));
+    }
+
+    fn chars(text: &str) -> Vec<char> {
+        text.chars().collect()
+    }
+
+    #[test]
+    fn edit_script_is_minimal() {
+        let (old, new) = (chars("ABCABBA"), chars("CBABAC"));
+        let edits = edit_script(&old, &new, 100).unwrap();
+        check_script(&old, &new, &edits);
+        assert_eq!(edits.iter().filter(|edit| **edit != Edit::Same).count(), 5);
+    }
+
+    #[test]
+    fn edit_script_handles_empty_and_equal_inputs() {
+        let text = chars("same");
+        assert_eq!(edit_script(&text, &text, 0), Some(vec![Edit::Same; 4]));
+        assert_eq!(edit_script(&[], &text, 10), Some(vec![Edit::Added; 4]));
+        assert_eq!(edit_script(&text, &[], 10), Some(vec![Edit::Removed; 4]));
+        assert_eq!(edit_script::<char>(&[], &[], 0), Some(Vec::new()));
+    }
+
+    #[test]
+    fn edit_script_gives_up_past_the_limit() {
+        let (old, new) = (chars("abc"), chars("xyz"));
+        assert_eq!(edit_script(&old, &new, 5), None);
+        let edits = edit_script(&old, &new, 6).unwrap();
+        check_script(&old, &new, &edits);
+    }
+
+    #[test]
+    fn words_keep_their_trailing_whitespace() {
+        let line = "  let  x = 1;\t";
+        let split: Vec<&str> = words(line).collect();
+        assert_eq!(split, ["  ", "let  ", "x ", "= ", "1;\t"]);
+        assert_eq!(split.concat(), line);
+    }
+
+    #[test]
+    fn unchanged_responses_have_no_markers() {
+        let text = "First line\nSecond line\n";
+        assert_eq!(marked_response_diff(text, text).as_deref(), Some(text));
+    }
+
+    #[test]
+    fn changed_lines_show_the_removed_line_and_highlight_new_words() {
+        let marked = marked_response_diff(
+            "Intro\nUse a Vec here.\nOutro",
+            "Intro\nUse a VecDeque here.\nOutro",
+        )
+        .unwrap();
+        assert_eq!(
+            marked,
+            "Intro\n\x12\u{2212} Use a Vec here.\n\x11Use a \x13VecDeque \x14here.\nOutro\n"
+        );
+    }
+
+    #[test]
+    fn removed_blocks_collapse_to_a_preview() {
+        let long = "x".repeat(REMOVED_PREVIEW_CHARS + 5);
+        let marked = marked_response_diff(&format!("{}\ngone\nkept", long), "kept").unwrap();
+        assert_eq!(
+            marked,
+            format!(
+                "\x12\u{2212} {}\u{2026} (+1 more removed)\nkept\n",
+                "x".repeat(REMOVED_PREVIEW_CHARS)
+            )
+        );
+    }
+
+    #[test]
+    fn reasoning_and_status_lines_are_ignored() {
+        let marked = marked_response_diff(
+            "\x1Ethinking about it\nAnswer",
+            "\x1Ethinking differently\n\x1FAnswer",
+        )
+        .unwrap();
+        assert_eq!(marked, "Answer\n");
+    }
+
+    #[test]
+    fn large_responses_are_not_diffed() {
+        let old = "line\n".repeat(MAX_DIFF_LINES / 2);
+        let new = "line\n".repeat(MAX_DIFF_LINES / 2 + 1);
+        assert_eq!(marked_response_diff(&old, &new), None);
+
+        let old: String = (0..600).map(|line| format!("old {}\n", line)).collect();
+        let new: String = (0..600).map(|line| format!("new {}\n", line)).collect();
+        assert_eq!(marked_response_diff(&old, &new), None);
+    }
+}
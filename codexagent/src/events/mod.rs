@@ -1,20 +1,72 @@
-use crate::runtime::ContextMenuSelection;
+use std::path::PathBuf;
+use std::sync::Arc;
 
-pub(crate) enum PromptResult {
-    Ok(String, Option<String>),
-    Err(String),
+use crate::logging::LogHealth;
+use crate::prompt::PromptOutput;
+use crate::runtime::{AccessibilityPrefs, ContextMenuSelection, PowerEvent, ProbeKey, ProbeValue};
+
+#[derive(Clone)]
+pub struct RepaintHandle(Arc<dyn Fn() + Send + Sync>);
+
+impl RepaintHandle {
+    pub fn new(repaint: impl Fn() + Send + Sync + 'static) -> Self {
+        Self(Arc::new(repaint))
+    }
+
+    pub fn request_repaint(&self) {
+        (self.0)()
+    }
+}
+
+pub enum PromptResult {
+    Ok(PromptOutput),
+    Err(ErrorKind, String),
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ErrorKind {
+    SpawnFailed,
+    CodexNotFound,
+    AuthRequired,
+    RateLimited,
+    Timeout,
+    Cancelled,
+    NonZeroExit,
+    InvalidOutput,
+    ResumeFailed,
+    BudgetExceeded,
 }
 
-pub(crate) enum CodexCheckResult {
+pub enum CodexCheckResult {
     Ready,
     NotInstalled { node_available: bool },
 }
 
-pub(crate) enum AppEvent {
+pub enum AppEvent {
     PromptStream(u64),
     Prompt(u64, PromptResult),
+    SessionResumeFailed(u64),
+    PromptCancelled(u64, Result<(), String>),
     CodexCheck(CodexCheckResult),
     CodexInstallOutput(String),
     CodexInstallDone(Result<(), String>),
     ContextMenuSelection(Result<ContextMenuSelection, String>),
+    CodexPath(Option<PathBuf>),
+    CodexAuth(bool),
+    FolderPicked(Option<PathBuf>),
+    RecentSessionExport(PathBuf, Option<PathBuf>),
+    ChangedFileDiff(String, Result<String, String>),
+    ChangedFileReverted(String, Result<(), String>),
+    HookOutput(u64, String),
+    HookDone(u64, Result<i32, String>),
+    ResponseDiff((usize, usize), Option<String>),
+    AccessibilityChanged(AccessibilityPrefs),
+    SystemThemeChanged(bool),
+    LogHealth(LogHealth),
+    Diagnostics(Result<String, String>),
+    ProbeResult(ProbeKey, ProbeValue),
+    #[cfg_attr(not(windows), allow(dead_code))]
+    Power(PowerEvent),
+    #[cfg_attr(not(windows), allow(dead_code))]
+    ClipboardText(String),
 }
@@ -0,0 +1,508 @@
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::time::Duration;
+
+use crate::logging;
+use crate::runtime::user_locale_name;
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Text {
+    Abort,
+    Apply,
+    Approve,
+    Cancel,
+    Cancelling,
+    CheckAgain,
+    Clear,
+    Close,
+    Continue,
+    CopyAll,
+    Delete,
+    Deny,
+    Diff,
+    DiscardAndClear,
+    Dismiss,
+    DontAsk,
+    Done,
+    Edit,
+    Export,
+    Hide,
+    Ignore,
+    Keep,
+    Never,
+    Open,
+    RedactAndSend,
+    Replace,
+    Review,
+    Revert,
+    Save,
+    Send,
+    SendAnyway,
+    Settings,
+    UntitledSession,
+    OneTurn,
+    Turns,
+    JustNow,
+    MinutesAgo,
+    HoursAgo,
+    DaysAgo,
+    ResetsIn,
+    ResetsTodayAt,
+    ResetsOnAt,
+    Monday,
+    Tuesday,
+    Wednesday,
+    Thursday,
+    Friday,
+    Saturday,
+    Sunday,
+    SpawnFailed,
+    SpawnFailedHint,
+    CodexNotFound,
+    CodexNotFoundHint,
+    AuthRequired,
+    AuthRequiredHint,
+    RateLimited,
+    RateLimitedHint,
+    TimedOut,
+    TimedOutHint,
+    PromptCancelled,
+    PromptCancelledHint,
+    NonZeroExit,
+    NonZeroExitHint,
+    InvalidOutput,
+    InvalidOutputHint,
+    ResumeFailed,
+    ResumeFailedHint,
+    BudgetExceeded,
+    BudgetExceededHint,
+}
+
+const TEXT_COUNT: usize = Text::BudgetExceededHint as usize + 1;
+
+const TEXTS: [Text; TEXT_COUNT] = [
+    Text::Abort,
+    Text::Apply,
+    Text::Approve,
+    Text::Cancel,
+    Text::Cancelling,
+    Text::CheckAgain,
+    Text::Clear,
+    Text::Close,
+    Text::Continue,
+    Text::CopyAll,
+    Text::Delete,
+    Text::Deny,
+    Text::Diff,
+    Text::DiscardAndClear,
+    Text::Dismiss,
+    Text::DontAsk,
+    Text::Done,
+    Text::Edit,
+    Text::Export,
+    Text::Hide,
+    Text::Ignore,
+    Text::Keep,
+    Text::Never,
+    Text::Open,
+    Text::RedactAndSend,
+    Text::Replace,
+    Text::Review,
+    Text::Revert,
+    Text::Save,
+    Text::Send,
+    Text::SendAnyway,
+    Text::Settings,
+    Text::UntitledSession,
+    Text::OneTurn,
+    Text::Turns,
+    Text::JustNow,
+    Text::MinutesAgo,
+    Text::HoursAgo,
+    Text::DaysAgo,
+    Text::ResetsIn,
+    Text::ResetsTodayAt,
+    Text::ResetsOnAt,
+    Text::Monday,
+    Text::Tuesday,
+    Text::Wednesday,
+    Text::Thursday,
+    Text::Friday,
+    Text::Saturday,
+    Text::Sunday,
+    Text::SpawnFailed,
+    Text::SpawnFailedHint,
+    Text::CodexNotFound,
+    Text::CodexNotFoundHint,
+    Text::AuthRequired,
+    Text::AuthRequiredHint,
+    Text::RateLimited,
+    Text::RateLimitedHint,
+    Text::TimedOut,
+    Text::TimedOutHint,
+    Text::PromptCancelled,
+    Text::PromptCancelledHint,
+    Text::NonZeroExit,
+    Text::NonZeroExitHint,
+    Text::InvalidOutput,
+    Text::InvalidOutputHint,
+    Text::ResumeFailed,
+    Text::ResumeFailedHint,
+    Text::BudgetExceeded,
+    Text::BudgetExceededHint,
+];
+
+pub struct Catalog {
+    pub tag: &'static str,
+    pub name: &'static str,
+    clock_24h: bool,
+    strings: fn(Text) -> &'static str,
+}
+
+pub const CATALOGS: [Catalog; 2] = [
+    Catalog {
+        tag: "en",
+        name: "English",
+        clock_24h: false,
+        strings: english,
+    },
+    Catalog {
+        tag: "de",
+        name: "Deutsch",
+        clock_24h: true,
+        strings: german,
+    },
+];
+
+const _: () = {
+    let mut index = 0;
+    while index < TEXT_COUNT {
+        let text = TEXTS[index];
+        assert!(text as usize == index, "TEXTS must list every key in order");
+        check_translation(english(text), english(text));
+        check_translation(english(text), german(text));
+        index += 1;
+    }
+};
+
+const fn placeholder_count(text: &str) -> usize {
+    let bytes = text.as_bytes();
+    let mut count = 0;
+    let mut index = 0;
+    while index + 1 < bytes.len() {
+        if bytes[index] == b'{' && bytes[index + 1] == b'}' {
+            count += 1;
+        }
+        index += 1;
+    }
+    count
+}
+
+const fn check_translation(source: &str, translation: &str) {
+    assert!(!translation.is_empty(), "every key needs a value");
+    assert!(
+        placeholder_count(source) == placeholder_count(translation),
+        "a translation must keep the placeholders of its key"
+    );
+}
+
+const fn english(text: Text) -> &'static str {
+    match text {
+        Text::Abort => "Abort",
+        Text::Apply => "Apply",
+        Text::Approve => "Approve",
+        Text::Cancel => "Cancel",
+        Text::Cancelling => "Cancelling",
+        Text::CheckAgain => "Check again",
+        Text::Clear => "Clear",
+        Text::Close => "Close",
+        Text::Continue => "Continue",
+        Text::CopyAll => "Copy all",
+        Text::Delete => "Delete",
+        Text::Deny => "Deny",
+        Text::Diff => "Diff",
+        Text::DiscardAndClear => "Discard and clear",
+        Text::Dismiss => "Dismiss",
+        Text::DontAsk => "Don't ask",
+        Text::Done => "Done",
+        Text::Edit => "Edit",
+        Text::Export => "Export",
+        Text::Hide => "Hide",
+        Text::Ignore => "Ignore",
+        Text::Keep => "Keep",
+        Text::Never => "Never",
+        Text::Open => "Open",
+        Text::RedactAndSend => "Redact and send",
+        Text::Replace => "Replace",
+        Text::Review => "Review",
+        Text::Revert => "Revert",
+        Text::Save => "Save",
+        Text::Send => "Send",
+        Text::SendAnyway => "Send anyway",
+        Text::Settings => "Settings",
+        Text::UntitledSession => "Untitled session",
+        Text::OneTurn => "{} turn",
+        Text::Turns => "{} turns",
+        Text::JustNow => "just now",
+        Text::MinutesAgo => "{}m ago",
+        Text::HoursAgo => "{}h ago",
+        Text::DaysAgo => "{}d ago",
+        Text::ResetsIn => "Resets in {}",
+        Text::ResetsTodayAt => "Resets Today at {}",
+        Text::ResetsOnAt => "Resets {} at {}",
+        Text::Monday => "Monday",
+        Text::Tuesday => "Tuesday",
+        Text::Wednesday => "Wednesday",
+        Text::Thursday => "Thursday",
+        Text::Friday => "Friday",
+        Text::Saturday => "Saturday",
+        Text::Sunday => "Sunday",
+        Text::SpawnFailed => "Codex could not be started",
+        Text::SpawnFailedHint => {
+            "Check that the working directory exists and codex runs from a terminal"
+        }
+        Text::CodexNotFound => "Codex was not found",
+        Text::CodexNotFoundHint => {
+            "Reinstall it from Settings \u{2192} Codex Install, or put codex on PATH"
+        }
+        Text::AuthRequired => "Codex is not signed in",
+        Text::AuthRequiredHint => "Run codex login, then retry the prompt",
+        Text::RateLimited => "Rate limited",
+        Text::RateLimitedHint => "Automatic retries ran out \u{2014} wait a minute, then retry",
+        Text::TimedOut => "Codex timed out",
+        Text::TimedOutHint => "Check the network connection, then retry",
+        Text::PromptCancelled => "Prompt cancelled",
+        Text::PromptCancelledHint => "Resubmit the prompt to run it again",
+        Text::NonZeroExit => "Codex exited with an error",
+        Text::NonZeroExitHint => "Expand the details below for codex's own message",
+        Text::InvalidOutput => "Codex output could not be read",
+        Text::InvalidOutputHint => "Open Inspect events from the output menu to see the raw stream",
+        Text::ResumeFailed => "The session could not be resumed",
+        Text::ResumeFailedHint => "Start a new topic, or /resume a different session id",
+        Text::BudgetExceeded => "Usage quota used up",
+        Text::BudgetExceededHint => {
+            "Check the account's plan or billing, or wait for the quota to reset"
+        }
+    }
+}
+
+const fn german(text: Text) -> &'static str {
+    match text {
+        Text::Abort => "Abbrechen",
+        Text::Apply => "\u{00DC}bernehmen",
+        Text::Approve => "Zulassen",
+        Text::Cancel => "Abbrechen",
+        Text::Cancelling => "Wird abgebrochen",
+        Text::CheckAgain => "Erneut pr\u{00FC}fen",
+        Text::Clear => "Leeren",
+        Text::Close => "Schlie\u{00DF}en",
+        Text::Continue => "Fortfahren",
+        Text::CopyAll => "Alles kopieren",
+        Text::Delete => "L\u{00F6}schen",
+        Text::Deny => "Ablehnen",
+        Text::Diff => "Diff",
+        Text::DiscardAndClear => "Verwerfen und leeren",
+        Text::Dismiss => "Ausblenden",
+        Text::DontAsk => "Nicht mehr fragen",
+        Text::Done => "Fertig",
+        Text::Edit => "Bearbeiten",
+        Text::Export => "Exportieren",
+        Text::Hide => "Ausblenden",
+        Text::Ignore => "Ignorieren",
+        Text::Keep => "Behalten",
+        Text::Never => "Nie",
+        Text::Open => "\u{00D6}ffnen",
+        Text::RedactAndSend => "Schw\u{00E4}rzen und senden",
+        Text::Replace => "Ersetzen",
+        Text::Review => "Pr\u{00FC}fen",
+        Text::Revert => "Zur\u{00FC}cksetzen",
+        Text::Save => "Speichern",
+        Text::Send => "Senden",
+        Text::SendAnyway => "Trotzdem senden",
+        Text::Settings => "Einstellungen",
+        Text::UntitledSession => "Unbenannte Sitzung",
+        Text::OneTurn => "{} Runde",
+        Text::Turns => "{} Runden",
+        Text::JustNow => "gerade eben",
+        Text::MinutesAgo => "vor {} Min.",
+        Text::HoursAgo => "vor {} Std.",
+        Text::DaysAgo => "vor {} Tg.",
+        Text::ResetsIn => "Zur\u{00FC}ckgesetzt in {}",
+        Text::ResetsTodayAt => "Zur\u{00FC}ckgesetzt heute um {}",
+        Text::ResetsOnAt => "Zur\u{00FC}ckgesetzt am {} um {}",
+        Text::Monday => "Montag",
+        Text::Tuesday => "Dienstag",
+        Text::Wednesday => "Mittwoch",
+        Text::Thursday => "Donnerstag",
+        Text::Friday => "Freitag",
+        Text::Saturday => "Samstag",
+        Text::Sunday => "Sonntag",
+        Text::SpawnFailed => "Codex konnte nicht gestartet werden",
+        Text::SpawnFailedHint => {
+            "Pr\u{00FC}fen, ob das Arbeitsverzeichnis existiert und codex im Terminal l\u{00E4}uft"
+        }
+        Text::CodexNotFound => "Codex wurde nicht gefunden",
+        Text::CodexNotFoundHint => {
+            "Unter Settings \u{2192} Codex Install neu installieren oder codex in den PATH aufnehmen"
+        }
+        Text::AuthRequired => "Codex ist nicht angemeldet",
+        Text::AuthRequiredHint => "codex login ausf\u{00FC}hren und den Prompt wiederholen",
+        Text::RateLimited => "Ratenlimit erreicht",
+        Text::RateLimitedHint => {
+            "Automatische Wiederholungen ersch\u{00F6}pft \u{2014} eine Minute warten und wiederholen"
+        }
+        Text::TimedOut => "Zeit\u{00FC}berschreitung bei Codex",
+        Text::TimedOutHint => "Netzwerkverbindung pr\u{00FC}fen und wiederholen",
+        Text::PromptCancelled => "Prompt abgebrochen",
+        Text::PromptCancelledHint => {
+            "Den Prompt erneut senden, um ihn noch einmal auszuf\u{00FC}hren"
+        }
+        Text::NonZeroExit => "Codex wurde mit einem Fehler beendet",
+        Text::NonZeroExitHint => "Die Details unten aufklappen, um die Meldung von codex zu sehen",
+        Text::InvalidOutput => "Die Ausgabe von Codex war nicht lesbar",
+        Text::InvalidOutputHint => {
+            "Inspect events im Ausgabemen\u{00FC} \u{00F6}ffnen, um den Rohdatenstrom zu sehen"
+        }
+        Text::ResumeFailed => "Die Sitzung konnte nicht fortgesetzt werden",
+        Text::ResumeFailedHint => {
+            "Ein neues Thema beginnen oder mit /resume eine andere Sitzungs-ID fortsetzen"
+        }
+        Text::BudgetExceeded => "Nutzungskontingent aufgebraucht",
+        Text::BudgetExceededHint => {
+            "Tarif oder Abrechnung des Kontos pr\u{00FC}fen oder warten, bis das Kontingent zur\u{00FC}ckgesetzt wird"
+        }
+    }
+}
+
+static LOCALE: AtomicUsize = AtomicUsize::new(0);
+static FOLLOW_SYSTEM: AtomicBool = AtomicBool::new(true);
+
+fn catalog() -> &'static Catalog {
+    &CATALOGS[LOCALE.load(Ordering::Relaxed)]
+}
+
+fn catalog_index(tag: &str) -> Option<usize> {
+    let primary = tag.split(['-', '_']).next().unwrap_or_default();
+    CATALOGS
+        .iter()
+        .position(|catalog| catalog.tag.eq_ignore_ascii_case(primary))
+}
+
+pub fn apply_locale(tag: Option<&str>) -> &'static str {
+    let system = tag.is_none();
+    let tag = tag.map(str::to_owned).or_else(user_locale_name);
+    let index = tag.as_deref().and_then(catalog_index).unwrap_or(0);
+    LOCALE.store(index, Ordering::Relaxed);
+    FOLLOW_SYSTEM.store(system, Ordering::Relaxed);
+    logging::trace(format!(
+        "interface language {} (locale {}{})",
+        CATALOGS[index].tag,
+        tag.as_deref().unwrap_or("unknown"),
+        if system { ", system default" } else { "" }
+    ));
+    CATALOGS[index].name
+}
+
+pub fn pinned_locale() -> Option<&'static str> {
+    (!FOLLOW_SYSTEM.load(Ordering::Relaxed)).then(|| catalog().tag)
+}
+
+pub fn tr(text: Text) -> &'static str {
+    (catalog().strings)(text)
+}
+
+pub fn tr_fill(text: Text, values: &[&str]) -> String {
+    let mut pieces = tr(text).split("{}");
+    let mut filled = pieces.next().unwrap_or_default().to_owned();
+    for (index, piece) in pieces.enumerate() {
+        filled.push_str(values.get(index).copied().unwrap_or_default());
+        filled.push_str(piece);
+    }
+    filled
+}
+
+pub fn turn_count(turns: usize) -> String {
+    let text = if turns == 1 {
+        Text::OneTurn
+    } else {
+        Text::Turns
+    };
+    tr_fill(text, &[&turns.to_string()])
+}
+
+pub fn relative_age(age: Duration) -> String {
+    let minutes = age.as_secs() / 60;
+    let (text, value) = match minutes {
+        0 => return tr(Text::JustNow).to_owned(),
+        1..60 => (Text::MinutesAgo, minutes),
+        60..1440 => (Text::HoursAgo, minutes / 60),
+        _ => (Text::DaysAgo, minutes / 1440),
+    };
+    tr_fill(text, &[&value.to_string()])
+}
+
+pub fn clock_time(hour: u8, minute: u8) -> String {
+    if catalog().clock_24h {
+        return format!("{}:{:02}", hour, minute);
+    }
+    let display_hour = match hour % 12 {
+        0 => 12,
+        value => value,
+    };
+    let suffix = if hour < 12 { "am" } else { "pm" };
+    if minute == 0 {
+        return format!("{}{}", display_hour, suffix);
+    }
+    format!("{}:{:02}{}", display_hour, minute, suffix)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_key_has_a_value_in_every_locale() {
+        for catalog in &CATALOGS {
+            for text in TEXTS {
+                let value = (catalog.strings)(text);
+                assert!(!value.trim().is_empty(), "{} {:?}", catalog.tag, text);
+                assert_eq!(
+                    placeholder_count(value),
+                    placeholder_count(english(text)),
+                    "{} {:?}",
+                    catalog.tag,
+                    text
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn catalog_tags_are_unique_primary_tags() {
+        for (index, catalog) in CATALOGS.iter().enumerate() {
+            assert!(!catalog.tag.contains(['-', '_']), "{}", catalog.tag);
+            assert_eq!(catalog_index(catalog.tag), Some(index));
+        }
+    }
+
+    #[test]
+    fn locale_names_match_on_the_primary_tag() {
+        assert_eq!(catalog_index("de-DE"), Some(1));
+        assert_eq!(catalog_index("DE_at"), Some(1));
+        assert_eq!(catalog_index("en-GB"), Some(0));
+        assert_eq!(catalog_index("fr-FR"), None);
+        assert_eq!(catalog_index(""), None);
+    }
+
+    #[test]
+    fn placeholders_fill_in_order() {
+        assert_eq!(placeholder_count("{} of {} done"), 2);
+        assert_eq!(placeholder_count("no { } here"), 0);
+        let minutes = tr_fill(Text::MinutesAgo, &["5"]);
+        assert!(
+            minutes.contains('5') && !minutes.contains("{}"),
+            "{}",
+            minutes
+        );
+        assert!(!tr_fill(Text::MinutesAgo, &[]).contains("{}"));
+    }
+}
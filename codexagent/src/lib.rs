@@ -0,0 +1,23 @@
+#[cfg(feature = "gui")]
+mod app;
+pub mod attach;
+pub mod config;
+pub mod diff;
+pub mod events;
+pub mod i18n;
+pub mod logging;
+pub mod notify;
+pub mod plaintext;
+pub mod postprocess;
+pub mod prompt;
+pub mod redact;
+pub mod runtime;
+pub mod status;
+pub mod transform;
+pub mod usage;
+
+// The egui app is compiled here rather than in the binary so tests/ can
+// drive it headlessly; only the window entry points and the headless
+// accessors on CodexAgentApp are public.
+#[cfg(feature = "gui")]
+pub use app::{CodexAgentApp, rendered_any_frame, take_render_restart, terminate_tracked_codex};
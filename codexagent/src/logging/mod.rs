@@ -1,20 +1,42 @@
 use std::any::Any;
+use std::collections::VecDeque;
 use std::fmt;
 use std::fs::{self, OpenOptions};
 use std::io::{BufWriter, Write, stderr};
 use std::panic::{self, PanicHookInfo};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::sync::{
     Mutex, OnceLock,
     atomic::{AtomicBool, Ordering},
     mpsc,
 };
-use std::time::{SystemTime, UNIX_EPOCH};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
-use crate::config::APP_NAME;
+use crate::config::{APP_DISPLAY_NAME, build_stamp};
+use crate::redact;
+use crate::runtime::join_within;
 
 pub static FILE_LOGGING: AtomicBool = AtomicBool::new(true);
 pub static CONSOLE_LOGGING: AtomicBool = AtomicBool::new(true);
+static REOPEN_REQUESTED: AtomicBool = AtomicBool::new(false);
+
+#[derive(Clone)]
+pub struct LogHealth {
+    pub file_logging: bool,
+    pub last_error: Option<String>,
+    pub log_files: Vec<PathBuf>,
+}
+
+type HealthListener = Box<dyn Fn(LogHealth) + Send>;
+
+static HEALTH: Mutex<LogHealth> = Mutex::new(LogHealth {
+    file_logging: true,
+    last_error: None,
+    log_files: Vec::new(),
+});
+static HEALTH_LISTENER: Mutex<Option<HealthListener>> = Mutex::new(None);
+static RECENT_ERRORS: Mutex<VecDeque<String>> = Mutex::new(VecDeque::new());
+const RECENT_ERROR_LINES: usize = 50;
 
 #[derive(Clone, Copy)]
 enum LogLevel {
@@ -27,16 +49,16 @@ struct LogEntry {
     message: LogMessage,
 }
 
-pub(crate) enum LogMessage {
+pub enum LogMessage {
     Static(&'static str),
     Owned(String),
 }
 
 impl LogMessage {
-    fn as_bytes(&self) -> &[u8] {
+    fn as_str(&self) -> &str {
         match self {
-            Self::Static(message) => message.as_bytes(),
-            Self::Owned(message) => message.as_bytes(),
+            Self::Static(message) => message,
+            Self::Owned(message) => message,
         }
     }
 }
@@ -61,33 +83,25 @@ struct LogHandle {
 static LOG_HANDLE: OnceLock<LogHandle> = OnceLock::new();
 static PANIC_HOOK_INSTALLED: OnceLock<()> = OnceLock::new();
 const FLUSH_BATCHES: u8 = 16;
+const CLOSE_TIMEOUT: Duration = Duration::from_secs(2);
 
 fn write_stderr(args: fmt::Arguments<'_>) {
     let mut lock = stderr().lock();
     let _ = lock.write_fmt(args).and_then(|_| lock.write_all(b"\n"));
 }
 
-pub fn init() {
+pub fn init(log_dir: &Path) {
+    let mut started = false;
     LOG_HANDLE.get_or_init(|| {
-        let mut date_buf = [0u8; 10];
-        let secs = SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .map(|duration| duration.as_secs() as i64)
-            .unwrap_or(0);
-        let days = secs.div_euclid(86_400);
-        let (year, mon, day) = civil_from_days(days);
-
-        write_date(&mut date_buf, year, mon, day);
-        let date = unsafe { std::str::from_utf8_unchecked(&date_buf) };
-
-        let error_file = open_log_file(date, "errors.log");
-        let trace_file = open_log_file(date, "traces.log");
+        started = true;
+        let (error_file, trace_file) = open_log_files(log_dir);
 
         let (tx, rx) = mpsc::channel::<LogEntry>();
 
+        let log_dir = log_dir.to_path_buf();
         let handle = std::thread::spawn(move || {
             let _ = catch_panic("log receiver thread", || {
-                recv_loop(rx, error_file, trace_file)
+                recv_loop(rx, &log_dir, error_file, trace_file)
             });
         });
 
@@ -96,6 +110,9 @@ pub fn init() {
             handle: Mutex::new(Some(handle)),
         }
     });
+    if started {
+        trace(format!("{} {}", APP_DISPLAY_NAME, build_stamp()));
+    }
 }
 
 pub fn install_panic_hook() {
@@ -110,14 +127,71 @@ pub fn install_panic_hook() {
 
 pub fn close() {
     if let Some(lh) = LOG_HANDLE.get() {
-        let sender = lh.tx.lock().unwrap_or_else(|e| e.into_inner()).take();
-        drop(sender);
-        let handle = lh.handle.lock().unwrap_or_else(|e| e.into_inner()).take();
-        if let Some(h) = handle {
-            if h.join().is_err() {
-                write_stderr(format_args!("Log receiver thread panicked during shutdown"));
-            }
+        close_within(lh, CLOSE_TIMEOUT);
+    }
+}
+
+fn close_within(lh: &LogHandle, timeout: Duration) {
+    let sender = lh.tx.lock().unwrap_or_else(|e| e.into_inner()).take();
+    drop(sender);
+    let handle = lh.handle.lock().unwrap_or_else(|e| e.into_inner()).take();
+    match handle.map(|handle| join_within(handle, timeout)) {
+        Some(Ok(Err(_))) => {
+            write_stderr(format_args!("Log receiver thread panicked during shutdown"))
         }
+        Some(Err(_)) => write_stderr(format_args!(
+            "Log receiver thread did not finish within {} ms; exiting without it",
+            timeout.as_millis()
+        )),
+        Some(Ok(Ok(()))) | None => {}
+    }
+}
+
+pub fn health() -> LogHealth {
+    HEALTH.lock().unwrap_or_else(|e| e.into_inner()).clone()
+}
+
+pub fn watch_health(listener: impl Fn(LogHealth) + Send + 'static) {
+    *HEALTH_LISTENER.lock().unwrap_or_else(|e| e.into_inner()) = Some(Box::new(listener));
+}
+
+pub fn recent_errors() -> Vec<String> {
+    RECENT_ERRORS
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .iter()
+        .cloned()
+        .collect()
+}
+
+fn remember_error(message: &str) {
+    let mut ts_buf = [0u8; 19];
+    local_timestamp(&mut ts_buf);
+    let timestamp = std::str::from_utf8(&ts_buf).unwrap_or_default();
+    let mut recent = RECENT_ERRORS.lock().unwrap_or_else(|e| e.into_inner());
+    if recent.len() == RECENT_ERROR_LINES {
+        recent.pop_front();
+    }
+    recent.push_back(format!("{} {}", timestamp, message));
+}
+
+pub fn retry_file_logging() {
+    REOPEN_REQUESTED.store(true, Ordering::Relaxed);
+    trace("retrying file logging");
+}
+
+fn update_health(change: impl FnOnce(&mut LogHealth)) {
+    let health = {
+        let mut health = HEALTH.lock().unwrap_or_else(|e| e.into_inner());
+        change(&mut health);
+        health.clone()
+    };
+    if let Some(listener) = HEALTH_LISTENER
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .as_ref()
+    {
+        listener(health);
     }
 }
 
@@ -151,15 +225,18 @@ where
 }
 
 fn send(level: LogLevel, message: LogMessage) {
+    if matches!(level, LogLevel::Error) {
+        remember_error(message.as_str());
+    }
     if let Some(lh) = LOG_HANDLE.get() {
         match lh.tx.lock() {
             Ok(guard) => {
-                if let Some(tx) = guard.as_ref() {
-                    if tx.send(LogEntry { level, message }).is_err() {
-                        write_stderr(format_args!(
-                            "Log channel closed: receiver thread has exited"
-                        ));
-                    }
+                if let Some(tx) = guard.as_ref()
+                    && tx.send(LogEntry { level, message }).is_err()
+                {
+                    write_stderr(format_args!(
+                        "Log channel closed: receiver thread has exited"
+                    ));
                 }
             }
             Err(_) => {
@@ -171,6 +248,7 @@ fn send(level: LogLevel, message: LogMessage) {
 
 fn recv_loop(
     rx: mpsc::Receiver<LogEntry>,
+    log_dir: &Path,
     mut error_file: Option<BufWriter<std::fs::File>>,
     mut trace_file: Option<BufWriter<std::fs::File>>,
 ) {
@@ -184,6 +262,13 @@ fn recv_loop(
             batch.push(entry);
         }
 
+        if REOPEN_REQUESTED.swap(false, Ordering::Relaxed) {
+            flush_if_needed(&mut error_file);
+            flush_if_needed(&mut trace_file);
+            (error_file, trace_file) = open_log_files(log_dir);
+            batches_since_flush = 0;
+        }
+
         let mut file_logging = FILE_LOGGING.load(Ordering::Relaxed);
         let console_logging = CONSOLE_LOGGING.load(Ordering::Relaxed);
 
@@ -203,33 +288,28 @@ fn recv_loop(
         let ts_bytes = &ts_buf[..];
 
         for entry in &batch {
-            let message = entry.message.as_bytes();
+            let message = entry.message.as_str().as_bytes();
             let writer = match entry.level {
                 LogLevel::Error => &mut error_file,
                 LogLevel::Trace => &mut trace_file,
             };
 
-            if file_logging {
-                if let Some(f) = writer.as_mut() {
-                    for attempt in 0..3u8 {
-                        let res = f
-                            .write_all(ts_bytes)
-                            .and_then(|_| f.write_all(b" "))
-                            .and_then(|_| f.write_all(message))
-                            .and_then(|_| f.write_all(b"\n"));
-                        match res {
-                            Ok(()) => break,
-                            Err(e) if attempt < 2 => {
-                                write_stderr(format_args!("Log write failed: {}. Retrying...", e));
-                            }
-                            Err(e) => {
-                                write_stderr(format_args!(
-                                    "Log write failed on final retry: {}. Disabling file logging.",
-                                    e
-                                ));
-                                disable_file_logging();
-                                file_logging = false;
-                            }
+            if file_logging && let Some(f) = writer.as_mut() {
+                let persisted = redact::redact_persistent(entry.message.as_str());
+                for attempt in 0..3u8 {
+                    let res = f
+                        .write_all(ts_bytes)
+                        .and_then(|_| f.write_all(b" "))
+                        .and_then(|_| f.write_all(persisted.as_bytes()))
+                        .and_then(|_| f.write_all(b"\n"));
+                    match res {
+                        Ok(()) => break,
+                        Err(e) if attempt < 2 => {
+                            write_stderr(format_args!("Log write failed: {}. Retrying...", e));
+                        }
+                        Err(e) => {
+                            disable_file_logging(format!("Log write failed: {}", e));
+                            file_logging = false;
                         }
                     }
                 }
@@ -273,63 +353,89 @@ fn recv_loop(
 }
 
 fn flush_if_needed(file: &mut Option<BufWriter<std::fs::File>>) {
-    if let Some(f) = file.as_mut() {
+    if let Some(f) = file.as_mut()
+        && let Err(e) = f.flush()
+    {
+        write_stderr(format_args!("Log flush failed: {}. Retrying...", e));
         if let Err(e) = f.flush() {
-            write_stderr(format_args!("Log flush failed: {}. Retrying...", e));
-            if let Err(e) = f.flush() {
-                write_stderr(format_args!(
-                    "Log flush failed on retry: {}. Disabling file logging.",
-                    e
-                ));
-                disable_file_logging();
-            }
+            disable_file_logging(format!("Log flush failed: {}", e));
         }
     }
 }
 
-fn disable_file_logging() {
+fn disable_file_logging(reason: String) {
     FILE_LOGGING.store(false, Ordering::Relaxed);
-    write_stderr(format_args!("File logging has been disabled."));
+    write_stderr(format_args!("{}. File logging has been disabled.", reason));
+    update_health(|health| {
+        health.file_logging = false;
+        health.last_error = Some(reason);
+    });
 }
 
-fn open_log_file(date: &str, filename: &str) -> Option<BufWriter<std::fs::File>> {
-    let mut path = PathBuf::from(r"C:\Local\Logs");
-    path.push(APP_NAME);
+type LogFiles = (
+    Option<BufWriter<std::fs::File>>,
+    Option<BufWriter<std::fs::File>>,
+);
 
+fn open_log_files(log_dir: &Path) -> LogFiles {
+    let mut date_buf = [0u8; 10];
+    let secs = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs() as i64)
+        .unwrap_or(0);
+    let days = secs.div_euclid(86_400);
+    let (year, mon, day) = civil_from_days(days);
+
+    write_date(&mut date_buf, year, mon, day);
+    let date = unsafe { std::str::from_utf8_unchecked(&date_buf) };
+
+    FILE_LOGGING.store(true, Ordering::Relaxed);
+    let error_path = log_file_path(log_dir, date, "errors.log");
+    let trace_path = log_file_path(log_dir, date, "traces.log");
+    let error_file = open_log_file(&error_path);
+    let trace_file = open_log_file(&trace_path);
+    if FILE_LOGGING.load(Ordering::Relaxed) {
+        update_health(|health| {
+            health.file_logging = true;
+            health.last_error = None;
+            health.log_files = vec![error_path, trace_path];
+        });
+        (error_file, trace_file)
+    } else {
+        (None, None)
+    }
+}
+
+fn log_file_path(log_dir: &Path, date: &str, filename: &str) -> PathBuf {
     let mut name = String::with_capacity(date.len() + 1 + filename.len());
     name.push_str(date);
     name.push('_');
     name.push_str(filename);
-    path.push(name);
+    log_dir.join(name)
+}
 
+fn open_log_file(path: &Path) -> Option<BufWriter<std::fs::File>> {
     let parent = match path.parent() {
         Some(p) => p,
         None => {
-            write_stderr(format_args!("Invalid log path: {}", path.display()));
-            disable_file_logging();
+            disable_file_logging(format!("Invalid log path: {}", path.display()));
             return None;
         }
     };
 
     if let Err(e) = fs::create_dir_all(parent) {
-        write_stderr(format_args!(
+        disable_file_logging(format!(
             "Failed to create log directory {}: {}",
             parent.display(),
             e
         ));
-        disable_file_logging();
         return None;
     }
 
-    match OpenOptions::new().create(true).append(true).open(&path) {
+    match OpenOptions::new().create(true).append(true).open(path) {
         Ok(f) => Some(BufWriter::with_capacity(4096, f)),
         Err(e) => {
-            write_stderr(format_args!(
-                "Failed to open log file {}: {}",
-                path.display(),
-                e
-            ));
-            disable_file_logging();
+            disable_file_logging(format!("Failed to open log file {}: {}", path.display(), e));
             None
         }
     }
@@ -348,11 +454,21 @@ fn write_date(buf: &mut [u8], year: i32, mon: i32, day: i32) {
     buf[9] = b'0' + (day % 10) as u8;
 }
 
+pub fn format_timestamp(secs: i64) -> String {
+    let mut buf = [0u8; 19];
+    write_timestamp(&mut buf, secs);
+    String::from_utf8_lossy(&buf).into_owned()
+}
+
 fn local_timestamp(buf: &mut [u8; 19]) {
     let secs = SystemTime::now()
         .duration_since(UNIX_EPOCH)
         .map(|duration| duration.as_secs() as i64)
         .unwrap_or(0);
+    write_timestamp(buf, secs);
+}
+
+fn write_timestamp(buf: &mut [u8; 19], secs: i64) {
     let (year, mon, day, hour, min, sec) = timestamp_parts(secs);
 
     write_date(buf, year, mon, day);
@@ -416,3 +532,101 @@ fn panic_payload(payload: &(dyn Any + Send)) -> String {
     }
     "non-string panic payload".to_owned()
 }
+
+#[cfg(test)]
+mod tests {
+    use std::time::Instant;
+
+    use super::*;
+
+    #[test]
+    fn close_returns_within_its_budget_when_the_receiver_is_stuck() {
+        let (tx, rx) = mpsc::channel::<LogEntry>();
+        let (release, stuck) = mpsc::channel::<()>();
+        let receiver = std::thread::spawn(move || {
+            let _rx = rx;
+            let _ = stuck.recv();
+        });
+        let lh = LogHandle {
+            tx: Mutex::new(Some(tx)),
+            handle: Mutex::new(Some(receiver)),
+        };
+        let started = Instant::now();
+        close_within(&lh, Duration::from_millis(100));
+        assert!(started.elapsed() < Duration::from_secs(1));
+        assert!(lh.tx.lock().unwrap().is_none());
+        assert!(lh.handle.lock().unwrap().is_none());
+        drop(release);
+    }
+
+    #[test]
+    fn close_joins_a_receiver_that_drains() {
+        let (tx, rx) = mpsc::channel::<LogEntry>();
+        let receiver = std::thread::spawn(move || while rx.recv().is_ok() {});
+        let lh = LogHandle {
+            tx: Mutex::new(Some(tx)),
+            handle: Mutex::new(Some(receiver)),
+        };
+        close_within(&lh, Duration::from_secs(5));
+        assert!(lh.handle.lock().unwrap().is_none());
+        close_within(&lh, Duration::from_secs(5));
+    }
+
+    #[test]
+    fn failed_and_reopened_log_files_report_their_health() {
+        let dir =
+            std::env::temp_dir().join(format!("codexagent-log-health-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let blocked = dir.join("blocked");
+        fs::write(&blocked, "a file where the log folder should be").unwrap();
+        let (tx, changes) = mpsc::channel();
+        watch_health(move |health| {
+            let _ = tx.send(health);
+        });
+
+        let (error_file, trace_file) = open_log_files(&blocked);
+        assert!(error_file.is_none() && trace_file.is_none());
+        let degraded = health();
+        assert!(!degraded.file_logging);
+        assert!(
+            degraded
+                .last_error
+                .as_deref()
+                .is_some_and(|error| error.starts_with("Failed to create log directory")),
+            "{:?}",
+            degraded.last_error
+        );
+        let failures: Vec<_> = changes.try_iter().collect();
+        assert!(!failures.is_empty());
+        assert!(failures.iter().all(|health| !health.file_logging));
+
+        let logs = dir.join("logs");
+        let (error_file, trace_file) = open_log_files(&logs);
+        assert!(error_file.is_some() && trace_file.is_some());
+        let healthy = changes.try_iter().last().unwrap();
+        assert!(healthy.file_logging);
+        assert_eq!(healthy.last_error, None);
+        assert_eq!(healthy.log_files.len(), 2);
+        assert!(
+            healthy
+                .log_files
+                .iter()
+                .all(|path| path.parent() == Some(&*logs))
+        );
+        assert!(
+            healthy.log_files[0]
+                .to_string_lossy()
+                .ends_with("_errors.log")
+        );
+        assert!(
+            healthy.log_files[1]
+                .to_string_lossy()
+                .ends_with("_traces.log")
+        );
+        assert!(health().file_logging);
+
+        *HEALTH_LISTENER.lock().unwrap() = None;
+        drop((error_file, trace_file));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
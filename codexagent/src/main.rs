@@ -1,34 +1,60 @@
 #![cfg_attr(target_os = "windows", windows_subsystem = "windows")]
 
-mod app;
-mod config;
-mod events;
-mod logging;
-mod notify;
-mod prompt;
-mod runtime;
-mod status;
-
 use std::io;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::{Duration, Instant};
 
 use eframe::egui::{self, Vec2};
 
-use crate::app::CodexAgentApp;
-use crate::config::{
-    APP_DISPLAY_NAME, APP_NAME, DEFAULT_WINDOW_HEIGHT, DEFAULT_WINDOW_WIDTH, MIN_WINDOW_HEIGHT,
-    MIN_WINDOW_WIDTH,
+use codexagent::attach::sweep_attachments;
+use codexagent::config::paths::{self, Paths};
+use codexagent::config::{
+    APP_DISPLAY_NAME, APP_NAME, DEFAULT_PERSIST_TRANSCRIPTS, DEFAULT_WINDOW_HEIGHT,
+    DEFAULT_WINDOW_WIDTH, MIN_WINDOW_HEIGHT, MIN_WINDOW_WIDTH, apply_persist_transcripts,
+    flush_settings, load_interface_locale, load_persist_transcripts,
 };
-use crate::runtime::{
-    LaunchRequest, acquire_instance_mutex, apply_launch_request, ensure_app_identity,
-    ensure_codex_files,
+use codexagent::i18n;
+use codexagent::logging::{self, LogHealth};
+use codexagent::prompt::discard_warm_codex;
+use codexagent::runtime::{
+    ErrorDialog, InstanceMutex, LaunchRequest, MessageSink, acquire_instance_mutex,
+    apply_launch_request, ensure_app_identity, ensure_codex_files, environment_report,
 };
+use codexagent::{CodexAgentApp, rendered_any_frame, take_render_restart, terminate_tracked_codex};
 
 const APP_ICON_BYTES: &[u8] = include_bytes!("../assets/app-icon.png");
+const RENDER_RESTART_COOLDOWN: Duration = Duration::from_secs(60);
+const STARTUP_HEADLINE_MAX_CHARS: usize = 160;
+const STARTUP_DETAILS_MAX_CHARS: usize = 600;
 
 fn main() -> io::Result<()> {
-    logging::init();
+    let paths = paths::init(Paths::detect());
+    let prepare_errors = paths::prepare_dirs(paths);
+    logging::init(&paths.log_dir);
     logging::install_panic_hook();
+    for error in prepare_errors {
+        logging::error(error);
+    }
+    paths::migrate_legacy_files(paths);
+    apply_persist_transcripts(load_persist_transcripts().unwrap_or_else(|error| {
+        logging::error(format!(
+            "failed to load transcript persistence setting: {}",
+            error
+        ));
+        DEFAULT_PERSIST_TRANSCRIPTS
+    }));
+    i18n::apply_locale(
+        load_interface_locale()
+            .unwrap_or_else(|error| {
+                logging::error(format!(
+                    "failed to load interface language setting: {}",
+                    error
+                ));
+                None
+            })
+            .as_deref(),
+    );
     struct LogGuard;
     impl Drop for LogGuard {
         fn drop(&mut self) {
@@ -37,43 +63,71 @@ fn main() -> io::Result<()> {
     }
     let _log_guard = LogGuard;
 
-    let _instance_mutex = acquire_instance_mutex();
+    let instance_mutex = acquire_instance_mutex();
     let launch_request = parse_launch_request();
     apply_launch_request(&launch_request);
     ensure_app_identity();
 
     let result = logging::catch_panic("main thread", || -> io::Result<()> {
         logging::trace("process start");
+        if paths.portable {
+            logging::trace(format!(
+                "portable mode; settings and logs stored beside the executable in {}",
+                paths
+                    .settings_file
+                    .parent()
+                    .unwrap_or(&paths.log_dir)
+                    .display()
+            ));
+        }
 
-        ensure_codex_files()?;
-
-        let options = eframe::NativeOptions {
-            viewport: egui::ViewportBuilder::default()
-                .with_inner_size(Vec2::new(DEFAULT_WINDOW_WIDTH, DEFAULT_WINDOW_HEIGHT))
-                .with_min_inner_size(Vec2::new(MIN_WINDOW_WIDTH, MIN_WINDOW_HEIGHT))
-                .with_clamp_size_to_monitor_size(true)
-                .with_resizable(true)
-                .with_visible(true)
-                .with_decorations(false)
-                .with_transparent(true)
-                .with_icon(load_app_icon()?)
-                .with_title(APP_DISPLAY_NAME),
-            persist_window: false,
-            ..Default::default()
-        };
+        environment_report();
+        if let Err(error) = ensure_codex_files() {
+            report_startup_failure(
+                &ErrorDialog,
+                "could not prepare the codex files it needs.",
+                &error,
+            );
+            return Err(error);
+        }
+        if instance_mutex.as_ref().is_some_and(InstanceMutex::first) {
+            thread::spawn(terminate_tracked_codex);
+            thread::spawn(sweep_attachments);
+        }
 
-        logging::trace("starting native app runtime");
-        eframe::run_native(
-            APP_NAME,
-            options,
-            Box::new(move |cc| {
-                CodexAgentApp::new(cc)
-                    .map(|app| Box::new(app) as Box<dyn eframe::App>)
-                    .map_err(|error| error.to_string().into())
-            }),
-        )
-        .map_err(|error| io::Error::other(error.to_string()))?;
+        let mut last_restart: Option<Instant> = None;
+        let mut renderer = RendererMode::Hardware;
+        loop {
+            let (mode, result) = run_with_renderer_fallback(renderer, run_app, rendered_any_frame);
+            renderer = mode;
+            let restart = take_render_restart() || (result.is_err() && rendered_any_frame());
+            if !restart {
+                if let Err(error) = &result
+                    && !rendered_any_frame()
+                {
+                    report_startup_failure(
+                        &ErrorDialog,
+                        "could not open its window, even with software rendering.",
+                        error,
+                    );
+                }
+                result?;
+                break;
+            }
+            if let Err(error) = &result {
+                logging::error(format!("native app runtime failed: {}", error));
+            }
+            if last_restart.is_some_and(|at| at.elapsed() < RENDER_RESTART_COOLDOWN) {
+                logging::error("renderer failed again right after a restart; giving up");
+                return result.and(Err(io::Error::other("renderer failed repeatedly")));
+            }
+            last_restart = Some(Instant::now());
+            terminate_tracked_codex();
+            logging::trace("relaunching native app runtime with the recovered session");
+        }
 
+        discard_warm_codex("the app is exiting");
+        let _ = flush_settings();
         logging::trace("process exit");
         Ok(())
     })
@@ -86,6 +140,127 @@ fn main() -> io::Result<()> {
     result
 }
 
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum RendererMode {
+    Hardware,
+    Software,
+}
+
+impl RendererMode {
+    fn label(self) -> &'static str {
+        match self {
+            RendererMode::Hardware => "hardware",
+            RendererMode::Software => "software",
+        }
+    }
+}
+
+fn run_app(mode: RendererMode) -> io::Result<()> {
+    logging::trace(format!(
+        "starting native app runtime with {} rendering",
+        mode.label()
+    ));
+    eframe::run_native(
+        APP_NAME,
+        native_options(mode)?,
+        Box::new(move |cc| {
+            CodexAgentApp::new(cc)
+                .map(|app| Box::new(app) as Box<dyn eframe::App>)
+                .map_err(|error| error.to_string().into())
+        }),
+    )
+    .map_err(|error| io::Error::other(error.to_string()))
+}
+
+fn run_with_renderer_fallback(
+    mode: RendererMode,
+    mut run: impl FnMut(RendererMode) -> io::Result<()>,
+    started: impl Fn() -> bool,
+) -> (RendererMode, io::Result<()>) {
+    match run(mode) {
+        Err(error) if mode == RendererMode::Hardware && !started() => {
+            logging::error(format!(
+                "renderer failed to start: {}; retrying with software rendering",
+                error
+            ));
+            (RendererMode::Software, run(RendererMode::Software))
+        }
+        result => (mode, result),
+    }
+}
+
+fn report_startup_failure(sink: &impl MessageSink, summary: &str, error: &io::Error) {
+    logging::error(format!("{} {}: {}", APP_DISPLAY_NAME, summary, error));
+    let log_file = startup_log_path(&logging::health(), &paths::get().log_dir);
+    sink.show_error(
+        APP_DISPLAY_NAME,
+        &startup_failure_message(summary, &error.to_string(), &log_file),
+    );
+}
+
+fn startup_log_path(health: &LogHealth, log_dir: &Path) -> PathBuf {
+    health
+        .log_files
+        .first()
+        .filter(|_| health.file_logging)
+        .cloned()
+        .unwrap_or_else(|| log_dir.to_path_buf())
+}
+
+fn startup_failure_message(summary: &str, error: &str, log_file: &Path) -> String {
+    let mut lines = error.lines().map(str::trim).filter(|line| !line.is_empty());
+    let headline = lines.next().unwrap_or("Unknown error");
+    let details = lines.collect::<Vec<_>>().join(" ");
+    let mut message = format!(
+        "{} {}\n\n{}",
+        APP_DISPLAY_NAME,
+        summary,
+        clip_chars(headline, STARTUP_HEADLINE_MAX_CHARS)
+    );
+    if !details.is_empty() {
+        message.push_str("\n\n");
+        message.push_str(&clip_chars(&details, STARTUP_DETAILS_MAX_CHARS));
+    }
+    message.push_str(&format!(
+        "\n\nThe full error is in the log:\n{}",
+        log_file.display()
+    ));
+    message
+}
+
+fn clip_chars(text: &str, max_chars: usize) -> String {
+    if text.chars().count() <= max_chars {
+        return text.to_owned();
+    }
+    let mut clipped: String = text.chars().take(max_chars).collect();
+    clipped.truncate(clipped.trim_end().len());
+    clipped.push('\u{2026}');
+    clipped
+}
+
+fn native_options(mode: RendererMode) -> io::Result<eframe::NativeOptions> {
+    let software = mode == RendererMode::Software;
+    Ok(eframe::NativeOptions {
+        viewport: egui::ViewportBuilder::default()
+            .with_inner_size(Vec2::new(DEFAULT_WINDOW_WIDTH, DEFAULT_WINDOW_HEIGHT))
+            .with_min_inner_size(Vec2::new(MIN_WINDOW_WIDTH, MIN_WINDOW_HEIGHT))
+            .with_clamp_size_to_monitor_size(true)
+            .with_resizable(true)
+            .with_visible(true)
+            .with_decorations(false)
+            .with_transparent(!software)
+            .with_icon(load_app_icon()?)
+            .with_title(APP_DISPLAY_NAME),
+        persist_window: false,
+        hardware_acceleration: if software {
+            eframe::HardwareAcceleration::Off
+        } else {
+            eframe::HardwareAcceleration::Preferred
+        },
+        ..Default::default()
+    })
+}
+
 fn load_app_icon() -> io::Result<egui::IconData> {
     eframe::icon_data::from_png_bytes(APP_ICON_BYTES)
         .map_err(|error| io::Error::other(format!("failed to load app icon: {error}")))
@@ -105,10 +280,182 @@ fn parse_launch_request() -> LaunchRequest {
             continue;
         }
 
+        if arg == "--batch" {
+            if let Some(path) = args.next() {
+                request.batch = Some(PathBuf::from(path));
+            } else {
+                logging::error("missing path after --batch");
+            }
+            continue;
+        }
+
         if let Some(value) = arg.to_str().and_then(|arg| arg.strip_prefix("--cwd=")) {
             request.cwd = Some(PathBuf::from(value));
         }
+
+        if let Some(value) = arg.to_str().and_then(|arg| arg.strip_prefix("--batch=")) {
+            request.batch = Some(PathBuf::from(value));
+        }
     }
 
     request
 }
+
+#[cfg(test)]
+mod tests {
+    use std::cell::{Cell, RefCell};
+
+    use super::*;
+
+    #[derive(Default)]
+    struct RecordingSink(RefCell<Vec<(String, String)>>);
+
+    impl MessageSink for RecordingSink {
+        fn show_error(&self, title: &str, text: &str) {
+            self.0
+                .borrow_mut()
+                .push((title.to_owned(), text.to_owned()));
+        }
+    }
+
+    fn fallback(
+        start: RendererMode,
+        results: &[(RendererMode, Result<(), &str>)],
+        started: bool,
+    ) -> (RendererMode, Result<(), String>, Vec<RendererMode>) {
+        let attempts = RefCell::new(Vec::new());
+        let (mode, result) = run_with_renderer_fallback(
+            start,
+            |mode| {
+                attempts.borrow_mut().push(mode);
+                let (expected, result) = results[attempts.borrow().len() - 1];
+                assert_eq!(mode, expected);
+                result.map_err(io::Error::other)
+            },
+            || started,
+        );
+        (
+            mode,
+            result.map_err(|error| error.to_string()),
+            attempts.into_inner(),
+        )
+    }
+
+    #[test]
+    fn a_hardware_start_failure_retries_in_software() {
+        use RendererMode::*;
+        assert_eq!(
+            fallback(
+                Hardware,
+                &[(Hardware, Err("no GL")), (Software, Ok(()))],
+                false
+            ),
+            (Software, Ok(()), vec![Hardware, Software])
+        );
+        assert_eq!(
+            fallback(
+                Hardware,
+                &[(Hardware, Err("no GL")), (Software, Err("no GDI"))],
+                false
+            ),
+            (Software, Err("no GDI".to_owned()), vec![Hardware, Software])
+        );
+    }
+
+    #[test]
+    fn failures_after_a_frame_or_in_software_do_not_retry() {
+        use RendererMode::*;
+        assert_eq!(
+            fallback(Hardware, &[(Hardware, Err("lost context"))], true),
+            (Hardware, Err("lost context".to_owned()), vec![Hardware])
+        );
+        assert_eq!(
+            fallback(Software, &[(Software, Err("no GDI"))], false),
+            (Software, Err("no GDI".to_owned()), vec![Software])
+        );
+        assert_eq!(
+            fallback(Hardware, &[(Hardware, Ok(()))], false),
+            (Hardware, Ok(()), vec![Hardware])
+        );
+    }
+
+    #[test]
+    fn the_started_check_runs_only_after_a_failure() {
+        let checks = Cell::new(0);
+        let _ = run_with_renderer_fallback(
+            RendererMode::Hardware,
+            |_| Ok(()),
+            || {
+                checks.set(checks.get() + 1);
+                false
+            },
+        );
+        assert_eq!(checks.get(), 0);
+    }
+
+    #[test]
+    fn startup_message_points_at_the_log() {
+        let log_dir = Path::new("logs");
+        let message = startup_failure_message(
+            "could not open its window.",
+            "no suitable adapter\n  \ncaused by: GL 1.1\n",
+            &log_dir.join("codexagent.log"),
+        );
+        assert_eq!(
+            message,
+            format!(
+                "{} could not open its window.\n\nno suitable adapter\n\ncaused by: GL 1.1\
+                 \n\nThe full error is in the log:\n{}",
+                APP_DISPLAY_NAME,
+                log_dir.join("codexagent.log").display()
+            )
+        );
+        let long = "x".repeat(STARTUP_HEADLINE_MAX_CHARS + 10);
+        let message = startup_failure_message("failed.", &long, log_dir);
+        assert!(message.contains(&format!(
+            "{}\u{2026}",
+            "x".repeat(STARTUP_HEADLINE_MAX_CHARS)
+        )));
+    }
+
+    #[test]
+    fn startup_log_falls_back_to_the_log_dir() {
+        let log_dir = Path::new("logs");
+        let mut health = LogHealth {
+            file_logging: true,
+            last_error: None,
+            log_files: vec![PathBuf::from("logs/today.log")],
+        };
+        assert_eq!(
+            startup_log_path(&health, log_dir),
+            PathBuf::from("logs/today.log")
+        );
+        health.file_logging = false;
+        assert_eq!(startup_log_path(&health, log_dir), log_dir);
+        health.file_logging = true;
+        health.log_files.clear();
+        assert_eq!(startup_log_path(&health, log_dir), log_dir);
+    }
+
+    #[test]
+    fn a_startup_failure_shows_one_message_naming_the_log() {
+        let sink = RecordingSink::default();
+        let error = io::Error::other("codex files are read-only\ncaused by: access denied");
+        report_startup_failure(&sink, "could not prepare the codex files it needs.", &error);
+
+        let shown = sink.0.into_inner();
+        assert_eq!(shown.len(), 1);
+        let (title, text) = &shown[0];
+        assert_eq!(title, APP_DISPLAY_NAME);
+        let log_file = startup_log_path(&logging::health(), &paths::get().log_dir);
+        assert_eq!(
+            *text,
+            startup_failure_message(
+                "could not prepare the codex files it needs.",
+                &error.to_string(),
+                &log_file
+            )
+        );
+        assert!(text.contains("codex files are read-only\n\ncaused by: access denied"));
+    }
+}
@@ -1,26 +1,37 @@
+#[cfg(windows)]
 use std::ffi::c_void;
+#[cfg(windows)]
 use std::mem;
+#[cfg(windows)]
 use std::ptr;
+#[cfg(windows)]
 use std::thread;
+#[cfg(windows)]
 use std::time::Duration;
 
+use windows_sys::Win32::Foundation::HWND;
+#[cfg(windows)]
 use windows_sys::Win32::System::LibraryLoader::GetModuleHandleW;
+#[cfg(windows)]
 use windows_sys::Win32::UI::Shell::{
     NIF_ICON, NIF_INFO, NIF_TIP, NIIF_INFO, NIIF_NOSOUND, NIM_ADD, NIM_DELETE, NOTIFYICONDATAW,
     Shell_NotifyIconW,
 };
+#[cfg(windows)]
 use windows_sys::Win32::UI::WindowsAndMessaging::{
     GetForegroundWindow, IDI_APPLICATION, LoadIconW,
 };
 
+#[cfg(windows)]
 use crate::logging;
 
-type HWND = *mut c_void;
-
+#[cfg(windows)]
 const APP_ICON_RESOURCE_ID: u16 = 1;
+#[cfg(windows)]
 const NOTIFY_ICON_ID: u32 = 1;
 
-pub(crate) fn try_capture_hwnd(hwnd: &mut HWND) {
+#[cfg(windows)]
+pub fn try_capture_hwnd(hwnd: &mut HWND) {
     if !(*hwnd).is_null() {
         return;
     }
@@ -31,7 +42,8 @@ pub(crate) fn try_capture_hwnd(hwnd: &mut HWND) {
     }
 }
 
-pub(crate) fn prompt_completed(hwnd: HWND) {
+#[cfg(windows)]
+pub fn prompt_completed(hwnd: HWND) {
     if hwnd.is_null() {
         return;
     }
@@ -42,13 +54,24 @@ pub(crate) fn prompt_completed(hwnd: HWND) {
     show_balloon(hwnd);
 }
 
-pub(crate) fn cleanup(hwnd: HWND) {
+#[cfg(windows)]
+pub fn cleanup(hwnd: HWND) {
     if hwnd.is_null() {
         return;
     }
     remove_tray_icon(hwnd);
 }
 
+#[cfg(not(windows))]
+pub fn try_capture_hwnd(_hwnd: &mut HWND) {}
+
+#[cfg(not(windows))]
+pub fn prompt_completed(_hwnd: HWND) {}
+
+#[cfg(not(windows))]
+pub fn cleanup(_hwnd: HWND) {}
+
+#[cfg(windows)]
 fn show_balloon(hwnd: HWND) {
     let icon = load_app_icon();
     if icon.is_null() {
@@ -79,6 +102,7 @@ fn show_balloon(hwnd: HWND) {
     });
 }
 
+#[cfg(windows)]
 fn load_app_icon() -> *mut c_void {
     let module = unsafe { GetModuleHandleW(ptr::null()) };
     let resource = APP_ICON_RESOURCE_ID as usize as *const u16;
@@ -90,6 +114,7 @@ fn load_app_icon() -> *mut c_void {
     }
 }
 
+#[cfg(windows)]
 fn remove_tray_icon(hwnd: HWND) {
     let mut data: NOTIFYICONDATAW = unsafe { mem::zeroed() };
     data.cbSize = mem::size_of::<NOTIFYICONDATAW>() as u32;
@@ -102,10 +127,12 @@ fn remove_tray_icon(hwnd: HWND) {
     }
 }
 
+#[cfg(windows)]
 fn should_show_notification(hwnd: HWND, foreground: HWND) -> bool {
     hwnd != foreground
 }
 
+#[cfg(windows)]
 fn encode_wide_into(s: &str, dst: &mut [u16]) {
     if dst.is_empty() {
         return;
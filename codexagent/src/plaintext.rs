@@ -0,0 +1,339 @@
+const CODE_INDENT: &str = "    ";
+const BULLET: &str = "\u{2022} ";
+const RULE: &str = "----------";
+const FENCES: [&str; 2] = ["```", "~~~"];
+
+pub fn markdown_to_plain(markdown: &str) -> String {
+    let mut plain = String::with_capacity(markdown.len());
+    let mut fence: Option<&str> = None;
+    for line in markdown.lines() {
+        let trimmed = line.trim_start();
+        if let Some(open) = fence {
+            if trimmed.starts_with(open) && trimmed[open.len()..].trim().is_empty() {
+                fence = None;
+            } else {
+                plain.push_str(CODE_INDENT);
+                plain.push_str(line.trim_end());
+                plain.push('\n');
+            }
+            continue;
+        }
+        if let Some(open) = FENCES.into_iter().find(|open| trimmed.starts_with(open)) {
+            fence = Some(open);
+            continue;
+        }
+        if let Some(block) = plain_block_line(line) {
+            plain.push_str(&block);
+            plain.push('\n');
+        }
+    }
+    let trimmed_len = plain.trim_end().len();
+    plain.truncate(trimmed_len);
+    plain
+}
+
+fn plain_block_line(line: &str) -> Option<String> {
+    let trimmed = line.trim_start();
+    let indent = &line[..line.len() - trimmed.len()];
+    if let Some((level, title)) = heading(trimmed) {
+        let title = plain_inline(title);
+        return Some(match level {
+            1 => format!("{}\n{}", title, "=".repeat(title.chars().count().max(3))),
+            2 => format!("{}\n{}", title, "-".repeat(title.chars().count().max(3))),
+            _ => title.to_uppercase(),
+        });
+    }
+    if is_rule(trimmed) {
+        return Some(RULE.to_owned());
+    }
+    if is_table_separator(trimmed) {
+        return None;
+    }
+    if let Some(item) = ["- ", "* ", "+ "]
+        .into_iter()
+        .find_map(|marker| trimmed.strip_prefix(marker))
+    {
+        return Some(format!("{}{}{}", indent, BULLET, plain_inline(item)));
+    }
+    if let Some(quote) = trimmed.strip_prefix('>') {
+        return Some(format!(
+            "{}> {}",
+            indent,
+            plain_inline(quote.strip_prefix(' ').unwrap_or(quote))
+        ));
+    }
+    let digits = trimmed.bytes().take_while(u8::is_ascii_digit).count();
+    if digits > 0
+        && let Some(item) = trimmed[digits..]
+            .strip_prefix(". ")
+            .or_else(|| trimmed[digits..].strip_prefix(") "))
+    {
+        return Some(format!(
+            "{}{}. {}",
+            indent,
+            &trimmed[..digits],
+            plain_inline(item)
+        ));
+    }
+    Some(format!("{}{}", indent, plain_inline(trimmed.trim_end())))
+}
+
+fn heading(line: &str) -> Option<(usize, &str)> {
+    let level = line.bytes().take_while(|&byte| byte == b'#').count();
+    if !(1..=6).contains(&level) {
+        return None;
+    }
+    let rest = &line[level..];
+    if !rest.is_empty() && !rest.starts_with([' ', '\t']) {
+        return None;
+    }
+    Some((level, rest.trim().trim_end_matches('#').trim_end()))
+}
+
+fn is_rule(line: &str) -> bool {
+    let compact: String = line.chars().filter(|c| !c.is_whitespace()).collect();
+    compact.len() >= 3
+        && ['-', '*', '_']
+            .into_iter()
+            .any(|marker| compact.chars().all(|c| c == marker))
+}
+
+fn is_table_separator(line: &str) -> bool {
+    line.contains('-')
+        && line.contains('|')
+        && line
+            .chars()
+            .all(|c| matches!(c, '|' | '-' | ':' | ' ' | '\t'))
+}
+
+fn plain_inline(text: &str) -> String {
+    let chars: Vec<char> = text.chars().collect();
+    let mut plain = String::with_capacity(text.len());
+    let mut index = 0;
+    while index < chars.len() {
+        let c = chars[index];
+        if c == '\\' && chars.get(index + 1).is_some_and(char::is_ascii_punctuation) {
+            plain.push(chars[index + 1]);
+            index += 2;
+            continue;
+        }
+        if c == '`'
+            && let Some((code, next)) = code_span(&chars, index)
+        {
+            plain.push_str(&code);
+            index = next;
+            continue;
+        }
+        let image = c == '!' && chars.get(index + 1) == Some(&'[');
+        if (c == '[' || image)
+            && let Some((label, url, next)) = link(&chars, index + usize::from(image))
+        {
+            let label = plain_inline(&label);
+            if label.is_empty() || label == url {
+                plain.push_str(&url);
+            } else {
+                plain.push_str(&format!("{} ({})", label, url));
+            }
+            index = next;
+            continue;
+        }
+        if matches!(c, '*' | '_' | '~')
+            && let Some((inner, next)) = emphasis(&chars, index)
+        {
+            plain.push_str(&plain_inline(&inner));
+            index = next;
+            continue;
+        }
+        plain.push(c);
+        index += 1;
+    }
+    plain
+}
+
+fn run_length(chars: &[char], index: usize, c: char) -> usize {
+    chars[index..].iter().take_while(|&&next| next == c).count()
+}
+
+fn code_span(chars: &[char], start: usize) -> Option<(String, usize)> {
+    let ticks = run_length(chars, start, '`');
+    let mut index = start + ticks;
+    while index < chars.len() {
+        if chars[index] == '`' {
+            let run = run_length(chars, index, '`');
+            if run == ticks {
+                let code: String = chars[start + ticks..index].iter().collect();
+                let code = match code
+                    .strip_prefix(' ')
+                    .and_then(|code| code.strip_suffix(' '))
+                {
+                    Some(inner) if !inner.trim().is_empty() => inner.to_owned(),
+                    _ => code,
+                };
+                return Some((code, index + run));
+            }
+            index += run;
+        } else {
+            index += 1;
+        }
+    }
+    None
+}
+
+fn link(chars: &[char], open: usize) -> Option<(String, String, usize)> {
+    let mut depth = 0;
+    let mut close = None;
+    for (offset, &c) in chars[open..].iter().enumerate() {
+        match c {
+            '[' => depth += 1,
+            ']' => {
+                depth -= 1;
+                if depth == 0 {
+                    close = Some(open + offset);
+                    break;
+                }
+            }
+            _ => {}
+        }
+    }
+    let close = close?;
+    if chars.get(close + 1) != Some(&'(') {
+        return None;
+    }
+    let url_start = close + 2;
+    let url_end = url_start + chars[url_start..].iter().position(|&c| c == ')')?;
+    let target: String = chars[url_start..url_end].iter().collect();
+    let url = target
+        .split_whitespace()
+        .next()
+        .unwrap_or_default()
+        .trim_start_matches('<')
+        .trim_end_matches('>')
+        .to_owned();
+    if url.is_empty() {
+        return None;
+    }
+    let label = chars[open + 1..close].iter().collect();
+    Some((label, url, url_end + 1))
+}
+
+fn emphasis(chars: &[char], start: usize) -> Option<(String, usize)> {
+    let marker = chars[start];
+    let run = run_length(chars, start, marker);
+    if run > 3 || marker == '~' && run != 2 {
+        return None;
+    }
+    let word = |c: &char| c.is_alphanumeric();
+    if marker == '_'
+        && start
+            .checked_sub(1)
+            .and_then(|i| chars.get(i))
+            .is_some_and(word)
+    {
+        return None;
+    }
+    let first = chars.get(start + run)?;
+    if first.is_whitespace() {
+        return None;
+    }
+    let mut index = start + run + 1;
+    while index < chars.len() {
+        if chars[index] == marker {
+            let closing = run_length(chars, index, marker);
+            let after = chars.get(index + closing);
+            if closing == run
+                && !chars[index - 1].is_whitespace()
+                && !(marker == '_' && after.is_some_and(word))
+            {
+                let inner = chars[start + run..index].iter().collect();
+                return Some((inner, index + closing));
+            }
+            index += closing;
+        } else {
+            index += 1;
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MARKDOWN: &str = "# Parser notes
+
+Use **bold** and _italic_ text with `code` and [docs](https://example.com/docs \"Title\").
+
+## Steps
+- Read the *tokens*
+  * nested item
+1. Build the ~~old~~ tree
+2) Emit
+
+### caveats
+> Watch for snake_case_names and `a_b`.
+
+| a | b |
+|---|---|
+| 1 | 2 |
+
+---
+```rust
+fn main() {
+    run();
+}
+```
+![diagram](img.png) and <auto> and [https://x.y](https://x.y)
+";
+
+    const PLAIN: &str = "Parser notes
+============
+
+Use bold and italic text with code and docs (https://example.com/docs).
+
+Steps
+-----
+\u{2022} Read the tokens
+  \u{2022} nested item
+1. Build the old tree
+2. Emit
+
+CAVEATS
+> Watch for snake_case_names and a_b.
+
+| a | b |
+| 1 | 2 |
+
+----------
+    fn main() {
+        run();
+    }
+diagram (img.png) and <auto> and https://x.y";
+
+    #[test]
+    fn markdown_document_matches_its_golden_text() {
+        assert_eq!(markdown_to_plain(MARKDOWN), PLAIN);
+    }
+
+    #[test]
+    fn plain_text_is_left_alone() {
+        let text = "Nothing to convert here.\nSecond line: 3 < 4, (a) and [b].";
+        assert_eq!(markdown_to_plain(text), text);
+    }
+
+    #[test]
+    fn escapes_and_unmatched_markers_stay_literal() {
+        assert_eq!(
+            markdown_to_plain(r"\*not bold\* and a * lone star and `open tick"),
+            "*not bold* and a * lone star and `open tick"
+        );
+        assert_eq!(markdown_to_plain("#hashtag"), "#hashtag");
+    }
+
+    #[test]
+    fn unclosed_fences_indent_the_rest() {
+        assert_eq!(
+            markdown_to_plain("Run:\n~~~sh\ncargo test  \n# not a heading"),
+            "Run:\n    cargo test\n    # not a heading"
+        );
+    }
+}
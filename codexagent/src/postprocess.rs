@@ -0,0 +1,200 @@
+const MAX_BLANK_LINES: usize = 2;
+const PATH_SEPARATORS: [char; 2] = ['\\', '/'];
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum OutputProcessor {
+    WorkingDirectory,
+    TrailingWhitespace,
+    BlankLines,
+}
+
+pub const OUTPUT_PROCESSORS: [OutputProcessor; 3] = [
+    OutputProcessor::WorkingDirectory,
+    OutputProcessor::TrailingWhitespace,
+    OutputProcessor::BlankLines,
+];
+
+impl OutputProcessor {
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::WorkingDirectory => "Working directory",
+            Self::TrailingWhitespace => "Trailing whitespace",
+            Self::BlankLines => "Blank lines",
+        }
+    }
+
+    pub fn description(self) -> &'static str {
+        match self {
+            Self::WorkingDirectory => "Replace the working directory with ./",
+            Self::TrailingWhitespace => "Strip spaces at line ends",
+            Self::BlankLines => "Collapse runs of blank lines",
+        }
+    }
+
+    fn process(self, text: &mut String, cwd: &str) {
+        match self {
+            Self::WorkingDirectory => relativize_working_directory(text, cwd),
+            Self::TrailingWhitespace => strip_trailing_whitespace(text),
+            Self::BlankLines => collapse_blank_lines(text),
+        }
+    }
+}
+
+pub fn process_output(mut text: String, enabled: &[OutputProcessor], cwd: &str) -> String {
+    for processor in OUTPUT_PROCESSORS {
+        if enabled.contains(&processor) {
+            processor.process(&mut text, cwd);
+        }
+    }
+    text
+}
+
+fn is_path_char(c: char) -> bool {
+    c.is_alphanumeric() || matches!(c, '_' | '-' | '.' | '~' | '$')
+}
+
+fn relativize_working_directory(text: &mut String, cwd: &str) {
+    let cwd = cwd.trim().trim_end_matches(PATH_SEPARATORS);
+    if !cwd.contains(PATH_SEPARATORS) {
+        return;
+    }
+    let mut prefixes = vec![cwd.to_ascii_lowercase()];
+    let slashed = prefixes[0].replace('\\', "/");
+    if slashed != prefixes[0] {
+        prefixes.push(slashed);
+    }
+    let lower = text.to_ascii_lowercase();
+    let mut rewritten = String::with_capacity(text.len());
+    let mut copied = 0;
+    let mut index = 0;
+    while index < lower.len() {
+        let matched = prefixes.iter().find(|prefix| {
+            lower[index..].starts_with(prefix.as_str())
+                && lower[index + prefix.len()..]
+                    .chars()
+                    .next()
+                    .is_none_or(|next| PATH_SEPARATORS.contains(&next) || !is_path_char(next))
+                && lower[..index].chars().next_back().is_none_or(|previous| {
+                    !is_path_char(previous) && !PATH_SEPARATORS.contains(&previous)
+                })
+        });
+        match matched {
+            Some(prefix) => {
+                rewritten.push_str(&text[copied..index]);
+                rewritten.push('.');
+                index += prefix.len();
+                copied = index;
+            }
+            None => {
+                index += lower[index..].chars().next().map_or(1, char::len_utf8);
+            }
+        }
+    }
+    if copied > 0 {
+        rewritten.push_str(&text[copied..]);
+        *text = rewritten;
+    }
+}
+
+fn strip_trailing_whitespace(text: &mut String) {
+    *text = text
+        .split('\n')
+        .map(str::trim_end)
+        .collect::<Vec<_>>()
+        .join("\n");
+}
+
+fn collapse_blank_lines(text: &mut String) {
+    let mut blank_run = 0;
+    *text = text
+        .split('\n')
+        .filter(|line| {
+            if line.trim().is_empty() {
+                blank_run += 1;
+                blank_run <= MAX_BLANK_LINES
+            } else {
+                blank_run = 0;
+                true
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CWD: &str = r"C:\Users\dev\repo";
+
+    fn relativized(text: &str, cwd: &str) -> String {
+        process_output(text.to_owned(), &[OutputProcessor::WorkingDirectory], cwd)
+    }
+
+    #[test]
+    fn working_directory_matches_either_slash_style() {
+        assert_eq!(
+            relativized(
+                r"Edited C:\Users\dev\repo\src\main.rs and c:/users/dev/repo/Cargo.toml",
+                CWD
+            ),
+            r"Edited .\src\main.rs and ./Cargo.toml"
+        );
+        assert_eq!(
+            relativized("see /home/dev/repo/src/lib.rs", "/home/dev/repo/"),
+            "see ./src/lib.rs"
+        );
+        assert_eq!(relativized(r"cd C:\Users\dev\repo", CWD), "cd .");
+    }
+
+    #[test]
+    fn working_directory_only_matches_on_path_boundaries() {
+        for text in [
+            r"C:\Users\dev\repository\src",
+            r"D:\backup\C:\Users\dev\repo",
+            r"xC:\Users\dev\repo\a.rs",
+        ] {
+            assert_eq!(relativized(text, CWD), text);
+        }
+        assert_eq!(relativized("a repo file", "repo"), "a repo file");
+    }
+
+    #[test]
+    fn trailing_whitespace_is_stripped_per_line() {
+        assert_eq!(
+            process_output(
+                "fn main() {  \n\trun();\t\n}   ".to_owned(),
+                &[OutputProcessor::TrailingWhitespace],
+                CWD
+            ),
+            "fn main() {\n\trun();\n}"
+        );
+    }
+
+    #[test]
+    fn long_blank_runs_collapse_to_two() {
+        assert_eq!(
+            process_output(
+                "one\n\n\n\n\ntwo\n\nthree\n \n\t\n  \nfour".to_owned(),
+                &[OutputProcessor::BlankLines],
+                CWD
+            ),
+            "one\n\n\ntwo\n\nthree\n \n\t\nfour"
+        );
+    }
+
+    #[test]
+    fn only_enabled_processors_run_in_a_fixed_order() {
+        let text = "Open C:\\Users\\dev\\repo\\a.rs   \n\n\n\n\ndone  ";
+        assert_eq!(process_output(text.to_owned(), &[], CWD), text);
+        let forward = process_output(text.to_owned(), &OUTPUT_PROCESSORS, CWD);
+        let mut reversed = OUTPUT_PROCESSORS;
+        reversed.reverse();
+        assert_eq!(process_output(text.to_owned(), &reversed, CWD), forward);
+        assert_eq!(forward, "Open .\\a.rs\n\n\ndone");
+        assert_eq!(
+            process_output(text.to_owned(), &[OutputProcessor::TrailingWhitespace], CWD),
+            "Open C:\\Users\\dev\\repo\\a.rs\n\n\n\n\ndone"
+        );
+    }
+}
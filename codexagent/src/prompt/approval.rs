@@ -0,0 +1,208 @@
+use serde_json::Value;
+
+const APPROVAL_EVENT_SUFFIX: &str = "approval_request";
+const MAX_APPROVAL_DETAIL_CHARS: usize = 2000;
+const MAX_APPROVAL_PATHS: usize = 12;
+const UNKNOWN_APPROVAL_DETAIL: &str = "(codex did not say what it wants to run)";
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ApprovalKind {
+    Command,
+    Patch,
+}
+
+impl ApprovalKind {
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Command => "Run command",
+            Self::Patch => "Apply patch",
+        }
+    }
+}
+
+/// A command exactly as codex asked to run it, so approving it runs that
+/// command and nothing else.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum CommandLine {
+    Argv(Vec<String>),
+    Shell(String),
+}
+
+#[derive(Clone, Debug)]
+pub struct ApprovalRequest {
+    pub kind: ApprovalKind,
+    pub detail: String,
+    pub reason: Option<String>,
+    /// Only set for commands; patches cannot be approved from the app.
+    pub command: Option<CommandLine>,
+}
+
+impl ApprovalRequest {
+    pub fn can_approve(&self) -> bool {
+        self.command.is_some()
+    }
+}
+
+fn event_kind(value: &Value) -> Option<&str> {
+    value
+        .get("type")
+        .and_then(Value::as_str)
+        .filter(|kind| kind.ends_with(APPROVAL_EVENT_SUFFIX))
+}
+
+fn command_text(command: Option<&Value>) -> Option<String> {
+    match command? {
+        Value::String(command) => Some(command.clone()),
+        Value::Array(parts) => Some(
+            parts
+                .iter()
+                .filter_map(Value::as_str)
+                .collect::<Vec<_>>()
+                .join(" "),
+        ),
+        _ => None,
+    }
+}
+
+fn command_line(command: Option<&Value>) -> Option<CommandLine> {
+    let line = match command? {
+        Value::String(command) => CommandLine::Shell(command.clone()),
+        Value::Array(parts) => CommandLine::Argv(
+            parts
+                .iter()
+                .map(|part| part.as_str().map(ToOwned::to_owned))
+                .collect::<Option<Vec<_>>>()?,
+        ),
+        _ => return None,
+    };
+    let empty = match &line {
+        CommandLine::Argv(parts) => parts.first().is_none_or(|program| program.is_empty()),
+        CommandLine::Shell(command) => command.trim().is_empty(),
+    };
+    (!empty).then_some(line)
+}
+
+fn patch_paths(changes: Option<&Value>) -> Option<String> {
+    let paths: Vec<&str> = match changes? {
+        Value::Object(changes) => changes.keys().map(String::as_str).collect(),
+        Value::Array(changes) => changes
+            .iter()
+            .filter_map(|change| change.get("path").and_then(Value::as_str))
+            .collect(),
+        _ => return None,
+    };
+    let mut detail = paths
+        .iter()
+        .take(MAX_APPROVAL_PATHS)
+        .copied()
+        .collect::<Vec<_>>()
+        .join("\n");
+    if paths.len() > MAX_APPROVAL_PATHS {
+        detail.push_str(&format!("\n+{} more", paths.len() - MAX_APPROVAL_PATHS));
+    }
+    Some(detail)
+}
+
+pub(super) fn approval_request(event: &Value) -> Option<ApprovalRequest> {
+    let (payload, kind) = [Some(event), event.get("msg"), event.get("item")]
+        .into_iter()
+        .flatten()
+        .find_map(|value| event_kind(value).map(|kind| (value, kind)))?;
+    let kind = if kind.contains("patch") {
+        ApprovalKind::Patch
+    } else {
+        ApprovalKind::Command
+    };
+    let detail = match kind {
+        ApprovalKind::Command => command_text(payload.get("command")),
+        ApprovalKind::Patch => patch_paths(payload.get("changes")),
+    }
+    .filter(|detail| !detail.trim().is_empty())
+    .unwrap_or_else(|| UNKNOWN_APPROVAL_DETAIL.to_owned());
+    let reason = payload
+        .get("reason")
+        .and_then(Value::as_str)
+        .map(str::trim)
+        .filter(|reason| !reason.is_empty())
+        .map(ToOwned::to_owned);
+    let command = match kind {
+        ApprovalKind::Command => command_line(payload.get("command")),
+        ApprovalKind::Patch => None,
+    };
+    Some(ApprovalRequest {
+        kind,
+        detail: detail.chars().take(MAX_APPROVAL_DETAIL_CHARS).collect(),
+        reason,
+        command,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn reads_command_approval_requests() {
+        let request = approval_request(&json!({
+            "msg": {
+                "type": "exec_approval_request",
+                "command": ["cargo", "test"],
+                "reason": " needs network "
+            }
+        }))
+        .unwrap();
+        assert_eq!(request.kind, ApprovalKind::Command);
+        assert_eq!(request.detail, "cargo test");
+        assert_eq!(request.reason.as_deref(), Some("needs network"));
+        assert_eq!(
+            request.command,
+            Some(CommandLine::Argv(vec![
+                "cargo".to_owned(),
+                "test".to_owned()
+            ]))
+        );
+    }
+
+    #[test]
+    fn keeps_shell_commands_whole() {
+        let long = format!("echo {}", "x".repeat(MAX_APPROVAL_DETAIL_CHARS));
+        let request = approval_request(&json!({
+            "type": "exec_approval_request",
+            "command": long
+        }))
+        .unwrap();
+        assert_eq!(request.detail.chars().count(), MAX_APPROVAL_DETAIL_CHARS);
+        assert_eq!(request.command, Some(CommandLine::Shell(long)));
+    }
+
+    #[test]
+    fn commands_codex_did_not_spell_out_cannot_be_approved() {
+        for command in [json!(null), json!(""), json!(["bash", 1]), json!([])] {
+            let request = approval_request(&json!({
+                "type": "exec_approval_request",
+                "command": command
+            }))
+            .unwrap();
+            assert!(!request.can_approve(), "{:?}", request.command);
+        }
+    }
+
+    #[test]
+    fn reads_patch_approval_requests() {
+        let request = approval_request(&json!({
+            "type": "apply_patch_approval_request",
+            "changes": {"src/main.rs": {}, "README.md": {}}
+        }))
+        .unwrap();
+        assert_eq!(request.kind, ApprovalKind::Patch);
+        assert_eq!(request.detail, "README.md\nsrc/main.rs");
+        assert!(!request.can_approve());
+    }
+
+    #[test]
+    fn ignores_other_events() {
+        assert!(approval_request(&json!({"type": "item.completed"})).is_none());
+    }
+}
@@ -0,0 +1,245 @@
+use std::io::{self, Read};
+use std::process::{Command, Stdio};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use crate::logging;
+use crate::runtime::{BackgroundCommand, record_running_process, shell_command};
+
+use super::approval::CommandLine;
+use super::buffers::sanitize_external_text;
+use super::codex::runs_commands_natively;
+use super::state::{
+    PromptProcessExt, PromptProcessGuard, PromptStreamState, RunningPrompt, RunningPromptGuard,
+    RunningPrompts,
+};
+
+const MAX_APPROVED_OUTPUT_BYTES: usize = 16 * 1024;
+const READ_CHUNK_BYTES: usize = 8 * 1024;
+
+pub(super) struct ApprovedCommandRun {
+    pub(super) code: i32,
+    pub(super) stdout: String,
+    pub(super) stderr: String,
+}
+
+fn build_command(line: &CommandLine) -> io::Result<Command> {
+    match line {
+        CommandLine::Argv(parts) => {
+            let (program, args) = parts
+                .split_first()
+                .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "Empty command"))?;
+            let mut command = Command::new(program);
+            command.args(args);
+            Ok(command)
+        }
+        CommandLine::Shell(command_line) => Ok(shell_command(command_line)),
+    }
+}
+
+/// Runs the one command the user approved, in place of codex, so the resumed
+/// session keeps its own approval policy for everything after it.
+pub(super) fn run_approved_command(
+    prompt_id: u64,
+    line: &CommandLine,
+    running_prompt: &Arc<Mutex<RunningPrompts>>,
+    shared_stream: &Mutex<PromptStreamState>,
+) -> io::Result<ApprovedCommandRun> {
+    if !runs_commands_natively() {
+        return Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "Approved commands cannot be run while codex runs under WSL",
+        ));
+    }
+    logging::trace(format!("running approved command: {:?}", line));
+    let child = build_command(line)?
+        .hide_window()
+        .own_process_group()
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+    let pid = child.id();
+    let _process_record = record_running_process(pid);
+    let mut process = PromptProcessGuard {
+        child: Some(child),
+        stderr_handle: None,
+    };
+    running_prompt
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .register(RunningPrompt {
+            id: prompt_id,
+            pid,
+            session_id: None,
+        });
+    let _running_prompt_guard = RunningPromptGuard {
+        prompt_id,
+        running_prompt: Arc::clone(running_prompt),
+    };
+    if shared_stream
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .cancelled(prompt_id)
+    {
+        return Err(io::Error::new(
+            io::ErrorKind::Interrupted,
+            "Prompt cancelled before the approved command started",
+        ));
+    }
+
+    let child = process.child_mut("opening approved command output")?;
+    let stdout = child.stdout.take();
+    let stderr = child.stderr.take();
+    let stderr_handle =
+        stderr.map(|stderr| thread::spawn(move || read_tail(stderr, MAX_APPROVED_OUTPUT_BYTES)));
+    let stdout = match stdout {
+        Some(stdout) => read_tail(stdout, MAX_APPROVED_OUTPUT_BYTES)?,
+        None => String::new(),
+    };
+    let stderr = match stderr_handle.map(thread::JoinHandle::join) {
+        Some(Ok(stderr)) => stderr?,
+        Some(Err(_)) => {
+            logging::error("approved command stderr reader thread panicked");
+            String::new()
+        }
+        None => String::new(),
+    };
+    let status = process.child_mut("waiting for approved command")?.wait()?;
+    let code = status.code().unwrap_or(-1);
+    logging::trace(format!("approved command exited with {}", code));
+    if shared_stream
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .cancelled(prompt_id)
+    {
+        return Err(io::Error::new(
+            io::ErrorKind::Interrupted,
+            "Prompt cancelled while the approved command ran",
+        ));
+    }
+    Ok(ApprovedCommandRun {
+        code,
+        stdout,
+        stderr,
+    })
+}
+
+/// Keeps the last `max` bytes of a stream; the end of a command's output is
+/// where its result usually is.
+fn read_tail<R: Read>(mut reader: R, max: usize) -> io::Result<String> {
+    let mut kept = Vec::new();
+    let mut dropped = false;
+    let mut chunk = [0; READ_CHUNK_BYTES];
+    loop {
+        let read = match reader.read(&mut chunk) {
+            Ok(0) => break,
+            Ok(read) => read,
+            Err(error) if error.kind() == io::ErrorKind::Interrupted => continue,
+            Err(error) => return Err(error),
+        };
+        kept.extend_from_slice(&chunk[..read]);
+        if kept.len() > max {
+            kept.drain(..kept.len() - max);
+            dropped = true;
+        }
+    }
+    let text = String::from_utf8_lossy(&kept);
+    let text = sanitize_external_text(&text);
+    let text = text.trim_end();
+    Ok(if dropped {
+        format!(
+            "[earlier output omitted]\n{}",
+            text.trim_start_matches('\u{fffd}')
+        )
+    } else {
+        text.to_owned()
+    })
+}
+
+pub(super) fn approved_command_prompt(prompt: &str, run: &ApprovedCommandRun) -> String {
+    let mut text = format!("{}\n\nExit code: {}", prompt, run.code);
+    for (label, output) in [("Output", &run.stdout), ("Errors", &run.stderr)] {
+        if !output.is_empty() {
+            text.push_str(&format!("\n{}:\n```\n{}\n```", label, output));
+        }
+    }
+    if run.stdout.is_empty() && run.stderr.is_empty() {
+        text.push_str("\nThe command printed nothing.");
+    }
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run(code: i32, stdout: &str, stderr: &str) -> ApprovedCommandRun {
+        ApprovedCommandRun {
+            code,
+            stdout: stdout.to_owned(),
+            stderr: stderr.to_owned(),
+        }
+    }
+
+    #[test]
+    fn read_tail_keeps_short_output_whole() {
+        assert_eq!(read_tail(&b"ok\n"[..], 16).unwrap(), "ok");
+    }
+
+    #[test]
+    fn read_tail_keeps_only_the_end_of_long_output() {
+        let output = format!("{}end", "x".repeat(3 * READ_CHUNK_BYTES));
+        let tail = read_tail(output.as_bytes(), 8).unwrap();
+        assert_eq!(tail, "[earlier output omitted]\nxxxxxend");
+    }
+
+    #[test]
+    fn prompt_reports_code_and_both_streams() {
+        assert_eq!(
+            approved_command_prompt("Approved.", &run(1, "built", "warning")),
+            "Approved.\n\nExit code: 1\nOutput:\n```\nbuilt\n```\nErrors:\n```\nwarning\n```"
+        );
+    }
+
+    #[test]
+    fn prompt_says_when_the_command_printed_nothing() {
+        assert_eq!(
+            approved_command_prompt("Approved.", &run(0, "", "")),
+            "Approved.\n\nExit code: 0\nThe command printed nothing."
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn runs_exactly_the_approved_argv() {
+        let running = Arc::new(Mutex::new(RunningPrompts::default()));
+        let stream = Mutex::new(PromptStreamState::default());
+        stream.lock().unwrap().start(7);
+        let line = CommandLine::Argv(vec![
+            "sh".to_owned(),
+            "-c".to_owned(),
+            "echo \"$0\"; echo oops >&2; exit 3".to_owned(),
+            "a b; echo not run".to_owned(),
+        ]);
+        let run = run_approved_command(7, &line, &running, &stream).unwrap();
+        assert_eq!(run.code, 3);
+        assert_eq!(run.stdout, "a b; echo not run");
+        assert_eq!(run.stderr, "oops");
+        assert!(running.lock().unwrap().get(7).is_none());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn cancelled_prompt_does_not_report_a_result() {
+        let running = Arc::new(Mutex::new(RunningPrompts::default()));
+        let stream = Mutex::new(PromptStreamState::default());
+        stream.lock().unwrap().start(8);
+        stream.lock().unwrap().mark_cancelled(8);
+        let line = CommandLine::Shell("true".to_owned());
+        let error = run_approved_command(8, &line, &running, &stream)
+            .err()
+            .unwrap();
+        assert_eq!(error.kind(), io::ErrorKind::Interrupted);
+    }
+}
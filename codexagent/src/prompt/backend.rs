@@ -0,0 +1,57 @@
+use std::io;
+use std::sync::{Arc, Mutex, atomic::AtomicBool, mpsc};
+
+use crate::config::project::ProjectConfig;
+use crate::events::{AppEvent, RepaintHandle};
+
+use super::approval::ApprovalRequest;
+use super::execution::{PromptOutput, kill_prompt_process, prompt_codex};
+use super::inspector::EventLog;
+use super::state::{PromptStreamState, RunningPrompts};
+
+pub struct PromptRequest {
+    pub id: u64,
+    pub prompt: String,
+    pub session_id: Option<String>,
+    pub project: Option<ProjectConfig>,
+    pub approved: Option<ApprovalRequest>,
+}
+
+pub struct PromptChannels {
+    pub running_prompt: Arc<Mutex<RunningPrompts>>,
+    pub shared_stream: Arc<Mutex<PromptStreamState>>,
+    pub stream_notification_pending: Arc<AtomicBool>,
+    pub event_log: Arc<Mutex<EventLog>>,
+    pub tx: mpsc::Sender<AppEvent>,
+    pub repaint: RepaintHandle,
+}
+
+pub trait PromptBackend: Send + Sync {
+    fn run(&self, request: PromptRequest, channels: &PromptChannels) -> io::Result<PromptOutput>;
+
+    fn cancel(&self, pid: u32) -> io::Result<()>;
+}
+
+pub struct CodexBackend;
+
+impl PromptBackend for CodexBackend {
+    fn run(&self, request: PromptRequest, channels: &PromptChannels) -> io::Result<PromptOutput> {
+        prompt_codex(
+            request.id,
+            request.prompt,
+            request.session_id,
+            request.project,
+            request.approved,
+            Arc::clone(&channels.running_prompt),
+            Arc::clone(&channels.shared_stream),
+            Arc::clone(&channels.stream_notification_pending),
+            Arc::clone(&channels.event_log),
+            &channels.tx,
+            &channels.repaint,
+        )
+    }
+
+    fn cancel(&self, pid: u32) -> io::Result<()> {
+        kill_prompt_process(pid)
+    }
+}
@@ -0,0 +1,208 @@
+use std::collections::HashSet;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, mpsc};
+use std::thread;
+use std::time::Duration;
+
+use serde_json::Value;
+
+use crate::config::PromptBudget;
+use crate::logging;
+
+use super::execution::kill_prompt_process;
+
+const TOOL_ITEM_TYPES: [&str; 4] = [
+    "command_execution",
+    "mcp_tool_call",
+    "file_change",
+    "web_search",
+];
+
+pub(super) struct BudgetTracker {
+    budget: PromptBudget,
+    tool_items: HashSet<String>,
+    tool_events: usize,
+}
+
+impl BudgetTracker {
+    pub(super) fn new(budget: PromptBudget) -> Self {
+        Self {
+            budget,
+            tool_items: HashSet::new(),
+            tool_events: 0,
+        }
+    }
+
+    pub(super) fn time_limit(&self) -> Option<Duration> {
+        (self.budget.minutes > 0).then(|| Duration::from_secs(self.budget.minutes * 60))
+    }
+
+    pub(super) fn record_event(&mut self, event: &Value) {
+        let kind = event.get("type").and_then(Value::as_str).unwrap_or("");
+        if !matches!(kind, "item.started" | "item.completed") {
+            return;
+        }
+        let Some(item) = event.get("item") else {
+            return;
+        };
+        let item_type = item
+            .get("type")
+            .or_else(|| item.get("item_type"))
+            .and_then(Value::as_str)
+            .unwrap_or("");
+        if !TOOL_ITEM_TYPES.contains(&item_type) {
+            return;
+        }
+        let counted = match item.get("id").and_then(Value::as_str) {
+            Some(id) => self.tool_items.insert(id.to_owned()),
+            None => kind == "item.started",
+        };
+        if counted {
+            self.tool_events += 1;
+        }
+    }
+
+    pub(super) fn exceeded(&self, elapsed: Duration) -> bool {
+        self.budget.tool_events > 0 && self.tool_events >= self.budget.tool_events
+            || self.time_limit().is_some_and(|limit| elapsed >= limit)
+    }
+
+    pub(super) fn stop_summary(&self, elapsed: Duration) -> String {
+        let seconds = elapsed.as_secs();
+        format!(
+            "stopped by budget ({} tool {} / {})",
+            self.tool_events,
+            if self.tool_events == 1 {
+                "call"
+            } else {
+                "calls"
+            },
+            if seconds < 60 {
+                format!("{}s", seconds)
+            } else {
+                format!("{}m", seconds / 60)
+            }
+        )
+    }
+}
+
+pub(super) struct BudgetWatchdog {
+    tripped: Arc<AtomicBool>,
+    _stop: mpsc::Sender<()>,
+}
+
+impl BudgetWatchdog {
+    pub(super) fn start(pid: u32, limit: Duration) -> Self {
+        let tripped = Arc::new(AtomicBool::new(false));
+        let (stop, stopped) = mpsc::channel::<()>();
+        let watchdog_tripped = Arc::clone(&tripped);
+        thread::spawn(move || {
+            let _ = logging::catch_panic("budget watchdog thread", || {
+                if stopped.recv_timeout(limit) != Err(mpsc::RecvTimeoutError::Timeout) {
+                    return;
+                }
+                logging::trace(format!(
+                    "prompt pid {} reached its {} s time budget",
+                    pid,
+                    limit.as_secs()
+                ));
+                watchdog_tripped.store(true, Ordering::Relaxed);
+                if let Err(error) = kill_prompt_process(pid) {
+                    logging::error(format!(
+                        "failed to stop prompt pid {} after its time budget: {}",
+                        pid, error
+                    ));
+                }
+            });
+        });
+        Self {
+            tripped,
+            _stop: stop,
+        }
+    }
+
+    pub(super) fn tripped(&self) -> bool {
+        self.tripped.load(Ordering::Relaxed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn tool_event(kind: &str, id: &str, item_type: &str) -> Value {
+        json!({"type": kind, "item": {"id": id, "type": item_type}})
+    }
+
+    fn budget(minutes: u64, tool_events: usize) -> BudgetTracker {
+        BudgetTracker::new(PromptBudget {
+            minutes,
+            tool_events,
+        })
+    }
+
+    #[test]
+    fn tool_items_count_once_per_id() {
+        let mut tracker = budget(0, 2);
+        tracker.record_event(&tool_event("item.started", "c1", "command_execution"));
+        tracker.record_event(&tool_event("item.completed", "c1", "command_execution"));
+        assert!(!tracker.exceeded(Duration::ZERO));
+        tracker.record_event(&tool_event("item.started", "m1", "mcp_tool_call"));
+        assert!(tracker.exceeded(Duration::ZERO));
+        assert_eq!(
+            tracker.stop_summary(Duration::from_secs(42)),
+            "stopped by budget (2 tool calls / 42s)"
+        );
+    }
+
+    #[test]
+    fn other_events_are_not_tool_calls() {
+        let mut tracker = budget(0, 1);
+        tracker.record_event(&tool_event("item.started", "r1", "reasoning"));
+        tracker.record_event(&tool_event("item.completed", "a1", "agent_message"));
+        tracker.record_event(&tool_event("item.updated", "c1", "command_execution"));
+        tracker.record_event(&json!({"type": "turn.completed"}));
+        assert!(!tracker.exceeded(Duration::ZERO));
+    }
+
+    #[test]
+    fn items_without_ids_count_when_they_start() {
+        let mut tracker = budget(0, 0);
+        let started = json!({"type": "item.started", "item": {"item_type": "file_change"}});
+        let completed = json!({"type": "item.completed", "item": {"item_type": "file_change"}});
+        tracker.record_event(&started);
+        tracker.record_event(&completed);
+        assert_eq!(
+            tracker.stop_summary(Duration::ZERO),
+            "stopped by budget (1 tool call / 0s)"
+        );
+    }
+
+    #[test]
+    fn time_limits_use_the_given_elapsed_time() {
+        let tracker = budget(10, 0);
+        assert_eq!(tracker.time_limit(), Some(Duration::from_secs(600)));
+        assert!(!tracker.exceeded(Duration::from_secs(599)));
+        assert!(tracker.exceeded(Duration::from_secs(600)));
+        assert_eq!(
+            tracker.stop_summary(Duration::from_secs(605)),
+            "stopped by budget (0 tool calls / 10m)"
+        );
+    }
+
+    #[test]
+    fn zero_limits_are_unlimited() {
+        let mut tracker = budget(0, 0);
+        for step in 0..100 {
+            tracker.record_event(&tool_event(
+                "item.started",
+                &format!("c{}", step),
+                "web_search",
+            ));
+        }
+        assert_eq!(tracker.time_limit(), None);
+        assert!(!tracker.exceeded(Duration::from_secs(24 * 60 * 60)));
+    }
+}
@@ -2,6 +2,20 @@ use std::borrow::Cow;
 
 use serde_json::Value;
 
+use super::state::PromptPhase;
+
+const SUGGESTIONS_FENCE: &str = "```suggestions";
+const MAX_SUGGESTIONS: usize = 3;
+const MAX_SUGGESTION_CHARS: usize = 200;
+const REASONING_MARKER: char = '\x1E';
+const MARKER_CHARS: [char; 6] = ['\x19', '\x1A', '\x1C', '\x1D', '\x1E', '\x1F'];
+const CONTROL_PICTURES_START: u32 = 0x2400;
+const CODE_FENCE: &str = "```";
+const LENGTH_STOP_KEYS: [&str; 3] = ["finish_reason", "stop_reason", "reason"];
+const LENGTH_STOP_REASONS: [&str; 4] = ["length", "max_tokens", "max_output_tokens", "token_limit"];
+const SENTENCE_ENDINGS: [char; 10] = ['.', '!', '?', ':', ')', ']', '"', '\'', '`', '\u{2026}'];
+const ITEM_ENVELOPES: [&str; 3] = ["item.started", "item.updated", "item.completed"];
+
 #[derive(Clone, Copy, Eq, PartialEq)]
 enum SegmentKind {
     Plain,
@@ -22,14 +36,43 @@ struct Segment {
     display_start: usize,
 }
 
+struct ItemSegment {
+    id: String,
+    segment: usize,
+}
+
 #[derive(Default)]
 pub(super) struct ResponseBuffers {
     segments: Vec<Segment>,
+    items: Vec<ItemSegment>,
+    trimmed_items: Vec<String>,
     display: String,
     last_text: String,
+    reasoning_window: usize,
+    reasoning_retention: usize,
+    reasoning_trimmed: usize,
+    next_retention_check: usize,
+}
+
+struct LineShift {
+    old_start: usize,
+    new_start: usize,
+    kept: bool,
 }
 
 impl ResponseBuffers {
+    pub(super) fn with_reasoning_limits(
+        reasoning_window: usize,
+        reasoning_retention: usize,
+    ) -> Self {
+        Self {
+            reasoning_window,
+            reasoning_retention,
+            next_retention_check: reasoning_retention,
+            ..Self::default()
+        }
+    }
+
     pub(super) fn push_fragment(&mut self, text: &str) {
         self.push_fragment_inner(text, SegmentKind::Plain);
     }
@@ -42,34 +85,109 @@ impl ResponseBuffers {
         if text.is_empty() {
             return;
         }
-        let text = strip_bold_blocks(text);
+        let text = sanitize_external_text(text);
+        let text = strip_bold_blocks(&text);
         let text = text.as_ref();
         if text.is_empty() {
             return;
         }
-        if let Some(last) = self.last_segment() {
-            if last.kind == kind {
-                let last_text = self.last_segment_text();
-                if last_text == text || last_text.ends_with(text) {
-                    return;
-                }
-                if text.starts_with(last_text) {
-                    self.extend_last_segment_text(text, SegmentStyle::Block);
-                    return;
-                }
-                if kind == SegmentKind::Agent && agent_fragment_matches(last_text, text) {
-                    return;
-                }
+        if let Some(last) = self.last_segment()
+            && last.kind == kind
+        {
+            let last_text = self.last_segment_text();
+            if last_text == text || last_text.ends_with(text) {
+                return;
+            }
+            if text.starts_with(last_text) {
+                self.extend_last_segment_text(text, SegmentStyle::Block);
+                return;
+            }
+            if kind == SegmentKind::Agent && agent_fragment_matches(last_text, text) {
+                return;
             }
         }
         self.append_segment(kind, SegmentStyle::Block, text);
     }
 
+    fn push_item_text(&mut self, id: &str, kind: SegmentKind, text: &str) {
+        if self.trimmed_items.iter().any(|trimmed| trimmed == id) {
+            return;
+        }
+        let text = sanitize_external_text(text);
+        let (text, suffix) = if kind == SegmentKind::Reasoning {
+            (strip_bold_markers(text.trim()).trim().to_owned(), "...")
+        } else {
+            (strip_bold_blocks(&text).into_owned(), "")
+        };
+        if text.is_empty() {
+            return;
+        }
+        if let Some(index) = self
+            .items
+            .iter()
+            .find(|item| item.id == id)
+            .map(|item| item.segment)
+        {
+            self.replace_segment_text(index, &text, suffix);
+            return;
+        }
+        let adopt = self.segments.last().is_some_and(|last| {
+            let existing = self
+                .last_text
+                .strip_suffix(suffix)
+                .unwrap_or(&self.last_text);
+            last.kind == kind
+                && !self
+                    .items
+                    .iter()
+                    .any(|item| item.segment + 1 == self.segments.len())
+                && (text.starts_with(existing)
+                    || kind == SegmentKind::Agent && agent_fragment_matches(existing, &text))
+        });
+        if adopt {
+            self.replace_segment_text(self.segments.len() - 1, &text, suffix);
+        } else {
+            self.append_segment_with_suffix(kind, SegmentStyle::Block, &text, suffix);
+        }
+        self.items.push(ItemSegment {
+            id: id.to_owned(),
+            segment: self.segments.len() - 1,
+        });
+    }
+
+    fn replace_segment_text(&mut self, index: usize, text: &str, suffix: &str) {
+        if index + 1 == self.segments.len() {
+            if self.last_text.strip_suffix(suffix) != Some(text) {
+                self.replace_last_segment_with_suffix(text, suffix, SegmentStyle::Block);
+            }
+            return;
+        }
+        let (Some(segment), Some(next)) = (self.segments.get(index), self.segments.get(index + 1))
+        else {
+            return;
+        };
+        let (start, end) = (segment.display_start, next.display_start);
+        let text = format!("{}{}", text, suffix);
+        let mut rendered = String::new();
+        if needs_break(&self.display[..start], segment.style, &text) {
+            rendered.push_str("\n\n");
+        }
+        render_segment(&mut rendered, segment.kind, &text);
+        if self.display[start..end] == rendered {
+            return;
+        }
+        self.display.replace_range(start..end, &rendered);
+        for later in &mut self.segments[index + 1..] {
+            later.display_start = later.display_start - end + start + rendered.len();
+        }
+    }
+
     pub(super) fn push_delta(&mut self, text: &str) {
         self.push_streaming_fragment(text, SegmentKind::Plain);
     }
 
     pub(super) fn push_reasoning(&mut self, text: &str) {
+        let text = sanitize_external_text(text);
         let trimmed = text.trim();
         if trimmed.is_empty() {
             return;
@@ -79,17 +197,17 @@ impl ResponseBuffers {
         if content.is_empty() {
             return;
         }
-        if let Some(last) = self.last_segment() {
-            if last.kind == SegmentKind::Reasoning {
-                let last_text = self.last_segment_text();
-                let existing = last_text.strip_suffix("...").unwrap_or(last_text);
-                if existing.ends_with(content) {
-                    return;
-                }
-                if content.starts_with(existing) {
-                    self.replace_last_segment_with_suffix(content, "...", SegmentStyle::Block);
-                    return;
-                }
+        if let Some(last) = self.last_segment()
+            && last.kind == SegmentKind::Reasoning
+        {
+            let last_text = self.last_segment_text();
+            let existing = last_text.strip_suffix("...").unwrap_or(last_text);
+            if existing.ends_with(content) {
+                return;
+            }
+            if content.starts_with(existing) {
+                self.replace_last_segment_with_suffix(content, "...", SegmentStyle::Block);
+                return;
             }
         }
         self.append_segment_with_suffix(
@@ -105,39 +223,37 @@ impl ResponseBuffers {
     }
 
     fn push_streaming_fragment(&mut self, text: &str, kind: SegmentKind) {
+        let text = sanitize_external_text(text);
+        let text = text.as_ref();
         if text.is_empty() {
             return;
         }
-        if let Some(last) = self.last_segment() {
-            if last.kind == kind {
-                let last_text = self.last_segment_text();
-                let existing = if kind == SegmentKind::Reasoning {
-                    last_text.strip_suffix("...").unwrap_or(last_text)
+        if let Some(last) = self.last_segment()
+            && last.kind == kind
+        {
+            let last_text = self.last_segment_text();
+            let existing = if kind == SegmentKind::Reasoning {
+                last_text.strip_suffix("...").unwrap_or(last_text)
+            } else {
+                last_text
+            };
+            if last_text.ends_with(text) || existing.ends_with(text) {
+                return;
+            }
+            if text.starts_with(existing) {
+                if kind != SegmentKind::Reasoning && last_text == existing {
+                    self.extend_last_segment_text(text, SegmentStyle::Streaming);
                 } else {
-                    last_text
-                };
-                if last_text.ends_with(text) || existing.ends_with(text) {
-                    return;
+                    self.replace_last_segment_text(text, SegmentStyle::Streaming);
                 }
-                if text.starts_with(existing) {
-                    if kind != SegmentKind::Reasoning && last_text == existing {
-                        self.extend_last_segment_text(text, SegmentStyle::Streaming);
-                    } else {
-                        self.replace_last_segment_text(text, SegmentStyle::Streaming);
-                    }
+            } else {
+                if kind != SegmentKind::Reasoning && last_text.len() == existing.len() {
+                    self.extend_last_segment_suffix(text, SegmentStyle::Streaming);
                 } else {
-                    if kind != SegmentKind::Reasoning && last_text.len() == existing.len() {
-                        self.extend_last_segment_suffix(text, SegmentStyle::Streaming);
-                    } else {
-                        self.append_to_last_segment_text(
-                            existing.len(),
-                            text,
-                            SegmentStyle::Streaming,
-                        );
-                    }
+                    self.append_to_last_segment_text(existing.len(), text, SegmentStyle::Streaming);
                 }
-                return;
             }
+            return;
         }
         self.append_segment(
             kind,
@@ -150,6 +266,20 @@ impl ResponseBuffers {
         );
     }
 
+    pub(super) fn phase(&self) -> PromptPhase {
+        if self
+            .segments
+            .iter()
+            .any(|segment| segment.kind != SegmentKind::Reasoning)
+        {
+            PromptPhase::Answering
+        } else if self.segments.is_empty() {
+            PromptPhase::Waiting
+        } else {
+            PromptPhase::Thinking
+        }
+    }
+
     pub(super) fn has_deltas(&self) -> bool {
         self.segments
             .iter()
@@ -160,12 +290,18 @@ impl ResponseBuffers {
         self.display.len()
     }
 
-    pub(super) fn visible_text(&self) -> &str {
-        &self.display
+    pub(super) fn visible_text(&self) -> Cow<'_, str> {
+        windowed_reasoning(&self.display, self.reasoning_window)
     }
 
-    pub(super) fn into_response(self) -> String {
-        self.display
+    pub(super) fn reasoning_trimmed(&self) -> usize {
+        self.reasoning_trimmed
+    }
+
+    pub(super) fn into_response(self) -> (String, Vec<String>) {
+        let mut display = self.display;
+        let suggestions = strip_suggestions(&mut display);
+        (display, suggestions)
     }
 
     fn last_segment(&self) -> Option<&Segment> {
@@ -187,6 +323,7 @@ impl ResponseBuffers {
         text: &str,
         suffix: &str,
     ) {
+        self.enforce_reasoning_retention();
         let display_start = self.display.len();
         self.set_last_segment_text(text, suffix);
         append_segment_display(&mut self.display, kind, style, &self.last_text);
@@ -275,6 +412,131 @@ impl ResponseBuffers {
         }
     }
 
+    fn enforce_reasoning_retention(&mut self) {
+        if self.reasoning_retention == 0 || self.display.len() < self.next_retention_check {
+            return;
+        }
+        self.next_retention_check = self.display.len() + (self.reasoning_retention / 4).max(1);
+        let retained = self
+            .display
+            .split_inclusive('\n')
+            .filter(|line| line.starts_with(REASONING_MARKER))
+            .map(str::len)
+            .sum::<usize>();
+        let Some(excess) = retained.checked_sub(self.reasoning_retention) else {
+            return;
+        };
+        let protected = self
+            .segments
+            .last()
+            .map_or(self.display.len(), |segment| segment.display_start);
+        let mut trimmed = String::with_capacity(self.display.len());
+        let mut shifts = Vec::new();
+        let mut dropped_at = Vec::new();
+        let mut removed = 0usize;
+        let mut dropped = 0usize;
+        let mut sentinel_at = None;
+        let mut collapsing = false;
+        let mut old_start = 0usize;
+        for line in self.display[..protected].split_inclusive('\n') {
+            let complete = line.ends_with('\n');
+            let reasoning = line.starts_with(REASONING_MARKER);
+            let sentinel = reasoning && sentinel_at.is_none() && self.reasoning_trimmed > 0;
+            let drop = sentinel || reasoning && complete && removed < excess;
+            if reasoning && sentinel_at.is_none() {
+                sentinel_at = Some(trimmed.len());
+            }
+            if drop {
+                dropped_at.push(old_start);
+            }
+            if drop && !sentinel {
+                removed += line.len();
+                dropped += 1;
+            }
+            let kept = !(drop || collapsing && complete && line.trim().is_empty());
+            shifts.push(LineShift {
+                old_start,
+                new_start: trimmed.len(),
+                kept,
+            });
+            if kept {
+                trimmed.push_str(line);
+            }
+            collapsing = drop || collapsing && !kept;
+            old_start += line.len();
+        }
+        let Some(sentinel_at) = sentinel_at.filter(|_| dropped > 0) else {
+            return;
+        };
+        self.reasoning_trimmed += dropped;
+        let sentinel = format!(
+            "{}\u{2026}earlier reasoning trimmed ({} {})\u{2026}\n",
+            REASONING_MARKER,
+            self.reasoning_trimmed,
+            if self.reasoning_trimmed == 1 {
+                "line"
+            } else {
+                "lines"
+            }
+        );
+        trimmed.insert_str(sentinel_at, &sentinel);
+        let new_protected = trimmed.len();
+        trimmed.push_str(&self.display[protected..]);
+        let mut stale: Vec<bool> = (0..self.segments.len())
+            .map(|index| {
+                let (start, end) = self.segment_range(index);
+                start < protected && dropped_at.iter().any(|at| (start..end).contains(at))
+            })
+            .collect();
+        for segment in &mut self.segments {
+            segment.display_start = if segment.display_start >= protected {
+                segment.display_start - protected + new_protected
+            } else {
+                shifted_offset(&shifts, segment.display_start, sentinel_at, sentinel.len())
+            };
+        }
+        self.display = trimmed;
+        for (index, stale) in stale.iter_mut().enumerate() {
+            let (start, end) = self.segment_range(index);
+            *stale |= start <= sentinel_at && sentinel_at < end;
+        }
+        self.forget_trimmed_items(&stale);
+        self.next_retention_check = self.display.len() + (self.reasoning_retention / 4).max(1);
+    }
+
+    fn segment_range(&self, index: usize) -> (usize, usize) {
+        let end = self
+            .segments
+            .get(index + 1)
+            .map_or(self.display.len(), |next| next.display_start);
+        (self.segments[index].display_start, end)
+    }
+
+    // Items whose lines were trimmed, or whose range now holds the trimmed
+    // note, can't be rewritten in place; later updates to them are dropped.
+    fn forget_trimmed_items(&mut self, stale: &[bool]) {
+        let (kept, trimmed): (Vec<_>, Vec<_>) =
+            self.items.drain(..).partition(|item| !stale[item.segment]);
+        self.trimmed_items
+            .extend(trimmed.into_iter().map(|item| item.id));
+        self.items = kept;
+        let last = self.segments.len().saturating_sub(1);
+        let empty: Vec<bool> = (0..self.segments.len())
+            .map(|index| {
+                let (start, end) = self.segment_range(index);
+                index < last && start == end
+            })
+            .collect();
+        for item in &mut self.items {
+            item.segment -= empty[..item.segment].iter().filter(|&&empty| empty).count();
+        }
+        let mut index = 0;
+        self.segments.retain(|_| {
+            index += 1;
+            !empty[index - 1]
+        });
+    }
+
     fn set_last_segment_text(&mut self, text: &str, suffix: &str) {
         self.last_text.clear();
         self.last_text.reserve(text.len() + suffix.len());
@@ -284,6 +546,33 @@ impl ResponseBuffers {
 }
 
 pub(super) fn collect_response_text(value: &Value, response: &mut ResponseBuffers) {
+    let envelope = value.get("type").and_then(Value::as_str).unwrap_or("");
+    if ITEM_ENVELOPES.contains(&envelope)
+        && let Some(item) = value.get("item")
+        && let Some(id) = item.get("id").and_then(Value::as_str)
+        && let Some((kind, text)) = item_text(item)
+    {
+        response.push_item_text(id, kind, text);
+        return;
+    }
+    collect_nested_text(value, response);
+}
+
+fn item_text(item: &Value) -> Option<(SegmentKind, &str)> {
+    let kind = match item
+        .get("type")
+        .or_else(|| item.get("item_type"))
+        .and_then(Value::as_str)?
+    {
+        "agent_message" => SegmentKind::Agent,
+        "assistant_message" => SegmentKind::Plain,
+        "reasoning" => SegmentKind::Reasoning,
+        _ => return None,
+    };
+    Some((kind, item.get("text").and_then(Value::as_str)?))
+}
+
+fn collect_nested_text(value: &Value, response: &mut ResponseBuffers) {
     match value {
         Value::Object(map) => {
             let kind = map.get("type").and_then(Value::as_str).unwrap_or("");
@@ -310,37 +599,63 @@ pub(super) fn collect_response_text(value: &Value, response: &mut ResponseBuffer
             if matches!(
                 kind,
                 "output_text" | "text" | "agent_message" | "assistant_message"
-            ) {
-                if let Some(text) = map.get("text").and_then(Value::as_str) {
-                    if kind == "agent_message" {
-                        response.push_agent_fragment(text);
-                    } else {
-                        response.push_fragment(text);
-                    }
-                }
-            }
-            if kind == "message" && map.get("role").and_then(Value::as_str) == Some("assistant") {
-                if let Some(text) = map.get("text").and_then(Value::as_str) {
+            ) && let Some(text) = map.get("text").and_then(Value::as_str)
+            {
+                if kind == "agent_message" {
+                    response.push_agent_fragment(text);
+                } else {
                     response.push_fragment(text);
                 }
             }
+            if kind == "message"
+                && map.get("role").and_then(Value::as_str) == Some("assistant")
+                && let Some(text) = map.get("text").and_then(Value::as_str)
+            {
+                response.push_fragment(text);
+            }
             for key in [
                 "content", "contents", "item", "items", "message", "messages", "output",
             ] {
                 if let Some(child) = map.get(key) {
-                    collect_response_text(child, response);
+                    collect_nested_text(child, response);
                 }
             }
         }
         Value::Array(items) => {
             for item in items {
-                collect_response_text(item, response);
+                collect_nested_text(item, response);
             }
         }
         _ => {}
     }
 }
 
+pub fn normalize_line_endings(text: &str) -> Cow<'_, str> {
+    if !text.contains('\r') {
+        return Cow::Borrowed(text);
+    }
+    Cow::Owned(text.replace("\r\n", "\n").replace('\r', ""))
+}
+
+pub fn sanitize_external_text(text: &str) -> Cow<'_, str> {
+    let text = normalize_line_endings(text);
+    if !text.contains(MARKER_CHARS) {
+        return text;
+    }
+    Cow::Owned(
+        text.chars()
+            .map(|c| {
+                if MARKER_CHARS.contains(&c) {
+                    char::from_u32(CONTROL_PICTURES_START + c as u32)
+                        .unwrap_or(char::REPLACEMENT_CHARACTER)
+                } else {
+                    c
+                }
+            })
+            .collect(),
+    )
+}
+
 fn strip_bold_blocks(text: &str) -> Cow<'_, str> {
     let Some(first) = text.find("**") else {
         return Cow::Borrowed(text);
@@ -401,6 +716,10 @@ fn append_segment_display(
     if needs_break(display, style, text) {
         display.push_str("\n\n");
     }
+    render_segment(display, kind, text);
+}
+
+fn render_segment(display: &mut String, kind: SegmentKind, text: &str) {
     match kind {
         SegmentKind::Plain => display.push_str(text),
         SegmentKind::Agent => append_marked(display, '\x1F', text),
@@ -451,6 +770,68 @@ fn append_marked_continuation(
     }
 }
 
+fn windowed_reasoning(display: &str, window: usize) -> Cow<'_, str> {
+    if window == 0 {
+        return Cow::Borrowed(display);
+    }
+    let reasoning_lines = display
+        .split_inclusive('\n')
+        .filter(|line| line.starts_with(REASONING_MARKER))
+        .count();
+    let hidden = reasoning_lines.saturating_sub(window);
+    if hidden == 0 {
+        return Cow::Borrowed(display);
+    }
+    let mut result = String::with_capacity(display.len());
+    let mut remaining = hidden;
+    let mut collapsing = false;
+    for line in display.split_inclusive('\n') {
+        if remaining > 0 && line.starts_with(REASONING_MARKER) {
+            if remaining == hidden {
+                result.push(REASONING_MARKER);
+                result.push_str(&format!(
+                    "\u{2026}{} earlier reasoning {}\n",
+                    hidden,
+                    if hidden == 1 { "line" } else { "lines" }
+                ));
+            }
+            remaining -= 1;
+            collapsing = true;
+            continue;
+        }
+        if collapsing && line.trim().is_empty() {
+            continue;
+        }
+        collapsing = false;
+        result.push_str(line);
+    }
+    Cow::Owned(result)
+}
+
+fn shifted_offset(
+    shifts: &[LineShift],
+    offset: usize,
+    sentinel_at: usize,
+    sentinel_len: usize,
+) -> usize {
+    let index = shifts
+        .partition_point(|shift| shift.old_start <= offset)
+        .saturating_sub(1);
+    let Some(shift) = shifts.get(index) else {
+        return offset;
+    };
+    let shifted = if shift.kept {
+        shift.new_start + offset - shift.old_start
+    } else {
+        shift.new_start
+    };
+    if shifted > sentinel_at || shifted == sentinel_at && shift.kept {
+        shifted + sentinel_len
+    } else {
+        shifted
+    }
+}
+
 fn agent_fragment_matches(marked: &str, text: &str) -> bool {
     let mut remaining = marked;
     for line in text.split_inclusive('\n') {
@@ -482,3 +863,518 @@ fn collect_reasoning_text(value: &Value, response: &mut ResponseBuffers) {
         _ => {}
     }
 }
+
+fn strip_suggestions(display: &mut String) -> Vec<String> {
+    let content = |line: &str| line.trim_start_matches(MARKER_CHARS).trim().to_owned();
+    let mut block_start = None;
+    let mut offset = 0usize;
+    for line in display.split_inclusive('\n') {
+        if content(line) == SUGGESTIONS_FENCE {
+            block_start = Some(offset);
+        }
+        offset += line.len();
+    }
+    let Some(block_start) = block_start else {
+        return Vec::new();
+    };
+    let mut lines = display[block_start..].split_inclusive('\n').map(content);
+    lines.next();
+    let mut suggestions = Vec::new();
+    let mut closed = false;
+    for line in lines.by_ref() {
+        if line == "```" {
+            closed = true;
+            break;
+        }
+        let item = line
+            .trim_start_matches(['-', '*'])
+            .trim_start_matches(|c: char| c.is_ascii_digit())
+            .trim_start_matches(['.', ')'])
+            .trim();
+        if !item.is_empty() {
+            suggestions.push(item.chars().take(MAX_SUGGESTION_CHARS).collect());
+        }
+    }
+    if !closed || lines.any(|line| !line.is_empty()) || suggestions.is_empty() {
+        return Vec::new();
+    }
+    suggestions.truncate(MAX_SUGGESTIONS);
+    display.truncate(block_start);
+    let trimmed = display.trim_end_matches(['\n', '\x1F']).len();
+    display.truncate(trimmed);
+    if !display.is_empty() {
+        display.push('\n');
+    }
+    suggestions
+}
+
+pub(super) fn collect_changed_files(event: &Value, files: &mut Vec<String>) {
+    if event.get("type").and_then(Value::as_str) != Some("item.completed") {
+        return;
+    }
+    let Some(item) = event.get("item") else {
+        return;
+    };
+    let item_type = item
+        .get("type")
+        .or_else(|| item.get("item_type"))
+        .and_then(Value::as_str);
+    if item_type != Some("file_change") {
+        return;
+    }
+    let changes = item.get("changes").and_then(Value::as_array);
+    for path in changes
+        .into_iter()
+        .flatten()
+        .filter_map(|change| change.get("path").and_then(Value::as_str))
+    {
+        if !files.iter().any(|file| file == path) {
+            files.push(path.to_owned());
+        }
+    }
+}
+
+pub(super) fn reports_length_stop(value: &Value) -> bool {
+    match value {
+        Value::Object(map) => map.iter().any(|(key, child)| {
+            LENGTH_STOP_KEYS.contains(&key.as_str())
+                && child
+                    .as_str()
+                    .is_some_and(|reason| LENGTH_STOP_REASONS.contains(&reason))
+                || reports_length_stop(child)
+        }),
+        Value::Array(items) => items.iter().any(reports_length_stop),
+        _ => false,
+    }
+}
+
+pub fn looks_truncated(text: &str, length_stopped: bool) -> bool {
+    let mut open_fence = false;
+    let mut last_line = "";
+    for line in text.lines() {
+        if line.starts_with(REASONING_MARKER) {
+            continue;
+        }
+        let line = line.trim_start_matches('\x1F').trim();
+        if line.starts_with(CODE_FENCE) {
+            open_fence = !open_fence;
+        }
+        if !line.is_empty() {
+            last_line = line;
+        }
+    }
+    if open_fence {
+        return true;
+    }
+    length_stopped && !last_line.is_empty() && !last_line.ends_with(SENTENCE_ENDINGS)
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn item_event(envelope: &str, id: &str, kind: &str, text: &str) -> Value {
+        json!({"type": envelope, "item": {"id": id, "type": kind, "text": text}})
+    }
+
+    fn push_item(response: &mut ResponseBuffers, envelope: &str, id: &str, kind: &str, text: &str) {
+        collect_response_text(&item_event(envelope, id, kind, text), response);
+    }
+
+    fn stream_reasoning(response: &mut ResponseBuffers, steps: usize) {
+        for step in 0..steps {
+            push_item(
+                response,
+                "item.completed",
+                &format!("r{}", step),
+                "reasoning",
+                &format!("reasoning step {:02} looks at the next file", step),
+            );
+        }
+    }
+
+    fn sentinel_lines(display: &str) -> Vec<&str> {
+        display
+            .lines()
+            .filter(|line| line.contains("earlier reasoning trimmed"))
+            .collect()
+    }
+
+    fn reasoning_bytes(display: &str) -> usize {
+        display
+            .split_inclusive('\n')
+            .filter(|line| line.starts_with(REASONING_MARKER))
+            .map(str::len)
+            .sum()
+    }
+
+    #[test]
+    fn reasoning_window_collapses_older_lines_into_a_counter() {
+        let display = "\x1Eone...\n\x1Etwo...\n\x1Ethree...\n\x1Efour...\n\x1Efive...\n";
+        assert_eq!(
+            windowed_reasoning(display, 3),
+            "\x1E\u{2026}2 earlier reasoning lines\n\x1Ethree...\n\x1Efour...\n\x1Efive...\n"
+        );
+        assert_eq!(
+            windowed_reasoning(display, 4),
+            "\x1E\u{2026}1 earlier reasoning line\n\x1Etwo...\n\x1Ethree...\n\x1Efour...\n\x1Efive...\n"
+        );
+    }
+
+    #[test]
+    fn reasoning_window_leaves_short_or_unlimited_text_alone() {
+        let display = "\x1Eone...\n\x1Etwo...\n\x1FAnswer\n";
+        assert!(matches!(windowed_reasoning(display, 0), Cow::Borrowed(text) if text == display));
+        assert!(matches!(windowed_reasoning(display, 2), Cow::Borrowed(text) if text == display));
+    }
+
+    #[test]
+    fn reasoning_window_keeps_answer_lines_and_partial_lines() {
+        let display =
+            "\x1Eplan...\n\n\x1Eread...\n\n\x1FFirst answer line\n\n\x1Echeck...\n\x1Estill stream";
+        assert_eq!(
+            windowed_reasoning(display, 2),
+            "\x1E\u{2026}2 earlier reasoning lines\n\x1FFirst answer line\n\n\x1Echeck...\n\x1Estill stream"
+        );
+    }
+
+    #[test]
+    fn reasoning_window_only_changes_the_live_view() {
+        let mut response = ResponseBuffers::with_reasoning_limits(3, 0);
+        stream_reasoning(&mut response, 6);
+        let visible = response.visible_text().into_owned();
+        assert!(visible.starts_with("\x1E\u{2026}3 earlier reasoning lines\n"));
+        assert!(visible.contains("reasoning step 03"));
+        assert!(!visible.contains("reasoning step 02"));
+        for line in visible.lines().filter(|line| !line.is_empty()) {
+            assert!(line.starts_with(REASONING_MARKER), "{:?}", line);
+        }
+        let (display, _) = response.into_response();
+        assert!(display.contains("reasoning step 00"));
+        assert!(!display.contains("earlier reasoning lines"));
+    }
+
+    #[test]
+    fn retention_trims_old_reasoning_and_keeps_the_answer() {
+        let mut response = ResponseBuffers::with_reasoning_limits(0, 400);
+        stream_reasoning(&mut response, 40);
+        push_item(
+            &mut response,
+            "item.completed",
+            "answer",
+            "agent_message",
+            "All files checked.\nNothing else to do.",
+        );
+        let trimmed = response.reasoning_trimmed();
+        assert!(trimmed > 0);
+        let (display, _) = response.into_response();
+        assert!(display.contains("\x1FAll files checked.\n\x1FNothing else to do."));
+        assert!(display.contains("reasoning step 39"));
+        assert!(!display.contains("reasoning step 00"));
+        assert_eq!(
+            sentinel_lines(&display),
+            [format!(
+                "{}\u{2026}earlier reasoning trimmed ({} lines)\u{2026}",
+                REASONING_MARKER, trimmed
+            )]
+        );
+        assert!(reasoning_bytes(&display) < 400 + 200);
+    }
+
+    #[test]
+    fn retention_keeps_one_sentinel_across_trims() {
+        let mut response = ResponseBuffers::with_reasoning_limits(0, 300);
+        stream_reasoning(&mut response, 80);
+        let (display, _) = response.into_response();
+        assert_eq!(sentinel_lines(&display).len(), 1);
+        let first_reasoning = display
+            .lines()
+            .find(|line| line.starts_with(REASONING_MARKER))
+            .unwrap();
+        assert!(first_reasoning.contains("earlier reasoning trimmed"));
+    }
+
+    #[test]
+    fn zero_retention_keeps_everything() {
+        let mut response = ResponseBuffers::with_reasoning_limits(0, 0);
+        stream_reasoning(&mut response, 40);
+        assert_eq!(response.reasoning_trimmed(), 0);
+        let (display, _) = response.into_response();
+        assert!(display.contains("reasoning step 00"));
+        assert!(sentinel_lines(&display).is_empty());
+    }
+
+    #[test]
+    fn update_after_trim_does_not_restore_trimmed_text() {
+        let mut response = ResponseBuffers::with_reasoning_limits(0, 400);
+        stream_reasoning(&mut response, 40);
+        assert!(response.reasoning_trimmed() > 0);
+        push_item(
+            &mut response,
+            "item.completed",
+            "r1",
+            "reasoning",
+            "UPDATED reasoning one",
+        );
+        push_item(
+            &mut response,
+            "item.updated",
+            "r25",
+            "reasoning",
+            "UPDATED reasoning twentyfive",
+        );
+        let (display, _) = response.into_response();
+        assert!(!display.contains("UPDATED reasoning one"));
+        for line in sentinel_lines(&display) {
+            assert!(line.starts_with(REASONING_MARKER));
+            assert!(line.ends_with("\u{2026}"));
+            assert!(!line.contains("UPDATED"));
+        }
+        assert_eq!(sentinel_lines(&display).len(), 1);
+    }
+
+    #[test]
+    fn update_to_a_kept_item_after_trim_replaces_it() {
+        let mut response = ResponseBuffers::with_reasoning_limits(0, 400);
+        stream_reasoning(&mut response, 40);
+        push_item(
+            &mut response,
+            "item.completed",
+            "r38",
+            "reasoning",
+            "reasoning step 38 was rewritten",
+        );
+        let (display, _) = response.into_response();
+        assert!(display.contains("reasoning step 38 was rewritten"));
+        assert!(!display.contains("reasoning step 38 looks"));
+        assert!(display.contains("reasoning step 39 looks"));
+    }
+
+    #[test]
+    fn updated_items_replace_their_earlier_text() {
+        let mut response = ResponseBuffers::default();
+        push_item(&mut response, "item.started", "a", "agent_message", "Hel");
+        push_item(
+            &mut response,
+            "item.updated",
+            "a",
+            "agent_message",
+            "Hello wor",
+        );
+        push_item(
+            &mut response,
+            "item.completed",
+            "a",
+            "agent_message",
+            "Hello world.",
+        );
+        let (display, _) = response.into_response();
+        assert_eq!(display, "\x1FHello world.");
+    }
+
+    #[test]
+    fn updates_and_completed_only_streams_match() {
+        let mut incremental = ResponseBuffers::default();
+        push_item(&mut incremental, "item.started", "r", "reasoning", "Plan");
+        push_item(
+            &mut incremental,
+            "item.updated",
+            "r",
+            "reasoning",
+            "Plan the change",
+        );
+        push_item(
+            &mut incremental,
+            "item.completed",
+            "r",
+            "reasoning",
+            "Plan the change first",
+        );
+        push_item(
+            &mut incremental,
+            "item.updated",
+            "a",
+            "agent_message",
+            "Done",
+        );
+        push_item(
+            &mut incremental,
+            "item.completed",
+            "a",
+            "agent_message",
+            "Done. Tests pass.",
+        );
+
+        let mut completed = ResponseBuffers::default();
+        push_item(
+            &mut completed,
+            "item.completed",
+            "r",
+            "reasoning",
+            "Plan the change first",
+        );
+        push_item(
+            &mut completed,
+            "item.completed",
+            "a",
+            "agent_message",
+            "Done. Tests pass.",
+        );
+
+        assert_eq!(incremental.into_response(), completed.into_response());
+    }
+
+    #[test]
+    fn updating_an_earlier_item_keeps_later_segments() {
+        let mut response = ResponseBuffers::default();
+        push_item(&mut response, "item.started", "r", "reasoning", "Look");
+        push_item(
+            &mut response,
+            "item.started",
+            "a",
+            "agent_message",
+            "Answer",
+        );
+        push_item(
+            &mut response,
+            "item.completed",
+            "r",
+            "reasoning",
+            "Look around",
+        );
+        let (display, _) = response.into_response();
+        assert_eq!(display, "\x1ELook around...\n\n\x1FAnswer");
+    }
+
+    #[test]
+    fn unclosed_fences_look_truncated() {
+        let cut = "\x1FHere is the fix:\n\n```rust\nfn main() {\n    run(";
+        assert!(looks_truncated(cut, false));
+        let closed = "\x1FHere is the fix:\n\n```rust\nfn main() {}\n```\nDone.";
+        assert!(!looks_truncated(closed, false));
+        assert!(!looks_truncated(closed, true));
+    }
+
+    #[test]
+    fn length_stops_need_an_unfinished_sentence() {
+        let cut = "\x1FThe parser first reads the header and then";
+        assert!(looks_truncated(cut, true));
+        assert!(!looks_truncated(cut, false));
+        for finished in [
+            "It works.",
+            "Is it ready?",
+            "See `run`",
+            "Options:",
+            "(done)",
+        ] {
+            assert!(!looks_truncated(finished, true), "{:?}", finished);
+        }
+    }
+
+    #[test]
+    fn reasoning_and_blank_lines_are_ignored() {
+        let text = "\x1FAll tests pass.\n\n\x1Estill thinking about ```\n\n";
+        assert!(!looks_truncated(text, true));
+        assert!(!looks_truncated("", true));
+        assert!(!looks_truncated("\x1Ereasoning only", true));
+    }
+
+    #[test]
+    fn length_stops_are_found_anywhere_in_the_event() {
+        assert!(reports_length_stop(
+            &json!({"type": "turn.completed", "finish_reason": "length"})
+        ));
+        assert!(reports_length_stop(
+            &json!({"type": "turn.completed", "response": {"details": [{"reason": "max_output_tokens"}]}})
+        ));
+        assert!(!reports_length_stop(
+            &json!({"type": "turn.completed", "finish_reason": "stop"})
+        ));
+        assert!(!reports_length_stop(
+            &json!({"type": "item.completed", "item": {"text": "length"}})
+        ));
+    }
+
+    #[test]
+    fn carriage_returns_are_normalized() {
+        assert!(matches!(normalize_line_endings("a\nb"), Cow::Borrowed(_)));
+        assert_eq!(normalize_line_endings("a\r\nb\r\n"), "a\nb\n");
+        assert_eq!(normalize_line_endings("50%\r100%\r\n"), "50%100%\n");
+        assert_eq!(sanitize_external_text("x\r\n\x1Fy"), "x\n\u{241F}y");
+    }
+
+    #[test]
+    fn crlf_events_render_without_carriage_returns() {
+        let mut response = ResponseBuffers::default();
+        push_item(
+            &mut response,
+            "item.completed",
+            "r",
+            "reasoning",
+            "Look at\r\nthe tests",
+        );
+        push_item(
+            &mut response,
+            "item.completed",
+            "a",
+            "agent_message",
+            "First line\r\nSecond line\r\n\r\n```\r\ncargo test\r\n```",
+        );
+        let (display, _) = response.into_response();
+        assert!(!display.contains('\r'), "{:?}", display);
+        assert_eq!(
+            display,
+            "\x1ELook at\n\x1Ethe tests...\n\n\x1FFirst line\n\x1FSecond line\n\x1F\n\
+             \x1F```\n\x1Fcargo test\n\x1F```"
+        );
+    }
+
+    #[test]
+    fn carriage_returns_split_across_deltas_are_dropped() {
+        let mut response = ResponseBuffers::default();
+        for delta in ["one\r", "\ntwo\r", "\r\nthree"] {
+            collect_response_text(
+                &json!({"type": "response.output_text.delta", "delta": delta}),
+                &mut response,
+            );
+        }
+        let (display, _) = response.into_response();
+        assert_eq!(display, "one\ntwo\nthree");
+    }
+
+    #[test]
+    fn marker_bytes_in_codex_text_never_classify_lines() {
+        let mut response = ResponseBuffers::default();
+        push_item(
+            &mut response,
+            "item.completed",
+            "r",
+            "reasoning",
+            "\x1Dnot an error\n\x19#9",
+        );
+        push_item(
+            &mut response,
+            "item.completed",
+            "a",
+            "agent_message",
+            "\x1Enot reasoning\n\x1Cnot an action",
+        );
+        collect_response_text(
+            &json!({"type": "response.output_text.delta", "delta": "\n\x1Fnot agent\x1A"}),
+            &mut response,
+        );
+        let (display, _) = response.into_response();
+        assert_eq!(
+            display,
+            "\x1E\u{241D}not an error\n\x1E\u{2419}#9...\n\n\
+             \x1F\u{241E}not reasoning\n\x1F\u{241C}not an action\n\
+             \u{241F}not agent\u{241A}"
+        );
+        for line in display.lines() {
+            let inner = line.strip_prefix(['\x1E', '\x1F']).unwrap_or(line);
+            assert!(!inner.contains(MARKER_CHARS), "{:?}", line);
+        }
+    }
+}
@@ -1,53 +1,349 @@
+use std::collections::BTreeMap;
+#[cfg(unix)]
 use std::env;
+#[cfg(windows)]
+use std::fs;
 use std::io::{self, BufRead};
+#[cfg(windows)]
+use std::os::windows::io::AsRawHandle;
+#[cfg(windows)]
 use std::os::windows::process::CommandExt;
-use std::path::PathBuf;
-use std::process::{ChildStdin, Command, Stdio};
-use std::sync::{Arc, Mutex, mpsc};
+use std::path::{Path, PathBuf};
+use std::process::{Child, ChildStdin, Command, Stdio};
+use std::sync::{Arc, Mutex, RwLock, mpsc};
 use std::thread;
 
-use eframe::egui;
-use windows_sys::Win32::System::Threading::CREATE_NO_WINDOW;
+#[cfg(windows)]
+use windows_sys::Win32::Foundation::{HANDLE_FLAG_INHERIT, SetHandleInformation};
 
-use crate::events::{AppEvent, CodexCheckResult};
+use crate::config::load_env_overrides;
+use crate::config::project::ProjectConfig;
+#[cfg(windows)]
+use crate::config::{DEFAULT_LAUNCHER_MODE, LauncherMode, load_launcher_mode};
+use crate::events::{AppEvent, CodexCheckResult, RepaintHandle};
 use crate::logging;
+use crate::runtime::{BackgroundCommand, current_cwd_text};
+#[cfg(windows)]
+use crate::runtime::{node_dirs, npm_prefix};
 
+const DRY_RUN_PROMPT_CHARS: usize = 80;
+const STDIN_PROMPT: &str = "-";
+const SECRET_ENV_MARKERS: [&str; 4] = ["TOKEN", "KEY", "SECRET", "PASSWORD"];
+
+static CODEX_LAUNCHER: RwLock<Option<CodexLauncher>> = RwLock::new(None);
+static CODEX_PROFILE: RwLock<Option<String>> = RwLock::new(None);
+
+#[derive(Clone)]
 enum CodexLauncher {
-    Node { node: PathBuf, script: PathBuf },
+    #[cfg(windows)]
+    Node {
+        node: PathBuf,
+        script: PathBuf,
+    },
+    #[cfg(windows)]
     Cmd(PathBuf),
+    #[cfg(windows)]
+    Wsl {
+        distro: Option<String>,
+    },
+    #[cfg(unix)]
+    Binary(PathBuf),
     Direct,
 }
 
-pub(super) fn build_codex_command(prompt: &str, session_id: Option<&str>) -> Command {
-    match codex_launcher() {
+pub(super) fn build_codex_command(
+    prompt: &str,
+    session_id: Option<&str>,
+    project: Option<&ProjectConfig>,
+    env_overrides: &[(String, String)],
+) -> Command {
+    let profile = codex_profile();
+    let mut command = codex_command(&codex_args(prompt, session_id, project, profile.as_deref()));
+    apply_env_overrides(&mut command, env_overrides);
+    command
+}
+
+/// Commands codex asks to run can only be run here when codex itself runs
+/// natively; under WSL they belong to the Linux side.
+pub(super) fn runs_commands_natively() -> bool {
+    #[cfg(windows)]
+    {
+        !matches!(codex_launcher(), CodexLauncher::Wsl { .. })
+    }
+    #[cfg(unix)]
+    {
+        true
+    }
+}
+
+pub(super) fn codex_env_overrides(project: Option<&ProjectConfig>) -> Vec<(String, String)> {
+    let settings = load_env_overrides().unwrap_or_else(|error| {
+        logging::error(format!("failed to load environment overrides: {}", error));
+        Vec::new()
+    });
+    merge_env_overrides(settings, project.map(|project| &project.env))
+}
+
+/// Project entries win over settings entries with the same name; an empty
+/// value means the variable is removed from codex's environment.
+fn merge_env_overrides(
+    settings: Vec<(String, String)>,
+    project: Option<&BTreeMap<String, String>>,
+) -> Vec<(String, String)> {
+    let mut overrides: BTreeMap<String, String> = settings.into_iter().collect();
+    if let Some(project) = project {
+        overrides.extend(
+            project
+                .iter()
+                .map(|(name, value)| (name.clone(), value.clone())),
+        );
+    }
+    overrides.into_iter().collect()
+}
+
+fn apply_env_overrides(command: &mut Command, env_overrides: &[(String, String)]) {
+    for (name, value) in env_overrides {
+        if value.is_empty() {
+            command.env_remove(name);
+        } else {
+            command.env(name, value);
+        }
+    }
+    #[cfg(windows)]
+    super::wsl::forward_env(
+        command,
+        env_overrides
+            .iter()
+            .filter(|(_, value)| !value.is_empty())
+            .map(|(name, _)| name.as_str()),
+    );
+}
+
+pub(super) fn describe_env_overrides(env_overrides: &[(String, String)]) -> String {
+    env_overrides
+        .iter()
+        .map(|(name, value)| {
+            if value.is_empty() {
+                format!("unset {}", name)
+            } else {
+                format!("{}={}", name, redact_env_value(name, value))
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+fn redact_env_value<'a>(name: &str, value: &'a str) -> &'a str {
+    let name = name.to_ascii_uppercase();
+    if SECRET_ENV_MARKERS
+        .iter()
+        .any(|marker| name.contains(marker))
+    {
+        "<redacted>"
+    } else {
+        value
+    }
+}
+
+pub fn describe_codex_command(
+    prompt: &str,
+    session_id: Option<&str>,
+    project: Option<&ProjectConfig>,
+) -> String {
+    let launcher = codex_launcher();
+    let profile = codex_profile();
+    let mut command = launcher_command(
+        launcher.clone(),
+        &codex_args(prompt, session_id, project, profile.as_deref()),
+    );
+    apply_env_overrides(&mut command, &codex_env_overrides(project));
+    describe_command(&launcher, &command, prompt)
+}
+
+fn describe_command(launcher: &CodexLauncher, command: &Command, prompt: &str) -> String {
+    let mut lines = vec![
+        format!("launcher: {}", launcher.describe()),
+        format!(
+            "program:  {}",
+            elide_prompt(&command.get_program().to_string_lossy(), prompt)
+        ),
+    ];
+    for (index, arg) in command.get_args().enumerate() {
+        lines.push(format!(
+            "argv[{}]:  {}",
+            index + 1,
+            elide_prompt(&arg.to_string_lossy(), prompt)
+        ));
+    }
+    let cwd = command
+        .get_current_dir()
+        .map_or_else(current_cwd_text, |dir| dir.display().to_string());
+    lines.push(format!("cwd:      {}", cwd));
+    let envs: Vec<(String, String)> = command
+        .get_envs()
+        .map(|(name, value)| {
+            (
+                name.to_string_lossy().into_owned(),
+                value.map_or_else(String::new, |value| value.to_string_lossy().into_owned()),
+            )
+        })
+        .collect();
+    if envs.is_empty() {
+        lines.push("env:      inherited".to_owned());
+    } else {
+        lines.push(format!("env:      {}", describe_env_overrides(&envs)));
+    }
+    lines.join("\n")
+}
+
+fn elide_prompt(text: &str, prompt: &str) -> String {
+    match prompt.char_indices().nth(DRY_RUN_PROMPT_CHARS) {
+        Some((end, _)) => text.replace(prompt, &format!("{}\u{2026}", &prompt[..end])),
+        _ => text.to_owned(),
+    }
+}
+
+fn codex_command(args: &[&str]) -> Command {
+    launcher_command(codex_launcher(), args)
+}
+
+fn launcher_command(launcher: CodexLauncher, args: &[&str]) -> Command {
+    match launcher {
+        #[cfg(windows)]
         CodexLauncher::Node { node, script } => {
             let mut command = Command::new(node);
             command.arg(script);
-            append_codex_args(&mut command, prompt, session_id);
+            command.args(args);
             command
         }
+        #[cfg(windows)]
         CodexLauncher::Cmd(codex_cmd) => {
             let mut command = Command::new("cmd.exe");
-            command.arg("/C");
-            command.arg(codex_cmd);
-            append_codex_args(&mut command, prompt, session_id);
+            command.raw_arg(cmd_command_line(&codex_cmd, args));
+            command
+        }
+        #[cfg(windows)]
+        CodexLauncher::Wsl { distro } => super::wsl::wsl_command(distro.as_deref(), args),
+        #[cfg(unix)]
+        CodexLauncher::Binary(codex) => {
+            let mut command = Command::new(codex);
+            command.args(args);
             command
         }
         CodexLauncher::Direct => {
             let mut command = Command::new("codex");
-            append_codex_args(&mut command, prompt, session_id);
+            command.args(args);
             command
         }
     }
 }
 
-pub(crate) fn check_codex_availability() -> CodexCheckResult {
+#[cfg(windows)]
+fn cmd_command_line(codex_cmd: &Path, args: &[&str]) -> String {
+    let mut line = String::from("/S /C \"");
+    line.push_str(&quote_cmd_arg(&codex_cmd.to_string_lossy()));
+    for arg in args {
+        line.push(' ');
+        line.push_str(&quote_cmd_arg(arg));
+    }
+    line.push('"');
+    line
+}
+
+#[cfg(windows)]
+fn quote_cmd_arg(arg: &str) -> String {
+    let mut quoted = String::with_capacity(arg.len() + 2);
+    quoted.push('"');
+    let mut backslashes = 0usize;
+    for ch in arg.chars() {
+        match ch {
+            '\\' => backslashes += 1,
+            '"' => {
+                quoted.extend(std::iter::repeat_n('\\', backslashes * 2));
+                quoted.push_str("\"\"");
+                backslashes = 0;
+            }
+            _ => {
+                quoted.extend(std::iter::repeat_n('\\', backslashes));
+                quoted.push(ch);
+                backslashes = 0;
+            }
+        }
+    }
+    quoted.extend(std::iter::repeat_n('\\', backslashes * 2));
+    quoted.push('"');
+    quoted
+}
+
+pub fn resolve_codex_path() -> Option<PathBuf> {
+    if let Some(path) = codex_cmd_path().or_else(codex_script_path) {
+        return Some(path);
+    }
+    let locator = if cfg!(windows) { "where" } else { "which" };
+    let output = Command::new(locator)
+        .arg("codex")
+        .hide_window()
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .output()
+        .ok()
+        .filter(|output| output.status.success())?;
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .map(str::trim)
+        .find(|line| !line.is_empty())
+        .map(PathBuf::from)
+}
+
+pub fn describe_codex_launcher() -> String {
+    codex_launcher().describe()
+}
+
+pub fn codex_version() -> Option<String> {
+    let output = codex_command(&["--version"])
+        .hide_window()
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .output()
+        .ok()
+        .filter(|output| output.status.success())?;
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .map(str::trim)
+        .find(|line| !line.is_empty())
+        .map(ToOwned::to_owned)
+}
+
+pub fn launch_codex_login() -> io::Result<()> {
+    logging::log_result(codex_command(&["login"]).new_console().spawn(), |error| {
+        format!("failed to launch codex login: {}", error)
+    })?;
+    logging::trace("launched codex login console");
+    Ok(())
+}
+
+pub fn codex_visible_path(path: &Path) -> String {
+    let text = path.display().to_string();
+    #[cfg(windows)]
+    if let CodexLauncher::Wsl { .. } = codex_launcher() {
+        return super::wsl::windows_to_wsl_path(&text).unwrap_or(text);
+    }
+    text
+}
+
+pub fn check_codex_availability() -> CodexCheckResult {
+    #[cfg(windows)]
+    if let CodexLauncher::Wsl { .. } = codex_launcher() {
+        return CodexCheckResult::Ready;
+    }
     if codex_script_path().is_some() || codex_cmd_path().is_some() {
         return CodexCheckResult::Ready;
     }
     match Command::new("codex")
         .arg("--version")
-        .creation_flags(CREATE_NO_WINDOW)
+        .hide_window()
         .stdin(Stdio::null())
         .stdout(Stdio::piped())
         .stderr(Stdio::piped())
@@ -67,10 +363,10 @@ pub(crate) fn check_codex_availability() -> CodexCheckResult {
     }
 }
 
-pub(crate) fn run_full_install(
+pub fn run_full_install(
     node_available: bool,
     tx: &mpsc::Sender<AppEvent>,
-    ctx: &egui::Context,
+    ctx: &RepaintHandle,
     install_stdin: &Arc<Mutex<Option<ChildStdin>>>,
 ) -> Result<(), String> {
     if !node_available {
@@ -89,14 +385,26 @@ pub(crate) fn run_full_install(
     run_codex_install(tx, ctx)
 }
 
+#[cfg(not(windows))]
+fn run_node_install(
+    _tx: &mpsc::Sender<AppEvent>,
+    _ctx: &RepaintHandle,
+    _install_stdin: &Arc<Mutex<Option<ChildStdin>>>,
+) -> Result<(), String> {
+    let message = "Install Node.js with your system package manager, then retry".to_owned();
+    logging::error(message.clone());
+    Err(message)
+}
+
+#[cfg(windows)]
 fn run_node_install(
     tx: &mpsc::Sender<AppEvent>,
-    ctx: &egui::Context,
+    ctx: &RepaintHandle,
     install_stdin: &Arc<Mutex<Option<ChildStdin>>>,
 ) -> Result<(), String> {
     let mut child = Command::new("winget")
         .args(["install", "OpenJS.NodeJS", "--accept-package-agreements"])
-        .creation_flags(CREATE_NO_WINDOW)
+        .hide_window()
         .stdin(Stdio::piped())
         .stdout(Stdio::piped())
         .stderr(Stdio::piped())
@@ -132,11 +440,11 @@ fn run_node_install(
     }
 }
 
-fn run_codex_install(tx: &mpsc::Sender<AppEvent>, ctx: &egui::Context) -> Result<(), String> {
+fn run_codex_install(tx: &mpsc::Sender<AppEvent>, ctx: &RepaintHandle) -> Result<(), String> {
     let npm = find_npm();
     let mut child = Command::new(&npm)
         .args(["i", "-g", "@openai/codex@latest"])
-        .creation_flags(CREATE_NO_WINDOW)
+        .hide_window()
         .stdin(Stdio::null())
         .stdout(Stdio::piped())
         .stderr(Stdio::piped())
@@ -169,7 +477,7 @@ fn run_codex_install(tx: &mpsc::Sender<AppEvent>, ctx: &egui::Context) -> Result
 fn read_child_output(
     child: &mut std::process::Child,
     tx: &mpsc::Sender<AppEvent>,
-    ctx: &egui::Context,
+    ctx: &RepaintHandle,
 ) {
     let stdout = child.stdout.take();
     let stderr = child.stderr.take();
@@ -183,14 +491,14 @@ fn read_child_output(
     if let Some(err) = stderr {
         forward_child_output(io::BufReader::new(err), "stderr", tx, ctx);
     }
-    if let Some(handle) = stdout_handle {
-        if handle.join().is_err() {
-            logging::error("installer stdout reader thread panicked");
-        }
+    if let Some(handle) = stdout_handle
+        && handle.join().is_err()
+    {
+        logging::error("installer stdout reader thread panicked");
     }
 }
 
-fn send_install_output(tx: &mpsc::Sender<AppEvent>, ctx: &egui::Context, line: String) {
+fn send_install_output(tx: &mpsc::Sender<AppEvent>, ctx: &RepaintHandle, line: String) {
     if tx.send(AppEvent::CodexInstallOutput(line)).is_err() {
         logging::error("failed to deliver install output to app");
     }
@@ -201,7 +509,7 @@ fn forward_child_output<R: BufRead>(
     reader: R,
     stream_name: &str,
     tx: &mpsc::Sender<AppEvent>,
-    ctx: &egui::Context,
+    ctx: &RepaintHandle,
 ) {
     for line in reader.lines() {
         match line {
@@ -217,13 +525,13 @@ fn forward_child_output<R: BufRead>(
     }
 }
 
-pub(crate) fn has_node() -> bool {
+pub fn has_node() -> bool {
     if node_path().is_some() {
         return true;
     }
     Command::new("node")
         .arg("--version")
-        .creation_flags(CREATE_NO_WINDOW)
+        .hide_window()
         .stdin(Stdio::null())
         .stdout(Stdio::null())
         .stderr(Stdio::null())
@@ -231,35 +539,56 @@ pub(crate) fn has_node() -> bool {
         .is_ok_and(|s| s.success())
 }
 
+#[cfg(unix)]
 fn find_npm() -> PathBuf {
-    if let Some(pf) = env::var_os("ProgramFiles") {
-        let path = PathBuf::from(&pf).join("nodejs").join("npm.cmd");
-        if path.exists() {
-            return path;
-        }
-    }
-    if let Some(local) = env::var_os("LOCALAPPDATA") {
-        let path = PathBuf::from(local)
-            .join("Programs")
-            .join("nodejs")
-            .join("npm.cmd");
-        if path.exists() {
-            return path;
-        }
-    }
-    if let Some(appdata) = env::var_os("APPDATA") {
-        let path = PathBuf::from(&appdata).join("npm").join("npm.cmd");
-        if path.exists() {
-            return path;
-        }
-    }
-    PathBuf::from("npm")
+    unix_tool_path("npm").unwrap_or_else(|| PathBuf::from("npm"))
 }
 
+#[cfg(unix)]
 fn codex_script_path() -> Option<PathBuf> {
-    let appdata = env::var_os("APPDATA")?;
-    let path = PathBuf::from(appdata)
-        .join("npm")
+    None
+}
+
+#[cfg(unix)]
+fn node_path() -> Option<PathBuf> {
+    None
+}
+
+#[cfg(unix)]
+fn codex_cmd_path() -> Option<PathBuf> {
+    unix_tool_path("codex")
+}
+
+#[cfg(unix)]
+fn unix_tool_path(name: &str) -> Option<PathBuf> {
+    let home = env::var_os("HOME").filter(|home| !home.is_empty());
+    unix_tool_dirs(home.map(PathBuf::from))
+        .into_iter()
+        .map(|dir| dir.join(name))
+        .find(|path| path.is_file())
+}
+
+#[cfg(unix)]
+fn unix_tool_dirs(home: Option<PathBuf>) -> Vec<PathBuf> {
+    home.map(|home| home.join(".npm-global").join("bin"))
+        .into_iter()
+        .chain([PathBuf::from("/usr/local/bin")])
+        .collect()
+}
+
+#[cfg(windows)]
+fn find_npm() -> PathBuf {
+    node_dirs()
+        .into_iter()
+        .chain(npm_prefix())
+        .map(|dir| dir.join("npm.cmd"))
+        .find(|path| path.exists())
+        .unwrap_or_else(|| PathBuf::from("npm"))
+}
+
+#[cfg(windows)]
+fn codex_script_path() -> Option<PathBuf> {
+    let path = npm_prefix()?
         .join("node_modules")
         .join("@openai")
         .join("codex")
@@ -268,52 +597,685 @@ fn codex_script_path() -> Option<PathBuf> {
     path.exists().then_some(path)
 }
 
+#[cfg(windows)]
 fn node_path() -> Option<PathBuf> {
-    let appdata = env::var_os("APPDATA")
-        .map(PathBuf::from)
-        .map(|path| path.join("npm").join("node.exe"));
-    if let Some(path) = appdata.filter(|path| path.exists()) {
-        return Some(path);
+    npm_prefix()
+        .into_iter()
+        .chain(node_dirs())
+        .map(|dir| dir.join("node.exe"))
+        .find(|path| path.exists())
+}
+
+#[cfg(windows)]
+fn codex_cmd_path() -> Option<PathBuf> {
+    let path = npm_prefix()?.join("codex.cmd");
+    path.exists().then_some(path)
+}
+
+fn codex_launcher() -> CodexLauncher {
+    {
+        let cached = CODEX_LAUNCHER.read().unwrap_or_else(|e| e.into_inner());
+        if let Some(launcher) = cached.as_ref().filter(|launcher| launcher.still_present()) {
+            return launcher.clone();
+        }
+    }
+    let launcher = probe_codex_launcher();
+    logging::trace(format!("resolved codex launcher: {}", launcher.describe()));
+    *CODEX_LAUNCHER.write().unwrap_or_else(|e| e.into_inner()) = Some(launcher.clone());
+    launcher
+}
+
+pub fn set_codex_profile(profile: Option<String>) {
+    logging::trace(format!(
+        "codex profile set to {}",
+        profile.as_deref().unwrap_or("the config default")
+    ));
+    *CODEX_PROFILE.write().unwrap_or_else(|e| e.into_inner()) = profile;
+}
+
+fn codex_profile() -> Option<String> {
+    CODEX_PROFILE
+        .read()
+        .unwrap_or_else(|e| e.into_inner())
+        .clone()
+}
+
+fn invalidate_codex_launcher() {
+    *CODEX_LAUNCHER.write().unwrap_or_else(|e| e.into_inner()) = None;
+}
+
+pub fn redetect_codex() -> String {
+    invalidate_codex_launcher();
+    codex_launcher().describe()
+}
+
+pub(super) fn codex_launch_key(
+    session_id: Option<&str>,
+    project: Option<&ProjectConfig>,
+    env_overrides: &[(String, String)],
+) -> Vec<String> {
+    let profile = codex_profile();
+    let mut key = vec![codex_launcher().describe(), current_cwd_text()];
+    key.extend(
+        codex_args(STDIN_PROMPT, session_id, project, profile.as_deref())
+            .into_iter()
+            .map(str::to_owned),
+    );
+    key.extend(
+        env_overrides
+            .iter()
+            .map(|(name, value)| format!("{}={}", name, value)),
+    );
+    key
+}
+
+pub(super) fn spawn_codex(
+    prompt: &str,
+    session_id: Option<&str>,
+    project: Option<&ProjectConfig>,
+    env_overrides: &[(String, String)],
+) -> io::Result<Child> {
+    spawn_codex_process(prompt, session_id, project, env_overrides, Stdio::null)
+}
+
+pub(super) fn spawn_codex_reading_stdin(
+    session_id: Option<&str>,
+    project: Option<&ProjectConfig>,
+    env_overrides: &[(String, String)],
+) -> io::Result<Child> {
+    spawn_codex_process(
+        STDIN_PROMPT,
+        session_id,
+        project,
+        env_overrides,
+        Stdio::piped,
+    )
+}
+
+fn spawn_codex_process(
+    prompt: &str,
+    session_id: Option<&str>,
+    project: Option<&ProjectConfig>,
+    env_overrides: &[(String, String)],
+    stdin: fn() -> Stdio,
+) -> io::Result<Child> {
+    let spawn = || -> io::Result<Child> {
+        let mut command = build_codex_command(prompt, session_id, project, env_overrides);
+        let child = command
+            .hide_window()
+            .own_process_group()
+            .stdin(stdin())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()?;
+        #[cfg(windows)]
+        {
+            keep_pipes_private(&child);
+            super::wsl::track_wsl_process(child.id(), &command);
+        }
+        Ok(child)
+    };
+    match spawn() {
+        Err(error) if error.kind() == io::ErrorKind::NotFound => {
+            logging::error(format!(
+                "codex launcher failed to start ({}); probing again",
+                error
+            ));
+            invalidate_codex_launcher();
+            spawn()
+        }
+        result => result,
     }
+}
 
-    let program_files = env::var_os("ProgramFiles")
-        .map(PathBuf::from)
-        .map(|path| path.join("nodejs").join("node.exe"));
-    if let Some(path) = program_files.filter(|path| path.exists()) {
-        return Some(path);
+#[cfg(windows)]
+fn keep_pipes_private(child: &Child) {
+    let handles = [
+        child.stdin.as_ref().map(AsRawHandle::as_raw_handle),
+        child.stdout.as_ref().map(AsRawHandle::as_raw_handle),
+        child.stderr.as_ref().map(AsRawHandle::as_raw_handle),
+    ];
+    for handle in handles.into_iter().flatten() {
+        if unsafe { SetHandleInformation(handle, HANDLE_FLAG_INHERIT, 0) } == 0 {
+            logging::error(format!(
+                "failed to mark codex pipe as non-inheritable: {}",
+                io::Error::last_os_error()
+            ));
+        }
     }
+}
 
-    let local_programs = env::var_os("LOCALAPPDATA")
-        .map(PathBuf::from)
-        .map(|path| path.join("Programs").join("nodejs").join("node.exe"));
-    local_programs.filter(|path| path.exists())
+impl CodexLauncher {
+    fn still_present(&self) -> bool {
+        match self {
+            #[cfg(windows)]
+            CodexLauncher::Node { node, script } => {
+                script.exists() && (node.is_relative() || node.exists())
+            }
+            #[cfg(windows)]
+            CodexLauncher::Cmd(codex_cmd) => codex_cmd.exists(),
+            #[cfg(windows)]
+            CodexLauncher::Wsl { .. } => true,
+            #[cfg(unix)]
+            CodexLauncher::Binary(codex) => codex.exists(),
+            CodexLauncher::Direct => true,
+        }
+    }
+
+    fn describe(&self) -> String {
+        match self {
+            #[cfg(windows)]
+            CodexLauncher::Node { node, script } => {
+                format!("{} via {}", script.display(), node.display())
+            }
+            #[cfg(windows)]
+            CodexLauncher::Cmd(codex_cmd) => format!("{} via cmd.exe", codex_cmd.display()),
+            #[cfg(windows)]
+            CodexLauncher::Wsl { distro: None } => "codex in the default WSL distro".to_owned(),
+            #[cfg(windows)]
+            CodexLauncher::Wsl {
+                distro: Some(distro),
+            } => format!("codex in WSL ({})", distro),
+            #[cfg(unix)]
+            CodexLauncher::Binary(codex) => codex.display().to_string(),
+            CodexLauncher::Direct => "codex on PATH".to_owned(),
+        }
+    }
 }
 
-fn codex_cmd_path() -> Option<PathBuf> {
-    let appdata = env::var_os("APPDATA")?;
-    let path = PathBuf::from(appdata).join("npm").join("codex.cmd");
-    path.exists().then_some(path)
+#[cfg(unix)]
+fn probe_codex_launcher() -> CodexLauncher {
+    codex_cmd_path().map_or(CodexLauncher::Direct, CodexLauncher::Binary)
 }
 
-fn codex_launcher() -> CodexLauncher {
+#[cfg(windows)]
+fn probe_codex_launcher() -> CodexLauncher {
+    let mode = load_launcher_mode().unwrap_or_else(|error| {
+        logging::error(format!("failed to load codex launcher setting: {}", error));
+        DEFAULT_LAUNCHER_MODE
+    });
+    if let LauncherMode::Wsl(distro) = mode {
+        return CodexLauncher::Wsl { distro };
+    }
     if let (Some(node), Some(script)) = (node_path(), codex_script_path()) {
         return CodexLauncher::Node { node, script };
     }
     if let Some(codex_cmd) = codex_cmd_path() {
+        if let Some(script) = shim_script_path(&codex_cmd) {
+            let node = codex_cmd
+                .parent()
+                .map(|dir| dir.join("node.exe"))
+                .filter(|path| path.exists())
+                .or_else(node_path)
+                .unwrap_or_else(|| PathBuf::from("node"));
+            return CodexLauncher::Node { node, script };
+        }
         return CodexLauncher::Cmd(codex_cmd);
     }
     CodexLauncher::Direct
 }
 
-fn append_codex_args(command: &mut Command, prompt: &str, session_id: Option<&str>) {
-    command.arg("exec");
+#[cfg(windows)]
+fn shim_script_path(codex_cmd: &Path) -> Option<PathBuf> {
+    let contents = match fs::read_to_string(codex_cmd) {
+        Ok(contents) => contents,
+        Err(error) => {
+            logging::error(format!(
+                "failed to read codex shim {}: {}",
+                codex_cmd.display(),
+                error
+            ));
+            return None;
+        }
+    };
+    let dir = codex_cmd.parent()?;
+    contents
+        .split('"')
+        .filter_map(|part| {
+            part.strip_prefix("%dp0%\\")
+                .or_else(|| part.strip_prefix("%~dp0\\"))
+        })
+        .filter(|relative| relative.ends_with(".js"))
+        .map(|relative| dir.join(relative))
+        .find(|path| path.exists())
+}
+
+const APPROVAL_POLICY_OVERRIDES: [(&str, &str); 4] = [
+    ("never", "approval_policy=\"never\""),
+    ("on-request", "approval_policy=\"on-request\""),
+    ("on-failure", "approval_policy=\"on-failure\""),
+    ("untrusted", "approval_policy=\"untrusted\""),
+];
+
+fn approval_policy_override(policy: &str) -> Option<&'static str> {
+    let policy = policy.trim();
+    let found = APPROVAL_POLICY_OVERRIDES
+        .iter()
+        .find(|(name, _)| *name == policy)
+        .map(|(_, value)| *value);
+    if found.is_none() {
+        logging::error(format!("ignoring unknown approval policy '{}'", policy));
+    }
+    found
+}
+
+fn codex_args<'a>(
+    prompt: &'a str,
+    session_id: Option<&'a str>,
+    project: Option<&'a ProjectConfig>,
+    profile: Option<&'a str>,
+) -> Vec<&'a str> {
+    let mut args = vec!["exec"];
     if session_id.is_some() {
-        command.arg("resume");
+        args.push("resume");
+    }
+    args.push("--skip-git-repo-check");
+    args.push("--json");
+    if let Some(profile) = profile {
+        args.push("--profile");
+        args.push(profile);
+    }
+    if let Some(model) = project.and_then(|project| project.model.as_deref()) {
+        args.push("--model");
+        args.push(model);
+    }
+    if let Some(sandbox) = project.and_then(|project| project.sandbox_mode.as_deref()) {
+        args.push("--sandbox");
+        args.push(sandbox);
     }
-    command.arg("--skip-git-repo-check");
-    command.arg("--json");
+    if let Some(policy) = project
+        .and_then(|project| project.approval_policy.as_deref())
+        .and_then(approval_policy_override)
+    {
+        args.push("-c");
+        args.push(policy);
+    }
+    args.push("--");
     if let Some(sid) = session_id {
-        command.arg(sid);
+        args.push(sid);
+    }
+    args.push(prompt);
+    args
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pairs(entries: &[(&str, &str)]) -> Vec<(String, String)> {
+        entries
+            .iter()
+            .map(|&(name, value)| (name.to_owned(), value.to_owned()))
+            .collect()
+    }
+
+    fn command_env(command: &Command) -> Vec<(String, Option<String>)> {
+        let mut envs: Vec<_> = command
+            .get_envs()
+            .map(|(name, value)| {
+                (
+                    name.to_string_lossy().into_owned(),
+                    value.map(|value| value.to_string_lossy().into_owned()),
+                )
+            })
+            .collect();
+        envs.sort();
+        envs
+    }
+
+    #[test]
+    fn settings_overrides_pass_through_sorted() {
+        let merged =
+            merge_env_overrides(pairs(&[("RUST_LOG", "debug"), ("HTTPS_PROXY", "p")]), None);
+        assert_eq!(
+            merged,
+            pairs(&[("HTTPS_PROXY", "p"), ("RUST_LOG", "debug")])
+        );
+    }
+
+    #[test]
+    fn project_overrides_replace_settings() {
+        let project = BTreeMap::from([
+            ("RUST_LOG".to_owned(), "trace".to_owned()),
+            ("PROJECT_ONLY".to_owned(), "1".to_owned()),
+        ]);
+        let merged = merge_env_overrides(pairs(&[("RUST_LOG", "debug")]), Some(&project));
+        assert_eq!(
+            merged,
+            pairs(&[("PROJECT_ONLY", "1"), ("RUST_LOG", "trace")])
+        );
+    }
+
+    #[test]
+    fn a_project_can_remove_a_settings_variable() {
+        let project = BTreeMap::from([("HTTPS_PROXY".to_owned(), String::new())]);
+        let merged = merge_env_overrides(pairs(&[("HTTPS_PROXY", "p")]), Some(&project));
+        assert_eq!(merged, pairs(&[("HTTPS_PROXY", "")]));
+    }
+
+    #[test]
+    fn command_env_adds_overrides_and_removes_empty_values() {
+        let mut command = Command::new("codex");
+        command.env("RUST_LOG", "info");
+        apply_env_overrides(
+            &mut command,
+            &pairs(&[("HTTPS_PROXY", "p"), ("RUST_LOG", "trace"), ("TERM", "")]),
+        );
+        assert_eq!(
+            command_env(&command),
+            [
+                ("HTTPS_PROXY".to_owned(), Some("p".to_owned())),
+                ("RUST_LOG".to_owned(), Some("trace".to_owned())),
+                ("TERM".to_owned(), None),
+            ]
+        );
+    }
+
+    #[test]
+    fn describing_overrides_redacts_secrets() {
+        assert_eq!(
+            describe_env_overrides(&pairs(&[
+                ("api_key", "abc"),
+                ("RUST_LOG", "debug"),
+                ("TERM", "")
+            ])),
+            "api_key=<redacted>, RUST_LOG=debug, unset TERM"
+        );
+    }
+
+    fn program_and_args(command: &Command) -> (String, Vec<String>) {
+        (
+            command.get_program().to_string_lossy().into_owned(),
+            command
+                .get_args()
+                .map(|arg| arg.to_string_lossy().into_owned())
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn direct_launcher_runs_codex_from_path() {
+        let command = launcher_command(CodexLauncher::Direct, &["exec", "--json"]);
+        assert_eq!(
+            program_and_args(&command),
+            (
+                "codex".to_owned(),
+                vec!["exec".to_owned(), "--json".to_owned()]
+            )
+        );
+        assert!(CodexLauncher::Direct.still_present());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn binary_launcher_runs_the_resolved_path() {
+        let codex = PathBuf::from("/opt/codex/bin/codex");
+        let command = launcher_command(CodexLauncher::Binary(codex.clone()), &["--version"]);
+        assert_eq!(
+            program_and_args(&command),
+            (
+                "/opt/codex/bin/codex".to_owned(),
+                vec!["--version".to_owned()]
+            )
+        );
+        assert_eq!(
+            CodexLauncher::Binary(codex).describe(),
+            "/opt/codex/bin/codex"
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn missing_binary_is_not_present() {
+        let launcher = CodexLauncher::Binary(PathBuf::from("/nonexistent/codexagent/codex"));
+        assert!(!launcher.still_present());
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn node_launcher_passes_the_script_first() {
+        let launcher = CodexLauncher::Node {
+            node: PathBuf::from("node.exe"),
+            script: PathBuf::from(r"C:\npm\codex.js"),
+        };
+        let command = launcher_command(launcher, &["exec"]);
+        assert_eq!(
+            program_and_args(&command),
+            (
+                "node.exe".to_owned(),
+                vec![r"C:\npm\codex.js".to_owned(), "exec".to_owned()]
+            )
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn npm_global_is_probed_before_usr_local() {
+        assert_eq!(
+            unix_tool_dirs(Some(PathBuf::from("/home/dev"))),
+            vec![
+                PathBuf::from("/home/dev/.npm-global/bin"),
+                PathBuf::from("/usr/local/bin")
+            ]
+        );
+        assert_eq!(unix_tool_dirs(None), vec![PathBuf::from("/usr/local/bin")]);
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn cmd_launcher_quotes_every_argument() {
+        assert_eq!(
+            cmd_command_line(
+                Path::new(r"C:\npm\codex.cmd"),
+                &["exec", "say \"hi\"", r"C:\dir\"]
+            ),
+            r#"/S /C ""C:\npm\codex.cmd" "exec" "say ""hi""" "C:\dir\\""#
+        );
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn cmd_launcher_keeps_paths_with_spaces_whole() {
+        assert_eq!(
+            cmd_command_line(
+                Path::new(r"C:\Users\Jane Doe\AppData\Roaming\npm\codex.cmd"),
+                &["exec", "--", "fix \"a b\""]
+            ),
+            r#"/S /C ""C:\Users\Jane Doe\AppData\Roaming\npm\codex.cmd" "exec" "--" "fix ""a b""""#
+        );
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn npm_shim_points_at_its_node_script() {
+        let dir = std::env::temp_dir().join(format!("codexagent shim {}", std::process::id()));
+        let script = dir.join(r"node_modules\@openai\codex\bin\codex.js");
+        fs::create_dir_all(script.parent().unwrap()).unwrap();
+        fs::write(&script, "").unwrap();
+        let shim = dir.join("codex.cmd");
+        fs::write(
+            &shim,
+            "@ECHO off\r\nSET dp0=%~dp0\r\n\
+             \"%_prog%\"  \"%dp0%\\node_modules\\@openai\\codex\\bin\\codex.js\" %*\r\n",
+        )
+        .unwrap();
+        assert_eq!(shim_script_path(&shim), Some(script));
+
+        fs::write(&shim, "@ECHO off\r\ncodex.exe %*\r\n").unwrap();
+        assert_eq!(shim_script_path(&shim), None);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    fn assert_describes_the_spawned_command(launcher: CodexLauncher) {
+        let prompt = "Explain why the parser rejects -- in quoted strings, then fix it and add \
+                      a regression test next to the existing tokenizer tests.";
+        let mut command = launcher_command(
+            launcher.clone(),
+            &codex_args(prompt, Some("thread-1"), None, None),
+        );
+        let overrides = [
+            ("CODEX_LOG".to_owned(), "debug".to_owned()),
+            ("HTTP_PROXY".to_owned(), String::new()),
+            ("OPENAI_API_KEY".to_owned(), "sk-secret".to_owned()),
+        ];
+        apply_env_overrides(&mut command, &overrides);
+        let described = describe_command(&launcher, &command, prompt);
+        let lines: Vec<&str> = described.lines().collect();
+        let (program, args) = program_and_args(&command);
+
+        assert_eq!(lines[0], format!("launcher: {}", launcher.describe()));
+        assert_eq!(lines[1], format!("program:  {}", program));
+        let argv = &lines[2..lines.len() - 2];
+        assert_eq!(argv.len(), args.len());
+        let shown_prompt = format!("{}\u{2026}", &prompt[..DRY_RUN_PROMPT_CHARS]);
+        for (index, (line, arg)) in argv.iter().zip(&args).enumerate() {
+            let prefix = format!("argv[{}]:  ", index + 1);
+            let shown = line.strip_prefix(&prefix).expect("numbered argv line");
+            assert_eq!(shown, arg.replace(prompt, &shown_prompt));
+        }
+        assert!(!described.contains(prompt));
+        assert_eq!(described.matches(&shown_prompt).count(), 1);
+
+        assert!(lines[lines.len() - 2].starts_with("cwd:      "));
+        let env = lines[lines.len() - 1];
+        assert!(env.contains("CODEX_LOG=debug"), "{}", env);
+        assert!(env.contains("unset HTTP_PROXY"), "{}", env);
+        assert!(env.contains("OPENAI_API_KEY=<redacted>"), "{}", env);
+        assert!(!described.contains("sk-secret"));
+    }
+
+    #[test]
+    fn dry_run_describes_the_direct_command() {
+        assert_describes_the_spawned_command(CodexLauncher::Direct);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn dry_run_describes_the_binary_command() {
+        assert_describes_the_spawned_command(CodexLauncher::Binary(PathBuf::from(
+            "/home/dev/.npm-global/bin/codex",
+        )));
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn dry_run_describes_the_node_command() {
+        assert_describes_the_spawned_command(CodexLauncher::Node {
+            node: PathBuf::from(r"C:\Program Files\nodejs\node.exe"),
+            script: PathBuf::from(r"C:\npm\node_modules\@openai\codex\bin\codex.js"),
+        });
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn dry_run_describes_the_cmd_command() {
+        assert_describes_the_spawned_command(CodexLauncher::Cmd(PathBuf::from(
+            r"C:\Users\Jane Doe\AppData\Roaming\npm\codex.cmd",
+        )));
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn dry_run_describes_the_wsl_command() {
+        assert_describes_the_spawned_command(CodexLauncher::Wsl {
+            distro: Some("Ubuntu".to_owned()),
+        });
+        assert_describes_the_spawned_command(CodexLauncher::Wsl { distro: None });
+    }
+
+    const DASHED_PROMPT: &str = "--model o3 -c \"x\" explain";
+
+    fn args_after_separator(args: &[String]) -> &[String] {
+        let separator = args
+            .iter()
+            .position(|arg| arg == "--")
+            .expect("-- separator");
+        &args[separator..]
+    }
+
+    #[test]
+    fn codex_args_end_options_before_the_session_and_prompt() {
+        let project = ProjectConfig {
+            model: Some("o3".to_owned()),
+            ..ProjectConfig::default()
+        };
+        let args = codex_args(DASHED_PROMPT, Some("thread-1"), Some(&project), None);
+        assert_eq!(args[..2], ["exec", "resume"]);
+        assert_eq!(args[args.len() - 3..], ["--", "thread-1", DASHED_PROMPT]);
+        assert_eq!(args.iter().filter(|arg| **arg == "--").count(), 1);
+
+        let args = codex_args("-v", None, None, None);
+        assert_eq!(args[args.len() - 2..], ["--", "-v"]);
+    }
+
+    #[test]
+    fn direct_launcher_passes_the_separator_through() {
+        let args = codex_args(DASHED_PROMPT, None, None, None);
+        let (_, passed) = program_and_args(&launcher_command(CodexLauncher::Direct, &args));
+        assert_eq!(passed, args);
+        assert_eq!(args_after_separator(&passed), ["--", DASHED_PROMPT]);
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn node_launcher_passes_the_separator_through() {
+        let launcher = CodexLauncher::Node {
+            node: PathBuf::from("node.exe"),
+            script: PathBuf::from(r"C:\npm\codex.js"),
+        };
+        let args = codex_args(DASHED_PROMPT, Some("thread-1"), None, None);
+        let (_, passed) = program_and_args(&launcher_command(launcher, &args));
+        assert_eq!(passed[1..], args);
+        assert_eq!(
+            args_after_separator(&passed),
+            ["--", "thread-1", DASHED_PROMPT]
+        );
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn cmd_launcher_quotes_the_separator_once() {
+        let args = codex_args(DASHED_PROMPT, None, None, None);
+        let line = cmd_command_line(Path::new(r"C:\npm\codex.cmd"), &args);
+        assert!(
+            line.ends_with(r##" "--" "--model o3 -c ""x"" explain"""##),
+            "{}",
+            line
+        );
+        assert_eq!(line.matches(r#""--""#).count(), 1);
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn wsl_launcher_passes_the_separator_through() {
+        let args = codex_args(DASHED_PROMPT, None, None, None);
+        let (program, passed) = program_and_args(&launcher_command(
+            CodexLauncher::Wsl {
+                distro: Some("Ubuntu".to_owned()),
+            },
+            &args,
+        ));
+        assert_eq!(program, "wsl.exe");
+        assert_eq!(passed[passed.len() - args.len()..], args);
+        assert_eq!(args_after_separator(&passed), ["--", DASHED_PROMPT]);
+    }
+
+    #[test]
+    fn selected_profile_is_passed_before_the_separator() {
+        assert!(!codex_args("hi", None, None, None).contains(&"--profile"));
+        assert_eq!(
+            codex_args("hi", Some("thread-1"), None, Some("deep")),
+            [
+                "exec",
+                "resume",
+                "--skip-git-repo-check",
+                "--json",
+                "--profile",
+                "deep",
+                "--",
+                "thread-1",
+                "hi"
+            ]
+        );
     }
-    command.arg(prompt);
 }
@@ -0,0 +1,254 @@
+use serde_json::{Deserializer, Value};
+
+use crate::logging;
+
+const MAX_CARRY_BYTES: usize = 4 * 1024 * 1024;
+const MAX_PREAMBLE_BYTES: usize = 4 * 1024;
+
+#[derive(Default)]
+pub(super) struct EventSplitter {
+    carry: String,
+    carry_started_on: usize,
+    started: bool,
+    preamble: String,
+    preamble_lines: usize,
+}
+
+impl EventSplitter {
+    pub(super) fn absorb_preamble(&mut self, line: &str, line_number: usize) -> bool {
+        if self.started || !self.carry.is_empty() || line.starts_with('{') {
+            return false;
+        }
+        logging::trace(format!(
+            "codex stdout line {} came before any JSON; keeping it as preamble: {}",
+            line_number, line
+        ));
+        self.preamble_lines += 1;
+        let separator = usize::from(!self.preamble.is_empty());
+        let room = MAX_PREAMBLE_BYTES.saturating_sub(self.preamble.len() + separator);
+        if room > 0 {
+            if separator > 0 {
+                self.preamble.push('\n');
+            }
+            let mut end = line.len().min(room);
+            while !line.is_char_boundary(end) {
+                end -= 1;
+            }
+            self.preamble.push_str(&line[..end]);
+        }
+        true
+    }
+
+    pub(super) fn started(&self) -> bool {
+        self.started
+    }
+
+    pub(super) fn preamble(&self) -> Option<&str> {
+        (!self.preamble.is_empty()).then_some(self.preamble.as_str())
+    }
+
+    pub(super) fn push_line(&mut self, line: &str, line_number: usize) -> Vec<Value> {
+        let joined;
+        let started_on = if self.carry.is_empty() {
+            line_number
+        } else {
+            self.carry_started_on
+        };
+        let text = if self.carry.is_empty() {
+            line
+        } else {
+            joined = std::mem::take(&mut self.carry) + line;
+            joined.as_str()
+        };
+        let mut events = Vec::new();
+        let mut stream = Deserializer::from_str(text).into_iter::<Value>();
+        loop {
+            let offset = stream.byte_offset();
+            match stream.next() {
+                None => break,
+                Some(Ok(event)) => {
+                    if !self.started && event.is_object() {
+                        self.started = true;
+                        if self.preamble_lines > 0 {
+                            logging::trace(format!(
+                                "codex JSON started on line {} after {} preamble lines",
+                                line_number, self.preamble_lines
+                            ));
+                        }
+                    }
+                    events.push(event);
+                }
+                Some(Err(error)) if error.is_eof() => {
+                    let rest = &text[offset..];
+                    if rest.len() > MAX_CARRY_BYTES {
+                        logging::error(format!(
+                            "codex stdout event starting on line {} is still incomplete after {} bytes; dropping it",
+                            started_on,
+                            rest.len()
+                        ));
+                    } else {
+                        self.carry_started_on = if offset > 0 { line_number } else { started_on };
+                        self.carry = rest.to_owned();
+                    }
+                    break;
+                }
+                Some(Err(error)) => {
+                    logging::error(format!(
+                        "skipping malformed codex stdout on line {}: {}; text: {}",
+                        line_number,
+                        error,
+                        &text[offset..]
+                    ));
+                    break;
+                }
+            }
+        }
+        if events.len() > 1 {
+            logging::trace(format!(
+                "codex stdout line {} held {} JSON events",
+                line_number,
+                events.len()
+            ));
+        }
+        events
+    }
+
+    pub(super) fn finish(&mut self) {
+        if self.carry.is_empty() {
+            return;
+        }
+        logging::error(format!(
+            "codex stdout ended inside a JSON event started on line {}; dropping {} bytes",
+            self.carry_started_on,
+            self.carry.len()
+        ));
+        self.carry.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn feed(splitter: &mut EventSplitter, lines: &[&str]) -> Vec<Value> {
+        let mut events = Vec::new();
+        for (index, line) in lines.iter().enumerate() {
+            if !splitter.absorb_preamble(line, index + 1) {
+                events.extend(splitter.push_line(line, index + 1));
+            }
+        }
+        events
+    }
+
+    #[test]
+    fn banner_lines_before_json_become_preamble() {
+        let mut splitter = EventSplitter::default();
+        let events = feed(
+            &mut splitter,
+            &[
+                "Debugger attached.",
+                "codex 0.9 (update available)",
+                r#"{"type":"thread.started","thread_id":"t1"}"#,
+                r#"{"type":"turn.completed"}"#,
+            ],
+        );
+        assert_eq!(events.len(), 2);
+        assert!(splitter.started());
+        assert_eq!(
+            splitter.preamble(),
+            Some("Debugger attached.\ncodex 0.9 (update available)")
+        );
+    }
+
+    #[test]
+    fn an_all_banner_stream_never_starts() {
+        let mut splitter = EventSplitter::default();
+        let events = feed(&mut splitter, &["Error: not logged in", "Run codex login"]);
+        assert!(events.is_empty());
+        assert!(!splitter.started());
+        assert_eq!(
+            splitter.preamble(),
+            Some("Error: not logged in\nRun codex login")
+        );
+    }
+
+    #[test]
+    fn garbage_after_json_starts_is_skipped_not_kept() {
+        let mut splitter = EventSplitter::default();
+        let events = feed(
+            &mut splitter,
+            &[
+                "banner",
+                r#"{"type":"thread.started"}"#,
+                "warning: not json",
+                r#"{"type":"turn.completed"}"#,
+            ],
+        );
+        assert_eq!(events.len(), 2);
+        assert_eq!(splitter.preamble(), Some("banner"));
+    }
+
+    #[test]
+    fn the_preamble_is_capped_on_a_char_boundary() {
+        let mut splitter = EventSplitter::default();
+        let line = "\u{00E9}".repeat(MAX_PREAMBLE_BYTES);
+        assert!(splitter.absorb_preamble(&line, 1));
+        assert!(splitter.absorb_preamble("more", 2));
+        let preamble = splitter.preamble().unwrap();
+        assert_eq!(preamble.len(), MAX_PREAMBLE_BYTES);
+        assert!(preamble.chars().all(|c| c == '\u{00E9}'));
+    }
+
+    fn types(events: &[Value]) -> Vec<&str> {
+        events
+            .iter()
+            .map(|event| event["type"].as_str().unwrap_or_default())
+            .collect()
+    }
+
+    #[test]
+    fn two_objects_on_one_line_are_both_parsed() {
+        let mut splitter = EventSplitter::default();
+        let events = feed(
+            &mut splitter,
+            &[r#"{"type":"thread.started","thread_id":"t1"}{"type":"turn.started"}"#],
+        );
+        assert_eq!(types(&events), ["thread.started", "turn.started"]);
+        assert_eq!(events[0]["thread_id"], "t1");
+    }
+
+    #[test]
+    fn a_split_line_is_joined_before_parsing() {
+        let mut splitter = EventSplitter::default();
+        let first = feed(&mut splitter, &[r#"{"type":"item.completed","item":{"te"#]);
+        assert!(first.is_empty());
+        let events = splitter.push_line(r#"xt":"hello"}}"#, 2);
+        assert_eq!(types(&events), ["item.completed"]);
+        assert_eq!(events[0]["item"]["text"], "hello");
+    }
+
+    #[test]
+    fn a_split_line_can_end_in_a_concatenated_object() {
+        let mut splitter = EventSplitter::default();
+        assert!(
+            splitter
+                .push_line(r#"{"type":"item.completed","item":{"text":"a"#, 1)
+                .is_empty()
+        );
+        let events = splitter.push_line(r#"b"}}{"type":"turn.completed"}{"type":"#, 2);
+        assert_eq!(types(&events), ["item.completed", "turn.completed"]);
+        assert_eq!(events[0]["item"]["text"], "ab");
+        let events = splitter.push_line(r#""error","message":"late"}"#, 3);
+        assert_eq!(types(&events), ["error"]);
+        assert_eq!(events[0]["message"], "late");
+    }
+
+    #[test]
+    fn an_unfinished_event_is_dropped_at_the_end() {
+        let mut splitter = EventSplitter::default();
+        assert!(splitter.push_line(r#"{"type":"turn"#, 1).is_empty());
+        splitter.finish();
+        let events = splitter.push_line(r#"{"type":"turn.completed"}"#, 2);
+        assert_eq!(types(&events), ["turn.completed"]);
+    }
+}
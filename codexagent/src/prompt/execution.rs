@@ -1,93 +1,366 @@
-use std::io::{self, BufRead, Read};
-use std::os::windows::process::CommandExt;
-use std::process::{Child, Command, Stdio};
+use std::borrow::Cow;
+use std::io::{self, BufRead};
+use std::process::{Child, Command, ExitStatus};
 use std::sync::{
     Arc, Mutex,
     atomic::{AtomicBool, Ordering},
     mpsc,
 };
 use std::thread;
+use std::time::{Duration, Instant};
 
-use eframe::egui;
 use serde_json::Value;
+#[cfg(windows)]
 use windows_sys::Win32::System::Power::{
-    ES_CONTINUOUS, ES_DISPLAY_REQUIRED, SetThreadExecutionState,
+    ES_CONTINUOUS, ES_DISPLAY_REQUIRED, ES_SYSTEM_REQUIRED, SetThreadExecutionState,
 };
-use windows_sys::Win32::System::Threading::CREATE_NO_WINDOW;
 
-use crate::config::CANCELLED_TEXT;
-use crate::events::AppEvent;
+use crate::config::project::ProjectConfig;
+use crate::config::{
+    DEFAULT_REASONING_RETENTION_LIMIT, DEFAULT_REASONING_WINDOW, DEFAULT_STREAM_UPDATE_INTERVAL,
+    DEFAULT_WAKE_MODE, PromptBudget, WakeMode, load_prompt_budget, load_reasoning_retention_limit,
+    load_reasoning_window, load_record_events, load_stream_update_interval, load_wake_mode,
+};
+use crate::events::{AppEvent, RepaintHandle};
 use crate::logging;
-use crate::runtime::current_cwd_text;
+#[cfg(windows)]
+use crate::runtime::BackgroundCommand;
+#[cfg(windows)]
+use crate::runtime::display_wake_paused;
+use crate::runtime::{
+    current_cwd_text, ensure_codex_files_cached, join_within, record_running_process, wait_within,
+};
+use crate::usage::TokenUsage;
+
+use super::approval::{ApprovalRequest, approval_request};
+use super::approved_command::{approved_command_prompt, run_approved_command};
+use super::budget::{BudgetTracker, BudgetWatchdog};
+use super::buffers::{
+    ResponseBuffers, collect_changed_files, collect_response_text, reports_length_stop,
+    sanitize_external_text,
+};
+use super::codex::{codex_env_overrides, describe_env_overrides, spawn_codex};
+use super::event_stream::EventSplitter;
+use super::failure::{PromptFailure, classify_failure, is_resume_failure};
+use super::inspector::EventLog;
+use super::recording::EventRecorder;
+use super::state::{
+    PROCESS_CLEANUP_TIMEOUT, PromptPhase, PromptProcessExt, PromptProcessGuard, PromptStreamState,
+    RunningPrompt, RunningPromptGuard, RunningPrompts,
+};
+use super::tool_step::{FailedToolStep, track_tool_step};
+use super::warm::take_warm_codex;
+
+const PREAMBLE_NOTICE: &str = "codex printed plain text instead of JSON events:";
 
-use super::buffers::{ResponseBuffers, collect_response_text};
-use super::codex::build_codex_command;
-use super::state::{PromptProcessGuard, PromptStreamState, RunningPrompt, RunningPromptGuard};
+#[cfg(windows)]
+const WAKE_POLL_INTERVAL: Duration = Duration::from_secs(1);
 
-struct DisplayWakeGuard {
-    active: bool,
+#[cfg(windows)]
+struct WakeGuard {
+    stop: Option<mpsc::Sender<()>>,
+    thread: Option<thread::JoinHandle<()>>,
 }
 
-impl DisplayWakeGuard {
-    fn enable() -> Self {
-        let state = unsafe { SetThreadExecutionState(ES_CONTINUOUS | ES_DISPLAY_REQUIRED) };
-        if state == 0 {
-            logging::error("SetThreadExecutionState failed to set display wake lock");
+#[cfg(windows)]
+impl WakeGuard {
+    fn enable(mode: WakeMode) -> Self {
+        if mode == WakeMode::Off {
+            logging::trace("keep awake off for this prompt");
+            return Self {
+                stop: None,
+                thread: None,
+            };
+        }
+        let (stop, stopped) = mpsc::channel();
+        let thread = thread::spawn(move || {
+            let _ = logging::catch_panic("wake lock thread", || hold_wake_lock(mode, &stopped));
+        });
+        Self {
+            stop: Some(stop),
+            thread: Some(thread),
         }
-        Self { active: state != 0 }
     }
 }
 
-impl Drop for DisplayWakeGuard {
+#[cfg(windows)]
+impl Drop for WakeGuard {
     fn drop(&mut self) {
-        if self.active {
-            let state = unsafe { SetThreadExecutionState(ES_CONTINUOUS) };
-            if state == 0 {
-                logging::error("SetThreadExecutionState failed to clear display wake lock");
-            }
+        drop(self.stop.take());
+        if let Some(thread) = self.thread.take()
+            && thread.join().is_err()
+        {
+            logging::error("wake lock thread panicked");
+        }
+    }
+}
+
+#[cfg(windows)]
+fn wake_flags(mode: WakeMode, display_paused: bool) -> u32 {
+    match mode {
+        WakeMode::SystemAndDisplay if !display_paused => {
+            ES_CONTINUOUS | ES_SYSTEM_REQUIRED | ES_DISPLAY_REQUIRED
+        }
+        _ => ES_CONTINUOUS | ES_SYSTEM_REQUIRED,
+    }
+}
+
+#[cfg(windows)]
+fn hold_wake_lock(mode: WakeMode, stopped: &mpsc::Receiver<()>) {
+    let mut display_paused = display_wake_paused();
+    if unsafe { SetThreadExecutionState(wake_flags(mode, display_paused)) } == 0 {
+        logging::error("SetThreadExecutionState failed to set wake lock");
+        return;
+    }
+    logging::trace(format!("keep awake: {}", mode.label()));
+    while let Err(mpsc::RecvTimeoutError::Timeout) = stopped.recv_timeout(WAKE_POLL_INTERVAL) {
+        let paused = display_wake_paused();
+        if mode != WakeMode::SystemAndDisplay || paused == display_paused {
+            continue;
+        }
+        display_paused = paused;
+        logging::trace(if paused {
+            "pausing display wake while the system sleeps"
+        } else {
+            "resuming display wake"
+        });
+        if unsafe { SetThreadExecutionState(wake_flags(mode, paused)) } == 0 {
+            logging::error("SetThreadExecutionState failed to update wake lock");
+        }
+    }
+    if unsafe { SetThreadExecutionState(ES_CONTINUOUS) } == 0 {
+        logging::error("SetThreadExecutionState failed to clear wake lock");
+    }
+}
+
+#[cfg(not(windows))]
+struct WakeGuard;
+
+#[cfg(not(windows))]
+impl WakeGuard {
+    fn enable(mode: WakeMode) -> Self {
+        if mode != WakeMode::Off {
+            logging::trace("keep awake is not supported on this platform");
         }
+        Self
     }
 }
 
-pub(crate) fn prompt_codex(
+/// What one codex run produced.
+pub struct PromptOutput {
+    pub response: String,
+    pub session_id: Option<String>,
+    pub usage: Option<TokenUsage>,
+    pub suggestions: Vec<String>,
+    /// codex stopped because the model ran out of output tokens.
+    pub length_stopped: bool,
+    /// Set when the prompt budget stopped the run, with a summary of why.
+    pub budget_stop: Option<String>,
+    pub changed_files: Vec<String>,
+    /// codex stopped to ask before running a command or applying a patch.
+    pub approval: Option<Box<ApprovalRequest>>,
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn prompt_codex(
     prompt_id: u64,
     prompt: String,
     session_id: Option<String>,
-    running_prompt: Arc<Mutex<Option<RunningPrompt>>>,
+    project: Option<ProjectConfig>,
+    approved: Option<ApprovalRequest>,
+    running_prompt: Arc<Mutex<RunningPrompts>>,
     shared_stream: Arc<Mutex<PromptStreamState>>,
     stream_notification_pending: Arc<AtomicBool>,
+    event_log: Arc<Mutex<EventLog>>,
+    tx: &mpsc::Sender<AppEvent>,
+    ctx: &RepaintHandle,
+) -> io::Result<PromptOutput> {
+    ensure_codex_files_cached().map_err(|error| {
+        io::Error::new(
+            error.kind(),
+            format!("Failed to prepare codex files: {}", error),
+        )
+    })?;
+    let wake_mode = load_wake_mode().unwrap_or_else(|error| {
+        logging::error(format!("failed to load keep awake setting: {}", error));
+        DEFAULT_WAKE_MODE
+    });
+    let _wake = WakeGuard::enable(wake_mode);
+    let update_interval = load_stream_update_interval().unwrap_or_else(|error| {
+        logging::error(format!("failed to load stream update interval: {}", error));
+        DEFAULT_STREAM_UPDATE_INTERVAL
+    });
+    let record_events = load_record_events().unwrap_or_else(|error| {
+        logging::error(format!("failed to load event recording setting: {}", error));
+        false
+    });
+    let reasoning_window = load_reasoning_window().unwrap_or_else(|error| {
+        logging::error(format!(
+            "failed to load reasoning window setting: {}",
+            error
+        ));
+        DEFAULT_REASONING_WINDOW
+    });
+    let reasoning_retention = load_reasoning_retention_limit().unwrap_or_else(|error| {
+        logging::error(format!(
+            "failed to load reasoning retention setting: {}",
+            error
+        ));
+        DEFAULT_REASONING_RETENTION_LIMIT
+    });
+    let budget = load_prompt_budget().unwrap_or_else(|error| {
+        logging::error(format!("failed to load prompt budget: {}", error));
+        PromptBudget::default()
+    });
+    if !budget.is_unlimited() {
+        logging::trace(format!("prompt budget: {}", budget.describe()));
+    }
+    let prompt = match approved.and_then(|request| request.command) {
+        Some(line) => {
+            let run = run_approved_command(prompt_id, &line, &running_prompt, &shared_stream)?;
+            approved_command_prompt(&prompt, &run)
+        }
+        None => prompt,
+    };
+    let resuming = session_id.is_some();
+    let result = run_codex_exec(
+        prompt_id,
+        &prompt,
+        session_id,
+        project.as_ref(),
+        &running_prompt,
+        &shared_stream,
+        &stream_notification_pending,
+        &event_log,
+        update_interval,
+        record_events,
+        reasoning_window,
+        reasoning_retention,
+        budget,
+        tx,
+        ctx,
+    );
+    let error = match result {
+        Err(error) if resuming && is_resume_failure(&error.to_string()) => error,
+        result => return result,
+    };
+    logging::error(format!(
+        "codex could not resume the stored session; retrying without it: {}",
+        error
+    ));
+    shared_stream
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .start(prompt_id);
+    if tx.send(AppEvent::SessionResumeFailed(prompt_id)).is_err() {
+        logging::error("failed to deliver session resume failure to app");
+    }
+    ctx.request_repaint();
+    run_codex_exec(
+        prompt_id,
+        &prompt,
+        None,
+        project.as_ref(),
+        &running_prompt,
+        &shared_stream,
+        &stream_notification_pending,
+        &event_log,
+        update_interval,
+        record_events,
+        reasoning_window,
+        reasoning_retention,
+        budget,
+        tx,
+        ctx,
+    )
+}
+
+#[allow(clippy::too_many_arguments)]
+fn run_codex_exec(
+    prompt_id: u64,
+    prompt: &str,
+    session_id: Option<String>,
+    project: Option<&ProjectConfig>,
+    running_prompt: &Arc<Mutex<RunningPrompts>>,
+    shared_stream: &Mutex<PromptStreamState>,
+    stream_notification_pending: &AtomicBool,
+    event_log: &Mutex<EventLog>,
+    update_interval: Duration,
+    record_events: bool,
+    reasoning_window: usize,
+    reasoning_retention: usize,
+    budget: PromptBudget,
     tx: &mpsc::Sender<AppEvent>,
-    ctx: &egui::Context,
-) -> io::Result<(String, Option<String>)> {
-    let _display_wake = DisplayWakeGuard::enable();
+    ctx: &RepaintHandle,
+) -> io::Result<PromptOutput> {
+    let env_overrides = codex_env_overrides(project);
+    let env_text = if env_overrides.is_empty() {
+        String::new()
+    } else {
+        format!(" and env {}", describe_env_overrides(&env_overrides))
+    };
     logging::trace(format!(
-        "starting codex exec from {} with {} chars",
+        "starting codex exec from {} with {} chars{}",
         current_cwd_text(),
-        prompt.chars().count()
+        prompt.chars().count(),
+        env_text
     ));
-    let child = build_codex_command(&prompt, session_id.as_deref())
-        .creation_flags(CREATE_NO_WINDOW)
-        .stdin(Stdio::null())
-        .stdout(Stdio::piped())
-        .stderr(Stdio::piped())
-        .spawn()?;
+    let (child, warm_start) =
+        match take_warm_codex(prompt, session_id.as_deref(), project, &env_overrides) {
+            Some(child) => (child, true),
+            None => {
+                logging::trace("starting a cold codex process");
+                (
+                    spawn_codex(prompt, session_id.as_deref(), project, &env_overrides)?,
+                    false,
+                )
+            }
+        };
+    let started_at = Instant::now();
+    shared_stream
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .set_warm_start(prompt_id, warm_start);
     let pid = child.id();
+    let _process_record = record_running_process(pid);
     let mut process = PromptProcessGuard {
         child: Some(child),
         stderr_handle: None,
     };
-    {
-        let mut active = running_prompt.lock().unwrap_or_else(|e| e.into_inner());
-        *active = Some(RunningPrompt {
+    running_prompt
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .register(RunningPrompt {
             id: prompt_id,
             pid,
             session_id: None,
         });
-    }
     let _running_prompt_guard = RunningPromptGuard {
         prompt_id,
-        running_prompt: Arc::clone(&running_prompt),
+        running_prompt: Arc::clone(running_prompt),
     };
+    if shared_stream
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .cancelled(prompt_id)
+    {
+        logging::trace(format!(
+            "prompt {} was cancelled before codex pid {} registered; stopping it",
+            prompt_id, pid
+        ));
+        return Err(io::Error::new(
+            io::ErrorKind::Interrupted,
+            "Prompt cancelled before codex started",
+        ));
+    }
+    let mut budget = BudgetTracker::new(budget);
+    let watchdog = budget
+        .time_limit()
+        .map(|limit| BudgetWatchdog::start(pid, limit));
+    let mut budget_stop = None;
+    let mut changed_files = Vec::new();
+    let mut failed_step = None;
+    let mut approval = None;
 
     let stdout = process
         .child_mut("opening stdout")?
@@ -103,13 +376,14 @@ pub(crate) fn prompt_codex(
         match logging::catch_panic("stderr reader thread", || -> io::Result<String> {
             let mut stderr = io::BufReader::new(stderr);
             let mut collected = String::new();
-            let mut buffer = [0u8; 4096];
+            let mut buffer = Vec::new();
             loop {
-                let read = stderr.read(&mut buffer)?;
-                if read == 0 {
+                buffer.clear();
+                if stderr.read_until(b'\n', &mut buffer)? == 0 {
                     break;
                 }
-                let text = String::from_utf8_lossy(&buffer[..read]);
+                let text = String::from_utf8_lossy(&buffer);
+                let text = sanitize_external_text(&text);
                 let trimmed = text.trim();
                 if !trimmed.is_empty() {
                     logging::trace(format!("codex stderr: {}", trimmed));
@@ -129,79 +403,187 @@ pub(crate) fn prompt_codex(
 
     let mut stdout = io::BufReader::new(stdout);
     let mut line_number = 0usize;
-    let mut response = ResponseBuffers::default();
+    let mut response =
+        ResponseBuffers::with_reasoning_limits(reasoning_window, reasoning_retention);
     let mut failure_message = None;
     let mut resolved_session_id = session_id;
-    let mut line = String::new();
+    let mut usage: Option<TokenUsage> = None;
+    let mut length_stopped = false;
+    let mut last_phase = PromptPhase::Waiting;
+    let mut raw_line = Vec::new();
+    let mut splitter = EventSplitter::default();
+    let mut stream_dirty = false;
+    let mut throttle = StreamThrottle::new(update_interval);
+    let mut recorder = if record_events {
+        EventRecorder::open(prompt_id)
+    } else {
+        None
+    };
 
-    loop {
-        line.clear();
-        if stdout.read_line(&mut line)? == 0 {
+    'read: loop {
+        raw_line.clear();
+        if stdout.read_until(b'\n', &mut raw_line)? == 0 {
             break;
         }
         line_number += 1;
+        let line = decode_output_line(&raw_line, line_number);
         let trimmed = line.trim();
         if trimmed.is_empty() {
             continue;
         }
-        let previous_visible_len = response.visible_len();
-        let previous_has_deltas = response.has_deltas();
-        let event: Value = serde_json::from_str(trimmed).map_err(|error| {
-            io::Error::new(
-                io::ErrorKind::InvalidData,
-                format!(
-                    "Invalid JSON event on line {}: {}; line: {}",
-                    line_number, error, trimmed
-                ),
-            )
-        })?;
-
-        if let Some(kind) = event.get("type").and_then(Value::as_str) {
-            if kind == "error" && failure_message.is_none() {
-                failure_message = event
-                    .get("message")
-                    .and_then(Value::as_str)
-                    .map(ToOwned::to_owned);
-                if let Some(message) = failure_message.as_ref() {
-                    logging::error(format!("codex reported error event: {}", message));
+        if let Some(active) = recorder.as_mut()
+            && let Err(error) = active.record(trimmed)
+        {
+            logging::error(format!("stopped recording codex events: {}", error));
+            recorder = None;
+        }
+        if splitter.absorb_preamble(trimmed, line_number) {
+            event_log
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .record_preamble(prompt_id, trimmed);
+            continue;
+        }
+        event_log
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .record(prompt_id, trimmed);
+        for event in splitter.push_line(trimmed, line_number) {
+            let previous_visible_len = response.visible_len();
+            let previous_has_deltas = response.has_deltas();
+            if let Some(kind) = event.get("type").and_then(Value::as_str) {
+                if kind == "error" && failure_message.is_none() {
+                    failure_message = event
+                        .get("message")
+                        .and_then(Value::as_str)
+                        .map(|message| sanitize_external_text(message).into_owned());
+                    if let Some(message) = failure_message.as_ref() {
+                        logging::error(format!("codex reported error event: {}", message));
+                    }
                 }
-            }
-            if kind == "thread.started" && resolved_session_id.is_none() {
-                if let Some(tid) = event.get("thread_id").and_then(Value::as_str) {
+                if kind == "thread.started"
+                    && resolved_session_id.is_none()
+                    && let Some(tid) = event.get("thread_id").and_then(Value::as_str)
+                {
                     resolved_session_id = Some(tid.to_owned());
-                    let mut active = running_prompt.lock().unwrap_or_else(|e| e.into_inner());
-                    if let Some(active_prompt) = active.as_mut() {
-                        if active_prompt.id == prompt_id {
-                            active_prompt.session_id = Some(tid.to_owned());
-                        }
-                    }
+                    running_prompt
+                        .lock()
+                        .unwrap_or_else(|e| e.into_inner())
+                        .set_session_id(prompt_id, tid);
                 }
             }
-        }
-        collect_response_text(&event, &mut response);
-        let visible_len = response.visible_len();
-        let has_deltas = response.has_deltas();
-        if visible_len != 0
-            && (visible_len != previous_visible_len || has_deltas != previous_has_deltas)
-        {
-            let visible_text = response.visible_text();
-            let updated = {
+            if let Some(turn_usage) = TokenUsage::from_event(&event) {
+                usage.get_or_insert_default().add(turn_usage);
+            }
+            if !length_stopped && reports_length_stop(&event) {
+                logging::trace(format!(
+                    "codex reported a length stop on line {}",
+                    line_number
+                ));
+                length_stopped = true;
+            }
+            collect_response_text(&event, &mut response);
+            collect_changed_files(&event, &mut changed_files);
+            budget.record_event(&event);
+            if track_tool_step(&event, &mut failed_step) {
+                let step = failed_step.clone().map(|step| FailedToolStep {
+                    session_id: resolved_session_id.clone(),
+                    ..step
+                });
+                shared_stream
+                    .lock()
+                    .unwrap_or_else(|e| e.into_inner())
+                    .set_failed_step(prompt_id, step);
+            }
+            if let Some(request) = approval_request(&event) {
+                logging::trace(format!(
+                    "prompt {} needs approval to {}; stopping codex until the user decides",
+                    prompt_id,
+                    request.kind.label().to_lowercase()
+                ));
+                if let Err(error) = kill_prompt_process(pid) {
+                    logging::error(format!(
+                        "failed to stop prompt pid {} waiting for approval: {}",
+                        pid, error
+                    ));
+                }
+                approval = Some(Box::new(request));
+                stream_dirty = true;
+                break 'read;
+            }
+            if budget.exceeded(started_at.elapsed()) {
+                let summary = budget.stop_summary(started_at.elapsed());
+                logging::trace(format!("prompt {} {}", prompt_id, summary));
+                if let Err(error) = kill_prompt_process(pid) {
+                    logging::error(format!(
+                        "failed to stop prompt pid {} after its budget: {}",
+                        pid, error
+                    ));
+                }
+                budget_stop = Some(summary);
+                stream_dirty = true;
+                break 'read;
+            }
+            let phase = response.phase();
+            let phase_advanced = phase != last_phase && {
+                last_phase = phase;
                 let mut stream = shared_stream.lock().unwrap_or_else(|e| e.into_inner());
-                stream.update(prompt_id, visible_text)
+                stream.advance_phase(prompt_id, phase)
             };
-            if updated {
-                logging::trace(format!("stream update: {} visible chars", visible_len));
-                if !stream_notification_pending.swap(true, Ordering::Relaxed) {
-                    if tx.send(AppEvent::PromptStream(prompt_id)).is_err() {
-                        logging::error("failed to deliver prompt stream update to app");
-                    }
-                    ctx.request_repaint();
-                }
+            if phase_advanced {
+                logging::trace(format!(
+                    "prompt {} after {} ms",
+                    phase.label(),
+                    started_at.elapsed().as_millis()
+                ));
+            }
+            let visible_len = response.visible_len();
+            let has_deltas = response.has_deltas();
+            if visible_len != 0
+                && (visible_len != previous_visible_len || has_deltas != previous_has_deltas)
+            {
+                stream_dirty = true;
+            }
+            if !throttle.flush_due(Instant::now(), stream_dirty, phase_advanced) {
+                continue;
+            }
+            let updated = stream_dirty && flush_stream_text(prompt_id, &response, shared_stream);
+            stream_dirty = false;
+            if updated || phase_advanced {
+                notify_stream(prompt_id, stream_notification_pending, tx, ctx);
             }
         }
     }
+    splitter.finish();
+    if budget_stop.is_none() && watchdog.as_ref().is_some_and(BudgetWatchdog::tripped) {
+        let summary = budget.stop_summary(started_at.elapsed());
+        logging::trace(format!("prompt {} {}", prompt_id, summary));
+        budget_stop = Some(summary);
+    }
+    drop(watchdog);
+    if stream_dirty
+        && flush_final_stream_text(
+            prompt_id,
+            &response,
+            shared_stream,
+            stream_notification_pending,
+            tx,
+            ctx,
+        )
+    {
+        throttle.flushes += 1;
+    }
+    if let Some(recorder) = recorder {
+        recorder.finish();
+    }
+    logging::trace(format!(
+        "prompt {} stream: {} stdout lines, {} stream flushes",
+        prompt_id, line_number, throttle.flushes
+    ));
 
-    let status = process.child_mut("before wait")?.wait()?;
+    let status = wait_for_exit(process.child_mut("before wait")?)?;
+    #[cfg(windows)]
+    super::wsl::forget_wsl_process(pid);
     logging::trace(format!("codex process exited with {}", status));
     let stderr_handle = process.stderr_handle.take().ok_or_else(|| {
         logging::error("missing stderr reader thread handle");
@@ -210,31 +592,67 @@ pub(crate) fn prompt_codex(
     let stderr_text = join_stderr_reader(stderr_handle)?;
     let _ = process.child.take();
 
-    if !status.success() {
-        let message = if !stderr_text.is_empty() {
-            stderr_text
-        } else {
-            failure_message.unwrap_or_else(|| format!("codex exited with {}", status))
+    if !status.success() && budget_stop.is_none() && approval.is_none() {
+        let kind = classify_failure(status.code(), &stderr_text, failure_message.as_slice());
+        let message = match failure_message {
+            Some(failure) if stderr_text.is_empty() => failure,
+            Some(_) => stderr_text,
+            None => {
+                let code = status.code().map_or_else(
+                    || "no exit code".to_owned(),
+                    |code| format!("code {}", code),
+                );
+                if stderr_text.is_empty() {
+                    format!("codex exited unexpectedly ({})", code)
+                } else {
+                    format!("codex exited unexpectedly ({})\n{}", code, stderr_text)
+                }
+            }
         };
-        logging::error(format!("codex exec failed: {}", message));
-        return Err(io::Error::other(message));
+        logging::error(format!("codex exec failed ({:?}): {}", kind, message));
+        return Err(io::Error::other(PromptFailure::new(kind, message)));
     }
 
-    let response = response.into_response();
-    if !response.is_empty() {
+    if response.reasoning_trimmed() > 0 {
+        logging::trace(format!(
+            "trimmed {} earlier reasoning lines past the {} byte retention limit",
+            response.reasoning_trimmed(),
+            reasoning_retention
+        ));
+    }
+    let (response, suggestions) = response.into_response();
+    let response = with_preamble_fallback(response, &splitter);
+    if !suggestions.is_empty() {
+        logging::trace(format!(
+            "codex response included {} follow-up suggestions",
+            suggestions.len()
+        ));
+    }
+    if response.is_empty() {
+        logging::trace("codex exec completed with empty output");
+    } else {
         logging::trace(format!(
             "codex exec completed with {} chars",
             response.chars().count()
         ));
-        return Ok((response, resolved_session_id));
     }
-    logging::trace("codex exec completed with empty output");
-    Ok((response, resolved_session_id))
+    Ok(PromptOutput {
+        response,
+        session_id: resolved_session_id,
+        usage,
+        suggestions,
+        length_stopped,
+        budget_stop,
+        changed_files,
+        approval,
+    })
 }
 
-pub(crate) fn kill_prompt_process(pid: u32) -> io::Result<()> {
+#[cfg(windows)]
+pub fn kill_prompt_process(pid: u32) -> io::Result<()> {
+    super::wsl::kill_wsl_process(pid);
     let status = Command::new("taskkill")
-        .creation_flags(CREATE_NO_WINDOW)
+        .hide_window()
         .args(["/PID", &pid.to_string(), "/T", "/F"])
         .status()?;
     if status.success() {
@@ -244,27 +662,168 @@ pub(crate) fn kill_prompt_process(pid: u32) -> io::Result<()> {
     Err(io::Error::other(format!("taskkill exited with {}", status)))
 }
 
-pub(crate) fn append_cancelled_text(input: &mut String) {
-    if input == "..." {
-        input.clear();
+#[cfg(unix)]
+pub fn kill_prompt_process(pid: u32) -> io::Result<()> {
+    let status = Command::new("kill")
+        .args(["-KILL", "--", &format!("-{}", pid)])
+        .status()?;
+    if status.success() {
+        return Ok(());
     }
-    if !input.is_empty() {
-        if !input.ends_with('\n') {
-            input.push('\n');
-        }
-        input.push('\n');
+    logging::error(format!("kill failed for process group {}: {}", pid, status));
+    Err(io::Error::other(format!("kill exited with {}", status)))
+}
+
+fn wait_for_exit(child: &mut Child) -> io::Result<ExitStatus> {
+    if let Some(status) = wait_within(child, PROCESS_CLEANUP_TIMEOUT)? {
+        return Ok(status);
     }
-    input.push_str(CANCELLED_TEXT);
+    logging::error(format!(
+        "codex closed its output but was still running after {} ms; killing it",
+        PROCESS_CLEANUP_TIMEOUT.as_millis()
+    ));
+    child.kill()?;
+    wait_within(child, PROCESS_CLEANUP_TIMEOUT)?.ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::TimedOut,
+            "codex did not exit after it was killed",
+        )
+    })
 }
 
 fn join_stderr_reader(handle: thread::JoinHandle<io::Result<String>>) -> io::Result<String> {
-    match handle.join() {
-        Ok(result) => result,
-        Err(_) => {
+    match join_within(handle, PROCESS_CLEANUP_TIMEOUT) {
+        Ok(Ok(result)) => result,
+        Ok(Err(_)) => {
             logging::error("stderr reader thread panicked during join");
             Err(io::Error::other("stderr reader thread panicked"))
         }
+        Err(_) => {
+            logging::error(format!(
+                "stderr still open {} ms after codex exited; another process may hold the pipe, continuing without it",
+                PROCESS_CLEANUP_TIMEOUT.as_millis()
+            ));
+            Ok(String::new())
+        }
+    }
+}
+
+// Codex relays raw tool output, which is not always UTF-8; an invalid byte
+// should cost one replacement character, not the whole prompt.
+fn with_preamble_fallback(response: String, splitter: &EventSplitter) -> String {
+    match splitter.preamble() {
+        Some(preamble) if !splitter.started() && response.trim().is_empty() => {
+            logging::error(format!(
+                "codex exited without JSON output; showing its {} bytes of plain output",
+                preamble.len()
+            ));
+            format!(
+                "\x1C{}\n{}\n",
+                PREAMBLE_NOTICE,
+                sanitize_external_text(preamble)
+            )
+        }
+        _ => response,
+    }
+}
+
+fn decode_output_line(raw_line: &[u8], line_number: usize) -> Cow<'_, str> {
+    let line = String::from_utf8_lossy(raw_line);
+    if let Cow::Owned(converted) = &line {
+        let replacements = converted.matches(char::REPLACEMENT_CHARACTER).count();
+        logging::error(format!(
+            "codex stdout line {} was not valid UTF-8; replaced {} invalid sequence(s)",
+            line_number, replacements
+        ));
+    }
+    line
+}
+
+// Coalesces stream updates so a flood of small deltas costs one lock and one
+// repaint per interval; the first update after a quiet spell goes out at once.
+struct StreamThrottle {
+    interval: Duration,
+    last_flush: Option<Instant>,
+    flushes: usize,
+}
+
+impl StreamThrottle {
+    fn new(interval: Duration) -> Self {
+        Self {
+            interval,
+            last_flush: None,
+            flushes: 0,
+        }
+    }
+
+    fn flush_due(&mut self, now: Instant, dirty: bool, phase_advanced: bool) -> bool {
+        let due = phase_advanced
+            || dirty
+                && self
+                    .last_flush
+                    .is_none_or(|at| now.duration_since(at) >= self.interval);
+        if due {
+            self.last_flush = Some(now);
+            self.flushes += 1;
+        }
+        due
+    }
+}
+
+fn flush_stream_text(
+    prompt_id: u64,
+    response: &ResponseBuffers,
+    shared_stream: &Mutex<PromptStreamState>,
+) -> bool {
+    let visible_text = response.visible_text();
+    let updated = {
+        let mut stream = shared_stream.lock().unwrap_or_else(|e| e.into_inner());
+        stream.update(prompt_id, &visible_text)
+    };
+    if updated {
+        logging::trace(format!(
+            "stream update: {} visible chars",
+            visible_text.len()
+        ));
+    }
+    updated
+}
+
+fn flush_final_stream_text(
+    prompt_id: u64,
+    response: &ResponseBuffers,
+    shared_stream: &Mutex<PromptStreamState>,
+    stream_notification_pending: &AtomicBool,
+    tx: &mpsc::Sender<AppEvent>,
+    ctx: &RepaintHandle,
+) -> bool {
+    if !flush_stream_text(prompt_id, response, shared_stream) {
+        return false;
+    }
+    // The completion follows right after, so this notification must not be
+    // swallowed by one that is still pending.
+    stream_notification_pending.store(true, Ordering::Relaxed);
+    send_stream_notification(prompt_id, tx, ctx);
+    true
+}
+
+fn notify_stream(
+    prompt_id: u64,
+    stream_notification_pending: &AtomicBool,
+    tx: &mpsc::Sender<AppEvent>,
+    ctx: &RepaintHandle,
+) {
+    if stream_notification_pending.swap(true, Ordering::Relaxed) {
+        return;
+    }
+    send_stream_notification(prompt_id, tx, ctx);
+}
+
+fn send_stream_notification(prompt_id: u64, tx: &mpsc::Sender<AppEvent>, ctx: &RepaintHandle) {
+    if tx.send(AppEvent::PromptStream(prompt_id)).is_err() {
+        logging::error("failed to deliver prompt stream update to app");
     }
+    ctx.request_repaint();
 }
 
 fn missing_stdio(name: &str) -> io::Error {
@@ -272,15 +831,210 @@ fn missing_stdio(name: &str) -> io::Error {
     io::Error::other(format!("Missing {name} pipe"))
 }
 
-trait PromptProcessExt {
-    fn child_mut(&mut self, context: &str) -> io::Result<&mut Child>;
-}
+#[cfg(test)]
+mod tests {
+    use std::process::Stdio;
+
+    use super::*;
+    use crate::prompt::replay_recording;
+    use crate::runtime::BackgroundCommand;
+
+    #[test]
+    fn a_flood_of_deltas_flushes_once_per_interval() {
+        let interval = Duration::from_millis(50);
+        let mut throttle = StreamThrottle::new(interval);
+        let start = Instant::now();
+        // 10k deltas 100 us apart: one second of output.
+        let flushes = (0..10_000u32)
+            .filter(|event| {
+                let now = start + Duration::from_micros(u64::from(*event) * 100);
+                throttle.flush_due(now, true, false)
+            })
+            .count();
+        assert_eq!(flushes, 20);
+        assert_eq!(throttle.flushes, 20);
+    }
+
+    #[test]
+    fn first_update_after_a_quiet_spell_is_not_delayed() {
+        let mut throttle = StreamThrottle::new(Duration::from_millis(50));
+        let start = Instant::now();
+        assert!(throttle.flush_due(start, true, false));
+        assert!(!throttle.flush_due(start + Duration::from_millis(10), true, false));
+        assert!(throttle.flush_due(start + Duration::from_secs(2), true, false));
+    }
+
+    #[test]
+    fn phase_changes_flush_inside_the_interval() {
+        let mut throttle = StreamThrottle::new(Duration::from_millis(50));
+        let start = Instant::now();
+        assert!(throttle.flush_due(start, true, false));
+        assert!(throttle.flush_due(start + Duration::from_millis(1), false, true));
+        assert!(!throttle.flush_due(start + Duration::from_millis(2), true, false));
+        assert!(!throttle.flush_due(start + Duration::from_secs(1), false, false));
+    }
+
+    #[test]
+    fn invalid_utf8_mid_line_still_completes_the_prompt() {
+        let mut output = Vec::new();
+        output.extend_from_slice(b"{\"type\":\"thread.started\",\"thread_id\":\"t1\"}\n");
+        output.extend_from_slice(
+            b"{\"type\":\"item.completed\",\"item\":{\"id\":\"a\",\"type\":\"agent_message\",\
+              \"text\":\"caf\xE9 \x93quoted\x94 done\"}}\n",
+        );
+        output.extend_from_slice(b"{\"type\":\"turn.completed\"}\n");
+
+        let mut stdout = io::BufReader::new(output.as_slice());
+        let mut raw_line = Vec::new();
+        let mut lines = String::new();
+        let mut line_number = 0;
+        loop {
+            raw_line.clear();
+            if stdout.read_until(b'\n', &mut raw_line).unwrap() == 0 {
+                break;
+            }
+            line_number += 1;
+            let line = decode_output_line(&raw_line, line_number);
+            assert_eq!(matches!(line, Cow::Owned(_)), line_number == 2);
+            lines.push_str(&line);
+        }
+        assert_eq!(line_number, 3);
+
+        let replayed = replay_recording(&lines);
+        assert_eq!(replayed.session_id.as_deref(), Some("t1"));
+        assert_eq!(replayed.error, None);
+        assert!(
+            replayed
+                .response
+                .contains("caf\u{FFFD} \u{FFFD}quoted\u{FFFD} done"),
+            "{:?}",
+            replayed.response
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn kill_prompt_process_kills_the_whole_group() {
+        let mut child = Command::new("sh")
+            .args(["-c", "sleep 30 & wait"])
+            .own_process_group()
+            .stdin(Stdio::null())
+            .spawn()
+            .unwrap();
+        kill_prompt_process(child.id()).unwrap();
+        let status = child.wait().unwrap();
+        assert!(!status.success());
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn display_wake_drops_while_the_display_is_paused() {
+        assert_eq!(
+            wake_flags(WakeMode::SystemAndDisplay, false),
+            ES_CONTINUOUS | ES_SYSTEM_REQUIRED | ES_DISPLAY_REQUIRED
+        );
+        assert_eq!(
+            wake_flags(WakeMode::SystemAndDisplay, true),
+            ES_CONTINUOUS | ES_SYSTEM_REQUIRED
+        );
+        assert_eq!(
+            wake_flags(WakeMode::System, false),
+            ES_CONTINUOUS | ES_SYSTEM_REQUIRED
+        );
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn kill_prompt_process_kills_the_process_tree() {
+        let mut child = Command::new("cmd.exe")
+            .args(["/C", "ping -n 30 127.0.0.1 > NUL"])
+            .hide_window()
+            .stdin(Stdio::null())
+            .spawn()
+            .unwrap();
+        kill_prompt_process(child.id()).unwrap();
+        let status = child.wait().unwrap();
+        assert!(!status.success());
+    }
 
-impl PromptProcessExt for PromptProcessGuard {
-    fn child_mut(&mut self, context: &str) -> io::Result<&mut Child> {
-        self.child.as_mut().ok_or_else(|| {
-            logging::error(format!("missing child process {}", context));
-            io::Error::other("Missing child process")
-        })
+    fn split(lines: &[&str]) -> (EventSplitter, Vec<Value>) {
+        let mut splitter = EventSplitter::default();
+        let mut events = Vec::new();
+        for (index, line) in lines.iter().enumerate() {
+            if !splitter.absorb_preamble(line, index + 1) {
+                events.extend(splitter.push_line(line, index + 1));
+            }
+        }
+        splitter.finish();
+        (splitter, events)
+    }
+
+    #[test]
+    fn an_all_banner_stream_becomes_the_response() {
+        let (splitter, events) = split(&["codex 0.9 (update available)", "Error: \x1Eno model"]);
+        assert!(events.is_empty());
+        assert_eq!(
+            with_preamble_fallback(String::new(), &splitter),
+            format!(
+                "\x1C{}\ncodex 0.9 (update available)\nError: \u{241E}no model\n",
+                PREAMBLE_NOTICE
+            )
+        );
+    }
+
+    #[test]
+    fn a_banner_before_json_keeps_the_real_response() {
+        let (splitter, events) = split(&[
+            "Debugger attached.",
+            r#"{"type":"thread.started","thread_id":"t1"}"#,
+        ]);
+        assert_eq!(events.len(), 1);
+        assert_eq!(
+            with_preamble_fallback(String::new(), &splitter),
+            "",
+            "the preamble only stands in when no JSON arrived"
+        );
+        assert_eq!(
+            with_preamble_fallback("Answer".to_owned(), &splitter),
+            "Answer"
+        );
+    }
+
+    #[test]
+    fn the_final_flush_notifies_even_while_a_notification_is_pending() {
+        let (tx, rx) = mpsc::channel();
+        let ctx = RepaintHandle::new(|| {});
+        let shared_stream = Mutex::new(PromptStreamState::default());
+        shared_stream.lock().unwrap().start(7);
+        let pending = AtomicBool::new(true);
+        let mut response = ResponseBuffers::default();
+        response.push_delta("Partial answer");
+
+        notify_stream(7, &pending, &tx, &ctx);
+        assert!(
+            rx.try_recv().is_err(),
+            "a pending notification suppresses more"
+        );
+
+        assert!(flush_final_stream_text(
+            7,
+            &response,
+            &shared_stream,
+            &pending,
+            &tx,
+            &ctx
+        ));
+        assert!(matches!(rx.try_recv(), Ok(AppEvent::PromptStream(7))));
+        assert_eq!(shared_stream.lock().unwrap().text, "Partial answer");
+
+        assert!(!flush_final_stream_text(
+            7,
+            &response,
+            &shared_stream,
+            &pending,
+            &tx,
+            &ctx
+        ));
+        assert!(rx.try_recv().is_err(), "unchanged text sends nothing");
     }
 }
@@ -0,0 +1,295 @@
+use std::fmt;
+use std::io;
+
+use crate::events::ErrorKind;
+
+use super::rate_limit::is_rate_limited;
+
+const CODEX_MISSING_MARKERS: [&str; 5] = [
+    "cannot find the file specified",
+    "file not found",
+    "program is not recognized",
+    "program not found",
+    "is not recognized as an internal or external command",
+];
+const AUTH_MARKERS: [&str; 8] = [
+    "status 401",
+    "unauthorized",
+    "not logged in",
+    "not signed in",
+    "please log in",
+    "codex login",
+    "invalid api key",
+    "authentication",
+];
+const QUOTA_MARKERS: [&str; 5] = [
+    "insufficient_quota",
+    "exceeded your current quota",
+    "quota exceeded",
+    "usage limit",
+    "billing",
+];
+const TIMEOUT_MARKERS: [&str; 4] = ["timed out", "timeout", "deadline exceeded", "time limit"];
+const CANCEL_MARKERS: [&str; 4] = [
+    "cancelled by user",
+    "operation was cancelled",
+    "operation was canceled",
+    "interrupted by user",
+];
+const SPAWN_MARKERS: [&str; 4] = [
+    "failed to spawn",
+    "failed to launch",
+    "failed to start",
+    "failed to prepare",
+];
+
+#[derive(Debug)]
+pub struct PromptFailure {
+    kind: ErrorKind,
+    message: String,
+}
+
+impl PromptFailure {
+    pub(super) fn new(kind: ErrorKind, message: String) -> Self {
+        Self { kind, message }
+    }
+}
+
+impl fmt::Display for PromptFailure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl std::error::Error for PromptFailure {}
+
+fn mentions(text: &str, markers: &[&str]) -> bool {
+    markers.iter().any(|marker| text.contains(marker))
+}
+
+pub(super) fn is_resume_failure(error: &str) -> bool {
+    let error = error.to_ascii_lowercase();
+    if error.contains("failed to resume") || error.contains("could not resume") {
+        return true;
+    }
+    let mentions_session = ["session", "thread", "conversation", "rollout"]
+        .iter()
+        .any(|subject| error.contains(subject));
+    mentions_session
+        && [
+            "not found",
+            "no such",
+            "unknown",
+            "does not exist",
+            "could not find",
+            "no rollout",
+        ]
+        .iter()
+        .any(|problem| error.contains(problem))
+}
+
+pub fn classify_failure(
+    exit_code: Option<i32>,
+    stderr: &str,
+    error_events: &[String],
+) -> ErrorKind {
+    let mut text = error_events.join("\n");
+    text.push('\n');
+    text.push_str(stderr);
+    let text = text.to_ascii_lowercase();
+    if mentions(&text, &CODEX_MISSING_MARKERS) {
+        ErrorKind::CodexNotFound
+    } else if mentions(&text, &AUTH_MARKERS) {
+        ErrorKind::AuthRequired
+    } else if mentions(&text, &QUOTA_MARKERS) {
+        ErrorKind::BudgetExceeded
+    } else if is_rate_limited(&text) {
+        ErrorKind::RateLimited
+    } else if is_resume_failure(&text) {
+        ErrorKind::ResumeFailed
+    } else if mentions(&text, &TIMEOUT_MARKERS) {
+        ErrorKind::Timeout
+    } else if mentions(&text, &CANCEL_MARKERS) {
+        ErrorKind::Cancelled
+    } else if exit_code.is_none() && error_events.is_empty() && mentions(&text, &SPAWN_MARKERS) {
+        ErrorKind::SpawnFailed
+    } else {
+        match exit_code {
+            Some(0) => ErrorKind::InvalidOutput,
+            Some(_) => ErrorKind::NonZeroExit,
+            None if error_events.is_empty() => ErrorKind::SpawnFailed,
+            None => ErrorKind::NonZeroExit,
+        }
+    }
+}
+
+pub fn failure_kind(error: &io::Error) -> ErrorKind {
+    if let Some(failure) = error
+        .get_ref()
+        .and_then(|inner| inner.downcast_ref::<PromptFailure>())
+    {
+        return failure.kind;
+    }
+    match error.kind() {
+        io::ErrorKind::TimedOut => ErrorKind::Timeout,
+        io::ErrorKind::Interrupted => ErrorKind::Cancelled,
+        io::ErrorKind::InvalidData => ErrorKind::InvalidOutput,
+        _ => classify_failure(None, &error.to_string(), &[]),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn missing_sessions_are_resume_failures() {
+        for error in [
+            "Error: failed to resume thread 0199a",
+            "codex exited: could not resume conversation",
+            "Session 0199a not found",
+            "no rollout found for thread id 0199a",
+            "Conversation does not exist",
+        ] {
+            assert!(is_resume_failure(error), "{}", error);
+        }
+    }
+
+    #[test]
+    fn other_failures_do_not_drop_the_session() {
+        for error in [
+            "file not found: C:\\npm\\codex.cmd",
+            "session expired; run codex login",
+            "stream error: unexpected status 500",
+            "unknown model o9",
+        ] {
+            assert!(!is_resume_failure(error), "{}", error);
+        }
+    }
+
+    #[test]
+    fn captured_failures_classify_by_kind() {
+        let events = |messages: &[&str]| messages.iter().map(|m| m.to_string()).collect::<Vec<_>>();
+        // (exit code, stderr, error events) -> kind
+        let corpus = [
+            (
+                None,
+                "failed to spawn codex: The system cannot find the file specified. (os error 2)",
+                events(&[]),
+                ErrorKind::CodexNotFound,
+            ),
+            (
+                Some(1),
+                "'codex' is not recognized as an internal or external command",
+                events(&[]),
+                ErrorKind::CodexNotFound,
+            ),
+            (
+                Some(1),
+                "",
+                events(&["unexpected status 401 Unauthorized: run codex login"]),
+                ErrorKind::AuthRequired,
+            ),
+            (
+                Some(1),
+                "",
+                events(&[
+                    "You exceeded your current quota, please check your plan and billing details.",
+                ]),
+                ErrorKind::BudgetExceeded,
+            ),
+            (
+                Some(1),
+                "",
+                events(&["Rate limit reached for o4-mini. Please try again in 20s."]),
+                ErrorKind::RateLimited,
+            ),
+            (
+                Some(1),
+                "Error: failed to resume thread 0199a: no rollout found",
+                events(&[]),
+                ErrorKind::ResumeFailed,
+            ),
+            (
+                Some(1),
+                "",
+                events(&["stream disconnected: request timed out"]),
+                ErrorKind::Timeout,
+            ),
+            (
+                Some(130),
+                "Interrupted by user",
+                events(&[]),
+                ErrorKind::Cancelled,
+            ),
+            (
+                None,
+                "failed to prepare the codex command: bad working directory",
+                events(&[]),
+                ErrorKind::SpawnFailed,
+            ),
+            (None, "", events(&[]), ErrorKind::SpawnFailed),
+            (
+                Some(2),
+                "error: unexpected argument '--frobnicate' found",
+                events(&[]),
+                ErrorKind::NonZeroExit,
+            ),
+            (
+                None,
+                "",
+                events(&["stream error: unexpected status 500"]),
+                ErrorKind::NonZeroExit,
+            ),
+            (
+                Some(0),
+                "",
+                events(&["no agent message in output"]),
+                ErrorKind::InvalidOutput,
+            ),
+        ];
+        for (exit_code, stderr, error_events, kind) in corpus {
+            assert_eq!(
+                classify_failure(exit_code, stderr, &error_events),
+                kind,
+                "{:?} {:?} {:?}",
+                exit_code,
+                stderr,
+                error_events
+            );
+        }
+    }
+
+    #[test]
+    fn earlier_markers_win_when_several_match() {
+        assert_eq!(
+            classify_failure(Some(1), "401 unauthorized; try again in 5s", &[]),
+            ErrorKind::AuthRequired
+        );
+        assert_eq!(
+            classify_failure(Some(1), "rate limit: request timed out", &[]),
+            ErrorKind::RateLimited
+        );
+    }
+
+    #[test]
+    fn io_errors_keep_a_wrapped_kind_or_map_their_own() {
+        let wrapped = io::Error::other(PromptFailure::new(
+            ErrorKind::ResumeFailed,
+            "codex exited with 1".to_string(),
+        ));
+        assert_eq!(failure_kind(&wrapped), ErrorKind::ResumeFailed);
+        assert_eq!(wrapped.to_string(), "codex exited with 1");
+        for (kind, expected) in [
+            (io::ErrorKind::TimedOut, ErrorKind::Timeout),
+            (io::ErrorKind::Interrupted, ErrorKind::Cancelled),
+            (io::ErrorKind::InvalidData, ErrorKind::InvalidOutput),
+        ] {
+            assert_eq!(failure_kind(&io::Error::new(kind, "x")), expected);
+        }
+        let missing = io::Error::new(io::ErrorKind::NotFound, "program not found");
+        assert_eq!(failure_kind(&missing), ErrorKind::CodexNotFound);
+        let other = io::Error::other("pipe closed");
+        assert_eq!(failure_kind(&other), ErrorKind::SpawnFailed);
+    }
+}
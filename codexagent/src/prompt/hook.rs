@@ -0,0 +1,95 @@
+use std::io::{self, BufRead};
+use std::process::Stdio;
+use std::sync::{Arc, Mutex, mpsc};
+use std::thread;
+
+use crate::events::{AppEvent, RepaintHandle};
+use crate::logging;
+use crate::runtime::{BackgroundCommand, record_running_process, shell_command};
+
+use super::buffers::sanitize_external_text;
+use super::state::{
+    PromptProcessExt, PromptProcessGuard, RunningPrompt, RunningPromptGuard, RunningPrompts,
+};
+
+pub fn run_hook(
+    prompt_id: u64,
+    command_line: &str,
+    running_prompt: Arc<Mutex<RunningPrompts>>,
+    tx: &mpsc::Sender<AppEvent>,
+    ctx: &RepaintHandle,
+) -> io::Result<i32> {
+    logging::trace(format!("running completion hook: {}", command_line));
+    let child = shell_command(command_line)
+        .hide_window()
+        .own_process_group()
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+    let pid = child.id();
+    let _process_record = record_running_process(pid);
+    let mut process = PromptProcessGuard {
+        child: Some(child),
+        stderr_handle: None,
+    };
+    {
+        running_prompt
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .register(RunningPrompt {
+                id: prompt_id,
+                pid,
+                session_id: None,
+            });
+    }
+    let _running_prompt_guard = RunningPromptGuard {
+        prompt_id,
+        running_prompt: Arc::clone(&running_prompt),
+    };
+
+    let child = process.child_mut("opening hook output")?;
+    let stdout = child.stdout.take();
+    let stderr = child.stderr.take();
+    let stderr_handle = stderr.map(|stderr| {
+        let tx = tx.clone();
+        let ctx = ctx.clone();
+        thread::spawn(move || forward_hook_output(io::BufReader::new(stderr), prompt_id, &tx, &ctx))
+    });
+    if let Some(stdout) = stdout {
+        forward_hook_output(io::BufReader::new(stdout), prompt_id, tx, ctx);
+    }
+    if stderr_handle.is_some_and(|handle| handle.join().is_err()) {
+        logging::error("hook stderr reader thread panicked");
+    }
+    let status = process.child_mut("waiting for hook")?.wait()?;
+    let code = status.code().unwrap_or(-1);
+    logging::trace(format!("completion hook exited with {}", code));
+    Ok(code)
+}
+
+fn forward_hook_output<R: BufRead>(
+    reader: R,
+    prompt_id: u64,
+    tx: &mpsc::Sender<AppEvent>,
+    ctx: &RepaintHandle,
+) {
+    for line in reader.split(b'\n') {
+        match line {
+            Ok(line) => {
+                let line = sanitize_external_text(&String::from_utf8_lossy(&line))
+                    .trim_end()
+                    .to_owned();
+                if tx.send(AppEvent::HookOutput(prompt_id, line)).is_err() {
+                    logging::error("failed to deliver hook output to app");
+                    break;
+                }
+                ctx.request_repaint();
+            }
+            Err(error) => {
+                logging::error(format!("failed to read hook output: {}", error));
+                break;
+            }
+        }
+    }
+}
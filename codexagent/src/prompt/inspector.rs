@@ -0,0 +1,123 @@
+use std::collections::VecDeque;
+
+const EVENT_LOG_LIMIT: usize = 4 * 1024 * 1024;
+
+#[derive(Default)]
+pub struct EventLog {
+    prompt_id: Option<u64>,
+    lines: VecDeque<String>,
+    bytes: usize,
+    dropped: usize,
+    preamble: usize,
+}
+
+impl EventLog {
+    pub fn start(&mut self, prompt_id: u64) {
+        self.reset();
+        self.prompt_id = Some(prompt_id);
+    }
+
+    pub fn reset(&mut self) {
+        self.prompt_id = None;
+        self.lines = VecDeque::new();
+        self.bytes = 0;
+        self.dropped = 0;
+        self.preamble = 0;
+    }
+
+    pub fn record_preamble(&mut self, prompt_id: u64, line: &str) {
+        let leading = self.prompt_id == Some(prompt_id) && self.preamble == self.lines.len();
+        if leading {
+            self.preamble += 1;
+        }
+        self.record(prompt_id, line);
+    }
+
+    pub fn record(&mut self, prompt_id: u64, line: &str) {
+        if self.prompt_id != Some(prompt_id) {
+            return;
+        }
+        self.bytes += line.len();
+        self.lines.push_back(line.to_owned());
+        while self.bytes > EVENT_LOG_LIMIT && self.lines.len() > 1 {
+            let Some(oldest) = self.lines.pop_front() else {
+                break;
+            };
+            self.bytes -= oldest.len();
+            self.dropped += 1;
+            self.preamble = self.preamble.saturating_sub(1);
+        }
+    }
+
+    pub fn prompt_id(&self) -> Option<u64> {
+        self.prompt_id
+    }
+
+    pub fn lines(&self) -> impl Iterator<Item = &str> {
+        self.lines.iter().map(String::as_str)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.lines.is_empty()
+    }
+
+    pub fn preamble(&self) -> usize {
+        self.preamble
+    }
+
+    pub fn dropped(&self) -> usize {
+        self.dropped
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_the_current_prompt_is_recorded() {
+        let mut log = EventLog::default();
+        log.record(1, "{}");
+        assert!(log.is_empty(), "nothing is kept before a prompt starts");
+        log.start(2);
+        log.record(2, r#"{"type":"turn.started"}"#);
+        log.record(1, r#"{"type":"late"}"#);
+        assert_eq!(
+            log.lines().collect::<Vec<_>>(),
+            [r#"{"type":"turn.started"}"#]
+        );
+        log.start(3);
+        assert!(log.is_empty());
+        assert_eq!(log.prompt_id(), Some(3));
+    }
+
+    #[test]
+    fn full_logs_drop_the_oldest_lines() {
+        let mut log = EventLog::default();
+        log.start(1);
+        log.record_preamble(1, "codex 0.46");
+        log.record_preamble(1, "model: gpt-5");
+        let line = "x".repeat(EVENT_LOG_LIMIT / 4);
+        for _ in 0..5 {
+            log.record(1, &line);
+        }
+        assert_eq!(log.dropped(), 3);
+        assert_eq!(log.preamble(), 0, "the preamble went first");
+        assert!(log.bytes <= EVENT_LOG_LIMIT);
+        assert_eq!(log.lines().count(), 4);
+
+        log.record(1, &"y".repeat(EVENT_LOG_LIMIT + 1));
+        assert_eq!(log.lines().count(), 1, "an oversized line is still kept");
+    }
+
+    #[test]
+    fn preamble_lines_count_only_before_events() {
+        let mut log = EventLog::default();
+        log.start(1);
+        log.record_preamble(1, "codex 0.46");
+        log.record(1, r#"{"type":"turn.started"}"#);
+        log.record_preamble(1, "warning: retrying");
+        assert_eq!(log.preamble(), 1);
+        assert_eq!(log.lines().count(), 3);
+    }
+}
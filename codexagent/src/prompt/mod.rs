@@ -1,8 +1,36 @@
+mod approval;
+mod approved_command;
+mod backend;
+mod budget;
 mod buffers;
 mod codex;
+mod event_stream;
 mod execution;
+mod failure;
+mod hook;
+mod inspector;
+mod rate_limit;
+mod recording;
 mod state;
+mod tool_step;
+mod warm;
+#[cfg(windows)]
+mod wsl;
 
-pub(crate) use codex::{check_codex_availability, has_node, run_full_install};
-pub(crate) use execution::{append_cancelled_text, kill_prompt_process, prompt_codex};
-pub(crate) use state::{PromptStreamState, RunningPrompt};
+pub use approval::{ApprovalKind, ApprovalRequest, CommandLine};
+pub use backend::{CodexBackend, PromptBackend, PromptChannels, PromptRequest};
+pub use buffers::{looks_truncated, normalize_line_endings, sanitize_external_text};
+pub use codex::{
+    check_codex_availability, codex_version, codex_visible_path, describe_codex_command,
+    describe_codex_launcher, has_node, launch_codex_login, redetect_codex, resolve_codex_path,
+    run_full_install, set_codex_profile,
+};
+pub use execution::{PromptOutput, kill_prompt_process};
+pub use failure::{classify_failure, failure_kind};
+pub use hook::run_hook;
+pub use inspector::EventLog;
+pub use rate_limit::{RateLimitBackoff, retry_after};
+pub use recording::{ReplayedSession, replay_recording};
+pub use state::{PromptPhase, PromptStreamState, RunningPrompt, RunningPrompts};
+pub use tool_step::FailedToolStep;
+pub use warm::{WARM_CODEX_MAX_AGE, discard_warm_codex, discard_warm_codex_async, prewarm_codex};
@@ -0,0 +1,196 @@
+use std::time::Duration;
+
+const RATE_LIMIT_MARKERS: [&str; 6] = [
+    "rate limit",
+    "rate_limit",
+    "ratelimit",
+    "too many requests",
+    "429 ",
+    " 429",
+];
+const RETRY_HINTS: [&str; 5] = [
+    "try again in ",
+    "retry after ",
+    "retry-after: ",
+    "retry-after ",
+    "retry in ",
+];
+const MAX_RATE_LIMIT_WAITS: u32 = 3;
+const MAX_RATE_LIMIT_WAIT_TOTAL: Duration = Duration::from_secs(5 * 60);
+const DEFAULT_RATE_LIMIT_WAIT: Duration = Duration::from_secs(15);
+const MIN_RATE_LIMIT_WAIT: Duration = Duration::from_secs(1);
+const MAX_RETRY_HINT: Duration = MAX_RATE_LIMIT_WAIT_TOTAL;
+
+#[derive(Default)]
+pub struct RateLimitBackoff {
+    waits: u32,
+    waited: Duration,
+}
+
+impl RateLimitBackoff {
+    pub fn next_wait(&mut self, hint: Option<Duration>) -> Option<Duration> {
+        if self.waits >= MAX_RATE_LIMIT_WAITS {
+            return None;
+        }
+        let wait = hint
+            .unwrap_or(DEFAULT_RATE_LIMIT_WAIT * 2u32.pow(self.waits))
+            .max(MIN_RATE_LIMIT_WAIT);
+        if self.waited + wait > MAX_RATE_LIMIT_WAIT_TOTAL {
+            return None;
+        }
+        self.waits += 1;
+        self.waited += wait;
+        Some(wait)
+    }
+
+    pub fn waits(&self) -> u32 {
+        self.waits
+    }
+}
+
+pub fn is_rate_limited(message: &str) -> bool {
+    let lower = message.to_ascii_lowercase();
+    RATE_LIMIT_MARKERS
+        .iter()
+        .any(|marker| lower.contains(marker))
+        || lower.contains("try again in ")
+}
+
+pub fn retry_after(message: &str) -> Option<Duration> {
+    let lower = message.to_ascii_lowercase();
+    RETRY_HINTS.iter().find_map(|hint| {
+        lower
+            .match_indices(hint)
+            .find_map(|(index, _)| parse_duration(&lower[index + hint.len()..]))
+    })
+}
+
+fn parse_duration(text: &str) -> Option<Duration> {
+    let mut rest = text.trim_start();
+    let mut seconds = 0.0;
+    let mut matched = false;
+    loop {
+        let digits = rest
+            .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+            .unwrap_or(rest.len());
+        let Ok(value) = rest[..digits].trim_end_matches('.').parse::<f64>() else {
+            break;
+        };
+        let after = rest[digits..].trim_start();
+        let unit_len = after
+            .find(|c: char| !c.is_ascii_alphabetic())
+            .unwrap_or(after.len());
+        let scale = match &after[..unit_len] {
+            "ms" | "millisecond" | "milliseconds" => 0.001,
+            "" | "s" | "sec" | "secs" | "second" | "seconds" => 1.0,
+            "m" | "min" | "mins" | "minute" | "minutes" => 60.0,
+            "h" | "hr" | "hrs" | "hour" | "hours" => 3600.0,
+            "d" | "day" | "days" => 86400.0,
+            _ => break,
+        };
+        seconds += value * scale;
+        matched = true;
+        rest = after[unit_len..].trim_start_matches([' ', ',']);
+        rest = rest.strip_prefix("and ").unwrap_or(rest);
+    }
+    if !matched {
+        return None;
+    }
+    Some(
+        Duration::try_from_secs_f64(seconds)
+            .map_or(MAX_RETRY_HINT, |wait| wait.min(MAX_RETRY_HINT)),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const OPENAI_TPM: &str = "Rate limit reached for gpt-4o in organization org-abc on tokens per min (TPM): Limit 30000, Used 29500, Requested 1200. Please try again in 1.4s. Visit https://platform.openai.com/account/rate-limits to learn more.";
+    const OPENAI_MS: &str =
+        "stream error: Rate limit reached for o4-mini. Please try again in 820ms.";
+    const GO_DURATION: &str = "429 Too Many Requests: usage limit hit, please try again in 2m30s";
+    const RETRY_AFTER_HEADER: &str = "unexpected status 429 Too Many Requests; Retry-After: 30";
+    const SPELLED_OUT: &str =
+        "You've hit your usage limit. Try again in 1 hour, 2 minutes and 5 seconds.";
+    const NO_HINT: &str = "exceeded retry limit, last status: 429 Too Many Requests";
+
+    #[test]
+    fn recognizes_rate_limit_errors() {
+        for message in [
+            OPENAI_TPM,
+            OPENAI_MS,
+            GO_DURATION,
+            RETRY_AFTER_HEADER,
+            SPELLED_OUT,
+            NO_HINT,
+        ] {
+            assert!(is_rate_limited(message), "{message}");
+        }
+        assert!(!is_rate_limited("error: failed to read config.toml"));
+        assert!(!is_rate_limited("stream disconnected before completion"));
+    }
+
+    #[test]
+    fn extracts_retry_hints() {
+        assert_eq!(retry_after(OPENAI_TPM), Some(Duration::from_millis(1400)));
+        assert_eq!(retry_after(OPENAI_MS), Some(Duration::from_millis(820)));
+        assert_eq!(retry_after(GO_DURATION), Some(Duration::from_secs(150)));
+        assert_eq!(
+            retry_after(RETRY_AFTER_HEADER),
+            Some(Duration::from_secs(30))
+        );
+        assert_eq!(retry_after(NO_HINT), None);
+    }
+
+    #[test]
+    fn long_hints_are_capped() {
+        assert_eq!(retry_after(SPELLED_OUT), Some(MAX_RETRY_HINT));
+        assert_eq!(retry_after("Try again in 400 days"), Some(MAX_RETRY_HINT));
+    }
+
+    #[test]
+    fn overflowing_hints_do_not_panic() {
+        let huge = format!("retry after {}s", "9".repeat(400));
+        assert_eq!(retry_after(&huge), Some(MAX_RETRY_HINT));
+        assert_eq!(
+            retry_after("retry after 99999999999999999999 days"),
+            Some(MAX_RETRY_HINT)
+        );
+    }
+
+    #[test]
+    fn backoff_doubles_without_a_hint() {
+        let mut backoff = RateLimitBackoff::default();
+        assert_eq!(backoff.next_wait(None), Some(Duration::from_secs(15)));
+        assert_eq!(backoff.next_wait(None), Some(Duration::from_secs(30)));
+        assert_eq!(backoff.next_wait(None), Some(Duration::from_secs(60)));
+        assert_eq!(backoff.waits(), 3);
+    }
+
+    #[test]
+    fn backoff_stops_after_three_waits() {
+        let mut backoff = RateLimitBackoff::default();
+        for _ in 0..3 {
+            assert!(backoff.next_wait(Some(Duration::from_secs(2))).is_some());
+        }
+        assert_eq!(backoff.next_wait(Some(Duration::from_secs(2))), None);
+    }
+
+    #[test]
+    fn backoff_stops_past_five_minutes_in_total() {
+        let mut backoff = RateLimitBackoff::default();
+        assert!(backoff.next_wait(Some(Duration::from_secs(200))).is_some());
+        assert_eq!(backoff.next_wait(Some(Duration::from_secs(101))), None);
+        assert!(backoff.next_wait(Some(Duration::from_secs(100))).is_some());
+    }
+
+    #[test]
+    fn short_hints_wait_at_least_a_second() {
+        let mut backoff = RateLimitBackoff::default();
+        assert_eq!(
+            backoff.next_wait(Some(Duration::from_millis(20))),
+            Some(MIN_RATE_LIMIT_WAIT)
+        );
+    }
+}
@@ -0,0 +1,124 @@
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write};
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde_json::Value;
+
+use crate::config::{
+    DEFAULT_REASONING_RETENTION_LIMIT, DEFAULT_REASONING_WINDOW, disk_space_low, paths,
+    transcripts_persisted,
+};
+use crate::logging;
+
+use super::buffers::{ResponseBuffers, collect_response_text};
+use super::event_stream::EventSplitter;
+
+const RECORDED_SESSIONS_DIR: &str = "sessions";
+
+pub(super) struct EventRecorder {
+    path: PathBuf,
+    writer: BufWriter<File>,
+}
+
+impl EventRecorder {
+    pub(super) fn open(prompt_id: u64) -> Option<Self> {
+        if !transcripts_persisted() {
+            logging::trace("event recording skipped; transcripts are not persisted");
+            return None;
+        }
+        if disk_space_low() {
+            logging::trace("event recording skipped; disk space is low");
+            return None;
+        }
+        let dir = paths::get().log_dir.join(RECORDED_SESSIONS_DIR);
+        let secs = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|duration| duration.as_secs())
+            .unwrap_or(0);
+        let path = dir.join(format!("{}_{}.jsonl", secs, prompt_id));
+        let file = logging::log_result(
+            fs::create_dir_all(&dir).and_then(|_| File::create(&path)),
+            |error| {
+                format!(
+                    "failed to open event recording {}: {}",
+                    path.display(),
+                    error
+                )
+            },
+        )
+        .ok()?;
+        logging::trace(format!("recording codex events to {}", path.display()));
+        Some(Self {
+            path,
+            writer: BufWriter::new(file),
+        })
+    }
+
+    pub(super) fn record(&mut self, line: &str) -> io::Result<()> {
+        if disk_space_low() {
+            return Err(io::Error::other("disk space is low"));
+        }
+        self.writer.write_all(line.as_bytes())?;
+        self.writer.write_all(b"\n")
+    }
+
+    pub(super) fn finish(mut self) {
+        let _ = logging::log_result(self.writer.flush(), |error| {
+            format!(
+                "failed to flush event recording {}: {}",
+                self.path.display(),
+                error
+            )
+        });
+    }
+}
+
+pub struct ReplayedSession {
+    pub response: String,
+    pub suggestions: Vec<String>,
+    pub session_id: Option<String>,
+    pub error: Option<String>,
+}
+
+pub fn replay_recording(recording: &str) -> ReplayedSession {
+    let mut splitter = EventSplitter::default();
+    let mut response = ResponseBuffers::with_reasoning_limits(
+        DEFAULT_REASONING_WINDOW,
+        DEFAULT_REASONING_RETENTION_LIMIT,
+    );
+    let mut session_id = None;
+    let mut error = None;
+    for (index, line) in recording.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || splitter.absorb_preamble(line, index + 1) {
+            continue;
+        }
+        for event in splitter.push_line(line, index + 1) {
+            match event.get("type").and_then(Value::as_str) {
+                Some("thread.started") if session_id.is_none() => {
+                    session_id = event
+                        .get("thread_id")
+                        .and_then(Value::as_str)
+                        .map(str::to_owned);
+                }
+                Some("error") if error.is_none() => {
+                    error = event
+                        .get("message")
+                        .and_then(Value::as_str)
+                        .map(str::to_owned);
+                }
+                _ => {}
+            }
+            collect_response_text(&event, &mut response);
+        }
+    }
+    splitter.finish();
+    let (response, suggestions) = response.into_response();
+    ReplayedSession {
+        response,
+        suggestions,
+        session_id,
+        error,
+    }
+}
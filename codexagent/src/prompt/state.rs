@@ -1,34 +1,112 @@
+use std::collections::HashMap;
 use std::process::Child;
 use std::sync::{Arc, Mutex};
+use std::time::Duration;
 use std::{io, thread};
 
 use crate::logging;
+use crate::runtime::{join_within, wait_within};
+
+use super::tool_step::FailedToolStep;
 
 #[derive(Clone)]
-pub(crate) struct RunningPrompt {
-    pub(crate) id: u64,
-    pub(crate) pid: u32,
-    pub(crate) session_id: Option<String>,
+pub struct RunningPrompt {
+    pub id: u64,
+    pub pid: u32,
+    pub session_id: Option<String>,
+}
+
+#[derive(Default)]
+pub struct RunningPrompts {
+    prompts: HashMap<u64, RunningPrompt>,
+}
+
+impl RunningPrompts {
+    pub fn get(&self, prompt_id: u64) -> Option<&RunningPrompt> {
+        self.prompts.get(&prompt_id)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.prompts.is_empty()
+    }
+
+    pub fn take(&mut self, prompt_id: u64) -> Option<RunningPrompt> {
+        self.prompts.remove(&prompt_id)
+    }
+
+    pub fn register(&mut self, prompt: RunningPrompt) {
+        if let Some(previous) = self.prompts.insert(prompt.id, prompt) {
+            logging::error(format!(
+                "prompt {} registered a second process; replacing pid {}",
+                previous.id, previous.pid
+            ));
+        }
+    }
+
+    pub fn set_session_id(&mut self, prompt_id: u64, session_id: &str) {
+        if let Some(prompt) = self.prompts.get_mut(&prompt_id) {
+            prompt.session_id = Some(session_id.to_owned());
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, Eq, Ord, PartialEq, PartialOrd)]
+pub enum PromptPhase {
+    #[default]
+    Waiting,
+    Thinking,
+    Answering,
+}
+
+impl PromptPhase {
+    pub fn label(self) -> &'static str {
+        match self {
+            PromptPhase::Waiting => "waiting",
+            PromptPhase::Thinking => "thinking",
+            PromptPhase::Answering => "answering",
+        }
+    }
 }
 
 #[derive(Default)]
-pub(crate) struct PromptStreamState {
-    pub(crate) prompt_id: Option<u64>,
-    pub(crate) generation: u64,
-    pub(crate) text: String,
+pub struct PromptStreamState {
+    pub prompt_id: Option<u64>,
+    pub generation: u64,
+    pub text: String,
+    pub phase: PromptPhase,
+    failed_step: Option<FailedToolStep>,
+    warm_start: bool,
+    cancelled: bool,
+    exited: bool,
 }
 
 const RETAINED_STREAM_CAPACITY: usize = 1024;
 const MAX_IDLE_STREAM_CAPACITY: usize = 16 * 1024;
+pub(super) const PROCESS_CLEANUP_TIMEOUT: Duration = Duration::from_secs(2);
 
 impl PromptStreamState {
-    pub(crate) fn start(&mut self, prompt_id: u64) {
+    pub fn start(&mut self, prompt_id: u64) {
+        if self.prompt_id != Some(prompt_id) {
+            self.cancelled = false;
+            self.exited = false;
+        }
         self.prompt_id = Some(prompt_id);
         self.generation = self.generation.wrapping_add(1);
         self.text.clear();
+        self.phase = PromptPhase::Waiting;
+        self.failed_step = None;
+        self.warm_start = false;
+    }
+
+    pub fn advance_phase(&mut self, prompt_id: u64, phase: PromptPhase) -> bool {
+        if self.prompt_id != Some(prompt_id) || phase <= self.phase {
+            return false;
+        }
+        self.phase = phase;
+        true
     }
 
-    pub(crate) fn update(&mut self, prompt_id: u64, text: &str) -> bool {
+    pub fn update(&mut self, prompt_id: u64, text: &str) -> bool {
         if self.prompt_id != Some(prompt_id) {
             return false;
         }
@@ -45,16 +123,67 @@ impl PromptStreamState {
         true
     }
 
-    pub(crate) fn clear(&mut self, prompt_id: u64) {
+    pub fn set_failed_step(&mut self, prompt_id: u64, step: Option<FailedToolStep>) {
+        if self.prompt_id == Some(prompt_id) {
+            self.failed_step = step;
+        }
+    }
+
+    pub fn failed_step(&self, prompt_id: u64) -> Option<FailedToolStep> {
+        self.failed_step
+            .clone()
+            .filter(|_| self.prompt_id == Some(prompt_id))
+    }
+
+    pub fn set_warm_start(&mut self, prompt_id: u64, warm_start: bool) {
+        if self.prompt_id == Some(prompt_id) {
+            self.warm_start = warm_start;
+        }
+    }
+
+    pub fn warm_start(&self, prompt_id: u64) -> bool {
+        self.prompt_id == Some(prompt_id) && self.warm_start
+    }
+
+    pub fn mark_cancelled(&mut self, prompt_id: u64) -> bool {
+        if self.prompt_id != Some(prompt_id) {
+            return false;
+        }
+        self.cancelled = true;
+        self.exited
+    }
+
+    pub fn cancelled(&self, prompt_id: u64) -> bool {
+        self.prompt_id == Some(prompt_id) && self.cancelled
+    }
+
+    pub fn mark_exited(&mut self, prompt_id: u64) -> bool {
+        if self.prompt_id != Some(prompt_id) {
+            return false;
+        }
+        self.exited = true;
+        self.cancelled
+    }
+
+    pub fn has_exited(&self, prompt_id: u64) -> bool {
+        self.prompt_id == Some(prompt_id) && self.exited
+    }
+
+    pub fn clear(&mut self, prompt_id: u64) {
         if self.prompt_id == Some(prompt_id) {
             self.reset();
         }
     }
 
-    pub(crate) fn reset(&mut self) {
+    pub fn reset(&mut self) {
         self.prompt_id = None;
         self.generation = self.generation.wrapping_add(1);
         self.text.clear();
+        self.phase = PromptPhase::Waiting;
+        self.failed_step = None;
+        self.warm_start = false;
+        self.cancelled = false;
+        self.exited = false;
         if self.text.capacity() > MAX_IDLE_STREAM_CAPACITY {
             self.text.shrink_to(RETAINED_STREAM_CAPACITY);
         }
@@ -63,12 +192,15 @@ impl PromptStreamState {
 
 pub(super) struct RunningPromptGuard {
     pub(super) prompt_id: u64,
-    pub(super) running_prompt: Arc<Mutex<Option<RunningPrompt>>>,
+    pub(super) running_prompt: Arc<Mutex<RunningPrompts>>,
 }
 
 impl Drop for RunningPromptGuard {
     fn drop(&mut self) {
-        clear_running_prompt(&self.running_prompt, self.prompt_id);
+        self.running_prompt
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .take(self.prompt_id);
     }
 }
 
@@ -82,36 +214,50 @@ impl Drop for PromptProcessGuard {
         if let Some(mut child) = self.child.take() {
             match child.try_wait() {
                 Ok(Some(_)) => {}
-                Ok(None) => {
-                    if let Err(e) = child.kill() {
-                        logging::error(format!("failed to kill child process: {}", e));
-                    }
-                    if let Err(e) = child.wait() {
-                        logging::error(format!("failed to wait for child process: {}", e));
-                    }
-                }
+                Ok(None) => stop_child(&mut child),
                 Err(e) => {
                     logging::error(format!("failed to check child process status: {}", e));
-                    if let Err(e) = child.kill() {
-                        logging::error(format!("failed to kill child process: {}", e));
-                    }
-                    if let Err(e) = child.wait() {
-                        logging::error(format!("failed to wait for child process: {}", e));
-                    }
+                    stop_child(&mut child);
                 }
             }
         }
         if let Some(handle) = self.stderr_handle.take() {
-            if handle.join().is_err() {
-                logging::error("stderr reader thread panicked during cleanup");
+            match join_within(handle, PROCESS_CLEANUP_TIMEOUT) {
+                Ok(Ok(_)) => {}
+                Ok(Err(_)) => logging::error("stderr reader thread panicked during cleanup"),
+                Err(_) => logging::error(format!(
+                    "stderr reader still blocked after {} ms; another process may hold the pipe, detaching it",
+                    PROCESS_CLEANUP_TIMEOUT.as_millis()
+                )),
             }
         }
     }
 }
 
-fn clear_running_prompt(running_prompt: &Arc<Mutex<Option<RunningPrompt>>>, prompt_id: u64) {
-    let mut active = running_prompt.lock().unwrap_or_else(|e| e.into_inner());
-    if active.as_ref().is_some_and(|prompt| prompt.id == prompt_id) {
-        *active = None;
+fn stop_child(child: &mut Child) {
+    if let Err(e) = child.kill() {
+        logging::error(format!("failed to kill child process: {}", e));
+    }
+    match wait_within(child, PROCESS_CLEANUP_TIMEOUT) {
+        Ok(Some(_)) => {}
+        Ok(None) => logging::error(format!(
+            "child process {} did not exit within {} ms of being killed; leaving it",
+            child.id(),
+            PROCESS_CLEANUP_TIMEOUT.as_millis()
+        )),
+        Err(e) => logging::error(format!("failed to wait for child process: {}", e)),
+    }
+}
+
+pub(super) trait PromptProcessExt {
+    fn child_mut(&mut self, context: &str) -> io::Result<&mut Child>;
+}
+
+impl PromptProcessExt for PromptProcessGuard {
+    fn child_mut(&mut self, context: &str) -> io::Result<&mut Child> {
+        self.child.as_mut().ok_or_else(|| {
+            logging::error(format!("missing child process {}", context));
+            io::Error::other("Missing child process")
+        })
     }
 }
@@ -0,0 +1,259 @@
+use serde_json::Value;
+
+const COMMAND_ITEM_TYPE: &str = "command_execution";
+const MAX_COMMAND_CHARS: usize = 500;
+const MAX_OUTPUT_LINES: usize = 20;
+const MAX_OUTPUT_CHARS: usize = 1500;
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct FailedToolStep {
+    pub command: String,
+    pub exit_code: Option<i64>,
+    pub output: String,
+    pub session_id: Option<String>,
+}
+
+impl FailedToolStep {
+    pub fn retry_prompt(&self) -> String {
+        let mut prompt = match self.exit_code {
+            Some(code) => format!("The previous command failed with exit code {}:\n", code),
+            None => "The previous command failed:\n".to_owned(),
+        };
+        prompt.push_str(&format!("\n```\n{}\n```\n", self.command));
+        if !self.output.is_empty() {
+            prompt.push_str(&format!(
+                "\nIts output ended with:\n\n```\n{}\n```\n",
+                self.output
+            ));
+        }
+        prompt.push_str("\nPlease retry that step and continue the task.");
+        prompt
+    }
+}
+
+fn command_text(command: Option<&Value>) -> Option<String> {
+    let command = match command? {
+        Value::String(command) => command.clone(),
+        Value::Array(parts) => parts
+            .iter()
+            .filter_map(Value::as_str)
+            .collect::<Vec<_>>()
+            .join(" "),
+        _ => return None,
+    };
+    let command = command.trim();
+    (!command.is_empty()).then(|| command.chars().take(MAX_COMMAND_CHARS).collect())
+}
+
+fn output_excerpt(output: &str) -> String {
+    let lines: Vec<&str> = output.trim_end().lines().collect();
+    let tail = lines[lines.len().saturating_sub(MAX_OUTPUT_LINES)..].join("\n");
+    let skip = tail.chars().count().saturating_sub(MAX_OUTPUT_CHARS);
+    tail.chars()
+        .skip(skip)
+        .collect::<String>()
+        .trim()
+        .to_owned()
+}
+
+fn failed_command(item: &Value) -> Option<FailedToolStep> {
+    let exit_code = item.get("exit_code").and_then(Value::as_i64);
+    let failed = item.get("status").and_then(Value::as_str) == Some("failed")
+        || exit_code.is_some_and(|code| code != 0);
+    if !failed {
+        return None;
+    }
+    Some(FailedToolStep {
+        command: command_text(item.get("command"))?,
+        exit_code,
+        output: item
+            .get("aggregated_output")
+            .or_else(|| item.get("output"))
+            .and_then(Value::as_str)
+            .map(output_excerpt)
+            .unwrap_or_default(),
+        session_id: None,
+    })
+}
+
+pub(super) fn track_tool_step(event: &Value, last: &mut Option<FailedToolStep>) -> bool {
+    let kind = event.get("type").and_then(Value::as_str).unwrap_or("");
+    if !matches!(kind, "item.started" | "item.completed") {
+        return false;
+    }
+    let Some(item) = event.get("item") else {
+        return false;
+    };
+    let item_type = item
+        .get("type")
+        .or_else(|| item.get("item_type"))
+        .and_then(Value::as_str)
+        .unwrap_or("");
+    let step = match item_type {
+        "reasoning" => return false,
+        COMMAND_ITEM_TYPE if kind == "item.completed" => failed_command(item),
+        _ => None,
+    };
+    if *last == step {
+        return false;
+    }
+    *last = step;
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn failed(command: &str, exit_code: Option<i64>, output: &str) -> FailedToolStep {
+        FailedToolStep {
+            command: command.to_owned(),
+            exit_code,
+            output: output.to_owned(),
+            session_id: None,
+        }
+    }
+
+    fn track(event: Value, last: &mut Option<FailedToolStep>) -> bool {
+        track_tool_step(&event, last)
+    }
+
+    #[test]
+    fn a_command_that_exits_non_zero_is_a_failed_step() {
+        let mut last = None;
+        assert!(track(
+            json!({"type": "item.completed", "item": {
+                "type": "command_execution",
+                "command": ["cargo", "test"],
+                "exit_code": 101,
+                "aggregated_output": "running 3 tests\ntest parse ... FAILED\n\n"
+            }}),
+            &mut last
+        ));
+        assert_eq!(
+            last,
+            Some(failed(
+                "cargo test",
+                Some(101),
+                "running 3 tests\ntest parse ... FAILED"
+            ))
+        );
+    }
+
+    #[test]
+    fn a_failed_status_counts_without_an_exit_code() {
+        let mut last = None;
+        track(
+            json!({"type": "item.completed", "item": {
+                "item_type": "command_execution",
+                "command": "  rm target/lock  ",
+                "status": "failed",
+                "output": "Permission denied"
+            }}),
+            &mut last,
+        );
+        assert_eq!(
+            last,
+            Some(failed("rm target/lock", None, "Permission denied"))
+        );
+    }
+
+    #[test]
+    fn later_steps_clear_the_failure() {
+        let failure = Some(failed("cargo test", Some(1), ""));
+        for event in [
+            json!({"type": "item.completed", "item": {
+                "type": "command_execution", "command": "cargo test", "exit_code": 0
+            }}),
+            json!({"type": "item.started", "item": {
+                "type": "command_execution", "command": "cargo test", "exit_code": 1
+            }}),
+            json!({"type": "item.completed", "item": {"type": "agent_message", "text": "Done"}}),
+            json!({"type": "item.completed", "item": {"type": "file_change"}}),
+        ] {
+            let mut last = failure.clone();
+            assert!(track(event.clone(), &mut last), "{}", event);
+            assert_eq!(last, None, "{}", event);
+        }
+    }
+
+    #[test]
+    fn reasoning_and_other_events_leave_the_failure() {
+        let failure = Some(failed("cargo test", Some(1), ""));
+        for event in [
+            json!({"type": "item.completed", "item": {"type": "reasoning", "text": "Retrying"}}),
+            json!({"type": "item.started", "item": {"type": "reasoning"}}),
+            json!({"type": "turn.completed", "usage": {}}),
+            json!({"type": "item.completed"}),
+            json!({"msg": {"type": "exec_command_end", "exit_code": 1}}),
+        ] {
+            let mut last = failure.clone();
+            assert!(!track(event.clone(), &mut last), "{}", event);
+            assert_eq!(last, failure, "{}", event);
+        }
+    }
+
+    #[test]
+    fn a_failure_without_a_command_cannot_be_retried() {
+        for command in [json!(null), json!("  "), json!({"argv": ["ls"]})] {
+            let mut last = None;
+            assert!(!track(
+                json!({"type": "item.completed", "item": {
+                    "type": "command_execution", "command": command, "exit_code": 2
+                }}),
+                &mut last
+            ));
+            assert_eq!(last, None);
+        }
+    }
+
+    #[test]
+    fn the_same_failure_twice_is_not_a_change() {
+        let event = json!({"type": "item.completed", "item": {
+            "type": "command_execution", "command": "make", "exit_code": 2
+        }});
+        let mut last = None;
+        assert!(track(event.clone(), &mut last));
+        assert!(!track(event, &mut last));
+    }
+
+    #[test]
+    fn long_commands_and_output_are_cut_to_their_limits() {
+        let output: String = (1..=40).map(|line| format!("line {}\n", line)).collect();
+        let mut last = None;
+        track(
+            json!({"type": "item.completed", "item": {
+                "type": "command_execution",
+                "command": "x".repeat(MAX_COMMAND_CHARS + 10),
+                "exit_code": 1,
+                "aggregated_output": output
+            }}),
+            &mut last,
+        );
+        let step = last.unwrap();
+        assert_eq!(step.command.chars().count(), MAX_COMMAND_CHARS);
+        assert_eq!(step.output.lines().count(), MAX_OUTPUT_LINES);
+        assert!(step.output.starts_with("line 21\n"), "{}", step.output);
+        assert!(step.output.ends_with("line 40"));
+
+        let wide = "y".repeat(MAX_OUTPUT_CHARS * 2);
+        assert_eq!(output_excerpt(&wide).chars().count(), MAX_OUTPUT_CHARS);
+    }
+
+    #[test]
+    fn retry_prompt_quotes_the_command_and_its_output() {
+        assert_eq!(
+            failed("cargo test", Some(101), "test parse ... FAILED").retry_prompt(),
+            "The previous command failed with exit code 101:\n\n```\ncargo test\n```\n\n\
+             Its output ended with:\n\n```\ntest parse ... FAILED\n```\n\n\
+             Please retry that step and continue the task."
+        );
+        assert_eq!(
+            failed("rm target/lock", None, "").retry_prompt(),
+            "The previous command failed:\n\n```\nrm target/lock\n```\n\n\
+             Please retry that step and continue the task."
+        );
+    }
+}
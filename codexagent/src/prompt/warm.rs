@@ -0,0 +1,273 @@
+use std::io::{self, Write};
+use std::process::Child;
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::config::project::ProjectConfig;
+use crate::logging;
+use crate::runtime::wait_within;
+
+use super::codex::{codex_env_overrides, codex_launch_key, spawn_codex_reading_stdin};
+use super::execution::kill_prompt_process;
+use super::state::PROCESS_CLEANUP_TIMEOUT;
+
+pub const WARM_CODEX_MAX_AGE: Duration = Duration::from_secs(5 * 60);
+
+static WARM_CODEX: Mutex<Option<WarmCodex>> = Mutex::new(None);
+static PREWARM_LOCK: Mutex<()> = Mutex::new(());
+
+struct WarmCodex {
+    child: Child,
+    key: Vec<String>,
+    ready_at: Instant,
+}
+
+impl WarmCodex {
+    fn stop(mut self, reason: &str) {
+        let pid = self.child.id();
+        logging::trace(format!("discarding warm codex process {}: {}", pid, reason));
+        drop(self.child.stdin.take());
+        if let Ok(None) = self.child.try_wait()
+            && let Err(error) = kill_prompt_process(pid)
+        {
+            logging::error(format!(
+                "failed to stop warm codex process {}: {}",
+                pid, error
+            ));
+        }
+        match wait_within(&mut self.child, PROCESS_CLEANUP_TIMEOUT) {
+            Ok(Some(_)) => {}
+            Ok(None) => logging::error(format!(
+                "warm codex process {} did not exit within {} ms; leaving it",
+                pid,
+                PROCESS_CLEANUP_TIMEOUT.as_millis()
+            )),
+            Err(error) => logging::error(format!(
+                "failed to wait for warm codex process {}: {}",
+                pid, error
+            )),
+        }
+    }
+
+    fn exited(&mut self) -> bool {
+        !matches!(self.child.try_wait(), Ok(None))
+    }
+}
+
+fn take_slot() -> Option<WarmCodex> {
+    WARM_CODEX.lock().unwrap_or_else(|e| e.into_inner()).take()
+}
+
+pub fn prewarm_codex(session_id: Option<String>, project: Option<ProjectConfig>) {
+    thread::spawn(move || {
+        let _ = logging::catch_panic("warm codex thread", || {
+            let env_overrides = codex_env_overrides(project.as_ref());
+            let key = codex_launch_key(session_id.as_deref(), project.as_ref(), &env_overrides);
+            let session = if session_id.is_some() {
+                "resumed"
+            } else {
+                "new"
+            };
+            prewarm(key, session, WARM_CODEX_MAX_AGE, || {
+                spawn_codex_reading_stdin(session_id.as_deref(), project.as_ref(), &env_overrides)
+            });
+        });
+    });
+}
+
+fn prewarm(
+    key: Vec<String>,
+    session: &str,
+    max_age: Duration,
+    spawn: impl FnOnce() -> io::Result<Child>,
+) {
+    let serial = PREWARM_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    if let Some(mut warm) = take_slot() {
+        if warm.key == key && !warm.exited() && warm.ready_at.elapsed() < max_age {
+            *WARM_CODEX.lock().unwrap_or_else(|e| e.into_inner()) = Some(warm);
+            return;
+        }
+        warm.stop("the next prompt needs a different command");
+    }
+    let started_at = Instant::now();
+    let child = match spawn() {
+        Ok(child) => child,
+        Err(error) => {
+            logging::error(format!("failed to start warm codex process: {}", error));
+            return;
+        }
+    };
+    let pid = child.id();
+    logging::trace(format!(
+        "warm codex process {} started in {} ms for a {} session",
+        pid,
+        started_at.elapsed().as_millis(),
+        session
+    ));
+    let ready_at = Instant::now();
+    let replaced = WARM_CODEX
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .replace(WarmCodex {
+            child,
+            key,
+            ready_at,
+        });
+    if let Some(replaced) = replaced {
+        replaced.stop("a newer warm process replaced it");
+    }
+    // The next prewarm only needs the lock while it swaps the slot, not for the
+    // whole lifetime of this process.
+    drop(serial);
+    thread::sleep(max_age);
+    expire_warm_codex(pid, ready_at, max_age);
+}
+
+fn expire_warm_codex(pid: u32, ready_at: Instant, max_age: Duration) {
+    let expired = {
+        let mut slot = WARM_CODEX.lock().unwrap_or_else(|e| e.into_inner());
+        if slot
+            .as_ref()
+            .is_some_and(|warm| warm.child.id() == pid && warm.ready_at == ready_at)
+        {
+            slot.take()
+        } else {
+            None
+        }
+    };
+    if let Some(warm) = expired {
+        warm.stop(&format!("unused for {} s", max_age.as_secs()));
+    }
+}
+
+pub fn discard_warm_codex(reason: &str) {
+    if let Some(warm) = take_slot() {
+        warm.stop(reason);
+    }
+}
+
+pub fn discard_warm_codex_async(reason: &'static str) {
+    if WARM_CODEX
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .is_none()
+    {
+        return;
+    }
+    thread::spawn(move || discard_warm_codex(reason));
+}
+
+pub(super) fn take_warm_codex(
+    prompt: &str,
+    session_id: Option<&str>,
+    project: Option<&ProjectConfig>,
+    env_overrides: &[(String, String)],
+) -> Option<Child> {
+    let mut warm = take_slot()?;
+    let age = warm.ready_at.elapsed();
+    if warm.exited() {
+        warm.stop("it exited before a prompt arrived");
+        return None;
+    }
+    if age >= WARM_CODEX_MAX_AGE {
+        warm.stop("it is stale");
+        return None;
+    }
+    if warm.key != codex_launch_key(session_id, project, env_overrides) {
+        logging::trace("keeping the warm codex process; this prompt needs a different command");
+        *WARM_CODEX.lock().unwrap_or_else(|e| e.into_inner()) = Some(warm);
+        return None;
+    }
+    let pid = warm.child.id();
+    if let Err(error) = feed_prompt(&mut warm.child, prompt) {
+        logging::error(format!(
+            "failed to send the prompt to warm codex process {}: {}",
+            pid, error
+        ));
+        warm.stop("it did not accept the prompt");
+        return None;
+    }
+    logging::trace(format!(
+        "using warm codex process {} started {} ms ago",
+        pid,
+        age.as_millis()
+    ));
+    Some(warm.child)
+}
+
+fn feed_prompt(child: &mut Child, prompt: &str) -> io::Result<()> {
+    let mut stdin = child
+        .stdin
+        .take()
+        .ok_or_else(|| io::Error::other("stdin was not captured"))?;
+    stdin.write_all(prompt.as_bytes())?;
+    stdin.flush()
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use std::process::{Command, Stdio};
+
+    use crate::runtime::BackgroundCommand;
+
+    use super::*;
+
+    static SERIAL: Mutex<()> = Mutex::new(());
+
+    fn idle_child() -> io::Result<Child> {
+        Command::new("sleep")
+            .arg("30")
+            .own_process_group()
+            .stdin(Stdio::piped())
+            .spawn()
+    }
+
+    fn warm_key() -> Option<Vec<String>> {
+        WARM_CODEX
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .as_ref()
+            .map(|warm| warm.key.clone())
+    }
+
+    fn wait_for_key(key: &str, within: Duration) -> bool {
+        let deadline = Instant::now() + within;
+        while Instant::now() < deadline {
+            if warm_key().is_some_and(|warm| warm == [key]) {
+                return true;
+            }
+            thread::sleep(Duration::from_millis(5));
+        }
+        false
+    }
+
+    fn spawn_prewarm(key: &'static str, max_age: Duration) -> thread::JoinHandle<()> {
+        thread::spawn(move || prewarm(vec![key.to_owned()], "new", max_age, idle_child))
+    }
+
+    #[test]
+    fn a_second_prewarm_does_not_wait_for_the_first_to_expire() {
+        let _serial = SERIAL.lock().unwrap_or_else(|e| e.into_inner());
+        discard_warm_codex("test setup");
+        spawn_prewarm("first", Duration::from_secs(60));
+        assert!(wait_for_key("first", Duration::from_secs(5)));
+
+        spawn_prewarm("second", Duration::from_secs(60));
+        assert!(
+            wait_for_key("second", Duration::from_secs(5)),
+            "the second prewarm was blocked by the first"
+        );
+        discard_warm_codex("test done");
+    }
+
+    #[test]
+    fn an_unused_warm_process_expires() {
+        let _serial = SERIAL.lock().unwrap_or_else(|e| e.into_inner());
+        discard_warm_codex("test setup");
+        spawn_prewarm("short", Duration::from_millis(50))
+            .join()
+            .unwrap();
+        assert_eq!(warm_key(), None);
+    }
+}
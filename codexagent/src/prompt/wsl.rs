@@ -0,0 +1,211 @@
+use std::env;
+use std::process::Command;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use crate::logging;
+use crate::runtime::{BackgroundCommand, current_cwd_text};
+
+const WSL_EXE: &str = "wsl.exe";
+const TOKEN_PREFIX: &str = "codexagent-";
+const LAUNCH_SCRIPT: &str =
+    r#"echo $$ > "/tmp/$0.pid"; codex "$@"; status=$?; rm -f "/tmp/$0.pid"; exit $status"#;
+const KILL_SCRIPT: &str = r#"f="/tmp/$0.pid"; [ -f "$f" ] || exit 0; pid=$(cat "$f"); pkill -KILL -P "$pid"; kill -KILL "$pid"; rm -f "$f""#;
+
+static NEXT_TOKEN: AtomicU64 = AtomicU64::new(0);
+static WSL_PROCESSES: Mutex<Vec<WslProcess>> = Mutex::new(Vec::new());
+
+struct WslProcess {
+    pid: u32,
+    distro: Option<String>,
+    token: String,
+}
+
+pub(super) fn wsl_command(distro: Option<&str>, args: &[&str]) -> Command {
+    let token = format!(
+        "{}{}-{}",
+        TOKEN_PREFIX,
+        std::process::id(),
+        NEXT_TOKEN.fetch_add(1, Ordering::Relaxed)
+    );
+    let mut command = base_command(distro);
+    if let Some(cwd) = windows_to_wsl_path(&current_cwd_text()) {
+        command.arg("--cd").arg(cwd);
+    }
+    command
+        .args(["--exec", "bash", "-lc", LAUNCH_SCRIPT, &token])
+        .args(args);
+    command
+}
+
+fn base_command(distro: Option<&str>) -> Command {
+    let mut command = Command::new(WSL_EXE);
+    if let Some(distro) = distro {
+        command.args(["-d", distro]);
+    }
+    command
+}
+
+pub(super) fn forward_env<'a>(command: &mut Command, names: impl Iterator<Item = &'a str>) {
+    if command.get_program() != WSL_EXE {
+        return;
+    }
+    let mut wslenv = env::var("WSLENV").unwrap_or_default();
+    let initial_len = wslenv.len();
+    for name in names {
+        let forwarded = wslenv
+            .split(':')
+            .any(|entry| entry.split('/').next() == Some(name));
+        if !forwarded {
+            if !wslenv.is_empty() {
+                wslenv.push(':');
+            }
+            wslenv.push_str(name);
+        }
+    }
+    if wslenv.len() != initial_len {
+        command.env("WSLENV", wslenv);
+    }
+}
+
+pub(super) fn windows_to_wsl_path(path: &str) -> Option<String> {
+    let path = path.trim();
+    if path.starts_with('/') {
+        return Some(path.to_owned());
+    }
+    let unc = path
+        .strip_prefix(r"\\wsl$\")
+        .or_else(|| path.strip_prefix(r"\\wsl.localhost\"));
+    if let Some(rest) = unc {
+        let linux = rest.split_once('\\').map_or("", |(_, linux)| linux);
+        return Some(format!("/{}", linux.replace('\\', "/")));
+    }
+    let mut chars = path.chars();
+    let drive = chars.next().filter(char::is_ascii_alphabetic)?;
+    if chars.next() != Some(':') {
+        return None;
+    }
+    let rest = chars.as_str().trim_start_matches(['\\', '/']);
+    let mut linux = format!("/mnt/{}", drive.to_ascii_lowercase());
+    if !rest.is_empty() {
+        linux.push('/');
+        linux.push_str(&rest.replace('\\', "/"));
+    }
+    Some(linux.trim_end_matches('/').to_owned())
+}
+
+pub(super) fn track_wsl_process(pid: u32, command: &Command) {
+    if command.get_program() != WSL_EXE {
+        return;
+    }
+    let args: Vec<String> = command
+        .get_args()
+        .map(|arg| arg.to_string_lossy().into_owned())
+        .collect();
+    let distro = args
+        .first()
+        .filter(|arg| *arg == "-d")
+        .and_then(|_| args.get(1).cloned());
+    let Some(token) = args
+        .iter()
+        .find(|arg| arg.starts_with(TOKEN_PREFIX))
+        .cloned()
+    else {
+        return;
+    };
+    let mut processes = WSL_PROCESSES.lock().unwrap_or_else(|e| e.into_inner());
+    processes.push(WslProcess { pid, distro, token });
+}
+
+pub(super) fn forget_wsl_process(pid: u32) {
+    let mut processes = WSL_PROCESSES.lock().unwrap_or_else(|e| e.into_inner());
+    processes.retain(|process| process.pid != pid);
+}
+
+pub(super) fn kill_wsl_process(pid: u32) {
+    let process = {
+        let mut processes = WSL_PROCESSES.lock().unwrap_or_else(|e| e.into_inner());
+        processes
+            .iter()
+            .position(|process| process.pid == pid)
+            .map(|index| processes.swap_remove(index))
+    };
+    let Some(process) = process else {
+        return;
+    };
+    let result = base_command(process.distro.as_deref())
+        .args(["--exec", "sh", "-c", KILL_SCRIPT, &process.token])
+        .hide_window()
+        .status();
+    match result {
+        Ok(status) if status.success() => {
+            logging::trace(format!("killed WSL-side codex for pid {}", pid));
+        }
+        Ok(status) => logging::error(format!(
+            "WSL-side kill for pid {} exited with {}",
+            pid, status
+        )),
+        Err(error) => logging::error(format!(
+            "failed to run WSL-side kill for pid {}: {}",
+            pid, error
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn drive_paths_map_under_mnt() {
+        for (windows, linux) in [
+            (r"C:\Users\dev\repo", "/mnt/c/Users/dev/repo"),
+            (r"D:\", "/mnt/d"),
+            ("e:", "/mnt/e"),
+            ("c:/work/repo/", "/mnt/c/work/repo"),
+            (r"  C:\src\app  ", "/mnt/c/src/app"),
+        ] {
+            assert_eq!(windows_to_wsl_path(windows).as_deref(), Some(linux));
+        }
+    }
+
+    #[test]
+    fn spaces_are_kept_verbatim() {
+        assert_eq!(
+            windows_to_wsl_path(r"C:\Users\Jane Doe\My Repo").as_deref(),
+            Some("/mnt/c/Users/Jane Doe/My Repo")
+        );
+        assert_eq!(
+            windows_to_wsl_path(r"\\wsl$\Ubuntu\home\jane\my repo").as_deref(),
+            Some("/home/jane/my repo")
+        );
+    }
+
+    #[test]
+    fn wsl_shares_drop_the_distro() {
+        assert_eq!(
+            windows_to_wsl_path(r"\\wsl$\Ubuntu\home\dev\repo").as_deref(),
+            Some("/home/dev/repo")
+        );
+        assert_eq!(
+            windows_to_wsl_path(r"\\wsl.localhost\Ubuntu-22.04\home\dev").as_deref(),
+            Some("/home/dev")
+        );
+        assert_eq!(windows_to_wsl_path(r"\\wsl$\Ubuntu").as_deref(), Some("/"));
+    }
+
+    #[test]
+    fn linux_paths_pass_through() {
+        assert_eq!(
+            windows_to_wsl_path("/home/dev/repo").as_deref(),
+            Some("/home/dev/repo")
+        );
+    }
+
+    #[test]
+    fn other_paths_are_not_translated() {
+        for path in [r"\\server\share\repo", "repo", r"1:\repo", "", "C"] {
+            assert_eq!(windows_to_wsl_path(path), None, "{:?}", path);
+        }
+    }
+}
@@ -0,0 +1,151 @@
+use std::sync::{Mutex, mpsc};
+use std::thread;
+use std::time::Duration;
+
+#[cfg(windows)]
+use windows_sys::Win32::System::Registry::{HKEY_CURRENT_USER, RRF_RT_REG_DWORD, RegGetValueW};
+#[cfg(windows)]
+use windows_sys::Win32::UI::Accessibility::{HCF_HIGHCONTRASTON, HIGHCONTRASTW};
+#[cfg(windows)]
+use windows_sys::Win32::UI::WindowsAndMessaging::{SPI_GETHIGHCONTRAST, SystemParametersInfoW};
+
+use crate::events::{AppEvent, RepaintHandle};
+use crate::logging;
+
+#[cfg(windows)]
+use super::to_wide;
+
+#[cfg(windows)]
+const PERSONALIZE_KEY: &str = r"Software\Microsoft\Windows\CurrentVersion\Themes\Personalize";
+const ENABLE_TRANSPARENCY_VALUE: &str = "EnableTransparency";
+const APPS_USE_LIGHT_THEME_VALUE: &str = "AppsUseLightTheme";
+const ACCESSIBILITY_POLL_INTERVAL: Duration = Duration::from_secs(3);
+
+static CACHED_PREFS: Mutex<Option<AccessibilityPrefs>> = Mutex::new(None);
+static CACHED_LIGHT_THEME: Mutex<Option<bool>> = Mutex::new(None);
+
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct AccessibilityPrefs {
+    pub high_contrast: bool,
+    pub reduced_transparency: bool,
+}
+
+impl AccessibilityPrefs {
+    pub fn wants_opaque(self) -> bool {
+        self.high_contrast || self.reduced_transparency
+    }
+}
+
+pub fn accessibility_prefs() -> AccessibilityPrefs {
+    *CACHED_PREFS
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .get_or_insert_with(query_accessibility_prefs)
+}
+
+pub fn system_light_theme() -> bool {
+    *CACHED_LIGHT_THEME
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .get_or_insert_with(light_theme_enabled)
+}
+
+pub fn watch_accessibility_prefs(tx: mpsc::Sender<AppEvent>, repaint: RepaintHandle) {
+    thread::spawn(move || {
+        let _ = logging::catch_panic("accessibility watcher thread", || {
+            loop {
+                thread::sleep(ACCESSIBILITY_POLL_INTERVAL);
+                let prefs = query_accessibility_prefs();
+                let previous = CACHED_PREFS
+                    .lock()
+                    .unwrap_or_else(|e| e.into_inner())
+                    .replace(prefs);
+                if previous != Some(prefs) {
+                    logging::trace(format!("accessibility preferences changed: {:?}", prefs));
+                    if tx.send(AppEvent::AccessibilityChanged(prefs)).is_err() {
+                        break;
+                    }
+                    repaint.request_repaint();
+                }
+                let light = light_theme_enabled();
+                let previous = CACHED_LIGHT_THEME
+                    .lock()
+                    .unwrap_or_else(|e| e.into_inner())
+                    .replace(light);
+                if previous != Some(light) {
+                    logging::trace(format!(
+                        "system app theme changed to {}",
+                        if light { "light" } else { "dark" }
+                    ));
+                    if tx.send(AppEvent::SystemThemeChanged(light)).is_err() {
+                        break;
+                    }
+                    repaint.request_repaint();
+                }
+            }
+        });
+    });
+}
+
+fn query_accessibility_prefs() -> AccessibilityPrefs {
+    AccessibilityPrefs {
+        high_contrast: high_contrast_enabled(),
+        reduced_transparency: !transparency_enabled(),
+    }
+}
+
+#[cfg(windows)]
+fn high_contrast_enabled() -> bool {
+    let mut info = HIGHCONTRASTW {
+        cbSize: size_of::<HIGHCONTRASTW>() as u32,
+        dwFlags: 0,
+        lpszDefaultScheme: std::ptr::null_mut(),
+    };
+    let ok = unsafe {
+        SystemParametersInfoW(
+            SPI_GETHIGHCONTRAST,
+            info.cbSize,
+            (&mut info as *mut HIGHCONTRASTW).cast(),
+            0,
+        )
+    };
+    ok != 0 && info.dwFlags & HCF_HIGHCONTRASTON != 0
+}
+
+#[cfg(not(windows))]
+fn high_contrast_enabled() -> bool {
+    false
+}
+
+fn transparency_enabled() -> bool {
+    read_personalize_value(ENABLE_TRANSPARENCY_VALUE).is_none_or(|data| data != 0)
+}
+
+fn light_theme_enabled() -> bool {
+    read_personalize_value(APPS_USE_LIGHT_THEME_VALUE).is_none_or(|data| data != 0)
+}
+
+#[cfg(windows)]
+fn read_personalize_value(name: &str) -> Option<u32> {
+    let key = to_wide(PERSONALIZE_KEY);
+    let value = to_wide(name);
+    let mut data = 0u32;
+    let mut size = size_of::<u32>() as u32;
+    let status = unsafe {
+        RegGetValueW(
+            HKEY_CURRENT_USER,
+            key.as_ptr(),
+            value.as_ptr(),
+            RRF_RT_REG_DWORD,
+            std::ptr::null_mut(),
+            (&mut data as *mut u32).cast(),
+            &mut size,
+        )
+    };
+    (status == 0).then_some(data)
+}
+
+#[cfg(not(windows))]
+fn read_personalize_value(_name: &str) -> Option<u32> {
+    None
+}
@@ -0,0 +1,242 @@
+#[cfg(windows)]
+use std::cell::RefCell;
+#[cfg(windows)]
+use std::io;
+use std::sync::mpsc;
+#[cfg(windows)]
+use std::thread::{self, JoinHandle};
+
+#[cfg(windows)]
+use windows_sys::Win32::Foundation::{HWND, LPARAM, LRESULT, WPARAM};
+#[cfg(windows)]
+use windows_sys::Win32::System::DataExchange::{
+    AddClipboardFormatListener, GetClipboardOwner, GetClipboardSequenceNumber,
+    RemoveClipboardFormatListener,
+};
+#[cfg(windows)]
+use windows_sys::Win32::System::LibraryLoader::GetModuleHandleW;
+#[cfg(windows)]
+use windows_sys::Win32::System::Threading::GetCurrentProcessId;
+#[cfg(windows)]
+use windows_sys::Win32::UI::WindowsAndMessaging::{
+    CreateWindowExW, DefWindowProcW, DestroyWindow, DispatchMessageW, GetMessageW,
+    GetWindowThreadProcessId, HWND_MESSAGE, KillTimer, MSG, PostMessageW, PostQuitMessage,
+    RegisterClassW, SetTimer, WM_CLIPBOARDUPDATE, WM_CLOSE, WM_DESTROY, WM_TIMER, WNDCLASSW,
+};
+
+use crate::events::{AppEvent, RepaintHandle};
+#[cfg(windows)]
+use crate::logging;
+
+#[cfg(windows)]
+use super::{read_clipboard_text, to_wide};
+
+#[cfg(windows)]
+const CLIPBOARD_WINDOW_CLASS: &str = "CodexAgentClipboardWatcher";
+#[cfg(windows)]
+const DEBOUNCE_TIMER_ID: usize = 1;
+#[cfg(windows)]
+const DEBOUNCE_MS: u32 = 400;
+
+#[cfg(windows)]
+struct ClipboardListener {
+    tx: mpsc::Sender<AppEvent>,
+    repaint: RepaintHandle,
+    last_sequence: u32,
+}
+
+#[cfg(windows)]
+thread_local! {
+    static LISTENER: RefCell<Option<ClipboardListener>> = const { RefCell::new(None) };
+}
+
+#[cfg(windows)]
+pub struct ClipboardWatcher {
+    hwnd: usize,
+    thread: Option<JoinHandle<()>>,
+}
+
+#[cfg(windows)]
+impl Drop for ClipboardWatcher {
+    fn drop(&mut self) {
+        if unsafe { PostMessageW(self.hwnd as HWND, WM_CLOSE, 0, 0) } == 0 {
+            logging::error(format!(
+                "failed to stop clipboard watcher: {}",
+                io::Error::last_os_error()
+            ));
+            return;
+        }
+        if let Some(thread) = self.thread.take()
+            && thread.join().is_err()
+        {
+            logging::error("clipboard watcher thread panicked while stopping");
+        }
+        logging::trace("clipboard watcher stopped");
+    }
+}
+
+#[cfg(windows)]
+pub fn watch_clipboard(
+    tx: mpsc::Sender<AppEvent>,
+    repaint: RepaintHandle,
+) -> Option<ClipboardWatcher> {
+    let (ready_tx, ready_rx) = mpsc::channel();
+    let thread = thread::spawn(move || {
+        let _ = logging::catch_panic("clipboard watcher thread", || {
+            let hwnd = match create_listener_window() {
+                Ok(hwnd) => hwnd,
+                Err(error) => {
+                    let _ = ready_tx.send(Err(error));
+                    return;
+                }
+            };
+            LISTENER.with(|listener| {
+                *listener.borrow_mut() = Some(ClipboardListener {
+                    tx,
+                    repaint,
+                    last_sequence: unsafe { GetClipboardSequenceNumber() },
+                });
+            });
+            if unsafe { AddClipboardFormatListener(hwnd) } == 0 {
+                let error = io::Error::last_os_error();
+                unsafe { DestroyWindow(hwnd) };
+                let _ = ready_tx.send(Err(error));
+                return;
+            }
+            let _ = ready_tx.send(Ok(hwnd as usize));
+            let mut message: MSG = unsafe { std::mem::zeroed() };
+            while unsafe { GetMessageW(&mut message, std::ptr::null_mut(), 0, 0) } > 0 {
+                unsafe { DispatchMessageW(&message) };
+            }
+            LISTENER.with(|listener| listener.borrow_mut().take());
+        });
+    });
+    match ready_rx.recv() {
+        Ok(Ok(hwnd)) => {
+            logging::trace("clipboard watcher started");
+            Some(ClipboardWatcher {
+                hwnd,
+                thread: Some(thread),
+            })
+        }
+        Ok(Err(error)) => {
+            logging::error(format!("failed to start clipboard watcher: {}", error));
+            let _ = thread.join();
+            None
+        }
+        Err(_) => {
+            logging::error("clipboard watcher thread exited before it started");
+            let _ = thread.join();
+            None
+        }
+    }
+}
+
+#[cfg(windows)]
+fn create_listener_window() -> io::Result<HWND> {
+    let class_name = to_wide(CLIPBOARD_WINDOW_CLASS);
+    let instance = unsafe { GetModuleHandleW(std::ptr::null()) };
+    let class = WNDCLASSW {
+        lpfnWndProc: Some(clipboard_window_proc),
+        hInstance: instance,
+        lpszClassName: class_name.as_ptr(),
+        ..unsafe { std::mem::zeroed() }
+    };
+    unsafe { RegisterClassW(&class) };
+    let hwnd = unsafe {
+        CreateWindowExW(
+            0,
+            class_name.as_ptr(),
+            class_name.as_ptr(),
+            0,
+            0,
+            0,
+            0,
+            0,
+            HWND_MESSAGE,
+            std::ptr::null_mut(),
+            instance,
+            std::ptr::null(),
+        )
+    };
+    if hwnd.is_null() {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(hwnd)
+}
+
+#[cfg(windows)]
+fn owned_by_this_process() -> bool {
+    let owner = unsafe { GetClipboardOwner() };
+    if owner.is_null() {
+        return false;
+    }
+    let mut process_id = 0;
+    unsafe { GetWindowThreadProcessId(owner, &mut process_id) };
+    process_id == unsafe { GetCurrentProcessId() }
+}
+
+#[cfg(windows)]
+fn publish_clipboard_text() {
+    LISTENER.with(|listener| {
+        let mut listener = listener.borrow_mut();
+        let Some(listener) = listener.as_mut() else {
+            return;
+        };
+        let sequence = unsafe { GetClipboardSequenceNumber() };
+        if sequence == listener.last_sequence {
+            return;
+        }
+        listener.last_sequence = sequence;
+        if owned_by_this_process() {
+            logging::trace("ignoring clipboard change made by this app");
+            return;
+        }
+        let Some(text) = read_clipboard_text().filter(|text| !text.trim().is_empty()) else {
+            return;
+        };
+        if listener.tx.send(AppEvent::ClipboardText(text)).is_ok() {
+            listener.repaint.request_repaint();
+        }
+    });
+}
+
+#[cfg(windows)]
+unsafe extern "system" fn clipboard_window_proc(
+    hwnd: HWND,
+    message: u32,
+    wparam: WPARAM,
+    lparam: LPARAM,
+) -> LRESULT {
+    match message {
+        WM_CLIPBOARDUPDATE => {
+            unsafe { SetTimer(hwnd, DEBOUNCE_TIMER_ID, DEBOUNCE_MS, None) };
+            0
+        }
+        WM_TIMER if wparam == DEBOUNCE_TIMER_ID => {
+            unsafe { KillTimer(hwnd, DEBOUNCE_TIMER_ID) };
+            publish_clipboard_text();
+            0
+        }
+        WM_DESTROY => {
+            unsafe {
+                KillTimer(hwnd, DEBOUNCE_TIMER_ID);
+                RemoveClipboardFormatListener(hwnd);
+                PostQuitMessage(0);
+            }
+            0
+        }
+        _ => unsafe { DefWindowProcW(hwnd, message, wparam, lparam) },
+    }
+}
+
+#[cfg(not(windows))]
+pub struct ClipboardWatcher;
+
+#[cfg(not(windows))]
+pub fn watch_clipboard(
+    _tx: mpsc::Sender<AppEvent>,
+    _repaint: RepaintHandle,
+) -> Option<ClipboardWatcher> {
+    None
+}
@@ -0,0 +1,94 @@
+use std::process::Command;
+
+#[cfg(unix)]
+use std::os::unix::process::CommandExt;
+#[cfg(windows)]
+use std::os::windows::process::CommandExt;
+
+#[cfg(windows)]
+use windows_sys::Win32::System::Threading::{CREATE_NEW_CONSOLE, CREATE_NO_WINDOW};
+
+pub trait BackgroundCommand {
+    fn hide_window(&mut self) -> &mut Self;
+    fn new_console(&mut self) -> &mut Self;
+    fn own_process_group(&mut self) -> &mut Self;
+}
+
+#[cfg(windows)]
+impl BackgroundCommand for Command {
+    fn hide_window(&mut self) -> &mut Self {
+        self.creation_flags(CREATE_NO_WINDOW)
+    }
+
+    fn new_console(&mut self) -> &mut Self {
+        self.creation_flags(CREATE_NEW_CONSOLE)
+    }
+
+    fn own_process_group(&mut self) -> &mut Self {
+        self
+    }
+}
+
+#[cfg(unix)]
+impl BackgroundCommand for Command {
+    fn hide_window(&mut self) -> &mut Self {
+        self
+    }
+
+    fn new_console(&mut self) -> &mut Self {
+        self
+    }
+
+    fn own_process_group(&mut self) -> &mut Self {
+        self.process_group(0)
+    }
+}
+
+pub fn shell_command(command_line: &str) -> Command {
+    #[cfg(windows)]
+    {
+        let mut command = Command::new("cmd.exe");
+        command.arg("/C").raw_arg(command_line);
+        command
+    }
+    #[cfg(unix)]
+    {
+        let mut command = Command::new("sh");
+        command.arg("-c").arg(command_line);
+        command
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(unix)]
+    #[test]
+    fn shell_command_runs_through_sh() {
+        let status = shell_command("exit 3").status().unwrap();
+        assert_eq!(status.code(), Some(3));
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn shell_command_runs_through_cmd() {
+        let status = shell_command("exit /b 3").hide_window().status().unwrap();
+        assert_eq!(status.code(), Some(3));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn own_process_group_leads_a_new_group() {
+        let output = Command::new("sh")
+            .args(["-c", "ps -o pgid= -p $$"])
+            .own_process_group()
+            .output()
+            .unwrap();
+        let pgid: u32 = String::from_utf8_lossy(&output.stdout)
+            .trim()
+            .parse()
+            .unwrap();
+        assert_ne!(pgid, std::process::id());
+    }
+}
@@ -0,0 +1,61 @@
+use std::io;
+use std::path::Path;
+
+#[cfg(windows)]
+use windows_sys::Win32::Storage::FileSystem::GetDiskFreeSpaceExW;
+
+use crate::config::paths;
+use crate::logging;
+
+use super::probe::debug_assert_off_frame_path;
+#[cfg(windows)]
+use super::to_wide;
+
+const MEGABYTE: u64 = 1024 * 1024;
+
+#[cfg(windows)]
+fn free_disk_space(dir: &Path) -> io::Result<u64> {
+    let wide = to_wide(&dir.to_string_lossy());
+    let mut available = 0u64;
+    let ok = unsafe {
+        GetDiskFreeSpaceExW(
+            wide.as_ptr(),
+            &mut available,
+            std::ptr::null_mut(),
+            std::ptr::null_mut(),
+        )
+    };
+    if ok == 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(available)
+}
+
+#[cfg(not(windows))]
+fn free_disk_space(_dir: &Path) -> io::Result<u64> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "free disk space is only read on Windows",
+    ))
+}
+
+pub fn app_data_free_mb() -> Option<u64> {
+    debug_assert_off_frame_path("free disk space check");
+    let paths = paths::get();
+    let config_dir = paths.settings_file.parent().unwrap_or(&paths.log_dir);
+    [config_dir, paths.log_dir.as_path()]
+        .into_iter()
+        .filter_map(|dir| {
+            free_disk_space(dir)
+                .map_err(|error| {
+                    logging::trace(format!(
+                        "failed to read free disk space for {}: {}",
+                        dir.display(),
+                        error
+                    ))
+                })
+                .ok()
+        })
+        .min()
+        .map(|free| free / MEGABYTE)
+}
@@ -0,0 +1,287 @@
+use std::env;
+use std::ffi::OsString;
+use std::path::PathBuf;
+use std::sync::OnceLock;
+
+#[cfg(windows)]
+use windows_sys::Win32::Globalization::GetUserDefaultLocaleName;
+
+use crate::config::paths::{self, path_degradation_from};
+use crate::logging;
+
+#[cfg(windows)]
+const NPM_PREFIX_ENV: &str = "CODEXAGENT_NPM_PREFIX";
+#[cfg(windows)]
+const NODE_DIR_ENV: &str = "CODEXAGENT_NODE_DIR";
+#[cfg(windows)]
+const START_MENU_DIR_ENV: &str = "CODEXAGENT_START_MENU_DIR";
+#[cfg(windows)]
+const LOCALE_NAME_MAX_LENGTH: usize = 85;
+
+const CODEX_HOME_CHECK: EnvironmentCheck = EnvironmentCheck {
+    variable: "USERPROFILE",
+    overrides: &["CODEX_HOME", "HOME"],
+    capability: "codex home unresolved",
+};
+
+#[cfg(windows)]
+const ENVIRONMENT_CHECKS: [EnvironmentCheck; 5] = [
+    CODEX_HOME_CHECK,
+    EnvironmentCheck {
+        variable: "APPDATA",
+        overrides: &[NPM_PREFIX_ENV],
+        capability: "codex discovery limited",
+    },
+    EnvironmentCheck {
+        variable: "APPDATA",
+        overrides: &[START_MENU_DIR_ENV],
+        capability: "start menu shortcut skipped",
+    },
+    EnvironmentCheck {
+        variable: "ProgramFiles",
+        overrides: &[NODE_DIR_ENV],
+        capability: "node discovery limited",
+    },
+    EnvironmentCheck {
+        variable: "LOCALAPPDATA",
+        overrides: &[NODE_DIR_ENV],
+        capability: "node discovery limited",
+    },
+];
+
+// npm, node and the start menu are only looked up through these variables on
+// Windows; elsewhere codex is found on PATH.
+#[cfg(not(windows))]
+const ENVIRONMENT_CHECKS: [EnvironmentCheck; 1] = [CODEX_HOME_CHECK];
+
+static ENVIRONMENT_REPORT: OnceLock<EnvironmentReport> = OnceLock::new();
+
+struct EnvironmentCheck {
+    variable: &'static str,
+    overrides: &'static [&'static str],
+    capability: &'static str,
+}
+
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct EnvironmentReport {
+    pub missing: Vec<&'static str>,
+    pub degraded: Vec<String>,
+}
+
+impl EnvironmentReport {
+    pub fn is_empty(&self) -> bool {
+        self.degraded.is_empty()
+    }
+
+    fn add(&mut self, variable: &'static str, degraded: String) {
+        if !self.missing.contains(&variable) {
+            self.missing.push(variable);
+        }
+        if !self.degraded.contains(&degraded) {
+            self.degraded.push(degraded);
+        }
+    }
+}
+
+pub fn environment_report() -> &'static EnvironmentReport {
+    ENVIRONMENT_REPORT.get_or_init(|| {
+        let mut report = environment_report_from(|key| env::var_os(key));
+        if !paths::get().portable {
+            for (variable, degraded) in path_degradation_from(|key| env::var_os(key)) {
+                report.add(variable, degraded);
+            }
+        }
+        if !report.is_empty() {
+            logging::error(format!(
+                "environment incomplete; missing {}: {}",
+                report.missing.join(", "),
+                report.degraded.join("; ")
+            ));
+        }
+        report
+    })
+}
+
+fn environment_report_from(lookup: impl Fn(&str) -> Option<OsString>) -> EnvironmentReport {
+    let mut report = EnvironmentReport::default();
+    for check in &ENVIRONMENT_CHECKS {
+        if lookup_dir(&lookup, check.variable).is_some()
+            || check
+                .overrides
+                .iter()
+                .any(|key| lookup_dir(&lookup, key).is_some())
+        {
+            continue;
+        }
+        report.add(
+            check.variable,
+            format!("{}: {} not set", check.capability, check.variable),
+        );
+    }
+    report
+}
+
+#[cfg(windows)]
+pub fn npm_prefix() -> Option<PathBuf> {
+    npm_prefix_from(|key| env::var_os(key))
+}
+
+#[cfg(windows)]
+fn npm_prefix_from(lookup: impl Fn(&str) -> Option<OsString>) -> Option<PathBuf> {
+    lookup_dir(&lookup, NPM_PREFIX_ENV)
+        .or_else(|| lookup_dir(&lookup, "APPDATA").map(|path| path.join("npm")))
+}
+
+#[cfg(windows)]
+pub fn node_dirs() -> Vec<PathBuf> {
+    node_dirs_from(|key| env::var_os(key))
+}
+
+#[cfg(windows)]
+fn node_dirs_from(lookup: impl Fn(&str) -> Option<OsString>) -> Vec<PathBuf> {
+    [
+        lookup_dir(&lookup, NODE_DIR_ENV),
+        lookup_dir(&lookup, "ProgramFiles").map(|path| path.join("nodejs")),
+        lookup_dir(&lookup, "LOCALAPPDATA").map(|path| path.join("Programs").join("nodejs")),
+    ]
+    .into_iter()
+    .flatten()
+    .collect()
+}
+
+#[cfg(windows)]
+pub fn start_menu_programs_dir() -> Option<PathBuf> {
+    start_menu_programs_dir_from(|key| env::var_os(key))
+}
+
+#[cfg(windows)]
+fn start_menu_programs_dir_from(lookup: impl Fn(&str) -> Option<OsString>) -> Option<PathBuf> {
+    lookup_dir(&lookup, START_MENU_DIR_ENV).or_else(|| {
+        lookup_dir(&lookup, "APPDATA")
+            .map(|path| path.join(r"Microsoft\Windows\Start Menu\Programs"))
+    })
+}
+
+#[cfg(windows)]
+pub fn user_locale_name() -> Option<String> {
+    let mut buffer = [0u16; LOCALE_NAME_MAX_LENGTH];
+    let len = unsafe { GetUserDefaultLocaleName(buffer.as_mut_ptr(), buffer.len() as i32) };
+    if len <= 1 {
+        logging::error("failed to read the Windows user locale");
+        return None;
+    }
+    String::from_utf16(&buffer[..len as usize - 1]).ok()
+}
+
+#[cfg(not(windows))]
+pub fn user_locale_name() -> Option<String> {
+    ["LC_ALL", "LC_MESSAGES", "LANG"]
+        .into_iter()
+        .filter_map(|key| env::var(key).ok())
+        .find(|value| !value.is_empty())
+        .and_then(|value| {
+            let name = value.split(['.', '@']).next().unwrap_or_default();
+            (!name.is_empty() && name != "C" && name != "POSIX").then(|| name.replace('_', "-"))
+        })
+}
+
+fn lookup_dir(lookup: &impl Fn(&str) -> Option<OsString>, key: &str) -> Option<PathBuf> {
+    lookup(key)
+        .filter(|value| !value.is_empty())
+        .map(PathBuf::from)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lookup_from<'a>(vars: &'a [(&str, &str)]) -> impl Fn(&str) -> Option<OsString> + 'a {
+        |key| {
+            vars.iter()
+                .find(|(name, _)| *name == key)
+                .map(|(_, value)| OsString::from(value))
+        }
+    }
+
+    #[test]
+    fn missing_home_leaves_codex_home_unresolved() {
+        let report = environment_report_from(lookup_from(&[("USERPROFILE", ""), ("HOME", "")]));
+        assert_eq!(report.missing, ["USERPROFILE"]);
+        assert_eq!(
+            report.degraded[0],
+            "codex home unresolved: USERPROFILE not set"
+        );
+    }
+
+    #[test]
+    fn codex_home_counts_as_an_override() {
+        let report = environment_report_from(lookup_from(&[
+            ("CODEX_HOME", "/srv/codex"),
+            ("APPDATA", "a"),
+            ("ProgramFiles", "p"),
+            ("LOCALAPPDATA", "l"),
+        ]));
+        assert!(report.is_empty(), "{:?}", report);
+    }
+
+    #[cfg(not(windows))]
+    #[test]
+    fn windows_only_variables_are_not_reported_elsewhere() {
+        let report = environment_report_from(lookup_from(&[("HOME", "/home/dev")]));
+        assert!(report.is_empty(), "{:?}", report);
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn each_missing_variable_names_what_degrades() {
+        let complete = [
+            ("USERPROFILE", r"C:\Users\dev"),
+            ("APPDATA", r"C:\Users\dev\AppData\Roaming"),
+            ("ProgramFiles", r"C:\Program Files"),
+            ("LOCALAPPDATA", r"C:\Users\dev\AppData\Local"),
+        ];
+        let expected: [(&str, &[&str]); 4] = [
+            (
+                "USERPROFILE",
+                &["codex home unresolved: USERPROFILE not set"],
+            ),
+            (
+                "APPDATA",
+                &[
+                    "codex discovery limited: APPDATA not set",
+                    "start menu shortcut skipped: APPDATA not set",
+                ],
+            ),
+            (
+                "ProgramFiles",
+                &["node discovery limited: ProgramFiles not set"],
+            ),
+            (
+                "LOCALAPPDATA",
+                &["node discovery limited: LOCALAPPDATA not set"],
+            ),
+        ];
+        for (missing, degraded) in expected {
+            let vars: Vec<_> = complete
+                .iter()
+                .copied()
+                .filter(|(name, _)| *name != missing)
+                .collect();
+            let report = environment_report_from(lookup_from(&vars));
+            assert_eq!(report.missing, [missing]);
+            assert_eq!(report.degraded, degraded);
+        }
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn explicit_overrides_silence_their_checks() {
+        let report = environment_report_from(lookup_from(&[
+            ("HOME", r"C:\Users\dev"),
+            (NPM_PREFIX_ENV, r"D:\npm"),
+            (START_MENU_DIR_ENV, r"D:\menu"),
+            (NODE_DIR_ENV, r"D:\node"),
+        ]));
+        assert!(report.is_empty(), "{:?}", report);
+    }
+}
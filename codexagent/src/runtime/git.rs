@@ -0,0 +1,130 @@
+use std::io;
+use std::path::Path;
+use std::process::{Command, Stdio};
+use std::sync::OnceLock;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::logging;
+
+use super::BackgroundCommand;
+use super::probe::debug_assert_off_frame_path;
+
+const GIT_TIMEOUT: Duration = Duration::from_secs(3);
+const GIT_POLL_INTERVAL: Duration = Duration::from_millis(20);
+
+static GIT_AVAILABLE: OnceLock<bool> = OnceLock::new();
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct GitInfo {
+    pub branch: String,
+    pub changes: usize,
+}
+
+pub fn git_info(dir: &Path) -> Option<GitInfo> {
+    debug_assert_off_frame_path("git status");
+    if !git_available() {
+        return None;
+    }
+    let branch = run_git(dir, &["rev-parse", "--abbrev-ref", "HEAD"])?;
+    let status = run_git(dir, &["status", "--porcelain"])?;
+    Some(GitInfo {
+        branch: branch.trim().to_owned(),
+        changes: status
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .count(),
+    })
+}
+
+pub fn git_file_diff(dir: &Path, file: &str) -> io::Result<String> {
+    run_git_reporting(dir, &["diff", "--no-color", "--", file])
+}
+
+pub fn git_revert_file(dir: &Path, file: &str) -> io::Result<()> {
+    run_git_reporting(dir, &["checkout", "--", file]).map(|_| ())
+}
+
+fn git_available() -> bool {
+    *GIT_AVAILABLE.get_or_init(|| {
+        let available = Command::new("git")
+            .arg("--version")
+            .hide_window()
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .status()
+            .is_ok_and(|status| status.success());
+        if !available {
+            logging::trace("git not available; branch display disabled");
+        }
+        available
+    })
+}
+
+fn run_git_reporting(dir: &Path, args: &[&str]) -> io::Result<String> {
+    let output = Command::new("git")
+        .args(args)
+        .current_dir(dir)
+        .hide_window()
+        .stdin(Stdio::null())
+        .output()?;
+    if output.status.success() {
+        return Ok(String::from_utf8_lossy(&output.stdout).into_owned());
+    }
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    let message = stderr.trim();
+    logging::error(format!(
+        "git {} failed in {}: {}",
+        args.join(" "),
+        dir.display(),
+        message
+    ));
+    Err(io::Error::other(if message.is_empty() {
+        format!("git exited with {}", output.status)
+    } else {
+        message.to_owned()
+    }))
+}
+
+fn run_git(dir: &Path, args: &[&str]) -> Option<String> {
+    let mut child = Command::new("git")
+        .args(args)
+        .current_dir(dir)
+        .hide_window()
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .ok()?;
+    let deadline = Instant::now() + GIT_TIMEOUT;
+    loop {
+        match child.try_wait() {
+            Ok(Some(_)) => break,
+            Ok(None) if Instant::now() < deadline => thread::sleep(GIT_POLL_INTERVAL),
+            Ok(None) => {
+                logging::error(format!(
+                    "git {} timed out in {}",
+                    args.join(" "),
+                    dir.display()
+                ));
+                let _ = child.kill();
+                let _ = child.wait();
+                return None;
+            }
+            Err(error) => {
+                logging::error(format!(
+                    "failed to wait for git {}: {}",
+                    args.join(" "),
+                    error
+                ));
+                return None;
+            }
+        }
+    }
+    let output = child.wait_with_output().ok()?;
+    output
+        .status
+        .success()
+        .then(|| String::from_utf8_lossy(&output.stdout).into_owned())
+}
@@ -1,32 +1,96 @@
+mod accessibility;
+mod clipboard;
+mod command;
+mod disk;
+mod environment;
+mod git;
+mod power;
+mod probe;
+mod processes;
+mod profiles;
+mod seeds;
+mod shutdown;
+mod workdir;
+
+pub use accessibility::{
+    AccessibilityPrefs, accessibility_prefs, system_light_theme, watch_accessibility_prefs,
+};
+pub use clipboard::{ClipboardWatcher, watch_clipboard};
+pub use command::{BackgroundCommand, shell_command};
+pub use disk::app_data_free_mb;
+pub use environment::{environment_report, user_locale_name};
+#[cfg(windows)]
+pub use environment::{node_dirs, npm_prefix, start_menu_programs_dir};
+pub use git::{GitInfo, git_file_diff, git_info, git_revert_file};
+#[cfg(windows)]
+pub use power::display_wake_paused;
+pub use power::{PowerEvent, PowerWatcher, watch_power_events};
+pub use probe::{FramePath, ProbeKey, ProbeService, ProbeValue};
+pub use processes::{orphaned_process, process_running, record_running_process};
+pub use profiles::{CodexProfiles, codex_config_modified, codex_profiles};
+pub use seeds::{
+    CodexFileUpdate, DiffLine, apply_codex_file_update, check_seeded_codex_files, content_hash,
+    ignore_codex_file_update,
+};
+pub use shutdown::{join_within, wait_within};
+pub use workdir::check_working_directory;
+
 use std::collections::HashSet;
 use std::env;
+use std::ffi::OsString;
 use std::fs::{self, OpenOptions};
 use std::io::{self, Write};
-use std::os::windows::process::CommandExt;
+#[cfg(windows)]
+use std::os::windows::ffi::OsStringExt;
 use std::path::{Path, PathBuf};
 use std::process::Command;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
 
 use serde::Deserialize;
+use windows_sys::Win32::Foundation::ERROR_ACCESS_DENIED;
+#[cfg(windows)]
 use windows_sys::Win32::Foundation::{
-    CloseHandle, ERROR_ACCESS_DENIED, ERROR_ALREADY_EXISTS, ERROR_FILE_NOT_FOUND, GetLastError,
-    HANDLE,
+    CloseHandle, ERROR_ALREADY_EXISTS, ERROR_FILE_NOT_FOUND, GetLastError, HANDLE, MAX_PATH,
 };
+#[cfg(windows)]
+use windows_sys::Win32::System::Com::{
+    COINIT_APARTMENTTHREADED, CoInitializeEx, CoTaskMemFree, CoUninitialize,
+};
+#[cfg(windows)]
+use windows_sys::Win32::System::DataExchange::{CloseClipboard, GetClipboardData, OpenClipboard};
+#[cfg(windows)]
+use windows_sys::Win32::System::Memory::{GlobalLock, GlobalUnlock};
+#[cfg(windows)]
+use windows_sys::Win32::System::Ole::CF_UNICODETEXT;
+use windows_sys::Win32::System::Registry::{HKEY_CURRENT_USER, HKEY_LOCAL_MACHINE};
+#[cfg(windows)]
 use windows_sys::Win32::System::Registry::{
-    HKEY_CURRENT_USER, HKEY_LOCAL_MACHINE, KEY_QUERY_VALUE, KEY_SET_VALUE, REG_OPTION_NON_VOLATILE,
-    REG_SZ, RegCloseKey, RegCreateKeyExW, RegDeleteTreeW, RegOpenKeyExW, RegQueryValueExW,
-    RegSetValueExW,
+    KEY_QUERY_VALUE, KEY_SET_VALUE, REG_OPTION_NON_VOLATILE, REG_SZ, RegCloseKey, RegCreateKeyExW,
+    RegDeleteTreeW, RegOpenKeyExW, RegQueryValueExW, RegSetValueExW,
 };
-use windows_sys::Win32::System::Threading::{CREATE_NO_WINDOW, CreateMutexW};
+#[cfg(windows)]
+use windows_sys::Win32::System::Threading::CreateMutexW;
+#[cfg(windows)]
 use windows_sys::Win32::UI::Shell::{
-    SHCNE_ASSOCCHANGED, SHCNF_IDLIST, SHChangeNotify, SetCurrentProcessExplicitAppUserModelID,
+    BIF_NEWDIALOGSTYLE, BIF_RETURNONLYFSDIRS, BROWSEINFOW, SHBrowseForFolderW, SHCNE_ASSOCCHANGED,
+    SHCNF_IDLIST, SHChangeNotify, SHGetPathFromIDListW, SetCurrentProcessExplicitAppUserModelID,
+    ShellExecuteW,
 };
-
-use crate::config::{
-    APP_DISPLAY_NAME, APP_USER_MODEL_ID, CODEX_AGENTS_CONTENTS, CODEX_CONFIG_CONTENTS,
-    DEFAULT_MODEL,
+#[cfg(windows)]
+use windows_sys::Win32::UI::WindowsAndMessaging::{
+    MB_ICONERROR, MB_OK, MB_SETFOREGROUND, MessageBoxW, SW_SHOWNORMAL, SetWindowDisplayAffinity,
+    WDA_EXCLUDEFROMCAPTURE, WDA_NONE,
 };
+
+#[cfg(not(windows))]
+use crate::config::paths;
+#[cfg(windows)]
+use crate::config::{APP_DISPLAY_NAME, APP_USER_MODEL_ID};
+use crate::config::{CODEX_AGENTS_CONTENTS, CODEX_CONFIG_CONTENTS, DEFAULT_MODEL};
 use crate::logging;
 
+#[cfg(windows)]
 #[repr(C)]
 struct PropertyKey {
     fmtid: [u8; 16],
@@ -34,6 +98,7 @@ struct PropertyKey {
 }
 
 // {9F4C2855-9F79-4B39-A8D0-E1D42DE1D5F3}, pid 5
+#[cfg(windows)]
 const PKEY_APPUSERMODEL_ID: PropertyKey = PropertyKey {
     fmtid: [
         0x55, 0x28, 0x4C, 0x9F, 0x79, 0x9F, 0x39, 0x4B, 0xA8, 0xD0, 0xE1, 0xD4, 0x2D, 0xE1, 0xD5,
@@ -42,6 +107,7 @@ const PKEY_APPUSERMODEL_ID: PropertyKey = PropertyKey {
     pid: 5,
 };
 
+#[cfg(windows)]
 #[repr(C)]
 struct PropVariant {
     vt: u16,
@@ -49,6 +115,7 @@ struct PropVariant {
     data: [usize; 2],
 }
 
+#[cfg(windows)]
 #[repr(C)]
 struct IPropertyStoreVtbl {
     query_interface: usize,
@@ -65,13 +132,16 @@ struct IPropertyStoreVtbl {
     commit: unsafe extern "system" fn(*mut IPropertyStoreRaw) -> i32,
 }
 
+#[cfg(windows)]
 #[repr(C)]
 struct IPropertyStoreRaw {
     vtbl: *const IPropertyStoreVtbl,
 }
 
+#[cfg(windows)]
 const VT_LPWSTR: u16 = 31;
 
+#[cfg(windows)]
 unsafe extern "system" {
     fn SHGetPropertyStoreForWindow(
         hwnd: *mut std::ffi::c_void,
@@ -81,11 +151,14 @@ unsafe extern "system" {
 }
 
 // {886D8EEB-8CF2-4446-8D02-CDBA1DBDCF99}
+#[cfg(windows)]
 const IID_IPROPERTYSTORE: [u8; 16] = [
     0xEB, 0x8E, 0x6D, 0x88, 0xF2, 0x8C, 0x46, 0x44, 0x8D, 0x02, 0xCD, 0xBA, 0x1D, 0xBD, 0xCF, 0x99,
 ];
 
-pub(crate) fn set_window_app_id(hwnd: *mut std::ffi::c_void) {
+#[cfg(windows)]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub fn set_window_app_id(hwnd: *mut std::ffi::c_void) {
     if hwnd.is_null() {
         return;
     }
@@ -118,28 +191,88 @@ pub(crate) fn set_window_app_id(hwnd: *mut std::ffi::c_void) {
     }
 }
 
+#[cfg(not(windows))]
+pub fn set_window_app_id(_hwnd: *mut std::ffi::c_void) {}
+
 const INSTALL_PATH: &str = r"C:\Local\Software\codexagent.exe";
+#[cfg(windows)]
 const LEGACY_SHORTCUT_NAMES: &[&str] = &[];
+#[cfg(not(windows))]
+const INSTANCE_LOCK_FILE: &str = "CodexAgent.lock";
+#[cfg(windows)]
 const INSTANCE_MUTEX_NAME: &str = "Local\\CodexAgent.Instance";
 const LEGACY_CONTEXT_MENU_NAME: &str = "Launch Codex";
 const LEGACY_DIRECTORY_MENU_KEY: &str = r"Software\Classes\Directory\shell\Launch Codex";
 const LEGACY_BACKGROUND_MENU_KEY: &str =
     r"Software\Classes\Directory\Background\shell\Launch Codex";
 const LEGACY_CONTEXT_MENU_KEYS: &[&str] = &[LEGACY_DIRECTORY_MENU_KEY, LEGACY_BACKGROUND_MENU_KEY];
+#[cfg(windows)]
+const WINDOWS_VERSION_KEY: &str = r"SOFTWARE\Microsoft\Windows NT\CurrentVersion";
+#[cfg(windows)]
+const CAPTURE_EXCLUSION_MIN_BUILD: u32 = 19041;
+const CODEX_FILES_RECHECK_INTERVAL: Duration = Duration::from_secs(5 * 60);
+
+static CODEX_FILES_VERIFIED: Mutex<Option<(PathBuf, Instant)>> = Mutex::new(None);
+static LAUNCH_BATCH: Mutex<Option<PathBuf>> = Mutex::new(None);
 
 #[derive(Clone, Debug, Default)]
-pub(crate) struct LaunchRequest {
-    pub(crate) cwd: Option<PathBuf>,
+pub struct LaunchRequest {
+    pub cwd: Option<PathBuf>,
+    pub batch: Option<PathBuf>,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum CodexFile {
+    Config,
+    Agents,
+}
+
+impl CodexFile {
+    pub fn file_name(self) -> &'static str {
+        match self {
+            Self::Config => "config.toml",
+            Self::Agents => "AGENTS.md",
+        }
+    }
+
+    pub fn default_contents(self) -> &'static [u8] {
+        match self {
+            Self::Config => CODEX_CONFIG_CONTENTS,
+            Self::Agents => CODEX_AGENTS_CONTENTS,
+        }
+    }
 }
 
 #[derive(Clone, Debug, Eq, PartialEq)]
-pub(crate) enum ContextMenuSelection {
+pub enum ContextMenuSelection {
     Add,
     Remove,
 }
 
-pub(crate) struct InstanceMutex(HANDLE);
+#[cfg(windows)]
+pub struct InstanceMutex(HANDLE, bool);
+
+#[cfg(not(windows))]
+pub struct InstanceMutex {
+    _lock: fs::File,
+    first: bool,
+}
 
+#[cfg(windows)]
+impl InstanceMutex {
+    pub fn first(&self) -> bool {
+        self.1
+    }
+}
+
+#[cfg(not(windows))]
+impl InstanceMutex {
+    pub fn first(&self) -> bool {
+        self.first
+    }
+}
+
+#[cfg(windows)]
 impl Drop for InstanceMutex {
     fn drop(&mut self) {
         unsafe {
@@ -148,7 +281,8 @@ impl Drop for InstanceMutex {
     }
 }
 
-pub(crate) fn acquire_instance_mutex() -> Option<InstanceMutex> {
+#[cfg(windows)]
+pub fn acquire_instance_mutex() -> Option<InstanceMutex> {
     let name: Vec<u16> = INSTANCE_MUTEX_NAME
         .encode_utf16()
         .chain(std::iter::once(0))
@@ -161,7 +295,38 @@ pub(crate) fn acquire_instance_mutex() -> Option<InstanceMutex> {
     if first {
         clear_codex_state();
     }
-    Some(InstanceMutex(handle))
+    Some(InstanceMutex(handle, first))
+}
+
+#[cfg(not(windows))]
+pub fn acquire_instance_mutex() -> Option<InstanceMutex> {
+    let path = paths::get()
+        .settings_file
+        .with_file_name(INSTANCE_LOCK_FILE);
+    let file = match OpenOptions::new()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(&path)
+    {
+        Ok(file) => file,
+        Err(error) => {
+            logging::error(format!("failed to open {}: {}", path.display(), error));
+            return None;
+        }
+    };
+    let first = match file.try_lock() {
+        Ok(()) => true,
+        Err(fs::TryLockError::WouldBlock) => false,
+        Err(fs::TryLockError::Error(error)) => {
+            logging::error(format!("failed to lock {}: {}", path.display(), error));
+            return None;
+        }
+    };
+    if first {
+        clear_codex_state();
+    }
+    Some(InstanceMutex { _lock: file, first })
 }
 
 const CODEX_STATE_FILES: &[&str] = &[
@@ -172,10 +337,9 @@ const CODEX_STATE_FILES: &[&str] = &[
 ];
 
 fn clear_codex_state() {
-    let Some(home) = env::var_os("USERPROFILE") else {
+    let Some(codex_dir) = codex_home() else {
         return;
     };
-    let codex_dir = PathBuf::from(home).join(".codex");
 
     for name in CODEX_STATE_FILES {
         let _ = fs::remove_file(codex_dir.join(name));
@@ -184,24 +348,292 @@ fn clear_codex_state() {
     logging::trace("cleared codex state");
 }
 
-pub(crate) fn apply_launch_request(request: &LaunchRequest) {
+pub fn apply_launch_request(request: &LaunchRequest) {
+    if let Some(path) = request.batch.as_deref() {
+        let path = env::current_dir()
+            .map(|cwd| cwd.join(path))
+            .unwrap_or_else(|_| path.to_path_buf());
+        *LAUNCH_BATCH.lock().unwrap_or_else(|e| e.into_inner()) = Some(path);
+    }
     if let Some(path) = request.cwd.as_deref() {
-        set_process_cwd(path);
+        let _ = set_process_cwd(path);
     }
 }
 
-fn set_process_cwd(path: &Path) {
-    match env::set_current_dir(path) {
-        Ok(()) => logging::trace(format!("set working directory to {}", path.display())),
-        Err(error) => logging::error(format!(
+pub fn take_launch_batch() -> Option<PathBuf> {
+    LAUNCH_BATCH
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .take()
+}
+
+pub fn set_process_cwd(path: &Path) -> io::Result<()> {
+    logging::log_result(env::set_current_dir(path), |error| {
+        format!(
             "failed to set working directory to {}: {}",
             path.display(),
             error
-        )),
+        )
+    })?;
+    logging::trace(format!("set working directory to {}", path.display()));
+    Ok(())
+}
+
+pub fn working_directory_chosen() -> bool {
+    let Ok(cwd) = env::current_dir() else {
+        return false;
+    };
+    let exe_dir = env::current_exe()
+        .ok()
+        .and_then(|path| path.parent().map(Path::to_path_buf));
+    let in_windows_dir = env::var_os("WINDIR").is_some_and(|dir| cwd.starts_with(dir));
+    exe_dir.as_deref() != Some(cwd.as_path()) && !in_windows_dir
+}
+
+pub fn codex_authenticated() -> bool {
+    codex_home().is_some_and(|home| home.join("auth.json").is_file())
+}
+
+#[cfg(windows)]
+pub fn os_build() -> String {
+    let value = |name: &str| {
+        read_registry_string(HKEY_LOCAL_MACHINE, WINDOWS_VERSION_KEY, Some(name)).unwrap_or_else(
+            |error| {
+                logging::error(format!("failed to read Windows {}: {}", name, error));
+                None
+            },
+        )
+    };
+    let mut build = value("ProductName").unwrap_or_else(|| "Windows".to_owned());
+    if let Some(version) = value("DisplayVersion") {
+        build.push(' ');
+        build.push_str(&version);
     }
+    if let Some(number) = value("CurrentBuild") {
+        build.push_str(&format!(" (build {})", number));
+    }
+    build
 }
 
-pub(crate) fn ensure_app_identity() {
+#[cfg(not(windows))]
+pub fn os_build() -> String {
+    fs::read_to_string("/etc/os-release")
+        .ok()
+        .and_then(|release| {
+            release.lines().find_map(|line| {
+                line.strip_prefix("PRETTY_NAME=")
+                    .map(|name| name.trim_matches('"').to_owned())
+            })
+        })
+        .unwrap_or_else(|| env::consts::OS.to_owned())
+}
+
+#[cfg(windows)]
+fn windows_build_number() -> Option<u32> {
+    read_registry_string(
+        HKEY_LOCAL_MACHINE,
+        WINDOWS_VERSION_KEY,
+        Some("CurrentBuild"),
+    )
+    .ok()
+    .flatten()
+    .and_then(|build| build.trim().parse().ok())
+}
+
+#[cfg(windows)]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub fn set_capture_excluded(hwnd: *mut std::ffi::c_void, excluded: bool) -> io::Result<()> {
+    if hwnd.is_null() {
+        return Err(io::Error::other("the window handle is not available"));
+    }
+    if excluded
+        && let Some(build) =
+            windows_build_number().filter(|&build| build < CAPTURE_EXCLUSION_MIN_BUILD)
+    {
+        return Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            format!(
+                "needs Windows 10 version 2004 (build {}) or later; this is build {}",
+                CAPTURE_EXCLUSION_MIN_BUILD, build
+            ),
+        ));
+    }
+    let affinity = if excluded {
+        WDA_EXCLUDEFROMCAPTURE
+    } else {
+        WDA_NONE
+    };
+    if unsafe { SetWindowDisplayAffinity(hwnd, affinity) } == 0 {
+        return Err(io::Error::last_os_error());
+    }
+    logging::trace(format!(
+        "window {} screen capture",
+        if excluded {
+            "excluded from"
+        } else {
+            "included in"
+        }
+    ));
+    Ok(())
+}
+
+#[cfg(not(windows))]
+pub fn set_capture_excluded(_hwnd: *mut std::ffi::c_void, _excluded: bool) -> io::Result<()> {
+    Err(windows_only("screen capture exclusion"))
+}
+
+/// Where errors go when there is no window to show them in.
+pub trait MessageSink {
+    fn show_error(&self, title: &str, text: &str);
+}
+
+/// The platform's own error dialog, or stderr where there is none.
+pub struct ErrorDialog;
+
+impl MessageSink for ErrorDialog {
+    fn show_error(&self, title: &str, text: &str) {
+        show_error_message(title, text);
+    }
+}
+
+#[cfg(windows)]
+pub fn show_error_message(title: &str, text: &str) {
+    let title = to_wide(title);
+    let text = to_wide(text);
+    unsafe {
+        MessageBoxW(
+            std::ptr::null_mut(),
+            text.as_ptr(),
+            title.as_ptr(),
+            MB_OK | MB_ICONERROR | MB_SETFOREGROUND,
+        );
+    }
+}
+
+#[cfg(not(windows))]
+pub fn show_error_message(title: &str, text: &str) {
+    eprintln!("{}: {}", title, text);
+}
+
+pub fn open_in_shell(target: &str) -> io::Result<()> {
+    shell_execute("open", target)
+}
+
+pub fn explore_folder(dir: &Path) -> io::Result<()> {
+    shell_execute("explore", &dir.to_string_lossy())
+}
+
+#[cfg(windows)]
+fn shell_execute(verb: &str, target: &str) -> io::Result<()> {
+    let operation = to_wide(verb);
+    let file = to_wide(target);
+    let result = unsafe {
+        ShellExecuteW(
+            std::ptr::null_mut(),
+            operation.as_ptr(),
+            file.as_ptr(),
+            std::ptr::null(),
+            std::ptr::null(),
+            SW_SHOWNORMAL,
+        )
+    } as isize;
+    if result <= 32 {
+        let error = io::Error::other(format!("ShellExecuteW failed with {}", result));
+        logging::error(format!("failed to open {}: {}", target, error));
+        return Err(error);
+    }
+    logging::trace(format!("opened {}", target));
+    Ok(())
+}
+
+#[cfg(not(windows))]
+fn shell_execute(_verb: &str, target: &str) -> io::Result<()> {
+    Command::new("xdg-open")
+        .arg(target)
+        .spawn()
+        .map(|_| logging::trace(format!("opened {}", target)))
+        .map_err(|error| {
+            logging::error(format!("failed to open {}: {}", target, error));
+            error
+        })
+}
+
+#[cfg(windows)]
+pub fn pick_folder(owner: usize, title: &str) -> Option<PathBuf> {
+    let title = to_wide(title);
+    let mut display_name = [0u16; MAX_PATH as usize];
+    let info = BROWSEINFOW {
+        hwndOwner: owner as *mut core::ffi::c_void,
+        pidlRoot: std::ptr::null_mut(),
+        pszDisplayName: display_name.as_mut_ptr(),
+        lpszTitle: title.as_ptr(),
+        ulFlags: BIF_RETURNONLYFSDIRS | BIF_NEWDIALOGSTYLE,
+        lpfn: None,
+        lParam: 0,
+        iImage: 0,
+    };
+    unsafe {
+        let initialized = CoInitializeEx(std::ptr::null(), COINIT_APARTMENTTHREADED as u32) >= 0;
+        let pidl = SHBrowseForFolderW(&info);
+        let mut path = None;
+        if !pidl.is_null() {
+            let mut buffer = [0u16; MAX_PATH as usize];
+            if SHGetPathFromIDListW(pidl, buffer.as_mut_ptr()) != 0 {
+                let len = buffer.iter().position(|c| *c == 0).unwrap_or(buffer.len());
+                path = Some(PathBuf::from(os_string_from_wide(&buffer[..len])));
+            }
+            CoTaskMemFree(pidl as *const core::ffi::c_void);
+        }
+        if initialized {
+            CoUninitialize();
+        }
+        path
+    }
+}
+
+#[cfg(not(windows))]
+pub fn pick_folder(_owner: usize, _title: &str) -> Option<PathBuf> {
+    logging::error(windows_only("the folder picker").to_string());
+    None
+}
+
+#[cfg(windows)]
+pub fn read_clipboard_text() -> Option<String> {
+    unsafe {
+        if OpenClipboard(std::ptr::null_mut()) == 0 {
+            logging::error(format!(
+                "failed to open clipboard: {}",
+                io::Error::last_os_error()
+            ));
+            return None;
+        }
+        let handle = GetClipboardData(CF_UNICODETEXT as u32);
+        let mut text = None;
+        if !handle.is_null() {
+            let data = GlobalLock(handle) as *const u16;
+            if !data.is_null() {
+                let mut len = 0;
+                while *data.add(len) != 0 {
+                    len += 1;
+                }
+                text = Some(String::from_utf16_lossy(std::slice::from_raw_parts(
+                    data, len,
+                )));
+                GlobalUnlock(handle);
+            }
+        }
+        CloseClipboard();
+        text
+    }
+}
+
+#[cfg(not(windows))]
+pub fn read_clipboard_text() -> Option<String> {
+    None
+}
+
+#[cfg(windows)]
+pub fn ensure_app_identity() {
     let wide: Vec<u16> = APP_USER_MODEL_ID
         .encode_utf16()
         .chain(std::iter::once(0))
@@ -218,7 +650,10 @@ pub(crate) fn ensure_app_identity() {
     ensure_start_menu_shortcut();
 }
 
-pub(crate) fn current_context_menu_selection() -> io::Result<ContextMenuSelection> {
+#[cfg(not(windows))]
+pub fn ensure_app_identity() {}
+
+pub fn current_context_menu_selection() -> io::Result<ContextMenuSelection> {
     let directory_command = read_registry_string_in_known_roots(
         &format!(r"{}\command", LEGACY_DIRECTORY_MENU_KEY),
         None,
@@ -237,7 +672,7 @@ pub(crate) fn current_context_menu_selection() -> io::Result<ContextMenuSelectio
     Ok(ContextMenuSelection::Remove)
 }
 
-pub(crate) fn install_context_menu() -> io::Result<bool> {
+pub fn install_context_menu() -> io::Result<bool> {
     write_registry_string(
         HKEY_CURRENT_USER,
         LEGACY_DIRECTORY_MENU_KEY,
@@ -278,7 +713,7 @@ pub(crate) fn install_context_menu() -> io::Result<bool> {
     Ok(true)
 }
 
-pub(crate) fn remove_context_menu() -> io::Result<bool> {
+pub fn remove_context_menu() -> io::Result<bool> {
     delete_registry_tree(HKEY_CURRENT_USER, LEGACY_DIRECTORY_MENU_KEY)?;
     delete_registry_tree(HKEY_CURRENT_USER, LEGACY_BACKGROUND_MENU_KEY)?;
     remove_machine_context_menu()?;
@@ -286,11 +721,11 @@ pub(crate) fn remove_context_menu() -> io::Result<bool> {
     Ok(false)
 }
 
+#[cfg(windows)]
 fn ensure_start_menu_shortcut() {
-    let Some(appdata) = env::var_os("APPDATA") else {
+    let Some(programs_dir) = start_menu_programs_dir() else {
         return;
     };
-    let programs_dir = PathBuf::from(appdata).join(r"Microsoft\Windows\Start Menu\Programs");
     let lnk_path = programs_dir.join(format!("{}.lnk", APP_DISPLAY_NAME));
 
     for legacy_name in LEGACY_SHORTCUT_NAMES {
@@ -346,7 +781,7 @@ fn ensure_start_menu_shortcut() {
 
     match Command::new("powershell")
         .args(["-NoProfile", "-NonInteractive", "-Command", &script])
-        .creation_flags(CREATE_NO_WINDOW)
+        .hide_window()
         .output()
     {
         Ok(out) if out.status.success() => {
@@ -372,6 +807,7 @@ fn read_registry_string_in_known_roots(
     read_registry_string(HKEY_LOCAL_MACHINE, path, name)
 }
 
+#[cfg(windows)]
 fn registry_key_exists(root: *mut core::ffi::c_void, path: &str) -> io::Result<bool> {
     let mut key = std::ptr::null_mut();
     let path = to_wide(path);
@@ -388,6 +824,12 @@ fn registry_key_exists(root: *mut core::ffi::c_void, path: &str) -> io::Result<b
     Err(io::Error::from_raw_os_error(status as i32))
 }
 
+#[cfg(not(windows))]
+fn registry_key_exists(_root: *mut core::ffi::c_void, _path: &str) -> io::Result<bool> {
+    Err(windows_only("the registry"))
+}
+
+#[cfg(windows)]
 fn read_registry_string(
     root: *mut core::ffi::c_void,
     path: &str,
@@ -479,6 +921,15 @@ fn read_registry_string(
     Ok(Some(String::from_utf16_lossy(&wide[..end])))
 }
 
+#[cfg(not(windows))]
+fn read_registry_string(
+    _root: *mut core::ffi::c_void,
+    _path: &str,
+    _name: Option<&str>,
+) -> io::Result<Option<String>> {
+    Err(windows_only("the registry"))
+}
+
 fn remove_machine_context_menu() -> io::Result<()> {
     let mut needs_elevation = false;
 
@@ -530,7 +981,7 @@ fn run_elevated_powershell(script: &str) -> io::Result<()> {
             "-Command",
             &parent_command,
         ])
-        .creation_flags(CREATE_NO_WINDOW)
+        .hide_window()
         .output()?;
 
     if output.status.success() {
@@ -554,6 +1005,7 @@ fn powershell_single_quote(value: &str) -> String {
     format!("'{}'", value.replace('\'', "''"))
 }
 
+#[cfg(windows)]
 fn write_registry_string(
     root: *mut core::ffi::c_void,
     path: &str,
@@ -605,6 +1057,17 @@ fn write_registry_string(
     Ok(())
 }
 
+#[cfg(not(windows))]
+fn write_registry_string(
+    _root: *mut core::ffi::c_void,
+    _path: &str,
+    _name: Option<&str>,
+    _value: &str,
+) -> io::Result<()> {
+    Err(windows_only("the registry"))
+}
+
+#[cfg(windows)]
 fn delete_registry_tree(root: *mut core::ffi::c_void, path: &str) -> io::Result<()> {
     let path = to_wide(path);
     let status = unsafe { RegDeleteTreeW(root, path.as_ptr()) };
@@ -614,6 +1077,12 @@ fn delete_registry_tree(root: *mut core::ffi::c_void, path: &str) -> io::Result<
     Err(io::Error::from_raw_os_error(status as i32))
 }
 
+#[cfg(not(windows))]
+fn delete_registry_tree(_root: *mut core::ffi::c_void, _path: &str) -> io::Result<()> {
+    Err(windows_only("the registry"))
+}
+
+#[cfg(windows)]
 fn notify_shell_associations_changed() {
     unsafe {
         SHChangeNotify(
@@ -625,6 +1094,17 @@ fn notify_shell_associations_changed() {
     }
 }
 
+#[cfg(not(windows))]
+fn notify_shell_associations_changed() {}
+
+#[cfg(not(windows))]
+fn windows_only(feature: &str) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::Unsupported,
+        format!("{} is only available on Windows", feature),
+    )
+}
+
 fn context_menu_command(token: &str) -> String {
     format!(
         "\"{}\" --show --cwd \"{}\"",
@@ -642,19 +1122,53 @@ fn codex_exe_path() -> PathBuf {
     env::current_exe().unwrap_or_else(|_| PathBuf::from(INSTALL_PATH))
 }
 
-pub(crate) fn ensure_codex_files() -> io::Result<()> {
+pub fn ensure_codex_files_cached() -> io::Result<()> {
+    let codex_dir = codex_home();
+    let verified = CODEX_FILES_VERIFIED
+        .lock()
+        .unwrap_or_else(|e| e.into_inner());
+    if codex_files_fresh(verified.as_ref(), codex_dir.as_deref(), Instant::now()) {
+        return Ok(());
+    }
+    drop(verified);
+    ensure_codex_files()
+}
+
+fn codex_files_fresh(
+    verified: Option<&(PathBuf, Instant)>,
+    codex_dir: Option<&Path>,
+    now: Instant,
+) -> bool {
+    verified.is_some_and(|(dir, at)| {
+        Some(dir.as_path()) == codex_dir
+            && now.saturating_duration_since(*at) < CODEX_FILES_RECHECK_INTERVAL
+    })
+}
+
+pub fn ensure_codex_files() -> io::Result<()> {
     logging::trace("ensuring codex files");
-    let Some(user_profile) = env::var_os("USERPROFILE") else {
-        logging::trace("USERPROFILE not set; skipping codex file setup");
+    let Some(codex_dir) = codex_home() else {
+        logging::trace("codex home not resolved; skipping codex file setup");
         return Ok(());
     };
 
-    let codex_dir = PathBuf::from(user_profile).join(".codex");
+    for file in write_codex_files(&codex_dir)? {
+        seeds::record_seeded(file);
+    }
+
+    logging::trace(format!("codex files ready in {}", codex_dir.display()));
+    *CODEX_FILES_VERIFIED
+        .lock()
+        .unwrap_or_else(|e| e.into_inner()) = Some((codex_dir, Instant::now()));
+    Ok(())
+}
+
+fn write_codex_files(codex_dir: &Path) -> io::Result<Vec<CodexFile>> {
     let config_path = codex_dir.join("config.toml");
     let agents_path = codex_dir.join("AGENTS.md");
 
     if !config_path.exists() || !agents_path.exists() {
-        logging::log_result(fs::create_dir_all(&codex_dir), |error| {
+        logging::log_result(fs::create_dir_all(codex_dir), |error| {
             format!(
                 "failed to create codex directory {}: {}",
                 codex_dir.display(),
@@ -663,32 +1177,49 @@ pub(crate) fn ensure_codex_files() -> io::Result<()> {
         })?;
     }
 
-    logging::log_result(
-        write_file_if_missing(&config_path, CODEX_CONFIG_CONTENTS),
-        |error| {
-            format!(
-                "failed to ensure codex config {}: {}",
-                config_path.display(),
-                error
-            )
-        },
-    )?;
-    logging::log_result(
-        write_file_if_missing(&agents_path, CODEX_AGENTS_CONTENTS),
-        |error| {
-            format!(
-                "failed to ensure codex agents file {}: {}",
-                agents_path.display(),
-                error
-            )
-        },
-    )?;
+    let mut created = Vec::new();
+    for (file, path) in [
+        (CodexFile::Config, &config_path),
+        (CodexFile::Agents, &agents_path),
+    ] {
+        if logging::log_result(
+            write_file_if_missing(path, file.default_contents()),
+            |error| format!("failed to ensure codex file {}: {}", path.display(), error),
+        )? {
+            created.push(file);
+        }
+    }
+    Ok(created)
+}
 
-    logging::trace(format!("codex files ready in {}", codex_dir.display()));
-    Ok(())
+pub fn find_agents_files(dir: &Path) -> Vec<PathBuf> {
+    probe::debug_assert_off_frame_path("AGENTS.md lookup");
+    let mut searched = Vec::new();
+    let mut repo_root_found = false;
+    for ancestor in dir.ancestors() {
+        searched.push(ancestor);
+        if ancestor.join(".git").exists() {
+            repo_root_found = true;
+            break;
+        }
+    }
+    if !repo_root_found {
+        searched.truncate(1);
+    }
+    let files: Vec<PathBuf> = searched
+        .into_iter()
+        .map(|ancestor| ancestor.join("AGENTS.md"))
+        .filter(|path| path.is_file())
+        .collect();
+    logging::trace(format!(
+        "found {} AGENTS.md file(s) for {}",
+        files.len(),
+        dir.display()
+    ));
+    files
 }
 
-pub(crate) fn current_cwd_text() -> String {
+pub fn current_cwd_text() -> String {
     match env::current_dir() {
         Ok(path) => path.display().to_string(),
         Err(error) => {
@@ -701,7 +1232,7 @@ pub(crate) fn current_cwd_text() -> String {
     }
 }
 
-pub(crate) fn current_model() -> String {
+pub fn current_model() -> String {
     codex_config_path()
         .and_then(|path| match fs::read_to_string(&path) {
             Ok(contents) => Some(contents),
@@ -718,11 +1249,39 @@ pub(crate) fn current_model() -> String {
         .unwrap_or_else(|| DEFAULT_MODEL.to_owned())
 }
 
+pub fn current_sandbox_mode() -> Option<String> {
+    let path = codex_config_path()?;
+    match fs::read_to_string(&path) {
+        Ok(contents) => parse_string_value(&contents, "sandbox_mode"),
+        Err(error) => {
+            logging::error(format!(
+                "failed to read codex config {}: {}",
+                path.display(),
+                error
+            ));
+            None
+        }
+    }
+}
+
+#[cfg(windows)]
+#[cfg(windows)]
 fn to_wide(value: &str) -> Vec<u16> {
     value.encode_utf16().chain(std::iter::once(0)).collect()
 }
 
-pub(crate) fn set_model(model: &str) -> io::Result<String> {
+#[cfg(windows)]
+fn os_string_from_wide(value: &[u16]) -> OsString {
+    OsString::from_wide(value)
+}
+
+#[cfg(not(windows))]
+#[cfg_attr(not(test), allow(dead_code))]
+fn os_string_from_wide(value: &[u16]) -> OsString {
+    OsString::from(String::from_utf16_lossy(value))
+}
+
+pub fn set_model(model: &str) -> io::Result<String> {
     logging::log_result(ensure_codex_files(), |error| {
         format!(
             "failed to prepare codex files before setting model: {}",
@@ -730,10 +1289,10 @@ pub(crate) fn set_model(model: &str) -> io::Result<String> {
         )
     })?;
     let Some(path) = codex_config_path() else {
-        logging::error("failed to set model: USERPROFILE not set");
+        logging::error("failed to set model: codex home not resolved");
         return Err(io::Error::new(
             io::ErrorKind::NotFound,
-            "USERPROFILE not set",
+            "codex home not resolved",
         ));
     };
     let contents = match fs::read_to_string(&path) {
@@ -758,27 +1317,153 @@ pub(crate) fn set_model(model: &str) -> io::Result<String> {
     Ok(model.to_owned())
 }
 
-fn write_file_if_missing(path: &Path, contents: &[u8]) -> io::Result<()> {
+pub fn codex_file_path(file: CodexFile) -> Option<PathBuf> {
+    codex_home().map(|path| path.join(file.file_name()))
+}
+
+pub fn load_codex_file(file: CodexFile) -> io::Result<(PathBuf, String)> {
+    logging::log_result(ensure_codex_files(), |error| {
+        format!(
+            "failed to prepare codex files before loading {}: {}",
+            file.file_name(),
+            error
+        )
+    })?;
+    let Some(path) = codex_file_path(file) else {
+        logging::error(format!(
+            "failed to load {}: codex home not resolved",
+            file.file_name()
+        ));
+        return Err(io::Error::new(
+            io::ErrorKind::NotFound,
+            "codex home not resolved",
+        ));
+    };
+    let contents = match fs::read_to_string(&path) {
+        Ok(contents) => contents,
+        Err(error) if error.kind() == io::ErrorKind::NotFound => String::new(),
+        Err(error) => {
+            logging::error(format!(
+                "failed to read codex file {}: {}",
+                path.display(),
+                error
+            ));
+            return Err(error);
+        }
+    };
+    Ok((path, contents))
+}
+
+pub fn save_codex_file(file: CodexFile, contents: &str) -> io::Result<PathBuf> {
+    if file == CodexFile::Config {
+        validate_toml(contents)?;
+    }
+    let Some(path) = codex_file_path(file) else {
+        logging::error(format!(
+            "failed to save {}: codex home not resolved",
+            file.file_name()
+        ));
+        return Err(io::Error::new(
+            io::ErrorKind::NotFound,
+            "codex home not resolved",
+        ));
+    };
+    logging::log_result(
+        write_file_atomic(&path, contents.as_bytes(), true),
+        |error| format!("failed to save codex file {}: {}", path.display(), error),
+    )?;
+    logging::trace(format!("saved codex file {}", path.display()));
+    Ok(path)
+}
+
+pub fn load_project_file(path: &Path) -> io::Result<String> {
+    logging::log_result(fs::read_to_string(path), |error| {
+        format!("failed to read project file {}: {}", path.display(), error)
+    })
+}
+
+pub fn save_project_file(path: &Path, contents: &str) -> io::Result<()> {
+    logging::log_result(
+        write_file_atomic(path, contents.as_bytes(), true),
+        |error| format!("failed to save project file {}: {}", path.display(), error),
+    )?;
+    logging::trace(format!("saved project file {}", path.display()));
+    Ok(())
+}
+
+fn validate_toml(contents: &str) -> io::Result<()> {
+    contents
+        .parse::<toml::Table>()
+        .map(|_| ())
+        .map_err(|error| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("Invalid TOML: {}", error.message()),
+            )
+        })
+}
+
+pub fn write_file_atomic(path: &Path, contents: &[u8], backup: bool) -> io::Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let temp_path = sibling_path(path, "tmp");
+    fs::write(&temp_path, contents)?;
+    if backup && path.exists() {
+        fs::copy(path, sibling_path(path, "bak")).inspect_err(|_| {
+            let _ = fs::remove_file(&temp_path);
+        })?;
+    }
+    fs::rename(&temp_path, path).inspect_err(|_| {
+        let _ = fs::remove_file(&temp_path);
+    })
+}
+
+fn sibling_path(path: &Path, extension: &str) -> PathBuf {
+    let mut name = path.file_name().map(OsString::from).unwrap_or_default();
+    name.push(".");
+    name.push(extension);
+    path.with_file_name(name)
+}
+
+fn write_file_if_missing(path: &Path, contents: &[u8]) -> io::Result<bool> {
     match OpenOptions::new().create_new(true).write(true).open(path) {
         Ok(mut file) => {
             logging::trace(format!("creating {}", path.display()));
-            file.write_all(contents)
+            file.write_all(contents)?;
+            Ok(true)
         }
-        Err(error) if error.kind() == io::ErrorKind::AlreadyExists => Ok(()),
+        Err(error) if error.kind() == io::ErrorKind::AlreadyExists => Ok(false),
         Err(error) => Err(error),
     }
 }
 
+pub fn codex_home() -> Option<PathBuf> {
+    codex_home_from(|key| env::var_os(key))
+}
+
+fn codex_home_from(lookup: impl Fn(&str) -> Option<OsString>) -> Option<PathBuf> {
+    if let Some(path) = lookup("CODEX_HOME").filter(|value| !value.is_empty()) {
+        return Some(PathBuf::from(path));
+    }
+    lookup("USERPROFILE")
+        .filter(|value| !value.is_empty())
+        .or_else(|| lookup("HOME").filter(|value| !value.is_empty()))
+        .map(|path| PathBuf::from(path).join(".codex"))
+}
+
 fn codex_config_path() -> Option<PathBuf> {
-    env::var_os("USERPROFILE")
-        .map(PathBuf::from)
-        .map(|path| path.join(".codex").join("config.toml"))
+    codex_home().map(|path| path.join("config.toml"))
 }
 
 fn parse_model(contents: &str) -> Option<String> {
+    parse_string_value(contents, "model")
+}
+
+fn parse_string_value(contents: &str, name: &str) -> Option<String> {
     contents.lines().find_map(|line| {
         let (key, value) = line.split_once('=')?;
-        if key.trim() != "model" {
+        if key.trim() != name {
             return None;
         }
         let value = value.trim();
@@ -789,7 +1474,7 @@ fn parse_model(contents: &str) -> Option<String> {
     })
 }
 
-pub(crate) fn available_models(current_model: &str) -> Vec<String> {
+pub fn available_models(current_model: &str) -> Vec<String> {
     let mut models = models_from_cache().unwrap_or_else(fallback_models);
     if !current_model.is_empty() && !models.iter().any(|model| model == current_model) {
         models.insert(0, current_model.to_owned());
@@ -863,9 +1548,7 @@ fn models_from_cache() -> Option<Vec<String>> {
 }
 
 fn models_cache_path() -> Option<PathBuf> {
-    env::var_os("USERPROFILE")
-        .map(PathBuf::from)
-        .map(|path| path.join(".codex").join("models_cache.json"))
+    codex_home().map(|path| path.join("models_cache.json"))
 }
 
 fn fallback_models() -> Vec<String> {
@@ -924,3 +1607,132 @@ fn quoted_value(value: &str) -> String {
     quoted.push('"');
     quoted
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn wide_strings_round_trip() {
+        let wide: Vec<u16> = r"C:\Users\dev\project".encode_utf16().collect();
+        assert_eq!(
+            os_string_from_wide(&wide),
+            OsString::from(r"C:\Users\dev\project")
+        );
+    }
+
+    fn lookup_from<'a>(vars: &'a [(&str, &str)]) -> impl Fn(&str) -> Option<OsString> + 'a {
+        |key| {
+            vars.iter()
+                .find(|(name, _)| *name == key)
+                .map(|(_, value)| OsString::from(value))
+        }
+    }
+
+    #[test]
+    fn codex_home_prefers_the_codex_home_variable() {
+        let home = codex_home_from(lookup_from(&[
+            ("CODEX_HOME", "/srv/codex"),
+            ("USERPROFILE", "/users/dev"),
+            ("HOME", "/home/dev"),
+        ]));
+        assert_eq!(home, Some(PathBuf::from("/srv/codex")));
+    }
+
+    #[test]
+    fn empty_codex_home_falls_back_to_the_profile() {
+        let home = codex_home_from(lookup_from(&[
+            ("CODEX_HOME", ""),
+            ("USERPROFILE", "/users/dev"),
+            ("HOME", "/home/dev"),
+        ]));
+        assert_eq!(home, Some(Path::new("/users/dev").join(".codex")));
+    }
+
+    #[test]
+    fn unset_codex_home_falls_back_to_home() {
+        let home = codex_home_from(lookup_from(&[("USERPROFILE", ""), ("HOME", "/home/dev")]));
+        assert_eq!(home, Some(Path::new("/home/dev").join(".codex")));
+        assert_eq!(codex_home_from(lookup_from(&[])), None);
+    }
+
+    #[cfg(not(windows))]
+    #[test]
+    fn unpaired_surrogates_are_replaced_off_windows() {
+        assert_eq!(
+            os_string_from_wide(&[0x61, 0xD800, 0x62]),
+            OsString::from("a\u{FFFD}b")
+        );
+    }
+
+    #[test]
+    fn codex_file_checks_are_reused_until_they_expire() {
+        let home = PathBuf::from("/home/dev/.codex");
+        let at = Instant::now();
+        let verified = (home.clone(), at);
+        assert!(codex_files_fresh(Some(&verified), Some(&home), at));
+        assert!(codex_files_fresh(
+            Some(&verified),
+            Some(&home),
+            at + CODEX_FILES_RECHECK_INTERVAL - Duration::from_secs(1)
+        ));
+        assert!(!codex_files_fresh(
+            Some(&verified),
+            Some(&home),
+            at + CODEX_FILES_RECHECK_INTERVAL
+        ));
+    }
+
+    #[test]
+    fn codex_file_checks_rerun_when_the_home_changes() {
+        let at = Instant::now();
+        let verified = (PathBuf::from("/home/dev/.codex"), at);
+        assert!(!codex_files_fresh(
+            Some(&verified),
+            Some(Path::new("/srv/codex")),
+            at
+        ));
+        assert!(!codex_files_fresh(Some(&verified), None, at));
+        assert!(!codex_files_fresh(
+            None,
+            Some(Path::new("/home/dev/.codex")),
+            at
+        ));
+    }
+
+    #[test]
+    fn missing_codex_files_are_created_once() {
+        let dir = env::temp_dir().join(format!("codexagent-codex-files-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let home = dir.join(".codex");
+        assert_eq!(
+            write_codex_files(&home).expect("created codex files"),
+            [CodexFile::Config, CodexFile::Agents]
+        );
+        fs::write(home.join("AGENTS.md"), "edited").expect("edited AGENTS.md");
+        assert!(
+            write_codex_files(&home)
+                .expect("checked codex files")
+                .is_empty()
+        );
+        assert_eq!(
+            fs::read_to_string(home.join("AGENTS.md")).expect("AGENTS.md"),
+            "edited"
+        );
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn codex_file_failures_are_returned() {
+        let dir = env::temp_dir().join(format!(
+            "codexagent-codex-files-blocked-{}",
+            std::process::id()
+        ));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).expect("temp dir");
+        let blocker = dir.join("not-a-dir");
+        fs::write(&blocker, "file").expect("blocking file");
+        assert!(write_codex_files(&blocker.join(".codex")).is_err());
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,134 @@
+#[cfg(windows)]
+use std::ffi::c_void;
+#[cfg(windows)]
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc;
+
+#[cfg(windows)]
+use windows_sys::Win32::System::Power::{
+    DEVICE_NOTIFY_SUBSCRIBE_PARAMETERS, HPOWERNOTIFY, PowerRegisterSuspendResumeNotification,
+    PowerUnregisterSuspendResumeNotification,
+};
+#[cfg(windows)]
+use windows_sys::Win32::UI::WindowsAndMessaging::{
+    DEVICE_NOTIFY_CALLBACK, PBT_APMRESUMEAUTOMATIC, PBT_APMRESUMESUSPEND, PBT_APMSUSPEND,
+};
+
+use crate::events::{AppEvent, RepaintHandle};
+#[cfg(windows)]
+use crate::logging;
+
+#[cfg(windows)]
+static DISPLAY_WAKE_PAUSED: AtomicBool = AtomicBool::new(false);
+
+#[cfg_attr(not(windows), allow(dead_code))]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum PowerEvent {
+    Suspend,
+    Resume,
+}
+
+#[cfg(windows)]
+struct PowerListener {
+    tx: mpsc::Sender<AppEvent>,
+    repaint: RepaintHandle,
+}
+
+#[cfg(windows)]
+pub struct PowerWatcher {
+    registration: HPOWERNOTIFY,
+    listener: *mut PowerListener,
+    _parameters: Box<DEVICE_NOTIFY_SUBSCRIBE_PARAMETERS>,
+}
+
+#[cfg(windows)]
+impl Drop for PowerWatcher {
+    fn drop(&mut self) {
+        let status = unsafe { PowerUnregisterSuspendResumeNotification(self.registration) };
+        if status != 0 {
+            logging::error(format!(
+                "failed to unregister power notifications: error {}",
+                status
+            ));
+            return;
+        }
+        drop(unsafe { Box::from_raw(self.listener) });
+        logging::trace("power notifications unregistered");
+    }
+}
+
+#[cfg(windows)]
+pub fn display_wake_paused() -> bool {
+    DISPLAY_WAKE_PAUSED.load(Ordering::Relaxed)
+}
+
+#[cfg(windows)]
+pub fn watch_power_events(
+    tx: mpsc::Sender<AppEvent>,
+    repaint: RepaintHandle,
+) -> Option<PowerWatcher> {
+    let listener = Box::into_raw(Box::new(PowerListener { tx, repaint }));
+    let mut parameters = Box::new(DEVICE_NOTIFY_SUBSCRIBE_PARAMETERS {
+        Callback: Some(power_callback),
+        Context: listener.cast(),
+    });
+    let mut registration: *mut c_void = std::ptr::null_mut();
+    let status = unsafe {
+        PowerRegisterSuspendResumeNotification(
+            DEVICE_NOTIFY_CALLBACK,
+            (&mut *parameters as *mut DEVICE_NOTIFY_SUBSCRIBE_PARAMETERS).cast(),
+            &mut registration,
+        )
+    };
+    if status != 0 {
+        drop(unsafe { Box::from_raw(listener) });
+        logging::error(format!(
+            "failed to register for power notifications: error {}",
+            status
+        ));
+        return None;
+    }
+    logging::trace("registered for power notifications");
+    Some(PowerWatcher {
+        registration: registration as HPOWERNOTIFY,
+        listener,
+        _parameters: parameters,
+    })
+}
+
+#[cfg(windows)]
+unsafe extern "system" fn power_callback(
+    context: *const c_void,
+    kind: u32,
+    _setting: *const c_void,
+) -> u32 {
+    let listener = unsafe { &*context.cast::<PowerListener>() };
+    let event = match kind {
+        PBT_APMSUSPEND => {
+            DISPLAY_WAKE_PAUSED.store(true, Ordering::Relaxed);
+            PowerEvent::Suspend
+        }
+        PBT_APMRESUMESUSPEND => {
+            DISPLAY_WAKE_PAUSED.store(false, Ordering::Relaxed);
+            PowerEvent::Resume
+        }
+        PBT_APMRESUMEAUTOMATIC => PowerEvent::Resume,
+        _ => return 0,
+    };
+    logging::trace(format!("power event: {:?}", event));
+    if listener.tx.send(AppEvent::Power(event)).is_ok() {
+        listener.repaint.request_repaint();
+    }
+    0
+}
+
+#[cfg(not(windows))]
+pub struct PowerWatcher;
+
+#[cfg(not(windows))]
+pub fn watch_power_events(
+    _tx: mpsc::Sender<AppEvent>,
+    _repaint: RepaintHandle,
+) -> Option<PowerWatcher> {
+    None
+}
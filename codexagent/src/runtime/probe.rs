@@ -0,0 +1,357 @@
+use std::cell::Cell;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::events::{AppEvent, RepaintHandle};
+use crate::logging;
+
+use crate::config::paths;
+
+use super::{GitInfo, app_data_free_mb, check_working_directory, find_agents_files, git_info};
+
+const PROBE_WATCH_INTERVAL: Duration = Duration::from_secs(5);
+
+thread_local! {
+    static ON_FRAME_PATH: Cell<bool> = const { Cell::new(false) };
+}
+
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum ProbeKind {
+    Directory,
+    AgentsFiles,
+    DiskSpace,
+    GitInfo,
+}
+
+impl ProbeKind {
+    fn ttl(self) -> Duration {
+        match self {
+            Self::Directory | Self::DiskSpace => PROBE_WATCH_INTERVAL,
+            Self::AgentsFiles => Duration::from_secs(60),
+            Self::GitInfo => Duration::from_secs(30),
+        }
+    }
+
+    fn watched(self) -> bool {
+        matches!(self, Self::Directory | Self::DiskSpace)
+    }
+}
+
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct ProbeKey {
+    pub kind: ProbeKind,
+    pub path: PathBuf,
+}
+
+impl ProbeKey {
+    pub fn directory(path: &Path) -> Self {
+        Self {
+            kind: ProbeKind::Directory,
+            path: path.to_path_buf(),
+        }
+    }
+
+    pub fn agents_files(dir: &Path) -> Self {
+        Self {
+            kind: ProbeKind::AgentsFiles,
+            path: dir.to_path_buf(),
+        }
+    }
+
+    pub fn git_info(dir: &Path) -> Self {
+        Self {
+            kind: ProbeKind::GitInfo,
+            path: dir.to_path_buf(),
+        }
+    }
+
+    pub fn disk_space() -> Self {
+        Self {
+            kind: ProbeKind::DiskSpace,
+            path: paths::get().log_dir.clone(),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum ProbeValue {
+    Directory(Result<(), String>),
+    AgentsFiles(Vec<PathBuf>),
+    DiskSpace(Option<u64>),
+    GitInfo(Option<GitInfo>),
+}
+
+#[derive(Default)]
+struct ProbeEntry {
+    value: Option<ProbeValue>,
+    checked_at: Option<Instant>,
+    pending: bool,
+    recheck: bool,
+}
+
+#[derive(Default)]
+pub struct ProbeCache {
+    entries: HashMap<ProbeKey, ProbeEntry>,
+}
+
+impl ProbeCache {
+    pub fn begin(&mut self, key: &ProbeKey, now: Instant) -> bool {
+        if !self.entries.contains_key(key) {
+            self.entries.insert(key.clone(), ProbeEntry::default());
+        }
+        let Some(entry) = self.entries.get_mut(key) else {
+            return false;
+        };
+        let fresh = entry
+            .checked_at
+            .is_some_and(|at| now.saturating_duration_since(at) < key.kind.ttl());
+        if entry.pending || fresh {
+            return false;
+        }
+        entry.pending = true;
+        true
+    }
+
+    pub fn store(&mut self, key: ProbeKey, value: ProbeValue, now: Instant) {
+        let entry = self.entries.entry(key).or_default();
+        entry.value = Some(value);
+        entry.checked_at = (!entry.recheck).then_some(now);
+        entry.pending = false;
+        entry.recheck = false;
+    }
+
+    pub fn invalidate(&mut self, key: &ProbeKey) {
+        if let Some(entry) = self.entries.get_mut(key) {
+            entry.checked_at = None;
+            entry.recheck = entry.pending;
+        }
+    }
+
+    pub fn get(&self, key: &ProbeKey) -> Option<&ProbeValue> {
+        self.entries.get(key).and_then(|entry| entry.value.as_ref())
+    }
+}
+
+pub struct ProbeService {
+    requests: mpsc::Sender<ProbeKey>,
+    cache: ProbeCache,
+}
+
+impl ProbeService {
+    pub fn spawn(tx: mpsc::Sender<AppEvent>, repaint: RepaintHandle) -> Self {
+        let (requests, queue) = mpsc::channel();
+        thread::spawn(move || {
+            let _ = logging::catch_panic("probe thread", || run_probes(&queue, &tx, &repaint));
+        });
+        Self {
+            requests,
+            cache: ProbeCache::default(),
+        }
+    }
+
+    pub fn request(&mut self, key: &ProbeKey) {
+        if !self.cache.begin(key, Instant::now()) {
+            return;
+        }
+        if self.requests.send(key.clone()).is_err() {
+            logging::error(format!("probe thread is gone; dropping {:?}", key));
+        }
+    }
+
+    pub fn invalidate(&mut self, key: &ProbeKey) {
+        self.cache.invalidate(key);
+        self.request(key);
+    }
+
+    pub fn store(&mut self, key: ProbeKey, value: ProbeValue) {
+        self.cache.store(key, value, Instant::now());
+    }
+
+    pub fn get(&self, key: &ProbeKey) -> Option<&ProbeValue> {
+        self.cache.get(key)
+    }
+}
+
+fn run_probe(key: &ProbeKey) -> ProbeValue {
+    match key.kind {
+        ProbeKind::Directory => ProbeValue::Directory(check_working_directory(&key.path)),
+        ProbeKind::AgentsFiles => ProbeValue::AgentsFiles(find_agents_files(&key.path)),
+        ProbeKind::DiskSpace => ProbeValue::DiskSpace(app_data_free_mb()),
+        ProbeKind::GitInfo => ProbeValue::GitInfo(git_info(&key.path)),
+    }
+}
+
+fn run_probes(
+    queue: &mpsc::Receiver<ProbeKey>,
+    tx: &mpsc::Sender<AppEvent>,
+    repaint: &RepaintHandle,
+) {
+    let mut watched: HashMap<ProbeKind, (ProbeKey, ProbeValue)> = HashMap::new();
+    let mut watched_at = Instant::now();
+    loop {
+        let mut results = Vec::new();
+        match queue.recv_timeout(PROBE_WATCH_INTERVAL) {
+            Ok(key) => {
+                let value = run_probe(&key);
+                if key.kind.watched() {
+                    watched.insert(key.kind, (key.clone(), value.clone()));
+                }
+                results.push((key, value));
+            }
+            Err(mpsc::RecvTimeoutError::Timeout) => {}
+            Err(mpsc::RecvTimeoutError::Disconnected) => break,
+        }
+        if watched_at.elapsed() >= PROBE_WATCH_INTERVAL {
+            watched_at = Instant::now();
+            for (key, last) in watched.values_mut() {
+                let value = run_probe(key);
+                if value != *last {
+                    logging::trace(format!("watched probe changed: {:?} -> {:?}", key, value));
+                    *last = value.clone();
+                    results.push((key.clone(), value));
+                }
+            }
+        }
+        if results.is_empty() {
+            continue;
+        }
+        for (key, value) in results {
+            if tx.send(AppEvent::ProbeResult(key, value)).is_err() {
+                return;
+            }
+        }
+        repaint.request_repaint();
+    }
+}
+
+pub struct FramePath;
+
+impl FramePath {
+    pub fn enter() -> Self {
+        ON_FRAME_PATH.set(true);
+        Self
+    }
+}
+
+impl Drop for FramePath {
+    fn drop(&mut self) {
+        ON_FRAME_PATH.set(false);
+    }
+}
+
+pub fn debug_assert_off_frame_path(operation: &str) {
+    debug_assert!(
+        !ON_FRAME_PATH.get(),
+        "{} ran on the frame path; read it through the probe service",
+        operation
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(kind: ProbeKind, path: &str) -> ProbeKey {
+        ProbeKey {
+            kind,
+            path: PathBuf::from(path),
+        }
+    }
+
+    #[test]
+    fn pending_and_fresh_probes_are_deduplicated() {
+        let mut cache = ProbeCache::default();
+        let start = Instant::now();
+        let dir = key(ProbeKind::Directory, "repo");
+        assert!(cache.begin(&dir, start));
+        assert!(!cache.begin(&dir, start + Duration::from_secs(1)));
+        assert!(cache.begin(&key(ProbeKind::AgentsFiles, "repo"), start));
+        assert!(cache.begin(&key(ProbeKind::Directory, "other"), start));
+
+        cache.store(dir.clone(), ProbeValue::Directory(Ok(())), start);
+        assert_eq!(cache.get(&dir), Some(&ProbeValue::Directory(Ok(()))));
+        assert!(!cache.begin(&dir, start + PROBE_WATCH_INTERVAL / 2));
+        assert!(cache.begin(&dir, start + PROBE_WATCH_INTERVAL));
+    }
+
+    #[test]
+    fn each_kind_expires_after_its_own_ttl() {
+        let mut cache = ProbeCache::default();
+        let start = Instant::now();
+        let agents = key(ProbeKind::AgentsFiles, "repo");
+        assert!(cache.begin(&agents, start));
+        cache.store(agents.clone(), ProbeValue::AgentsFiles(Vec::new()), start);
+        assert!(!cache.begin(&agents, start + PROBE_WATCH_INTERVAL));
+        assert!(cache.begin(&agents, start + Duration::from_secs(60)));
+
+        let git = key(ProbeKind::GitInfo, "repo");
+        assert!(cache.begin(&git, start));
+        cache.store(git.clone(), ProbeValue::GitInfo(None), start);
+        assert!(!cache.begin(&git, start + Duration::from_secs(29)));
+        assert!(cache.begin(&git, start + Duration::from_secs(30)));
+        assert!(!ProbeKind::GitInfo.watched(), "git runs only when asked");
+    }
+
+    #[test]
+    fn invalidated_git_info_is_kept_until_the_new_result() {
+        let mut cache = ProbeCache::default();
+        let start = Instant::now();
+        let git = key(ProbeKind::GitInfo, "repo");
+        let info = GitInfo {
+            branch: "main".to_owned(),
+            changes: 2,
+        };
+        assert!(cache.begin(&git, start));
+        cache.store(git.clone(), ProbeValue::GitInfo(Some(info.clone())), start);
+        cache.invalidate(&git);
+        assert!(cache.begin(&git, start));
+        assert_eq!(cache.get(&git), Some(&ProbeValue::GitInfo(Some(info))));
+        assert_eq!(cache.get(&key(ProbeKind::GitInfo, "other")), None);
+    }
+
+    #[test]
+    fn invalidating_a_pending_probe_rechecks_its_result() {
+        let mut cache = ProbeCache::default();
+        let start = Instant::now();
+        let dir = key(ProbeKind::Directory, "repo");
+        assert!(cache.begin(&dir, start));
+        cache.invalidate(&dir);
+        cache.store(
+            dir.clone(),
+            ProbeValue::Directory(Err("gone".into())),
+            start,
+        );
+        assert!(
+            cache.begin(&dir, start),
+            "a result that raced an invalidation is not fresh"
+        );
+        cache.store(dir.clone(), ProbeValue::Directory(Ok(())), start);
+        assert!(!cache.begin(&dir, start));
+        cache.invalidate(&dir);
+        assert!(cache.begin(&dir, start));
+        assert_eq!(cache.get(&dir), Some(&ProbeValue::Directory(Ok(()))));
+    }
+
+    #[test]
+    fn unknown_keys_have_no_value() {
+        let mut cache = ProbeCache::default();
+        let dir = key(ProbeKind::Directory, "repo");
+        cache.invalidate(&dir);
+        assert_eq!(cache.get(&dir), None);
+        assert!(cache.begin(&dir, Instant::now()));
+        assert_eq!(cache.get(&dir), None);
+    }
+
+    #[test]
+    fn the_frame_path_guard_is_scoped() {
+        {
+            let _frame = FramePath::enter();
+            assert!(ON_FRAME_PATH.get());
+        }
+        assert!(!ON_FRAME_PATH.get());
+        debug_assert_off_frame_path("test probe");
+    }
+}
@@ -0,0 +1,201 @@
+use std::fs;
+use std::io;
+
+#[cfg(windows)]
+use windows_sys::Win32::Foundation::{CloseHandle, FILETIME, HANDLE, STILL_ACTIVE};
+#[cfg(windows)]
+use windows_sys::Win32::System::Threading::{
+    GetExitCodeProcess, GetProcessTimes, OpenProcess, PROCESS_QUERY_LIMITED_INFORMATION,
+};
+
+use crate::config::paths;
+use crate::logging;
+
+use super::write_file_atomic;
+
+pub struct ProcessRecord {
+    pid: u32,
+}
+
+impl Drop for ProcessRecord {
+    fn drop(&mut self) {
+        let path = &paths::get().running_process_file;
+        match read_record() {
+            Some((pid, _)) if pid == self.pid => {}
+            _ => return,
+        }
+        match fs::remove_file(path) {
+            Err(error) if error.kind() != io::ErrorKind::NotFound => {
+                logging::error(format!("failed to remove {}: {}", path.display(), error));
+            }
+            _ => {}
+        }
+    }
+}
+
+pub fn record_running_process(pid: u32) -> ProcessRecord {
+    let path = &paths::get().running_process_file;
+    let created = process_creation_time(pid).unwrap_or(0);
+    let contents = format!("pid={}\ncreated={}\n", pid, created);
+    if let Err(error) = write_file_atomic(path, contents.as_bytes(), false) {
+        logging::error(format!(
+            "failed to record running process in {}: {}",
+            path.display(),
+            error
+        ));
+    }
+    ProcessRecord { pid }
+}
+
+pub fn orphaned_process() -> Option<u32> {
+    let (pid, created) = read_record()?;
+    let path = &paths::get().running_process_file;
+    if let Err(error) = fs::remove_file(path) {
+        logging::error(format!("failed to remove {}: {}", path.display(), error));
+    }
+    record_is_live(pid, created, process_creation_time(pid)).then_some(pid)
+}
+
+// A pid alone can be reused after a crash; only a process that also started
+// at the recorded time is the codex run left behind.
+fn record_is_live(pid: u32, created: u64, current: Option<u64>) -> bool {
+    if created == 0 {
+        logging::trace(format!(
+            "ignoring stale process record for pid {} without a creation time",
+            pid
+        ));
+        return false;
+    }
+    if current != Some(created) {
+        logging::trace(format!("process record for pid {} is stale", pid));
+        return false;
+    }
+    true
+}
+
+pub fn process_running(pid: u32) -> bool {
+    process_creation_time(pid).is_some()
+}
+
+fn read_record() -> Option<(u32, u64)> {
+    let path = &paths::get().running_process_file;
+    let contents = match fs::read_to_string(path) {
+        Ok(contents) => contents,
+        Err(error) => {
+            if error.kind() != io::ErrorKind::NotFound {
+                logging::error(format!("failed to read {}: {}", path.display(), error));
+            }
+            return None;
+        }
+    };
+    let record = parse_record(&contents);
+    if record.is_none() {
+        logging::error(format!("malformed process record in {}", path.display()));
+    }
+    record
+}
+
+fn parse_record(contents: &str) -> Option<(u32, u64)> {
+    let mut pid = None;
+    let mut created = None;
+    for line in contents.lines() {
+        match line.trim().split_once('=') {
+            Some(("pid", value)) => pid = value.trim().parse::<u32>().ok(),
+            Some(("created", value)) => created = value.trim().parse::<u64>().ok(),
+            _ => {}
+        }
+    }
+    match (pid, created) {
+        (Some(pid), Some(created)) if pid != 0 => Some((pid, created)),
+        _ => None,
+    }
+}
+
+#[cfg(windows)]
+fn process_creation_time(pid: u32) -> Option<u64> {
+    let handle = unsafe { OpenProcess(PROCESS_QUERY_LIMITED_INFORMATION, 0, pid) };
+    if handle.is_null() {
+        return None;
+    }
+    let created = running_process_creation_time(handle);
+    unsafe {
+        CloseHandle(handle);
+    }
+    created
+}
+
+#[cfg(windows)]
+fn running_process_creation_time(handle: HANDLE) -> Option<u64> {
+    let mut exit_code = 0u32;
+    if unsafe { GetExitCodeProcess(handle, &mut exit_code) } == 0
+        || exit_code != STILL_ACTIVE as u32
+    {
+        return None;
+    }
+    let empty = FILETIME {
+        dwLowDateTime: 0,
+        dwHighDateTime: 0,
+    };
+    let (mut creation, mut exit, mut kernel, mut user) = (empty, empty, empty, empty);
+    if unsafe { GetProcessTimes(handle, &mut creation, &mut exit, &mut kernel, &mut user) } == 0 {
+        return None;
+    }
+    Some((u64::from(creation.dwHighDateTime) << 32) | u64::from(creation.dwLowDateTime))
+}
+
+#[cfg(not(windows))]
+fn process_creation_time(pid: u32) -> Option<u64> {
+    let stat = fs::read_to_string(format!("/proc/{}/stat", pid)).ok()?;
+    stat.rsplit_once(')')?
+        .1
+        .split_whitespace()
+        .nth(19)?
+        .parse()
+        .ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn records_parse_pid_and_creation_time() {
+        assert_eq!(parse_record("pid=4242\ncreated=133\n"), Some((4242, 133)));
+        assert_eq!(
+            parse_record("created=133\r\n  pid=4242\r\n"),
+            Some((4242, 133))
+        );
+        assert_eq!(parse_record("pid=4242\ncreated=0\n"), Some((4242, 0)));
+    }
+
+    #[test]
+    fn malformed_records_are_rejected() {
+        for contents in [
+            "",
+            "pid=4242\n",
+            "created=133\n",
+            "pid=0\ncreated=133\n",
+            "pid=-1\ncreated=133\n",
+            "pid=abc\ncreated=133\n",
+            "pid=4242\ncreated=soon\n",
+        ] {
+            assert_eq!(parse_record(contents), None, "{:?}", contents);
+        }
+    }
+
+    #[test]
+    fn only_the_same_process_is_live() {
+        assert!(record_is_live(4242, 133, Some(133)));
+        assert!(!record_is_live(4242, 133, Some(134)));
+        assert!(!record_is_live(4242, 133, None));
+        assert!(!record_is_live(4242, 0, Some(0)));
+    }
+
+    #[test]
+    fn this_process_has_a_creation_time() {
+        let pid = std::process::id();
+        let created = process_creation_time(pid).expect("own creation time");
+        assert!(record_is_live(pid, created, process_creation_time(pid)));
+        assert!(process_running(pid));
+    }
+}
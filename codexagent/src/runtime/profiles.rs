@@ -0,0 +1,137 @@
+use std::fs;
+use std::io;
+use std::time::SystemTime;
+
+use toml::{Table, Value};
+
+use crate::logging;
+
+use super::codex_config_path;
+
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct CodexProfiles {
+    pub names: Vec<String>,
+    pub default: Option<String>,
+}
+
+pub fn codex_config_modified() -> Option<SystemTime> {
+    let path = codex_config_path()?;
+    fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+}
+
+pub fn codex_profiles() -> CodexProfiles {
+    let Some(path) = codex_config_path() else {
+        return CodexProfiles::default();
+    };
+    let contents = match fs::read_to_string(&path) {
+        Ok(contents) => contents,
+        Err(error) if error.kind() == io::ErrorKind::NotFound => {
+            return CodexProfiles::default();
+        }
+        Err(error) => {
+            logging::error(format!(
+                "failed to read codex config {} for profiles: {}",
+                path.display(),
+                error
+            ));
+            return CodexProfiles::default();
+        }
+    };
+    parse_profiles(&contents).unwrap_or_else(|error| {
+        logging::error(format!(
+            "ignoring profiles in codex config {}: {}",
+            path.display(),
+            error.message()
+        ));
+        CodexProfiles::default()
+    })
+}
+
+fn parse_profiles(contents: &str) -> Result<CodexProfiles, toml::de::Error> {
+    let table = contents.parse::<Table>()?;
+    let names = table
+        .get("profiles")
+        .and_then(Value::as_table)
+        .map(|profiles| {
+            profiles
+                .iter()
+                .filter(|(name, profile)| profile.is_table() && !name.trim().is_empty())
+                .map(|(name, _)| name.clone())
+                .collect()
+        })
+        .unwrap_or_default();
+    let default = table
+        .get("profile")
+        .and_then(Value::as_str)
+        .map(str::trim)
+        .filter(|name| !name.is_empty())
+        .map(str::to_owned);
+    Ok(CodexProfiles { names, default })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn profiles(contents: &str) -> CodexProfiles {
+        parse_profiles(contents).expect("sample config should parse")
+    }
+
+    #[test]
+    fn profile_tables_are_enumerated() {
+        let parsed = profiles(
+            r#"
+model = "o4-mini"
+profile = " fast "
+
+[profiles.fast]
+model = "gpt-4.1-mini"
+
+[profiles.deep]
+model = "o3"
+model_reasoning_effort = "high"
+
+[profiles."local llm"]
+model_provider = "ollama"
+
+[model_providers.ollama]
+base_url = "http://localhost:11434/v1"
+"#,
+        );
+        assert_eq!(parsed.names, ["deep", "fast", "local llm"]);
+        assert_eq!(parsed.default.as_deref(), Some("fast"));
+    }
+
+    #[test]
+    fn configs_without_profiles_have_none() {
+        assert_eq!(profiles(""), CodexProfiles::default());
+        assert_eq!(profiles("model = \"o3\"\n"), CodexProfiles::default());
+        assert_eq!(profiles("profile = \"  \"\n").default, None);
+    }
+
+    #[test]
+    fn non_table_profiles_are_skipped() {
+        let parsed = profiles(
+            r#"
+profile = 3
+profiles = { fast = { model = "o4-mini" }, broken = "o3", "" = {} }
+"#,
+        );
+        assert_eq!(parsed.names, ["fast"]);
+        assert_eq!(parsed.default, None);
+        assert!(profiles("profiles = [\"fast\"]\n").names.is_empty());
+    }
+
+    #[test]
+    fn malformed_configs_are_errors() {
+        for contents in [
+            "[profiles.fast\nmodel = \"o3\"",
+            "model = ",
+            "[profiles.fast]\nmodel = \"o3\"\n[profiles.fast]\nmodel = \"o4\"",
+        ] {
+            assert!(parse_profiles(contents).is_err(), "{:?}", contents);
+        }
+    }
+}
@@ -0,0 +1,215 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+use crate::config::{load_codex_seed_state, save_codex_seed_state};
+use crate::logging;
+
+use super::{CodexFile, codex_file_path, write_file_atomic};
+
+const SEEDED_FILES: [CodexFile; 2] = [CodexFile::Config, CodexFile::Agents];
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum DiffLine {
+    Same(String),
+    Removed(String),
+    Added(String),
+}
+
+#[derive(Clone, Debug)]
+pub struct CodexFileUpdate {
+    pub file: CodexFile,
+    pub diff: Vec<DiffLine>,
+}
+
+pub fn check_seeded_codex_files() -> Vec<CodexFileUpdate> {
+    let mut state = match load_codex_seed_state() {
+        Ok(state) => state,
+        Err(error) => {
+            logging::error(format!("skipping codex default check: {}", error));
+            return Vec::new();
+        }
+    };
+    let mut state_changed = false;
+    let mut updates = Vec::new();
+    for file in SEEDED_FILES {
+        let Some(path) = codex_file_path(file) else {
+            return Vec::new();
+        };
+        let current = match fs::read(&path) {
+            Ok(contents) => contents,
+            Err(error) if error.kind() == io::ErrorKind::NotFound => continue,
+            Err(error) => {
+                logging::error(format!(
+                    "failed to read {} for codex default check: {}",
+                    path.display(),
+                    error
+                ));
+                continue;
+            }
+        };
+        let defaults = file.default_contents();
+        if current == defaults {
+            state_changed |= remember_seeded(&mut state, file, defaults);
+            continue;
+        }
+        if seeded_hashes(&state, file).any(|hash| hash == content_hash(&current)) {
+            match write_file_atomic(&path, defaults, true) {
+                Ok(()) => {
+                    logging::trace(format!(
+                        "updated unmodified {} to the current defaults",
+                        path.display()
+                    ));
+                    state_changed |= remember_seeded(&mut state, file, defaults);
+                }
+                Err(error) => logging::error(format!(
+                    "failed to update {} to the current defaults: {}",
+                    path.display(),
+                    error
+                )),
+            }
+            continue;
+        }
+        if state.get(&ignored_key(file)) == Some(&content_hash(defaults)) {
+            logging::trace(format!(
+                "{} differs from the current defaults; update ignored permanently",
+                path.display()
+            ));
+            continue;
+        }
+        logging::trace(format!(
+            "{} was edited and differs from the current defaults; offering update",
+            path.display()
+        ));
+        updates.push(CodexFileUpdate {
+            file,
+            diff: line_diff(
+                &String::from_utf8_lossy(&current),
+                &String::from_utf8_lossy(defaults),
+            ),
+        });
+    }
+    if state_changed {
+        let _ = save_codex_seed_state(&state);
+    }
+    updates
+}
+
+pub fn apply_codex_file_update(file: CodexFile) -> io::Result<PathBuf> {
+    let Some(path) = codex_file_path(file) else {
+        return Err(io::Error::new(
+            io::ErrorKind::NotFound,
+            "codex home not resolved",
+        ));
+    };
+    let defaults = file.default_contents();
+    logging::log_result(write_file_atomic(&path, defaults, true), |error| {
+        format!("failed to apply default {}: {}", path.display(), error)
+    })?;
+    logging::trace(format!(
+        "applied current defaults to {} (backup kept)",
+        path.display()
+    ));
+    record_seeded(file);
+    Ok(path)
+}
+
+pub fn ignore_codex_file_update(file: CodexFile) -> io::Result<()> {
+    let mut state = load_codex_seed_state()?;
+    state.insert(ignored_key(file), content_hash(file.default_contents()));
+    save_codex_seed_state(&state)?;
+    logging::trace(format!(
+        "ignoring current {} defaults permanently",
+        file.file_name()
+    ));
+    Ok(())
+}
+
+pub(super) fn record_seeded(file: CodexFile) {
+    let Ok(mut state) = load_codex_seed_state() else {
+        return;
+    };
+    if remember_seeded(&mut state, file, file.default_contents()) {
+        let _ = save_codex_seed_state(&state);
+    }
+}
+
+fn remember_seeded(state: &mut HashMap<String, String>, file: CodexFile, contents: &[u8]) -> bool {
+    let hash = content_hash(contents);
+    if seeded_hashes(state, file).any(|seeded| seeded == hash) {
+        return false;
+    }
+    let key = seeded_key(file);
+    let value = match state.get(&key) {
+        Some(existing) if !existing.is_empty() => format!("{},{}", existing, hash),
+        _ => hash,
+    };
+    state.insert(key, value);
+    true
+}
+
+fn seeded_hashes(state: &HashMap<String, String>, file: CodexFile) -> impl Iterator<Item = &str> {
+    state
+        .get(&seeded_key(file))
+        .map(String::as_str)
+        .unwrap_or_default()
+        .split(',')
+        .filter(|hash| !hash.is_empty())
+}
+
+fn seeded_key(file: CodexFile) -> String {
+    format!("{}.seeded", file.file_name())
+}
+
+fn ignored_key(file: CodexFile) -> String {
+    format!("{}.ignored", file.file_name())
+}
+
+pub fn content_hash(contents: &[u8]) -> String {
+    let hash = contents.iter().fold(0xcbf29ce484222325u64, |hash, byte| {
+        (hash ^ u64::from(*byte)).wrapping_mul(0x100000001b3)
+    });
+    format!("{:016x}", hash)
+}
+
+fn line_diff(old: &str, new: &str) -> Vec<DiffLine> {
+    let old: Vec<&str> = old.lines().collect();
+    let new: Vec<&str> = new.lines().collect();
+    let mut common = vec![vec![0usize; new.len() + 1]; old.len() + 1];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            common[i][j] = if old[i] == new[j] {
+                common[i + 1][j + 1] + 1
+            } else {
+                common[i + 1][j].max(common[i][j + 1])
+            };
+        }
+    }
+    let mut diff = Vec::with_capacity(old.len().max(new.len()));
+    let (mut i, mut j) = (0, 0);
+    while i < old.len() && j < new.len() {
+        if old[i] == new[j] {
+            diff.push(DiffLine::Same(old[i].to_owned()));
+            i += 1;
+            j += 1;
+        } else if common[i + 1][j] >= common[i][j + 1] {
+            diff.push(DiffLine::Removed(old[i].to_owned()));
+            i += 1;
+        } else {
+            diff.push(DiffLine::Added(new[j].to_owned()));
+            j += 1;
+        }
+    }
+    diff.extend(
+        old[i..]
+            .iter()
+            .map(|line| DiffLine::Removed((*line).to_owned())),
+    );
+    diff.extend(
+        new[j..]
+            .iter()
+            .map(|line| DiffLine::Added((*line).to_owned())),
+    );
+    diff
+}
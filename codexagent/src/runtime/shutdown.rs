@@ -0,0 +1,62 @@
+use std::io;
+use std::process::{Child, ExitStatus};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+const POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+pub fn join_within<T>(
+    handle: JoinHandle<T>,
+    timeout: Duration,
+) -> Result<thread::Result<T>, JoinHandle<T>> {
+    let deadline = Instant::now() + timeout;
+    while !handle.is_finished() {
+        if Instant::now() >= deadline {
+            return Err(handle);
+        }
+        thread::sleep(POLL_INTERVAL);
+    }
+    Ok(handle.join())
+}
+
+pub fn wait_within(child: &mut Child, timeout: Duration) -> io::Result<Option<ExitStatus>> {
+    let deadline = Instant::now() + timeout;
+    loop {
+        if let Some(status) = child.try_wait()? {
+            return Ok(Some(status));
+        }
+        if Instant::now() >= deadline {
+            return Ok(None);
+        }
+        thread::sleep(POLL_INTERVAL);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::mpsc;
+
+    use super::*;
+
+    #[test]
+    fn join_within_hands_back_a_stuck_thread() {
+        let (release, stuck) = mpsc::channel::<()>();
+        let handle = thread::spawn(move || {
+            let _ = stuck.recv();
+            7
+        });
+        let started = Instant::now();
+        let handle = join_within(handle, Duration::from_millis(50)).unwrap_err();
+        assert!(started.elapsed() < Duration::from_secs(1));
+        drop(release);
+        let joined = join_within(handle, Duration::from_secs(5));
+        assert_eq!(joined.ok().and_then(Result::ok), Some(7));
+    }
+
+    #[test]
+    fn join_within_reports_a_panicked_thread() {
+        let handle = thread::spawn(|| panic!("reader failed"));
+        let joined = join_within::<()>(handle, Duration::from_secs(5));
+        assert!(matches!(joined, Ok(Err(_))));
+    }
+}
@@ -0,0 +1,20 @@
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use super::probe::debug_assert_off_frame_path;
+
+pub fn check_working_directory(path: &Path) -> Result<(), String> {
+    debug_assert_off_frame_path("working directory check");
+    match fs::metadata(path) {
+        Ok(metadata) if metadata.is_dir() => Ok(()),
+        Ok(_) => Err("the path is no longer a folder".to_owned()),
+        Err(error) if error.kind() == io::ErrorKind::NotFound => {
+            Err("the folder no longer exists".to_owned())
+        }
+        Err(error) if error.kind() == io::ErrorKind::PermissionDenied => {
+            Err("access to the folder was denied".to_owned())
+        }
+        Err(error) => Err(error.to_string()),
+    }
+}
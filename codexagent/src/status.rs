@@ -1,4 +1,3 @@
-use std::env;
 use std::fs::{self, File};
 use std::io::{self, BufRead, BufReader};
 use std::path::{Path, PathBuf};
@@ -7,9 +6,11 @@ use serde::Deserialize;
 use time::format_description::well_known::Rfc3339;
 use time::{Duration, OffsetDateTime, UtcOffset, Weekday};
 
+use crate::i18n::{Text, clock_time, tr, tr_fill};
 use crate::logging;
+use crate::runtime::codex_home;
 
-pub(crate) fn current_usage_text() -> String {
+pub fn current_usage_text() -> String {
     match collect_usage() {
         Ok(status) => format_status(status),
         Err(error) => {
@@ -128,7 +129,7 @@ fn collect_usage() -> io::Result<UsageStatus> {
                 dirs.push(path);
                 continue;
             }
-            if !path.extension().is_some_and(|ext| ext == "jsonl") {
+            if path.extension().is_none_or(|ext| ext != "jsonl") {
                 continue;
             }
             let Some(status) = (match read_session_usage(&path) {
@@ -157,9 +158,7 @@ fn collect_usage() -> io::Result<UsageStatus> {
 }
 
 fn sessions_dir() -> Option<PathBuf> {
-    env::var_os("USERPROFILE")
-        .map(PathBuf::from)
-        .map(|path| path.join(".codex").join("sessions"))
+    codex_home().map(|path| path.join("sessions"))
 }
 
 fn read_session_usage(path: &Path) -> io::Result<Option<RateLimitStatus>> {
@@ -263,22 +262,24 @@ fn format_reset(limit: RateLimit, now: OffsetDateTime, local_offset: UtcOffset)
     if let Some(resets_at) = normalize_reset(limit, now) {
         let remaining_seconds = (resets_at - now).whole_seconds().max(1);
         if remaining_seconds <= 10 * 60 {
-            return format!(
-                "Resets in {}",
-                format_duration_seconds(remaining_seconds as u64)
+            return tr_fill(
+                Text::ResetsIn,
+                &[&format_duration_seconds(remaining_seconds as u64)],
             );
         }
         let now_local = now.to_offset(local_offset).date();
         if resets_at.to_offset(local_offset).date() == now_local {
-            return format!(
-                "Resets Today at {}",
-                format_local_time(resets_at, local_offset)
+            return tr_fill(
+                Text::ResetsTodayAt,
+                &[&format_local_time(resets_at, local_offset)],
             );
         }
-        return format!(
-            "Resets {} at {}",
-            format_local_day(resets_at, local_offset),
-            format_local_time(resets_at, local_offset)
+        return tr_fill(
+            Text::ResetsOnAt,
+            &[
+                format_local_day(resets_at, local_offset),
+                &format_local_time(resets_at, local_offset),
+            ],
         );
     }
     format!("Window {}", format_duration_minutes(limit.window_minutes))
@@ -301,29 +302,20 @@ fn normalize_reset(limit: RateLimit, now: OffsetDateTime) -> Option<OffsetDateTi
 }
 
 fn format_local_day(datetime: OffsetDateTime, local_offset: UtcOffset) -> &'static str {
-    match datetime.to_offset(local_offset).weekday() {
-        Weekday::Monday => "Monday",
-        Weekday::Tuesday => "Tuesday",
-        Weekday::Wednesday => "Wednesday",
-        Weekday::Thursday => "Thursday",
-        Weekday::Friday => "Friday",
-        Weekday::Saturday => "Saturday",
-        Weekday::Sunday => "Sunday",
-    }
+    tr(match datetime.to_offset(local_offset).weekday() {
+        Weekday::Monday => Text::Monday,
+        Weekday::Tuesday => Text::Tuesday,
+        Weekday::Wednesday => Text::Wednesday,
+        Weekday::Thursday => Text::Thursday,
+        Weekday::Friday => Text::Friday,
+        Weekday::Saturday => Text::Saturday,
+        Weekday::Sunday => Text::Sunday,
+    })
 }
 
 fn format_local_time(datetime: OffsetDateTime, local_offset: UtcOffset) -> String {
     let local = datetime.to_offset(local_offset);
-    let hour = local.hour();
-    let display_hour = match hour % 12 {
-        0 => 12,
-        value => value,
-    };
-    let suffix = if hour < 12 { "am" } else { "pm" };
-    if local.minute() == 0 {
-        return format!("{}{}", display_hour, suffix);
-    }
-    format!("{}:{:02}{}", display_hour, local.minute(), suffix)
+    clock_time(local.hour(), local.minute())
 }
 
 fn format_duration_minutes(minutes: u64) -> String {
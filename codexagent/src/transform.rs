@@ -0,0 +1,432 @@
+use std::fs;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+const MAX_INLINE_FILE_BYTES: u64 = 64 * 1024;
+const MAX_INLINE_TOTAL_BYTES: u64 = 256 * 1024;
+const BINARY_SNIFF_BYTES: usize = 8 * 1024;
+const CLIPBOARD_REFERENCE: &str = "@clipboard";
+const REFERENCE_TRAILING_PUNCTUATION: [char; 7] = ['.', ',', ';', ':', '!', '?', ')'];
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Transformer {
+    TrailingWhitespace,
+    FileReferences,
+    Clipboard,
+}
+
+pub const TRANSFORMERS: [Transformer; 3] = [
+    Transformer::TrailingWhitespace,
+    Transformer::FileReferences,
+    Transformer::Clipboard,
+];
+
+pub struct TransformContext<'a> {
+    pub cwd: &'a Path,
+    pub clipboard: &'a dyn Fn() -> Option<String>,
+}
+
+impl Transformer {
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::TrailingWhitespace => "Trailing whitespace",
+            Self::FileReferences => "@file references",
+            Self::Clipboard => "@clipboard",
+        }
+    }
+
+    pub fn description(self) -> &'static str {
+        match self {
+            Self::TrailingWhitespace => "Strip spaces at line ends",
+            Self::FileReferences => "Inline referenced files",
+            Self::Clipboard => "Inline clipboard text",
+        }
+    }
+
+    fn apply(self, prompt: &str, context: &TransformContext<'_>) -> Option<(String, String)> {
+        match self {
+            Self::TrailingWhitespace => strip_trailing_whitespace(prompt),
+            Self::FileReferences => expand_file_references(prompt, context.cwd),
+            Self::Clipboard => expand_clipboard(prompt, context.clipboard),
+        }
+    }
+}
+
+pub fn transform_prompt(
+    mut prompt: String,
+    enabled: &[Transformer],
+    context: &TransformContext<'_>,
+) -> (String, Vec<String>) {
+    let mut notes = Vec::new();
+    for transformer in TRANSFORMERS {
+        if !enabled.contains(&transformer) {
+            continue;
+        }
+        if let Some((rewritten, note)) = transformer.apply(&prompt, context) {
+            prompt = rewritten;
+            notes.push(note);
+        }
+    }
+    (prompt, notes)
+}
+
+fn strip_trailing_whitespace(prompt: &str) -> Option<(String, String)> {
+    let mut stripped = 0;
+    let rewritten = prompt
+        .split('\n')
+        .map(|line| {
+            let trimmed = line.trim_end();
+            if trimmed.len() != line.len() {
+                stripped += 1;
+            }
+            trimmed
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+    (stripped > 0).then(|| {
+        (
+            rewritten,
+            format!(
+                "Stripped trailing whitespace from {} line{}",
+                stripped,
+                if stripped == 1 { "" } else { "s" }
+            ),
+        )
+    })
+}
+
+fn references(prompt: &str) -> impl Iterator<Item = (usize, &str)> {
+    prompt
+        .char_indices()
+        .filter(|&(index, c)| {
+            c == '@'
+                && prompt[..index]
+                    .chars()
+                    .next_back()
+                    .is_none_or(char::is_whitespace)
+        })
+        .map(|(index, _)| {
+            let len = prompt[index..]
+                .find(char::is_whitespace)
+                .unwrap_or(prompt.len() - index);
+            (index, &prompt[index..index + len])
+        })
+}
+
+fn expand_file_references(prompt: &str, cwd: &Path) -> Option<(String, String)> {
+    let mut rewritten = String::with_capacity(prompt.len());
+    let mut copied = 0;
+    let mut inlined = Vec::new();
+    let mut skipped = Vec::new();
+    let mut total = 0;
+    for (start, token) in references(prompt) {
+        if token == CLIPBOARD_REFERENCE {
+            continue;
+        }
+        let Some((reference, path)) = resolve_reference(&token[1..], cwd) else {
+            continue;
+        };
+        let end = start + 1 + reference.len();
+        rewritten.push_str(&prompt[copied..start]);
+        copied = end;
+        if inlined.iter().any(|seen| seen == reference) {
+            rewritten.push('`');
+            rewritten.push_str(reference);
+            rewritten.push('`');
+            continue;
+        }
+        match read_inline_file(&path, MAX_INLINE_TOTAL_BYTES.saturating_sub(total)) {
+            Ok(contents) => {
+                total += contents.len() as u64;
+                push_fenced_block(
+                    &mut rewritten,
+                    reference,
+                    fence_language(reference),
+                    &contents,
+                );
+                inlined.push(reference.to_owned());
+            }
+            Err(reason) => {
+                rewritten.push_str(&prompt[start..end]);
+                skipped.push(format!("@{} ({})", reference, reason));
+            }
+        }
+    }
+    if inlined.is_empty() && skipped.is_empty() {
+        return None;
+    }
+    rewritten.push_str(&prompt[copied..]);
+    let mut note = Vec::new();
+    if !inlined.is_empty() {
+        note.push(format!("Inlined @{}", inlined.join(", @")));
+    }
+    if !skipped.is_empty() {
+        note.push(format!("Left {} as written", skipped.join(", ")));
+    }
+    Some((rewritten, note.join("; ")))
+}
+
+fn resolve_reference<'a>(reference: &'a str, cwd: &Path) -> Option<(&'a str, PathBuf)> {
+    let trimmed = reference.trim_end_matches(REFERENCE_TRAILING_PUNCTUATION);
+    [reference, trimmed]
+        .into_iter()
+        .filter(|candidate| !candidate.is_empty())
+        .map(|candidate| (candidate, cwd.join(candidate)))
+        .find(|(_, path)| path.is_file())
+}
+
+fn read_inline_file(path: &Path, budget: u64) -> Result<String, &'static str> {
+    let len = fs::metadata(path).map_err(|_| "unreadable")?.len();
+    if len > MAX_INLINE_FILE_BYTES {
+        return Err("too large");
+    }
+    if len > budget {
+        return Err("inline limit reached");
+    }
+    let mut bytes = Vec::with_capacity(len as usize);
+    fs::File::open(path)
+        .and_then(|file| file.take(MAX_INLINE_FILE_BYTES).read_to_end(&mut bytes))
+        .map_err(|_| "unreadable")?;
+    if bytes[..bytes.len().min(BINARY_SNIFF_BYTES)].contains(&0) {
+        return Err("binary");
+    }
+    String::from_utf8(bytes).map_err(|_| "binary")
+}
+
+fn fence_language(reference: &str) -> &str {
+    Path::new(reference)
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .unwrap_or_default()
+}
+
+fn push_fenced_block(rewritten: &mut String, label: &str, language: &str, contents: &str) {
+    let longest_run = contents
+        .split(|c| c != '`')
+        .map(str::len)
+        .max()
+        .unwrap_or_default();
+    let fence = "`".repeat(longest_run.max(2) + 1);
+    rewritten.push('`');
+    rewritten.push_str(label);
+    rewritten.push_str("`:\n");
+    rewritten.push_str(&fence);
+    rewritten.push_str(language);
+    rewritten.push('\n');
+    rewritten.push_str(contents.trim_end_matches('\n'));
+    rewritten.push('\n');
+    rewritten.push_str(&fence);
+    rewritten.push('\n');
+}
+
+fn expand_clipboard(
+    prompt: &str,
+    clipboard: &dyn Fn() -> Option<String>,
+) -> Option<(String, String)> {
+    let starts: Vec<usize> = references(prompt)
+        .filter(|&(_, token)| {
+            token.trim_end_matches(REFERENCE_TRAILING_PUNCTUATION) == CLIPBOARD_REFERENCE
+        })
+        .map(|(start, _)| start)
+        .collect();
+    if starts.is_empty() {
+        return None;
+    }
+    let text = clipboard().filter(|text| !text.trim().is_empty());
+    let Some(text) = text else {
+        return Some((
+            prompt.to_owned(),
+            "Clipboard has no text; left @clipboard as written".to_owned(),
+        ));
+    };
+    if text.len() as u64 > MAX_INLINE_FILE_BYTES {
+        return Some((
+            prompt.to_owned(),
+            "Clipboard text is too large; left @clipboard as written".to_owned(),
+        ));
+    }
+    let mut rewritten = String::with_capacity(prompt.len() + text.len() * starts.len());
+    let mut copied = 0;
+    for start in &starts {
+        rewritten.push_str(&prompt[copied..*start]);
+        push_fenced_block(&mut rewritten, "clipboard", "", &text);
+        copied = start + CLIPBOARD_REFERENCE.len();
+    }
+    rewritten.push_str(&prompt[copied..]);
+    Some((
+        rewritten,
+        format!("Inlined clipboard text ({} chars)", text.chars().count()),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, process};
+
+    use super::*;
+
+    struct Fixture(PathBuf);
+
+    impl Fixture {
+        fn new(name: &str) -> Self {
+            let dir =
+                env::temp_dir().join(format!("codexagent-transform-{}-{}", name, process::id()));
+            fs::create_dir_all(dir.join("src")).unwrap();
+            fs::write(dir.join("src/main.rs"), "fn main() {}\n").unwrap();
+            Self(dir)
+        }
+
+        fn write(&self, name: &str, contents: impl AsRef<[u8]>) {
+            fs::write(self.0.join(name), contents).unwrap();
+        }
+    }
+
+    impl Drop for Fixture {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    fn no_clipboard() -> Option<String> {
+        None
+    }
+
+    #[test]
+    fn trailing_whitespace_is_stripped_per_line() {
+        assert_eq!(
+            strip_trailing_whitespace("fix this  \nand that\t\n  keep indent"),
+            Some((
+                "fix this\nand that\n  keep indent".to_owned(),
+                "Stripped trailing whitespace from 2 lines".to_owned()
+            ))
+        );
+        assert_eq!(strip_trailing_whitespace("clean\nprompt"), None);
+    }
+
+    #[test]
+    fn file_references_inline_relative_to_the_cwd() {
+        let fixture = Fixture::new("inline");
+        let (rewritten, note) =
+            expand_file_references("Explain @src/main.rs.", &fixture.0).unwrap();
+        assert_eq!(
+            rewritten,
+            "Explain `src/main.rs`:\n```rs\nfn main() {}\n```\n."
+        );
+        assert_eq!(note, "Inlined @src/main.rs");
+    }
+
+    #[test]
+    fn repeated_references_inline_once() {
+        let fixture = Fixture::new("repeat");
+        let (rewritten, _) =
+            expand_file_references("@src/main.rs then @src/main.rs", &fixture.0).unwrap();
+        assert_eq!(rewritten.matches("fn main()").count(), 1);
+        assert!(rewritten.ends_with(" then `src/main.rs`"));
+    }
+
+    #[test]
+    fn large_and_binary_files_are_left_as_written() {
+        let fixture = Fixture::new("skip");
+        fixture.write("big.txt", "x".repeat(MAX_INLINE_FILE_BYTES as usize + 1));
+        fixture.write("blob.bin", b"PK\x03\x04\x00\x00data");
+        fixture.write("latin1.txt", b"caf\xE9");
+        let prompt = "Compare @big.txt with @blob.bin and @latin1.txt";
+        let (rewritten, note) = expand_file_references(prompt, &fixture.0).unwrap();
+        assert_eq!(rewritten, prompt);
+        assert_eq!(
+            note,
+            "Left @big.txt (too large), @blob.bin (binary), @latin1.txt (binary) as written"
+        );
+    }
+
+    #[test]
+    fn inlining_stops_at_the_total_limit() {
+        let fixture = Fixture::new("total");
+        let chunk = "y".repeat(60 * 1024);
+        for name in ["a.txt", "b.txt", "c.txt", "d.txt", "e.txt"] {
+            fixture.write(name, &chunk);
+        }
+        let (_, note) =
+            expand_file_references("@a.txt @b.txt @c.txt @d.txt @e.txt", &fixture.0).unwrap();
+        assert_eq!(
+            note,
+            "Inlined @a.txt, @b.txt, @c.txt, @d.txt; Left @e.txt (inline limit reached) as written"
+        );
+    }
+
+    #[test]
+    fn mentions_that_are_not_files_are_ignored() {
+        let fixture = Fixture::new("ignore");
+        for prompt in [
+            "mail me@example.com",
+            "ping @reviewer about it",
+            "see @src",
+            "nothing here",
+        ] {
+            assert_eq!(
+                expand_file_references(prompt, &fixture.0),
+                None,
+                "{}",
+                prompt
+            );
+        }
+    }
+
+    #[test]
+    fn fences_outgrow_backticks_in_the_file() {
+        let fixture = Fixture::new("fence");
+        fixture.write("README.md", "```sh\nmake\n```\n");
+        let (rewritten, _) = expand_file_references("@README.md", &fixture.0).unwrap();
+        assert_eq!(rewritten, "`README.md`:\n````md\n```sh\nmake\n```\n````\n");
+    }
+
+    #[test]
+    fn clipboard_references_inline_the_clipboard_text() {
+        let clipboard = || Some("panic at line 3".to_owned());
+        assert_eq!(
+            expand_clipboard("Why @clipboard?", &clipboard),
+            Some((
+                "Why `clipboard`:\n```\npanic at line 3\n```\n?".to_owned(),
+                "Inlined clipboard text (15 chars)".to_owned()
+            ))
+        );
+        assert_eq!(expand_clipboard("no reference", &clipboard), None);
+        assert_eq!(
+            expand_clipboard("Why @clipboard", &no_clipboard),
+            Some((
+                "Why @clipboard".to_owned(),
+                "Clipboard has no text; left @clipboard as written".to_owned()
+            ))
+        );
+        let huge = || Some("z".repeat(MAX_INLINE_FILE_BYTES as usize + 1));
+        assert_eq!(
+            expand_clipboard("@clipboard", &huge).map(|(_, note)| note),
+            Some("Clipboard text is too large; left @clipboard as written".to_owned())
+        );
+    }
+
+    #[test]
+    fn only_enabled_transformers_run_in_order() {
+        let fixture = Fixture::new("pipeline");
+        let clipboard = || Some("copied  ".to_owned());
+        let context = TransformContext {
+            cwd: &fixture.0,
+            clipboard: &clipboard,
+        };
+        let prompt = "Read @src/main.rs  \n@clipboard".to_owned();
+        let (rewritten, notes) = transform_prompt(prompt.clone(), &[], &context);
+        assert_eq!((rewritten.as_str(), notes.len()), (prompt.as_str(), 0));
+
+        let (rewritten, notes) = transform_prompt(prompt, &TRANSFORMERS, &context);
+        assert_eq!(
+            notes,
+            [
+                "Stripped trailing whitespace from 1 line",
+                "Inlined @src/main.rs",
+                "Inlined clipboard text (8 chars)"
+            ]
+        );
+        assert!(rewritten.contains("fn main() {}"));
+        assert!(rewritten.ends_with("```\ncopied  \n```\n"));
+    }
+}
@@ -0,0 +1,276 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::logging;
+
+const TOKENS_PER_MILLION: f64 = 1_000_000.0;
+
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Serialize)]
+#[serde(default)]
+pub struct TokenUsage {
+    pub input_tokens: u64,
+    pub cached_input_tokens: u64,
+    pub output_tokens: u64,
+}
+
+impl TokenUsage {
+    pub fn from_event(event: &Value) -> Option<Self> {
+        if event.get("type").and_then(Value::as_str) != Some("turn.completed") {
+            return None;
+        }
+        serde_json::from_value(event.get("usage")?.clone()).ok()
+    }
+
+    pub fn add(&mut self, other: TokenUsage) {
+        self.input_tokens += other.input_tokens;
+        self.cached_input_tokens += other.cached_input_tokens;
+        self.output_tokens += other.output_tokens;
+    }
+
+    pub fn total(&self) -> u64 {
+        self.input_tokens + self.output_tokens
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ModelPrice {
+    pub input_per_million: f64,
+    pub output_per_million: f64,
+}
+
+impl ModelPrice {
+    fn parse(value: &str) -> Option<Self> {
+        let (input, output) = value.split_once(',')?;
+        let input = input.trim().trim_start_matches('$').parse::<f64>().ok()?;
+        let output = output.trim().trim_start_matches('$').parse::<f64>().ok()?;
+        (input >= 0.0 && output >= 0.0).then_some(Self {
+            input_per_million: input,
+            output_per_million: output,
+        })
+    }
+
+    pub fn cost(&self, usage: TokenUsage) -> f64 {
+        (usage.input_tokens as f64 * self.input_per_million
+            + usage.output_tokens as f64 * self.output_per_million)
+            / TOKENS_PER_MILLION
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct PriceTable {
+    prices: HashMap<String, ModelPrice>,
+}
+
+impl PriceTable {
+    pub fn parse(entries: &HashMap<String, String>) -> Self {
+        let mut prices = HashMap::new();
+        for (model, value) in entries {
+            match ModelPrice::parse(value) {
+                Some(price) => {
+                    prices.insert(model.to_ascii_lowercase(), price);
+                }
+                None => logging::error(format!(
+                    "ignoring price for {}: expected \"input,output\" per million tokens, got {}",
+                    model, value
+                )),
+            }
+        }
+        Self { prices }
+    }
+
+    pub fn cost(&self, model: &str, usage: TokenUsage) -> Option<f64> {
+        self.prices
+            .get(&model.to_ascii_lowercase())
+            .map(|price| price.cost(usage))
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct SessionUsage {
+    pub tokens: TokenUsage,
+    pub cost: Option<f64>,
+    pub unpriced_prompts: usize,
+}
+
+impl SessionUsage {
+    pub fn record(&mut self, usage: TokenUsage, cost: Option<f64>) {
+        self.tokens.add(usage);
+        match cost {
+            Some(cost) => *self.cost.get_or_insert(0.0) += cost,
+            None => self.unpriced_prompts += 1,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tokens.total() == 0
+    }
+
+    pub fn summary(&self) -> String {
+        let tokens = format!("{} tok", format_tokens(self.tokens.total()));
+        match self.cost {
+            Some(cost) => format!("{} \u{b7} {}", tokens, format_cost(cost)),
+            None => tokens,
+        }
+    }
+
+    pub fn details(&self) -> String {
+        let mut details = format!(
+            "Input {} ({} cached)\nOutput {}",
+            self.tokens.input_tokens, self.tokens.cached_input_tokens, self.tokens.output_tokens
+        );
+        if self.unpriced_prompts > 0 {
+            details.push_str(&format!(
+                "\n{} prompt(s) without a configured price",
+                self.unpriced_prompts
+            ));
+        }
+        details
+    }
+}
+
+pub fn estimate_tokens(text: &str) -> u64 {
+    let mut tokens = 0u64;
+    let mut word_chars = 0u64;
+    for c in text.chars() {
+        if c.is_ascii_alphanumeric() {
+            word_chars += 1;
+            continue;
+        }
+        tokens += word_chars.div_ceil(4);
+        word_chars = 0;
+        if !c.is_whitespace() {
+            tokens += 1;
+        }
+    }
+    tokens + word_chars.div_ceil(4)
+}
+
+pub fn format_tokens(tokens: u64) -> String {
+    match tokens {
+        0..1_000 => tokens.to_string(),
+        1_000..1_000_000 => format!("{:.1}k", tokens as f64 / 1_000.0),
+        _ => format!("{:.2}M", tokens as f64 / 1_000_000.0),
+    }
+}
+
+pub fn format_cost(cost: f64) -> String {
+    if cost < 0.01 {
+        format!("${:.4}", cost)
+    } else {
+        format!("${:.2}", cost)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn usage(input: u64, output: u64) -> TokenUsage {
+        TokenUsage {
+            input_tokens: input,
+            cached_input_tokens: 0,
+            output_tokens: output,
+        }
+    }
+
+    fn table(entries: &[(&str, &str)]) -> PriceTable {
+        PriceTable::parse(
+            &entries
+                .iter()
+                .map(|(model, price)| (model.to_string(), price.to_string()))
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn usage_comes_from_completed_turns_only() {
+        let event = serde_json::json!({
+            "type": "turn.completed",
+            "usage": {"input_tokens": 1200, "cached_input_tokens": 200, "output_tokens": 80}
+        });
+        assert_eq!(
+            TokenUsage::from_event(&event),
+            Some(TokenUsage {
+                input_tokens: 1200,
+                cached_input_tokens: 200,
+                output_tokens: 80,
+            })
+        );
+        let started = serde_json::json!({"type": "turn.started", "usage": {"input_tokens": 5}});
+        assert_eq!(TokenUsage::from_event(&started), None);
+    }
+
+    #[test]
+    fn prices_are_per_million_tokens() {
+        let prices = table(&[("GPT-5", "$1.25, $10")]);
+        let cost = prices.cost("gpt-5", usage(2_000_000, 100_000)).unwrap();
+        assert!((cost - 3.5).abs() < 1e-9, "{}", cost);
+    }
+
+    #[test]
+    fn unknown_models_and_bad_prices_have_no_cost() {
+        let prices = table(&[("o3", "2"), ("o4-mini", "-1,4"), ("gpt-5", "1,10")]);
+        assert_eq!(prices.cost("o3", usage(10, 10)), None);
+        assert_eq!(prices.cost("o4-mini", usage(10, 10)), None);
+        assert_eq!(prices.cost("codex-mini", usage(10, 10)), None);
+    }
+
+    #[test]
+    fn session_shows_tokens_without_a_made_up_price() {
+        let mut session = SessionUsage::default();
+        assert!(session.is_empty());
+        session.record(usage(1_500, 500), None);
+        assert_eq!(session.summary(), "2.0k tok");
+        assert!(
+            session
+                .details()
+                .contains("1 prompt(s) without a configured price")
+        );
+
+        session.record(usage(100, 0), Some(0.25));
+        session.record(usage(100, 0), Some(0.5));
+        assert_eq!(session.summary(), "2.2k tok \u{b7} $0.75");
+        assert_eq!(session.unpriced_prompts, 1);
+    }
+
+    #[test]
+    fn small_costs_keep_their_digits() {
+        assert_eq!(format_cost(0.0042), "$0.0042");
+        assert_eq!(format_cost(1.5), "$1.50");
+    }
+
+    #[test]
+    fn token_estimates_are_stable() {
+        for (text, tokens) in [
+            ("", 0),
+            ("   \n\t", 0),
+            ("hello world", 4),
+            ("internationalization", 5),
+            ("fn main() {}", 6),
+            ("a, b.", 4),
+            ("日本語", 3),
+            ("Refactor src/app/ui.rs to use egui 0.31.", 16),
+        ] {
+            assert_eq!(estimate_tokens(text), tokens, "{:?}", text);
+        }
+    }
+
+    #[test]
+    fn token_estimates_add_up_across_a_paste() {
+        let line = "let total = items.iter().map(|item| item.cost).sum::<f64>();\n";
+        let paste = line.repeat(1_700);
+        assert!(paste.len() > 100_000);
+        assert_eq!(estimate_tokens(&paste), estimate_tokens(line) * 1_700);
+    }
+
+    #[test]
+    fn token_counts_are_formatted_compactly() {
+        assert_eq!(format_tokens(0), "0");
+        assert_eq!(format_tokens(999), "999");
+        assert_eq!(format_tokens(1_000), "1.0k");
+        assert_eq!(format_tokens(3_240), "3.2k");
+        assert_eq!(format_tokens(1_500_000), "1.50M");
+    }
+}
@@ -0,0 +1,1281 @@
+use std::io;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex, MutexGuard, Once};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use codexagent::CodexAgentApp;
+use codexagent::config::paths::{self, Paths};
+use codexagent::config::snapshot::{Rating, list_recent_sessions, load_snapshot};
+use codexagent::config::{
+    set_cancel_on_sleep, set_clipboard_watch, set_codex_titles, set_dirty_tree_warning_enabled,
+    set_persist_transcripts, set_project_config_enabled, set_warm_start,
+};
+use codexagent::events::AppEvent;
+use codexagent::prompt::{
+    ApprovalKind, ApprovalRequest, CommandLine, PromptBackend, PromptChannels, PromptOutput,
+    PromptRequest, RunningPrompt,
+};
+use codexagent::runtime::PowerEvent;
+
+const PID: u32 = 4242;
+const SESSION: &str = "thread-1";
+const WAIT: Duration = Duration::from_secs(5);
+
+static SERIAL: Mutex<()> = Mutex::new(());
+static SETUP: Once = Once::new();
+
+enum Step {
+    Stream(&'static str),
+    // Updates the shared stream without a notification, as when one is already pending.
+    StreamSilently(&'static str),
+    Finish(Result<&'static str, &'static str>),
+    // Ends the run the way the worker does when codex stops to ask first.
+    AskApproval(ApprovalKind, &'static str),
+}
+
+#[derive(Default)]
+struct Calls {
+    prompts: Vec<String>,
+    sessions: Vec<Option<String>>,
+    policies: Vec<Option<String>>,
+    approved: Vec<Option<String>>,
+    streamed: usize,
+    finished: usize,
+    cancelled: Vec<u32>,
+}
+
+struct ScriptedBackend {
+    steps: Mutex<Receiver<Step>>,
+    calls: Mutex<Calls>,
+    // Set when the process has already exited, so the kill finds nothing.
+    cancel_fails: AtomicBool,
+}
+
+impl PromptBackend for ScriptedBackend {
+    fn run(&self, request: PromptRequest, channels: &PromptChannels) -> io::Result<PromptOutput> {
+        {
+            let mut calls = self.calls.lock().unwrap();
+            calls.prompts.push(request.prompt.clone());
+            calls.sessions.push(request.session_id.clone());
+            calls.policies.push(
+                request
+                    .project
+                    .as_ref()
+                    .and_then(|project| project.approval_policy.clone()),
+            );
+            calls.approved.push(
+                request
+                    .approved
+                    .as_ref()
+                    .map(|request| request.detail.clone()),
+            );
+        }
+        channels
+            .running_prompt
+            .lock()
+            .unwrap()
+            .register(RunningPrompt {
+                id: request.id,
+                pid: PID,
+                session_id: Some(SESSION.to_owned()),
+            });
+        let result = self.play(request.id, channels);
+        // Like the codex worker's guard, forget the process once the run ends.
+        channels.running_prompt.lock().unwrap().take(request.id);
+        self.calls.lock().unwrap().finished += 1;
+        result
+    }
+
+    fn cancel(&self, pid: u32) -> io::Result<()> {
+        self.calls.lock().unwrap().cancelled.push(pid);
+        if self.cancel_fails.load(Ordering::Relaxed) {
+            return Err(io::Error::other("no such process"));
+        }
+        Ok(())
+    }
+}
+
+impl ScriptedBackend {
+    fn play(&self, id: u64, channels: &PromptChannels) -> io::Result<PromptOutput> {
+        let steps = self.steps.lock().unwrap();
+        loop {
+            match steps.recv_timeout(WAIT) {
+                Ok(Step::Stream(text)) => {
+                    channels.shared_stream.lock().unwrap().update(id, text);
+                    channels.tx.send(AppEvent::PromptStream(id)).ok();
+                    channels.repaint.request_repaint();
+                    self.calls.lock().unwrap().streamed += 1;
+                }
+                Ok(Step::StreamSilently(text)) => {
+                    channels.shared_stream.lock().unwrap().update(id, text);
+                }
+                Ok(Step::Finish(Ok(text))) => return Ok(finished(text, None)),
+                Ok(Step::Finish(Err(error))) => return Err(io::Error::other(error)),
+                Ok(Step::AskApproval(kind, detail)) => {
+                    let request = ApprovalRequest {
+                        kind,
+                        detail: detail.to_owned(),
+                        reason: None,
+                        command: (kind == ApprovalKind::Command)
+                            .then(|| CommandLine::Shell(detail.to_owned())),
+                    };
+                    return Ok(finished("", Some(Box::new(request))));
+                }
+                Err(_) => return Err(io::Error::other("script ended without a result")),
+            }
+        }
+    }
+}
+
+fn finished(response: &str, approval: Option<Box<ApprovalRequest>>) -> PromptOutput {
+    PromptOutput {
+        response: response.to_owned(),
+        session_id: Some(SESSION.to_owned()),
+        usage: None,
+        suggestions: Vec::new(),
+        length_stopped: false,
+        budget_stop: None,
+        changed_files: Vec::new(),
+        approval,
+    }
+}
+
+struct Harness {
+    app: CodexAgentApp,
+    steps: Sender<Step>,
+    backend: Arc<ScriptedBackend>,
+    _serial: MutexGuard<'static, ()>,
+}
+
+impl Harness {
+    fn new() -> Self {
+        let serial = SERIAL.lock().unwrap_or_else(|e| e.into_inner());
+        SETUP.call_once(|| {
+            let data_dir =
+                std::env::temp_dir().join(format!("codexagent-harness-{}", std::process::id()));
+            paths::prepare_dirs(paths::init(Paths::portable(&data_dir)));
+            set_dirty_tree_warning_enabled(false).unwrap();
+            set_project_config_enabled(false).unwrap();
+            set_warm_start(false).unwrap();
+            set_codex_titles(false).unwrap();
+            set_clipboard_watch(false).unwrap();
+        });
+        let (steps, receiver) = mpsc::channel();
+        let backend = Arc::new(ScriptedBackend {
+            steps: Mutex::new(receiver),
+            calls: Mutex::new(Calls::default()),
+            cancel_fails: AtomicBool::new(false),
+        });
+        let mut app = CodexAgentApp::headless(Arc::clone(&backend) as Arc<dyn PromptBackend>)
+            .expect("app should start without a window");
+        app.clear_transcript();
+        Self {
+            app,
+            steps,
+            backend,
+            _serial: serial,
+        }
+    }
+
+    fn step(&self, step: Step) {
+        self.steps.send(step).unwrap();
+    }
+
+    fn calls(&self) -> MutexGuard<'_, Calls> {
+        self.backend.calls.lock().unwrap()
+    }
+
+    fn pump_until(&mut self, what: &str, done: impl Fn(&CodexAgentApp) -> bool) {
+        let deadline = Instant::now() + WAIT;
+        loop {
+            self.app.pump_events();
+            if done(&self.app) {
+                return;
+            }
+            assert!(
+                Instant::now() < deadline,
+                "timed out waiting for {what}; transcript: {:?}",
+                self.app.transcript_text()
+            );
+            thread::sleep(Duration::from_millis(5));
+        }
+    }
+
+    fn pump_for(&mut self, duration: Duration) {
+        let deadline = Instant::now() + duration;
+        while Instant::now() < deadline {
+            self.app.pump_events();
+            thread::sleep(Duration::from_millis(5));
+        }
+    }
+
+    fn response(&self) -> &str {
+        &self.app.transcript_text()[self.app.response_start()..]
+    }
+
+    fn prompt_texts(&self) -> Vec<&str> {
+        let text = self.app.transcript_text();
+        self.app
+            .prompt_ranges()
+            .iter()
+            .map(|&(start, end)| &text[start..end])
+            .collect()
+    }
+
+    fn assert_ranges_in_bounds(&self) {
+        let len = self.app.transcript_text().len();
+        let mut previous_end = 0;
+        for &(start, end) in self.app.prompt_ranges() {
+            assert!(previous_end <= start && start <= end && end <= len);
+            previous_end = end;
+        }
+        assert!(self.app.response_start() <= len);
+    }
+
+    fn complete(&mut self, prompt: &str, answer: &'static str) {
+        self.app.submit_prompt(prompt);
+        self.step(Step::Finish(Ok(answer)));
+        self.pump_until("the answer", |app| !app.is_busy());
+    }
+}
+
+#[test]
+fn submit_stream_complete() {
+    let mut harness = Harness::new();
+    harness.app.submit_prompt("explain the parser");
+    assert!(harness.app.is_busy());
+    assert_eq!(harness.prompt_texts().len(), 1);
+    assert!(harness.prompt_texts()[0].contains("explain the parser"));
+    assert_eq!(harness.app.session_id(), None);
+
+    harness.step(Step::Stream("The parser"));
+    harness.pump_until("the first delta", |app| {
+        app.transcript_text().contains("The parser")
+    });
+    assert!(harness.app.is_busy());
+    harness.step(Step::Stream("The parser reads tokens."));
+    harness.pump_until("the second delta", |app| {
+        app.transcript_text().contains("reads tokens.")
+    });
+
+    harness.step(Step::Finish(Ok("The parser reads tokens.")));
+    harness.pump_until("completion", |app| !app.is_busy());
+    assert_eq!(harness.app.session_id(), Some(SESSION));
+    assert!(harness.response().contains("The parser reads tokens."));
+    assert_eq!(harness.response().matches("The parser").count(), 1);
+    assert!(
+        harness.app.prompt_ranges()[0].1 <= harness.app.response_start(),
+        "the answer starts after the prompt"
+    );
+    harness.assert_ranges_in_bounds();
+    assert_eq!(harness.calls().prompts.len(), 1);
+    assert_eq!(harness.calls().sessions, [None]);
+}
+
+#[test]
+fn follow_up_resumes_the_session() {
+    let mut harness = Harness::new();
+    harness.complete("first question", "first answer");
+    harness.complete("second question", "second answer");
+
+    assert_eq!(harness.calls().sessions, [None, Some(SESSION.to_owned())]);
+    let prompts = harness.prompt_texts();
+    assert_eq!(prompts.len(), 2);
+    assert!(prompts[0].contains("first question"));
+    assert!(prompts[1].contains("second question"));
+    let text = harness.app.transcript_text();
+    assert!(text.find("first answer") < text.find("second question"));
+    assert!(harness.response().contains("second answer"));
+    assert!(!harness.response().contains("first answer"));
+    harness.assert_ranges_in_bounds();
+}
+
+#[test]
+fn submit_then_cancel() {
+    let mut harness = Harness::new();
+    harness.app.submit_prompt("long task");
+    harness.step(Step::Stream("Working on"));
+    harness.pump_until("the delta", |app| {
+        app.transcript_text().contains("Working on")
+    });
+
+    harness.app.cancel_prompt();
+    harness.pump_until("the cancel", |app| !app.is_cancelling());
+    harness.step(Step::Finish(Err("codex was killed")));
+    harness.pump_for(Duration::from_millis(100));
+
+    assert!(!harness.app.is_busy());
+    assert_eq!(harness.calls().cancelled, [PID]);
+    assert_eq!(harness.app.session_id(), Some(SESSION));
+    assert!(harness.app.transcript_text().contains("Working on"));
+    assert!(!harness.app.transcript_text().contains("codex was killed"));
+    harness.assert_ranges_in_bounds();
+}
+
+#[test]
+fn cancel_keeps_the_unapplied_stream_text() {
+    let mut harness = Harness::new();
+    harness.app.submit_prompt("long task");
+    harness.step(Step::Stream("Working on"));
+    harness.pump_until("the delta", |app| {
+        app.transcript_text().contains("Working on")
+    });
+
+    // The second delta is still queued when the user cancels.
+    harness.step(Step::Stream("Working on the second half"));
+    let deadline = Instant::now() + WAIT;
+    while harness.calls().streamed < 2 {
+        assert!(
+            Instant::now() < deadline,
+            "timed out waiting for the stream"
+        );
+        thread::sleep(Duration::from_millis(5));
+    }
+    harness.app.cancel_prompt();
+    harness.pump_until("the cancel", |app| !app.is_cancelling());
+    harness.step(Step::Finish(Err("codex was killed")));
+    harness.pump_for(Duration::from_millis(100));
+
+    let text = harness.app.transcript_text();
+    let partial = text
+        .find("Working on the second half")
+        .expect("the queued delta is kept");
+    assert!(partial < text.rfind("cancelled").expect("the cancellation marker"));
+    assert_eq!(text.matches("Working on").count(), 1);
+    harness.assert_ranges_in_bounds();
+}
+
+#[test]
+fn stream_after_cancel_is_ignored() {
+    let mut harness = Harness::new();
+    harness.app.submit_prompt("long task");
+    harness.step(Step::Stream("partial"));
+    harness.pump_until("the delta", |app| app.transcript_text().contains("partial"));
+
+    harness.app.cancel_prompt();
+    harness.pump_until("the cancel", |app| !app.is_cancelling());
+    let after_cancel = harness.app.transcript_text().to_owned();
+    harness.step(Step::Stream("partial and a late delta"));
+    harness.step(Step::Finish(Ok("partial and a late delta")));
+    harness.pump_for(Duration::from_millis(100));
+
+    assert_eq!(harness.app.transcript_text(), after_cancel);
+    assert!(!harness.app.is_busy());
+
+    harness.complete("next task", "next answer");
+    assert!(harness.response().contains("next answer"));
+    assert!(!harness.app.transcript_text().contains("late delta"));
+    harness.assert_ranges_in_bounds();
+}
+
+#[test]
+fn failed_prompt_reports_the_error() {
+    let mut harness = Harness::new();
+    harness.app.submit_prompt("break something");
+    harness.step(Step::Finish(Err("codex exited with status 1")));
+    harness.pump_until("the failure", |app| !app.is_busy());
+
+    assert_eq!(harness.app.session_id(), None);
+    assert!(
+        harness
+            .app
+            .transcript_text()
+            .contains("codex exited with status 1")
+    );
+    assert_eq!(harness.prompt_texts().len(), 1);
+    assert_eq!(harness.app.input_text(), "break something");
+    harness.assert_ranges_in_bounds();
+
+    harness.complete("try again", "it worked");
+    assert!(harness.response().contains("it worked"));
+}
+
+#[test]
+fn clear_resets_the_session() {
+    let mut harness = Harness::new();
+    harness.complete("question", "answer");
+    assert_eq!(harness.app.session_id(), Some(SESSION));
+
+    harness.app.clear_transcript();
+    assert_eq!(harness.app.session_id(), None);
+    assert!(harness.app.prompt_ranges().is_empty());
+    assert!(!harness.app.transcript_text().contains("answer"));
+    harness.assert_ranges_in_bounds();
+
+    harness.complete("fresh question", "fresh answer");
+    assert_eq!(harness.calls().sessions, [None, None]);
+}
+
+#[test]
+fn typing_while_locked_becomes_the_next_input() {
+    let mut harness = Harness::new();
+    harness.app.submit_prompt("refactor the parser");
+    assert!(harness.app.is_busy());
+    harness.app.type_text("now add tests");
+    assert_eq!(harness.app.input_text(), "");
+    assert_eq!(harness.app.shown_input_text(), "now add tests");
+
+    harness.step(Step::Finish(Ok("done")));
+    harness.pump_until("the answer", |app| !app.is_busy());
+    assert_eq!(harness.app.input_text(), "now add tests");
+    assert_eq!(harness.app.shown_input_text(), "now add tests");
+}
+
+#[test]
+fn typing_while_locked_follows_a_restored_prompt() {
+    let mut harness = Harness::new();
+    harness.app.submit_prompt("break something");
+    harness.app.type_text("and explain why");
+    harness.step(Step::Finish(Err("codex exited with status 1")));
+    harness.pump_until("the failure", |app| !app.is_busy());
+    assert_eq!(harness.app.input_text(), "break something\nand explain why");
+}
+
+#[test]
+fn rewriting_streams_keep_earlier_prompts_intact() {
+    let mut harness = Harness::new();
+    harness.complete("first question", "first answer");
+    harness.app.submit_prompt("second question");
+    for text in [
+        "A long draft of the second answer",
+        "Short",
+        "Short, then a much longer rewrite of the second answer",
+        "Rewritten",
+    ] {
+        harness.step(Step::Stream(text));
+        harness.pump_until("the rewrite", |app| app.transcript_text().ends_with(text));
+        assert_eq!(harness.response(), text);
+        assert_eq!(
+            harness.prompt_texts(),
+            ["first question", "second question"]
+        );
+        harness.assert_ranges_in_bounds();
+    }
+    harness.step(Step::Finish(Ok("Final second answer")));
+    harness.pump_until("completion", |app| !app.is_busy());
+    assert_eq!(
+        harness.prompt_texts(),
+        ["first question", "second question"]
+    );
+    assert!(harness.app.transcript_text().contains("first answer"));
+    harness.assert_ranges_in_bounds();
+}
+
+#[test]
+fn concise_mode_applies_from_the_next_prompt_without_showing() {
+    let mut harness = Harness::new();
+    harness.complete("first question", "first answer");
+    harness.app.submit_prompt("/concise");
+    assert!(!harness.app.is_busy());
+    harness.complete("second question", "second answer");
+    harness.app.submit_prompt("/concise");
+
+    let calls = harness.calls();
+    assert_eq!(calls.prompts.len(), 2);
+    assert!(!calls.prompts[0].contains("Answer tersely"));
+    assert!(calls.prompts[1].contains("second question\n\nAnswer tersely"));
+    drop(calls);
+    let prompts = harness.prompt_texts();
+    assert!(prompts[1].contains("second question"));
+    assert!(!harness.app.transcript_text().contains("Answer tersely"));
+}
+
+#[test]
+fn marker_bytes_in_a_prompt_are_shown_not_interpreted() {
+    let mut harness = Harness::new();
+    harness.complete("log:\n\x1Dfailed\n\x1Ethinking\n\x19#7", "answer");
+
+    let prompts = harness.prompt_texts();
+    assert_eq!(prompts.len(), 1);
+    assert!(
+        prompts[0].contains("log:\n\u{241D}failed\n\u{241E}thinking\n\u{2419}#7"),
+        "{:?}",
+        prompts[0]
+    );
+    assert!(!prompts[0].contains(['\x1D', '\x1E']));
+    assert!(harness.calls().prompts[0].contains("\u{241D}failed"));
+    assert!(harness.response().contains("answer"));
+    harness.assert_ranges_in_bounds();
+}
+
+fn assert_response_never_shrinks(harness: &mut Harness, final_text: &str) {
+    let deadline = Instant::now() + WAIT;
+    let mut previous = harness.response().len();
+    while harness.app.is_busy() {
+        assert!(
+            Instant::now() < deadline,
+            "timed out waiting for completion"
+        );
+        harness.app.pump_events();
+        let len = harness.response().len();
+        assert!(
+            len >= previous,
+            "response shrank from {} to {} bytes: {:?}",
+            previous,
+            len,
+            harness.response()
+        );
+        previous = len;
+        thread::sleep(Duration::from_millis(1));
+    }
+    assert!(harness.response().contains(final_text));
+    assert_eq!(harness.response().matches("Partial").count(), 1);
+    harness.assert_ranges_in_bounds();
+}
+
+#[test]
+fn completion_after_a_stream_event_never_shows_shorter_text() {
+    let mut harness = Harness::new();
+    harness.app.submit_prompt("stream then finish");
+    harness.step(Step::Stream("Partial"));
+    harness.pump_until("the first delta", |app| {
+        app.transcript_text().contains("Partial")
+    });
+    harness.step(Step::Stream("Partial answer that grew"));
+    harness.step(Step::Finish(Ok("Partial answer that grew, done.")));
+    assert_response_never_shrinks(&mut harness, "Partial answer that grew, done.");
+}
+
+#[test]
+fn completion_with_a_suppressed_stream_event_never_shows_shorter_text() {
+    let mut harness = Harness::new();
+    harness
+        .app
+        .submit_prompt("finish while a notification is pending");
+    harness.step(Step::Stream("Partial"));
+    harness.pump_until("the first delta", |app| {
+        app.transcript_text().contains("Partial")
+    });
+    harness.step(Step::StreamSilently("Partial answer that grew"));
+    harness.step(Step::Finish(Ok("Partial answer that grew, done.")));
+    assert_response_never_shrinks(&mut harness, "Partial answer that grew, done.");
+}
+
+#[test]
+fn unapplied_stream_text_is_kept_when_completion_fails() {
+    let mut harness = Harness::new();
+    harness
+        .app
+        .submit_prompt("finish with a rate limit after output");
+    harness.step(Step::StreamSilently("Partial answer"));
+    harness.step(Step::Finish(Err(
+        "Rate limit reached for o4-mini. Please try again in 20s.",
+    )));
+    harness.pump_until("the failure", |app| !app.is_busy());
+    assert!(
+        harness.app.transcript_text().contains("Partial answer"),
+        "{:?}",
+        harness.app.transcript_text()
+    );
+    assert_eq!(harness.calls().prompts.len(), 1);
+    harness.assert_ranges_in_bounds();
+}
+
+impl Harness {
+    fn ask_approval(&mut self, prompt: &str, kind: ApprovalKind, detail: &'static str) {
+        self.app.submit_prompt(prompt);
+        self.step(Step::AskApproval(kind, detail));
+        self.pump_until("the approval request", |app| {
+            !app.is_busy() && app.pending_approval().is_some()
+        });
+    }
+}
+
+#[test]
+fn approving_resumes_under_the_original_policy_with_only_that_command() {
+    let mut harness = Harness::new();
+    harness.ask_approval("run the tests", ApprovalKind::Command, "cargo test");
+
+    harness.app.approve();
+    assert!(harness.app.is_busy());
+    assert!(harness.app.pending_approval().is_none());
+    harness.step(Step::Finish(Ok("All tests pass.")));
+    harness.pump_until("the resumed answer", |app| !app.is_busy());
+
+    let calls = harness.calls();
+    assert_eq!(calls.approved, [None, Some("cargo test".to_owned())]);
+    assert_eq!(calls.sessions, [None, Some(SESSION.to_owned())]);
+    assert_eq!(calls.policies[1], calls.policies[0]);
+    assert_ne!(calls.policies[1].as_deref(), Some("never"));
+    assert!(calls.prompts[1].contains("do not run it again"));
+    assert!(calls.prompts[1].contains("cargo test"));
+    drop(calls);
+    assert!(
+        harness
+            .app
+            .transcript_text()
+            .contains("Approved: Run command")
+    );
+    assert!(harness.response().contains("All tests pass."));
+}
+
+#[test]
+fn denying_runs_nothing() {
+    let mut harness = Harness::new();
+    harness.ask_approval("clean up", ApprovalKind::Command, "rm -rf target");
+
+    harness.app.deny();
+    harness.pump_for(Duration::from_millis(50));
+    assert!(!harness.app.is_busy());
+    assert!(harness.app.pending_approval().is_none());
+    assert_eq!(harness.calls().prompts.len(), 1);
+    assert!(
+        harness
+            .app
+            .transcript_text()
+            .contains("Denied: Run command")
+    );
+}
+
+#[test]
+fn an_unapproved_follow_up_step_asks_again() {
+    let mut harness = Harness::new();
+    harness.ask_approval("run the tests", ApprovalKind::Command, "cargo test");
+    harness.app.approve();
+    harness.step(Step::AskApproval(ApprovalKind::Command, "rm -rf target"));
+    harness.pump_until("the second approval request", |app| {
+        !app.is_busy() && app.pending_approval().is_some()
+    });
+
+    let pending = harness.app.pending_approval().unwrap();
+    assert_eq!(pending.detail, "rm -rf target");
+    assert_eq!(harness.calls().prompts.len(), 2);
+    harness.app.deny();
+    assert_eq!(harness.calls().prompts.len(), 2);
+}
+
+#[test]
+fn patches_can_only_be_denied() {
+    let mut harness = Harness::new();
+    harness.ask_approval("fix it", ApprovalKind::Patch, "src/lib.rs");
+
+    harness.app.approve();
+    assert!(!harness.app.is_busy());
+    assert_eq!(harness.calls().prompts.len(), 1);
+    assert!(harness.app.pending_approval().is_some());
+    harness.app.deny();
+    assert!(harness.app.pending_approval().is_none());
+}
+
+#[test]
+fn submit_cancel_submit_in_one_frame() {
+    let mut harness = Harness::new();
+    harness.app.submit_prompt("first task");
+    harness.app.cancel_prompt();
+    harness.app.submit_prompt("second task");
+    assert!(harness.app.is_cancelling());
+    assert_eq!(harness.prompt_texts().len(), 1);
+
+    harness.step(Step::Finish(Err("codex was killed")));
+    harness.pump_until("the cancel", |app| !app.is_busy() && !app.is_cancelling());
+    assert!(!harness.app.has_running_process());
+    assert_eq!(harness.calls().prompts, ["first task"]);
+    assert_eq!(harness.app.input_text(), "second task");
+    assert!(!harness.app.transcript_text().contains("codex was killed"));
+    assert!(harness.app.transcript_text().contains("cancelled"));
+    harness.assert_ranges_in_bounds();
+
+    harness.complete("second task", "second answer");
+    let calls = harness.calls();
+    assert_eq!(calls.prompts.len(), 2);
+    // The first run was cancelled before codex named a session, so the second
+    // one carries it as context instead.
+    assert!(calls.prompts[1].ends_with("second task"));
+    assert_eq!(calls.sessions, [None, None]);
+    drop(calls);
+    assert_eq!(harness.prompt_texts().len(), 2);
+    assert!(harness.response().contains("second answer"));
+    assert!(!harness.app.has_running_process());
+    harness.assert_ranges_in_bounds();
+}
+
+#[test]
+fn clear_while_the_result_is_queued() {
+    let mut harness = Harness::new();
+    harness.app.submit_prompt("question");
+    harness.step(Step::Finish(Ok("queued answer")));
+    let deadline = Instant::now() + WAIT;
+    while harness.app.has_running_process() {
+        assert!(
+            Instant::now() < deadline,
+            "timed out waiting for the worker"
+        );
+        thread::sleep(Duration::from_millis(5));
+    }
+
+    // The result is in the channel but not applied, so the app is still busy
+    // and the clear must not run under it.
+    harness.app.clear_transcript();
+    assert!(harness.app.is_busy());
+    assert_eq!(harness.prompt_texts().len(), 1);
+    harness.pump_until("the queued result", |app| !app.is_busy());
+    assert_eq!(harness.response().matches("queued answer").count(), 1);
+    assert_eq!(harness.app.session_id(), Some(SESSION));
+    harness.assert_ranges_in_bounds();
+
+    harness.app.clear_transcript();
+    assert_eq!(harness.app.session_id(), None);
+    assert!(harness.app.prompt_ranges().is_empty());
+    assert!(!harness.app.transcript_text().contains("queued answer"));
+    harness.pump_for(Duration::from_millis(50));
+    assert!(!harness.app.transcript_text().contains("queued answer"));
+    harness.assert_ranges_in_bounds();
+}
+
+const CRASH: &str = "codex exited unexpectedly (code 137)";
+
+impl Harness {
+    fn stream_partial(&mut self) {
+        self.app.submit_prompt("long task");
+        self.step(Step::Stream("partial answer"));
+        self.pump_until("the delta", |app| {
+            app.transcript_text().contains("partial answer")
+        });
+    }
+
+    // Waits without pumping, so whatever the worker sends stays queued.
+    fn wait_for_runs(&self, runs: usize) {
+        let deadline = Instant::now() + WAIT;
+        while self.calls().finished < runs {
+            assert!(
+                Instant::now() < deadline,
+                "timed out waiting for the worker"
+            );
+            thread::sleep(Duration::from_millis(5));
+        }
+        // The worker records the exit just after the backend returns.
+        thread::sleep(Duration::from_millis(20));
+    }
+
+    fn assert_cancelled_once(&self) {
+        let text = self.app.transcript_text();
+        let partial = text.find("partial answer").expect("the partial answer");
+        assert_eq!(text.matches("cancelled").count(), 1, "{text:?}");
+        assert!(partial < text.find("cancelled").unwrap());
+        assert!(!text.contains(CRASH), "{text:?}");
+        assert!(!text.contains("no such process"), "{text:?}");
+    }
+}
+
+#[test]
+fn cancel_then_exit_shows_only_the_cancel() {
+    let mut harness = Harness::new();
+    harness.stream_partial();
+    harness.app.cancel_prompt();
+    harness.pump_until("the cancel", |app| !app.is_cancelling());
+    harness.step(Step::Finish(Err(CRASH)));
+    harness.wait_for_runs(1);
+    harness.pump_for(Duration::from_millis(50));
+
+    assert!(!harness.app.is_busy());
+    assert_eq!(harness.calls().cancelled, [PID]);
+    harness.assert_cancelled_once();
+    harness.assert_ranges_in_bounds();
+}
+
+#[test]
+fn exit_then_cancel_shows_only_the_cancel() {
+    let mut harness = Harness::new();
+    harness.stream_partial();
+    harness.backend.cancel_fails.store(true, Ordering::Relaxed);
+    harness.app.cancel_prompt();
+    // codex dies on its own before the app hears back from the kill.
+    harness.step(Step::Finish(Err(CRASH)));
+    harness.wait_for_runs(1);
+    harness.pump_until("the cancel", |app| !app.is_busy() && !app.is_cancelling());
+    harness.pump_for(Duration::from_millis(50));
+
+    assert_eq!(harness.calls().cancelled, [PID]);
+    harness.assert_cancelled_once();
+    harness.assert_ranges_in_bounds();
+}
+
+#[test]
+fn exit_with_the_result_queued_then_cancel_shows_only_the_cancel() {
+    let mut harness = Harness::new();
+    harness.stream_partial();
+    harness.step(Step::Finish(Err(CRASH)));
+    harness.wait_for_runs(1);
+    harness.app.cancel_prompt();
+    harness.pump_until("the cancel", |app| !app.is_busy() && !app.is_cancelling());
+    harness.pump_for(Duration::from_millis(50));
+
+    harness.assert_cancelled_once();
+    harness.assert_ranges_in_bounds();
+}
+
+#[test]
+fn a_crash_keeps_the_partial_answer_above_the_error() {
+    let mut harness = Harness::new();
+    harness.stream_partial();
+    harness.step(Step::Finish(Err(CRASH)));
+    harness.pump_until("the failure", |app| !app.is_busy());
+
+    let text = harness.app.transcript_text();
+    let partial = text.find("partial answer").expect("the partial answer");
+    let error = text.find(CRASH).expect("the crash message");
+    assert!(partial < error);
+    assert!(!text.contains("cancelled"));
+    assert!(harness.calls().cancelled.is_empty());
+    harness.assert_ranges_in_bounds();
+}
+
+fn session_files() -> Vec<std::path::PathBuf> {
+    std::fs::read_dir(&paths::get().sessions_dir)
+        .map(|entries| entries.flatten().map(|entry| entry.path()).collect())
+        .unwrap_or_default()
+}
+
+#[test]
+fn not_persisting_transcripts_writes_no_files() {
+    struct Restore;
+    impl Drop for Restore {
+        fn drop(&mut self) {
+            set_persist_transcripts(true).unwrap();
+        }
+    }
+
+    let mut harness = Harness::new();
+    let _restore = Restore;
+    let paths = paths::get();
+    let stored = [&paths.history_file, &paths.draft_file, &paths.snapshot_file];
+
+    harness.complete("first question", "first answer");
+    harness.app.type_text("half-written follow-up");
+    harness.app.save_session_state();
+    harness.pump_until("the saves", |_| stored.iter().all(|path| path.exists()));
+
+    harness.app.set_persist_transcripts(false);
+    harness.pump_until("the stored files to be removed", |_| {
+        stored.iter().all(|path| !path.exists())
+    });
+
+    let sessions = session_files();
+    harness.app.clear_transcript();
+    harness.complete("second question", "second answer");
+    harness.app.type_text("another draft");
+    harness.app.save_session_state();
+    harness.pump_for(Duration::from_millis(200));
+
+    for path in stored {
+        assert!(!path.exists(), "{} was written", path.display());
+    }
+    assert_eq!(session_files(), sessions, "no recent session was saved");
+    assert!(harness.response().contains("second answer"));
+    assert_eq!(harness.app.input_text(), "another draft");
+}
+
+#[test]
+fn input_focus_retries_stop_after_a_bounded_number_of_frames() {
+    let mut harness = Harness::new();
+    harness.complete("question", "answer");
+    assert!(
+        harness.app.wants_input_focus(),
+        "an answer hands focus back"
+    );
+
+    let mut frames = 0;
+    while harness.app.wants_input_focus() {
+        assert!(frames < 100, "focus was requested forever");
+        harness.app.input_focus_frame(false);
+        frames += 1;
+    }
+    assert!(frames > 1, "a refused request is retried");
+    for _ in 0..frames {
+        harness.app.input_focus_frame(false);
+        assert!(!harness.app.wants_input_focus());
+    }
+
+    harness.complete("next question", "next answer");
+    assert!(harness.app.wants_input_focus());
+    for _ in 1..frames {
+        harness.app.input_focus_frame(false);
+    }
+    harness.app.input_focus_frame(true);
+    assert!(!harness.app.wants_input_focus());
+    harness.complete("third question", "third answer");
+    let mut retried = 0;
+    while harness.app.wants_input_focus() {
+        harness.app.input_focus_frame(false);
+        retried += 1;
+    }
+    assert_eq!(retried, frames, "a grant resets the retry count");
+}
+
+#[test]
+fn answers_get_a_timing_footer_and_export_as_json() {
+    let mut harness = Harness::new();
+    harness.complete("first question", "first answer");
+    harness.complete("second question", "second answer");
+    harness.app.rate(0, Rating::Bad);
+    harness.app.rate(1, Rating::Good);
+    harness.app.rate(1, Rating::Good);
+
+    let footer = harness
+        .response()
+        .lines()
+        .find_map(|line| line.strip_prefix('\x1C'))
+        .expect("a footer under the answer");
+    let total = footer.split(' ').next().unwrap();
+    assert!(
+        total
+            .strip_suffix('s')
+            .is_some_and(|secs| secs.parse::<f64>().is_ok()),
+        "{footer:?}"
+    );
+
+    let path = std::env::temp_dir().join(format!(
+        "codexagent-harness-export-{}.json",
+        std::process::id()
+    ));
+    harness
+        .app
+        .submit_prompt(&format!("/export --json \"{}\"", path.display()));
+    harness.pump_for(Duration::from_millis(20));
+    let exported: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    let turns = exported["turns"].as_array().unwrap();
+    assert_eq!(turns.len(), 2);
+    assert_eq!(turns[0]["turn"], 1);
+    assert_eq!(turns[0]["rating"], "bad");
+    assert!(
+        turns[1]["rating"].is_null(),
+        "rating the same twice clears it"
+    );
+    assert_eq!(turns[0]["prompt"], "first question");
+    assert_eq!(turns[0]["response"], "first answer");
+    assert_eq!(turns[1]["prompt"], "second question");
+    assert_eq!(turns[1]["response"], "second answer");
+    assert!(turns[1]["tokens"].is_null(), "no usage was reported");
+    assert!(turns[1]["duration_ms"].is_u64());
+    assert!(turns[1]["sent_after_ms"].as_u64() >= turns[0]["sent_after_ms"].as_u64());
+    assert_eq!(turns[1]["failed"], false);
+    assert_eq!(exported["statistics"]["prompts"], 2);
+    assert_eq!(exported["statistics"]["prompts_without_usage"], 2);
+    assert_eq!(exported["session_id"], SESSION);
+    assert_eq!(exported["usage"]["tokens"]["input_tokens"], 0);
+    assert!(exported["usage"]["cost_usd"].is_null());
+    assert!(exported.get("notes").is_none());
+}
+
+#[test]
+fn searching_for_a_selection_finds_it_in_the_transcript() {
+    let mut harness = Harness::new();
+    harness.complete("why does it fail", "The parser rejects tabs.");
+    harness.complete("and now", "PARSER fixed; tabs parse.");
+
+    harness.app.search_for_selection("  parser\nrejects");
+    assert_eq!(harness.app.find_status(), "1 of 2");
+    harness.app.search_for_selection("lexer");
+    assert_eq!(harness.app.find_status(), "No matches");
+}
+
+#[test]
+fn the_first_prompt_names_the_saved_session() {
+    let mut harness = Harness::new();
+    harness.complete("Fix the flaky lexer test. It fails on CI.", "answer");
+    harness.app.save_session_state();
+
+    let title = Some("Fix the flaky lexer test".to_owned());
+    harness.pump_until("the titled recent session and snapshot", |_| {
+        let snapshot_titled = load_snapshot()
+            .ok()
+            .flatten()
+            .is_some_and(|snapshot| snapshot.title == title && !snapshot.title_renamed);
+        snapshot_titled
+            && list_recent_sessions()
+                .unwrap_or_default()
+                .iter()
+                .any(|session| session.title == title)
+    });
+}
+
+#[test]
+fn prompts_with_secrets_wait_for_a_choice() {
+    let mut harness = Harness::new();
+    let prompt = "deploy with sk-proj-abcdefghijklmnopqrstuv please";
+    harness.app.submit_prompt(prompt);
+    assert_eq!(harness.app.secret_warning(), Some("API token"));
+    assert!(!harness.app.is_busy());
+    assert_eq!(harness.app.input_text(), prompt);
+    harness.app.cancel_secret_warning();
+    assert_eq!(harness.app.secret_warning(), None);
+    assert_eq!(harness.app.input_text(), prompt, "cancel keeps the draft");
+    assert!(harness.calls().prompts.is_empty());
+
+    harness.app.submit_prompt(prompt);
+    harness.app.send_past_secret_warning(true, false);
+    harness.step(Step::Finish(Ok("deployed")));
+    harness.pump_until("the answer", |app| !app.is_busy());
+    assert!(harness.calls().prompts[0].contains("sk-[REDACTED] please"));
+    assert!(!harness.calls().prompts[0].contains("abcdefghijklmnopqrstuv"));
+    assert!(harness.prompt_texts()[0].contains("sk-[REDACTED]"));
+
+    harness.app.submit_prompt(prompt);
+    assert!(harness.app.secret_warning().is_some(), "asks again");
+    harness.app.send_past_secret_warning(false, true);
+    harness.step(Step::Finish(Ok("deployed again")));
+    harness.pump_until("the second answer", |app| !app.is_busy());
+    assert!(harness.calls().prompts[1].contains("abcdefghijklmnopqrstuv"));
+
+    harness.complete(prompt, "no questions asked");
+    assert_eq!(harness.app.secret_warning(), None);
+    assert_eq!(harness.calls().prompts.len(), 3);
+}
+
+#[test]
+fn the_response_language_is_sent_but_not_shown() {
+    let mut harness = Harness::new();
+    harness.app.submit_prompt("/lang de");
+    harness.complete("explain the lexer", "Der Lexer ...");
+    harness.complete("and the parser", "Der Parser ...");
+    for prompt in &harness.calls().prompts {
+        assert!(prompt.ends_with("Respond in German."), "{prompt:?}");
+    }
+    assert_eq!(
+        harness.prompt_texts(),
+        ["explain the lexer", "and the parser"]
+    );
+    harness.assert_ranges_in_bounds();
+
+    harness.app.submit_prompt("/lang off");
+    harness.complete("in English again", "English");
+    assert!(!harness.calls().prompts[2].contains("Respond in"));
+
+    harness.app.submit_prompt("/lang fr");
+    harness.app.clear_transcript();
+    harness.complete("after clearing", "default language");
+    assert!(
+        !harness.calls().prompts[3].contains("Respond in"),
+        "clearing the session resets the language"
+    );
+}
+
+#[test]
+fn a_prompt_that_fails_across_sleep_offers_a_retry() {
+    let mut harness = Harness::new();
+    harness.app.power_event(PowerEvent::Suspend);
+    harness.app.power_event(PowerEvent::Resume);
+    assert!(
+        !harness.app.offers_sleep_retry(),
+        "an idle sleep changes nothing"
+    );
+    harness.app.submit_prompt("run the slow migration");
+    harness.app.power_event(PowerEvent::Suspend);
+    assert!(harness.app.is_busy(), "the prompt keeps running by default");
+    harness.pump_until("the suspend snapshot", |_| {
+        load_snapshot().ok().flatten().is_some_and(|snapshot| {
+            snapshot.in_progress && snapshot.output.contains("run the slow migration")
+        })
+    });
+
+    harness.step(Step::Finish(Err("pipe closed")));
+    harness.pump_until("the failure", |app| !app.is_busy());
+    assert!(harness.response().contains("Interrupted by system sleep"));
+    assert!(!harness.response().contains("pipe closed"));
+    assert!(harness.app.offers_sleep_retry());
+
+    harness.app.retry_interrupted_prompt();
+    assert!(!harness.app.offers_sleep_retry());
+    harness.step(Step::Finish(Ok("migrated")));
+    harness.pump_until("the retried answer", |app| !app.is_busy());
+    assert_eq!(
+        harness.calls().prompts,
+        ["run the slow migration", "run the slow migration"]
+    );
+    assert!(harness.response().contains("migrated"));
+
+    harness.app.submit_prompt("unrelated failure");
+    harness.step(Step::Finish(Err("pipe closed")));
+    harness.pump_until("the plain failure", |app| !app.is_busy());
+    assert!(harness.response().contains("pipe closed"));
+    assert!(!harness.app.offers_sleep_retry());
+}
+
+#[test]
+fn cancel_on_sleep_stops_the_prompt_before_suspend() {
+    set_cancel_on_sleep(true).unwrap();
+    let mut harness = Harness::new();
+    set_cancel_on_sleep(false).unwrap();
+    harness.app.submit_prompt("long task");
+    harness.step(Step::Stream("Working"));
+    harness.pump_until("the delta", |app| app.transcript_text().contains("Working"));
+    harness.app.power_event(PowerEvent::Suspend);
+    assert!(harness.app.is_cancelling());
+    harness.pump_until("the cancel", |app| !app.is_cancelling());
+    harness.step(Step::Finish(Err("codex was killed")));
+    harness.pump_for(Duration::from_millis(100));
+    harness.app.power_event(PowerEvent::Resume);
+    assert!(!harness.app.is_busy());
+    assert_eq!(harness.calls().cancelled, [PID]);
+    assert!(!harness.app.offers_sleep_retry());
+    assert!(!harness.app.transcript_text().contains("codex was killed"));
+}
+
+fn run_batch(harness: &mut Harness, name: &str, contents: &str) -> String {
+    let path = std::env::temp_dir().join(format!(
+        "codexagent-harness-{}-{}",
+        std::process::id(),
+        name
+    ));
+    std::fs::write(&path, contents).unwrap();
+    harness
+        .app
+        .submit_prompt(&format!("/batch {}", path.display()));
+    let ended = format!("Batch codexagent-harness-{}-{} ", std::process::id(), name);
+    harness.pump_until("the end of the batch", |app| {
+        app.transcript_text()
+            .lines()
+            .any(|line| line.contains(&ended) && !line.contains(" prompts from "))
+    });
+    std::fs::remove_file(&path).unwrap();
+    harness
+        .app
+        .transcript_text()
+        .lines()
+        .rev()
+        .find(|line| line.contains(&ended))
+        .unwrap()
+        .to_owned()
+}
+
+#[test]
+fn batches_skip_failures_in_one_session() {
+    let mut harness = Harness::new();
+    harness.step(Step::Finish(Ok("first answer")));
+    harness.step(Step::Finish(Err("boom")));
+    harness.step(Step::Finish(Ok("third answer")));
+    let summary = run_batch(
+        &mut harness,
+        "skip.txt",
+        "#! on-error: skip\n\nfirst item\n\nsecond item\nover two lines\n\nthird item\n",
+    );
+    assert!(
+        summary.ends_with("finished: 2 done, 1 failed, 0 not run"),
+        "{summary}"
+    );
+    assert_eq!(
+        harness.calls().prompts,
+        ["first item", "second item\nover two lines", "third item"]
+    );
+    assert_eq!(
+        harness.calls().sessions,
+        [None, Some(SESSION.to_owned()), Some(SESSION.to_owned())]
+    );
+    let text = harness.app.transcript_text();
+    assert!(text.contains("Batch 1/3 done"));
+    assert!(text.contains("Batch 2/3 failed"));
+    assert!(text.contains("skipping to the next prompt"));
+    assert_eq!(harness.app.input_text(), "");
+}
+
+#[test]
+fn batches_stop_at_the_first_failure_by_default() {
+    let mut harness = Harness::new();
+    harness.step(Step::Finish(Ok("first answer")));
+    harness.step(Step::Finish(Err("boom")));
+    let summary = run_batch(
+        &mut harness,
+        "stop.txt",
+        "#! session: fresh\n\nfirst item\n\nsecond item\n\nthird item\n",
+    );
+    assert!(
+        summary.ends_with("stopped: 1 done, 1 failed, 1 not run"),
+        "{summary}"
+    );
+    assert_eq!(harness.calls().prompts, ["first item", "second item"]);
+    assert_eq!(
+        harness.calls().sessions,
+        [None, None],
+        "each prompt starts fresh"
+    );
+    assert!(!harness.app.is_busy());
+}
+
+#[test]
+fn copied_text_is_offered_for_a_question() {
+    struct Restore;
+    impl Drop for Restore {
+        fn drop(&mut self) {
+            set_clipboard_watch(false).unwrap();
+        }
+    }
+
+    let mut harness = Harness::new();
+    let _restore = Restore;
+    harness.app.set_clipboard_watch(true);
+    harness.app.type_text("Why does this panic?");
+    harness
+        .app
+        .clipboard_changed("let v: Vec<u8> = Vec::new();\nv[0];\n");
+    assert_eq!(
+        harness.app.clipboard_offer().as_deref(),
+        Some("Ask about copied text (35 chars)")
+    );
+    harness.app.ask_about_copied_text();
+    assert_eq!(harness.app.clipboard_offer(), None);
+    assert_eq!(
+        harness.app.input_text(),
+        "Why does this panic?\n\n```\nlet v: Vec<u8> = Vec::new();\nv[0];\n```\n\n"
+    );
+
+    harness.app.copy_text("copied from the transcript");
+    harness.app.clipboard_changed("copied from the transcript");
+    assert_eq!(
+        harness.app.clipboard_offer(),
+        None,
+        "the app's own copies are ignored"
+    );
+    harness.app.clipboard_changed("copied elsewhere");
+    assert!(harness.app.clipboard_offer().is_some());
+}
+
+#[test]
+fn the_clipboard_is_ignored_unless_enabled() {
+    let mut harness = Harness::new();
+    harness.app.copy_text("from the app");
+    harness.app.clipboard_changed("copied elsewhere");
+    assert_eq!(harness.app.clipboard_offer(), None);
+}
+
+#[test]
+fn notes_stay_local_until_exported_and_clear_asks_first() {
+    let mut harness = Harness::new();
+    harness.app.write_notes("ask about the lexer next");
+    harness.complete("explain the parser", "It reads tokens.");
+    assert!(
+        !harness.calls().prompts[0].contains("lexer"),
+        "notes are never sent"
+    );
+
+    let export = |harness: &mut Harness, flags: &str| {
+        let path = std::env::temp_dir().join(format!(
+            "codexagent-harness-notes-{}.txt",
+            std::process::id()
+        ));
+        harness
+            .app
+            .submit_prompt(&format!("/export {} \"{}\"", flags, path.display()));
+        harness.pump_for(Duration::from_millis(20));
+        let text = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        text
+    };
+    assert!(!export(&mut harness, "").contains("lexer"));
+    assert!(export(&mut harness, "--notes").contains("## Notes\n\nask about the lexer next"));
+    let json: serde_json::Value =
+        serde_json::from_str(&export(&mut harness, "--json --notes")).unwrap();
+    assert_eq!(json["notes"], "ask about the lexer next");
+
+    harness.app.clear_transcript();
+    assert!(harness.app.clear_needs_confirmation());
+    assert!(harness.app.transcript_text().contains("It reads tokens."));
+    harness.app.confirm_pending_clear();
+    assert!(!harness.app.clear_needs_confirmation());
+    assert_eq!(harness.app.notes_text(), "");
+    assert!(!harness.app.transcript_text().contains("It reads tokens."));
+}
@@ -0,0 +1,218 @@
+use std::fmt::Write as _;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use codexagent::prompt::{ReplayedSession, replay_recording};
+use serde_json::Value;
+
+const REASONING_MARKER: char = '\x1E';
+const MIN_REPEAT_CHECK_CHARS: usize = 12;
+
+fn fixtures_dir() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures")
+}
+
+// Sessions recorded with record_events land in <log dir>/sessions. Copying
+// one into tests/fixtures/sessions adds it here; the first run writes its
+// golden file for review.
+fn recordings() -> Vec<PathBuf> {
+    let mut paths: Vec<_> = fs::read_dir(fixtures_dir().join("sessions"))
+        .expect("fixture sessions directory")
+        .map(|entry| entry.expect("fixture entry").path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "jsonl"))
+        .collect();
+    paths.sort();
+    assert!(
+        !paths.is_empty(),
+        "no recorded sessions in tests/fixtures/sessions"
+    );
+    paths
+}
+
+fn escape_controls(text: &str) -> String {
+    text.chars().fold(String::new(), |mut out, c| {
+        if c.is_control() && c != '\n' {
+            let _ = write!(out, "\\x{:02X}", u32::from(c));
+        } else {
+            out.push(c);
+        }
+        out
+    })
+}
+
+fn golden_text(replayed: &ReplayedSession) -> String {
+    let mut out = String::new();
+    let _ = writeln!(
+        out,
+        "session: {}",
+        replayed.session_id.as_deref().unwrap_or("none")
+    );
+    let _ = writeln!(
+        out,
+        "error: {}",
+        replayed.error.as_deref().unwrap_or("none")
+    );
+    for suggestion in &replayed.suggestions {
+        let _ = writeln!(out, "suggestion: {}", suggestion);
+    }
+    let _ = writeln!(out, "response:");
+    out.push_str(&escape_controls(&replayed.response));
+    if !out.ends_with('\n') {
+        out.push('\n');
+    }
+    out
+}
+
+fn reasoning_texts(recording: &str) -> Vec<String> {
+    let mut texts = Vec::new();
+    for line in recording.lines() {
+        let Ok(event) = serde_json::from_str::<Value>(line) else {
+            continue;
+        };
+        let kind = event.get("type").and_then(Value::as_str).unwrap_or("");
+        if let Some(item) = event.get("item")
+            && item
+                .get("type")
+                .or_else(|| item.get("item_type"))
+                .and_then(Value::as_str)
+                == Some("reasoning")
+            && let Some(text) = item.get("text").and_then(Value::as_str)
+        {
+            texts.push(text.replace("**", ""));
+        }
+        if kind.contains("reasoning")
+            && let Some(delta) = event.get("delta").and_then(Value::as_str)
+        {
+            texts.push(delta.to_owned());
+        }
+    }
+    texts
+        .iter()
+        .flat_map(|text| text.lines())
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .map(str::to_owned)
+        .collect()
+}
+
+fn answer_lines(response: &str) -> impl Iterator<Item = &str> {
+    response
+        .lines()
+        .filter(|line| !line.starts_with(REASONING_MARKER))
+}
+
+#[test]
+fn recordings_match_their_golden_files() {
+    let golden_dir = fixtures_dir().join("golden");
+    let mut written = Vec::new();
+    for path in recordings() {
+        let recording = fs::read_to_string(&path).expect("readable recording");
+        let actual = golden_text(&replay_recording(&recording));
+        let golden = golden_dir.join(path.with_extension("txt").file_name().unwrap());
+        match fs::read_to_string(&golden) {
+            Ok(expected) => assert_eq!(
+                actual,
+                expected,
+                "{} no longer replays to {}",
+                path.display(),
+                golden.display()
+            ),
+            Err(_) => {
+                fs::create_dir_all(&golden_dir).expect("golden directory");
+                fs::write(&golden, &actual).expect("writable golden file");
+                written.push(golden);
+            }
+        }
+    }
+    assert!(
+        written.is_empty(),
+        "wrote new golden files; review and commit them: {:?}",
+        written
+    );
+}
+
+#[test]
+fn no_fragment_appears_twice() {
+    for path in recordings() {
+        let recording = fs::read_to_string(&path).expect("readable recording");
+        let replayed = replay_recording(&recording);
+        let mut seen = Vec::new();
+        for line in replayed.response.lines().map(str::trim) {
+            if line.chars().count() < MIN_REPEAT_CHECK_CHARS || line.starts_with("```") {
+                continue;
+            }
+            assert!(
+                !seen.contains(&line),
+                "{} repeats {:?}",
+                path.display(),
+                line
+            );
+            seen.push(line);
+        }
+    }
+}
+
+#[test]
+fn reasoning_never_leaks_into_the_answer() {
+    for path in recordings() {
+        let recording = fs::read_to_string(&path).expect("readable recording");
+        let replayed = replay_recording(&recording);
+        let reasoning = reasoning_texts(&recording);
+        for line in answer_lines(&replayed.response) {
+            for text in &reasoning {
+                assert!(
+                    !line.contains(text.as_str()),
+                    "{} shows reasoning {:?} in the answer line {:?}",
+                    path.display(),
+                    text,
+                    line
+                );
+            }
+        }
+    }
+}
+
+#[test]
+fn every_agent_message_is_shown_once() {
+    for path in recordings() {
+        let recording = fs::read_to_string(&path).expect("readable recording");
+        let replayed = replay_recording(&recording);
+        let answer: String = answer_lines(&replayed.response)
+            .collect::<Vec<_>>()
+            .join("\n");
+        let mut last_by_item = Vec::<(String, String)>::new();
+        for line in recording.lines() {
+            let Ok(event) = serde_json::from_str::<Value>(line) else {
+                continue;
+            };
+            let Some(item) = event.get("item") else {
+                continue;
+            };
+            let kind = item
+                .get("type")
+                .or_else(|| item.get("item_type"))
+                .and_then(Value::as_str);
+            if !matches!(kind, Some("agent_message" | "assistant_message")) {
+                continue;
+            }
+            let (Some(id), Some(text)) = (
+                item.get("id").and_then(Value::as_str),
+                item.get("text").and_then(Value::as_str),
+            ) else {
+                continue;
+            };
+            last_by_item.retain(|(seen, _)| seen != id);
+            last_by_item.push((id.to_owned(), text.to_owned()));
+        }
+        for (_, text) in last_by_item {
+            let first_line = text.lines().next().unwrap_or_default();
+            assert_eq!(
+                answer.matches(first_line).count(),
+                1,
+                "{} should show {:?} once",
+                path.display(),
+                first_line
+            );
+        }
+    }
+}